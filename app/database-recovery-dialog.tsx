@@ -0,0 +1,59 @@
+'use client';
+
+import { useEffect, useState } from 'react';
+import {
+  AlertDialog,
+  AlertDialogAction,
+  AlertDialogContent,
+  AlertDialogDescription,
+  AlertDialogFooter,
+  AlertDialogHeader,
+  AlertDialogTitle,
+} from '@/components/ui/alert-dialog';
+import { getDatabaseRecoveryReport, RecoveryReport } from '@/lib/tauri-commands';
+
+/**
+ * Blocking notice shown when the database was found corrupt at startup and recovered.
+ * Lists what was recovered and lost per table.
+ */
+export function DatabaseRecoveryDialog() {
+  const [report, setReport] = useState<RecoveryReport | null>(null);
+
+  useEffect(() => {
+    getDatabaseRecoveryReport()
+      .then(setReport)
+      .catch((error) => console.error('Failed to load database recovery report:', error));
+  }, []);
+
+  if (!report) return null;
+
+  const lostLabel = (table: RecoveryReport['tables'][number]) => {
+    if (table.estimated_lost_rows != null) return `${table.estimated_lost_rows} lost`;
+    return table.complete ? 'none lost' : 'unknown number lost';
+  };
+
+  return (
+    <AlertDialog open>
+      <AlertDialogContent>
+        <AlertDialogHeader>
+          <AlertDialogTitle>Database was corrupt and has been recovered</AlertDialogTitle>
+          <AlertDialogDescription>
+            The database failed its integrity check at startup. Readable data was copied into a
+            new database. The damaged file was kept at {report.corrupt_copy_path}.
+          </AlertDialogDescription>
+        </AlertDialogHeader>
+        <ul className="space-y-1 text-sm">
+          {report.tables.map((table) => (
+            <li key={table.table}>
+              <span className="font-medium">{table.table}</span>: {table.recovered_rows} recovered
+              {table.skipped_rows > 0 && `, ${table.skipped_rows} unreadable`}, {lostLabel(table)}
+            </li>
+          ))}
+        </ul>
+        <AlertDialogFooter>
+          <AlertDialogAction onClick={() => setReport(null)}>Continue</AlertDialogAction>
+        </AlertDialogFooter>
+      </AlertDialogContent>
+    </AlertDialog>
+  );
+}
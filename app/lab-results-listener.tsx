@@ -31,7 +31,10 @@ interface LabResultsEventPayload {
     value: string;
     units?: string;
     reference_range?: string;
-    flags: string[];
+    flags?: {
+      abnormal_flag?: string;
+      nature_of_abnormality?: string;
+    };
     status: string;
     completed_date_time?: string;
     analyzer_id?: string;
@@ -238,8 +241,8 @@ async function processLabResults(payload: LabResultsEventPayload): Promise<void>
           units: result.units,
           referenceRange: result.reference_range ? parseReferenceRange(result.reference_range) : undefined,
           flags: {
-            abnormalFlag: result.flags.find(flag => ['H', 'L', 'HH', 'LL', 'N'].includes(flag)) || undefined,
-            natureOfAbnormality: result.flags.find(flag => !['H', 'L', 'HH', 'LL', 'N'].includes(flag)) || undefined,
+            abnormalFlag: result.flags?.abnormal_flag || undefined,
+            natureOfAbnormality: result.flags?.nature_of_abnormality || undefined,
           },
           status: mapStatusFromString(result.status),
           completedDateTime: result.completed_date_time ? new Date(result.completed_date_time) : undefined,
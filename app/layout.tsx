@@ -4,6 +4,7 @@ import { Inter } from 'next/font/google';
 import { Navigation } from '@/components/navigation';
 import { Toaster } from '@/components/ui/toaster';
 import { LabResultsListener } from './lab-results-listener';
+import { DatabaseRecoveryDialog } from './database-recovery-dialog';
 
 const inter = Inter({ subsets: ['latin'] });
 
@@ -28,6 +29,7 @@ export default function RootLayout({
         </div>
         <Toaster />
         <LabResultsListener />
+        <DatabaseRecoveryDialog />
       </body>
     </html>
   );
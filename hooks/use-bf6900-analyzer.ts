@@ -102,6 +102,14 @@ export function useBF6900Analyzer(): useBF6900AnalyzerReturn {
         created_at: analyzer?.createdAt,
         updated_at: analyzer?.updatedAt,
         activate_on_start: updatedAnalyzer.activateOnStart ?? analyzer?.activateOnStart ?? false,
+        dilution_mode: updatedAnalyzer.dilutionMode ?? analyzer?.dilutionMode ?? 'PostDilution',
+        strict_remote_address: updatedAnalyzer.strictRemoteAddress ?? analyzer?.strictRemoteAddress ?? false,
+        stream_provisional_results: updatedAnalyzer.streamProvisionalResults ?? analyzer?.streamProvisionalResults ?? false,
+        bind_address: updatedAnalyzer.bindAddress ?? analyzer?.bindAddress ?? null,
+        dual_stack: updatedAnalyzer.dualStack ?? analyzer?.dualStack ?? false,
+        listen_backlog: updatedAnalyzer.listenBacklog ?? analyzer?.listenBacklog ?? 128,
+        max_connections_per_ip: updatedAnalyzer.maxConnectionsPerIp ?? analyzer?.maxConnectionsPerIp ?? null,
+        clock_skew_seconds: updatedAnalyzer.clockSkewSeconds ?? analyzer?.clockSkewSeconds ?? 0,
       };
 
       const settingsToUse = updatedHl7Settings || hl7Settings || {
@@ -67,6 +67,27 @@ export function useMerilAnalyzer(): UseMerilAnalyzerReturn {
         baud_rate: updatedAnalyzer.baudRate || analyzer?.baudRate,
         protocol: updatedAnalyzer.protocol?.protocol || analyzer?.protocol.protocol,
         activate_on_start: updatedAnalyzer.activateOnStart ?? analyzer?.activateOnStart ?? false,
+        dilution_mode: updatedAnalyzer.dilutionMode ?? analyzer?.dilutionMode ?? 'PostDilution',
+        strict_remote_address: updatedAnalyzer.strictRemoteAddress ?? analyzer?.strictRemoteAddress ?? false,
+        stream_provisional_results: updatedAnalyzer.streamProvisionalResults ?? analyzer?.streamProvisionalResults ?? false,
+        bind_address: updatedAnalyzer.bindAddress ?? analyzer?.bindAddress ?? null,
+        dual_stack: updatedAnalyzer.dualStack ?? analyzer?.dualStack ?? false,
+        listen_backlog: updatedAnalyzer.listenBacklog ?? analyzer?.listenBacklog ?? 128,
+        max_connections_per_ip: updatedAnalyzer.maxConnectionsPerIp ?? analyzer?.maxConnectionsPerIp ?? null,
+        post_eot_delay_ms: updatedAnalyzer.postEotDelayMs ?? analyzer?.postEotDelayMs ?? 0,
+        accept_frames_without_enq: updatedAnalyzer.acceptFramesWithoutEnq ?? analyzer?.acceptFramesWithoutEnq ?? false,
+        astm_frame_retries: updatedAnalyzer.astmFrameRetries ?? analyzer?.astmFrameRetries ?? 6,
+        clock_skew_seconds: updatedAnalyzer.clockSkewSeconds ?? analyzer?.clockSkewSeconds ?? 0,
+        log_sample_rate: updatedAnalyzer.logSampleRate ?? analyzer?.logSampleRate ?? 1,
+        astm_timeouts: (() => {
+          const timeouts = updatedAnalyzer.astmTimeouts ?? analyzer?.astmTimeouts;
+          return timeouts && {
+            inter_byte_ms: timeouts.interByteMs,
+            inter_frame_ms: timeouts.interFrameMs,
+            transmission_ms: timeouts.transmissionMs,
+            ack_ms: timeouts.ackMs,
+          };
+        })(),
       };
 
       const response: MerilConfigResponse = await updateMerilConfig(backendAnalyzer);
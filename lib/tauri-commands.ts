@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
+import type { ChecksumPolicy, DelimitedLayout } from './types';
 
 // Types matching the Rust API responses
 export interface AnalyzerResponse {
@@ -17,6 +18,39 @@ export interface AnalyzerResponse {
   protocol: string;
   status: string;
   activate_on_start: boolean;
+  dilution_mode?: 'PostDilution' | 'PreDilution';
+  strict_remote_address?: boolean;
+  astm_sender_id?: string | null;
+  astm_version?: string | null;
+  stream_provisional_results?: boolean;
+  bind_address?: string | null;
+  dual_stack?: boolean;
+  listen_backlog?: number;
+  max_connections_per_ip?: number | null;
+  post_eot_delay_ms?: number;
+  accept_frames_without_enq?: boolean;
+  host_initiated?: boolean;
+  astm_frame_retries?: number;
+  checksum_policy?: ChecksumPolicy;
+  clock_skew_seconds?: number;
+  result_acceptance?: {
+    max_past_days: number | null;
+    max_future_days: number | null;
+  };
+  default_patient_class?: string | null;
+  sex_codes?: Record<string, 'Male' | 'Female' | 'Other'>;
+  delimited_layout?: DelimitedLayout;
+  log_sample_rate?: number;
+  last_seen_identity?: AnalyzerIdentity | null;
+  identity_since?: string | null;
+  conformance_report_until?: string | null;
+  last_connected_at?: string | null;
+  astm_timeouts?: {
+    inter_byte_ms: number;
+    inter_frame_ms: number;
+    transmission_ms: number;
+    ack_ms?: number;
+  };
   created_at: string;
   updated_at: string;
 }
@@ -65,9 +99,39 @@ export function convertAnalyzerResponse(response: AnalyzerResponse) {
     external_port: response.external_port,
     comPort: response.com_port,
     baudRate: response.baud_rate,
-    protocol: { protocol: response.protocol as 'Astm' | 'Hl7' },
+    protocol: { protocol: response.protocol as 'Astm' | 'Hl7' | 'DelimitedText' },
     status: { status: response.status as 'Active' | 'Inactive' | 'Maintenance' },
     activateOnStart: response.activate_on_start,
+    dilutionMode: response.dilution_mode,
+    strictRemoteAddress: response.strict_remote_address,
+    astmSenderId: response.astm_sender_id ?? undefined,
+    astmVersion: response.astm_version ?? undefined,
+    streamProvisionalResults: response.stream_provisional_results,
+    bindAddress: response.bind_address ?? undefined,
+    dualStack: response.dual_stack,
+    listenBacklog: response.listen_backlog,
+    maxConnectionsPerIp: response.max_connections_per_ip ?? undefined,
+    postEotDelayMs: response.post_eot_delay_ms,
+    acceptFramesWithoutEnq: response.accept_frames_without_enq,
+    hostInitiated: response.host_initiated,
+    astmFrameRetries: response.astm_frame_retries,
+    checksumPolicy: response.checksum_policy,
+    clockSkewSeconds: response.clock_skew_seconds,
+    resultAcceptance: response.result_acceptance && {
+      maxPastDays: response.result_acceptance.max_past_days ?? undefined,
+      maxFutureDays: response.result_acceptance.max_future_days ?? undefined,
+    },
+    defaultPatientClass: response.default_patient_class ?? undefined,
+    sexCodes: response.sex_codes,
+    delimitedLayout: response.delimited_layout,
+    logSampleRate: response.log_sample_rate,
+    lastConnectedAt: response.last_connected_at ? new Date(response.last_connected_at) : undefined,
+    astmTimeouts: response.astm_timeouts && {
+      interByteMs: response.astm_timeouts.inter_byte_ms,
+      interFrameMs: response.astm_timeouts.inter_frame_ms,
+      transmissionMs: response.astm_timeouts.transmission_ms,
+      ackMs: response.astm_timeouts.ack_ms ?? 10000,
+    },
     createdAt: new Date(response.created_at),
     updatedAt: new Date(response.updated_at),
   };
@@ -78,6 +142,7 @@ export const fetchMerilConfig = async (): Promise<MerilConfigResponse> => {
   return invoke('fetch_meril_config');
 };
 
+// Fields left out of the analyzer (snake_case) keep their stored values
 export const updateMerilConfig = async (analyzer: any): Promise<MerilConfigResponse> => {
   return invoke('update_meril_config', { analyzer });
 };
@@ -90,6 +155,20 @@ export const stopMerilService = async (): Promise<void> => {
   return invoke('stop_meril_service');
 };
 
+// Orders are passed in the backend's snake_case shape; returns the queue length
+export const queueMerilHostOrder = async (order: any): Promise<number> => {
+  return invoke('queue_meril_host_order', { order });
+};
+
+export const sendMerilHostOrders = async (): Promise<number> => {
+  return invoke('send_meril_host_orders');
+};
+
+// Downloads a patient's queued orders; outcome also emitted as meril:orders-sent / meril:orders-send-failed
+export const sendMerilOrders = async (patientId: string, orderIds: string[]): Promise<void> => {
+  return invoke('send_meril_orders', { patientId, orderIds });
+};
+
 // BF-6900 commands
 export const fetchBF6900Config = async (): Promise<BF6900ConfigResponse> => {
   return invoke('fetch_bf6900_config');
@@ -113,4 +192,755 @@ export const startBF6900Service = async (): Promise<void> => {
 
 export const stopBF6900Service = async (): Promise<void> => {
   return invoke('stop_bf6900_service');
-}; 
\ No newline at end of file
+};
+
+export type ParameterKind = 'Metadata' | 'Numeric' | 'Crp' | 'Image';
+
+// Measurement mode (OBX 2002 MODE_EX) and analysis mode (OBX 2001 MODE) of a run
+export type HematologyPanel = 'Cbc' | 'CbcDiff' | 'CbcDiffCrp' | 'Crp';
+export type HematologyAnalysisMode = 'WholeBlood' | 'TraceWholeBlood' | 'PreDilution';
+
+export interface ParameterInfo {
+  code: string;
+  name: string;
+  display_name: string;
+  kind: ParameterKind;
+  units?: string | null;
+}
+
+// Run information carried with bf6900:lab-results (OBX 2001-2005)
+export interface HematologyRunInfo {
+  analysis_mode?: HematologyAnalysisMode | null;
+  panel?: HematologyPanel | null;
+  reference_group?: string | null;
+  remarks?: string | null;
+  qc_level?: string | null;
+}
+
+export const getParameterCatalog = async (): Promise<ParameterInfo[]> => {
+  return invoke('get_parameter_catalog');
+};
+
+// Printable analyzer setup sheet (Markdown)
+export const generateSetupSheet = async (analyzerId: string): Promise<string> => {
+  return invoke('generate_setup_sheet', { analyzerId });
+};
+
+// Analyzers started with the LIS
+export const getAutostartAnalyzers = async (): Promise<string[]> => {
+  return invoke('get_autostart_analyzers');
+};
+
+export const setAutostart = async (analyzerId: string, enabled: boolean): Promise<void> => {
+  return invoke('set_autostart', { analyzerId, enabled });
+};
+
+// Localized sample reports
+export interface ReportLocaleSettings {
+  default_locale: string;
+  templates: Record<string, string>;
+  clinics: Record<string, string>;
+}
+
+// Markdown; embedded graphs are inline <img> tags with data: URIs
+export const generateSampleReport = async (
+  sampleId: string,
+  patientId?: string,
+  clinic?: string
+): Promise<string> => {
+  return invoke('generate_sample_report', { sampleId, patientId, clinic });
+};
+
+export const getReportLocaleSettings = async (): Promise<ReportLocaleSettings> => {
+  return invoke('get_report_locale_settings');
+};
+
+export const setReportLocaleSettings = async (settings: ReportLocaleSettings): Promise<ReportLocaleSettings> => {
+  return invoke('set_report_locale_settings', { settings });
+};
+
+// Histograms and scattergrams on sample reports
+export interface ReportGraphSettings {
+  templates: Record<string, boolean>;
+}
+
+export interface ReportAttachment {
+  result_id: string;
+  kind: string;
+  parameter_code: string;
+  caption: string;
+  mime_type: string;
+  path: string;
+  size_bytes: number | null; // null when the file is missing
+}
+
+export const getSampleAttachments = async (sampleId: string): Promise<ReportAttachment[]> => {
+  return invoke('get_sample_attachments', { sampleId });
+};
+
+export const getReportGraphSettings = async (): Promise<ReportGraphSettings> => {
+  return invoke('get_report_graph_settings');
+};
+
+export const setReportGraphSettings = async (settings: ReportGraphSettings): Promise<ReportGraphSettings> => {
+  return invoke('set_report_graph_settings', { settings });
+};
+
+// Dashboard tiles
+export type ConnectionTermination = 'COMPLETED' | 'REMOTE_CLOSED' | 'TIMEOUT' | 'ERROR' | 'SHUTDOWN';
+
+export interface TerminationCounts {
+  completed: number;
+  remote_closed: number;
+  timeout: number;
+  error: number;
+  shutdown: number;
+}
+
+export interface DashboardCounts {
+  total_patients: number;
+  total_samples: number;
+  samples_today: number;
+  results_today: number;
+  pending_uploads: number;
+  connection_terminations_today: TerminationCounts;
+  abnormal_disconnections_today: number;
+}
+
+export const getDashboardCounts = async (): Promise<DashboardCounts> => {
+  return invoke('get_dashboard_counts');
+};
+
+// Processing latency
+export type ProcessingStage = 'Received' | 'Framed' | 'Parsed' | 'Validated' | 'Persisted' | 'Acked' | 'Uploaded';
+
+export interface LatencyBucket {
+  upper_ms: number | null;
+  count: number;
+}
+
+export interface LatencyHistogram {
+  from: ProcessingStage;
+  to: ProcessingStage;
+  count: number;
+  min_ms: number | null;
+  max_ms: number | null;
+  mean_ms: number | null;
+  p50_ms: number | null;
+  p95_ms: number | null;
+  buckets: LatencyBucket[];
+}
+
+export interface LatencyStats {
+  since: string;
+  messages: number;
+  segments: LatencyHistogram[];
+}
+
+export const getLatencyStats = async (since?: string): Promise<LatencyStats> => {
+  return invoke('get_latency_stats', { since });
+};
+
+// Database integrity
+export interface IntegrityReport {
+  ok: boolean;
+  messages: string[];
+  checked_at: string;
+}
+
+export interface TableRecovery {
+  table: string;
+  recovered_rows: number;
+  skipped_rows: number;
+  complete: boolean;
+  estimated_lost_rows?: number | null;
+  error?: string | null;
+}
+
+export interface RecoveryReport {
+  database_path: string;
+  corrupt_copy_path: string;
+  integrity_errors: string[];
+  tables: TableRecovery[];
+  recovered_at: string;
+}
+
+export const verifyDatabaseIntegrity = async (): Promise<IntegrityReport> => {
+  return invoke('verify_database_integrity');
+};
+
+export interface ResultIntegrityReport {
+  checked: number;
+  unhashed: number;
+  mismatched: string[];
+}
+
+export const verifyResultHashes = async (): Promise<ResultIntegrityReport> => {
+  return invoke('verify_result_hashes');
+};
+
+export interface DatabaseRetryMetrics {
+  retries: number;
+  recovered: number;
+  exhausted: number;
+  not_retried: number;
+  rejected: number;
+  breaker_trips: number;
+  breaker_state: 'Closed' | 'Open' | 'HalfOpen';
+}
+
+export const getDatabaseRetryMetrics = async (): Promise<DatabaseRetryMetrics> => {
+  return invoke('get_database_retry_metrics');
+};
+
+export const getDatabaseRecoveryReport = async (): Promise<RecoveryReport | null> => {
+  return invoke('get_database_recovery_report');
+};
+
+// Startup write check of the database, store and log directories
+export interface DirectoryCheck {
+  purpose: string;
+  path: string;
+  error: string | null;
+  checked_at: string;
+}
+
+export const getDirectoryChecks = async (): Promise<DirectoryCheck[]> => {
+  return invoke('get_directory_checks');
+};
+
+// Protocol conformance
+export interface ConformanceWarning {
+  rule_id: string;
+  description: string;
+  detail: string;
+}
+
+export interface ConformanceRuleCount {
+  rule_id: string;
+  description: string;
+  protocol: 'HL7' | 'ASTM';
+  count: number;
+  first_seen: string;
+  last_seen: string;
+  last_detail: string;
+}
+
+export interface ConformanceReport {
+  analyzer_id: string;
+  from: string;
+  to: string;
+  total: number;
+  by_rule: ConformanceRuleCount[];
+}
+
+export interface ConformanceMetrics {
+  messages_checked: number;
+  messages_with_warnings: number;
+  warnings_by_rule: Record<string, number>;
+}
+
+export const getConformanceReport = async (analyzerId: string, from: string, to: string): Promise<ConformanceReport> => {
+  return invoke('get_conformance_report', { analyzerId, from, to });
+};
+
+export const getConformanceMetrics = async (): Promise<ConformanceMetrics> => {
+  return invoke('get_conformance_metrics');
+};
+
+// Firmware identity
+export interface AnalyzerIdentity {
+  protocol: 'HL7' | 'ASTM';
+  application: string | null;
+  facility: string | null;
+  version: string | null;
+}
+
+export interface FirmwareChange {
+  id: string;
+  analyzer_id: string;
+  previous: AnalyzerIdentity;
+  current: AnalyzerIdentity;
+  previous_since: string | null;
+  changed_at: string;
+  conformance_report_until: string | null;
+}
+
+export const getFirmwareHistory = async (analyzerId: string): Promise<FirmwareChange[]> => {
+  return invoke('get_firmware_history', { analyzerId });
+};
+
+export const getFirmwareChangeConformanceMode = async (): Promise<boolean> => {
+  return invoke('get_firmware_change_conformance_mode');
+};
+
+export const setFirmwareChangeConformanceMode = async (enabled: boolean): Promise<boolean> => {
+  return invoke('set_firmware_change_conformance_mode', { enabled });
+};
+
+// HIS upload queue
+export type UploadPriority = 'Critical' | 'Stat' | 'Routine';
+
+export interface UploadQueueEntry {
+  upload_id: string;
+  result_id: string;
+  priority: UploadPriority;
+  retry_count: number;
+  claimed_by?: string | null;
+  claimed_at?: string | null;
+  next_attempt_at?: string | null;
+  created_at: string;
+}
+
+export interface UploadQueueSummary {
+  pending: number;
+  in_flight: number;
+  claimed: number;
+  critical: number;
+  stat: number;
+  routine: number;
+  next: UploadQueueEntry[];
+}
+
+export const getUploadQueueSummary = async (limit?: number): Promise<UploadQueueSummary> => {
+  return invoke('get_upload_queue_summary', { limit });
+};
+
+// HIS upload remediation
+export type CorrectionPattern = 'CorrectedResult' | 'CancelAndNew';
+
+export interface RemediationCandidate {
+  upload_id: string;
+  sample_id: string;
+  uploaded_at?: string | null;
+  sent_as?: string | null;
+  value: string;
+}
+
+export interface RemediationReport {
+  test_code: string;
+  corrected_name: string;
+  pattern: CorrectionPattern;
+  dry_run: boolean;
+  affected: RemediationCandidate[];
+  queued: number;
+  skipped: number;
+}
+
+export const getHisCorrectionPattern = async (): Promise<CorrectionPattern> => {
+  return invoke('get_his_correction_pattern');
+};
+
+export const setHisCorrectionPattern = async (pattern: CorrectionPattern): Promise<CorrectionPattern> => {
+  return invoke('set_his_correction_pattern', { pattern });
+};
+
+export const remapAndReupload = async (
+  testCode: string,
+  fromDate: string,
+  toDate: string,
+  dryRun: boolean
+): Promise<RemediationReport> => {
+  return invoke('remap_and_reupload', { testCode, fromDate, toDate, dryRun });
+};
+
+export const findUntrackedResults = async (from: string, to: string): Promise<string[]> => {
+  return invoke('find_untracked_results', { from, to });
+};
+
+// Only available in builds with the `his-simulation` feature
+export const setHisSimulatedFailure = async (enabled: boolean): Promise<void> => {
+  return invoke('set_his_simulated_failure', { enabled });
+};
+
+// Mandatory demographics
+export type DemographicField = 'Name' | 'BirthDate' | 'Sex';
+
+export interface DemographicsPolicy {
+  enabled: boolean;
+  required_fields: DemographicField[];
+}
+
+export interface DemographicsHold {
+  id: string;
+  patient_id?: string | null;
+  sample_id: string;
+  analyzer_id: string;
+  missing_fields: DemographicField[];
+  payload: string;
+  priority: UploadPriority;
+  status: 'PendingDemographics' | 'Released';
+  upload_id?: string | null;
+  created_at: string;
+  released_at?: string | null;
+}
+
+export const getDemographicsPolicy = async (): Promise<DemographicsPolicy> => {
+  return invoke('get_demographics_policy');
+};
+
+export const setDemographicsPolicy = async (policy: DemographicsPolicy): Promise<DemographicsPolicy> => {
+  return invoke('set_demographics_policy', { policy });
+};
+
+export const getResultsPendingDemographics = async (): Promise<DemographicsHold[]> => {
+  return invoke('get_results_pending_demographics');
+};
+
+export const updatePatientDemographics = async (
+  patientId: string,
+  demographics: { firstName?: string; lastName?: string; birthDate?: string; sex?: string }
+): Promise<DemographicsHold[]> => {
+  return invoke('update_patient_demographics', { patientId, ...demographics });
+};
+
+export const releaseDemographicsHold = async (holdId: string): Promise<DemographicsHold> => {
+  return invoke('release_demographics_hold', { holdId });
+};
+
+// Auto-verification rules
+export type VerificationDecision = 'AutoVerify' | 'Hold';
+
+export type VerificationCondition =
+  | { type: 'Test'; test_ids: string[] }
+  | { type: 'ValueBetween'; min?: number | null; max?: number | null }
+  | { type: 'NoFlags' }
+  | { type: 'Flag'; flag: string }
+  | { type: 'DeltaBelow'; max_percent: number }
+  | { type: 'PriorResult'; exists: boolean }
+  | { type: 'PatientAge'; min_years?: number | null; max_years?: number | null }
+  | { type: 'PatientSex'; sex: string }
+  | { type: 'Analyzer'; analyzer_ids: string[] }
+  | { type: 'All'; conditions: VerificationCondition[] }
+  | { type: 'Any'; conditions: VerificationCondition[] }
+  | { type: 'Not'; condition: VerificationCondition }
+  | { type: 'EveryResult'; condition: VerificationCondition }
+  | { type: 'AnyResult'; condition: VerificationCondition }
+  | { type: 'ResultFor'; test_id: string; condition: VerificationCondition };
+
+export interface VerificationRule {
+  id: string;
+  version?: number;
+  name: string;
+  enabled: boolean;
+  priority?: number;
+  scope?: 'Result' | 'Panel';
+  condition: VerificationCondition;
+  decision: VerificationDecision;
+  created_at?: string | null;
+}
+
+export interface VerificationStamp {
+  decision: VerificationDecision;
+  rule_id?: string | null;
+  rule_version?: number | null;
+  evaluated_at: string;
+}
+
+export interface VerificationHold {
+  id: string;
+  patient_id?: string | null;
+  sample_id: string;
+  analyzer_id: string;
+  reason: 'Verification' | 'Stale' | 'OutOfOrder';
+  reason_detail?: string | null;
+  decisions: [string, VerificationStamp][];
+  payload: string;
+  priority: UploadPriority;
+  status: 'PendingVerification' | 'Released';
+  upload_id?: string | null;
+  created_at: string;
+  released_at?: string | null;
+}
+
+export interface VerificationDryRun {
+  samples: number;
+  results: number;
+  current_released: number;
+  proposed_released: number;
+  current_release_rate: number;
+  proposed_release_rate: number;
+  newly_released: number;
+  newly_held: number;
+  released_by_rule: { rule_id: string; rule_version: number; released: number }[];
+}
+
+export const getVerificationRules = async (): Promise<VerificationRule[]> => {
+  return invoke('get_verification_rules');
+};
+
+export const saveVerificationRule = async (rule: VerificationRule): Promise<VerificationRule> => {
+  return invoke('save_verification_rule', { rule });
+};
+
+export const deleteVerificationRule = async (ruleId: string): Promise<void> => {
+  return invoke('delete_verification_rule', { ruleId });
+};
+
+export const getVerificationRuleHistory = async (ruleId: string): Promise<VerificationRule[]> => {
+  return invoke('get_verification_rule_history', { ruleId });
+};
+
+export const dryRunVerificationRules = async (
+  rules: VerificationRule[],
+  from: string,
+  to: string
+): Promise<VerificationDryRun> => {
+  return invoke('dry_run_verification_rules', { rules, from, to });
+};
+
+export const getResultsPendingVerification = async (): Promise<VerificationHold[]> => {
+  return invoke('get_results_pending_verification');
+};
+
+export const releaseVerificationHold = async (holdId: string): Promise<VerificationHold> => {
+  return invoke('release_verification_hold', { holdId });
+};
+
+// Stale results: received too long after they were run to upload without review
+export interface StaleResultSettings {
+  max_age_hours: number | null;
+}
+
+export interface ApprovalSummary {
+  released: VerificationHold[];
+  failed: [string, string][];
+}
+
+export const getStaleResults = async (): Promise<VerificationHold[]> => {
+  return invoke('get_stale_results');
+};
+
+export const approveResults = async (holdIds: string[]): Promise<ApprovalSummary> => {
+  return invoke('approve_results', { holdIds });
+};
+
+export const getStaleResultSettings = async (): Promise<StaleResultSettings> => {
+  return invoke('get_stale_result_settings');
+};
+
+export const setStaleResultSettings = async (settings: StaleResultSettings): Promise<StaleResultSettings> => {
+  return invoke('set_stale_result_settings', { settings });
+};
+
+// Results packages (offline transfer between LIS instances)
+export interface PackageManifest {
+  format: string;
+  version: number;
+  exported_at: string;
+  from: string;
+  to: string;
+  patients: number;
+  samples: number;
+  results: number;
+  content_sha256: string;
+}
+
+export interface EntityImportCounts {
+  inserted: number;
+  updated: number;
+  skipped: number;
+}
+
+export interface PackageImportReport {
+  manifest: PackageManifest;
+  patients: EntityImportCounts;
+  results: EntityImportCounts;
+  remapped_patients: { package_patient_id: string; imported_as: string }[];
+  error?: string | null;
+}
+
+export const exportResultsPackage = async (from: string, to: string, path: string): Promise<PackageManifest> => {
+  return invoke('export_results_package', { from, to, path });
+};
+
+export const importResultsPackage = async (path: string): Promise<PackageImportReport> => {
+  return invoke('import_results_package', { path });
+};
+
+// Patient results
+export interface ResultFilter {
+  analyzer_id?: string | null;
+  protocol?: 'Astm' | 'Hl7' | 'Hl7V24' | 'Hl7V231' | 'DelimitedText' | null;
+}
+
+// Results are returned in the backend's snake_case shape
+export const getPatientResults = async (patientId: string, filter?: ResultFilter): Promise<any[]> => {
+  return invoke('get_patient_results', { patientId, filter });
+};
+
+export const getResultsByMessageControlId = async (controlId: string): Promise<any[]> => {
+  return invoke('get_results_by_message_control_id', { controlId });
+};
+
+// Result detail: the result plus notes, images, uploads and audit trail (snake_case)
+export const getResultDetail = async (resultId: string): Promise<any> => {
+  return invoke('get_result_detail', { resultId });
+};
+
+// Change feed for polling consumers: start at 0 and pass back next_seq (snake_case).
+// A change with result null is a tombstone: every Delete is one, even if the id was
+// stored again later, and so is an Insert or Update of a result deleted since.
+export interface ResultChangePage {
+  changes: {
+    seq: number;
+    change_type: 'Insert' | 'Update' | 'Delete';
+    result_id: string;
+    changed_at: string;
+    result: any | null;
+  }[];
+  next_seq: number;
+}
+
+export const getResultChanges = async (sinceSeq: number, limit?: number): Promise<ResultChangePage> => {
+  return invoke('get_result_changes', { sinceSeq, limit });
+};
+
+export const addResultNote = async (resultId: string, text: string, author?: string): Promise<any> => {
+  return invoke('add_result_note', { resultId, text, author });
+};
+
+// Rejects with a 'SAMPLE_BUSY: ...' error while the sample is being updated; retry later
+export const editTestResult = async (resultId: string, value: string): Promise<any> => {
+  return invoke('edit_test_result', { resultId, value });
+};
+
+// Connection conversations (sequence diagrams)
+export type ConversationElement =
+  | { type: 'Enq' }
+  | { type: 'Ack' }
+  | { type: 'Nak' }
+  | { type: 'Eot' }
+  | { type: 'Frame'; number: number }
+  | { type: 'MllpFrame' }
+  | { type: 'MllpAck'; code: string };
+
+export interface ConversationEntry {
+  sequence: number;
+  direction: 'Inbound' | 'Outbound';
+  element: ConversationElement;
+  length: number;
+  at: string;
+  outcome: 'Accepted' | 'Rejected' | 'Ignored';
+  detail?: string | null;
+}
+
+export interface ConnectionSummary {
+  connection_id: string;
+  analyzer_id: string;
+  protocol: string;
+  remote_address: string;
+  opened_at: string;
+  closed_at?: string | null;
+  entries: number;
+  dropped_entries: number;
+  termination?: ConnectionTermination | null;
+}
+
+export const getConversation = async (connectionId: string, limit: number): Promise<ConversationEntry[]> => {
+  return invoke('get_conversation', { connectionId, limit });
+};
+
+export const getRecentConnections = async (): Promise<ConnectionSummary[]> => {
+  return invoke('get_recent_connections');
+};
+
+export interface ConnectionHistorySettings {
+  recent_limit: number;
+}
+
+export interface ConnectionRetentionMetrics {
+  live_connections: number;
+  live_conversations: number;
+  retained_closed: number;
+  recent_limit: number;
+  archived: number;
+}
+
+export const getConnectionHistorySettings = async (): Promise<ConnectionHistorySettings> => {
+  return invoke('get_connection_history_settings');
+};
+
+export const setConnectionHistorySettings = async (
+  settings: ConnectionHistorySettings
+): Promise<ConnectionHistorySettings> => {
+  return invoke('set_connection_history_settings', { settings });
+};
+
+// Closed connections beyond the in-memory ring, from the database
+export const getConnectionHistory = async (analyzerId: string, limit: number): Promise<ConnectionSummary[]> => {
+  return invoke('get_connection_history', { analyzerId, limit });
+};
+
+export const getConnectionRetentionMetrics = async (): Promise<ConnectionRetentionMetrics> => {
+  return invoke('get_connection_retention_metrics');
+};
+
+// Developer console (debug builds or when enabled in settings)
+export interface DeveloperConsoleSettings {
+  enabled: boolean;
+}
+
+export type RawPayload =
+  | { protocol: 'Hl7'; message: string }
+  | { protocol: 'Astm'; records: string[] };
+
+export interface RawExchange {
+  analyzer_id: string;
+  protocol: string;
+  bytes_sent: number;
+  responses: string[];
+  sent_at: string;
+}
+
+export const getDeveloperConsoleSettings = async (): Promise<DeveloperConsoleSettings> => {
+  return invoke('get_developer_console_settings');
+};
+
+export const setDeveloperConsoleSettings = async (
+  settings: DeveloperConsoleSettings
+): Promise<DeveloperConsoleSettings> => {
+  return invoke('set_developer_console_settings', { settings });
+};
+
+// `operator` is the username of an admin account from the setup wizard
+export const sendRawMessage = async (
+  analyzerId: string,
+  operator: string,
+  protocolElement: RawPayload,
+  understood: boolean,
+  responseWindowMs?: number
+): Promise<RawExchange> => {
+  return invoke('send_raw_message', { analyzerId, operator, protocolElement, understood, responseWindowMs });
+};
+
+// First-run setup wizard
+export type SetupSection = 'lab_identity' | 'analyzers' | 'his' | 'users' | 'retention';
+
+export interface SetupSectionState {
+  section: SetupSection;
+  configured: boolean;
+  current: unknown | null;
+}
+
+export interface SetupState {
+  completed_at: string | null;
+  sections: SetupSectionState[];
+}
+
+export const getSetupState = async (): Promise<SetupState> => {
+  return invoke('get_setup_state');
+};
+
+export const validateSetupStep = async (section: SetupSection, payload: unknown): Promise<void> => {
+  return invoke('validate_setup_step', { section, payload });
+};
+
+export const applySetupStep = async (section: SetupSection, payload: unknown): Promise<SetupState> => {
+  return invoke('apply_setup_step', { section, payload });
+};
+
+// Fails with the list of problems while any section is missing or invalid
+export const completeSetup = async (): Promise<SetupState> => {
+  return invoke('complete_setup');
+};
@@ -7,7 +7,7 @@ export interface AnalyzerStatus {
 }
 
 export interface Protocol {
-  protocol: 'Astm' | 'Hl7';
+  protocol: 'Astm' | 'Hl7' | 'DelimitedText';
 }
 
 export interface Analyzer {
@@ -26,6 +26,28 @@ export interface Analyzer {
   protocol: Protocol;
   status: AnalyzerStatus;
   activateOnStart: boolean;
+  dilutionMode?: 'PostDilution' | 'PreDilution';
+  strictRemoteAddress?: boolean;
+  astmSenderId?: string;
+  astmVersion?: string;
+  streamProvisionalResults?: boolean;
+  bindAddress?: string;
+  dualStack?: boolean;
+  listenBacklog?: number;
+  maxConnectionsPerIp?: number;
+  postEotDelayMs?: number;
+  acceptFramesWithoutEnq?: boolean;
+  hostInitiated?: boolean;
+  astmFrameRetries?: number;
+  checksumPolicy?: ChecksumPolicy;
+  clockSkewSeconds?: number;
+  resultAcceptance?: ResultAcceptanceWindow;
+  defaultPatientClass?: string;
+  sexCodes?: Record<string, 'Male' | 'Female' | 'Other'>;
+  delimitedLayout?: DelimitedLayout;
+  logSampleRate?: number;
+  lastConnectedAt?: Date;
+  astmTimeouts?: AstmTimeouts;
   createdAt: Date;
   updatedAt: Date;
 }
@@ -39,10 +61,24 @@ export interface PatientName {
 
 export interface PatientAddress {
   street?: string;
+  otherDesignation?: string;
   city?: string;
   state?: string;
   zip?: string;
   countryCode?: string;
+  raw?: string;
+  unparsed?: boolean;
+}
+
+export interface ContactInfo {
+  useCode?: string;
+  equipmentType?: string;
+  countryCode?: string;
+  areaCode?: string;
+  localNumber?: string;
+  extension?: string;
+  raw: string;
+  unparsed: boolean;
 }
 
 export interface PatientPhysicians {
@@ -68,8 +104,10 @@ export interface Patient {
   sex: 'Male' | 'Female' | 'Other';
   address?: PatientAddress;
   telephone: string[];
+  contacts?: ContactInfo[];
   physicians?: PatientPhysicians;
   physicalAttributes?: PhysicalAttributes;
+  comments?: string[];
   createdAt: Date;
   updatedAt: Date;
 }
@@ -87,6 +125,10 @@ export interface ResultFlags {
 export interface TestResultMetadata {
   sequenceNumber: number;
   instrument?: string;
+  parameterName?: string;
+  panel?: 'CBC' | 'CBC_DIFF' | 'CBC_DIFF_CRP' | 'CRP';
+  analysisMode?: 'WHOLE_BLOOD' | 'TRACE_WHOLE_BLOOD' | 'PRE_DILUTION';
+  unrecognizedStatus?: string;
 }
 
 export interface TestResult {
@@ -101,6 +143,7 @@ export interface TestResult {
   completedDateTime?: Date;
   metadata: TestResultMetadata;
   analyzerId?: string;
+  comments?: string[];
   patientId: string;
   createdAt: Date;
   updatedAt: Date;
@@ -174,4 +217,34 @@ export interface DataFlowMetrics {
   failedUploads: number;
   averageProcessingTime: number;
   systemUptime: number;
-}
\ No newline at end of file
+}
+
+// ASTM receive timers, in milliseconds
+export interface AstmTimeouts {
+  interByteMs: number;
+  interFrameMs: number;
+  transmissionMs: number;
+  ackMs: number;
+}
+
+// Completion dates accepted on results, in days from server time; unset is unbounded
+export interface ResultAcceptanceWindow {
+  maxPastDays?: number;
+  maxFutureDays?: number;
+}
+
+// What the ASTM receiver does with a frame whose checksum does not match; Strict (NAK) by default
+export type ChecksumPolicy = 'Lenient' | 'Strict';
+
+// Column layout of delimited-text results (0-based columns)
+export interface DelimitedLayout {
+  delimiter: string;
+  has_header: boolean;
+  sample_id_column: number;
+  test_column: number;
+  value_column: number;
+  units_column: number | null;
+  flag_column: number | null;
+  completed_at_column: number | null;
+  completed_at_format: string;
+}
@@ -1,5 +1,9 @@
-use crate::models::{Analyzer, AnalyzerStatus, ConnectionType, Protocol};
+use crate::models::{
+    Analyzer, AnalyzerStatus, AstmTimeouts, ChecksumPolicy, ConnectionType, DelimitedLayout, DilutionMode, Protocol,
+    ResultAcceptanceWindow, SexCodeMap,
+};
 use crate::models::hematology::HL7Settings;
+use crate::protocol::hl7_parser::ParameterInfo;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
@@ -38,7 +42,7 @@ fn validate_port(port: u16) -> bool {
 }
 
 /// Validates BF-6900 analyzer configuration
-fn validate_bf6900_config(analyzer: &Analyzer) -> Result<(), String> {
+pub(crate) fn validate_bf6900_config(analyzer: &Analyzer) -> Result<(), String> {
     // Ensure it's TCP/IP connection
     if analyzer.connection_type != ConnectionType::TcpIp {
         return Err("BF-6900 only supports TCP/IP connections".to_string());
@@ -160,13 +164,30 @@ async fn save_bf6900_config_to_store<R: tauri::Runtime>(
     Ok(())
 }
 
-/// Updates BF-6900 configuration
+/// Updates BF-6900 configuration. Analyzer fields left out keep their current values.
 #[tauri::command]
 pub async fn update_bf6900_config<R: tauri::Runtime>(
     app: tauri::AppHandle<R>,
-    analyzer: Analyzer,
+    analyzer: serde_json::Value,
     hl7_settings: HL7Settings,
 ) -> BF6900ConfigResponse {
+    let current = app
+        .state::<crate::app_state::AppState<R>>()
+        .get_bf6900_service()
+        .get_analyzer_config()
+        .await;
+    let analyzer = match current.merged_with(analyzer) {
+        Ok(analyzer) => analyzer,
+        Err(merge_error) => {
+            return BF6900ConfigResponse {
+                success: false,
+                analyzer: None,
+                hl7_settings: None,
+                error_message: Some(merge_error),
+            }
+        }
+    };
+
     // Validate the analyzer configuration first
     if let Err(validation_error) = validate_bf6900_config(&analyzer) {
         return BF6900ConfigResponse {
@@ -191,6 +212,12 @@ pub async fn update_bf6900_config<R: tauri::Runtime>(
     let mut updated_analyzer = analyzer;
     updated_analyzer.updated_at = Utc::now();
 
+    // The declared identity and any conformance-report window come from the analyzer, not the UI
+    updated_analyzer.last_seen_identity = current.last_seen_identity;
+    updated_analyzer.identity_since = current.identity_since;
+    updated_analyzer.conformance_report_until = current.conformance_report_until;
+    updated_analyzer.last_connected_at = current.last_connected_at;
+
     // TODO: Add update_analyzer_config method to BF6900 service
     // For now, we'll save to store and log that service update is not yet implemented
     log::warn!("update_bf6900_config: Service update not yet implemented, saving to store directly");
@@ -240,10 +267,9 @@ pub async fn get_bf6900_service_status<R: tauri::Runtime>(
 ) -> Result<BF6900ServiceStatus, String> {
     // Get the AppState from AppData
     let app_state = app.state::<crate::app_state::AppState<R>>();
-    let service = app_state.get_bf6900_service();
-    let status = service.get_status().await;
-    let connections_count = service.get_connections_count().await;
-    let is_running = status == AnalyzerStatus::Active;
+    let controller = app_state.get_bf6900_controller();
+    let status = controller.service().get_status().await;
+    let (is_running, connections_count) = controller.status().await;
     
     Ok(BF6900ServiceStatus {
         is_running,
@@ -260,17 +286,11 @@ pub async fn start_bf6900_service<R: tauri::Runtime>(
     // Get the AppState from AppData
     let app_state = app.state::<crate::app_state::AppState<R>>();
 
-    // Note: We need mutable access to start the service
-    // For now, we'll use a workaround by cloning the service and starting it
-    let service = app_state.get_bf6900_service().clone();
-
     log::info!("Starting BF-6900 service...");
 
-    // Start the service
-    match service.start().await {
+    // Start the service; only its own controller is locked, so the other service stays responsive
+    match app_state.get_bf6900_controller().start().await {
         Ok(()) => {
-            log::info!("BF-6900 service started successfully");
-
             // Emit event to frontend
             let _ = app.emit(
                 "bf6900:service-started",
@@ -306,17 +326,11 @@ pub async fn stop_bf6900_service<R: tauri::Runtime>(
     // Get the AppState from AppData
     let app_state = app.state::<crate::app_state::AppState<R>>();
 
-    // Note: We need mutable access to stop the service
-    // For now, we'll use a workaround by cloning the service and stopping it
-    let service = app_state.get_bf6900_service().clone();
-
     log::info!("Stopping BF-6900 service...");
 
-    // Stop the service
-    match service.stop().await {
+    // Stop the service; only its own controller is locked, so the other service stays responsive
+    match app_state.get_bf6900_controller().stop().await {
         Ok(()) => {
-            log::info!("BF-6900 service stopped successfully");
-
             // Emit event to frontend
             let _ = app.emit(
                 "bf6900:service-stopped",
@@ -364,6 +378,31 @@ fn create_default_bf6900_analyzer() -> Analyzer {
         protocol: Protocol::Hl7V24,
         status: AnalyzerStatus::Inactive,
         activate_on_start: false, // Don't auto-start by default
+        dilution_mode: DilutionMode::PostDilution,
+        strict_remote_address: false,
+        astm_sender_id: None,
+        astm_version: None,
+        stream_provisional_results: false,
+        bind_address: None,
+        dual_stack: false,
+        listen_backlog: 128,
+        max_connections_per_ip: None,
+        post_eot_delay_ms: 0,
+        astm_timeouts: AstmTimeouts::default(),
+        log_sample_rate: 1,
+        accept_frames_without_enq: false,
+        host_initiated: false,
+        astm_frame_retries: 6,
+        checksum_policy: ChecksumPolicy::default(),
+        clock_skew_seconds: 0,
+        result_acceptance: ResultAcceptanceWindow::default(),
+        default_patient_class: None,
+        sex_codes: SexCodeMap::default(),
+        delimited_layout: DelimitedLayout::default(),
+        last_seen_identity: None,
+        identity_since: None,
+        conformance_report_until: None,
+        last_connected_at: None,
         created_at: Utc::now(),
         updated_at: Utc::now(),
     }
@@ -470,4 +509,10 @@ mod tests {
         assert_eq!(analyzer.port, Some(9100));
         assert!(!analyzer.activate_on_start);
     }
-}
\ No newline at end of file
+}
+
+/// Lists the CQ 5 Plus parameter codes with display names, kind and units
+#[tauri::command]
+pub fn get_parameter_catalog() -> Vec<ParameterInfo> {
+    crate::protocol::hl7_parser::get_parameter_catalog()
+}
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use tauri::Manager;
+
+use crate::models::PackageManifest;
+use crate::protocol::message_profile::MessageProfile;
+use crate::services::result_export::{self, ExportFormat, ExportRequest, ExportSummary};
+use crate::services::results_package;
+
+/// Exports results completed between `from` and `to` to a CSV or HL7 file.
+/// HL7 exports are rendered with `profile`, or the default profile when omitted.
+/// With `patient_id`, only that patient's results are exported.
+#[tauri::command]
+pub async fn export_results<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    format: ExportFormat,
+    path: String,
+    profile: Option<MessageProfile>,
+    patient_id: Option<String>,
+) -> Result<ExportSummary, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state.get_disk_monitor().check_non_essential_write("exporting results")?;
+
+    result_export::export_results(
+        app_state.get_repository(),
+        app_state.get_his_client(),
+        &ExportRequest {
+            from,
+            to,
+            format,
+            patient_id,
+        },
+        &profile.unwrap_or_default(),
+        &PathBuf::from(&path),
+    )
+    .await
+    .map_err(|e| {
+        log::error!("Failed to export results to {}: {}", path, e);
+        e
+    })
+}
+
+/// Writes results completed between `from` and `to`, with their patients, to a
+/// compressed package for transfer to another LIS instance
+#[tauri::command]
+pub async fn export_results_package<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    path: String,
+) -> Result<PackageManifest, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state.get_disk_monitor().check_non_essential_write("exporting results")?;
+
+    results_package::export_results_package(app_state.get_repository(), from, to, &PathBuf::from(&path))
+        .await
+        .map_err(|e| {
+            log::error!("Failed to export results package to {}: {}", path, e);
+            e
+        })
+}
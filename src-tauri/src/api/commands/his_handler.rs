@@ -0,0 +1,161 @@
+use tauri::Manager;
+use tauri_plugin_store::StoreExt;
+
+use crate::models::{CorrectionPattern, RemediationReport, UploadQueueSummary};
+use crate::protocol::message_profile::MessageProfile;
+use crate::services::upload_remediation::{
+    correction_pattern_from_store, remap_and_reupload as run_remediation, RemapRequest, HIS_CORRECTION_PATTERN_STORE_KEY,
+};
+use crate::services::upload_worker::{preview_sample_oru, preview_sample_upload};
+
+/// Builds the HIS payload for a stored sample, with test name, unit and LOINC
+/// mapping applied, and returns it serialized without sending anything.
+/// With a `profile` override the sample is rendered as an HL7 ORU^R01 under that profile instead.
+#[tauri::command]
+pub async fn preview_his_upload<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    sample_id: String,
+    profile: Option<MessageProfile>,
+) -> Result<String, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    let preview = match profile {
+        Some(profile) => {
+            preview_sample_oru(app_state.get_repository(), app_state.get_his_client(), &sample_id, &profile).await
+        }
+        None => preview_sample_upload(app_state.get_repository(), app_state.get_his_client(), &sample_id).await,
+    };
+
+    preview.map_err(|e| {
+        log::error!("Failed to preview HIS upload for sample {}: {}", sample_id, e);
+        e
+    })
+}
+
+/// Gets the HIS upload queue counts and its next `limit` uploads in the order
+/// the worker sends them (critical, then STAT, then routine; oldest first)
+#[tauri::command]
+pub async fn get_upload_queue_summary<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    limit: Option<u32>,
+) -> Result<UploadQueueSummary, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    app_state
+        .get_repository()
+        .get_upload_queue_summary(limit.unwrap_or(20))
+        .await
+        .map_err(|e| {
+            log::error!("Failed to summarize HIS upload queue: {}", e);
+            e
+        })
+}
+
+/// Gets how the HIS takes corrections of results it already holds
+#[tauri::command]
+pub async fn get_his_correction_pattern<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<CorrectionPattern, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+
+    Ok(correction_pattern_from_store(store.get(HIS_CORRECTION_PATTERN_STORE_KEY)))
+}
+
+/// Sets how the HIS takes corrections: one corrected message, or a cancellation followed by a new one
+#[tauri::command]
+pub async fn set_his_correction_pattern<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    pattern: CorrectionPattern,
+) -> Result<CorrectionPattern, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+    let value = serde_json::to_value(pattern)
+        .map_err(|e| format!("Failed to serialize HIS correction pattern: {}", e))?;
+    store.set(HIS_CORRECTION_PATTERN_STORE_KEY.to_string(), value);
+
+    Ok(pattern)
+}
+
+/// Re-sends results of `test_code` uploaded between `from_date` and `to_date` (RFC 3339)
+/// under a stale HIS test mapping, using the configured correction pattern.
+/// A dry run only lists the affected results.
+#[tauri::command]
+pub async fn remap_and_reupload<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    test_code: String,
+    from_date: String,
+    to_date: String,
+    dry_run: bool,
+) -> Result<RemediationReport, String> {
+    let from = chrono::DateTime::parse_from_rfc3339(&from_date)
+        .map_err(|e| format!("Invalid 'from_date' timestamp: {}", e))?
+        .with_timezone(&chrono::Utc);
+    let to = chrono::DateTime::parse_from_rfc3339(&to_date)
+        .map_err(|e| format!("Invalid 'to_date' timestamp: {}", e))?
+        .with_timezone(&chrono::Utc);
+    let pattern = get_his_correction_pattern(app.clone()).await?;
+
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let request = RemapRequest {
+        test_code,
+        from,
+        to,
+        pattern,
+        dry_run,
+    };
+
+    run_remediation(
+        app_state.get_repository(),
+        app_state.get_his_client(),
+        app_state.get_upload_worker(),
+        &request,
+    )
+    .await
+    .map_err(|e| {
+        log::error!("Failed to remediate uploads of {}: {}", request.test_code, e);
+        e
+    })
+}
+
+/// Lists results created between `from` and `to` (RFC 3339) that were never queued
+/// for upload, so they can be enqueued
+#[tauri::command]
+pub async fn find_untracked_results<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    from: String,
+    to: String,
+) -> Result<Vec<String>, String> {
+    let from = chrono::DateTime::parse_from_rfc3339(&from)
+        .map_err(|e| format!("Invalid 'from' timestamp: {}", e))?
+        .with_timezone(&chrono::Utc);
+    let to = chrono::DateTime::parse_from_rfc3339(&to)
+        .map_err(|e| format!("Invalid 'to' timestamp: {}", e))?
+        .with_timezone(&chrono::Utc);
+
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state.get_repository().find_untracked_results(from, to).await
+}
+
+/// Makes the HIS client fail every send so the upload retry and spool paths can be
+/// exercised without taking the HIS down. Only available in `his-simulation` builds.
+#[tauri::command]
+pub async fn set_his_simulated_failure<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    enabled: bool,
+) -> Result<(), String> {
+    #[cfg(feature = "his-simulation")]
+    {
+        let app_state = app.state::<crate::app_state::AppState<R>>();
+        app_state.get_his_client().set_simulated_failure(enabled);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "his-simulation"))]
+    {
+        let _ = (app, enabled);
+        Err("HIS failure simulation is not available in this build".to_string())
+    }
+}
@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use tauri::Manager;
+
+use crate::models::PackageImportReport;
+use crate::services::csv_import::{self, ImportMappingProfile, ImportReport};
+use crate::services::results_package;
+
+/// Imports historical results from a CSV file using the given column mapping.
+/// With `dry_run` the file is only validated and the error report returned.
+#[tauri::command]
+pub async fn import_results_csv<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    path: String,
+    mapping_profile: ImportMappingProfile,
+    dry_run: Option<bool>,
+) -> Result<ImportReport, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let repository = app_state.get_repository().clone();
+
+    let report = csv_import::import_results_csv(
+        &repository,
+        &PathBuf::from(&path),
+        &mapping_profile,
+        dry_run.unwrap_or(false),
+    )
+    .await
+    .map_err(|e| {
+        log::error!("Failed to import results from {}: {}", path, e);
+        e
+    })?;
+
+    Ok(report)
+}
+
+/// Imports a results package exported by another LIS instance.
+/// The package is rejected unless its version is supported and its content hash matches.
+#[tauri::command]
+pub async fn import_results_package<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    path: String,
+) -> Result<PackageImportReport, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    results_package::import_results_package(app_state.get_repository(), &PathBuf::from(&path))
+        .await
+        .map_err(|e| {
+            log::error!("Failed to import results package {}: {}", path, e);
+            e
+        })
+}
@@ -1,4 +1,4 @@
-use crate::models::{Analyzer, AnalyzerStatus, ConnectionType, Protocol};
+use crate::models::{Analyzer, AnalyzerStatus, ConnectionType, DispatchStatus, OrderDispatch, Protocol, TestOrder};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
@@ -37,7 +37,7 @@ fn validate_port(port: u16) -> bool {
 // Removed unused function - using AppState::create_default_meril_analyzer instead
 
 /// Validates Meril analyzer configuration
-fn validate_meril_config(analyzer: &Analyzer) -> Result<(), String> {
+pub(crate) fn validate_meril_config(analyzer: &Analyzer) -> Result<(), String> {
     // Ensure it's TCP/IP connection
     if analyzer.connection_type != ConnectionType::TcpIp {
         return Err("Meril AutoQuant only supports TCP/IP connections".to_string());
@@ -71,11 +71,15 @@ fn validate_meril_config(analyzer: &Analyzer) -> Result<(), String> {
         }
     }
 
-    // Ensure protocol is ASTM
-    if analyzer.protocol != Protocol::Astm {
-        return Err("Meril AutoQuant only supports ASTM protocol".to_string());
+    // ASTM, or delimited text from the accessory devices that simply dump results
+    match analyzer.protocol {
+        Protocol::Astm => {}
+        Protocol::DelimitedText => analyzer.delimited_layout.validate()?,
+        _ => return Err("Meril AutoQuant only supports ASTM or delimited text".to_string()),
     }
 
+    analyzer.astm_timeouts.validate()?;
+
     Ok(())
 }
 
@@ -127,14 +131,31 @@ async fn save_meril_config_to_store<R: tauri::Runtime>(
     Ok(())
 }
 
-/// Updates Meril configuration via the service
+/// Updates Meril configuration via the service. Fields left out of `analyzer`
+/// keep their current values.
 /// Note: This is a placeholder implementation. In a full implementation,
 /// the service would need to be updated to handle configuration changes.
 #[tauri::command]
 pub async fn update_meril_config<R: tauri::Runtime>(
     app: tauri::AppHandle<R>,
-    analyzer: Analyzer,
+    analyzer: serde_json::Value,
 ) -> MerilConfigResponse {
+    let current = app
+        .state::<crate::app_state::AppState<R>>()
+        .get_autoquant_meril_service()
+        .get_analyzer_config()
+        .await;
+    let analyzer = match current.merged_with(analyzer) {
+        Ok(analyzer) => analyzer,
+        Err(merge_error) => {
+            return MerilConfigResponse {
+                success: false,
+                analyzer: None,
+                error_message: Some(merge_error),
+            }
+        }
+    };
+
     // Validate the configuration first
     if let Err(validation_error) = validate_meril_config(&analyzer) {
         return MerilConfigResponse {
@@ -148,6 +169,16 @@ pub async fn update_meril_config<R: tauri::Runtime>(
     let mut updated_analyzer = analyzer;
     updated_analyzer.updated_at = Utc::now();
 
+    // The ASTM sender/version are declared by the analyzer, not edited in the UI
+    if updated_analyzer.astm_sender_id.is_none() && updated_analyzer.astm_version.is_none() {
+        updated_analyzer.astm_sender_id = current.astm_sender_id;
+        updated_analyzer.astm_version = current.astm_version;
+    }
+    updated_analyzer.last_seen_identity = current.last_seen_identity;
+    updated_analyzer.identity_since = current.identity_since;
+    updated_analyzer.conformance_report_until = current.conformance_report_until;
+    updated_analyzer.last_connected_at = current.last_connected_at;
+
     // TODO: Add update_analyzer_config method to service
     // For now, we'll save to store and log that service update is not yet implemented
     log::warn!("update_meril_config: Service update not yet implemented, saving to store directly");
@@ -195,10 +226,9 @@ pub async fn get_meril_service_status<R: tauri::Runtime>(
     // Get the AppState from AppData
     let app_state = app.state::<crate::app_state::AppState<R>>();
 
-    let service = app_state.get_autoquant_meril_service();
-    let status = service.get_status().await;
-    let connections_count = service.get_connections_count().await;
-    let is_running = status == AnalyzerStatus::Active;
+    let controller = app_state.get_meril_controller();
+    let status = controller.service().get_status().await;
+    let (is_running, connections_count) = controller.status().await;
 
     Ok(MerilServiceStatus {
         is_running,
@@ -215,17 +245,11 @@ pub async fn start_meril_service<R: tauri::Runtime>(
     // Get the AppState from AppData
     let app_state = app.state::<crate::app_state::AppState<R>>();
 
-    // Note: We need mutable access to start the service
-    // For now, we'll use a workaround by cloning the service and starting it
-    let service = app_state.get_autoquant_meril_service().clone();
-
     log::info!("Starting Meril service...");
 
-    // Start the service
-    match service.start().await {
+    // Start the service; only its own controller is locked, so the other service stays responsive
+    match app_state.get_meril_controller().start().await {
         Ok(()) => {
-            log::info!("Meril service started successfully");
-
             // Emit event to frontend
             let _ = app.emit(
                 "meril:service-started",
@@ -259,17 +283,11 @@ pub async fn stop_meril_service<R: tauri::Runtime>(app: tauri::AppHandle<R>) ->
     // Get the AppState from AppData
     let app_state = app.state::<crate::app_state::AppState<R>>();
 
-    // Note: We need mutable access to stop the service
-    // For now, we'll use a workaround by cloning the service and stopping it
-    let service = app_state.get_autoquant_meril_service().clone();
-
     log::info!("Stopping Meril service...");
 
-    // Stop the service
-    match service.stop().await {
+    // Stop the service; only its own controller is locked, so the other service stays responsive
+    match app_state.get_meril_controller().stop().await {
         Ok(()) => {
-            log::info!("Meril service stopped successfully");
-
             // Emit event to frontend
             let _ = app.emit(
                 "meril:service-stopped",
@@ -297,6 +315,90 @@ pub async fn stop_meril_service<R: tauri::Runtime>(app: tauri::AppHandle<R>) ->
     }
 }
 
+/// Queues an order for the next host-initiated download to the analyzer
+#[tauri::command]
+pub async fn queue_meril_host_order<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    order: TestOrder,
+) -> Result<usize, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let service = app_state.get_autoquant_meril_service();
+    service.queue_host_order(order).await;
+    Ok(service.host_outbox_len().await)
+}
+
+/// Bids for the line (ENQ) and sends the queued orders to the connected analyzer.
+/// Returns the number of orders sent; zero when the analyzer kept or refused the line.
+#[tauri::command]
+pub async fn send_meril_host_orders<R: tauri::Runtime>(app: tauri::AppHandle<R>) -> Result<usize, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let sent = app_state.get_autoquant_meril_service().send_host_orders().await?;
+
+    let _ = app.emit(
+        "meril:host-orders-sent",
+        serde_json::json!({
+            "sent": sent,
+            "timestamp": chrono::Utc::now()
+        }),
+    );
+    Ok(sent)
+}
+
+/// Latest dispatch of `order_id`, which must be queued for `patient_id` and not yet sent
+async fn pending_dispatch(
+    repository: &crate::db::SqliteRepository,
+    patient_id: &str,
+    order_id: &str,
+) -> Result<OrderDispatch, String> {
+    let dispatch = repository
+        .get_latest_order_dispatch(order_id)
+        .await?
+        .ok_or_else(|| format!("Order {} is not queued", order_id))?;
+    if dispatch.patient_id.as_deref() != Some(patient_id) {
+        return Err(format!("Order {} is not queued for patient {}", order_id, patient_id));
+    }
+    if dispatch.status != DispatchStatus::Pending {
+        return Err(format!("Order {} is already {}", order_id, dispatch.status.to_string()));
+    }
+    Ok(dispatch)
+}
+
+/// Downloads a patient's queued orders to the connected analyzer as the line's sender.
+/// Orders sent are marked SENT in the dispatch queue; the outcome is also emitted as
+/// `meril:orders-sent` or `meril:orders-send-failed`.
+#[tauri::command]
+pub async fn send_meril_orders<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    patient_id: String,
+    order_ids: Vec<String>,
+) -> Result<(), String> {
+    if order_ids.is_empty() {
+        return Err("No orders to send".to_string());
+    }
+
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let repository = app_state.get_repository();
+    let patient = repository
+        .get_patient(&patient_id)
+        .await?
+        .ok_or_else(|| format!("Patient {} not found", patient_id))?;
+
+    let mut dispatches = Vec::with_capacity(order_ids.len());
+    for order_id in &order_ids {
+        dispatches.push(pending_dispatch(&repository, &patient_id, order_id).await?);
+    }
+    let orders: Vec<TestOrder> = dispatches.iter().map(|dispatch| dispatch.order.clone()).collect();
+
+    app_state.get_autoquant_meril_service().send_message(&patient, &orders).await?;
+
+    for dispatch in &dispatches {
+        repository
+            .update_order_dispatch_status(&dispatch.id, DispatchStatus::Sent, None)
+            .await?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -321,23 +423,9 @@ mod tests {
     #[test]
     fn test_validate_meril_config() {
         let valid_analyzer = Analyzer {
-            id: "test".to_string(),
-            name: "Test".to_string(),
-            model: "200i".to_string(),
-            serial_number: None,
-            manufacturer: Some("Meril".to_string()),
-            connection_type: ConnectionType::TcpIp,
             ip_address: Some("192.168.1.1".to_string()),
-            port: Some(5600),
-            com_port: None,
-            baud_rate: None,
-            external_ip: None,
-            external_port: None,
-            protocol: Protocol::Astm,
-            status: AnalyzerStatus::Inactive,
             activate_on_start: false,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
+            ..crate::test_support::meril_analyzer()
         };
 
         assert!(validate_meril_config(&valid_analyzer).is_ok());
@@ -370,5 +458,115 @@ mod tests {
             ..valid_analyzer.clone()
         };
         assert!(validate_meril_config(&valid_external).is_ok());
+
+        // A transmission timeout shorter than a gap timer can never be met
+        let invalid_timeouts = Analyzer {
+            astm_timeouts: crate::models::AstmTimeouts {
+                inter_byte_ms: 8_000,
+                inter_frame_ms: 30_000,
+                transmission_ms: 10_000,
+                ..Default::default()
+            },
+            ..valid_analyzer.clone()
+        };
+        assert!(validate_meril_config(&invalid_timeouts).is_err());
+        let zero_timeout = Analyzer {
+            astm_timeouts: crate::models::AstmTimeouts {
+                inter_byte_ms: 0,
+                ..Default::default()
+            },
+            ..valid_analyzer.clone()
+        };
+        assert!(validate_meril_config(&zero_timeout).is_err());
+
+        // Delimited-text accessories are accepted with a usable layout only
+        let delimited = Analyzer {
+            protocol: Protocol::DelimitedText,
+            ..valid_analyzer.clone()
+        };
+        assert!(validate_meril_config(&delimited).is_ok());
+        let no_time_column = Analyzer {
+            delimited_layout: crate::models::DelimitedLayout {
+                completed_at_column: None,
+                ..Default::default()
+            },
+            ..delimited
+        };
+        assert!(validate_meril_config(&no_time_column).is_err());
+        let hl7 = Analyzer {
+            protocol: Protocol::Hl7,
+            ..valid_analyzer.clone()
+        };
+        assert!(validate_meril_config(&hl7).is_err());
+    }
+
+    #[test]
+    fn test_partial_save_keeps_the_other_settings() {
+        let stored = Analyzer {
+            result_acceptance: crate::models::ResultAcceptanceWindow {
+                max_past_days: Some(7),
+                max_future_days: Some(1),
+            },
+            checksum_policy: crate::models::ChecksumPolicy::Strict,
+            clock_skew_seconds: -90,
+            default_patient_class: Some("O".to_string()),
+            ..crate::test_support::meril_analyzer()
+        };
+
+        // What the settings form sends: only the fields it edits
+        let saved = stored
+            .merged_with(serde_json::json!({
+                "name": "Meril bench 2",
+                "port": 5601,
+                "bind_address": null,
+            }))
+            .unwrap();
+        assert_eq!(saved.name, "Meril bench 2");
+        assert_eq!(saved.port, Some(5601));
+        assert_eq!(saved.bind_address, None);
+        assert_eq!(saved.result_acceptance, stored.result_acceptance);
+        assert_eq!(saved.checksum_policy, crate::models::ChecksumPolicy::Strict);
+        assert_eq!(saved.clock_skew_seconds, -90);
+        assert_eq!(saved.default_patient_class.as_deref(), Some("O"));
+        assert_eq!(saved.id, stored.id);
+        assert!(validate_meril_config(&saved).is_ok());
+
+        // A field sent with a bad value is refused rather than defaulted
+        assert!(stored.merged_with(serde_json::json!({ "port": "not a port" })).is_err());
+        assert!(stored.merged_with(serde_json::json!("name")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_only_the_patients_pending_orders_are_sent() {
+        let repository = crate::db::SqliteRepository::new(crate::db::establish_test_connection().await);
+        repository
+            .enqueue_order_dispatch("meril", "P1", &TestOrder::fixture("O1", "S1"))
+            .await
+            .unwrap();
+        let sent = repository
+            .enqueue_order_dispatch("meril", "P1", &TestOrder::fixture("O2", "S2"))
+            .await
+            .unwrap();
+        repository
+            .update_order_dispatch_status(&sent.id, DispatchStatus::Sent, None)
+            .await
+            .unwrap();
+        repository
+            .enqueue_order_dispatch("meril", "P2", &TestOrder::fixture("O3", "S3"))
+            .await
+            .unwrap();
+
+        let dispatch = pending_dispatch(&repository, "P1", "O1").await.unwrap();
+        assert_eq!(dispatch.order.specimen_id, "S1");
+        assert_eq!(
+            pending_dispatch(&repository, "P1", "O2").await.unwrap_err(),
+            "Order O2 is already SENT"
+        );
+        // Another patient's order is never sent under this patient's record
+        assert_eq!(
+            pending_dispatch(&repository, "P1", "O3").await.unwrap_err(),
+            "Order O3 is not queued for patient P1"
+        );
+        assert!(pending_dispatch(&repository, "P1", "O4").await.is_err());
     }
 }
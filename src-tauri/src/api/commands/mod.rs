@@ -1,7 +1,19 @@
 pub mod bf6900_handler;
+pub mod export_handler;
+pub mod his_handler;
+pub mod import_handler;
 pub mod ip_handler;
 pub mod meril_handler;
+pub mod order_handler;
+pub mod patient_handler;
+pub mod system_handler;
 
 pub use bf6900_handler::*;
+pub use export_handler::*;
+pub use his_handler::*;
+pub use import_handler::*;
 pub use ip_handler::*;
 pub use meril_handler::*;
+pub use order_handler::*;
+pub use patient_handler::*;
+pub use system_handler::*;
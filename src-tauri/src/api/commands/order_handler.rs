@@ -0,0 +1,14 @@
+use tauri::Manager;
+
+use crate::models::OrderDispatch;
+
+/// Lists queued worklist downloads with their dispatch state
+/// (pending/sent/acked/failed, attempts and last error), newest first
+#[tauri::command]
+pub async fn list_test_orders<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<Vec<OrderDispatch>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    app_state.get_repository().list_order_dispatches().await
+}
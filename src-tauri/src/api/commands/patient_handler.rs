@@ -0,0 +1,486 @@
+use chrono::{DateTime, Utc};
+use tauri::Manager;
+use tauri_plugin_store::StoreExt;
+
+use crate::db::SqliteRepository;
+use crate::models::patient::{PatientName, Sex};
+use crate::models::{
+    DataSource, DemographicsHold, DuplicateCandidate, ResultChangePage, ResultDetail, ResultFilter, ResultNote, ResultStatus,
+    ReviewReason, TestResult, VerificationHold, VerificationRule,
+};
+use crate::services::demographics_policy::{
+    demographics_policy_from_store, DemographicsPolicy, DEMOGRAPHICS_POLICY_STORE_KEY,
+};
+use crate::services::duplicate_detection::{
+    self, duplicate_detection_from_store, DuplicateDetectionSettings, DuplicateScanSummary, MergeOutcome,
+    DUPLICATE_DETECTION_STORE_KEY,
+};
+use crate::services::result_feed::{DEFAULT_RESULT_CHANGES_PAGE, MAX_RESULT_CHANGES_PAGE};
+use crate::services::sample_locks::SampleLocks;
+use crate::services::stale_results::{stale_results_from_store, StaleResultSettings, STALE_RESULTS_STORE_KEY};
+use crate::services::verification_rules::{validate_rule, ApprovalSummary, VerificationDryRun};
+
+/// Gets the heuristics used to suggest duplicate patients
+#[tauri::command]
+pub async fn get_duplicate_detection_settings<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<DuplicateDetectionSettings, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+
+    Ok(duplicate_detection_from_store(store.get(DUPLICATE_DETECTION_STORE_KEY)))
+}
+
+/// Replaces the duplicate detection heuristics; takes effect for the next scan
+#[tauri::command]
+pub async fn set_duplicate_detection_settings<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    settings: DuplicateDetectionSettings,
+) -> Result<DuplicateDetectionSettings, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+    let value = serde_json::to_value(&settings)
+        .map_err(|e| format!("Failed to serialize duplicate detection settings: {}", e))?;
+    store.set(DUPLICATE_DETECTION_STORE_KEY.to_string(), value);
+
+    Ok(settings)
+}
+
+/// Scans all patients for probable duplicates now instead of waiting for the scheduled scan
+#[tauri::command]
+pub async fn scan_for_duplicate_patients<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<DuplicateScanSummary, String> {
+    let settings = get_duplicate_detection_settings(app.clone()).await?;
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    duplicate_detection::scan_for_duplicates(app_state.get_repository(), &settings)
+        .await
+        .map_err(|e| {
+            log::error!("Duplicate patient scan failed: {}", e);
+            e
+        })
+}
+
+/// Lists duplicate pairs awaiting review that score at least `min_score`, best match first
+#[tauri::command]
+pub async fn get_duplicate_candidates<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    min_score: f64,
+) -> Result<Vec<DuplicateCandidate>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state.get_repository().get_pending_duplicate_candidates(min_score).await
+}
+
+/// Merges a duplicate pair. Keeps `keep_patient_id`, or the older patient when omitted.
+#[tauri::command]
+pub async fn accept_duplicate_candidate<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    candidate_id: String,
+    keep_patient_id: Option<String>,
+) -> Result<MergeOutcome, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    duplicate_detection::accept_duplicate_candidate(
+        app_state.get_repository(),
+        &candidate_id,
+        keep_patient_id.as_deref(),
+    )
+    .await
+    .map_err(|e| {
+        log::error!("Failed to accept duplicate candidate {}: {}", candidate_id, e);
+        e
+    })
+}
+
+/// Marks a duplicate pair as different patients; it will not be suggested again
+#[tauri::command]
+pub async fn dismiss_duplicate_candidate<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    candidate_id: String,
+) -> Result<(), String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    duplicate_detection::dismiss_duplicate_candidate(app_state.get_repository(), &candidate_id).await
+}
+
+/// Gets the patient fields required before results are uploaded to the HIS
+#[tauri::command]
+pub async fn get_demographics_policy<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<DemographicsPolicy, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+
+    Ok(demographics_policy_from_store(store.get(DEMOGRAPHICS_POLICY_STORE_KEY)))
+}
+
+/// Replaces the mandatory-demographics policy; applies to results received from now on
+#[tauri::command]
+pub async fn set_demographics_policy<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    policy: DemographicsPolicy,
+) -> Result<DemographicsPolicy, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+    let value = serde_json::to_value(&policy)
+        .map_err(|e| format!("Failed to serialize demographics policy: {}", e))?;
+    store.set(DEMOGRAPHICS_POLICY_STORE_KEY.to_string(), value);
+
+    Ok(policy)
+}
+
+/// Lists results held back from HIS upload until their patient's demographics are complete
+#[tauri::command]
+pub async fn get_results_pending_demographics<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<Vec<DemographicsHold>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state.get_repository().get_results_pending_demographics().await
+}
+
+/// Completes a patient's demographics; omitted fields keep their stored values.
+/// Returns the held results released for upload as a result.
+#[tauri::command]
+pub async fn update_patient_demographics<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    patient_id: String,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    birth_date: Option<DateTime<Utc>>,
+    sex: Option<String>,
+) -> Result<Vec<DemographicsHold>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let name = PatientName {
+        last_name,
+        first_name,
+        middle_name: None,
+        title: None,
+    };
+    let sex = sex.as_deref().map(Sex::from);
+
+    if !app_state
+        .get_repository()
+        .update_patient_demographics(&patient_id, &name, birth_date, sex)
+        .await?
+    {
+        return Err(format!("Patient {} not found", patient_id));
+    }
+
+    app_state.release_demographics_holds(&app, &patient_id).await.map_err(|e| {
+        log::error!("Failed to release held results for patient {}: {}", patient_id, e);
+        e
+    })
+}
+
+/// Releases held results for upload although their demographics are incomplete,
+/// e.g. for samples whose patient never became known
+#[tauri::command]
+pub async fn release_demographics_hold<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    hold_id: String,
+) -> Result<DemographicsHold, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state
+        .release_demographics_hold(&app, &hold_id)
+        .await?
+        .ok_or_else(|| format!("Demographics hold {} was already released", hold_id))
+}
+
+/// Gets the active auto-verification rules, latest version of each, in evaluation order
+#[tauri::command]
+pub async fn get_verification_rules<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<Vec<VerificationRule>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state.get_repository().get_active_verification_rules().await
+}
+
+/// Creates a rule, or saves an edit as its next version; takes effect for the next
+/// ingested sample. Returns the rule as stored, with its version.
+#[tauri::command]
+pub async fn save_verification_rule<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    rule: VerificationRule,
+) -> Result<VerificationRule, String> {
+    validate_rule(&rule)?;
+
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let stored = app_state.get_repository().save_verification_rule(&rule).await?;
+    log::info!("Saved verification rule {} version {}", stored.id, stored.version);
+    Ok(stored)
+}
+
+/// Stops applying a rule; its versions stay on record for results it decided
+#[tauri::command]
+pub async fn delete_verification_rule<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    rule_id: String,
+) -> Result<(), String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    if !app_state.get_repository().retire_verification_rule(&rule_id).await? {
+        return Err(format!("Verification rule {} not found", rule_id));
+    }
+    Ok(())
+}
+
+/// Gets every version of a rule, oldest first
+#[tauri::command]
+pub async fn get_verification_rule_history<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    rule_id: String,
+) -> Result<Vec<VerificationRule>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state.get_repository().get_verification_rule_history(&rule_id).await
+}
+
+/// Dry run: compares how many stored results completed between `from` and `to`
+/// the active rules and the proposed ones would auto-verify. Nothing is saved.
+#[tauri::command]
+pub async fn dry_run_verification_rules<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    rules: Vec<VerificationRule>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<VerificationDryRun, String> {
+    rules.iter().try_for_each(validate_rule)?;
+
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let current = app_state.get_repository().get_active_verification_rules().await?;
+    app_state.get_verification_gate().dry_run(&current, &rules, from, to).await
+}
+
+/// Lists results held back from HIS upload because the verification rules did not release them
+#[tauri::command]
+pub async fn get_results_pending_verification<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<Vec<VerificationHold>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state
+        .get_repository()
+        .get_results_pending_review(ReviewReason::Verification)
+        .await
+}
+
+/// Lists results held back from HIS upload because they were received long after they were run
+#[tauri::command]
+pub async fn get_stale_results<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<Vec<VerificationHold>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state.get_repository().get_results_pending_review(ReviewReason::Stale).await
+}
+
+/// Releases several reviewed holds at once, e.g. the whole stale-result queue
+#[tauri::command]
+pub async fn approve_results<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    hold_ids: Vec<String>,
+) -> Result<ApprovalSummary, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let approval = app_state.approve_results(&app, &hold_ids).await;
+    Ok(ApprovalSummary::from(&approval))
+}
+
+/// Gets the age beyond which results are held for review instead of uploaded
+#[tauri::command]
+pub async fn get_stale_result_settings<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<StaleResultSettings, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+
+    Ok(stale_results_from_store(store.get(STALE_RESULTS_STORE_KEY)))
+}
+
+/// Replaces the stale-result threshold; applies to results received from now on
+#[tauri::command]
+pub async fn set_stale_result_settings<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    settings: StaleResultSettings,
+) -> Result<StaleResultSettings, String> {
+    if settings.max_age_hours == Some(0) {
+        return Err("Stale result threshold must be at least one hour".to_string());
+    }
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+    let value = serde_json::to_value(&settings)
+        .map_err(|e| format!("Failed to serialize stale result settings: {}", e))?;
+    store.set(STALE_RESULTS_STORE_KEY.to_string(), value);
+
+    Ok(settings)
+}
+
+/// Releases results a technologist has verified. They are queued for upload
+/// unless the patient's mandatory demographics are still incomplete.
+#[tauri::command]
+pub async fn release_verification_hold<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    hold_id: String,
+) -> Result<VerificationHold, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state.release_verification_hold(&app, &hold_id).await.map_err(|e| {
+        log::error!("Failed to release verification hold {}: {}", hold_id, e);
+        e
+    })
+}
+
+/// Gets a patient's results, most recent first, optionally only those produced by
+/// one analyzer and/or by the configured analyzers speaking one protocol
+#[tauri::command]
+pub async fn get_patient_results<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    patient_id: String,
+    filter: Option<ResultFilter>,
+) -> Result<Vec<TestResult>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let repository = app_state.get_repository();
+    let filter = filter.unwrap_or_default();
+
+    if filter.is_empty() {
+        return repository.get_patient_results(&patient_id).await;
+    }
+
+    let analyzer_ids: Vec<String> = match (&filter.protocol, &filter.analyzer_id) {
+        // Results may name analyzers no longer configured here (e.g. imported ones)
+        (None, Some(analyzer_id)) => vec![analyzer_id.clone()],
+        _ => [
+            app_state.get_autoquant_meril_service().get_analyzer_config().await,
+            app_state.get_bf6900_service().get_analyzer_config().await,
+        ]
+        .into_iter()
+        .filter(|analyzer| filter.matches_analyzer(analyzer))
+        .map(|analyzer| analyzer.id)
+        .collect(),
+    };
+    repository.get_patient_results_by_analyzers(&patient_id, &analyzer_ids).await
+}
+
+/// Gets the results carried by one inbound message, by its MSH-10 / ASTM H.3 control id
+#[tauri::command]
+pub async fn get_results_by_message_control_id<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    control_id: String,
+) -> Result<Vec<TestResult>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    app_state
+        .get_repository()
+        .get_results_by_message_control_id(&control_id)
+        .await
+}
+
+/// Gets a result with its reference range, flags, notes, images, uploads and audit trail
+#[tauri::command]
+pub async fn get_result_detail<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    result_id: String,
+) -> Result<ResultDetail, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    app_state
+        .get_repository()
+        .get_result_detail(&result_id)
+        .await?
+        .ok_or_else(|| format!("Result {} not found", result_id))
+}
+
+/// Gets the result changes after `since_seq` for consumers polling for new results;
+/// start from 0 and pass back `next_seq`
+#[tauri::command]
+pub async fn get_result_changes<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    since_seq: i64,
+    limit: Option<u32>,
+) -> Result<ResultChangePage, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    app_state
+        .get_repository()
+        .get_result_changes(since_seq, limit.unwrap_or(DEFAULT_RESULT_CHANGES_PAGE).clamp(1, MAX_RESULT_CHANGES_PAGE))
+        .await
+}
+
+/// Attaches a free-text note to a result
+#[tauri::command]
+pub async fn add_result_note<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    result_id: String,
+    text: String,
+    author: Option<String>,
+) -> Result<ResultNote, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let repository = app_state.get_repository();
+    if text.trim().is_empty() {
+        return Err("A note needs some text".to_string());
+    }
+    if repository.get_test_result(&result_id).await?.is_none() {
+        return Err(format!("Result {} not found", result_id));
+    }
+
+    let note = ResultNote {
+        id: uuid::Uuid::new_v4().to_string(),
+        result_id,
+        author,
+        text: text.trim().to_string(),
+        created_at: Utc::now(),
+    };
+    repository.add_result_note(&note).await?;
+    Ok(note)
+}
+
+/// Replaces a result's value with a correction, keeping the previous value in the
+/// result's supersede chain. Fails with a retriable `SAMPLE_BUSY` error while the
+/// sample is being ingested or edited elsewhere.
+#[tauri::command]
+pub async fn edit_test_result<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    result_id: String,
+    value: String,
+) -> Result<TestResult, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    correct_test_result(app_state.get_repository(), app_state.get_sample_locks(), &result_id, value).await
+}
+
+/// Supersedes a result with a manual correction carrying `value` and the result's
+/// comments, holding the sample's lock for the read and the write
+pub(crate) async fn correct_test_result(
+    repository: &SqliteRepository,
+    sample_locks: &SampleLocks,
+    result_id: &str,
+    value: String,
+) -> Result<TestResult, String> {
+    let result = repository
+        .get_test_result(result_id)
+        .await?
+        .ok_or_else(|| format!("Result {} not found", result_id))?;
+    let _sample_lock = sample_locks.acquire(&result.sample_id).await?;
+
+    // Re-read under the lock; a correction may have landed while waiting
+    let current = repository
+        .get_current_result(&result.sample_id, &result.test_id)
+        .await?
+        .filter(|current| current.id == result_id)
+        .ok_or_else(|| format!("Result {} has been superseded, reload and try again", result_id))?;
+
+    let now = Utc::now();
+    let replacement = TestResult {
+        id: uuid::Uuid::new_v4().to_string(),
+        value,
+        status: ResultStatus::Correction,
+        created_at: now,
+        updated_at: now,
+        ..current
+    };
+    repository
+        .supersede_test_result(result_id, &replacement, &DataSource::Manual)
+        .await?;
+
+    log::info!("Result {} of sample {} corrected as {}", result_id, replacement.sample_id, replacement.id);
+    Ok(replacement)
+}
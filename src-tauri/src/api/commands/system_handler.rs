@@ -0,0 +1,846 @@
+use tauri::{Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+use crate::api::commands::bf6900_handler::BF6900StoreData;
+use crate::db::{check_integrity, IntegrityReport, RecoveryReport, RetryMetrics};
+use crate::models::{
+    ConformanceReport, DashboardCounts, EventSummary, FirmwareChange, LatencyStats, RemoteAddress,
+    ResultIntegrityReport, TimelineStageView,
+};
+use crate::protocol::message_profile::{message_profile_from_store, MESSAGE_PROFILE_STORE_KEY};
+use crate::services::app_paths::{DirectoryCheck, DirectoryCheckState};
+use crate::services::bootup::DatabaseRecoveryState;
+use crate::services::conformance::{conformance_metrics, ConformanceMetrics};
+use crate::services::conversation_log::{
+    connection_history_from_store, ConnectionHistorySettings, ConnectionRetentionMetrics, ConnectionSummary,
+    ConversationEntry, CONNECTION_HISTORY_STORE_KEY,
+};
+use crate::services::developer_console::{
+    audit_raw_send, developer_console_from_store, DeveloperConsoleSettings, RawExchange, RawPayload,
+    DEFAULT_RAW_RESPONSE_WINDOW, DEVELOPER_CONSOLE_STORE_KEY, MAX_RAW_RESPONSE_WINDOW,
+};
+use crate::services::disk_monitor::DiskStatus;
+use crate::services::firmware_tracking::{conformance_mode_from_store, FIRMWARE_CHANGE_CONFORMANCE_MODE_STORE_KEY};
+use crate::services::forwarding_rules::{
+    evaluate_rules, forwarding_rules_from_store, validate_rules, ForwardCandidate, ForwardMatch, ForwardingRule,
+    FORWARDING_RULES_STORE_KEY,
+};
+use crate::services::id_normalization::{id_normalization_from_store, IdNormalization, ID_NORMALIZATION_STORE_KEY};
+use crate::services::maintenance::MaintenanceStatus;
+use crate::services::report_locale::{report_locale_from_store, ReportLocaleSettings, REPORT_LOCALE_STORE_KEY};
+use crate::services::sample_report::{
+    load_report_graphs, render_sample_report, report_attachments, report_graphs_from_store, ReportAttachment,
+    ReportGraphSettings, SampleReportInput, REPORT_GRAPHS_STORE_KEY, SAMPLE_REPORT_TEMPLATE,
+};
+use crate::services::service_controller::autostart_enabled;
+use crate::services::setup_sheet::{host_interface_ips, render_setup_sheet, SetupSheetInput};
+use crate::services::setup_wizard::{
+    self, find_operator, operators_from_store, SetupSection, SetupState, SetupStores, USERS_STORE_KEY,
+};
+use crate::services::shadow_mode::SHADOW_MODE_STORE_KEY;
+
+/// Gets free space and protective-mode state for the app-data volume
+#[tauri::command]
+pub async fn get_disk_status<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<DiskStatus, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    Ok(app_state.get_disk_monitor().get_status().await)
+}
+
+/// Gets the counters shown on the dashboard tiles
+#[tauri::command]
+pub async fn get_dashboard_counts<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<DashboardCounts, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    app_state.get_repository().dashboard_counts().await
+}
+
+/// Gets the processing stages of a stored raw message with their durations
+#[tauri::command]
+pub async fn get_message_timeline<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    raw_message_id: String,
+) -> Result<Vec<TimelineStageView>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    app_state
+        .get_repository()
+        .get_message_timeline(&raw_message_id)
+        .await
+}
+
+/// Gets per-message processing latency histograms (receive → parse → store → upload)
+/// for messages received since `since` (RFC 3339), by default the last 24 hours
+#[tauri::command]
+pub async fn get_latency_stats<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    since: Option<String>,
+) -> Result<LatencyStats, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    let since = match since {
+        Some(since) => chrono::DateTime::parse_from_rfc3339(&since)
+            .map_err(|e| format!("Invalid 'since' timestamp: {}", e))?
+            .with_timezone(&chrono::Utc),
+        None => chrono::Utc::now() - chrono::Duration::hours(24),
+    };
+
+    app_state.get_repository().get_latency_stats(since).await
+}
+
+/// Gets an analyzer's stored errors/events between `from` and `to` (RFC 3339), grouped by type
+#[tauri::command]
+pub async fn get_analyzer_event_summary<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    analyzer_id: String,
+    from: String,
+    to: String,
+) -> Result<EventSummary, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    let from = chrono::DateTime::parse_from_rfc3339(&from)
+        .map_err(|e| format!("Invalid 'from' timestamp: {}", e))?
+        .with_timezone(&chrono::Utc);
+    let to = chrono::DateTime::parse_from_rfc3339(&to)
+        .map_err(|e| format!("Invalid 'to' timestamp: {}", e))?
+        .with_timezone(&chrono::Utc);
+
+    app_state
+        .get_repository()
+        .analyzer_event_summary(&analyzer_id, from, to)
+        .await
+}
+
+/// Gets the spec deviations an analyzer produced between `from` and `to` (RFC 3339), grouped by rule
+#[tauri::command]
+pub async fn get_conformance_report<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    analyzer_id: String,
+    from: String,
+    to: String,
+) -> Result<ConformanceReport, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    let from = chrono::DateTime::parse_from_rfc3339(&from)
+        .map_err(|e| format!("Invalid 'from' timestamp: {}", e))?
+        .with_timezone(&chrono::Utc);
+    let to = chrono::DateTime::parse_from_rfc3339(&to)
+        .map_err(|e| format!("Invalid 'to' timestamp: {}", e))?
+        .with_timezone(&chrono::Utc);
+
+    app_state
+        .get_repository()
+        .get_conformance_report(&analyzer_id, from, to)
+        .await
+}
+
+/// Gets how many messages were conformance-checked since startup and which rules they broke
+#[tauri::command]
+pub async fn get_conformance_metrics() -> Result<ConformanceMetrics, String> {
+    Ok(conformance_metrics())
+}
+
+/// Gets the identity changes (likely firmware updates) an analyzer declared, oldest first
+#[tauri::command]
+pub async fn get_firmware_history<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    analyzer_id: String,
+) -> Result<Vec<FirmwareChange>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    app_state.get_repository().get_firmware_history(&analyzer_id).await
+}
+
+/// Gets whether a firmware change puts the analyzer in conformance-report mode for 24 hours:
+/// every message is logged in full and messages with conformance deviations are held for review
+#[tauri::command]
+pub async fn get_firmware_change_conformance_mode<R: tauri::Runtime>(app: tauri::AppHandle<R>) -> Result<bool, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+
+    Ok(conformance_mode_from_store(store.get(FIRMWARE_CHANGE_CONFORMANCE_MODE_STORE_KEY)))
+}
+
+/// Turns conformance-report mode on firmware changes on or off
+#[tauri::command]
+pub async fn set_firmware_change_conformance_mode<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    enabled: bool,
+) -> Result<bool, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+    store.set(FIRMWARE_CHANGE_CONFORMANCE_MODE_STORE_KEY.to_string(), serde_json::json!(enabled));
+
+    Ok(enabled)
+}
+
+/// Gets whether shadow (store-only) mode is on
+#[tauri::command]
+pub async fn get_shadow_mode<R: tauri::Runtime>(app: tauri::AppHandle<R>) -> Result<bool, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    Ok(app_state.get_shadow_mode().is_enabled())
+}
+
+/// Turns shadow (store-only) mode on or off and persists the choice
+#[tauri::command]
+pub async fn set_shadow_mode<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    enabled: bool,
+) -> Result<bool, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+    store.set(SHADOW_MODE_STORE_KEY.to_string(), serde_json::json!(enabled));
+
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state.get_shadow_mode().set_enabled(enabled);
+
+    Ok(enabled)
+}
+
+/// Gets the rules applied to inbound patient/sample ids
+#[tauri::command]
+pub async fn get_id_normalization<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<IdNormalization, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+
+    Ok(id_normalization_from_store(store.get(ID_NORMALIZATION_STORE_KEY)))
+}
+
+/// Replaces the rules applied to inbound patient/sample ids; takes effect for the next message
+#[tauri::command]
+pub async fn set_id_normalization<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    rules: IdNormalization,
+) -> Result<IdNormalization, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+    let value = serde_json::to_value(&rules)
+        .map_err(|e| format!("Failed to serialize id normalization rules: {}", e))?;
+    store.set(ID_NORMALIZATION_STORE_KEY.to_string(), value);
+
+    Ok(rules)
+}
+
+/// Gets the rules that forward processed results to other systems
+#[tauri::command]
+pub async fn get_forwarding_rules<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<Vec<ForwardingRule>, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+
+    Ok(forwarding_rules_from_store(store.get(FORWARDING_RULES_STORE_KEY)))
+}
+
+/// Replaces the result forwarding rules; takes effect for the next processed result
+#[tauri::command]
+pub async fn set_forwarding_rules<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    rules: Vec<ForwardingRule>,
+) -> Result<Vec<ForwardingRule>, String> {
+    validate_rules(&rules)?;
+
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+    let value = serde_json::to_value(&rules)
+        .map_err(|e| format!("Failed to serialize forwarding rules: {}", e))?;
+    store.set(FORWARDING_RULES_STORE_KEY.to_string(), value);
+
+    Ok(rules)
+}
+
+/// Dry run: returns the rules that would fire for a result without forwarding it.
+/// Uses the given rules, or the stored ones when none are passed.
+#[tauri::command]
+pub async fn test_forwarding_rules<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    rules: Option<Vec<ForwardingRule>>,
+    result: ForwardCandidate,
+) -> Result<Vec<ForwardMatch>, String> {
+    let rules = match rules {
+        Some(rules) => rules,
+        None => get_forwarding_rules(app).await?,
+    };
+    validate_rules(&rules)?;
+
+    Ok(evaluate_rules(&rules, &result))
+}
+
+/// Lists the remote addresses an analyzer has connected from
+#[tauri::command]
+pub async fn list_remote_addresses<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    analyzer_id: String,
+) -> Result<Vec<RemoteAddress>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state.get_remote_address_guard().list(&analyzer_id).await
+}
+
+/// Approves a new remote address of a strict-mode analyzer and replays the
+/// messages held from it. Returns the number of replayed messages.
+#[tauri::command]
+pub async fn approve_remote_address<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    analyzer_id: String,
+    ip: String,
+) -> Result<usize, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state.approve_remote_address(&analyzer_id, ip.trim()).await
+}
+
+/// Renders a printable Markdown setup sheet for an analyzer from its live
+/// configuration: host IPs, port/protocol, framing, ACK mode, allowed addresses
+/// and the instrument-side checklist
+#[tauri::command]
+pub async fn generate_setup_sheet<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    analyzer_id: String,
+) -> Result<String, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    let meril = app_state.get_autoquant_meril_service().get_analyzer_config().await;
+    let bf6900 = app_state.get_bf6900_service().get_analyzer_config().await;
+
+    let (analyzer, hl7_settings, message_profile) = if meril.id == analyzer_id {
+        (meril, None, None)
+    } else if bf6900.id == analyzer_id {
+        let store = app
+            .store("bf6900.json")
+            .map_err(|e| format!("Error getting BF-6900 store: {}", e))?;
+        let hl7_settings = store
+            .get("config")
+            .and_then(|value| serde_json::from_value::<BF6900StoreData>(value).ok())
+            .and_then(|data| data.hl7_settings)
+            .unwrap_or_default();
+        let message_profile = message_profile_from_store(store.get(MESSAGE_PROFILE_STORE_KEY));
+        (bf6900, Some(hl7_settings), Some(message_profile))
+    } else {
+        return Err(format!("Unknown analyzer: {}", analyzer_id));
+    };
+
+    let remote_addresses = app_state.get_remote_address_guard().list(&analyzer_id).await?;
+
+    Ok(render_setup_sheet(&SetupSheetInput {
+        analyzer,
+        host_ips: host_interface_ips(),
+        hl7_settings,
+        message_profile,
+        remote_addresses,
+        shadow_mode: app_state.get_shadow_mode().is_enabled(),
+    }))
+}
+
+/// Ids of the analyzers started with the LIS
+#[tauri::command]
+pub async fn get_autostart_analyzers<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<Vec<String>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let autostart = app_state.get_repository().get_autostart_settings().await?;
+
+    let analyzers = [
+        app_state.get_autoquant_meril_service().get_analyzer_config().await,
+        app_state.get_bf6900_service().get_analyzer_config().await,
+    ];
+    Ok(analyzers
+        .into_iter()
+        .filter(|analyzer| autostart_enabled(analyzer, &autostart))
+        .map(|analyzer| analyzer.id)
+        .collect())
+}
+
+/// Adds an analyzer to, or removes it from, the set started with the LIS; the
+/// analyzer's `activate_on_start` is kept in step
+#[tauri::command]
+pub async fn set_autostart<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    analyzer_id: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    let meril = app_state.get_autoquant_meril_service();
+    let bf6900 = app_state.get_bf6900_service();
+    if meril.get_analyzer_config().await.id == analyzer_id {
+        meril.set_activate_on_start(enabled).await?;
+    } else if bf6900.get_analyzer_config().await.id == analyzer_id {
+        bf6900.set_activate_on_start(enabled).await?;
+    } else {
+        return Err(format!("Unknown analyzer: {}", analyzer_id));
+    }
+
+    app_state.get_repository().set_autostart(&analyzer_id, enabled).await?;
+    log::info!("Autostart {} for analyzer {}", if enabled { "enabled" } else { "disabled" }, analyzer_id);
+    Ok(())
+}
+
+/// Renders a printable Markdown report of a sample's results in the locale configured
+/// for the sample report template, or for `clinic` when the template has none.
+/// The sample's graphs are inlined when the template is set to include them.
+#[tauri::command]
+pub async fn generate_sample_report<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    sample_id: String,
+    patient_id: Option<String>,
+    clinic: Option<String>,
+) -> Result<String, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+    let locale = report_locale_from_store(store.get(REPORT_LOCALE_STORE_KEY))
+        .locale_for(SAMPLE_REPORT_TEMPLATE, clinic.as_deref());
+    let embed_graphs = report_graphs_from_store(store.get(REPORT_GRAPHS_STORE_KEY)).embeds_graphs(SAMPLE_REPORT_TEMPLATE);
+
+    let repository = app_state.get_repository();
+    let results = repository.get_results_by_sample_id(&sample_id).await?;
+    let patient = match patient_id {
+        Some(patient_id) => repository.get_patient(&patient_id).await?,
+        None => None,
+    };
+    let graphs = if embed_graphs {
+        load_report_graphs(report_attachments(&results, &repository.get_sample_images(&sample_id).await?).await).await
+    } else {
+        Vec::new()
+    };
+
+    Ok(render_sample_report(
+        &SampleReportInput {
+            sample_id,
+            patient,
+            results,
+            graphs,
+            embed_graphs,
+            generated_at: chrono::Utc::now(),
+        },
+        &locale,
+    ))
+}
+
+/// Lists the graphs of a sample's results as the report places them, with the
+/// size of each file or none when it is missing, for the report preview
+#[tauri::command]
+pub async fn get_sample_attachments<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    sample_id: String,
+) -> Result<Vec<ReportAttachment>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let repository = app_state.get_repository();
+
+    let results = repository.get_results_by_sample_id(&sample_id).await?;
+    let images = repository.get_sample_images(&sample_id).await?;
+    Ok(report_attachments(&results, &images).await)
+}
+
+/// Gets which report templates embed the sample's graphs
+#[tauri::command]
+pub async fn get_report_graph_settings<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<ReportGraphSettings, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+
+    Ok(report_graphs_from_store(store.get(REPORT_GRAPHS_STORE_KEY)))
+}
+
+/// Replaces the report graph settings; takes effect for the next report
+#[tauri::command]
+pub async fn set_report_graph_settings<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    settings: ReportGraphSettings,
+) -> Result<ReportGraphSettings, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+    let value = serde_json::to_value(&settings)
+        .map_err(|e| format!("Failed to serialize report graph settings: {}", e))?;
+    store.set(REPORT_GRAPHS_STORE_KEY.to_string(), value);
+
+    Ok(settings)
+}
+
+/// Gets the locale reports are rendered in, per template and per clinic
+#[tauri::command]
+pub async fn get_report_locale_settings<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<ReportLocaleSettings, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+
+    Ok(report_locale_from_store(store.get(REPORT_LOCALE_STORE_KEY)))
+}
+
+/// Replaces the report locale settings; takes effect for the next report
+#[tauri::command]
+pub async fn set_report_locale_settings<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    settings: ReportLocaleSettings,
+) -> Result<ReportLocaleSettings, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+    let value = serde_json::to_value(&settings)
+        .map_err(|e| format!("Failed to serialize report locale settings: {}", e))?;
+    store.set(REPORT_LOCALE_STORE_KEY.to_string(), value);
+
+    Ok(settings)
+}
+
+/// Stops accepting analyzer connections, gives open transmissions `grace_seconds`
+/// to finish and drains the queues in the background. `lis:maintenance-safe`
+/// fires once nothing is in flight.
+#[tauri::command]
+pub async fn enter_maintenance_mode<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    grace_seconds: u64,
+) -> Result<MaintenanceStatus, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let maintenance = app_state.get_maintenance().clone();
+    let status = maintenance
+        .begin(std::time::Duration::from_secs(grace_seconds))
+        .await?;
+
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match maintenance.quiesce().await {
+            Ok(status) => {
+                let _ = app_handle.emit("lis:maintenance-safe", &status);
+            }
+            Err(e) => {
+                log::error!("Maintenance drain did not complete: {}", e);
+                let _ = app_handle.emit("lis:maintenance-failed", &e);
+            }
+        }
+    });
+
+    Ok(status)
+}
+
+/// Gets the maintenance phase, open connections and queue backlog
+#[tauri::command]
+pub async fn get_maintenance_status<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<MaintenanceStatus, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    Ok(app_state.get_maintenance().status())
+}
+
+/// Reopens the analyzer listeners and resumes normal operation
+#[tauri::command]
+pub async fn exit_maintenance_mode<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<MaintenanceStatus, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state.get_maintenance().exit().await
+}
+
+/// Runs an integrity check (`PRAGMA quick_check`) on the LIS database
+#[tauri::command]
+pub async fn verify_database_integrity<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<IntegrityReport, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    let report = check_integrity(app_state.get_repository().pool()).await?;
+    if !report.ok {
+        log::error!("Database integrity check failed: {:?}", report.messages);
+    }
+    Ok(report)
+}
+
+/// Re-hashes stored results and lists any whose content changed since storage
+#[tauri::command]
+pub async fn verify_result_hashes<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<ResultIntegrityReport, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    let report = app_state.get_repository().verify_result_hashes().await?;
+    if !report.mismatched.is_empty() {
+        log::error!("Results no longer match their content hash: {:?}", report.mismatched);
+    }
+    Ok(report)
+}
+
+/// Gets busy/locked retry counters and the database circuit breaker state
+#[tauri::command]
+pub async fn get_database_retry_metrics<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<RetryMetrics, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    Ok(app_state.get_repository().retry_metrics())
+}
+
+/// Gets the report of a recovery performed at startup, if the database was corrupt
+#[tauri::command]
+pub async fn get_database_recovery_report<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<Option<RecoveryReport>, String> {
+    Ok(app.state::<DatabaseRecoveryState>().report.clone())
+}
+
+/// Gets the startup check of each required directory (database, stores, logs);
+/// a check with an error names the path that could not be created or written
+#[tauri::command]
+pub async fn get_directory_checks<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<Vec<DirectoryCheck>, String> {
+    Ok(app.state::<DirectoryCheckState>().checks.clone())
+}
+
+/// Gets the last `limit` protocol elements of an analyzer connection, open or
+/// recently closed, for drawing its sequence diagram
+#[tauri::command]
+pub async fn get_conversation<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    connection_id: String,
+    limit: usize,
+) -> Result<Vec<ConversationEntry>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    app_state
+        .get_autoquant_meril_service()
+        .conversation_log()
+        .get_conversation(&connection_id, limit)
+        .or_else(|| {
+            app_state
+                .get_bf6900_service()
+                .conversation_log()
+                .get_conversation(&connection_id, limit)
+        })
+        .ok_or_else(|| format!("No conversation recorded for connection {}", connection_id))
+}
+
+/// Lists open and recently closed analyzer connections, newest first
+#[tauri::command]
+pub async fn get_recent_connections<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<Vec<ConnectionSummary>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    let mut connections = app_state
+        .get_autoquant_meril_service()
+        .conversation_log()
+        .recent_connections();
+    connections.extend(app_state.get_bf6900_service().conversation_log().recent_connections());
+    // Open connections first, then by most recent activity
+    connections.sort_by(|a, b| {
+        a.closed_at
+            .is_some()
+            .cmp(&b.closed_at.is_some())
+            .then_with(|| b.closed_at.unwrap_or(b.opened_at).cmp(&a.closed_at.unwrap_or(a.opened_at)))
+    });
+    Ok(connections)
+}
+
+/// Gets how many closed connections stay in memory
+#[tauri::command]
+pub async fn get_connection_history_settings<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<ConnectionHistorySettings, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+
+    Ok(connection_history_from_store(store.get(CONNECTION_HISTORY_STORE_KEY)))
+}
+
+/// Replaces the connection history settings; the new bound applies immediately
+#[tauri::command]
+pub async fn set_connection_history_settings<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    settings: ConnectionHistorySettings,
+) -> Result<ConnectionHistorySettings, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+    let value = serde_json::to_value(&settings)
+        .map_err(|e| format!("Failed to serialize connection history settings: {}", e))?;
+    store.set(CONNECTION_HISTORY_STORE_KEY.to_string(), value);
+
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    app_state.get_autoquant_meril_service().conversation_log().set_recent_limit(settings.recent_limit);
+    app_state.get_bf6900_service().conversation_log().set_recent_limit(settings.recent_limit);
+    Ok(settings)
+}
+
+/// Gets an analyzer's closed connections from the database, most recent first
+#[tauri::command]
+pub async fn get_connection_history<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    analyzer_id: String,
+    limit: u32,
+) -> Result<Vec<ConnectionSummary>, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+
+    app_state.get_repository().get_connection_history(&analyzer_id, limit).await
+}
+
+/// Counts open connections against the closed ones kept in memory and in the database
+#[tauri::command]
+pub async fn get_connection_retention_metrics<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<ConnectionRetentionMetrics, String> {
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let meril_service = app_state.get_autoquant_meril_service();
+    let bf6900_service = app_state.get_bf6900_service();
+
+    let mut metrics = ConnectionRetentionMetrics {
+        live_connections: meril_service.get_connections_count().await + bf6900_service.get_connections_count().await,
+        archived: app_state.get_repository().count_archived_connections().await?,
+        ..Default::default()
+    };
+    for retention in [meril_service.conversation_log().retention(), bf6900_service.conversation_log().retention()] {
+        metrics.live_conversations += retention.live;
+        metrics.retained_closed += retention.retained_closed;
+        metrics.recent_limit = retention.recent_limit;
+    }
+    Ok(metrics)
+}
+
+/// Gets the developer console gate of this workstation
+#[tauri::command]
+pub async fn get_developer_console_settings<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+) -> Result<DeveloperConsoleSettings, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+
+    Ok(developer_console_from_store(store.get(DEVELOPER_CONSOLE_STORE_KEY)))
+}
+
+/// Replaces the developer console settings
+#[tauri::command]
+pub async fn set_developer_console_settings<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    settings: DeveloperConsoleSettings,
+) -> Result<DeveloperConsoleSettings, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+    let value = serde_json::to_value(&settings)
+        .map_err(|e| format!("Failed to serialize developer console settings: {}", e))?;
+    store.set(DEVELOPER_CONSOLE_STORE_KEY.to_string(), value);
+
+    Ok(settings)
+}
+
+/// Developer console: sends a hand-written HL7 message (MLLP framed) or ASTM record
+/// set (framed and sequenced) on an analyzer's live connection and returns what the
+/// analyzer answers within the window. Needs the console enabled, `operator` to be an
+/// admin account and `understood`; every attempt is written to the analyzer's event trail.
+#[tauri::command]
+pub async fn send_raw_message<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    analyzer_id: String,
+    operator: String,
+    protocol_element: RawPayload,
+    understood: bool,
+    response_window_ms: Option<u64>,
+) -> Result<RawExchange, String> {
+    let store = app
+        .store("settings.json")
+        .map_err(|e| format!("Error getting settings store: {}", e))?;
+    let operators = operators_from_store(store.get(USERS_STORE_KEY));
+    developer_console_from_store(store.get(DEVELOPER_CONSOLE_STORE_KEY)).authorize(
+        cfg!(debug_assertions),
+        find_operator(&operators, &operator),
+        understood,
+    )?;
+
+    let app_state = app.state::<crate::app_state::AppState<R>>();
+    let window = response_window_ms
+        .map(std::time::Duration::from_millis)
+        .unwrap_or(DEFAULT_RAW_RESPONSE_WINDOW)
+        .min(MAX_RAW_RESPONSE_WINDOW);
+    let meril_service = app_state.get_autoquant_meril_service();
+    let bf6900_service = app_state.get_bf6900_service();
+
+    let outcome = match protocol_element.normalized() {
+        Ok(RawPayload::Astm { records }) if meril_service.get_analyzer_config().await.id == analyzer_id => {
+            meril_service.send_raw_records(&records, window).await
+        }
+        Ok(RawPayload::Hl7 { message }) if bf6900_service.get_analyzer_config().await.id == analyzer_id => {
+            bf6900_service.send_raw_message(&message, window).await
+        }
+        Ok(payload) => Err(format!("No {} analyzer with id {}", payload.protocol(), analyzer_id)),
+        Err(e) => Err(e),
+    };
+
+    audit_raw_send(app_state.get_repository(), &analyzer_id, &operator, &protocol_element, &outcome).await?;
+    outcome
+}
+
+// ============================================================================
+// FIRST-RUN SETUP
+// ============================================================================
+
+/// Runs a wizard operation against the settings and analyzer stores
+fn with_setup_stores<R: tauri::Runtime, T>(
+    app: &tauri::AppHandle<R>,
+    operation: impl FnOnce(&SetupStores) -> Result<T, String>,
+) -> Result<T, String> {
+    let store = |name: &str| app.store(name).map_err(|e| format!("Error getting {} store: {}", name, e));
+    let (settings, meril, bf6900) = (store("settings.json")?, store("meril.json")?, store("bf6900.json")?);
+    operation(&SetupStores {
+        settings: &*settings,
+        meril: &*meril,
+        bf6900: &*bf6900,
+    })
+}
+
+/// Which setup sections are still unconfigured, with the current values of each
+#[tauri::command]
+pub async fn get_setup_state<R: tauri::Runtime>(app: tauri::AppHandle<R>) -> Result<SetupState, String> {
+    with_setup_stores(&app, |stores| Ok(setup_wizard::setup_state(stores)))
+}
+
+/// Validates a wizard step without saving it
+#[tauri::command]
+pub async fn validate_setup_step(section: SetupSection, payload: serde_json::Value) -> Result<(), String> {
+    setup_wizard::validate_setup_step(section, &payload)
+}
+
+/// Saves one wizard step; an invalid payload leaves every section unchanged
+#[tauri::command]
+pub async fn apply_setup_step<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    section: SetupSection,
+    payload: serde_json::Value,
+) -> Result<SetupState, String> {
+    let state = with_setup_stores(&app, |stores| setup_wizard::apply_setup_step(stores, section, &payload))?;
+
+    if section == SetupSection::Retention {
+        let settings = connection_history_from_store(Some(payload));
+        let app_state = app.state::<crate::app_state::AppState<R>>();
+        app_state.get_autoquant_meril_service().conversation_log().set_recent_limit(settings.recent_limit);
+        app_state.get_bf6900_service().conversation_log().set_recent_limit(settings.recent_limit);
+    }
+    Ok(state)
+}
+
+/// Runs the readiness checks, including the startup directory check, and marks setup
+/// complete so the wizard is not shown again
+#[tauri::command]
+pub async fn complete_setup<R: tauri::Runtime>(app: tauri::AppHandle<R>) -> Result<SetupState, String> {
+    let directories = app.state::<DirectoryCheckState>();
+    with_setup_stores(&app, |stores| setup_wizard::complete_setup(stores, &directories.checks))
+}
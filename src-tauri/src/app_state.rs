@@ -1,28 +1,101 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter, Runtime};
-use tokio::sync::mpsc;
-use tokio::task::JoinHandle;
 
-use crate::models::{ Analyzer, hematology::BF6900Event };
-use crate::services::autoquant_meril::AutoQuantMerilService;
+use async_trait::async_trait;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tauri_plugin_store::StoreExt;
+use tokio::sync::{mpsc, watch};
+
+use crate::db::{BreakerState, SqliteRepository};
+use crate::models::hematology::{HematologyResult, PatientData as HematologyPatientData};
+use crate::models::{ Analyzer, AnalyzerEventType, DataSource, DemographicsHold, FirmwareChange, hematology::BF6900Event, ProcessingStage, RawMessage, ResultStatus, ReviewReason, TestResult, UploadPriority, VerificationHold, VerificationStamp };
+use crate::services::autoquant_meril::{AutoQuantMerilService, MerilEvent, PatientData as MerilPatientData};
 use crate::services::bf6900_service::BF6900Service;
-use crate::services::his_client::HisClient;
+use crate::services::config_persistence::ConfigPersistence;
+use crate::services::conversation_log::{
+    archive_connections, connection_history_from_store, ConnectionHistorySettings, CONNECTION_HISTORY_STORE_KEY,
+};
+use crate::services::demographics_policy::{
+    demographics_policy_from_store, Demographics, DemographicsGate, DemographicsPolicy, GateOutcome,
+    DEMOGRAPHICS_POLICY_STORE_KEY,
+};
+use crate::services::disk_monitor::{
+    DiskMonitor, DiskMonitorEvent, DiskMonitorSettings, StoreReceipt, SystemFreeSpaceProvider,
+};
+use crate::services::duplicate_detection::{
+    self, duplicate_detection_from_store, DuplicateDetectionSettings, DUPLICATE_DETECTION_STORE_KEY,
+};
+use crate::services::firmware_tracking::{
+    astm_identity, conformance_mode_from_store, ANALYZER_IDENTITY_CHANGED_EVENT,
+    FIRMWARE_CHANGE_CONFORMANCE_MODE_STORE_KEY,
+};
+use crate::services::his_client::{HisApiConfig, HisApiPayload, HisClient, HisContact};
+use crate::services::forwarding_rules::{
+    evaluate_rules, forwarding_rules_from_store, ForwardCandidate, ForwardingRule, FORWARDING_RULES_STORE_KEY,
+};
+use crate::services::ingestion_lanes::{
+    next_arrival_sequence, ArrivalOrder, IngestionLanes, LaneHandler, LaneItem, SampleSequenceGuard,
+};
+use crate::services::id_normalization::{id_normalization_from_store, IdNormalization, ID_NORMALIZATION_STORE_KEY};
+use crate::services::maintenance::{
+    ChannelQueue, MaintenanceController, MaintenanceListener, MaintenancePhase, MaintenanceQueue, MaintenanceStatus,
+    SHUTDOWN_GRACE_SECONDS,
+};
+use crate::services::remote_address_guard::{remote_ip, RemoteAddressGuard, RemoteAddressStatus};
+use crate::services::result_images::{store_result_images, RESULT_IMAGES_DIR};
+use crate::services::sample_locks::SampleLocks;
+use crate::services::service_controller::ServiceController;
+use crate::services::shadow_mode::ShadowMode;
+use crate::services::stale_results::{stale_results_from_store, StaleResultSettings, STALE_RESULTS_STORE_KEY};
+use crate::services::upload_worker::{UploadRetryPolicy, UploadWorker, UploadWorkerConfig};
+use crate::services::verification_rules::{evaluate_panel, BulkApproval, VerificationGate, VerificationOutcome};
+
+/// Where [`AppState::route_results`] sent a sample's results
+#[derive(Debug)]
+pub(crate) enum RoutedResults {
+    /// Held for review before any rule ran, e.g. as stale; the hold says why
+    Held(VerificationHold),
+    /// Stamped by the enabled rules, in result order, and submitted through the gates
+    Submitted {
+        stamps: Vec<VerificationStamp>,
+        outcome: VerificationOutcome,
+    },
+}
 
 /// Central application state manager
 pub struct AppState<R: Runtime> {
-    autoquant_meril_service: Arc<AutoQuantMerilService<R>>,
-    bf6900_service: Arc<BF6900Service<R>>,
+    autoquant_meril_service: Arc<AutoQuantMerilService>,
+    bf6900_service: Arc<BF6900Service>,
     his_client: Arc<HisClient>,
-    meril_service_handle: Option<JoinHandle<Result<(), String>>>,
-    bf6900_service_handle: Option<JoinHandle<Result<(), String>>>,
+    repository: Arc<SqliteRepository>,
+    upload_worker: Arc<UploadWorker>,
+    demographics_gate: Arc<DemographicsGate>,
+    verification_gate: Arc<VerificationGate>,
+    disk_monitor: Arc<DiskMonitor>,
+    shadow_mode: ShadowMode,
+    remote_address_guard: Arc<RemoteAddressGuard>,
+    sample_locks: Arc<SampleLocks>,
+    meril_event_sender: mpsc::Sender<MerilEvent>,
+    bf6900_event_sender: mpsc::Sender<BF6900Event>,
+    maintenance: Arc<MaintenanceController>,
+    meril_controller: ServiceController<AutoQuantMerilService>,
+    bf6900_controller: ServiceController<BF6900Service>,
 }
 
 impl<R: Runtime> AppState<R> {
     /// Creates a new AppState instance
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         app_handle: AppHandle<R>,
-        meril_store: Arc<tauri_plugin_store::Store<R>>,
-        bf6900_store: Arc<tauri_plugin_store::Store<R>>,
+        meril_store: Arc<dyn ConfigPersistence>,
+        bf6900_store: Arc<dyn ConfigPersistence>,
+        repository: Arc<SqliteRepository>,
+        data_dir: PathBuf,
+        disk_monitor_settings: DiskMonitorSettings,
+        shadow_mode: ShadowMode,
+        his_config: HisApiConfig,
+        upload_retry: UploadRetryPolicy,
     ) -> Result<Self, String> {
         // Create event channel for AutoQuantMeril service
         let (event_sender, event_receiver) =
@@ -44,8 +117,8 @@ impl<R: Runtime> AppState<R> {
                         Self::create_default_meril_analyzer()
                     }
                 }
-                Err(_) => {
-                    // Invalid JSON, create default analyzer
+                Err(e) => {
+                    log::error!("Stored Meril configuration is invalid, using defaults: {}", e);
                     Self::create_default_meril_analyzer()
                 }
             }
@@ -54,21 +127,88 @@ impl<R: Runtime> AppState<R> {
             Self::create_default_meril_analyzer()
         };
 
-        // Create the AutoQuantMeril service
-        let service = Arc::new(AutoQuantMerilService::<R>::new(
-            analyzer,
-            event_sender,
-            meril_store,
+        // Create and start the disk space monitor for the app-data volume
+        let (disk_event_sender, disk_event_receiver) = mpsc::channel::<DiskMonitorEvent>(16);
+        let disk_monitor = Arc::new(DiskMonitor::new(
+            data_dir,
+            disk_monitor_settings,
+            Arc::new(SystemFreeSpaceProvider),
+            disk_event_sender,
         ));
+        tokio::spawn(disk_monitor.clone().run());
+
+        let app_handle_clone = app_handle.clone();
+        tokio::spawn(async move {
+            Self::handle_disk_events(app_handle_clone, disk_event_receiver).await;
+        });
+
+        // Create the AutoQuantMeril service
+        let meril_event_sender = event_sender.clone();
+        let service = Arc::new(
+            AutoQuantMerilService::new(analyzer, event_sender, meril_store, shadow_mode.clone())
+                .with_order_repository(repository.clone())
+                .with_protective_mode(disk_monitor.protective_mode()),
+        );
 
         // Create HIS client
-        let his_client = Arc::new(HisClient::with_default_config());
+        let his_client = Arc::new(HisClient::new(his_config));
+
+        // Create and start the persistent HIS upload worker
+        let upload_worker = Arc::new(UploadWorker::new(
+            repository.clone(),
+            his_client.clone(),
+            UploadWorkerConfig {
+                retry: upload_retry,
+                ..Default::default()
+            },
+            shadow_mode.clone(),
+        ));
+        tokio::spawn(upload_worker.clone().run());
+
+        // Holds results of patients missing mandatory demographics in front of the upload queue
+        let demographics_gate = Arc::new(DemographicsGate::new(repository.clone(), upload_worker.clone()));
+
+        // Holds results the auto-verification rules do not release, in front of the demographics gate
+        let verification_gate = Arc::new(VerificationGate::new(repository.clone(), demographics_gate.clone()));
+
+        // Report database degradation when the repository's circuit breaker opens
+        let app_handle_clone = app_handle.clone();
+        let breaker_state = repository.subscribe_breaker_state();
+        tokio::spawn(async move {
+            Self::handle_database_breaker(app_handle_clone, breaker_state).await;
+        });
+
+        // Suggest probable duplicate patients on the configured schedule
+        let app_handle_clone = app_handle.clone();
+        tokio::spawn(duplicate_detection::run_scheduled_scans(repository.clone(), move || {
+            Self::duplicate_detection(&app_handle_clone)
+        }));
+
+        // Tracks the remote addresses each analyzer connects from
+        let remote_address_guard = Arc::new(RemoteAddressGuard::new(repository.clone()));
+
+        // Serializes ingestion and result edits per sample
+        let sample_locks = Arc::new(SampleLocks::default());
+
+        // Processed results are ingested on lanes partitioned by sample id
+        let meril_ingestion = Arc::new(MerilIngestion {
+            app: app_handle.clone(),
+            his_client: his_client.clone(),
+            verification_gate: verification_gate.clone(),
+            repository: repository.clone(),
+            sequence_guard: SampleSequenceGuard::new(),
+            sample_locks: sample_locks.clone(),
+        });
+        let meril_lanes = Arc::new(IngestionLanes::new("Meril ingestion", INGESTION_LANE_COUNT, meril_ingestion));
 
         // Start event handler for frontend communication
         let app_handle_clone = app_handle.clone();
-        let his_client_clone = his_client.clone();
+        let lanes_clone = meril_lanes.clone();
+        let repository_clone = repository.clone();
+        let service_clone = service.clone();
+        let guard_clone = remote_address_guard.clone();
         tokio::spawn(async move {
-            Self::handle_meril_events(app_handle_clone, event_receiver, his_client_clone).await;
+            Self::handle_meril_events(app_handle_clone, event_receiver, lanes_clone, repository_clone, service_clone, guard_clone).await;
         });
 
         // Create event channel for BF-6900 service
@@ -91,8 +231,8 @@ impl<R: Runtime> AppState<R> {
                         Self::create_default_bf6900_analyzer()
                     }
                 }
-                Err(_) => {
-                    // Invalid JSON, create default analyzer
+                Err(e) => {
+                    log::error!("Stored BF-6900 configuration is invalid, using defaults: {}", e);
                     Self::create_default_bf6900_analyzer()
                 }
             }
@@ -102,118 +242,215 @@ impl<R: Runtime> AppState<R> {
         };
 
         // Create the BF-6900 service
-        let bf6900_service = Arc::new(BF6900Service::<R>::new(
-            bf6900_analyzer,
-            bf6900_event_sender,
-            bf6900_store,
-        ));
+        let bf6900_event_sender_clone = bf6900_event_sender.clone();
+        let bf6900_service = Arc::new(
+            BF6900Service::new(bf6900_analyzer, bf6900_event_sender, bf6900_store, shadow_mode.clone())
+                .with_protective_mode(disk_monitor.protective_mode()),
+        );
 
         // Start event handler for BF-6900 frontend communication
         let app_handle_clone = app_handle.clone();
         let his_client_clone = his_client.clone();
+        let gate_clone = verification_gate.clone();
         let bf6900_service_clone = bf6900_service.clone();
+        let repository_clone = repository.clone();
+        let guard_clone = remote_address_guard.clone();
+        let locks_clone = sample_locks.clone();
+        let disk_monitor_clone = disk_monitor.clone();
         tokio::spawn(async move {
-            Self::handle_bf6900_events(app_handle_clone, bf6900_event_receiver, his_client_clone, bf6900_service_clone).await;
+            Self::handle_bf6900_events(app_handle_clone, bf6900_event_receiver, his_client_clone, gate_clone, bf6900_service_clone, repository_clone, guard_clone, locks_clone, disk_monitor_clone).await;
         });
 
+        // Closed connections go to the database; only the most recent stay in memory
+        let history = Self::connection_history(&app_handle);
+        let (archive_sender, archive_receiver) = mpsc::unbounded_channel();
+        for conversation_log in [service.conversation_log(), bf6900_service.conversation_log()] {
+            conversation_log.set_recent_limit(history.recent_limit);
+            conversation_log.set_archive(archive_sender.clone());
+        }
+        let archive_monitor = disk_monitor.clone();
+        tokio::spawn(archive_connections(
+            repository.clone(),
+            move || archive_monitor.is_protective_mode(),
+            archive_receiver,
+        ));
+
+        // Quiesces both listeners and drains ingestion before uploads
+        let listeners: Vec<Arc<dyn MaintenanceListener>> =
+            vec![service.clone() as Arc<dyn MaintenanceListener>, bf6900_service.clone()];
+        let queues: Vec<Arc<dyn MaintenanceQueue>> = vec![
+            Arc::new(ChannelQueue::new("Meril events", meril_event_sender.clone())) as Arc<dyn MaintenanceQueue>,
+            meril_lanes,
+            Arc::new(ChannelQueue::new("BF-6900 events", bf6900_event_sender_clone.clone())),
+            upload_worker.clone(),
+        ];
+        let maintenance = Arc::new(MaintenanceController::new(listeners, queues));
+
         let app_state = Self {
+            meril_controller: ServiceController::new("Meril", service.clone()),
+            bf6900_controller: ServiceController::new("BF-6900", bf6900_service.clone()),
             autoquant_meril_service: service,
             bf6900_service,
             his_client,
-            meril_service_handle: None,
-            bf6900_service_handle: None,
+            repository,
+            upload_worker,
+            demographics_gate,
+            verification_gate,
+            disk_monitor,
+            shadow_mode,
+            remote_address_guard,
+            sample_locks,
+            meril_event_sender,
+            bf6900_event_sender: bf6900_event_sender_clone,
+            maintenance,
         };
 
         Ok(app_state)
     }
 
     /// Initializes the AppState (called after creation to handle async operations)
-    pub async fn initialize(&mut self) -> Result<(), String> {
-        // Auto-start Meril service if configured
+    pub async fn initialize(&self) -> Result<(), String> {
+        // Analyzers in the auto-start set; ones never configured use activate_on_start
+        let autostart = self.repository.get_autostart_settings().await.unwrap_or_else(|e| {
+            log::error!("Failed to load autostart settings, using activate_on_start: {}", e);
+            HashMap::new()
+        });
+
         let analyzer_config = self.autoquant_meril_service.get_analyzer_config().await;
-        if analyzer_config.activate_on_start {
-            log::info!("Auto-starting Meril service due to activate_on_start=true");
-            self.start_meril_service_internal().await?;
+        if let Err(e) = self.meril_controller.autostart(&analyzer_config, &autostart).await {
+            log::error!("Failed to auto-start Meril service: {}", e);
         }
 
-        // Auto-start BF-6900 service if configured
         let bf6900_config = self.bf6900_service.get_analyzer_config().await;
-        if bf6900_config.activate_on_start {
-            log::info!("Auto-starting BF-6900 service due to activate_on_start=true");
-            self.start_bf6900_service_internal().await?;
+        if let Err(e) = self.bf6900_controller.autostart(&bf6900_config, &autostart).await {
+            log::error!("Failed to auto-start BF-6900 service: {}", e);
         }
 
         Ok(())
     }
 
     /// Gets a reference to the AutoQuantMeril service
-    pub fn get_autoquant_meril_service(&self) -> &Arc<AutoQuantMerilService<R>> {
+    pub fn get_autoquant_meril_service(&self) -> &Arc<AutoQuantMerilService> {
         &self.autoquant_meril_service
     }
 
     /// Gets a reference to the BF-6900 service
-    pub fn get_bf6900_service(&self) -> &Arc<BF6900Service<R>> {
+    pub fn get_bf6900_service(&self) -> &Arc<BF6900Service> {
         &self.bf6900_service
     }
 
-    /// Starts the Meril service in a background thread
-    pub async fn start_meril_service_internal(&mut self) -> Result<(), String> {
-        // Check if service is already running
-        if self.meril_service_handle.is_some() {
-            return Err("Service is already running".to_string());
-        }
+    /// Gets the lifecycle controller of the Meril service
+    pub fn get_meril_controller(&self) -> &ServiceController<AutoQuantMerilService> {
+        &self.meril_controller
+    }
+
+    /// Gets the lifecycle controller of the BF-6900 service
+    pub fn get_bf6900_controller(&self) -> &ServiceController<BF6900Service> {
+        &self.bf6900_controller
+    }
 
-        // Clone the service for the background thread
-        let service = self.autoquant_meril_service.clone();
+    /// Gets a reference to the HIS client
+    pub fn get_his_client(&self) -> &Arc<HisClient> {
+        &self.his_client
+    }
 
-        // Spawn the service in a background thread
-        let handle = tokio::spawn(async move { service.start().await });
+    /// Gets a reference to the database repository
+    pub fn get_repository(&self) -> &Arc<SqliteRepository> {
+        &self.repository
+    }
 
-        self.meril_service_handle = Some(handle);
+    /// Gets the per-sample locks shared by ingestion and result edits
+    pub fn get_sample_locks(&self) -> &Arc<SampleLocks> {
+        &self.sample_locks
+    }
 
-        log::info!("Meril service started successfully");
-        Ok(())
+    /// Gets a reference to the HIS upload worker
+    pub fn get_upload_worker(&self) -> &Arc<UploadWorker> {
+        &self.upload_worker
+    }
+
+    /// Gets the gate holding results of patients missing mandatory demographics
+    pub fn get_demographics_gate(&self) -> &Arc<DemographicsGate> {
+        &self.demographics_gate
     }
 
-    /// Stops the Meril service and waits for thread completion
-    pub async fn stop_meril_service_internal(&mut self) -> Result<(), String> {
-        // Check if service is running
-        let handle = match &mut self.meril_service_handle {
-            Some(h) => h,
-            None => return Err("Service is not running".to_string()),
+    /// Gets the gate holding results the auto-verification rules do not release
+    pub fn get_verification_gate(&self) -> &Arc<VerificationGate> {
+        &self.verification_gate
+    }
+
+    /// Gets a reference to the disk space monitor
+    pub fn get_disk_monitor(&self) -> &Arc<DiskMonitor> {
+        &self.disk_monitor
+    }
+
+    /// Gets the global shadow (store-only) mode switch
+    pub fn get_shadow_mode(&self) -> &ShadowMode {
+        &self.shadow_mode
+    }
+
+    /// Gets the tracker of analyzer remote addresses
+    pub fn get_remote_address_guard(&self) -> &Arc<RemoteAddressGuard> {
+        &self.remote_address_guard
+    }
+
+    /// Gets the maintenance-mode controller
+    pub fn get_maintenance(&self) -> &Arc<MaintenanceController> {
+        &self.maintenance
+    }
+
+    /// Quiesces analyzers before the app exits, reusing the maintenance drain
+    pub async fn prepare_shutdown(&self) -> Result<MaintenanceStatus, String> {
+        let grace = std::time::Duration::from_secs(SHUTDOWN_GRACE_SECONDS);
+        let status = match self.maintenance.status().phase {
+            MaintenancePhase::Normal => self.maintenance.enter(grace).await,
+            _ => self.maintenance.quiesce().await,
         };
 
-        // Stop the service
-        let service = self.autoquant_meril_service.clone();
-        if let Err(e) = service.stop().await {
-            log::error!("Error stopping service: {}", e);
+        // Status changes still inside their coalescing interval
+        for (name, flushed) in [
+            ("Meril", self.autoquant_meril_service.flush_status()),
+            ("BF-6900", self.bf6900_service.flush_status()),
+        ] {
+            if let Err(e) = flushed {
+                log::error!("Failed to persist {} analyzer status on shutdown: {}", name, e);
+            }
         }
 
-        // Wait for thread completion
-        match handle.await {
-            Ok(Ok(())) => {
-                log::info!("Meril service stopped successfully");
-                self.meril_service_handle = None;
-                Ok(())
-            }
-            Ok(Err(e)) => {
-                log::error!("Service thread returned error: {}", e);
-                self.meril_service_handle = None;
-                Err(e)
-            }
-            Err(e) => {
-                log::error!("Failed to join service thread: {}", e);
-                self.meril_service_handle = None;
-                Err(format!("Thread join error: {}", e))
+        status
+    }
+
+    /// Approves an analyzer's remote address and replays the messages held for it.
+    /// Returns the number of replayed messages.
+    pub async fn approve_remote_address(&self, analyzer_id: &str, ip_address: &str) -> Result<usize, String> {
+        let held = self.remote_address_guard.approve(analyzer_id, ip_address).await?;
+        let count = held.len();
+
+        for message in held {
+            let replayed = match message.protocol.as_str() {
+                "ASTM" => match serde_json::from_str::<MerilEvent>(&message.payload) {
+                    Ok(mut event) => {
+                        // Released now, so it ranks after anything ingested while it was held
+                        if let MerilEvent::LabResultProcessed { arrival_sequence, .. } = &mut event {
+                            *arrival_sequence = next_arrival_sequence();
+                        }
+                        self.meril_event_sender.send(event).await.map_err(|e| e.to_string())
+                    }
+                    Err(e) => Err(e.to_string()),
+                },
+                "HL7" => match serde_json::from_str::<BF6900Event>(&message.payload) {
+                    Ok(event) => self.bf6900_event_sender.send(event).await.map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                },
+                other => Err(format!("unknown protocol {}", other)),
+            };
+            if let Err(e) = replayed {
+                log::error!("Failed to replay held message {}: {}", message.id, e);
             }
         }
-    }
 
-    /// Gets the service status
-    pub async fn get_service_status(&self) -> (bool, usize) {
-        let is_running = self.meril_service_handle.is_some();
-        let connections_count = self.autoquant_meril_service.get_connections_count().await;
-        (is_running, connections_count)
+        log::info!("Replayed {} held messages from {} for analyzer {}", count, ip_address, analyzer_id);
+        Ok(count)
     }
 
     /// Creates a default MERIL analyzer configuration
@@ -237,6 +474,31 @@ impl<R: Runtime> AppState<R> {
             protocol: crate::models::Protocol::Astm,
             status: crate::models::AnalyzerStatus::Inactive,
             activate_on_start: true, // Don't auto-start by default
+            dilution_mode: crate::models::DilutionMode::PostDilution,
+            strict_remote_address: false,
+            astm_sender_id: None,
+            astm_version: None,
+            stream_provisional_results: false,
+            bind_address: None,
+            dual_stack: false,
+            listen_backlog: 128,
+            max_connections_per_ip: None,
+            post_eot_delay_ms: 0,
+            astm_timeouts: crate::models::AstmTimeouts::default(),
+            log_sample_rate: 1,
+            accept_frames_without_enq: false,
+            host_initiated: false,
+            astm_frame_retries: 6,
+            checksum_policy: crate::models::ChecksumPolicy::default(),
+            clock_skew_seconds: 0,
+            result_acceptance: crate::models::ResultAcceptanceWindow::default(),
+            default_patient_class: None,
+            sex_codes: crate::models::SexCodeMap::default(),
+            delimited_layout: crate::models::DelimitedLayout::default(),
+            last_seen_identity: None,
+            identity_since: None,
+            conformance_report_until: None,
+            last_connected_at: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -246,9 +508,35 @@ impl<R: Runtime> AppState<R> {
     async fn handle_meril_events(
         app: AppHandle<R>,
         mut event_receiver: mpsc::Receiver<crate::services::autoquant_meril::MerilEvent>,
-        his_client: Arc<HisClient>,
+        ingestion_lanes: Arc<IngestionLanes<MerilEvent>>,
+        repository: Arc<SqliteRepository>,
+        meril_service: Arc<AutoQuantMerilService>,
+        remote_address_guard: Arc<RemoteAddressGuard>,
     ) {
         while let Some(event) = event_receiver.recv().await {
+            // Results from an address awaiting approval are archived, not ingested
+            if let MerilEvent::LabResultProcessed { analyzer_id, remote_addr, store_receipt, .. } = &event {
+                let strict = meril_service.get_analyzer_config().await.strict_remote_address;
+                if Self::hold_if_unapproved(&app, &remote_address_guard, analyzer_id, remote_addr, strict, "ASTM", &event, store_receipt)
+                    .await
+                {
+                    continue;
+                }
+            }
+
+            // Results of one sample are ingested on its lane, in arrival order
+            if let MerilEvent::LabResultProcessed { transmission_id, test_results, arrival_sequence, .. } = &event {
+                let sample_id = test_results
+                    .first()
+                    .map(|result| result.sample_id.clone())
+                    .unwrap_or_else(|| transmission_id.clone());
+                let arrival_sequence = *arrival_sequence;
+                if let Err(e) = ingestion_lanes.submit(&sample_id, arrival_sequence, event).await {
+                    log::error!("Failed to queue lab results for sample {}: {}", sample_id, e);
+                }
+                continue;
+            }
+
             match event {
                 crate::services::autoquant_meril::MerilEvent::AnalyzerConnected {
                     analyzer_id,
@@ -256,6 +544,12 @@ impl<R: Runtime> AppState<R> {
                     timestamp,
                 } => {
                     log::info!("Analyzer {} connected from {}", analyzer_id, remote_addr);
+                    if let Err(e) = meril_service.record_connected(timestamp).await {
+                        log::error!("Failed to persist the connection time of analyzer {}: {}", analyzer_id, e);
+                    }
+
+                    let strict = meril_service.get_analyzer_config().await.strict_remote_address;
+                    Self::check_remote_address(&app, &remote_address_guard, &analyzer_id, &remote_addr, strict).await;
 
                     // Emit event to frontend
                     let _ = app.emit(
@@ -269,15 +563,52 @@ impl<R: Runtime> AppState<R> {
                 }
                 crate::services::autoquant_meril::MerilEvent::AnalyzerDisconnected {
                     analyzer_id,
+                    termination,
                     timestamp,
                 } => {
-                    log::info!("Analyzer {} disconnected", analyzer_id);
+                    // Routine closes after a transmission are not worth a warning
+                    if termination.is_abnormal() {
+                        log::warn!("Analyzer {} disconnected ({})", analyzer_id, termination.as_db_str());
+                    } else {
+                        log::info!("Analyzer {} disconnected ({})", analyzer_id, termination.as_db_str());
+                    }
 
                     // Emit event to frontend
                     let _ = app.emit(
                         "meril:analyzer-disconnected",
                         serde_json::json!({
                             "analyzer_id": analyzer_id,
+                            "termination": termination,
+                            "timestamp": timestamp
+                        }),
+                    );
+                }
+                crate::services::autoquant_meril::MerilEvent::HeaderReceived {
+                    analyzer_id,
+                    header,
+                    timestamp,
+                } => {
+                    // Keep the declared sender/version against the analyzer
+                    if let Err(e) = meril_service.record_astm_header(&header).await {
+                        log::error!("Failed to store ASTM header for analyzer {}: {}", analyzer_id, e);
+                    }
+
+                    match meril_service
+                        .record_identity(astm_identity(&header), Self::firmware_change_conformance_mode(&app))
+                        .await
+                    {
+                        Ok(Some(change)) => Self::report_firmware_change(&app, &repository, &change).await,
+                        Ok(None) => {}
+                        Err(e) => log::error!("Failed to store identity of analyzer {}: {}", analyzer_id, e),
+                    }
+
+                    // Emit event to frontend
+                    let _ = app.emit(
+                        "meril:header-received",
+                        serde_json::json!({
+                            "analyzer_id": analyzer_id,
+                            "sender_id": header.sender_id,
+                            "version": header.version,
                             "timestamp": timestamp
                         }),
                     );
@@ -306,48 +637,166 @@ impl<R: Runtime> AppState<R> {
                         }),
                     );
                 }
-                crate::services::autoquant_meril::MerilEvent::LabResultProcessed {
+                crate::services::autoquant_meril::MerilEvent::TransmissionProgress {
                     analyzer_id,
-                    patient_id,
-                    patient_data,
-                    test_results,
+                    progress,
                     timestamp,
                 } => {
-                    log::info!(
-                        "Lab results processed for analyzer {}: {} tests",
-                        analyzer_id,
-                        test_results.len()
+                    // Emit event to frontend
+                    let _ = app.emit(
+                        "meril:transmission-progress",
+                        serde_json::json!({
+                            "analyzer_id": analyzer_id,
+                            "transmission_id": progress.transmission_id,
+                            "frames_received": progress.frames_received,
+                            "patients_seen": progress.patients_seen,
+                            "results_parsed": progress.results_parsed,
+                            "timestamp": timestamp
+                        }),
                     );
-
-                    // Send results to HIS system
-                    if !test_results.is_empty() {
-                        let his_client_clone = his_client.clone();
-                        let analyzer_id_clone = analyzer_id.clone();
-                        let patient_id_clone = patient_id.clone();
-                        let test_results_clone = test_results.clone();
-                        let timestamp_clone = timestamp;
-                        
-                        tokio::spawn(async move {
-                            if let Err(e) = his_client_clone.send_meril_results(
-                                &analyzer_id_clone,
-                                patient_id_clone.as_deref(),
-                                &test_results_clone,
-                            ).await {
-                                log::error!("Failed to send lab results to HIS system: {}", e);
-                            } else {
-                                log::info!("Successfully sent lab results to HIS system for analyzer {}", analyzer_id_clone);
-                            }
-                        });
+                }
+                crate::services::autoquant_meril::MerilEvent::ProvisionalResult {
+                    analyzer_id,
+                    transmission_id,
+                    result,
+                    timestamp,
+                } => {
+                    // Shown greyed-out until meril:lab-results for the same transmission replaces it
+                    let _ = app.emit(
+                        "meril:provisional-result",
+                        serde_json::json!({
+                            "analyzer_id": analyzer_id,
+                            "transmission_id": transmission_id,
+                            "result": result,
+                            "provisional": true,
+                            "timestamp": timestamp
+                        }),
+                    );
+                }
+                crate::services::autoquant_meril::MerilEvent::LabResultProcessed { .. } => {
+                    // Queued on the ingestion lanes above
+                }
+                crate::services::autoquant_meril::MerilEvent::ConformanceWarnings {
+                    analyzer_id,
+                    transmission_id,
+                    warnings,
+                    timestamp,
+                } => {
+                    // The same warnings are on the transmission's raw message timeline
+                    if let Err(e) = repository
+                        .save_conformance_warnings(&analyzer_id, "ASTM", Some(&transmission_id), &warnings)
+                        .await
+                    {
+                        log::error!("Failed to save conformance warnings: {}", e);
                     }
 
-                    // Emit event to frontend
                     let _ = app.emit(
-                        "meril:lab-results",
+                        "meril:conformance-warnings",
+                        serde_json::json!({
+                            "analyzer_id": analyzer_id,
+                            "transmission_id": transmission_id,
+                            "warnings": warnings,
+                            "timestamp": timestamp
+                        }),
+                    );
+                }
+                crate::services::autoquant_meril::MerilEvent::RejectedStaleResult {
+                    analyzer_id,
+                    transmission_id,
+                    result,
+                    reason,
+                    timestamp,
+                } => {
+                    // Already dropped from the transmission; shown so the analyzer clock gets fixed
+                    let _ = app.emit(
+                        "meril:rejected-stale-result",
+                        serde_json::json!({
+                            "analyzer_id": analyzer_id,
+                            "transmission_id": transmission_id,
+                            "result": result,
+                            "reason": reason,
+                            "timestamp": timestamp
+                        }),
+                    );
+                }
+                crate::services::autoquant_meril::MerilEvent::HostQueryReceived {
+                    analyzer_id,
+                    query,
+                    timestamp,
+                } => {
+                    // Worklist activity for the frontend
+                    let _ = app.emit(
+                        "meril:host-query",
+                        serde_json::json!({
+                            "analyzer_id": analyzer_id,
+                            "query": query,
+                            "timestamp": timestamp
+                        }),
+                    );
+                }
+                crate::services::autoquant_meril::MerilEvent::HostQueryAnswered {
+                    analyzer_id,
+                    query,
+                    orders,
+                    timestamp,
+                } => {
+                    let _ = app.emit(
+                        "meril:host-query-answered",
+                        serde_json::json!({
+                            "analyzer_id": analyzer_id,
+                            "query": query,
+                            "orders": orders,
+                            "timestamp": timestamp
+                        }),
+                    );
+                }
+                crate::services::autoquant_meril::MerilEvent::OrdersSent {
+                    analyzer_id,
+                    patient_id,
+                    order_ids,
+                    timestamp,
+                } => {
+                    let _ = app.emit(
+                        "meril:orders-sent",
                         serde_json::json!({
                             "analyzer_id": analyzer_id,
                             "patient_id": patient_id,
-                            "patient_data": patient_data,
-                            "test_results": test_results,
+                            "order_ids": order_ids,
+                            "timestamp": timestamp
+                        }),
+                    );
+                }
+                crate::services::autoquant_meril::MerilEvent::OrdersSendFailed {
+                    analyzer_id,
+                    patient_id,
+                    order_ids,
+                    error,
+                    timestamp,
+                } => {
+                    log::error!("Failed to send orders of patient {} to {}: {}", patient_id, analyzer_id, error);
+                    let _ = app.emit(
+                        "meril:orders-send-failed",
+                        serde_json::json!({
+                            "analyzer_id": analyzer_id,
+                            "patient_id": patient_id,
+                            "order_ids": order_ids,
+                            "error": error,
+                            "timestamp": timestamp
+                        }),
+                    );
+                }
+                crate::services::autoquant_meril::MerilEvent::ConnectionRejected {
+                    analyzer_id,
+                    remote_addr,
+                    reason,
+                    timestamp,
+                } => {
+                    let _ = app.emit(
+                        "meril:connection-rejected",
+                        serde_json::json!({
+                            "analyzer_id": analyzer_id,
+                            "remote_addr": remote_addr,
+                            "reason": reason,
                             "timestamp": timestamp
                         }),
                     );
@@ -376,6 +825,13 @@ impl<R: Runtime> AppState<R> {
                 } => {
                     log::error!("Error in analyzer {}: {}", analyzer_id, error);
 
+                    if let Err(e) = repository
+                        .record_analyzer_event(&analyzer_id, &AnalyzerEventType::classify(&error), &error)
+                        .await
+                    {
+                        log::error!("Failed to record analyzer event: {}", e);
+                    }
+
                     // Emit event to frontend
                     let _ = app.emit(
                         "meril:error",
@@ -390,66 +846,6 @@ impl<R: Runtime> AppState<R> {
         }
     }
 
-    /// Starts the BF-6900 service in a background thread
-    pub async fn start_bf6900_service_internal(&mut self) -> Result<(), String> {
-        // Check if service is already running
-        if self.bf6900_service_handle.is_some() {
-            return Err("BF-6900 service is already running".to_string());
-        }
-
-        // Clone the service for the background thread
-        let service = self.bf6900_service.clone();
-
-        // Spawn the service in a background thread
-        let handle = tokio::spawn(async move { service.start().await });
-
-        self.bf6900_service_handle = Some(handle);
-
-        log::info!("BF-6900 service started successfully");
-        Ok(())
-    }
-
-    /// Stops the BF-6900 service and waits for thread completion
-    pub async fn stop_bf6900_service_internal(&mut self) -> Result<(), String> {
-        // Check if service is running
-        let handle = match &mut self.bf6900_service_handle {
-            Some(h) => h,
-            None => return Err("BF-6900 service is not running".to_string()),
-        };
-
-        // Stop the service
-        let service = self.bf6900_service.clone();
-        if let Err(e) = service.stop().await {
-            log::error!("Error stopping BF-6900 service: {}", e);
-        }
-
-        // Wait for thread completion
-        match handle.await {
-            Ok(Ok(())) => {
-                log::info!("BF-6900 service stopped successfully");
-                self.bf6900_service_handle = None;
-                Ok(())
-            }
-            Ok(Err(e)) => {
-                log::error!("BF-6900 service thread returned error: {}", e);
-                self.bf6900_service_handle = None;
-                Err(e)
-            }
-            Err(e) => {
-                log::error!("Failed to join BF-6900 service thread: {}", e);
-                self.bf6900_service_handle = None;
-                Err(format!("Thread join error: {}", e))
-            }
-        }
-    }
-
-    /// Gets the BF-6900 service status
-    pub async fn get_bf6900_service_status(&self) -> (bool, usize) {
-        let is_running = self.bf6900_service_handle.is_some();
-        let connections_count = self.bf6900_service.get_connections_count().await;
-        (is_running, connections_count)
-    }
-
     /// Creates a default BF-6900 analyzer configuration
     pub fn create_default_bf6900_analyzer() -> Analyzer {
         use chrono::Utc;
@@ -471,19 +867,507 @@ impl<R: Runtime> AppState<R> {
             protocol: crate::models::Protocol::Hl7V231,
             status: crate::models::AnalyzerStatus::Inactive,
             activate_on_start: true, // Don't auto-start by default
+            dilution_mode: crate::models::DilutionMode::PostDilution,
+            strict_remote_address: false,
+            astm_sender_id: None,
+            astm_version: None,
+            stream_provisional_results: false,
+            bind_address: None,
+            dual_stack: false,
+            listen_backlog: 128,
+            max_connections_per_ip: None,
+            post_eot_delay_ms: 0,
+            astm_timeouts: crate::models::AstmTimeouts::default(),
+            log_sample_rate: 1,
+            accept_frames_without_enq: false,
+            host_initiated: false,
+            astm_frame_retries: 6,
+            checksum_policy: crate::models::ChecksumPolicy::default(),
+            clock_skew_seconds: 0,
+            result_acceptance: crate::models::ResultAcceptanceWindow::default(),
+            default_patient_class: None,
+            sex_codes: crate::models::SexCodeMap::default(),
+            delimited_layout: crate::models::DelimitedLayout::default(),
+            last_seen_identity: None,
+            identity_since: None,
+            conformance_report_until: None,
+            last_connected_at: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
     }
 
+    /// Reads the current patient/sample id normalization rules from the settings store
+    fn id_normalization(app: &AppHandle<R>) -> IdNormalization {
+        id_normalization_from_store(
+            app.store("settings.json")
+                .ok()
+                .and_then(|store| store.get(ID_NORMALIZATION_STORE_KEY)),
+        )
+    }
+
+    /// Reads the current connection history settings from the settings store
+    fn connection_history(app: &AppHandle<R>) -> ConnectionHistorySettings {
+        connection_history_from_store(
+            app.store("settings.json")
+                .ok()
+                .and_then(|store| store.get(CONNECTION_HISTORY_STORE_KEY)),
+        )
+    }
+
+    /// Reads the current duplicate patient detection heuristics from the settings store
+    fn duplicate_detection(app: &AppHandle<R>) -> DuplicateDetectionSettings {
+        duplicate_detection_from_store(
+            app.store("settings.json")
+                .ok()
+                .and_then(|store| store.get(DUPLICATE_DETECTION_STORE_KEY)),
+        )
+    }
+
+    /// Reads the current mandatory-demographics policy from the settings store
+    fn demographics_policy(app: &AppHandle<R>) -> DemographicsPolicy {
+        demographics_policy_from_store(
+            app.store("settings.json")
+                .ok()
+                .and_then(|store| store.get(DEMOGRAPHICS_POLICY_STORE_KEY)),
+        )
+    }
+
+    /// Emits `demographics:result-held` for held results and `demographics:results-released`
+    /// for earlier holds released by the same submission
+    fn report_demographics_gate(app: &AppHandle<R>, outcome: &GateOutcome, released: &[DemographicsHold]) {
+        if let GateOutcome::Held(hold) = outcome {
+            let _ = app.emit("demographics:result-held", hold);
+        }
+        if !released.is_empty() {
+            let _ = app.emit("demographics:results-released", released);
+        }
+    }
+
+    /// Releases a patient's held results once the stored record satisfies the
+    /// mandatory-demographics policy (e.g. after a manual edit)
+    pub async fn release_demographics_holds(&self, app: &AppHandle<R>, patient_id: &str) -> Result<Vec<DemographicsHold>, String> {
+        let released = self
+            .demographics_gate
+            .release_if_complete(&Self::demographics_policy(app), patient_id)
+            .await?;
+        if !released.is_empty() {
+            let _ = app.emit("demographics:results-released", &released);
+        }
+        Ok(released)
+    }
+
+    /// Releases one demographics hold by hand, without the policy being met.
+    /// Returns None if it was already released.
+    pub async fn release_demographics_hold(&self, app: &AppHandle<R>, hold_id: &str) -> Result<Option<DemographicsHold>, String> {
+        let released = self.demographics_gate.release_hold(hold_id).await?;
+        if let Some(hold) = &released {
+            let _ = app.emit("demographics:results-released", &vec![hold]);
+        }
+        Ok(released)
+    }
+
+    /// Applies the auto-verification rules to a sample's results and submits them
+    /// through the verification and demographics gates. Returns each result's
+    /// stamp, in result order (none while no rule is enabled), and the upload id
+    /// when the results were queued.
+    #[allow(clippy::too_many_arguments)]
+    async fn submit_results(
+        app: &AppHandle<R>,
+        verification_gate: &VerificationGate,
+        repository: &SqliteRepository,
+        analyzer_id: &str,
+        patient_id: Option<&str>,
+        demographics: Demographics,
+        payload: &HisApiPayload,
+        critical: bool,
+        results: &[TestResult],
+    ) -> Result<(Vec<VerificationStamp>, Option<String>), String> {
+        let routed = Self::route_results(
+            verification_gate,
+            repository,
+            &Self::stale_results(app),
+            &Self::demographics_policy(app),
+            analyzer_id,
+            patient_id,
+            demographics,
+            payload,
+            critical,
+            results,
+            chrono::Utc::now(),
+        )
+        .await?;
+        match routed {
+            RoutedResults::Held(hold) => {
+                log::warn!(
+                    "Holding results of sample {} for review: {}",
+                    hold.sample_id,
+                    hold.reason_detail.as_deref().unwrap_or_default()
+                );
+                let _ = app.emit("verification:result-held", &hold);
+                Ok((Vec::new(), None))
+            }
+            RoutedResults::Submitted { stamps, outcome } => {
+                let upload_id = match outcome {
+                    VerificationOutcome::Released(outcome, released) => {
+                        Self::report_demographics_gate(app, &outcome, &released);
+                        match outcome {
+                            GateOutcome::Queued { upload_id } => upload_id,
+                            GateOutcome::Held(_) => None,
+                        }
+                    }
+                    VerificationOutcome::Held(hold) => {
+                        let _ = app.emit("verification:result-held", &hold);
+                        None
+                    }
+                };
+                Ok((stamps, upload_id))
+            }
+        }
+    }
+
+    /// Routes a sample's results received at `now`: results replayed long after they
+    /// were run, or with a status the analyzer's protocol does not define, are held
+    /// whatever the rules say; the rest are stamped by the enabled rules and submitted
+    /// through the verification and demographics gates
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn route_results(
+        verification_gate: &VerificationGate,
+        repository: &SqliteRepository,
+        stale_results: &StaleResultSettings,
+        policy: &DemographicsPolicy,
+        analyzer_id: &str,
+        patient_id: Option<&str>,
+        demographics: Demographics,
+        payload: &HisApiPayload,
+        critical: bool,
+        results: &[TestResult],
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<RoutedResults, String> {
+        if let Some(reason) = stale_results.stale_reason(results, now) {
+            let hold = verification_gate
+                .hold(ReviewReason::Stale, Some(reason), analyzer_id, patient_id, payload, critical, Vec::new())
+                .await?;
+            return Ok(RoutedResults::Held(hold));
+        }
+
+        let unrecognized: Vec<String> = results
+            .iter()
+            .filter_map(|result| {
+                let code = result.metadata.unrecognized_status.as_ref()?;
+                Some(format!("{} ('{}')", result.test_id, code))
+            })
+            .collect();
+        if !unrecognized.is_empty() {
+            let reason = format!("Unknown result status: {}", unrecognized.join(", "));
+            let hold = verification_gate
+                .hold(ReviewReason::Verification, Some(reason), analyzer_id, patient_id, payload, critical, Vec::new())
+                .await?;
+            return Ok(RoutedResults::Held(hold));
+        }
+
+        let rules = repository.get_active_verification_rules().await?;
+        let stamps = if rules.iter().any(|rule| rule.enabled) {
+            let panel = verification_gate
+                .build_panel(analyzer_id, patient_id, demographics.clone(), results, now)
+                .await?;
+            evaluate_panel(&rules, &panel, now)
+        } else {
+            Vec::new()
+        };
+        let decisions = results
+            .iter()
+            .zip(&stamps)
+            .map(|(result, stamp)| (result.test_id.clone(), stamp.clone()))
+            .collect();
+
+        let outcome = verification_gate
+            .submit(policy, analyzer_id, patient_id, demographics, payload, critical, decisions)
+            .await?;
+        Ok(RoutedResults::Submitted { stamps, outcome })
+    }
+
+    /// Stores hematology results, with their parameter names and run panel, against
+    /// their patient, registering the patient on first sight. Results the samples got
+    /// before their patient was known are moved to them. Results without a patient id
+    /// are held against their sample until one is known. Callers hold the sample lock.
+    pub(crate) async fn store_hematology_results(
+        repository: &SqliteRepository,
+        patient_id: Option<&str>,
+        patient_data: Option<&HematologyPatientData>,
+        results: &[HematologyResult],
+    ) -> Result<(), String> {
+        let results: Vec<TestResult> = results.iter().cloned().map(TestResult::from).collect();
+        let Some(patient_id) = patient_id else {
+            let (held, unidentified): (Vec<TestResult>, Vec<TestResult>) =
+                results.into_iter().partition(|result| !result.sample_id.is_empty());
+            if !unidentified.is_empty() {
+                log::warn!("Not storing {} hematology results without a patient or sample id", unidentified.len());
+            }
+            let held = repository.hold_unassigned_results(&held, &DataSource::Analyzer).await?;
+            log::warn!("Holding {} hematology results until their patient is known", held);
+            return Ok(());
+        };
+
+        let patient = patient_data.cloned().unwrap_or_default().to_patient(patient_id, chrono::Utc::now());
+        repository.save_patient(&patient, &DataSource::Analyzer).await?;
+
+        let mut samples: Vec<&str> = results.iter().map(|result| result.sample_id.as_str()).collect();
+        samples.sort_unstable();
+        samples.dedup();
+        for sample_id in samples.into_iter().filter(|sample_id| !sample_id.is_empty()) {
+            let assigned = repository.assign_sample_results(sample_id, patient_id).await?;
+            if !assigned.is_empty() {
+                log::info!("Assigned {} held results of sample {} to patient {}", assigned.len(), sample_id, patient_id);
+            }
+        }
+        for result in &results {
+            repository.save_test_result(result, patient_id, &DataSource::Analyzer).await?;
+        }
+        Ok(())
+    }
+
+    /// Stores a sample's Meril results, with their comments and verification stamps,
+    /// against their patient. The patient is registered on first sight; a known patient
+    /// gets the address, telephone numbers and comments of the P record. Results the
+    /// sample got before its patient was known are moved to them. A correction
+    /// supersedes the test's current result, while results of a message that arrived
+    /// out of order are stored as superseded by it. Results without a patient id are
+    /// held against the sample until one is known. Callers hold the sample's lock.
+    pub(crate) async fn store_meril_results(
+        repository: &SqliteRepository,
+        sample_id: &str,
+        patient_id: Option<&str>,
+        patient_data: Option<&MerilPatientData>,
+        results: &[TestResult],
+        order: ArrivalOrder,
+    ) -> Result<(), String> {
+        let Some(patient_id) = patient_id else {
+            let held = repository.hold_unassigned_results(results, &DataSource::Analyzer).await?;
+            log::warn!("Holding {} results of sample {} until its patient is known", held, sample_id);
+            return Ok(());
+        };
+
+        let patient = patient_data.cloned().unwrap_or_default().to_patient(patient_id, chrono::Utc::now());
+        let registered = repository.save_patient(&patient, &DataSource::Analyzer).await?;
+        if !registered {
+            if !patient.comments.is_empty() {
+                repository.set_patient_comments(patient_id, &patient.comments).await?;
+            }
+            repository
+                .update_patient_contact_details(patient_id, patient.address.as_ref(), &patient.telephone, &patient.contacts)
+                .await?;
+        }
+
+        let assigned = repository.assign_sample_results(sample_id, patient_id).await?;
+        if !assigned.is_empty() {
+            log::info!("Assigned {} held results of sample {} to patient {}", assigned.len(), sample_id, patient_id);
+        }
+        for result in results {
+            // An analyzer correction replaces the result currently reported for its test;
+            // a late message slots in behind it
+            let current = if order != ArrivalOrder::InOrder || result.status == ResultStatus::Correction {
+                repository.get_current_result(&result.sample_id, &result.test_id).await?
+            } else {
+                None
+            };
+            match (current, order) {
+                (Some(current), ArrivalOrder::OutOfOrder { .. }) if current.id != result.id => {
+                    repository
+                        .save_superseded_test_result(result, &current.id, &DataSource::Analyzer)
+                        .await?;
+                }
+                (Some(current), ArrivalOrder::InOrder) if current.id != result.id => {
+                    repository.supersede_test_result(&current.id, result, &DataSource::Analyzer).await?
+                }
+                _ => {
+                    repository.save_test_result(result, patient_id, &DataSource::Analyzer).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Releases results a technologist verified; they still pass the demographics gate
+    pub async fn release_verification_hold(&self, app: &AppHandle<R>, hold_id: &str) -> Result<VerificationHold, String> {
+        let (hold, outcome, released) = self
+            .verification_gate
+            .release(&Self::demographics_policy(app), hold_id)
+            .await?;
+        Self::report_demographics_gate(app, &outcome, &released);
+        let _ = app.emit("verification:results-released", &hold);
+        Ok(hold)
+    }
+
+    /// Releases reviewed holds in bulk, e.g. the stale-result queue; they still pass the demographics gate
+    pub async fn approve_results(&self, app: &AppHandle<R>, hold_ids: &[String]) -> BulkApproval {
+        let approval = self
+            .verification_gate
+            .approve(&Self::demographics_policy(app), hold_ids)
+            .await;
+        for (hold, outcome, released) in &approval.released {
+            Self::report_demographics_gate(app, outcome, released);
+            let _ = app.emit("verification:results-released", hold);
+        }
+        approval
+    }
+
+    /// Reads the stale-result threshold from the settings store
+    fn stale_results(app: &AppHandle<R>) -> StaleResultSettings {
+        stale_results_from_store(
+            app.store("settings.json")
+                .ok()
+                .and_then(|store| store.get(STALE_RESULTS_STORE_KEY)),
+        )
+    }
+
+    /// Reads whether a firmware change puts the analyzer in conformance-report mode
+    fn firmware_change_conformance_mode(app: &AppHandle<R>) -> bool {
+        conformance_mode_from_store(
+            app.store("settings.json")
+                .ok()
+                .and_then(|store| store.get(FIRMWARE_CHANGE_CONFORMANCE_MODE_STORE_KEY)),
+        )
+    }
+
+    /// Records a changed analyzer identity and emits `lis:analyzer-identity-changed`
+    async fn report_firmware_change(app: &AppHandle<R>, repository: &SqliteRepository, change: &FirmwareChange) {
+        if let Err(e) = repository.save_firmware_change(change).await {
+            log::error!("Failed to record firmware change of analyzer {}: {}", change.analyzer_id, e);
+        }
+        let _ = app.emit(ANALYZER_IDENTITY_CHANGED_EVENT, change);
+    }
+
+    /// Reads the current result forwarding rules from the settings store
+    fn forwarding_rules(app: &AppHandle<R>) -> Vec<ForwardingRule> {
+        forwarding_rules_from_store(
+            app.store("settings.json")
+                .ok()
+                .and_then(|store| store.get(FORWARDING_RULES_STORE_KEY)),
+        )
+    }
+
+    /// Evaluates the forwarding rules against processed results and emits a
+    /// `forwarding:triggered` event for every rule that fires
+    fn forward_results(app: &AppHandle<R>, candidates: Vec<ForwardCandidate>) {
+        let rules = Self::forwarding_rules(app);
+        if rules.is_empty() {
+            return;
+        }
+
+        for candidate in &candidates {
+            for forward in evaluate_rules(&rules, candidate) {
+                log::info!(
+                    "Forwarding rule '{}' triggered for sample {} test {}",
+                    forward.rule_id,
+                    candidate.sample_id,
+                    candidate.test_id
+                );
+                let _ = app.emit("forwarding:triggered", &forward);
+            }
+        }
+    }
+
+    /// Classifies a connection's remote address and emits a `lis:new-remote-address`
+    /// warning the first time an address is seen
+    async fn check_remote_address(
+        app: &AppHandle<R>,
+        guard: &RemoteAddressGuard,
+        analyzer_id: &str,
+        remote_addr: &str,
+        strict: bool,
+    ) -> RemoteAddressStatus {
+        let ip_address = remote_ip(remote_addr);
+        let status = match guard.check(analyzer_id, &ip_address, strict).await {
+            Ok(status) => status,
+            Err(e) => {
+                log::error!("Failed to check remote address {}: {}", ip_address, e);
+                return RemoteAddressStatus::Known;
+            }
+        };
+
+        if let RemoteAddressStatus::New { held } = status {
+            let _ = app.emit(
+                "lis:new-remote-address",
+                serde_json::json!({
+                    "analyzer_id": analyzer_id,
+                    "ip_address": ip_address,
+                    "held": held,
+                    "timestamp": chrono::Utc::now()
+                }),
+            );
+        }
+
+        status
+    }
+
+    /// Archives a processed event when its remote address awaits approval, reporting
+    /// the archiving to the event's store receipt.
+    /// Returns true if the event was held and must not be ingested.
+    #[allow(clippy::too_many_arguments)]
+    async fn hold_if_unapproved<E: serde::Serialize>(
+        app: &AppHandle<R>,
+        guard: &RemoteAddressGuard,
+        analyzer_id: &str,
+        remote_addr: &str,
+        strict: bool,
+        protocol: &str,
+        event: &E,
+        store_receipt: &StoreReceipt,
+    ) -> bool {
+        if !Self::check_remote_address(app, guard, analyzer_id, remote_addr, strict)
+            .await
+            .holds_ingestion()
+        {
+            return false;
+        }
+
+        let ip_address = remote_ip(remote_addr);
+        let payload = match serde_json::to_string(event) {
+            Ok(payload) => payload,
+            Err(e) => {
+                log::error!("Failed to serialize held message from {}: {}", ip_address, e);
+                store_receipt.report(Err(format!("Failed to serialize held message: {}", e)));
+                return true;
+            }
+        };
+        let held = guard.hold(analyzer_id, &ip_address, protocol, payload).await;
+        match &held {
+            Ok(()) => log::warn!(
+                "Held message from unapproved address {} for analyzer {}",
+                ip_address,
+                analyzer_id
+            ),
+            Err(e) => log::error!("Failed to hold message from {}: {}", ip_address, e),
+        }
+        store_receipt.report(held);
+        true
+    }
+
     /// Handles BF-6900 events and sends them to the frontend
+    #[allow(clippy::too_many_arguments)]
     async fn handle_bf6900_events(
         app: AppHandle<R>,
         mut event_receiver: mpsc::Receiver<crate::models::hematology::BF6900Event>,
         his_client: Arc<HisClient>,
-        bf6900_service: Arc<BF6900Service<R>>,
+        verification_gate: Arc<VerificationGate>,
+        bf6900_service: Arc<BF6900Service>,
+        repository: Arc<SqliteRepository>,
+        remote_address_guard: Arc<RemoteAddressGuard>,
+        sample_locks: Arc<SampleLocks>,
+        disk_monitor: Arc<DiskMonitor>,
     ) {
         while let Some(event) = event_receiver.recv().await {
+            // Results from an address awaiting approval are archived, not ingested
+            if let BF6900Event::HematologyResultProcessed { analyzer_id, remote_addr, store_receipt, .. } = &event {
+                let strict = bf6900_service.get_analyzer_config().await.strict_remote_address;
+                if Self::hold_if_unapproved(&app, &remote_address_guard, analyzer_id, remote_addr, strict, "HL7", &event, store_receipt)
+                    .await
+                {
+                    continue;
+                }
+            }
+
             match event {
                 BF6900Event::AnalyzerConnected {
                     analyzer_id,
@@ -491,6 +1375,12 @@ impl<R: Runtime> AppState<R> {
                     timestamp,
                 } => {
                     log::info!("BF-6900 Analyzer {} connected from {}", analyzer_id, remote_addr);
+                    if let Err(e) = bf6900_service.record_connected(timestamp).await {
+                        log::error!("Failed to persist the connection time of analyzer {}: {}", analyzer_id, e);
+                    }
+
+                    let strict = bf6900_service.get_analyzer_config().await.strict_remote_address;
+                    Self::check_remote_address(&app, &remote_address_guard, &analyzer_id, &remote_addr, strict).await;
 
                     // Emit event to frontend
                     let _ = app.emit(
@@ -504,15 +1394,38 @@ impl<R: Runtime> AppState<R> {
                 }
                 BF6900Event::AnalyzerDisconnected {
                     analyzer_id,
+                    termination,
                     timestamp,
                 } => {
-                    log::info!("BF-6900 Analyzer {} disconnected", analyzer_id);
+                    // Routine closes after a transmission are not worth a warning
+                    if termination.is_abnormal() {
+                        log::warn!("BF-6900 Analyzer {} disconnected ({})", analyzer_id, termination.as_db_str());
+                    } else {
+                        log::info!("BF-6900 Analyzer {} disconnected ({})", analyzer_id, termination.as_db_str());
+                    }
 
                     // Emit event to frontend
                     let _ = app.emit(
                         "bf6900:analyzer-disconnected",
                         serde_json::json!({
                             "analyzer_id": analyzer_id,
+                            "termination": termination,
+                            "timestamp": timestamp
+                        }),
+                    );
+                }
+                BF6900Event::ConnectionRejected {
+                    analyzer_id,
+                    remote_addr,
+                    reason,
+                    timestamp,
+                } => {
+                    let _ = app.emit(
+                        "bf6900:connection-rejected",
+                        serde_json::json!({
+                            "analyzer_id": analyzer_id,
+                            "remote_addr": remote_addr,
+                            "reason": reason,
                             "timestamp": timestamp
                         }),
                     );
@@ -543,39 +1456,126 @@ impl<R: Runtime> AppState<R> {
                 }
                 BF6900Event::HematologyResultProcessed {
                     analyzer_id,
+                    remote_addr: _,
                     patient_id,
                     patient_data,
-                    test_results,
+                    patient_class,
+                    mut test_results,
+                    run,
+                    review_detail,
+                    store_receipt,
                     timestamp,
                 } => {
                     log::info!(
-                        "BF-6900 hematology results processed for analyzer {}: {} tests",
+                        "BF-6900 hematology results processed for analyzer {}: {} tests ({:?} panel)",
                         analyzer_id,
-                        test_results.len()
+                        test_results.len(),
+                        run.panel
                     );
 
-                    // Send results to HIS system
+                    // Bring the patient id to its canonical form before matching/storage
+                    let patient_id = Self::id_normalization(&app).normalize_opt(patient_id.as_deref());
+
+                    // Queue results for the HIS system; the upload worker sends them
                     if !test_results.is_empty() {
-                        let his_client_clone = his_client.clone();
-                        let analyzer_id_clone = analyzer_id.clone();
-                        let patient_id_clone = patient_id.clone();
-                        let test_results_clone = test_results.clone();
-                        let timestamp_clone = timestamp;
-                        
-                        tokio::spawn(async move {
-                            if let Err(e) = his_client_clone.send_hematology_results(
-                                &analyzer_id_clone,
-                                patient_id_clone.as_deref(),
-                                &test_results_clone,
-                                timestamp_clone,
-                            ).await {
-                                log::error!("Failed to send hematology results to HIS system: {}", e);
-                            } else {
-                                log::info!("Successfully sent hematology results to HIS system for analyzer {}", analyzer_id_clone);
-                            }
+                        let mut payload = his_client.build_hematology_payload(
+                            &analyzer_id,
+                            patient_id.as_deref(),
+                            &test_results,
+                            timestamp,
+                        );
+                        payload.contact = patient_data.as_ref().and_then(|patient| {
+                            HisContact::from_parts(&patient.contacts, patient.structured_address.as_ref())
                         });
+                        payload.patient_class = patient_class;
+                        let _sample_lock = sample_locks.lock(&payload.sample_no).await;
+                        let demographics = patient_data
+                            .as_ref()
+                            .map(|patient| {
+                                Demographics::from_message(&patient.name, patient.birth_date.as_deref(), patient.sex.as_deref())
+                            })
+                            .unwrap_or_default();
+                        let critical = test_results
+                            .iter()
+                            .flat_map(|result| result.flags.iter())
+                            .any(|flag| UploadPriority::is_critical_flag(flag));
+                        let results: Vec<TestResult> = test_results.iter().cloned().map(TestResult::from).collect();
+                        let submitted = match review_detail {
+                            None => {
+                                Self::submit_results(
+                                    &app,
+                                    &verification_gate,
+                                    &repository,
+                                    &analyzer_id,
+                                    patient_id.as_deref(),
+                                    demographics,
+                                    &payload,
+                                    critical,
+                                    &results,
+                                )
+                                .await
+                            }
+                            // Conformance-report mode: nothing in a deviating message uploads unreviewed
+                            Some(detail) => verification_gate
+                                .hold(ReviewReason::Verification, Some(detail), &analyzer_id, patient_id.as_deref(), &payload, critical, Vec::new())
+                                .await
+                                .map(|hold| {
+                                    let _ = app.emit("verification:result-held", &hold);
+                                    (Vec::new(), None)
+                                }),
+                        };
+                        match submitted {
+                            Ok((stamps, _)) => {
+                                for (result, stamp) in test_results.iter_mut().zip(stamps) {
+                                    result.verification = Some(stamp);
+                                }
+                            }
+                            Err(e) => log::error!("Failed to queue hematology results for HIS system: {}", e),
+                        }
+
+                        let stored = Self::store_hematology_results(
+                            &repository,
+                            patient_id.as_deref(),
+                            patient_data.as_ref(),
+                            &test_results,
+                        )
+                        .await;
+                        if let Err(e) = &stored {
+                            log::error!("Failed to store hematology results: {}", e);
+                        }
+                        // In protective mode the analyzer's accept ACK waits for this
+                        store_receipt.report(stored);
+
+                        // Histograms and scattergrams are kept as files for the result detail and reports
+                        match disk_monitor
+                            .check_non_essential_write("storing graph images")
+                            .and_then(|_| {
+                                app.path()
+                                    .app_data_dir()
+                                    .map_err(|e| format!("Error resolving app data dir: {}", e))
+                            }) {
+                            Ok(dir) => {
+                                if let Err(e) =
+                                    store_result_images(&repository, &dir.join(RESULT_IMAGES_DIR), &results).await
+                                {
+                                    log::error!("Failed to store hematology images: {}", e);
+                                }
+                            }
+                            Err(e) => log::warn!("Not storing hematology images: {}", e),
+                        }
+                    } else {
+                        // Nothing to store
+                        store_receipt.report(Ok(()));
                     }
 
+                    Self::forward_results(
+                        &app,
+                        test_results
+                            .iter()
+                            .map(|result| ForwardCandidate::from_hematology_result(&analyzer_id, result))
+                            .collect(),
+                    );
+
                     // Emit event to frontend
                     let _ = app.emit(
                         "bf6900:lab-results",
@@ -584,10 +1584,44 @@ impl<R: Runtime> AppState<R> {
                             "patient_id": patient_id,
                             "patient_data": patient_data,
                             "test_results": test_results,
+                            "run": run,
                             "timestamp": timestamp
                         }),
                     );
                 }
+                BF6900Event::ConformanceWarnings {
+                    analyzer_id,
+                    message_control_id,
+                    warnings,
+                    timestamp,
+                } => {
+                    if let Err(e) = repository
+                        .save_conformance_warnings(&analyzer_id, "HL7", Some(&message_control_id), &warnings)
+                        .await
+                    {
+                        log::error!("Failed to save conformance warnings: {}", e);
+                    }
+
+                    let _ = app.emit(
+                        "bf6900:conformance-warnings",
+                        serde_json::json!({
+                            "analyzer_id": analyzer_id,
+                            "message_control_id": message_control_id,
+                            "warnings": warnings,
+                            "timestamp": timestamp
+                        }),
+                    );
+                }
+                BF6900Event::IdentityDeclared { analyzer_id, identity, .. } => {
+                    match bf6900_service
+                        .record_identity(identity, Self::firmware_change_conformance_mode(&app))
+                        .await
+                    {
+                        Ok(Some(change)) => Self::report_firmware_change(&app, &repository, &change).await,
+                        Ok(None) => {}
+                        Err(e) => log::error!("Failed to store identity of analyzer {}: {}", analyzer_id, e),
+                    }
+                }
                 BF6900Event::AnalyzerStatusUpdated {
                     analyzer_id,
                     status,
@@ -655,6 +1689,41 @@ impl<R: Runtime> AppState<R> {
                         }),
                     );
                 }
+                BF6900Event::CorruptImage {
+                    analyzer_id,
+                    sample_id,
+                    parameter_code,
+                    reason,
+                    timestamp,
+                } => {
+                    log::warn!(
+                        "Corrupt image {} for sample {} from BF-6900 analyzer {}: {}",
+                        parameter_code,
+                        sample_id,
+                        analyzer_id,
+                        reason
+                    );
+
+                    let message = format!("{} for sample {}: {}", parameter_code, sample_id, reason);
+                    if let Err(e) = repository
+                        .record_analyzer_event(&analyzer_id, &AnalyzerEventType::CorruptImage, &message)
+                        .await
+                    {
+                        log::error!("Failed to record analyzer event: {}", e);
+                    }
+
+                    // Emit event to frontend
+                    let _ = app.emit(
+                        "bf6900:corrupt-image",
+                        serde_json::json!({
+                            "analyzer_id": analyzer_id,
+                            "sample_id": sample_id,
+                            "parameter_code": parameter_code,
+                            "reason": reason,
+                            "timestamp": timestamp
+                        }),
+                    );
+                }
                 BF6900Event::Error {
                     analyzer_id,
                     error,
@@ -662,6 +1731,13 @@ impl<R: Runtime> AppState<R> {
                 } => {
                     log::error!("Error in BF-6900 analyzer {}: {}", analyzer_id, error);
 
+                    if let Err(e) = repository
+                        .record_analyzer_event(&analyzer_id, &AnalyzerEventType::classify(&error), &error)
+                        .await
+                    {
+                        log::error!("Failed to record analyzer event: {}", e);
+                    }
+
                     // Emit event to frontend
                     let _ = app.emit(
                         "bf6900:error",
@@ -675,4 +1751,307 @@ impl<R: Runtime> AppState<R> {
             }
         }
     }
+
+    /// Handles disk monitor events, applying protective mode and notifying the frontend
+    async fn handle_disk_events(
+        app: AppHandle<R>,
+        mut event_receiver: mpsc::Receiver<DiskMonitorEvent>,
+    ) {
+        // Restore this level when leaving protective mode
+        let normal_log_level = log::max_level();
+
+        while let Some(event) = event_receiver.recv().await {
+            match event {
+                DiskMonitorEvent::LowDiskSpace {
+                    free_bytes,
+                    timestamp,
+                } => {
+                    // Leaving critical for warning also ends protective mode
+                    log::set_max_level(normal_log_level);
+
+                    let _ = app.emit(
+                        "disk:low-space",
+                        serde_json::json!({
+                            "free_bytes": free_bytes,
+                            "timestamp": timestamp
+                        }),
+                    );
+                }
+                DiskMonitorEvent::ProtectiveModeEntered {
+                    free_bytes,
+                    timestamp,
+                } => {
+                    // Stop debug/trace capture until space is available again
+                    if normal_log_level > log::LevelFilter::Info {
+                        log::set_max_level(log::LevelFilter::Info);
+                    }
+
+                    let _ = app.emit(
+                        "disk:protective-mode",
+                        serde_json::json!({
+                            "free_bytes": free_bytes,
+                            "timestamp": timestamp
+                        }),
+                    );
+                }
+                DiskMonitorEvent::DiskSpaceRecovered {
+                    free_bytes,
+                    timestamp,
+                } => {
+                    log::set_max_level(normal_log_level);
+
+                    let _ = app.emit(
+                        "disk:space-recovered",
+                        serde_json::json!({
+                            "free_bytes": free_bytes,
+                            "timestamp": timestamp
+                        }),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Notifies the frontend while database writes are failing fast
+    async fn handle_database_breaker(app: AppHandle<R>, mut breaker_state: watch::Receiver<BreakerState>) {
+        while breaker_state.changed().await.is_ok() {
+            let state = *breaker_state.borrow_and_update();
+            let event = match state {
+                BreakerState::Open => "database:degraded",
+                BreakerState::Closed => "database:recovered",
+                // The trial operation decides; nothing to report yet
+                BreakerState::HalfOpen => continue,
+            };
+            let _ = app.emit(
+                event,
+                serde_json::json!({
+                    "state": state,
+                    "timestamp": chrono::Utc::now()
+                }),
+            );
+        }
+    }
+}
+
+// ============================================================================
+// RESULT INGESTION
+// ============================================================================
+
+/// Ingestion lanes for processed Meril results
+const INGESTION_LANE_COUNT: usize = 4;
+
+/// Archives, uploads (through the verification and demographics gates), stores and forwards processed Meril results; runs on the ingestion lanes
+struct MerilIngestion<R: Runtime> {
+    app: AppHandle<R>,
+    his_client: Arc<HisClient>,
+    verification_gate: Arc<VerificationGate>,
+    repository: Arc<SqliteRepository>,
+    sequence_guard: SampleSequenceGuard,
+    sample_locks: Arc<SampleLocks>,
+}
+
+#[async_trait]
+impl<R: Runtime> LaneHandler<MerilEvent> for MerilIngestion<R> {
+    async fn handle(&self, item: LaneItem<MerilEvent>) {
+        let MerilEvent::LabResultProcessed {
+            analyzer_id,
+            transmission_id,
+            patient_id,
+            patient_data,
+            patient_class,
+            mut test_results,
+            raw_data,
+            mut timeline,
+            review_detail,
+            store_receipt,
+            timestamp,
+            ..
+        } = item.item
+        else {
+            return;
+        };
+
+        log::info!(
+            "Lab results processed for analyzer {}: {} tests",
+            analyzer_id,
+            test_results.len()
+        );
+
+        // Bring the patient id to its canonical form before matching/storage
+        let patient_id = AppState::<R>::id_normalization(&self.app).normalize_opt(patient_id.as_deref());
+
+        // Edits of the sample wait until its batch is persisted
+        let _sample_lock = self.sample_locks.lock(&item.sample_id).await;
+
+        // Keep the raw message and its processing timeline for support
+        let received_at = timeline.stage_at(ProcessingStage::Received).unwrap_or(timestamp);
+        let raw_message = RawMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            analyzer_id: analyzer_id.clone(),
+            protocol: "ASTM".to_string(),
+            message_type: "Result".to_string(),
+            raw_data,
+            timeline: timeline.clone(),
+            upload_id: None,
+            received_at,
+            created_at: timestamp,
+            updated_at: timestamp,
+        };
+        let raw_message_id = match self.repository.save_raw_message(&raw_message).await {
+            Ok(()) => {
+                timeline.mark(ProcessingStage::Persisted);
+                Some(raw_message.id.clone())
+            }
+            Err(e) => {
+                log::error!("Failed to store raw ASTM message: {}", e);
+                None
+            }
+        };
+
+        // An older message for the sample never supersedes one already applied; it is
+        // stored as superseded and held for review. The analyzer may have reconnected or
+        // the app restarted since the sample was last seen, so the latest sequence applied
+        // comes from storage when it is not remembered.
+        if !self.sequence_guard.is_tracked(&analyzer_id, &item.sample_id) {
+            match self.repository.get_sample_arrival(&analyzer_id, &item.sample_id).await {
+                Ok(Some(latest)) => self.sequence_guard.seed(&analyzer_id, &item.sample_id, latest),
+                Ok(None) => {}
+                Err(e) => log::error!("{}", e),
+            }
+        }
+        let order = self.sequence_guard.admit(&analyzer_id, &item.sample_id, item.arrival_sequence);
+        if order == ArrivalOrder::InOrder {
+            if let Err(e) = self
+                .repository
+                .record_sample_arrival(&analyzer_id, &item.sample_id, item.arrival_sequence)
+                .await
+            {
+                log::error!("{}", e);
+            }
+        }
+
+        // Queue results for the HIS system; the upload worker sends them
+        let mut upload_id = None;
+        if !test_results.is_empty() {
+            let mut payload = self.his_client.build_meril_payload(
+                &analyzer_id,
+                patient_id.as_deref(),
+                &test_results,
+            );
+            payload.contact = patient_data.as_ref().and_then(|patient| {
+                HisContact::from_parts(&patient.contacts, patient.structured_address.as_ref())
+            });
+            payload.patient_class = patient_class;
+            let demographics = patient_data
+                .as_ref()
+                .map(|patient| {
+                    Demographics::from_message(&patient.name, patient.birth_date.as_deref(), patient.sex.as_deref())
+                })
+                .unwrap_or_default();
+            let critical = test_results
+                .iter()
+                .flat_map(|result| result.flag_list())
+                .any(|flag| UploadPriority::is_critical_flag(&flag));
+            let submitted = match (order, review_detail) {
+                (ArrivalOrder::InOrder, None) => {
+                    AppState::<R>::submit_results(
+                        &self.app,
+                        &self.verification_gate,
+                        &self.repository,
+                        &analyzer_id,
+                        patient_id.as_deref(),
+                        demographics,
+                        &payload,
+                        critical,
+                        &test_results,
+                    )
+                    .await
+                }
+                // A malformed message is kept, but nothing in it uploads unreviewed
+                (ArrivalOrder::InOrder, Some(detail)) => self
+                    .verification_gate
+                    .hold(ReviewReason::Verification, Some(detail), &analyzer_id, patient_id.as_deref(), &payload, critical, Vec::new())
+                    .await
+                    .map(|hold| {
+                        let _ = self.app.emit("verification:result-held", &hold);
+                        (Vec::new(), None)
+                    }),
+                (ArrivalOrder::OutOfOrder { latest }, _) => {
+                    let detail = format!(
+                        "Message {} reached ingestion after message {} for the same sample",
+                        item.arrival_sequence, latest
+                    );
+                    self.verification_gate
+                        .hold(ReviewReason::OutOfOrder, Some(detail), &analyzer_id, patient_id.as_deref(), &payload, critical, Vec::new())
+                        .await
+                        .map(|hold| {
+                            let _ = self.app.emit("verification:result-held", &hold);
+                            (Vec::new(), None)
+                        })
+                }
+            };
+            match submitted {
+                Ok((stamps, id)) => {
+                    upload_id = id;
+                    for (result, stamp) in test_results.iter_mut().zip(stamps) {
+                        result.metadata.verification = Some(stamp);
+                    }
+                }
+                Err(e) => log::error!("Failed to queue lab results for HIS system: {}", e),
+            }
+
+            // Stored with their verification stamps while the sample is still locked
+            let stored = AppState::<R>::store_meril_results(
+                &self.repository,
+                &item.sample_id,
+                patient_id.as_deref(),
+                patient_data.as_ref(),
+                &test_results,
+                order,
+            )
+            .await;
+            if let Err(e) = &stored {
+                log::error!("Failed to store lab results of sample {}: {}", item.sample_id, e);
+            }
+            // In protective mode the analyzer's EOT ACK waits for this
+            store_receipt.report(stored);
+        } else {
+            // Nothing to store
+            store_receipt.report(Ok(()));
+        }
+
+        if let Some(message_id) = raw_message_id.as_deref() {
+            if let Err(e) = self
+                .repository
+                .update_raw_message_timeline(message_id, &timeline, upload_id.as_deref())
+                .await
+            {
+                log::error!("Failed to update processing timeline: {}", e);
+            }
+        }
+
+        AppState::<R>::forward_results(
+            &self.app,
+            test_results
+                .iter()
+                .map(|result| ForwardCandidate::from_meril_result(&analyzer_id, result))
+                .collect(),
+        );
+
+        // Emit event to frontend
+        let _ = self.app.emit(
+            "meril:lab-results",
+            serde_json::json!({
+                "analyzer_id": analyzer_id,
+                "transmission_id": transmission_id,
+                "provisional": false,
+                "out_of_order": order != ArrivalOrder::InOrder,
+                "raw_message_id": raw_message_id,
+                "patient_id": patient_id,
+                "patient_data": patient_data,
+                "test_results": test_results,
+                "timestamp": timestamp
+            }),
+        );
+    }
 }
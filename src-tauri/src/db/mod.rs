@@ -0,0 +1,111 @@
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+
+use sqlx::error::BoxDynError;
+use sqlx::migrate::{Migration as SqlxMigration, MigrationSource, MigrationType, Migrator};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use tauri_plugin_sql::MigrationKind;
+
+use crate::migrations::get_migrations;
+use crate::services::app_paths::ensure_parent_dir;
+
+pub mod recovery;
+pub mod repository;
+pub mod retry;
+
+pub use recovery::*;
+pub use repository::*;
+pub use retry::*;
+
+// ============================================================================
+// MIGRATIONS
+// ============================================================================
+
+/// Migration source built from the same list registered with the SQL plugin.
+/// Using identical versions and SQL keeps the checksums in `_sqlx_migrations`
+/// compatible, so whichever side opens the database first applies the schema.
+#[derive(Debug)]
+struct LisMigrationSource(Vec<SqlxMigration>);
+
+impl<'s> MigrationSource<'s> for LisMigrationSource {
+    fn resolve(
+        self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<SqlxMigration>, BoxDynError>> + Send + 's>> {
+        Box::pin(async move { Ok(self.0) })
+    }
+}
+
+/// Applies all pending schema migrations to the given pool
+pub async fn run_migrations(pool: &SqlitePool) -> Result<(), String> {
+    let migrations = get_migrations()
+        .into_iter()
+        .filter(|migration| matches!(migration.kind, MigrationKind::Up))
+        .map(|migration| {
+            SqlxMigration::new(
+                migration.version,
+                migration.description.into(),
+                MigrationType::ReversibleUp,
+                migration.sql.into(),
+                false,
+            )
+        })
+        .collect();
+
+    let migrator = Migrator::new(LisMigrationSource(migrations))
+        .await
+        .map_err(|e| format!("Failed to load database migrations: {}", e))?;
+
+    migrator
+        .run(pool)
+        .await
+        .map_err(|e| format!("Failed to run database migrations: {}", e))?;
+
+    log::debug!("Database migrations applied");
+    Ok(())
+}
+
+// ============================================================================
+// CONNECTION
+// ============================================================================
+
+/// Opens (creating it and its directory if needed) the LIS database and brings the
+/// schema up to date
+pub async fn establish_connection(db_path: &Path) -> Result<SqlitePool, String> {
+    ensure_parent_dir(db_path)?;
+    let options = SqliteConnectOptions::new()
+        .filename(db_path)
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .foreign_keys(true);
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(options)
+        .await
+        .map_err(|e| format!("Failed to open database at {}: {}", db_path.display(), e))?;
+
+    run_migrations(&pool).await?;
+
+    log::info!("Database connection established at {}", db_path.display());
+    Ok(pool)
+}
+
+/// Opens a migrated in-memory database for unit tests.
+/// A single connection is kept alive so every query sees the same database.
+#[cfg(test)]
+pub async fn establish_test_connection() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .expect("Failed to open in-memory database");
+
+    run_migrations(&pool)
+        .await
+        .expect("Failed to run migrations on in-memory database");
+
+    pool
+}
@@ -0,0 +1,521 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::pool::PoolConnection;
+use sqlx::sqlite::{Sqlite, SqlitePool};
+
+use super::establish_connection;
+use crate::services::app_paths::with_file_name_suffix;
+
+// ============================================================================
+// REPORTS
+// ============================================================================
+
+/// Result of `PRAGMA quick_check`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    pub messages: Vec<String>, // "ok", or one line per problem found
+    pub checked_at: DateTime<Utc>,
+}
+
+/// What was salvaged from one table of a corrupt database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableRecovery {
+    pub table: String,
+    pub recovered_rows: u64,
+    /// Rows that were located but could not be read or inserted
+    pub skipped_rows: u64,
+    /// Whether the whole table was walked without hitting unreadable pages
+    pub complete: bool,
+    /// Rows believed lost in unreadable pages, when rowids allow an estimate
+    pub estimated_lost_rows: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Outcome of an automatic recovery, shown to the user and kept next to the corrupt copy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveryReport {
+    pub database_path: String,
+    pub corrupt_copy_path: String,
+    pub integrity_errors: Vec<String>,
+    pub tables: Vec<TableRecovery>,
+    pub recovered_at: DateTime<Utc>,
+}
+
+impl RecoveryReport {
+    pub fn total_recovered_rows(&self) -> u64 {
+        self.tables.iter().map(|table| table.recovered_rows).sum()
+    }
+
+    /// True when every table was read in full and no row was skipped
+    pub fn is_lossless(&self) -> bool {
+        self.tables
+            .iter()
+            .all(|table| table.complete && table.skipped_rows == 0 && table.error.is_none())
+    }
+}
+
+// ============================================================================
+// INTEGRITY CHECK
+// ============================================================================
+
+/// Runs `PRAGMA quick_check` against the database
+pub async fn check_integrity(pool: &SqlitePool) -> Result<IntegrityReport, String> {
+    let messages: Vec<String> = sqlx::query_scalar("PRAGMA quick_check")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to run integrity check: {}", e))?;
+
+    Ok(IntegrityReport {
+        ok: messages.len() == 1 && messages[0] == "ok",
+        messages,
+        checked_at: Utc::now(),
+    })
+}
+
+/// Whether an error message is SQLite reporting a damaged file
+fn is_corruption_error(error: &str) -> bool {
+    let error = error.to_lowercase();
+    error.contains("malformed") || error.contains("not a database") || error.contains("corrupt")
+}
+
+// ============================================================================
+// STARTUP
+// ============================================================================
+
+/// Opens the LIS database, checking its integrity first. A corrupt database is moved
+/// aside and salvaged into a fresh one; the report describes what was kept and lost.
+pub async fn open_database_with_recovery(
+    db_path: &Path,
+) -> Result<(SqlitePool, Option<RecoveryReport>), String> {
+    let integrity_errors = match establish_connection(db_path).await {
+        Ok(pool) => match check_integrity(&pool).await {
+            Ok(integrity) if integrity.ok => return Ok((pool, None)),
+            Ok(integrity) => {
+                pool.close().await;
+                integrity.messages
+            }
+            Err(e) if is_corruption_error(&e) => {
+                pool.close().await;
+                vec![e]
+            }
+            Err(e) => return Err(e),
+        },
+        Err(e) if is_corruption_error(&e) => vec![e],
+        Err(e) => return Err(e),
+    };
+
+    log::error!(
+        "Database at {} failed its integrity check ({} problems); starting recovery",
+        db_path.display(),
+        integrity_errors.len()
+    );
+    let (pool, report) = recover_database(db_path, integrity_errors).await?;
+    Ok((pool, Some(report)))
+}
+
+// ============================================================================
+// RECOVERY
+// ============================================================================
+
+/// Moves the corrupt database aside, creates a fresh migrated database in its place
+/// and copies every readable row across
+pub async fn recover_database(
+    db_path: &Path,
+    integrity_errors: Vec<String>,
+) -> Result<(SqlitePool, RecoveryReport), String> {
+    let corrupt_copy = move_aside(db_path)?;
+    log::warn!("Corrupt database moved to {}", corrupt_copy.display());
+
+    let pool = establish_connection(db_path).await?;
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| format!("Failed to open recovered database: {}", e))?;
+
+    // Orphans are kept rather than lost: a result whose patient row is gone is still a result
+    sqlx::query("PRAGMA foreign_keys = OFF")
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to prepare recovered database: {}", e))?;
+
+    // ATTACH takes the file name as text; a path that is not Unicode is refused, not mangled
+    let corrupt_copy_name = corrupt_copy
+        .to_str()
+        .ok_or_else(|| format!("Database path {} is not valid Unicode", corrupt_copy.display()))?;
+    let tables = match sqlx::query("ATTACH DATABASE ? AS old")
+        .bind(corrupt_copy_name)
+        .execute(&mut *conn)
+        .await
+    {
+        Ok(_) => {
+            let tables = salvage_tables(&mut conn).await?;
+            let _ = sqlx::query("DETACH DATABASE old").execute(&mut *conn).await;
+            tables
+        }
+        Err(e) => {
+            log::error!("Could not open corrupt database copy: {}", e);
+            let error = format!("Corrupt database could not be opened: {}", e);
+            schema_tables(&mut conn)
+                .await?
+                .into_iter()
+                .map(|table| TableRecovery {
+                    table,
+                    recovered_rows: 0,
+                    skipped_rows: 0,
+                    complete: false,
+                    estimated_lost_rows: None,
+                    error: Some(error.clone()),
+                })
+                .collect()
+        }
+    };
+
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to restore foreign keys: {}", e))?;
+    drop(conn);
+
+    let report = RecoveryReport {
+        database_path: db_path.display().to_string(),
+        corrupt_copy_path: corrupt_copy.display().to_string(),
+        integrity_errors,
+        tables,
+        recovered_at: Utc::now(),
+    };
+    log_report(&report);
+    write_report(&corrupt_copy, &report);
+
+    Ok((pool, report))
+}
+
+/// Renames the database and its WAL/SHM files to `<name>.corrupt-<timestamp>`
+fn move_aside(db_path: &Path) -> Result<PathBuf, String> {
+    if db_path.file_name().is_none() {
+        return Err(format!("Invalid database path: {}", db_path.display()));
+    }
+    let corrupt_copy = with_file_name_suffix(db_path, &format!(".corrupt-{}", Utc::now().format("%Y%m%d%H%M%S")));
+
+    std::fs::rename(db_path, &corrupt_copy).map_err(|e| {
+        format!(
+            "Failed to move corrupt database {} aside to {}: {}",
+            db_path.display(),
+            corrupt_copy.display(),
+            e
+        )
+    })?;
+
+    // SQLite finds the sidecar files by name, so they move with the main file
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = with_file_name_suffix(db_path, suffix);
+        if sidecar.exists() {
+            let target = with_file_name_suffix(&corrupt_copy, suffix);
+            std::fs::rename(&sidecar, &target)
+                .map_err(|e| format!("Failed to move {} aside: {}", sidecar.display(), e))?;
+        }
+    }
+
+    Ok(corrupt_copy)
+}
+
+/// Application tables of the fresh schema, in creation order so parents come first
+async fn schema_tables(conn: &mut PoolConnection<Sqlite>) -> Result<Vec<String>, String> {
+    sqlx::query_scalar(
+        "SELECT name FROM main.sqlite_master
+         WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name != '_sqlx_migrations'
+         ORDER BY rowid",
+    )
+    .fetch_all(&mut **conn)
+    .await
+    .map_err(|e| format!("Failed to list tables: {}", e))
+}
+
+async fn table_columns(
+    conn: &mut PoolConnection<Sqlite>,
+    schema: &str,
+    table: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(&format!("SELECT name FROM pragma_table_info('{}', '{}')", table, schema))
+        .fetch_all(&mut **conn)
+        .await
+}
+
+async fn salvage_tables(conn: &mut PoolConnection<Sqlite>) -> Result<Vec<TableRecovery>, String> {
+    let mut tables = Vec::new();
+
+    sqlx::query("BEGIN")
+        .execute(&mut **conn)
+        .await
+        .map_err(|e| format!("Failed to start recovery transaction: {}", e))?;
+
+    for table in schema_tables(conn).await? {
+        let recovery = salvage_table(conn, &table).await;
+        log::info!(
+            "Recovered {} rows from {} ({} skipped, complete: {})",
+            recovery.recovered_rows,
+            table,
+            recovery.skipped_rows,
+            recovery.complete
+        );
+        tables.push(recovery);
+    }
+
+    sqlx::query("COMMIT")
+        .execute(&mut **conn)
+        .await
+        .map_err(|e| format!("Failed to commit recovered rows: {}", e))?;
+
+    Ok(tables)
+}
+
+/// Copies one table row by row. Rows are walked in rowid order from both ends so
+/// that a damaged page in the middle only costs the rows stored on it.
+async fn salvage_table(conn: &mut PoolConnection<Sqlite>, table: &str) -> TableRecovery {
+    let mut recovery = TableRecovery {
+        table: table.to_string(),
+        recovered_rows: 0,
+        skipped_rows: 0,
+        complete: false,
+        estimated_lost_rows: None,
+        error: None,
+    };
+
+    let old_columns = match table_columns(conn, "old", table).await {
+        Ok(columns) if !columns.is_empty() => columns,
+        Ok(_) => {
+            // Table did not exist yet in the corrupt database's schema version
+            recovery.complete = true;
+            return recovery;
+        }
+        Err(e) => {
+            recovery.error = Some(format!("Table schema unreadable: {}", e));
+            return recovery;
+        }
+    };
+    let columns: Vec<String> = match table_columns(conn, "main", table).await {
+        Ok(columns) => columns
+            .into_iter()
+            .filter(|column| old_columns.contains(column))
+            .map(|column| format!("\"{}\"", column))
+            .collect(),
+        Err(e) => {
+            recovery.error = Some(format!("Failed to read recovered schema: {}", e));
+            return recovery;
+        }
+    };
+    let column_list = columns.join(", ");
+    let copy_sql = format!(
+        "INSERT OR IGNORE INTO main.\"{table}\" ({column_list}) SELECT {column_list} FROM old.\"{table}\" WHERE rowid = ?"
+    );
+    let next_sql = format!(
+        "SELECT rowid FROM old.\"{table}\" WHERE rowid > ? AND rowid < ? ORDER BY rowid LIMIT 1"
+    );
+    let previous_sql = format!(
+        "SELECT rowid FROM old.\"{table}\" WHERE rowid > ? AND rowid < ? ORDER BY rowid DESC LIMIT 1"
+    );
+
+    // Forward from the start until the end of the table or the first unreadable page
+    let mut lower = i64::MIN;
+    loop {
+        match sqlx::query_scalar::<_, i64>(&next_sql)
+            .bind(lower)
+            .bind(i64::MAX)
+            .fetch_optional(&mut **conn)
+            .await
+        {
+            Ok(Some(rowid)) => {
+                copy_row(conn, &copy_sql, rowid, &mut recovery).await;
+                lower = rowid;
+            }
+            Ok(None) => {
+                recovery.complete = true;
+                return recovery;
+            }
+            Err(e) => {
+                recovery.error = Some(format!("Unreadable data after rowid {}: {}", lower, e));
+                break;
+            }
+        }
+    }
+
+    // Backward from the end until meeting the forward pass or another unreadable page
+    let mut upper = i64::MAX;
+    loop {
+        match sqlx::query_scalar::<_, i64>(&previous_sql)
+            .bind(lower)
+            .bind(upper)
+            .fetch_optional(&mut **conn)
+            .await
+        {
+            Ok(Some(rowid)) => {
+                copy_row(conn, &copy_sql, rowid, &mut recovery).await;
+                upper = rowid;
+            }
+            Ok(None) | Err(_) => break,
+        }
+    }
+
+    // Rows between the two passes are lost; rowids are dense unless rows were deleted
+    if upper == i64::MAX {
+        upper = match sqlx::query_scalar::<_, Option<i64>>(&format!("SELECT max(rowid) FROM old.\"{table}\""))
+            .fetch_one(&mut **conn)
+            .await
+        {
+            Ok(Some(max_rowid)) => max_rowid.saturating_add(1),
+            _ => i64::MAX,
+        };
+    }
+    if lower != i64::MIN && upper != i64::MAX && upper > lower {
+        recovery.estimated_lost_rows = Some((upper - lower - 1) as u64);
+    }
+
+    recovery
+}
+
+async fn copy_row(
+    conn: &mut PoolConnection<Sqlite>,
+    copy_sql: &str,
+    rowid: i64,
+    recovery: &mut TableRecovery,
+) {
+    match sqlx::query(copy_sql).bind(rowid).execute(&mut **conn).await {
+        Ok(result) if result.rows_affected() > 0 => recovery.recovered_rows += 1,
+        Ok(_) => recovery.skipped_rows += 1,
+        Err(e) => {
+            log::warn!("Skipping unreadable row {} of {}: {}", rowid, recovery.table, e);
+            recovery.skipped_rows += 1;
+        }
+    }
+}
+
+/// Records the outcome in the application log
+fn log_report(report: &RecoveryReport) {
+    log::warn!(
+        "DATABASE RECOVERY: {} rows recovered into {}, corrupt copy kept at {}",
+        report.total_recovered_rows(),
+        report.database_path,
+        report.corrupt_copy_path
+    );
+    for table in &report.tables {
+        log::warn!(
+            "DATABASE RECOVERY: {}: {} recovered, {} skipped, {} estimated lost{}",
+            table.table,
+            table.recovered_rows,
+            table.skipped_rows,
+            table
+                .estimated_lost_rows
+                .map(|rows| rows.to_string())
+                .unwrap_or_else(|| if table.complete { "0".to_string() } else { "unknown".to_string() }),
+            table.error.as_ref().map(|e| format!(" ({})", e)).unwrap_or_default()
+        );
+    }
+}
+
+/// Keeps the report beside the corrupt copy for later support
+fn write_report(corrupt_copy: &Path, report: &RecoveryReport) {
+    let report_path = with_file_name_suffix(corrupt_copy, ".recovery.json");
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => {
+            if let Err(e) = std::fs::write(&report_path, json) {
+                log::error!("Failed to write recovery report {}: {}", report_path.display(), e);
+            }
+        }
+        Err(e) => log::error!("Failed to serialize recovery report: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn populated_database(dir: &Path, patients: usize) -> PathBuf {
+        std::fs::create_dir_all(dir).unwrap();
+        let db_path = dir.join("nramh-lis.db");
+        let pool = establish_connection(&db_path).await.unwrap();
+
+        for i in 0..patients {
+            sqlx::query(
+                "INSERT INTO patients (id, last_name, first_name, sex, street, created_at, updated_at)
+                 VALUES (?, ?, ?, 'U', ?, '2025-01-01T00:00:00Z', '2025-01-01T00:00:00Z')",
+            )
+            .bind(format!("P{:05}", i))
+            .bind(format!("Patient{}", i))
+            .bind("Test")
+            .bind("x".repeat(300))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&pool).await.unwrap();
+        pool.close().await;
+        db_path
+    }
+
+    fn test_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("nramh-lis-recovery-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_healthy_database_opens_without_recovery() {
+        let dir = test_dir();
+        let db_path = populated_database(&dir, 5).await;
+
+        let (pool, report) = open_database_with_recovery(&db_path).await.unwrap();
+        assert!(report.is_none());
+        assert!(check_integrity(&pool).await.unwrap().ok);
+
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_database_is_recovered_with_loss_report() {
+        let dir = test_dir();
+        let db_path = populated_database(&dir, 300).await;
+
+        // Power cut mid-write: lose the second half of the file
+        let length = std::fs::metadata(&db_path).unwrap().len();
+        let file = std::fs::OpenOptions::new().write(true).open(&db_path).unwrap();
+        file.set_len(length / 2).unwrap();
+        drop(file);
+
+        let (pool, report) = open_database_with_recovery(&db_path).await.unwrap();
+        let report = report.expect("corruption should trigger recovery");
+
+        assert!(!report.integrity_errors.is_empty());
+        assert!(!report.is_lossless());
+        assert!(Path::new(&report.corrupt_copy_path).exists());
+
+        let patients = report.tables.iter().find(|t| t.table == "patients").unwrap();
+        assert!(!patients.complete);
+        assert!(patients.recovered_rows > 0);
+        assert!(patients.recovered_rows < 300);
+
+        // The report matches what actually landed in the new database
+        let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM patients")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored as u64, patients.recovered_rows);
+        if let Some(lost) = patients.estimated_lost_rows {
+            assert_eq!(patients.recovered_rows + patients.skipped_rows + lost, 300);
+        }
+
+        // Recovered database is healthy and writable
+        assert!(check_integrity(&pool).await.unwrap().ok);
+        sqlx::query(
+            "INSERT INTO patients (id, sex, created_at, updated_at)
+             VALUES ('NEW1', 'F', '2025-01-02T00:00:00Z', '2025-01-02T00:00:00Z')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        pool.close().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,4350 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use futures_util::stream::{BoxStream, StreamExt};
+use sqlx::query::Query;
+use sqlx::sqlite::{Sqlite, SqliteArguments, SqlitePool, SqliteRow};
+use sqlx::Row;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use super::retry::{BreakerState, RetryLayer, RetryMetrics};
+use crate::models::hematology::HematologyResult;
+use crate::models::patient::{ContactInfo, PatientAddress, PatientName, Sex};
+use crate::models::result::{ReferenceRange, ResultFlags, TestResultMetadata};
+use crate::models::{
+    AnalyzerEventType, CandidateStatus, ConformanceReport, ConformanceRuleCount, ConformanceWarning, ConnectionSummary,
+    ConnectionTermination, CorrectionPattern, DashboardCounts, DataSource, DemographicsHold, DispatchStatus,
+    DuplicateCandidate, EventSummary, EventTypeCount, FirmwareChange, HeldMessage, HoldStatus, LatencyStats,
+    OrderDispatch, OrderPriority, Patient, PatientImport, ProcessingStage, ProcessingTimeline, RawMessage,
+    RemoteAddress, ResultAuditAction, ResultAuditEntry, ResultDetail, ResultImage, ResultImport, ResultIntegrityReport,
+    ResultChange, ResultChangePage, ResultChangeType, ResultNote, ResultStatus, ResultUploadStatus, ReviewReason, RuleScope, TerminationCounts, TestOrder, TestResult,
+    TimelineStageView, UploadPriority, UploadQueueEntry, UploadQueueSummary, UploadRemediation, UploadStatus,
+    VerificationCondition, VerificationDecision, VerificationHold, VerificationRule, VerificationStamp,
+};
+
+/// Column order bound by `test_result_query`. A result whose content hash is already
+/// stored for its analyzer and patient is skipped (no row affected); unhashed results
+/// are always inserted.
+const INSERT_TEST_RESULT_SQL: &str = r#"
+    INSERT INTO test_results (
+        id, test_id, sample_id, value, units, reference_range_lower, reference_range_upper,
+        abnormal_flag, nature_of_abnormality, status, completed_date_time, sequence_number,
+        instrument, operator, dilution_factor, raw_value, source_message_control_id, analyzer_id, patient_id,
+        source, content_hash, verification_decision, verification_rule_id, verification_rule_version, verified_at,
+        comments, parameter_name, panel, analysis_mode, created_at, updated_at
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+    ON CONFLICT (IFNULL(analyzer_id, ''), patient_id, content_hash)
+        WHERE content_hash IS NOT NULL AND content_hash != '' DO NOTHING
+"#;
+
+/// Same columns as [`INSERT_TEST_RESULT_SQL`], overwriting a result with the same id
+const UPSERT_TEST_RESULT_SQL: &str = r#"
+    INSERT INTO test_results (
+        id, test_id, sample_id, value, units, reference_range_lower, reference_range_upper,
+        abnormal_flag, nature_of_abnormality, status, completed_date_time, sequence_number,
+        instrument, operator, dilution_factor, raw_value, source_message_control_id, analyzer_id, patient_id,
+        source, content_hash, verification_decision, verification_rule_id, verification_rule_version, verified_at,
+        comments, parameter_name, panel, analysis_mode, created_at, updated_at
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+    ON CONFLICT (id) DO UPDATE SET
+        test_id = excluded.test_id,
+        sample_id = excluded.sample_id,
+        value = excluded.value,
+        units = excluded.units,
+        reference_range_lower = excluded.reference_range_lower,
+        reference_range_upper = excluded.reference_range_upper,
+        abnormal_flag = excluded.abnormal_flag,
+        nature_of_abnormality = excluded.nature_of_abnormality,
+        status = excluded.status,
+        completed_date_time = excluded.completed_date_time,
+        sequence_number = excluded.sequence_number,
+        instrument = excluded.instrument,
+        operator = excluded.operator,
+        dilution_factor = excluded.dilution_factor,
+        raw_value = excluded.raw_value,
+        source_message_control_id = excluded.source_message_control_id,
+        analyzer_id = excluded.analyzer_id,
+        patient_id = excluded.patient_id,
+        source = excluded.source,
+        content_hash = excluded.content_hash,
+        verification_decision = excluded.verification_decision,
+        verification_rule_id = excluded.verification_rule_id,
+        verification_rule_version = excluded.verification_rule_version,
+        verified_at = excluded.verified_at,
+        comments = excluded.comments,
+        parameter_name = excluded.parameter_name,
+        panel = excluded.panel,
+        analysis_mode = excluded.analysis_mode,
+        updated_at = excluded.updated_at
+    ON CONFLICT (IFNULL(analyzer_id, ''), patient_id, content_hash)
+        WHERE content_hash IS NOT NULL AND content_hash != '' DO NOTHING
+"#;
+
+// ============================================================================
+// SQLITE REPOSITORY
+// ============================================================================
+
+/// SQLite-backed repository shared by the analyzer services and background workers
+#[derive(Debug, Clone)]
+pub struct SqliteRepository {
+    pool: SqlitePool,
+    /// Busy/locked retries and circuit breaker for ingestion writes
+    retry: Arc<RetryLayer>,
+    /// Active verification rules, read once per change instead of once per sample
+    rule_cache: Arc<Mutex<RuleCache>>,
+}
+
+/// Active verification rules as last read. Saving or retiring a rule bumps the
+/// generation, so a read that raced the change does not refill the cache.
+#[derive(Debug, Default)]
+struct RuleCache {
+    generation: u64,
+    rules: Option<Vec<VerificationRule>>,
+}
+
+impl SqliteRepository {
+    /// Creates a new repository on top of an established pool
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            retry: Arc::new(RetryLayer::default()),
+            rule_cache: Arc::new(Mutex::new(RuleCache::default())),
+        }
+    }
+
+    /// Gets the underlying connection pool
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    /// Retry and circuit breaker counters
+    pub fn retry_metrics(&self) -> RetryMetrics {
+        self.retry.metrics()
+    }
+
+    /// Receives circuit breaker state changes
+    pub fn subscribe_breaker_state(&self) -> watch::Receiver<BreakerState> {
+        self.retry.subscribe()
+    }
+
+    // ------------------------------------------------------------------------
+    // PATIENTS
+    // ------------------------------------------------------------------------
+
+    /// Inserts a patient if no patient with the same id exists.
+    /// Returns true when a new row was created.
+    pub async fn save_patient(&self, patient: &Patient, source: &DataSource) -> Result<bool, String> {
+        let result = self
+            .retry
+            .run("save_patient", || async move {
+                Self::insert_patient_query(patient, source)
+                    .map_err(sqlx::Error::Protocol)?
+                    .execute(&self.pool)
+                    .await
+            })
+            .await
+            .map_err(|e| format!("Failed to save patient {}: {}", patient.id, e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Finds a patient by id
+    pub async fn get_patient(&self, patient_id: &str) -> Result<Option<Patient>, String> {
+        let row = sqlx::query("SELECT * FROM patients WHERE id = ?")
+            .bind(patient_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch patient {}: {}", patient_id, e))?;
+
+        row.as_ref()
+            .map(Self::row_to_patient)
+            .transpose()
+            .map_err(|e| format!("Failed to decode patient {}: {}", patient_id, e))
+    }
+
+    /// Lists all patients, oldest first
+    pub async fn list_patients(&self) -> Result<Vec<Patient>, String> {
+        let rows = sqlx::query("SELECT * FROM patients ORDER BY created_at, id")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to list patients: {}", e))?;
+
+        rows.iter()
+            .map(Self::row_to_patient)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode patients: {}", e))
+    }
+
+    /// Gets the patients with results on any of `sample_ids`, each once, oldest first
+    pub async fn get_patients_by_sample_ids(&self, sample_ids: &[String]) -> Result<Vec<Patient>, String> {
+        if sample_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = vec!["?"; sample_ids.len()].join(", ");
+        let sql = format!(
+            r#"
+            SELECT * FROM patients
+            WHERE id IN (SELECT patient_id FROM test_results WHERE sample_id IN ({}))
+            ORDER BY created_at, id
+            "#,
+            placeholders
+        );
+        let mut query = sqlx::query(&sql);
+        for sample_id in sample_ids {
+            query = query.bind(sample_id.as_str());
+        }
+        let rows = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch patients for samples: {}", e))?;
+
+        rows.iter()
+            .map(Self::row_to_patient)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode patients for samples: {}", e))
+    }
+
+    /// Updates a patient's demographics; `None` values keep what is stored.
+    /// Returns false when the patient does not exist.
+    pub async fn update_patient_demographics(
+        &self,
+        patient_id: &str,
+        name: &PatientName,
+        birth_date: Option<DateTime<Utc>>,
+        sex: Option<Sex>,
+    ) -> Result<bool, String> {
+        let result = sqlx::query(
+            r#"
+            UPDATE patients SET
+                last_name = COALESCE(?, last_name),
+                first_name = COALESCE(?, first_name),
+                middle_name = COALESCE(?, middle_name),
+                birth_date = COALESCE(?, birth_date),
+                sex = COALESCE(?, sex),
+                updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(name.last_name.as_deref())
+        .bind(name.first_name.as_deref())
+        .bind(name.middle_name.as_deref())
+        .bind(birth_date)
+        .bind(sex.map(String::from))
+        .bind(Utc::now())
+        .bind(patient_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to update patient {}: {}", patient_id, e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replaces a patient's address and telephone numbers with those of their latest
+    /// message. Whatever the message does not carry is kept.
+    pub async fn update_patient_contact_details(
+        &self,
+        patient_id: &str,
+        address: Option<&PatientAddress>,
+        telephone: &[String],
+        contacts: &[ContactInfo],
+    ) -> Result<bool, String> {
+        let serialize = |label: &str, value: Result<String, serde_json::Error>| {
+            value.map_err(|e| format!("Failed to serialize {} of patient {}: {}", label, patient_id, e))
+        };
+        let telephone = (!telephone.is_empty())
+            .then(|| serialize("telephone numbers", serde_json::to_string(telephone)))
+            .transpose()?;
+        let contacts = (!contacts.is_empty())
+            .then(|| serialize("contacts", serde_json::to_string(contacts)))
+            .transpose()?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE patients SET
+                street = CASE WHEN ?1 THEN ?2 ELSE street END,
+                address_other_designation = CASE WHEN ?1 THEN ?3 ELSE address_other_designation END,
+                city = CASE WHEN ?1 THEN ?4 ELSE city END,
+                state = CASE WHEN ?1 THEN ?5 ELSE state END,
+                zip = CASE WHEN ?1 THEN ?6 ELSE zip END,
+                country_code = CASE WHEN ?1 THEN ?7 ELSE country_code END,
+                address_raw = CASE WHEN ?1 THEN ?8 ELSE address_raw END,
+                address_unparsed = CASE WHEN ?1 THEN ?9 ELSE address_unparsed END,
+                telephone = COALESCE(?10, telephone),
+                contacts = COALESCE(?11, contacts),
+                updated_at = ?12
+            WHERE id = ?13
+            "#,
+        )
+        .bind(address.is_some())
+        .bind(address.and_then(|a| a.street.as_deref()))
+        .bind(address.and_then(|a| a.other_designation.as_deref()))
+        .bind(address.and_then(|a| a.city.as_deref()))
+        .bind(address.and_then(|a| a.state.as_deref()))
+        .bind(address.and_then(|a| a.zip.as_deref()))
+        .bind(address.and_then(|a| a.country_code.as_deref()))
+        .bind(address.and_then(|a| a.raw.as_deref()))
+        .bind(address.is_some_and(|a| a.unparsed))
+        .bind(telephone)
+        .bind(contacts)
+        .bind(Utc::now())
+        .bind(patient_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to update contact details of patient {}: {}", patient_id, e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replaces the comments stored for a patient with those of their latest message
+    pub async fn set_patient_comments(&self, patient_id: &str, comments: &[String]) -> Result<bool, String> {
+        let serialized = (!comments.is_empty())
+            .then(|| serde_json::to_string(comments))
+            .transpose()
+            .map_err(|e| format!("Failed to serialize comments of patient {}: {}", patient_id, e))?;
+        let result = sqlx::query("UPDATE patients SET comments = ?, updated_at = ? WHERE id = ?")
+            .bind(serialized)
+            .bind(Utc::now())
+            .bind(patient_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to update comments of patient {}: {}", patient_id, e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Merges `duplicate_id` into `primary_id`: the duplicate's results move to the primary,
+    /// demographics missing on the primary are filled from the duplicate, and the duplicate
+    /// is deleted. Returns the number of results moved.
+    pub async fn merge_patients(&self, primary_id: &str, duplicate_id: &str) -> Result<u64, String> {
+        if primary_id == duplicate_id {
+            return Err(format!("Cannot merge patient {} into itself", primary_id));
+        }
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        let filled = sqlx::query(
+            r#"
+            UPDATE patients SET
+                last_name = COALESCE(last_name, (SELECT last_name FROM patients WHERE id = ?1)),
+                first_name = COALESCE(first_name, (SELECT first_name FROM patients WHERE id = ?1)),
+                middle_name = COALESCE(middle_name, (SELECT middle_name FROM patients WHERE id = ?1)),
+                birth_date = COALESCE(birth_date, (SELECT birth_date FROM patients WHERE id = ?1)),
+                updated_at = ?2
+            WHERE id = ?3 AND EXISTS (SELECT 1 FROM patients WHERE id = ?1)
+            "#,
+        )
+        .bind(duplicate_id)
+        .bind(Utc::now())
+        .bind(primary_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to update patient {}: {}", primary_id, e))?;
+        if filled.rows_affected() == 0 {
+            return Err(format!("Patient {} or {} not found", primary_id, duplicate_id));
+        }
+
+        // A result both patients got from the same analyzer is kept once under its hash;
+        // the moved copy keeps its row but loses the hash
+        sqlx::query(
+            r#"
+            UPDATE test_results AS moving SET content_hash = NULL
+            WHERE patient_id = ?1 AND EXISTS (
+                SELECT 1 FROM test_results AS kept
+                WHERE kept.patient_id = ?2
+                  AND kept.content_hash = moving.content_hash
+                  AND IFNULL(kept.analyzer_id, '') = IFNULL(moving.analyzer_id, '')
+            )
+            "#,
+        )
+        .bind(duplicate_id)
+        .bind(primary_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to unhash duplicate results of patient {}: {}", duplicate_id, e))?;
+
+        let moved = sqlx::query("UPDATE test_results SET patient_id = ?, updated_at = ? WHERE patient_id = ?")
+            .bind(primary_id)
+            .bind(Utc::now())
+            .bind(duplicate_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to move results of patient {}: {}", duplicate_id, e))?;
+
+        sqlx::query("DELETE FROM patients WHERE id = ?")
+            .bind(duplicate_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to delete patient {}: {}", duplicate_id, e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Failed to commit patient merge: {}", e))?;
+
+        Ok(moved.rows_affected())
+    }
+
+    // ------------------------------------------------------------------------
+    // TEST RESULTS
+    // ------------------------------------------------------------------------
+
+    /// Stores a test result for a patient
+    ///
+    /// Returns false without storing when an identical result (same content hash)
+    /// is already stored, e.g. when an analyzer retransmits.
+    pub async fn save_test_result(
+        &self,
+        result: &TestResult,
+        patient_id: &str,
+        source: &DataSource,
+    ) -> Result<bool, String> {
+        let inserted = self
+            .retry
+            .run("save_test_result", || {
+                Self::test_result_query(INSERT_TEST_RESULT_SQL, result, patient_id, source).execute(&self.pool)
+            })
+            .await
+            .map_err(|e| format!("Failed to save test result {}: {}", result.id, e))?
+            .rows_affected()
+            > 0;
+
+        if !inserted {
+            log::info!("Skipping result {}: identical to a stored result", result.id);
+        }
+        Ok(inserted)
+    }
+
+    /// Saves a hematology result in `test_results`: the parameter's OBX-3 becomes the
+    /// test id, its "low-high" range the reference limits and its flags the abnormal
+    /// flags. Returns false when an identical result is already stored.
+    pub async fn save_hematology_result(
+        &self,
+        result: &HematologyResult,
+        patient_id: &str,
+        source: &DataSource,
+    ) -> Result<bool, String> {
+        self.save_test_result(&TestResult::from(result.clone()), patient_id, source)
+            .await
+            .map_err(|e| format!("Failed to save hematology result {}: {}", result.parameter_code, e))
+    }
+
+    /// Holds results received without a patient id against their sample until
+    /// [`Self::assign_sample_results`] names the patient. Returns how many were held.
+    pub async fn hold_unassigned_results(&self, results: &[TestResult], source: &DataSource) -> Result<usize, String> {
+        let mut held = 0;
+        for result in results {
+            let serialized = serde_json::to_string(result)
+                .map_err(|e| format!("Failed to serialize result {}: {}", result.id, e))?;
+            let outcome = self
+                .retry
+                .run("hold_unassigned_result", || {
+                    sqlx::query(
+                        r#"
+                        INSERT OR IGNORE INTO unassigned_results (id, sample_id, analyzer_id, source, result, created_at)
+                        VALUES (?, ?, ?, ?, ?, ?)
+                        "#,
+                    )
+                    .bind(result.id.as_str())
+                    .bind(result.sample_id.as_str())
+                    .bind(result.analyzer_id.as_deref())
+                    .bind(source.to_string())
+                    .bind(serialized.as_str())
+                    .bind(Utc::now())
+                    .execute(&self.pool)
+                })
+                .await
+                .map_err(|e| format!("Failed to hold result {} of sample {}: {}", result.id, result.sample_id, e))?;
+            held += outcome.rows_affected() as usize;
+        }
+        Ok(held)
+    }
+
+    /// Gets the results held against a sample without a patient, oldest first
+    pub async fn get_unassigned_results(&self, sample_id: &str) -> Result<Vec<TestResult>, String> {
+        let rows: Vec<String> =
+            sqlx::query_scalar("SELECT result FROM unassigned_results WHERE sample_id = ? ORDER BY created_at, rowid")
+                .bind(sample_id)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to fetch unassigned results of sample {}: {}", sample_id, e))?;
+
+        rows.iter()
+            .map(|row| serde_json::from_str(row))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode unassigned results of sample {}: {}", sample_id, e))
+    }
+
+    /// Stores the results held against `sample_id` under `patient_id`, who must already
+    /// be registered, and clears the holds. Results identical to a stored one are dropped.
+    /// Returns the ids of the results stored.
+    pub async fn assign_sample_results(&self, sample_id: &str, patient_id: &str) -> Result<Vec<String>, String> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT source, result FROM unassigned_results WHERE sample_id = ? ORDER BY created_at, rowid",
+        )
+        .bind(sample_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to fetch unassigned results of sample {}: {}", sample_id, e))?;
+
+        let mut stored = Vec::new();
+        for (source, serialized) in &rows {
+            let result: TestResult = serde_json::from_str(serialized)
+                .map_err(|e| format!("Failed to decode unassigned result of sample {}: {}", sample_id, e))?;
+            let inserted =
+                Self::test_result_query(INSERT_TEST_RESULT_SQL, &result, patient_id, &DataSource::from(source.as_str()))
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| format!("Failed to save test result {}: {}", result.id, e))?
+                    .rows_affected()
+                    > 0;
+            if inserted {
+                stored.push(result.id);
+            }
+        }
+        sqlx::query("DELETE FROM unassigned_results WHERE sample_id = ?")
+            .bind(sample_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to clear unassigned results of sample {}: {}", sample_id, e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Failed to commit results of sample {} for patient {}: {}", sample_id, patient_id, e))?;
+        Ok(stored)
+    }
+
+    /// Re-hashes every stored result and reports those whose content no longer
+    /// matches the hash recorded when it was stored
+    pub async fn verify_result_hashes(&self) -> Result<ResultIntegrityReport, String> {
+        let rows = sqlx::query("SELECT * FROM test_results ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch results for verification: {}", e))?;
+
+        let mut report = ResultIntegrityReport::default();
+        for row in &rows {
+            let result = Self::row_to_test_result(row)
+                .map_err(|e| format!("Failed to decode result for verification: {}", e))?;
+            let stored_hash: Option<String> = row
+                .try_get("content_hash")
+                .map_err(|e| format!("Failed to read content hash of result {}: {}", result.id, e))?;
+
+            report.checked += 1;
+            match stored_hash.filter(|hash| !hash.is_empty()) {
+                None => report.unhashed += 1,
+                Some(hash) if result.content_hash().as_deref() != Some(hash.as_str()) => {
+                    log::warn!("Result {} does not match its content hash", result.id);
+                    report.mismatched.push(result.id);
+                }
+                Some(_) => {}
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Gets all results for a patient, most recent first
+    pub async fn get_patient_results(&self, patient_id: &str) -> Result<Vec<TestResult>, String> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM test_results
+            WHERE patient_id = ?
+            ORDER BY completed_date_time DESC, sequence_number ASC
+            "#,
+        )
+        .bind(patient_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch results for patient {}: {}", patient_id, e))?;
+
+        rows.iter()
+            .map(Self::row_to_test_result)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode results for patient {}: {}", patient_id, e))
+    }
+
+    /// Streams a patient's results in the order of `get_patient_results`, decoding each
+    /// row as it is read so a busy patient's history is never held in memory at once.
+    /// The stream holds a pooled connection until it is dropped.
+    pub fn stream_patient_results<'a>(&'a self, patient_id: &'a str) -> BoxStream<'a, Result<TestResult, String>> {
+        sqlx::query(
+            r#"
+            SELECT * FROM test_results
+            WHERE patient_id = ?
+            ORDER BY completed_date_time DESC, sequence_number ASC
+            "#,
+        )
+        .bind(patient_id)
+        .fetch(&self.pool)
+        .map(move |row| {
+            let row = row.map_err(|e| format!("Failed to fetch results for patient {}: {}", patient_id, e))?;
+            Self::row_to_test_result(&row)
+                .map_err(|e| format!("Failed to decode results for patient {}: {}", patient_id, e))
+        })
+        .boxed()
+    }
+
+    /// Gets a patient's results produced by any of `analyzer_ids`, most recent first
+    pub async fn get_patient_results_by_analyzers(
+        &self,
+        patient_id: &str,
+        analyzer_ids: &[String],
+    ) -> Result<Vec<TestResult>, String> {
+        if analyzer_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = vec!["?"; analyzer_ids.len()].join(", ");
+        let sql = format!(
+            r#"
+            SELECT * FROM test_results
+            WHERE patient_id = ? AND analyzer_id IN ({})
+            ORDER BY completed_date_time DESC, sequence_number ASC
+            "#,
+            placeholders
+        );
+        let mut query = sqlx::query(&sql).bind(patient_id);
+        for analyzer_id in analyzer_ids {
+            query = query.bind(analyzer_id.as_str());
+        }
+        let rows = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch results for patient {}: {}", patient_id, e))?;
+
+        rows.iter()
+            .map(Self::row_to_test_result)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode results for patient {}: {}", patient_id, e))
+    }
+
+    /// Finds a test result by id
+    pub async fn get_test_result(&self, result_id: &str) -> Result<Option<TestResult>, String> {
+        let row = sqlx::query("SELECT * FROM test_results WHERE id = ?")
+            .bind(result_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch test result {}: {}", result_id, e))?;
+
+        row.as_ref()
+            .map(Self::row_to_test_result)
+            .transpose()
+            .map_err(|e| format!("Failed to decode test result {}: {}", result_id, e))
+    }
+
+    /// Gets all results recorded for a sample, in sequence order
+    pub async fn get_results_by_sample_id(&self, sample_id: &str) -> Result<Vec<TestResult>, String> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM test_results
+            WHERE sample_id = ?
+            ORDER BY sequence_number ASC
+            "#,
+        )
+        .bind(sample_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch results for sample {}: {}", sample_id, e))?;
+
+        rows.iter()
+            .map(Self::row_to_test_result)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode results for sample {}: {}", sample_id, e))
+    }
+
+    /// Gets the results carried by the inbound message with this MSH-10 / H.3 control id
+    pub async fn get_results_by_message_control_id(&self, control_id: &str) -> Result<Vec<TestResult>, String> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM test_results
+            WHERE source_message_control_id = ?
+            ORDER BY sample_id ASC, sequence_number ASC
+            "#,
+        )
+        .bind(control_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch results for message {}: {}", control_id, e))?;
+
+        rows.iter()
+            .map(Self::row_to_test_result)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode results for message {}: {}", control_id, e))
+    }
+
+    /// Gets the result of a sample's test that has not been superseded
+    pub async fn get_current_result(&self, sample_id: &str, test_id: &str) -> Result<Option<TestResult>, String> {
+        let row = sqlx::query(
+            r#"
+            SELECT * FROM test_results
+            WHERE sample_id = ? AND test_id = ? AND superseded_by IS NULL
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(sample_id)
+        .bind(test_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch current {} result of sample {}: {}", test_id, sample_id, e))?;
+
+        row.as_ref()
+            .map(Self::row_to_test_result)
+            .transpose()
+            .map_err(|e| format!("Failed to decode current {} result of sample {}: {}", test_id, sample_id, e))
+    }
+
+    /// Gets the patient's most recent current result of a test completed before
+    /// `before`, ignoring results of `sample_id` itself (for delta checks)
+    pub async fn get_previous_result(
+        &self,
+        patient_id: &str,
+        test_id: &str,
+        sample_id: &str,
+        before: DateTime<Utc>,
+    ) -> Result<Option<TestResult>, String> {
+        let row = sqlx::query(
+            r#"
+            SELECT * FROM test_results
+            WHERE patient_id = ? AND test_id = ? AND sample_id != ? AND superseded_by IS NULL
+              AND COALESCE(completed_date_time, created_at) < ?
+            ORDER BY COALESCE(completed_date_time, created_at) DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(patient_id)
+        .bind(test_id)
+        .bind(sample_id)
+        .bind(before)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch previous {} result of patient {}: {}", test_id, patient_id, e))?;
+
+        row.as_ref()
+            .map(Self::row_to_test_result)
+            .transpose()
+            .map_err(|e| format!("Failed to decode previous {} result of patient {}: {}", test_id, patient_id, e))
+    }
+
+    /// Stores `replacement` and marks `previous_id` as superseded by it, for the
+    /// same patient, in one transaction. Fails without storing anything when
+    /// `previous_id` was already superseded.
+    ///
+    /// Callers read-modify-write the current result, so they hold the sample's lock
+    /// (see `SampleLocks`) for the whole operation.
+    pub async fn supersede_test_result(
+        &self,
+        previous_id: &str,
+        replacement: &TestResult,
+        source: &DataSource,
+    ) -> Result<(), String> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        let patient_id = sqlx::query_scalar::<_, Option<String>>(
+            "SELECT patient_id FROM test_results WHERE id = ? AND superseded_by IS NULL",
+        )
+        .bind(previous_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to fetch test result {}: {}", previous_id, e))?
+        .flatten();
+        let Some(patient_id) = patient_id else {
+            return Err(format!("Test result {} is not current; it was superseded or does not exist", previous_id));
+        };
+
+        let inserted = Self::test_result_query(INSERT_TEST_RESULT_SQL, replacement, &patient_id, source)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to save test result {}: {}", replacement.id, e))?
+            .rows_affected()
+            > 0;
+        if !inserted {
+            return Err(format!("Test result {} is identical to a stored result", replacement.id));
+        }
+        sqlx::query("UPDATE test_results SET superseded_by = ? WHERE id = ?")
+            .bind(replacement.id.as_str())
+            .bind(previous_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to supersede test result {}: {}", previous_id, e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Failed to commit supersede of {}: {}", previous_id, e))
+    }
+
+    /// Stores `result` as already superseded by `successor_id`, the current result of
+    /// the same sample and test, for a message that reached ingestion after a newer one.
+    /// It takes its place in the chain just before the successor. Returns false when an
+    /// identical result is already stored.
+    pub async fn save_superseded_test_result(
+        &self,
+        result: &TestResult,
+        successor_id: &str,
+        source: &DataSource,
+    ) -> Result<bool, String> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        let patient_id = sqlx::query_scalar::<_, String>("SELECT patient_id FROM test_results WHERE id = ?")
+            .bind(successor_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to fetch test result {}: {}", successor_id, e))?
+            .ok_or_else(|| format!("Test result {} not found", successor_id))?;
+        let inserted = Self::test_result_query(INSERT_TEST_RESULT_SQL, result, &patient_id, source)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to save test result {}: {}", result.id, e))?
+            .rows_affected()
+            > 0;
+        if !inserted {
+            return Ok(false);
+        }
+        // The version the successor replaced is now replaced by the late result
+        sqlx::query("UPDATE test_results SET superseded_by = ? WHERE superseded_by = ? AND id != ?")
+            .bind(result.id.as_str())
+            .bind(successor_id)
+            .bind(result.id.as_str())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to relink results superseded by {}: {}", successor_id, e))?;
+        sqlx::query("UPDATE test_results SET superseded_by = ? WHERE id = ?")
+            .bind(successor_id)
+            .bind(result.id.as_str())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to supersede test result {}: {}", result.id, e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Failed to commit superseded result {}: {}", result.id, e))?;
+        Ok(true)
+    }
+
+    /// Gets every version of a sample's test result, oldest first, following the
+    /// supersede links. Fails if the versions do not form a single chain.
+    pub async fn get_result_chain(&self, sample_id: &str, test_id: &str) -> Result<Vec<TestResult>, String> {
+        let rows = sqlx::query("SELECT * FROM test_results WHERE sample_id = ? AND test_id = ?")
+            .bind(sample_id)
+            .bind(test_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch {} results of sample {}: {}", test_id, sample_id, e))?;
+
+        let mut links: HashMap<String, (TestResult, Option<String>)> = HashMap::new();
+        for row in &rows {
+            let result = Self::row_to_test_result(row)
+                .map_err(|e| format!("Failed to decode {} result of sample {}: {}", test_id, sample_id, e))?;
+            let superseded_by: Option<String> = row
+                .try_get("superseded_by")
+                .map_err(|e| format!("Failed to decode {} result of sample {}: {}", test_id, sample_id, e))?;
+            links.insert(result.id.clone(), (result, superseded_by));
+        }
+
+        let inconsistent = || format!("Results of {} for sample {} do not form a single chain", test_id, sample_id);
+        let mut roots = links
+            .keys()
+            .filter(|id| !links.values().any(|(_, next)| next.as_deref() == Some(id.as_str())));
+        let mut next = roots.next().cloned();
+        if roots.next().is_some() {
+            return Err(inconsistent());
+        }
+
+        let mut chain = Vec::with_capacity(links.len());
+        while let Some(id) = next {
+            let (result, superseded_by) = links.remove(&id).ok_or_else(inconsistent)?;
+            chain.push(result);
+            next = superseded_by;
+        }
+        if !links.is_empty() {
+            return Err(inconsistent());
+        }
+        Ok(chain)
+    }
+
+    /// Gets one page of results completed between `from` and `to` (falling back
+    /// to when they were stored), in storage order after `after_rowid`.
+    /// Returns `(rowid, patient_id, result)` so callers can page through large ranges.
+    pub async fn get_results_page_in_range(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        after_rowid: i64,
+        limit: u32,
+    ) -> Result<Vec<(i64, String, TestResult)>, String> {
+        let rows = sqlx::query(
+            r#"
+            SELECT rowid AS page_rowid, * FROM test_results
+            WHERE COALESCE(completed_date_time, created_at) >= ?
+              AND COALESCE(completed_date_time, created_at) <= ?
+              AND rowid > ?
+            ORDER BY rowid ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .bind(after_rowid)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch results in range: {}", e))?;
+
+        rows.iter()
+            .map(|row| -> Result<(i64, String, TestResult), sqlx::Error> {
+                Ok((
+                    row.try_get("page_rowid")?,
+                    row.try_get("patient_id")?,
+                    Self::row_to_test_result(row)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode results in range: {}", e))
+    }
+
+    /// Gets up to `limit` entries of the result change feed after `since_seq`, oldest
+    /// first. A delete is always a tombstone, even if the id was stored again later;
+    /// inserts and updates carry the result as it is now, or none once it is deleted.
+    /// SQLite commits writes one at a time, so a seq is never committed after a
+    /// higher one has been read and a consumer resuming from `next_seq` skips nothing.
+    pub async fn get_result_changes(&self, since_seq: i64, limit: u32) -> Result<ResultChangePage, String> {
+        let rows = sqlx::query(
+            r#"
+            SELECT c.seq AS change_seq, c.result_id AS change_result_id, c.change_type, c.changed_at, t.*
+            FROM result_changes c
+            LEFT JOIN test_results t ON t.id = c.result_id
+            WHERE c.seq > ?
+            ORDER BY c.seq ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(since_seq)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch result changes: {}", e))?;
+
+        let changes = rows
+            .iter()
+            .map(|row| -> Result<ResultChange, sqlx::Error> {
+                let change_type: String = row.try_get("change_type")?;
+                let change_type = ResultChangeType::from_db_str(&change_type).ok_or_else(|| {
+                    sqlx::Error::Decode(format!("Unknown result change type {}", change_type).into())
+                })?;
+                let current_id: Option<String> = row.try_get("id")?;
+                let result = match change_type {
+                    ResultChangeType::Delete => None,
+                    ResultChangeType::Insert | ResultChangeType::Update => {
+                        current_id.map(|_| Self::row_to_test_result(row)).transpose()?
+                    }
+                };
+                Ok(ResultChange {
+                    seq: row.try_get("change_seq")?,
+                    change_type,
+                    result_id: row.try_get("change_result_id")?,
+                    changed_at: row.try_get("changed_at")?,
+                    result,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode result changes: {}", e))?;
+
+        let next_seq = changes.last().map(|change| change.seq).unwrap_or(since_seq);
+        Ok(ResultChangePage { changes, next_seq })
+    }
+
+    /// Counts stored results by where they came from
+    pub async fn count_results_by_source(&self, source: &DataSource) -> Result<u64, String> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM test_results WHERE source = ?")
+            .bind(source.to_string())
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to count results: {}", e))?;
+
+        Ok(count as u64)
+    }
+
+    /// Gets the dashboard counters in a single query
+    pub async fn dashboard_counts(&self) -> Result<DashboardCounts, String> {
+        let today_start = chrono::Local::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .and_then(|midnight| midnight.and_local_timezone(chrono::Local).earliest())
+            .map(|midnight| midnight.with_timezone(&Utc))
+            .unwrap_or_else(Utc::now);
+
+        let row = sqlx::query(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM patients) AS total_patients,
+                (SELECT COUNT(DISTINCT sample_id) FROM test_results) AS total_samples,
+                (SELECT COUNT(DISTINCT sample_id) FROM test_results WHERE created_at >= ?1) AS samples_today,
+                (SELECT COUNT(*) FROM test_results WHERE created_at >= ?1) AS results_today,
+                (SELECT COUNT(*) FROM result_upload_status WHERE status IN (?2, ?3)) AS pending_uploads
+            "#,
+        )
+        .bind(today_start)
+        .bind(UploadStatus::Pending.to_string())
+        .bind(UploadStatus::Uploading.to_string())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to count dashboard totals: {}", e))?;
+
+        let count = |column: &str| -> Result<u64, String> {
+            row.try_get::<i64, _>(column)
+                .map(|value| value as u64)
+                .map_err(|e| format!("Failed to decode {}: {}", column, e))
+        };
+
+        let terminations = self.count_terminations_since(today_start).await?;
+
+        Ok(DashboardCounts {
+            total_patients: count("total_patients")?,
+            total_samples: count("total_samples")?,
+            samples_today: count("samples_today")?,
+            results_today: count("results_today")?,
+            pending_uploads: count("pending_uploads")?,
+            abnormal_disconnections_today: terminations.abnormal(),
+            connection_terminations_today: terminations,
+        })
+    }
+
+    // ------------------------------------------------------------------------
+    // RESULT DETAIL
+    // ------------------------------------------------------------------------
+
+    /// Attaches a note to a result
+    pub async fn add_result_note(&self, note: &ResultNote) -> Result<(), String> {
+        self.retry
+            .run("add_result_note", || {
+                sqlx::query("INSERT INTO result_notes (id, result_id, author, text, created_at) VALUES (?, ?, ?, ?, ?)")
+                    .bind(note.id.as_str())
+                    .bind(note.result_id.as_str())
+                    .bind(note.author.as_deref())
+                    .bind(note.text.as_str())
+                    .bind(note.created_at)
+                    .execute(&self.pool)
+            })
+            .await
+            .map_err(|e| format!("Failed to add note to result {}: {}", note.result_id, e))?;
+
+        Ok(())
+    }
+
+    /// Records where an image of a result is kept
+    pub async fn save_result_image(&self, image: &ResultImage) -> Result<(), String> {
+        self.retry
+            .run("save_result_image", || {
+                sqlx::query(
+                    r#"
+                    INSERT INTO result_images (id, result_id, kind, mime_type, reference, created_at)
+                    VALUES (?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(image.id.as_str())
+                .bind(image.result_id.as_str())
+                .bind(image.kind.as_str())
+                .bind(image.mime_type.as_str())
+                .bind(image.reference.as_str())
+                .bind(image.created_at)
+                .execute(&self.pool)
+            })
+            .await
+            .map_err(|e| format!("Failed to save image for result {}: {}", image.result_id, e))?;
+
+        Ok(())
+    }
+
+    /// Gets the images kept for any result of a sample, in result order
+    pub async fn get_sample_images(&self, sample_id: &str) -> Result<Vec<ResultImage>, String> {
+        sqlx::query(
+            r#"
+            SELECT result_images.* FROM result_images
+            JOIN test_results ON test_results.id = result_images.result_id
+            WHERE test_results.sample_id = ?
+            ORDER BY test_results.sequence_number ASC, result_images.created_at ASC, result_images.rowid ASC
+            "#,
+        )
+        .bind(sample_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch images of sample {}: {}", sample_id, e))?
+        .iter()
+        .map(Self::row_to_result_image)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to decode images of sample {}: {}", sample_id, e))
+    }
+
+    /// Gets a result with its notes, images, uploads and audit trail; `None` for an unknown id
+    pub async fn get_result_detail(&self, result_id: &str) -> Result<Option<ResultDetail>, String> {
+        let Some(row) = sqlx::query("SELECT * FROM test_results WHERE id = ?")
+            .bind(result_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch test result {}: {}", result_id, e))?
+        else {
+            return Ok(None);
+        };
+        let decode_error = |e: sqlx::Error| format!("Failed to decode detail of result {}: {}", result_id, e);
+        let result = Self::row_to_test_result(&row).map_err(decode_error)?;
+        let source: String = row.try_get("source").map_err(decode_error)?;
+        let superseded_by: Option<String> = row.try_get("superseded_by").map_err(decode_error)?;
+
+        let replaces: Option<String> = sqlx::query_scalar("SELECT id FROM test_results WHERE superseded_by = ?")
+            .bind(result_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch the result replaced by {}: {}", result_id, e))?;
+        let replacement_created_at: Option<DateTime<Utc>> = match &superseded_by {
+            Some(replacement_id) => sqlx::query_scalar("SELECT created_at FROM test_results WHERE id = ?")
+                .bind(replacement_id.as_str())
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to fetch replacement result {}: {}", replacement_id, e))?,
+            None => None,
+        };
+
+        let notes = sqlx::query("SELECT * FROM result_notes WHERE result_id = ? ORDER BY created_at ASC, rowid ASC")
+            .bind(result_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch notes of result {}: {}", result_id, e))?
+            .iter()
+            .map(|row| -> Result<ResultNote, sqlx::Error> {
+                Ok(ResultNote {
+                    id: row.try_get("id")?,
+                    result_id: row.try_get("result_id")?,
+                    author: row.try_get("author")?,
+                    text: row.try_get("text")?,
+                    created_at: row.try_get("created_at")?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(decode_error)?;
+
+        let images = sqlx::query("SELECT * FROM result_images WHERE result_id = ? ORDER BY created_at ASC, rowid ASC")
+            .bind(result_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch images of result {}: {}", result_id, e))?
+            .iter()
+            .map(Self::row_to_result_image)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(decode_error)?;
+
+        // Uploads are tracked per result or per sample
+        let uploads = sqlx::query(
+            "SELECT * FROM result_upload_status WHERE result_id IN (?, ?) ORDER BY created_at ASC, rowid ASC",
+        )
+        .bind(result_id)
+        .bind(result.sample_id.as_str())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch uploads of result {}: {}", result_id, e))?
+        .iter()
+        .map(Self::row_to_upload_status)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(decode_error)?;
+
+        let mut audit = vec![ResultAuditEntry {
+            at: result.created_at,
+            action: if replaces.is_some() { ResultAuditAction::Replaced } else { ResultAuditAction::Received },
+            detail: match &replaces {
+                Some(previous_id) => format!("Replaced result {} ({})", previous_id, source),
+                None => format!(
+                    "Received from {} ({})",
+                    result.analyzer_id.as_deref().unwrap_or("unknown analyzer"),
+                    source
+                ),
+            },
+        }];
+        if let (Some(replacement_id), Some(at)) = (&superseded_by, replacement_created_at) {
+            audit.push(ResultAuditEntry {
+                at,
+                action: ResultAuditAction::Superseded,
+                detail: format!("Superseded by result {}", replacement_id),
+            });
+        }
+        audit.extend(notes.iter().map(|note| ResultAuditEntry {
+            at: note.created_at,
+            action: ResultAuditAction::NoteAdded,
+            detail: format!("Note by {}", note.author.as_deref().unwrap_or("unknown")),
+        }));
+        audit.extend(images.iter().map(|image| ResultAuditEntry {
+            at: image.created_at,
+            action: ResultAuditAction::ImageAttached,
+            detail: format!("{} image attached", image.kind),
+        }));
+        for upload in &uploads {
+            audit.push(ResultAuditEntry {
+                at: upload.created_at,
+                action: ResultAuditAction::UploadQueued,
+                detail: format!("Queued for {}", upload.external_system_id),
+            });
+            let outcome = match upload.status {
+                UploadStatus::Uploaded => Some(ResultAuditAction::Uploaded),
+                UploadStatus::Failed => Some(ResultAuditAction::UploadFailed),
+                _ => None,
+            };
+            if let Some(action) = outcome {
+                audit.push(ResultAuditEntry {
+                    at: upload.upload_date.unwrap_or(upload.updated_at),
+                    action,
+                    detail: match upload.response_message.as_deref() {
+                        Some(message) => format!(
+                            "{} after {} retries: {}",
+                            upload.external_system_id, upload.retry_count, message
+                        ),
+                        None => format!("{} after {} retries", upload.external_system_id, upload.retry_count),
+                    },
+                });
+            }
+        }
+        audit.sort_by_key(|entry| entry.at);
+
+        Ok(Some(ResultDetail {
+            result,
+            replaces,
+            superseded_by,
+            notes,
+            images,
+            uploads,
+            audit,
+        }))
+    }
+
+    // ------------------------------------------------------------------------
+    // CONNECTION HISTORY
+    // ------------------------------------------------------------------------
+
+    /// Stores the summary of a closed analyzer connection
+    pub async fn archive_connection(&self, summary: &ConnectionSummary) -> Result<(), String> {
+        self.retry
+            .run("archive_connection", || {
+                sqlx::query(
+                    r#"
+                    INSERT OR REPLACE INTO connection_events (
+                        connection_id, analyzer_id, protocol, remote_address, opened_at, closed_at,
+                        entries, dropped_entries, termination
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(summary.connection_id.as_str())
+                .bind(summary.analyzer_id.as_str())
+                .bind(summary.protocol.as_str())
+                .bind(summary.remote_address.as_str())
+                .bind(summary.opened_at)
+                .bind(summary.closed_at)
+                .bind(summary.entries as i64)
+                .bind(summary.dropped_entries as i64)
+                .bind(summary.termination.map(|termination| termination.as_db_str()))
+                .execute(&self.pool)
+            })
+            .await
+            .map_err(|e| format!("Failed to archive connection {}: {}", summary.connection_id, e))?;
+
+        Ok(())
+    }
+
+    /// Gets an analyzer's closed connections, most recently closed first
+    pub async fn get_connection_history(&self, analyzer_id: &str, limit: u32) -> Result<Vec<ConnectionSummary>, String> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM connection_events
+            WHERE analyzer_id = ?
+            ORDER BY closed_at DESC, rowid DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(analyzer_id)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch connection history of {}: {}", analyzer_id, e))?;
+
+        rows.iter()
+            .map(|row| -> Result<ConnectionSummary, sqlx::Error> {
+                let entries: i64 = row.try_get("entries")?;
+                let dropped_entries: i64 = row.try_get("dropped_entries")?;
+                let termination: Option<String> = row.try_get("termination")?;
+                Ok(ConnectionSummary {
+                    connection_id: row.try_get("connection_id")?,
+                    analyzer_id: row.try_get("analyzer_id")?,
+                    protocol: row.try_get("protocol")?,
+                    remote_address: row.try_get("remote_address")?,
+                    opened_at: row.try_get("opened_at")?,
+                    closed_at: row.try_get("closed_at")?,
+                    entries: entries as usize,
+                    dropped_entries: dropped_entries as u64,
+                    termination: termination.as_deref().and_then(ConnectionTermination::from_db_str),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode connection history of {}: {}", analyzer_id, e))
+    }
+
+    /// Number of archived connections
+    pub async fn count_archived_connections(&self) -> Result<u64, String> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM connection_events")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to count archived connections: {}", e))?;
+
+        Ok(count as u64)
+    }
+
+    /// Archived connections closed since `since`, by how they ended
+    pub async fn count_terminations_since(&self, since: DateTime<Utc>) -> Result<TerminationCounts, String> {
+        let rows: Vec<(Option<String>, i64)> = sqlx::query_as(
+            r#"
+            SELECT termination, COUNT(*) FROM connection_events
+            WHERE closed_at >= ?
+            GROUP BY termination
+            "#,
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to count connection terminations: {}", e))?;
+
+        let mut counts = TerminationCounts::default();
+        for (termination, count) in rows {
+            // Connections archived before terminations were recorded are left out
+            if let Some(termination) = termination.as_deref().and_then(ConnectionTermination::from_db_str) {
+                counts.add(termination, count as u64);
+            }
+        }
+        Ok(counts)
+    }
+
+    // ------------------------------------------------------------------------
+    // RAW MESSAGES
+    // ------------------------------------------------------------------------
+
+    /// Stores a raw message together with its processing timeline
+    pub async fn save_raw_message(&self, message: &RawMessage) -> Result<(), String> {
+        let timeline = serde_json::to_string(&message.timeline)
+            .map_err(|e| format!("Failed to serialize processing timeline: {}", e))?;
+
+        self.retry
+            .run("save_raw_message", || {
+                sqlx::query(
+                    r#"
+                    INSERT INTO raw_messages (
+                        id, analyzer_id, protocol, message_type, raw_data, timeline, upload_id,
+                        received_at, created_at, updated_at
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(message.id.as_str())
+                .bind(message.analyzer_id.as_str())
+                .bind(message.protocol.as_str())
+                .bind(message.message_type.as_str())
+                .bind(message.raw_data.as_str())
+                .bind(timeline.as_str())
+                .bind(message.upload_id.as_deref())
+                .bind(message.received_at)
+                .bind(message.created_at)
+                .bind(message.updated_at)
+                .execute(&self.pool)
+            })
+            .await
+            .map_err(|e| format!("Failed to save raw message {}: {}", message.id, e))?;
+
+        Ok(())
+    }
+
+    /// Replaces the stored timeline and upload link of a raw message
+    pub async fn update_raw_message_timeline(
+        &self,
+        message_id: &str,
+        timeline: &ProcessingTimeline,
+        upload_id: Option<&str>,
+    ) -> Result<(), String> {
+        let timeline = serde_json::to_string(timeline)
+            .map_err(|e| format!("Failed to serialize processing timeline: {}", e))?;
+
+        sqlx::query(
+            r#"
+            UPDATE raw_messages
+            SET timeline = ?, upload_id = COALESCE(?, upload_id), updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(timeline)
+        .bind(upload_id)
+        .bind(Utc::now())
+        .bind(message_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to update timeline for raw message {}: {}", message_id, e))?;
+
+        Ok(())
+    }
+
+    /// Finds a raw message by id
+    pub async fn get_raw_message(&self, message_id: &str) -> Result<Option<RawMessage>, String> {
+        let row = sqlx::query("SELECT * FROM raw_messages WHERE id = ?")
+            .bind(message_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch raw message {}: {}", message_id, e))?;
+
+        row.as_ref()
+            .map(Self::row_to_raw_message)
+            .transpose()
+            .map_err(|e| format!("Failed to decode raw message {}: {}", message_id, e))
+    }
+
+    /// Gets the processing timeline of a raw message, including HIS delivery once
+    /// the linked upload has completed
+    pub async fn get_message_timeline(&self, message_id: &str) -> Result<Vec<TimelineStageView>, String> {
+        let message = self
+            .get_raw_message(message_id)
+            .await?
+            .ok_or_else(|| format!("Raw message {} not found", message_id))?;
+
+        let mut timeline = message.timeline;
+        if let Some(upload_id) = message.upload_id.as_deref() {
+            if let Some(upload) = self.get_upload(upload_id).await? {
+                match (upload.status, upload.upload_date) {
+                    (UploadStatus::Uploaded, Some(uploaded_at)) => {
+                        timeline.mark_at(ProcessingStage::Uploaded, uploaded_at)
+                    }
+                    (UploadStatus::Failed, _) => timeline.warn(
+                        ProcessingStage::Uploaded,
+                        upload
+                            .response_message
+                            .unwrap_or_else(|| "HIS upload failed".to_string()),
+                    ),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(timeline.to_view())
+    }
+
+    /// Latency histograms of the messages received since `since`. HIS delivery
+    /// comes from the linked upload, as in `get_message_timeline`.
+    pub async fn get_latency_stats(&self, since: DateTime<Utc>) -> Result<LatencyStats, String> {
+        let rows: Vec<(String, Option<DateTime<Utc>>)> = sqlx::query_as(
+            r#"
+            SELECT m.timeline, u.upload_date
+            FROM raw_messages m
+            LEFT JOIN result_upload_status u ON u.id = m.upload_id
+            WHERE m.received_at >= ?
+            "#,
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch message timelines: {}", e))?;
+
+        let timelines = rows
+            .into_iter()
+            .map(|(timeline, uploaded_at)| {
+                let mut timeline: ProcessingTimeline = serde_json::from_str(&timeline)
+                    .map_err(|e| format!("Failed to decode processing timeline: {}", e))?;
+                if let Some(uploaded_at) = uploaded_at {
+                    timeline.mark_at(ProcessingStage::Uploaded, uploaded_at);
+                }
+                Ok(timeline)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(LatencyStats::from_timelines(since, &timelines))
+    }
+
+    // ------------------------------------------------------------------------
+    // ANALYZER EVENTS
+    // ------------------------------------------------------------------------
+
+    /// Stores an error/event reported by an analyzer service
+    pub async fn record_analyzer_event(
+        &self,
+        analyzer_id: &str,
+        event_type: &AnalyzerEventType,
+        message: &str,
+    ) -> Result<(), String> {
+        let event_id = Uuid::new_v4().to_string();
+        let created_at = Utc::now();
+        self.retry
+            .run("record_analyzer_event", || {
+                sqlx::query(
+                    r#"
+                    INSERT INTO analyzer_events (id, analyzer_id, event_type, message, created_at)
+                    VALUES (?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(event_id.as_str())
+                .bind(analyzer_id)
+                .bind(event_type.as_db_str())
+                .bind(message)
+                .bind(created_at)
+                .execute(&self.pool)
+            })
+            .await
+            .map_err(|e| format!("Failed to record event for analyzer {}: {}", analyzer_id, e))?;
+
+        Ok(())
+    }
+
+    /// Summarizes an analyzer's stored events between `from` and `to`, grouped by type
+    pub async fn analyzer_event_summary(
+        &self,
+        analyzer_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<EventSummary, String> {
+        // SQLite takes the bare `message` column from the row holding MAX(created_at)
+        let rows = sqlx::query(
+            r#"
+            SELECT event_type, COUNT(*) AS count, MAX(created_at) AS last_seen, message AS last_message
+            FROM analyzer_events
+            WHERE analyzer_id = ? AND created_at >= ? AND created_at <= ?
+            GROUP BY event_type
+            ORDER BY count DESC, event_type
+            "#,
+        )
+        .bind(analyzer_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to summarize events for analyzer {}: {}", analyzer_id, e))?;
+
+        let by_type = rows
+            .iter()
+            .map(|row| -> Result<EventTypeCount, sqlx::Error> {
+                let event_type: String = row.try_get("event_type")?;
+                let count: i64 = row.try_get("count")?;
+                Ok(EventTypeCount {
+                    event_type: AnalyzerEventType::from_db_str(&event_type),
+                    count: count as u64,
+                    last_message: row.try_get("last_message")?,
+                    last_seen: row.try_get("last_seen")?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode events for analyzer {}: {}", analyzer_id, e))?;
+
+        Ok(EventSummary {
+            analyzer_id: analyzer_id.to_string(),
+            from,
+            to,
+            total: by_type.iter().map(|c| c.count).sum(),
+            by_type,
+        })
+    }
+
+    // ------------------------------------------------------------------------
+    // CONFORMANCE WARNINGS
+    // ------------------------------------------------------------------------
+
+    /// Stores the spec deviations found in one message (`message_ref` is its control/transmission ID)
+    pub async fn save_conformance_warnings(
+        &self,
+        analyzer_id: &str,
+        protocol: &str,
+        message_ref: Option<&str>,
+        warnings: &[ConformanceWarning],
+    ) -> Result<(), String> {
+        let created_at = Utc::now();
+        for warning in warnings {
+            let warning_id = Uuid::new_v4().to_string();
+            self.retry
+                .run("save_conformance_warning", || {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO conformance_warnings (
+                            id, analyzer_id, protocol, rule_id, description, detail, message_ref, created_at
+                        )
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                        "#,
+                    )
+                    .bind(warning_id.as_str())
+                    .bind(analyzer_id)
+                    .bind(protocol)
+                    .bind(warning.rule_id.as_str())
+                    .bind(warning.description.as_str())
+                    .bind(warning.detail.as_str())
+                    .bind(message_ref)
+                    .bind(created_at)
+                    .execute(&self.pool)
+                })
+                .await
+                .map_err(|e| {
+                    format!(
+                        "Failed to save conformance warning {} for analyzer {}: {}",
+                        warning.rule_id, analyzer_id, e
+                    )
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Summarizes an analyzer's conformance warnings between `from` and `to`, grouped by rule
+    pub async fn get_conformance_report(
+        &self,
+        analyzer_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<ConformanceReport, String> {
+        // Each rule's latest warning carries the counts of the whole group
+        let rows = sqlx::query(
+            r#"
+            SELECT rule_id, description, protocol, count, first_seen, last_seen, detail AS last_detail
+            FROM (
+                SELECT rule_id, description, protocol, detail,
+                       COUNT(*) OVER (PARTITION BY rule_id) AS count,
+                       MIN(created_at) OVER (PARTITION BY rule_id) AS first_seen,
+                       MAX(created_at) OVER (PARTITION BY rule_id) AS last_seen,
+                       ROW_NUMBER() OVER (PARTITION BY rule_id ORDER BY created_at DESC, rowid DESC) AS recency
+                FROM conformance_warnings
+                WHERE analyzer_id = ? AND created_at >= ? AND created_at <= ?
+            )
+            WHERE recency = 1
+            ORDER BY count DESC, rule_id
+            "#,
+        )
+        .bind(analyzer_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to summarize conformance for analyzer {}: {}", analyzer_id, e))?;
+
+        let by_rule = rows
+            .iter()
+            .map(|row| -> Result<ConformanceRuleCount, sqlx::Error> {
+                let count: i64 = row.try_get("count")?;
+                Ok(ConformanceRuleCount {
+                    rule_id: row.try_get("rule_id")?,
+                    description: row.try_get("description")?,
+                    protocol: row.try_get("protocol")?,
+                    count: count as u64,
+                    first_seen: row.try_get("first_seen")?,
+                    last_seen: row.try_get("last_seen")?,
+                    last_detail: row.try_get("last_detail")?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode conformance warnings for analyzer {}: {}", analyzer_id, e))?;
+
+        Ok(ConformanceReport {
+            analyzer_id: analyzer_id.to_string(),
+            from,
+            to,
+            total: by_rule.iter().map(|c| c.count).sum(),
+            by_rule,
+        })
+    }
+
+    // ------------------------------------------------------------------------
+    // FIRMWARE CHANGES
+    // ------------------------------------------------------------------------
+
+    /// Records a change in the identity an analyzer declares
+    pub async fn save_firmware_change(&self, change: &FirmwareChange) -> Result<(), String> {
+        let previous = serde_json::to_string(&change.previous)
+            .map_err(|e| format!("Failed to serialize previous identity: {}", e))?;
+        let current = serde_json::to_string(&change.current)
+            .map_err(|e| format!("Failed to serialize current identity: {}", e))?;
+
+        self.retry
+            .run("save_firmware_change", || {
+                sqlx::query(
+                    r#"
+                    INSERT INTO firmware_changes (
+                        id, analyzer_id, protocol, previous_identity, current_identity,
+                        previous_since, changed_at, conformance_report_until
+                    )
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(change.id.as_str())
+                .bind(change.analyzer_id.as_str())
+                .bind(change.current.protocol.as_str())
+                .bind(previous.as_str())
+                .bind(current.as_str())
+                .bind(change.previous_since)
+                .bind(change.changed_at)
+                .bind(change.conformance_report_until)
+                .execute(&self.pool)
+            })
+            .await
+            .map_err(|e| format!("Failed to save firmware change for analyzer {}: {}", change.analyzer_id, e))?;
+
+        Ok(())
+    }
+
+    /// Identity changes of an analyzer, oldest first
+    pub async fn get_firmware_history(&self, analyzer_id: &str) -> Result<Vec<FirmwareChange>, String> {
+        let rows = sqlx::query("SELECT * FROM firmware_changes WHERE analyzer_id = ? ORDER BY changed_at ASC, rowid ASC")
+            .bind(analyzer_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch firmware history for analyzer {}: {}", analyzer_id, e))?;
+
+        rows.iter()
+            .map(|row| -> Result<FirmwareChange, String> {
+                let decode = |column: &str| -> Result<_, String> {
+                    let json: String = row.try_get(column).map_err(|e| e.to_string())?;
+                    serde_json::from_str(&json).map_err(|e| format!("Invalid {}: {}", column, e))
+                };
+                Ok(FirmwareChange {
+                    id: row.try_get("id").map_err(|e| e.to_string())?,
+                    analyzer_id: row.try_get("analyzer_id").map_err(|e| e.to_string())?,
+                    previous: decode("previous_identity")?,
+                    current: decode("current_identity")?,
+                    previous_since: row.try_get("previous_since").map_err(|e| e.to_string())?,
+                    changed_at: row.try_get("changed_at").map_err(|e| e.to_string())?,
+                    conformance_report_until: row.try_get("conformance_report_until").map_err(|e| e.to_string())?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode firmware history for analyzer {}: {}", analyzer_id, e))
+    }
+
+    // ------------------------------------------------------------------------
+    // AUTO-START
+    // ------------------------------------------------------------------------
+
+    /// Adds an analyzer to, or removes it from, the set started with the LIS
+    pub async fn set_autostart(&self, analyzer_id: &str, enabled: bool) -> Result<(), String> {
+        self.retry
+            .run("set_autostart", || {
+                sqlx::query(
+                    r#"
+                    INSERT INTO analyzer_autostart (analyzer_id, enabled, updated_at)
+                    VALUES (?, ?, ?)
+                    ON CONFLICT(analyzer_id) DO UPDATE SET
+                        enabled = excluded.enabled,
+                        updated_at = excluded.updated_at
+                    "#,
+                )
+                .bind(analyzer_id)
+                .bind(enabled)
+                .bind(Utc::now())
+                .execute(&self.pool)
+            })
+            .await
+            .map_err(|e| format!("Failed to set autostart for analyzer {}: {}", analyzer_id, e))?;
+
+        Ok(())
+    }
+
+    /// Auto-start entries by analyzer id; analyzers never configured have none
+    pub async fn get_autostart_settings(&self) -> Result<HashMap<String, bool>, String> {
+        let rows = sqlx::query("SELECT analyzer_id, enabled FROM analyzer_autostart")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch autostart settings: {}", e))?;
+
+        rows.iter()
+            .map(|row| -> Result<(String, bool), sqlx::Error> {
+                Ok((row.try_get("analyzer_id")?, row.try_get("enabled")?))
+            })
+            .collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|e| format!("Failed to decode autostart settings: {}", e))
+    }
+
+    // ------------------------------------------------------------------------
+    // ORDER DISPATCH QUEUE
+    // ------------------------------------------------------------------------
+
+    /// Queues a worklist download of `patient_id` for an analyzer as PENDING
+    pub async fn enqueue_order_dispatch(
+        &self,
+        analyzer_id: &str,
+        patient_id: &str,
+        order: &TestOrder,
+    ) -> Result<OrderDispatch, String> {
+        let now = Utc::now();
+        let payload = serde_json::to_string(order)
+            .map_err(|e| format!("Failed to serialize order {}: {}", order.id, e))?;
+        let dispatch = OrderDispatch {
+            id: Uuid::new_v4().to_string(),
+            order_id: order.id.clone(),
+            analyzer_id: analyzer_id.to_string(),
+            patient_id: Some(patient_id.to_string()),
+            order: order.clone(),
+            status: DispatchStatus::Pending,
+            attempts: 0,
+            last_error: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO order_dispatch_queue (
+                id, order_id, analyzer_id, patient_id, priority, payload, status, attempts, last_error,
+                created_at, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(dispatch.id.as_str())
+        .bind(dispatch.order_id.as_str())
+        .bind(analyzer_id)
+        .bind(patient_id)
+        .bind(order.priority.dispatch_rank())
+        .bind(payload)
+        .bind(dispatch.status.to_string())
+        .bind(0i64)
+        .bind(None::<String>)
+        .bind(now)
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to queue order {}: {}", order.id, e))?;
+
+        Ok(dispatch)
+    }
+
+    /// Gets PENDING orders for an analyzer, STAT first, then oldest first
+    pub async fn get_pending_order_dispatches(
+        &self,
+        analyzer_id: &str,
+        limit: u32,
+    ) -> Result<Vec<OrderDispatch>, String> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM order_dispatch_queue
+            WHERE analyzer_id = ? AND status = ?
+            ORDER BY priority ASC, created_at ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(analyzer_id)
+        .bind(DispatchStatus::Pending.to_string())
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch pending orders: {}", e))?;
+
+        rows.iter()
+            .map(Self::row_to_order_dispatch)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode pending orders: {}", e))
+    }
+
+    /// Gets PENDING orders for an analyzer whose specimen id lies in `specimen_range`
+    /// (inclusive), or every pending order when no range is given; STAT first
+    pub async fn get_pending_orders_for_specimens(
+        &self,
+        analyzer_id: &str,
+        specimen_range: Option<(&str, &str)>,
+    ) -> Result<Vec<OrderDispatch>, String> {
+        let (first, last) = specimen_range.unzip();
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM order_dispatch_queue
+            WHERE analyzer_id = ? AND status = ?
+              AND (? IS NULL OR json_extract(payload, '$.specimen_id') BETWEEN ? AND ?)
+            ORDER BY priority ASC, created_at ASC
+            "#,
+        )
+        .bind(analyzer_id)
+        .bind(DispatchStatus::Pending.to_string())
+        .bind(first)
+        .bind(first)
+        .bind(last)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch pending orders for query: {}", e))?;
+
+        rows.iter()
+            .map(Self::row_to_order_dispatch)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode pending orders: {}", e))
+    }
+
+    /// Counts orders sent to an analyzer that it has not acknowledged yet
+    pub async fn count_in_flight_order_dispatches(&self, analyzer_id: &str) -> Result<u32, String> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM order_dispatch_queue WHERE analyzer_id = ? AND status = ?",
+        )
+        .bind(analyzer_id)
+        .bind(DispatchStatus::Sent.to_string())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to count in-flight orders: {}", e))?;
+
+        Ok(count as u32)
+    }
+
+    /// Moves a queue entry to a new status. Marking an entry SENT counts an attempt.
+    pub async fn update_order_dispatch_status(
+        &self,
+        dispatch_id: &str,
+        status: DispatchStatus,
+        last_error: Option<&str>,
+    ) -> Result<(), String> {
+        let attempt = if status == DispatchStatus::Sent { 1i64 } else { 0i64 };
+
+        sqlx::query(
+            r#"
+            UPDATE order_dispatch_queue
+            SET status = ?, attempts = attempts + ?, last_error = COALESCE(?, last_error), updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(status.to_string())
+        .bind(attempt)
+        .bind(last_error)
+        .bind(Utc::now())
+        .bind(dispatch_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to update order dispatch {}: {}", dispatch_id, e))?;
+
+        Ok(())
+    }
+
+    /// Marks the in-flight entry of an order as accepted by the analyzer.
+    /// Returns false when the order had nothing in flight.
+    pub async fn acknowledge_order_dispatch(&self, order_id: &str) -> Result<bool, String> {
+        let result = sqlx::query(
+            "UPDATE order_dispatch_queue SET status = ?, updated_at = ? WHERE order_id = ? AND status = ?",
+        )
+        .bind(DispatchStatus::Acked.to_string())
+        .bind(Utc::now())
+        .bind(order_id)
+        .bind(DispatchStatus::Sent.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to acknowledge order {}: {}", order_id, e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Gets the newest queue entry of an order
+    pub async fn get_latest_order_dispatch(&self, order_id: &str) -> Result<Option<OrderDispatch>, String> {
+        let row = sqlx::query(
+            "SELECT * FROM order_dispatch_queue WHERE order_id = ? ORDER BY created_at DESC LIMIT 1",
+        )
+        .bind(order_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch order {}: {}", order_id, e))?;
+
+        row.as_ref()
+            .map(Self::row_to_order_dispatch)
+            .transpose()
+            .map_err(|e| format!("Failed to decode order {}: {}", order_id, e))
+    }
+
+    /// Lists all queued orders with their dispatch state, newest first
+    pub async fn list_order_dispatches(&self) -> Result<Vec<OrderDispatch>, String> {
+        let rows = sqlx::query("SELECT * FROM order_dispatch_queue ORDER BY created_at DESC")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to list orders: {}", e))?;
+
+        rows.iter()
+            .map(Self::row_to_order_dispatch)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode orders: {}", e))
+    }
+
+    // ------------------------------------------------------------------------
+    // REMOTE ADDRESSES
+    // ------------------------------------------------------------------------
+
+    /// Gets a known remote address of an analyzer
+    pub async fn get_remote_address(&self, analyzer_id: &str, ip_address: &str) -> Result<Option<RemoteAddress>, String> {
+        let row = sqlx::query("SELECT * FROM analyzer_remote_addresses WHERE analyzer_id = ? AND ip_address = ?")
+            .bind(analyzer_id)
+            .bind(ip_address)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch remote address {}: {}", ip_address, e))?;
+
+        row.map(|row| Self::row_to_remote_address(&row))
+            .transpose()
+            .map_err(|e| format!("Failed to decode remote address {}: {}", ip_address, e))
+    }
+
+    /// Lists the remote addresses an analyzer has connected from, oldest first
+    pub async fn list_remote_addresses(&self, analyzer_id: &str) -> Result<Vec<RemoteAddress>, String> {
+        let rows = sqlx::query("SELECT * FROM analyzer_remote_addresses WHERE analyzer_id = ? ORDER BY first_seen")
+            .bind(analyzer_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to list remote addresses for analyzer {}: {}", analyzer_id, e))?;
+
+        rows.iter()
+            .map(Self::row_to_remote_address)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode remote addresses for analyzer {}: {}", analyzer_id, e))
+    }
+
+    /// Records a remote address seen for the first time
+    pub async fn save_remote_address(&self, address: &RemoteAddress) -> Result<(), String> {
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO analyzer_remote_addresses (analyzer_id, ip_address, approved, first_seen, last_seen)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&address.analyzer_id)
+        .bind(&address.ip_address)
+        .bind(address.approved)
+        .bind(address.first_seen)
+        .bind(address.last_seen)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to save remote address {}: {}", address.ip_address, e))?;
+
+        Ok(())
+    }
+
+    /// Updates when a known remote address was last seen
+    pub async fn touch_remote_address(&self, analyzer_id: &str, ip_address: &str) -> Result<(), String> {
+        sqlx::query("UPDATE analyzer_remote_addresses SET last_seen = ? WHERE analyzer_id = ? AND ip_address = ?")
+            .bind(Utc::now())
+            .bind(analyzer_id)
+            .bind(ip_address)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to update remote address {}: {}", ip_address, e))?;
+
+        Ok(())
+    }
+
+    /// Marks a remote address as approved. Returns false if the address was never seen.
+    pub async fn approve_remote_address(&self, analyzer_id: &str, ip_address: &str) -> Result<bool, String> {
+        let result = sqlx::query("UPDATE analyzer_remote_addresses SET approved = 1 WHERE analyzer_id = ? AND ip_address = ?")
+            .bind(analyzer_id)
+            .bind(ip_address)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to approve remote address {}: {}", ip_address, e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Archives a message received from an address awaiting approval
+    pub async fn hold_message(&self, message: &HeldMessage) -> Result<(), String> {
+        self.retry
+            .run("hold_message", || {
+                sqlx::query(
+                    r#"
+                    INSERT INTO held_messages (id, analyzer_id, ip_address, protocol, payload, received_at)
+                    VALUES (?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&message.id)
+                .bind(&message.analyzer_id)
+                .bind(&message.ip_address)
+                .bind(&message.protocol)
+                .bind(&message.payload)
+                .bind(message.received_at)
+                .execute(&self.pool)
+            })
+            .await
+            .map_err(|e| format!("Failed to hold message from {}: {}", message.ip_address, e))?;
+
+        Ok(())
+    }
+
+    /// Removes and returns the messages held for an address, in the order they arrived
+    pub async fn take_held_messages(&self, analyzer_id: &str, ip_address: &str) -> Result<Vec<HeldMessage>, String> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        let rows = sqlx::query("SELECT * FROM held_messages WHERE analyzer_id = ? AND ip_address = ? ORDER BY received_at, rowid")
+            .bind(analyzer_id)
+            .bind(ip_address)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to fetch held messages from {}: {}", ip_address, e))?;
+
+        sqlx::query("DELETE FROM held_messages WHERE analyzer_id = ? AND ip_address = ?")
+            .bind(analyzer_id)
+            .bind(ip_address)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to clear held messages from {}: {}", ip_address, e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Failed to commit held messages: {}", e))?;
+
+        rows.iter()
+            .map(|row| -> Result<HeldMessage, sqlx::Error> {
+                Ok(HeldMessage {
+                    id: row.try_get("id")?,
+                    analyzer_id: row.try_get("analyzer_id")?,
+                    ip_address: row.try_get("ip_address")?,
+                    protocol: row.try_get("protocol")?,
+                    payload: row.try_get("payload")?,
+                    received_at: row.try_get("received_at")?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode held messages from {}: {}", ip_address, e))
+    }
+
+    // ------------------------------------------------------------------------
+    // SAMPLE ARRIVALS
+    // ------------------------------------------------------------------------
+
+    /// Gets the latest arrival sequence applied for a sample of an analyzer
+    pub async fn get_sample_arrival(&self, analyzer_id: &str, sample_id: &str) -> Result<Option<u64>, String> {
+        let sequence: Option<i64> =
+            sqlx::query_scalar("SELECT arrival_sequence FROM sample_arrivals WHERE analyzer_id = ? AND sample_id = ?")
+                .bind(analyzer_id)
+                .bind(sample_id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to fetch arrival of sample {}: {}", sample_id, e))?;
+
+        Ok(sequence.map(|sequence| sequence as u64))
+    }
+
+    /// Records the arrival sequence applied for a sample; an older sequence never
+    /// replaces a newer one
+    pub async fn record_sample_arrival(&self, analyzer_id: &str, sample_id: &str, arrival_sequence: u64) -> Result<(), String> {
+        sqlx::query(
+            r#"
+            INSERT INTO sample_arrivals (analyzer_id, sample_id, arrival_sequence, updated_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (analyzer_id, sample_id) DO UPDATE SET
+                arrival_sequence = MAX(arrival_sequence, excluded.arrival_sequence),
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(analyzer_id)
+        .bind(sample_id)
+        .bind(arrival_sequence as i64)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to record arrival of sample {}: {}", sample_id, e))?;
+
+        Ok(())
+    }
+
+    /// Gets the highest arrival sequence ever applied, 0 when none was
+    pub async fn get_latest_arrival_sequence(&self) -> Result<u64, String> {
+        let sequence: Option<i64> = sqlx::query_scalar("SELECT MAX(arrival_sequence) FROM sample_arrivals")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch latest arrival sequence: {}", e))?;
+
+        Ok(sequence.unwrap_or(0) as u64)
+    }
+
+    // ------------------------------------------------------------------------
+    // DUPLICATE CANDIDATES
+    // ------------------------------------------------------------------------
+
+    /// Records a suggested duplicate pair, refreshing the score of a pair still pending.
+    /// Accepted and dismissed pairs are left untouched. Returns true when a new pair was added.
+    pub async fn upsert_duplicate_candidate(&self, candidate: &DuplicateCandidate) -> Result<bool, String> {
+        let reasons = serde_json::to_string(&candidate.reasons)
+            .map_err(|e| format!("Failed to serialize duplicate reasons: {}", e))?;
+
+        let existing: Option<String> = sqlx::query_scalar(
+            "SELECT status FROM duplicate_candidates WHERE patient_id_a = ? AND patient_id_b = ?",
+        )
+        .bind(&candidate.patient_id_a)
+        .bind(&candidate.patient_id_b)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch duplicate candidate: {}", e))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO duplicate_candidates (id, patient_id_a, patient_id_b, score, reasons, status, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (patient_id_a, patient_id_b) DO UPDATE SET
+                score = excluded.score,
+                reasons = excluded.reasons,
+                updated_at = excluded.updated_at
+            WHERE duplicate_candidates.status = 'PENDING'
+            "#,
+        )
+        .bind(&candidate.id)
+        .bind(&candidate.patient_id_a)
+        .bind(&candidate.patient_id_b)
+        .bind(candidate.score)
+        .bind(reasons)
+        .bind(candidate.status.to_string())
+        .bind(candidate.created_at)
+        .bind(candidate.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            format!(
+                "Failed to save duplicate candidate {}/{}: {}",
+                candidate.patient_id_a, candidate.patient_id_b, e
+            )
+        })?;
+
+        Ok(existing.is_none())
+    }
+
+    /// Gets a duplicate candidate by id
+    pub async fn get_duplicate_candidate(&self, candidate_id: &str) -> Result<Option<DuplicateCandidate>, String> {
+        let row = sqlx::query("SELECT * FROM duplicate_candidates WHERE id = ?")
+            .bind(candidate_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch duplicate candidate {}: {}", candidate_id, e))?;
+
+        row.map(|row| Self::row_to_duplicate_candidate(&row))
+            .transpose()
+            .map_err(|e| format!("Failed to decode duplicate candidate {}: {}", candidate_id, e))
+    }
+
+    /// Lists pending duplicate candidates scoring at least `min_score`, best match first
+    pub async fn get_pending_duplicate_candidates(&self, min_score: f64) -> Result<Vec<DuplicateCandidate>, String> {
+        let rows = sqlx::query(
+            "SELECT * FROM duplicate_candidates WHERE status = ? AND score >= ? ORDER BY score DESC, created_at",
+        )
+        .bind(CandidateStatus::Pending.to_string())
+        .bind(min_score)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to list duplicate candidates: {}", e))?;
+
+        rows.iter()
+            .map(Self::row_to_duplicate_candidate)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode duplicate candidates: {}", e))
+    }
+
+    /// Sets the review state of a duplicate candidate. Returns false if it does not exist.
+    pub async fn update_duplicate_candidate_status(
+        &self,
+        candidate_id: &str,
+        status: CandidateStatus,
+    ) -> Result<bool, String> {
+        let result = sqlx::query("UPDATE duplicate_candidates SET status = ?, updated_at = ? WHERE id = ?")
+            .bind(status.to_string())
+            .bind(Utc::now())
+            .bind(candidate_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to update duplicate candidate {}: {}", candidate_id, e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Drops pending candidates that involve a patient, e.g. after it was merged away
+    pub async fn delete_pending_duplicate_candidates_for(&self, patient_id: &str) -> Result<u64, String> {
+        let result = sqlx::query(
+            "DELETE FROM duplicate_candidates WHERE status = ? AND (patient_id_a = ? OR patient_id_b = ?)",
+        )
+        .bind(CandidateStatus::Pending.to_string())
+        .bind(patient_id)
+        .bind(patient_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to clear duplicate candidates for patient {}: {}", patient_id, e))?;
+
+        Ok(result.rows_affected())
+    }
+
+    // ------------------------------------------------------------------------
+    // DEMOGRAPHICS HOLDS
+    // ------------------------------------------------------------------------
+
+    /// Stores results held until the patient's mandatory demographics are complete
+    pub async fn save_demographics_hold(&self, hold: &DemographicsHold) -> Result<(), String> {
+        let missing_fields = serde_json::to_string(&hold.missing_fields)
+            .map_err(|e| format!("Failed to serialize missing demographics: {}", e))?;
+
+        self.retry
+            .run("save_demographics_hold", || {
+                sqlx::query(
+                    r#"
+                    INSERT INTO demographics_holds (
+                        id, patient_id, sample_id, analyzer_id, missing_fields, payload, priority, status, upload_id,
+                        created_at, released_at
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&hold.id)
+                .bind(hold.patient_id.as_deref())
+                .bind(&hold.sample_id)
+                .bind(&hold.analyzer_id)
+                .bind(missing_fields.as_str())
+                .bind(&hold.payload)
+                .bind(hold.priority.rank())
+                .bind(hold.status.to_string())
+                .bind(hold.upload_id.as_deref())
+                .bind(hold.created_at)
+                .bind(hold.released_at)
+                .execute(&self.pool)
+            })
+            .await
+            .map_err(|e| format!("Failed to save demographics hold for sample {}: {}", hold.sample_id, e))?;
+
+        Ok(())
+    }
+
+    /// Gets all results waiting for demographics, oldest first
+    pub async fn get_results_pending_demographics(&self) -> Result<Vec<DemographicsHold>, String> {
+        let rows = sqlx::query("SELECT * FROM demographics_holds WHERE status = ? ORDER BY created_at, rowid")
+            .bind(HoldStatus::PendingDemographics.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch results pending demographics: {}", e))?;
+
+        rows.iter()
+            .map(Self::row_to_demographics_hold)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode demographics holds: {}", e))
+    }
+
+    /// Gets a patient's results waiting for demographics, oldest first
+    pub async fn get_patient_demographics_holds(&self, patient_id: &str) -> Result<Vec<DemographicsHold>, String> {
+        let rows = sqlx::query(
+            "SELECT * FROM demographics_holds WHERE patient_id = ? AND status = ? ORDER BY created_at, rowid",
+        )
+        .bind(patient_id)
+        .bind(HoldStatus::PendingDemographics.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch demographics holds for patient {}: {}", patient_id, e))?;
+
+        rows.iter()
+            .map(Self::row_to_demographics_hold)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode demographics holds: {}", e))
+    }
+
+    /// Gets a held result by id, whatever its status
+    pub async fn get_demographics_hold(&self, hold_id: &str) -> Result<Option<DemographicsHold>, String> {
+        let row = sqlx::query("SELECT * FROM demographics_holds WHERE id = ?")
+            .bind(hold_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch demographics hold {}: {}", hold_id, e))?;
+
+        row.as_ref()
+            .map(Self::row_to_demographics_hold)
+            .transpose()
+            .map_err(|e| format!("Failed to decode demographics hold {}: {}", hold_id, e))
+    }
+
+    /// Attaches a sample's pending holds that were made before its patient was known
+    /// to the patient. Returns how many were attached.
+    pub async fn assign_sample_demographics_holds(&self, sample_id: &str, patient_id: &str) -> Result<u64, String> {
+        let result = sqlx::query(
+            "UPDATE demographics_holds SET patient_id = ? WHERE sample_id = ? AND patient_id IS NULL AND status = ?",
+        )
+        .bind(patient_id)
+        .bind(sample_id)
+        .bind(HoldStatus::PendingDemographics.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to assign demographics holds of sample {}: {}", sample_id, e))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Marks a held result released. Returns false if it was already released.
+    pub async fn release_demographics_hold(&self, hold_id: &str, upload_id: Option<&str>) -> Result<bool, String> {
+        let result = sqlx::query(
+            "UPDATE demographics_holds SET status = ?, upload_id = ?, released_at = ? WHERE id = ? AND status = ?",
+        )
+        .bind(HoldStatus::Released.to_string())
+        .bind(upload_id)
+        .bind(Utc::now())
+        .bind(hold_id)
+        .bind(HoldStatus::PendingDemographics.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to release demographics hold {}: {}", hold_id, e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // ------------------------------------------------------------------------
+    // VERIFICATION RULES
+    // ------------------------------------------------------------------------
+
+    /// Stores a rule as the next version of its id and returns it as stored
+    pub async fn save_verification_rule(&self, rule: &VerificationRule) -> Result<VerificationRule, String> {
+        let definition = serde_json::json!({
+            "scope": rule.scope,
+            "condition": rule.condition,
+            "decision": rule.decision,
+        })
+        .to_string();
+
+        let stored = self
+            .retry
+            .run("save_verification_rule", || {
+                let definition = definition.as_str();
+                async move {
+                    let mut tx = self.pool.begin().await?;
+                    let latest: Option<i64> =
+                        sqlx::query_scalar("SELECT MAX(version) FROM verification_rules WHERE id = ?")
+                            .bind(&rule.id)
+                            .fetch_one(&mut *tx)
+                            .await?;
+
+                    let stored = VerificationRule {
+                        version: latest.unwrap_or(0) as u32 + 1,
+                        created_at: Some(Utc::now()),
+                        ..rule.clone()
+                    };
+                    sqlx::query(
+                        r#"
+                        INSERT INTO verification_rules (id, version, name, enabled, priority, definition, created_at)
+                        VALUES (?, ?, ?, ?, ?, ?, ?)
+                        "#,
+                    )
+                    .bind(&stored.id)
+                    .bind(stored.version as i64)
+                    .bind(&stored.name)
+                    .bind(stored.enabled)
+                    .bind(stored.priority)
+                    .bind(definition)
+                    .bind(stored.created_at)
+                    .execute(&mut *tx)
+                    .await?;
+
+                    tx.commit().await?;
+                    Ok(stored)
+                }
+            })
+            .await
+            .map_err(|e| format!("Failed to save verification rule {}: {}", rule.id, e))?;
+
+        self.invalidate_rule_cache();
+        Ok(stored)
+    }
+
+    /// Gets the latest version of every rule that has not been deleted, in evaluation order
+    pub async fn get_active_verification_rules(&self) -> Result<Vec<VerificationRule>, String> {
+        let generation = {
+            let cache = self.rule_cache.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(rules) = &cache.rules {
+                return Ok(rules.clone());
+            }
+            cache.generation
+        };
+
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM verification_rules AS rule
+            WHERE retired_at IS NULL
+              AND version = (SELECT MAX(version) FROM verification_rules WHERE id = rule.id)
+            ORDER BY priority ASC, id ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch verification rules: {}", e))?;
+
+        let rules = rows
+            .iter()
+            .map(Self::row_to_verification_rule)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode verification rules: {}", e))?;
+
+        let mut cache = self.rule_cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.generation == generation {
+            cache.rules = Some(rules.clone());
+        }
+        Ok(rules)
+    }
+
+    /// Drops the cached active rules after a rule changed
+    fn invalidate_rule_cache(&self) {
+        let mut cache = self.rule_cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache.generation += 1;
+        cache.rules = None;
+    }
+
+    /// Gets every version of a rule, oldest first
+    pub async fn get_verification_rule_history(&self, rule_id: &str) -> Result<Vec<VerificationRule>, String> {
+        let rows = sqlx::query("SELECT * FROM verification_rules WHERE id = ? ORDER BY version ASC")
+            .bind(rule_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch history of verification rule {}: {}", rule_id, e))?;
+
+        rows.iter()
+            .map(Self::row_to_verification_rule)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode history of verification rule {}: {}", rule_id, e))
+    }
+
+    /// Stops applying a rule. Its versions are kept so stamped results stay traceable.
+    /// Returns false if no active rule has this id.
+    pub async fn retire_verification_rule(&self, rule_id: &str) -> Result<bool, String> {
+        let result = sqlx::query("UPDATE verification_rules SET retired_at = ? WHERE id = ? AND retired_at IS NULL")
+            .bind(Utc::now())
+            .bind(rule_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to delete verification rule {}: {}", rule_id, e))?;
+
+        self.invalidate_rule_cache();
+        Ok(result.rows_affected() > 0)
+    }
+
+    // ------------------------------------------------------------------------
+    // VERIFICATION HOLDS
+    // ------------------------------------------------------------------------
+
+    /// Stores results held for technologist verification
+    pub async fn save_verification_hold(&self, hold: &VerificationHold) -> Result<(), String> {
+        let decisions = serde_json::to_string(&hold.decisions)
+            .map_err(|e| format!("Failed to serialize verification decisions: {}", e))?;
+
+        self.retry
+            .run("save_verification_hold", || {
+                sqlx::query(
+                    r#"
+                    INSERT INTO verification_holds (
+                        id, patient_id, sample_id, analyzer_id, reason, reason_detail, decisions, payload, priority,
+                        status, upload_id, created_at, released_at
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&hold.id)
+                .bind(hold.patient_id.as_deref())
+                .bind(&hold.sample_id)
+                .bind(&hold.analyzer_id)
+                .bind(hold.reason.to_string())
+                .bind(hold.reason_detail.as_deref())
+                .bind(decisions.as_str())
+                .bind(&hold.payload)
+                .bind(hold.priority.rank())
+                .bind(hold.status.to_string())
+                .bind(hold.upload_id.as_deref())
+                .bind(hold.created_at)
+                .bind(hold.released_at)
+                .execute(&self.pool)
+            })
+            .await
+            .map_err(|e| format!("Failed to save verification hold for sample {}: {}", hold.sample_id, e))?;
+
+        Ok(())
+    }
+
+    /// Finds a verification hold by id
+    pub async fn get_verification_hold(&self, hold_id: &str) -> Result<Option<VerificationHold>, String> {
+        let row = sqlx::query("SELECT * FROM verification_holds WHERE id = ?")
+            .bind(hold_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch verification hold {}: {}", hold_id, e))?;
+
+        row.as_ref()
+            .map(Self::row_to_verification_hold)
+            .transpose()
+            .map_err(|e| format!("Failed to decode verification hold {}: {}", hold_id, e))
+    }
+
+    /// Gets all results waiting for verification, oldest first
+    pub async fn get_results_pending_verification(&self) -> Result<Vec<VerificationHold>, String> {
+        let rows = sqlx::query("SELECT * FROM verification_holds WHERE status = ? ORDER BY created_at, rowid")
+            .bind(HoldStatus::PendingVerification.to_string())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch results pending verification: {}", e))?;
+
+        rows.iter()
+            .map(Self::row_to_verification_hold)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode verification holds: {}", e))
+    }
+
+    /// Gets the results held for one reason and still waiting for review, oldest first
+    pub async fn get_results_pending_review(&self, reason: ReviewReason) -> Result<Vec<VerificationHold>, String> {
+        let rows = sqlx::query(
+            "SELECT * FROM verification_holds WHERE reason = ? AND status = ? ORDER BY created_at, rowid",
+        )
+        .bind(reason.to_string())
+        .bind(HoldStatus::PendingVerification.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch results pending review: {}", e))?;
+
+        rows.iter()
+            .map(Self::row_to_verification_hold)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode verification holds: {}", e))
+    }
+
+    /// Marks a verification hold released. Returns false if it was already released.
+    pub async fn release_verification_hold(&self, hold_id: &str, upload_id: Option<&str>) -> Result<bool, String> {
+        let result = sqlx::query(
+            "UPDATE verification_holds SET status = ?, upload_id = ?, released_at = ? WHERE id = ? AND status = ?",
+        )
+        .bind(HoldStatus::Released.to_string())
+        .bind(upload_id)
+        .bind(Utc::now())
+        .bind(hold_id)
+        .bind(HoldStatus::PendingVerification.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to release verification hold {}: {}", hold_id, e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // ------------------------------------------------------------------------
+    // RESULTS PACKAGES
+    // ------------------------------------------------------------------------
+
+    /// Applies one batch of packaged patients in a single transaction; nothing
+    /// from the batch is kept when any statement fails
+    pub async fn import_patient_batch(&self, batch: &[PatientImport]) -> Result<(), String> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        for import in batch {
+            match import {
+                PatientImport::Insert(patient) => {
+                    Self::insert_patient_query(patient, &DataSource::Imported)?
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(|e| format!("Failed to import patient {}: {}", patient.id, e))?;
+                }
+                PatientImport::FillGaps(patient) => {
+                    sqlx::query(
+                        r#"
+                        UPDATE patients SET
+                            last_name = COALESCE(last_name, ?),
+                            first_name = COALESCE(first_name, ?),
+                            middle_name = COALESCE(middle_name, ?),
+                            title = COALESCE(title, ?),
+                            birth_date = COALESCE(birth_date, ?),
+                            sex = CASE WHEN sex = 'U' THEN ? ELSE sex END,
+                            updated_at = ?
+                        WHERE id = ?
+                        "#,
+                    )
+                    .bind(patient.name.last_name.as_deref())
+                    .bind(patient.name.first_name.as_deref())
+                    .bind(patient.name.middle_name.as_deref())
+                    .bind(patient.name.title.as_deref())
+                    .bind(patient.birth_date)
+                    .bind(String::from(patient.sex.clone()))
+                    .bind(Utc::now())
+                    .bind(patient.id.as_str())
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| format!("Failed to update patient {}: {}", patient.id, e))?;
+                }
+            }
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Failed to commit patient batch: {}", e))
+    }
+
+    /// Applies one batch of packaged results in a single transaction; nothing
+    /// from the batch is kept when any statement fails
+    pub async fn import_result_batch(&self, batch: &[ResultImport]) -> Result<(), String> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        for import in batch {
+            let (sql, packaged) = match import {
+                ResultImport::Insert(packaged) => (INSERT_TEST_RESULT_SQL, packaged),
+                ResultImport::Replace(packaged) => (UPSERT_TEST_RESULT_SQL, packaged),
+            };
+            Self::test_result_query(sql, &packaged.result, &packaged.patient_id, &DataSource::Imported)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to import test result {}: {}", packaged.result.id, e))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Failed to commit result batch: {}", e))
+    }
+
+    // ------------------------------------------------------------------------
+    // RESULT UPLOADS
+    // ------------------------------------------------------------------------
+
+    /// Records an upload intent before anything is sent to the external system.
+    /// The row starts as PENDING and carries the full payload so it can be resent after a restart.
+    pub async fn track_result_upload(
+        &self,
+        result_id: &str,
+        external_system_id: &str,
+        payload: &str,
+        priority: UploadPriority,
+    ) -> Result<ResultUploadStatus, String> {
+        let now = Utc::now();
+        let upload = ResultUploadStatus {
+            id: Uuid::new_v4().to_string(),
+            result_id: result_id.to_string(),
+            external_system_id: external_system_id.to_string(),
+            status: UploadStatus::Pending,
+            upload_date: None,
+            response_code: None,
+            response_message: None,
+            retry_count: 0,
+            payload: payload.to_string(),
+            priority,
+            claimed_by: None,
+            claimed_at: None,
+            next_attempt_at: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        self.retry
+            .run("track_result_upload", || {
+                sqlx::query(
+                    r#"
+                    INSERT INTO result_upload_status (
+                        id, result_id, external_system_id, status, upload_date, response_code,
+                        response_message, retry_count, payload, priority, created_at, updated_at
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(upload.id.as_str())
+                .bind(upload.result_id.as_str())
+                .bind(upload.external_system_id.as_str())
+                .bind(upload.status.to_string())
+                .bind(upload.upload_date)
+                .bind(upload.response_code.as_deref())
+                .bind(upload.response_message.as_deref())
+                .bind(upload.retry_count as i64)
+                .bind(upload.payload.as_str())
+                .bind(upload.priority.rank())
+                .bind(upload.created_at)
+                .bind(upload.updated_at)
+                .execute(&self.pool)
+            })
+            .await
+            .map_err(|e| format!("Failed to track result upload for {}: {}", result_id, e))?;
+
+        log::debug!("Tracked upload {} for result {}", upload.id, result_id);
+        Ok(upload)
+    }
+
+    /// Gets uploads waiting to be sent in dispatch order: highest priority first, then oldest first
+    pub async fn get_pending_uploads(&self, limit: u32) -> Result<Vec<ResultUploadStatus>, String> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM result_upload_status
+            WHERE status = ?
+            ORDER BY priority ASC, created_at ASC, rowid ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(UploadStatus::Pending.to_string())
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch pending uploads: {}", e))?;
+
+        rows.iter()
+            .map(Self::row_to_upload_status)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode pending uploads: {}", e))
+    }
+
+    /// Reserves up to `limit` unclaimed pending uploads for a worker, in dispatch order,
+    /// skipping failed uploads whose next attempt is not due at `now`.
+    /// The reservation is a single statement, so two workers never claim the same row.
+    /// Returns every pending upload the worker holds, including earlier unfinished claims.
+    pub async fn claim_pending_uploads(
+        &self,
+        worker_id: &str,
+        limit: u32,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<ResultUploadStatus>, String> {
+        sqlx::query(
+            r#"
+            UPDATE result_upload_status
+            SET claimed_by = ?, claimed_at = ?
+            WHERE id IN (
+                SELECT id FROM result_upload_status
+                WHERE status = ? AND claimed_by IS NULL
+                  AND (next_attempt_at IS NULL OR next_attempt_at <= ?)
+                ORDER BY priority ASC, created_at ASC, rowid ASC
+                LIMIT ?
+            )
+            "#,
+        )
+        .bind(worker_id)
+        .bind(now)
+        .bind(UploadStatus::Pending.to_string())
+        .bind(now)
+        .bind(limit as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to claim pending uploads for {}: {}", worker_id, e))?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM result_upload_status
+            WHERE status = ? AND claimed_by = ?
+            ORDER BY priority ASC, created_at ASC, rowid ASC
+            "#,
+        )
+        .bind(UploadStatus::Pending.to_string())
+        .bind(worker_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to fetch uploads claimed by {}: {}", worker_id, e))?;
+
+        rows.iter()
+            .map(Self::row_to_upload_status)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode claimed uploads: {}", e))
+    }
+
+    /// Releases claims taken before `claimed_before`, returning rows a worker abandoned
+    /// mid-send to the queue. Returns the number of claims released.
+    pub async fn release_stale_upload_claims(&self, claimed_before: DateTime<Utc>) -> Result<u64, String> {
+        let result = sqlx::query(
+            r#"
+            UPDATE result_upload_status
+            SET status = ?, claimed_by = NULL, claimed_at = NULL, updated_at = ?
+            WHERE claimed_by IS NOT NULL AND claimed_at < ? AND status IN (?, ?)
+            "#,
+        )
+        .bind(UploadStatus::Pending.to_string())
+        .bind(Utc::now())
+        .bind(claimed_before)
+        .bind(UploadStatus::Pending.to_string())
+        .bind(UploadStatus::Uploading.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to release stale upload claims: {}", e))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Highest priority among the orders queued for a sample, if any were
+    pub async fn get_sample_order_priority(&self, sample_id: &str) -> Result<Option<OrderPriority>, String> {
+        let rank: Option<i64> = sqlx::query_scalar(
+            "SELECT MIN(priority) FROM order_dispatch_queue WHERE json_extract(payload, '$.specimen_id') = ?",
+        )
+        .bind(sample_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to look up order priority of sample {}: {}", sample_id, e))?;
+
+        Ok(rank.map(|rank| if rank == 0 { OrderPriority::Stat } else { OrderPriority::Routine }))
+    }
+
+    /// Counts of the upload queue and its first `limit` entries in dispatch order
+    pub async fn get_upload_queue_summary(&self, limit: u32) -> Result<UploadQueueSummary, String> {
+        let next = self
+            .get_pending_uploads(limit)
+            .await?
+            .into_iter()
+            .map(|upload| UploadQueueEntry {
+                upload_id: upload.id,
+                result_id: upload.result_id,
+                priority: upload.priority,
+                retry_count: upload.retry_count,
+                claimed_by: upload.claimed_by,
+                claimed_at: upload.claimed_at,
+                next_attempt_at: upload.next_attempt_at,
+                created_at: upload.created_at,
+            })
+            .collect();
+
+        let row = sqlx::query(
+            r#"
+            SELECT
+                COALESCE(SUM(status = ?1), 0) AS pending,
+                COALESCE(SUM(status = ?2), 0) AS in_flight,
+                COALESCE(SUM(status = ?1 AND claimed_by IS NOT NULL), 0) AS claimed,
+                COALESCE(SUM(status = ?1 AND priority = 0), 0) AS critical,
+                COALESCE(SUM(status = ?1 AND priority = 1), 0) AS stat,
+                COALESCE(SUM(status = ?1 AND priority >= 2), 0) AS routine
+            FROM result_upload_status
+            "#,
+        )
+        .bind(UploadStatus::Pending.to_string())
+        .bind(UploadStatus::Uploading.to_string())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to summarize upload queue: {}", e))?;
+
+        let count = |column: &str| -> Result<u64, String> {
+            row.try_get::<i64, _>(column)
+                .map(|value| value as u64)
+                .map_err(|e| format!("Failed to decode upload queue summary: {}", e))
+        };
+
+        Ok(UploadQueueSummary {
+            pending: count("pending")?,
+            in_flight: count("in_flight")?,
+            claimed: count("claimed")?,
+            critical: count("critical")?,
+            stat: count("stat")?,
+            routine: count("routine")?,
+            next,
+        })
+    }
+
+    /// Counts uploads not yet finished (pending or in flight)
+    pub async fn count_queued_uploads(&self) -> Result<usize, String> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM result_upload_status WHERE status IN (?, ?)",
+        )
+        .bind(UploadStatus::Pending.to_string())
+        .bind(UploadStatus::Uploading.to_string())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to count queued uploads: {}", e))?;
+
+        Ok(count as usize)
+    }
+
+    /// Ids of results created between `from` and `to` whose sample has no upload row,
+    /// i.e. results that were never queued for the HIS
+    pub async fn find_untracked_results(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<String>, String> {
+        sqlx::query_scalar(
+            r#"
+            SELECT t.id FROM test_results t
+            WHERE t.created_at >= ? AND t.created_at <= ?
+              AND NOT EXISTS (
+                  SELECT 1 FROM result_upload_status u WHERE u.result_id IN (t.sample_id, t.id)
+              )
+            ORDER BY t.created_at ASC, t.id
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to find untracked results: {}", e))
+    }
+
+    /// Finds a single upload row by id
+    pub async fn get_upload(&self, upload_id: &str) -> Result<Option<ResultUploadStatus>, String> {
+        let row = sqlx::query("SELECT * FROM result_upload_status WHERE id = ?")
+            .bind(upload_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch upload {}: {}", upload_id, e))?;
+
+        row.as_ref()
+            .map(Self::row_to_upload_status)
+            .transpose()
+            .map_err(|e| format!("Failed to decode upload {}: {}", upload_id, e))
+    }
+
+    /// Updates the status of an upload, stamping the upload date once it succeeds
+    pub async fn update_upload_status(
+        &self,
+        upload_id: &str,
+        status: UploadStatus,
+        response_code: Option<&str>,
+        response_message: Option<&str>,
+    ) -> Result<(), String> {
+        let now = Utc::now();
+        let upload_date = if status == UploadStatus::Uploaded {
+            Some(now)
+        } else {
+            None
+        };
+        // A finished upload no longer needs its worker reservation
+        let release_claim = matches!(status, UploadStatus::Uploaded | UploadStatus::Failed);
+
+        sqlx::query(
+            r#"
+            UPDATE result_upload_status
+            SET status = ?, upload_date = COALESCE(?, upload_date), response_code = ?,
+                response_message = ?, updated_at = ?, next_attempt_at = NULL,
+                claimed_by = CASE WHEN ? THEN NULL ELSE claimed_by END,
+                claimed_at = CASE WHEN ? THEN NULL ELSE claimed_at END
+            WHERE id = ?
+            "#,
+        )
+        .bind(status.to_string())
+        .bind(upload_date)
+        .bind(response_code)
+        .bind(response_message)
+        .bind(now)
+        .bind(release_claim)
+        .bind(release_claim)
+        .bind(upload_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to update upload {}: {}", upload_id, e))?;
+
+        Ok(())
+    }
+
+    /// Returns a failed attempt to the queue, incrementing its retry count. The upload
+    /// is not claimed again before `next_attempt_at`.
+    pub async fn record_upload_retry(
+        &self,
+        upload_id: &str,
+        error: &str,
+        next_attempt_at: DateTime<Utc>,
+    ) -> Result<(), String> {
+        sqlx::query(
+            r#"
+            UPDATE result_upload_status
+            SET status = ?, retry_count = retry_count + 1, response_message = ?, updated_at = ?,
+                next_attempt_at = ?, claimed_by = NULL, claimed_at = NULL
+            WHERE id = ?
+            "#,
+        )
+        .bind(UploadStatus::Pending.to_string())
+        .bind(error)
+        .bind(Utc::now())
+        .bind(next_attempt_at)
+        .bind(upload_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to record retry for upload {}: {}", upload_id, e))?;
+
+        Ok(())
+    }
+
+    /// Puts uploads that were in flight when the app stopped back into the queue:
+    /// unclaimed ones, those claimed by `worker_id` and those claimed before
+    /// `claimed_before`. Uploads another worker is still sending are left alone.
+    /// Returns the number of uploads requeued.
+    pub async fn requeue_in_flight_uploads(
+        &self,
+        worker_id: &str,
+        claimed_before: DateTime<Utc>,
+    ) -> Result<u64, String> {
+        let result = sqlx::query(
+            r#"
+            UPDATE result_upload_status
+            SET status = ?, updated_at = ?, claimed_by = NULL, claimed_at = NULL
+            WHERE status = ? AND (claimed_by IS NULL OR claimed_by = ? OR claimed_at IS NULL OR claimed_at < ?)
+            "#,
+        )
+        .bind(UploadStatus::Pending.to_string())
+        .bind(Utc::now())
+        .bind(UploadStatus::Uploading.to_string())
+        .bind(worker_id)
+        .bind(claimed_before)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to requeue in-flight uploads: {}", e))?;
+
+        Ok(result.rows_affected())
+    }
+
+    // ------------------------------------------------------------------------
+    // UPLOAD REMEDIATIONS
+    // ------------------------------------------------------------------------
+
+    /// Finds HIS uploads sent between `from` and `to` for samples with a stored result of
+    /// `test_code` (ASTM `^` separators ignored), skipping uploads already remediated
+    pub async fn find_uploads_for_test(
+        &self,
+        test_code: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<ResultUploadStatus>, String> {
+        let rows = sqlx::query(
+            r#"
+            SELECT u.* FROM result_upload_status u
+            WHERE u.status = ? AND u.upload_date >= ? AND u.upload_date <= ?
+              AND EXISTS (
+                  SELECT 1 FROM test_results t
+                  WHERE t.sample_id = u.result_id
+                    AND UPPER(REPLACE(t.test_id, '^', '')) = UPPER(REPLACE(?, '^', ''))
+              )
+              AND NOT EXISTS (SELECT 1 FROM upload_remediations r WHERE r.original_upload_id = u.id)
+            ORDER BY u.upload_date ASC, u.rowid ASC
+            "#,
+        )
+        .bind(UploadStatus::Uploaded.to_string())
+        .bind(from)
+        .bind(to)
+        .bind(test_code)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to find uploads for test {}: {}", test_code, e))?;
+
+        rows.iter()
+            .map(Self::row_to_upload_status)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode uploads for test {}: {}", test_code, e))
+    }
+
+    /// Records the uploads that corrected an earlier one
+    pub async fn record_upload_remediation(&self, remediation: &UploadRemediation) -> Result<(), String> {
+        self.retry
+            .run("record_upload_remediation", || {
+                sqlx::query(
+                    r#"
+                    INSERT INTO upload_remediations (
+                        id, original_upload_id, sample_id, test_code, pattern, cancel_upload_id, new_upload_id, created_at
+                    )
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(remediation.id.as_str())
+                .bind(remediation.original_upload_id.as_str())
+                .bind(remediation.sample_id.as_str())
+                .bind(remediation.test_code.as_str())
+                .bind(remediation.pattern.to_string())
+                .bind(remediation.cancel_upload_id.as_deref())
+                .bind(remediation.new_upload_id.as_str())
+                .bind(remediation.created_at)
+                .execute(&self.pool)
+            })
+            .await
+            .map_err(|e| format!("Failed to record remediation of upload {}: {}", remediation.original_upload_id, e))?;
+
+        Ok(())
+    }
+
+    /// Remediations of a test code, oldest first
+    pub async fn get_upload_remediations(&self, test_code: &str) -> Result<Vec<UploadRemediation>, String> {
+        let rows = sqlx::query("SELECT * FROM upload_remediations WHERE test_code = ? ORDER BY created_at ASC, rowid ASC")
+            .bind(test_code)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to fetch remediations for test {}: {}", test_code, e))?;
+
+        rows.iter()
+            .map(|row| -> Result<UploadRemediation, sqlx::Error> {
+                let pattern: String = row.try_get("pattern")?;
+                Ok(UploadRemediation {
+                    id: row.try_get("id")?,
+                    original_upload_id: row.try_get("original_upload_id")?,
+                    sample_id: row.try_get("sample_id")?,
+                    test_code: row.try_get("test_code")?,
+                    pattern: CorrectionPattern::from(pattern.as_str()),
+                    cancel_upload_id: row.try_get("cancel_upload_id")?,
+                    new_upload_id: row.try_get("new_upload_id")?,
+                    created_at: row.try_get("created_at")?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to decode remediations for test {}: {}", test_code, e))
+    }
+
+    /// Builds the `INSERT OR IGNORE` of a patient
+    fn insert_patient_query<'q>(
+        patient: &'q Patient,
+        source: &DataSource,
+    ) -> Result<Query<'q, Sqlite, SqliteArguments<'q>>, String> {
+        let telephone = serde_json::to_string(&patient.telephone)
+            .map_err(|e| format!("Failed to serialize telephone numbers: {}", e))?;
+        let contacts = serde_json::to_string(&patient.contacts)
+            .map_err(|e| format!("Failed to serialize contacts: {}", e))?;
+        let address = patient.address.as_ref();
+        let physicians = patient.physicians.as_ref();
+        let height = patient.physical_attributes.as_ref().and_then(|p| p.height.as_ref());
+        let weight = patient.physical_attributes.as_ref().and_then(|p| p.weight.as_ref());
+
+        Ok(sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO patients (
+                id, last_name, first_name, middle_name, title, birth_date, sex,
+                street, address_other_designation, city, state, zip, country_code,
+                address_raw, address_unparsed, telephone, contacts,
+                ordering_physician, attending_physician, referring_physician,
+                height_value, height_unit, weight_value, weight_unit,
+                comments, source, created_at, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(patient.id.as_str())
+        .bind(patient.name.last_name.as_deref())
+        .bind(patient.name.first_name.as_deref())
+        .bind(patient.name.middle_name.as_deref())
+        .bind(patient.name.title.as_deref())
+        .bind(patient.birth_date)
+        .bind(String::from(patient.sex.clone()))
+        .bind(address.and_then(|a| a.street.as_deref()))
+        .bind(address.and_then(|a| a.other_designation.as_deref()))
+        .bind(address.and_then(|a| a.city.as_deref()))
+        .bind(address.and_then(|a| a.state.as_deref()))
+        .bind(address.and_then(|a| a.zip.as_deref()))
+        .bind(address.and_then(|a| a.country_code.as_deref()))
+        .bind(address.and_then(|a| a.raw.as_deref()))
+        .bind(address.is_some_and(|a| a.unparsed))
+        .bind(telephone)
+        .bind(contacts)
+        .bind(physicians.and_then(|p| p.ordering.as_deref()))
+        .bind(physicians.and_then(|p| p.attending.as_deref()))
+        .bind(physicians.and_then(|p| p.referring.as_deref()))
+        .bind(height.map(|h| h.value))
+        .bind(height.map(|h| h.unit.as_str()))
+        .bind(weight.map(|w| w.value))
+        .bind(weight.map(|w| w.unit.as_str()))
+        .bind((!patient.comments.is_empty()).then(|| serde_json::to_string(&patient.comments).unwrap_or_default()))
+        .bind(source.to_string())
+        .bind(patient.created_at)
+        .bind(patient.updated_at))
+    }
+
+    /// Binds a test result to [`INSERT_TEST_RESULT_SQL`] or [`UPSERT_TEST_RESULT_SQL`]
+    fn test_result_query<'q>(
+        sql: &'q str,
+        result: &'q TestResult,
+        patient_id: &'q str,
+        source: &DataSource,
+    ) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        let reference_range = result.reference_range.as_ref();
+        let flags = result.flags.as_ref();
+        let verification = result.metadata.verification.as_ref();
+
+        sqlx::query(sql)
+            .bind(result.id.as_str())
+            .bind(result.test_id.as_str())
+            .bind(result.sample_id.as_str())
+            .bind(result.value.as_str())
+            .bind(result.units.as_deref())
+            .bind(reference_range.and_then(|r| r.lower_limit))
+            .bind(reference_range.and_then(|r| r.upper_limit))
+            .bind(flags.and_then(|f| f.abnormal_flag.as_deref()))
+            .bind(flags.and_then(|f| f.nature_of_abnormality.as_deref()))
+            .bind(result.status.as_db_str())
+            .bind(result.completed_date_time)
+            .bind(result.metadata.sequence_number as i64)
+            .bind(result.metadata.instrument.as_deref())
+            .bind(result.metadata.operator.as_deref())
+            .bind(result.metadata.dilution_factor)
+            .bind(result.metadata.raw_value.as_deref())
+            .bind(result.metadata.source_message_control_id.as_deref())
+            .bind(result.analyzer_id.as_deref())
+            .bind(patient_id)
+            .bind(source.to_string())
+            .bind(result.content_hash())
+            .bind(verification.map(|v| v.decision.to_string()))
+            .bind(verification.and_then(|v| v.rule_id.clone()))
+            .bind(verification.and_then(|v| v.rule_version.map(|version| version as i64)))
+            .bind(verification.map(|v| v.evaluated_at))
+            .bind((!result.comments.is_empty()).then(|| serde_json::to_string(&result.comments).unwrap_or_default()))
+            .bind(result.metadata.parameter_name.as_deref())
+            .bind(result.metadata.panel.as_deref())
+            .bind(result.metadata.analysis_mode.as_deref())
+            .bind(result.created_at)
+            .bind(result.updated_at)
+    }
+
+    /// Maps a `patients` row to its model
+    fn row_to_patient(row: &SqliteRow) -> Result<Patient, sqlx::Error> {
+        use crate::models::patient::{PatientAddress, PatientPhysicians, PhysicalAttribute, PhysicalAttributes};
+
+        let sex: String = row.try_get("sex")?;
+        let telephone: Option<String> = row.try_get("telephone")?;
+        let contacts: Option<String> = row.try_get("contacts")?;
+        let street: Option<String> = row.try_get("street")?;
+        let other_designation: Option<String> = row.try_get("address_other_designation")?;
+        let city: Option<String> = row.try_get("city")?;
+        let state: Option<String> = row.try_get("state")?;
+        let zip: Option<String> = row.try_get("zip")?;
+        let country_code: Option<String> = row.try_get("country_code")?;
+        let address_raw: Option<String> = row.try_get("address_raw")?;
+        let address_unparsed: bool = row.try_get("address_unparsed")?;
+        let ordering: Option<String> = row.try_get("ordering_physician")?;
+        let attending: Option<String> = row.try_get("attending_physician")?;
+        let referring: Option<String> = row.try_get("referring_physician")?;
+        let height_value: Option<f64> = row.try_get("height_value")?;
+        let height_unit: Option<String> = row.try_get("height_unit")?;
+        let weight_value: Option<f64> = row.try_get("weight_value")?;
+        let weight_unit: Option<String> = row.try_get("weight_unit")?;
+        let comments: Option<String> = row.try_get("comments")?;
+
+        let address = if street.is_some()
+            || other_designation.is_some()
+            || city.is_some()
+            || state.is_some()
+            || zip.is_some()
+            || country_code.is_some()
+            || address_raw.is_some()
+        {
+            Some(PatientAddress {
+                street,
+                other_designation,
+                city,
+                state,
+                zip,
+                country_code,
+                raw: address_raw,
+                unparsed: address_unparsed,
+            })
+        } else {
+            None
+        };
+        let physicians = if ordering.is_some() || attending.is_some() || referring.is_some() {
+            Some(PatientPhysicians { ordering, attending, referring })
+        } else {
+            None
+        };
+        let height = height_value.map(|value| PhysicalAttribute { value, unit: height_unit.unwrap_or_default() });
+        let weight = weight_value.map(|value| PhysicalAttribute { value, unit: weight_unit.unwrap_or_default() });
+        let physical_attributes = if height.is_some() || weight.is_some() {
+            Some(PhysicalAttributes { height, weight })
+        } else {
+            None
+        };
+
+        Ok(Patient {
+            id: row.try_get("id")?,
+            name: PatientName {
+                last_name: row.try_get("last_name")?,
+                first_name: row.try_get("first_name")?,
+                middle_name: row.try_get("middle_name")?,
+                title: row.try_get("title")?,
+            },
+            birth_date: row.try_get("birth_date")?,
+            sex: Sex::from(sex.as_str()),
+            address,
+            telephone: telephone
+                .and_then(|t| serde_json::from_str(&t).ok())
+                .unwrap_or_default(),
+            contacts: contacts
+                .and_then(|c| serde_json::from_str(&c).ok())
+                .unwrap_or_default(),
+            physicians,
+            physical_attributes,
+            comments: match comments {
+                Some(comments) => serde_json::from_str(&comments).map_err(|e| sqlx::Error::Decode(e.into()))?,
+                None => Vec::new(),
+            },
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+
+    /// Maps a `test_results` row to its model
+    fn row_to_test_result(row: &SqliteRow) -> Result<TestResult, sqlx::Error> {
+        let status: String = row.try_get("status")?;
+        let sequence_number: i64 = row.try_get("sequence_number")?;
+        let lower_limit: Option<f64> = row.try_get("reference_range_lower")?;
+        let upper_limit: Option<f64> = row.try_get("reference_range_upper")?;
+        let abnormal_flag: Option<String> = row.try_get("abnormal_flag")?;
+        let nature_of_abnormality: Option<String> = row.try_get("nature_of_abnormality")?;
+        let verification_decision: Option<String> = row.try_get("verification_decision")?;
+        let verification_rule_version: Option<i64> = row.try_get("verification_rule_version")?;
+        let verified_at: Option<DateTime<Utc>> = row.try_get("verified_at")?;
+        let comments: Option<String> = row.try_get("comments")?;
+        let verification = match (verification_decision, verified_at) {
+            (Some(decision), Some(evaluated_at)) => Some(VerificationStamp {
+                decision: VerificationDecision::from(decision.as_str()),
+                rule_id: row.try_get("verification_rule_id")?,
+                rule_version: verification_rule_version.map(|version| version as u32),
+                evaluated_at,
+            }),
+            _ => None,
+        };
+
+        Ok(TestResult {
+            id: row.try_get("id")?,
+            test_id: row.try_get("test_id")?,
+            sample_id: row.try_get("sample_id")?,
+            value: row.try_get("value")?,
+            units: row.try_get("units")?,
+            reference_range: if lower_limit.is_some() || upper_limit.is_some() {
+                Some(ReferenceRange { lower_limit, upper_limit })
+            } else {
+                None
+            },
+            flags: if abnormal_flag.is_some() || nature_of_abnormality.is_some() {
+                Some(ResultFlags { abnormal_flag, nature_of_abnormality })
+            } else {
+                None
+            },
+            status: ResultStatus::from_db_str(&status).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            completed_date_time: row.try_get("completed_date_time")?,
+            metadata: TestResultMetadata {
+                sequence_number: sequence_number as u32,
+                instrument: row.try_get("instrument")?,
+                operator: row.try_get("operator")?,
+                dilution_factor: row.try_get("dilution_factor")?,
+                raw_value: row.try_get("raw_value")?,
+                source_message_control_id: row.try_get("source_message_control_id")?,
+                verification,
+                parameter_name: row.try_get("parameter_name")?,
+                panel: row.try_get("panel")?,
+                analysis_mode: row.try_get("analysis_mode")?,
+                // Only set on ingestion; the hold it caused records the code
+                unrecognized_status: None,
+            },
+            analyzer_id: row.try_get("analyzer_id")?,
+            comments: match comments {
+                Some(comments) => serde_json::from_str(&comments).map_err(|e| sqlx::Error::Decode(e.into()))?,
+                None => Vec::new(),
+            },
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+
+    /// Maps a `result_images` row to its model
+    fn row_to_result_image(row: &SqliteRow) -> Result<ResultImage, sqlx::Error> {
+        Ok(ResultImage {
+            id: row.try_get("id")?,
+            result_id: row.try_get("result_id")?,
+            kind: row.try_get("kind")?,
+            mime_type: row.try_get("mime_type")?,
+            reference: row.try_get("reference")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+
+    /// Maps a `raw_messages` row to its model
+    fn row_to_raw_message(row: &SqliteRow) -> Result<RawMessage, sqlx::Error> {
+        let timeline: String = row.try_get("timeline")?;
+
+        Ok(RawMessage {
+            id: row.try_get("id")?,
+            analyzer_id: row.try_get("analyzer_id")?,
+            protocol: row.try_get("protocol")?,
+            message_type: row.try_get("message_type")?,
+            raw_data: row.try_get("raw_data")?,
+            timeline: serde_json::from_str(&timeline).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            upload_id: row.try_get("upload_id")?,
+            received_at: row.try_get("received_at")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+
+    /// Maps an `order_dispatch_queue` row to its model
+    fn row_to_order_dispatch(row: &SqliteRow) -> Result<OrderDispatch, sqlx::Error> {
+        let payload: String = row.try_get("payload")?;
+        let status: String = row.try_get("status")?;
+        let attempts: i64 = row.try_get("attempts")?;
+
+        Ok(OrderDispatch {
+            id: row.try_get("id")?,
+            order_id: row.try_get("order_id")?,
+            analyzer_id: row.try_get("analyzer_id")?,
+            patient_id: row.try_get("patient_id")?,
+            order: serde_json::from_str(&payload).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            status: DispatchStatus::from(status.as_str()),
+            attempts: attempts as u32,
+            last_error: row.try_get("last_error")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+
+    /// Maps an `analyzer_remote_addresses` row to its model
+    fn row_to_remote_address(row: &SqliteRow) -> Result<RemoteAddress, sqlx::Error> {
+        Ok(RemoteAddress {
+            analyzer_id: row.try_get("analyzer_id")?,
+            ip_address: row.try_get("ip_address")?,
+            approved: row.try_get("approved")?,
+            first_seen: row.try_get("first_seen")?,
+            last_seen: row.try_get("last_seen")?,
+        })
+    }
+
+    /// Maps a `duplicate_candidates` row to its model
+    fn row_to_duplicate_candidate(row: &SqliteRow) -> Result<DuplicateCandidate, sqlx::Error> {
+        let reasons: String = row.try_get("reasons")?;
+        let status: String = row.try_get("status")?;
+
+        Ok(DuplicateCandidate {
+            id: row.try_get("id")?,
+            patient_id_a: row.try_get("patient_id_a")?,
+            patient_id_b: row.try_get("patient_id_b")?,
+            score: row.try_get("score")?,
+            reasons: serde_json::from_str(&reasons).unwrap_or_default(),
+            status: CandidateStatus::from(status.as_str()),
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+
+    /// Maps a `demographics_holds` row to its model
+    fn row_to_demographics_hold(row: &SqliteRow) -> Result<DemographicsHold, sqlx::Error> {
+        let missing_fields: String = row.try_get("missing_fields")?;
+        let priority: i64 = row.try_get("priority")?;
+        let status: String = row.try_get("status")?;
+
+        Ok(DemographicsHold {
+            id: row.try_get("id")?,
+            patient_id: row.try_get("patient_id")?,
+            sample_id: row.try_get("sample_id")?,
+            analyzer_id: row.try_get("analyzer_id")?,
+            missing_fields: serde_json::from_str(&missing_fields).unwrap_or_default(),
+            payload: row.try_get("payload")?,
+            priority: UploadPriority::from_rank(priority),
+            status: HoldStatus::from_db_str(&status).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            upload_id: row.try_get("upload_id")?,
+            created_at: row.try_get("created_at")?,
+            released_at: row.try_get("released_at")?,
+        })
+    }
+
+    /// Maps a `verification_rules` row to its model
+    fn row_to_verification_rule(row: &SqliteRow) -> Result<VerificationRule, sqlx::Error> {
+        #[derive(serde::Deserialize)]
+        struct Definition {
+            scope: RuleScope,
+            condition: VerificationCondition,
+            decision: VerificationDecision,
+        }
+
+        let definition: String = row.try_get("definition")?;
+        let definition: Definition = serde_json::from_str(&definition).map_err(|e| sqlx::Error::Decode(e.into()))?;
+        let version: i64 = row.try_get("version")?;
+
+        Ok(VerificationRule {
+            id: row.try_get("id")?,
+            version: version as u32,
+            name: row.try_get("name")?,
+            enabled: row.try_get("enabled")?,
+            priority: row.try_get("priority")?,
+            scope: definition.scope,
+            condition: definition.condition,
+            decision: definition.decision,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+
+    /// Maps a `verification_holds` row to its model
+    fn row_to_verification_hold(row: &SqliteRow) -> Result<VerificationHold, sqlx::Error> {
+        let decisions: String = row.try_get("decisions")?;
+        let priority: i64 = row.try_get("priority")?;
+        let status: String = row.try_get("status")?;
+        let reason: String = row.try_get("reason")?;
+
+        Ok(VerificationHold {
+            id: row.try_get("id")?,
+            patient_id: row.try_get("patient_id")?,
+            sample_id: row.try_get("sample_id")?,
+            analyzer_id: row.try_get("analyzer_id")?,
+            reason: ReviewReason::from_db_str(&reason).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            reason_detail: row.try_get("reason_detail")?,
+            decisions: serde_json::from_str(&decisions).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            payload: row.try_get("payload")?,
+            priority: UploadPriority::from_rank(priority),
+            status: HoldStatus::from_db_str(&status).map_err(|e| sqlx::Error::Decode(e.into()))?,
+            upload_id: row.try_get("upload_id")?,
+            created_at: row.try_get("created_at")?,
+            released_at: row.try_get("released_at")?,
+        })
+    }
+
+    /// Maps a `result_upload_status` row to its model
+    fn row_to_upload_status(row: &SqliteRow) -> Result<ResultUploadStatus, sqlx::Error> {
+        let status: String = row.try_get("status")?;
+        let retry_count: i64 = row.try_get("retry_count")?;
+        let priority: i64 = row.try_get("priority")?;
+
+        Ok(ResultUploadStatus {
+            id: row.try_get("id")?,
+            result_id: row.try_get("result_id")?,
+            external_system_id: row.try_get("external_system_id")?,
+            status: UploadStatus::from(status.as_str()),
+            upload_date: row.try_get("upload_date")?,
+            response_code: row.try_get("response_code")?,
+            response_message: row.try_get("response_message")?,
+            retry_count: retry_count as u32,
+            payload: row.try_get("payload")?,
+            priority: UploadPriority::from_rank(priority),
+            claimed_by: row.try_get("claimed_by")?,
+            claimed_at: row.try_get("claimed_at")?,
+            next_attempt_at: row.try_get("next_attempt_at")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+
+    #[tokio::test]
+    async fn test_track_and_fetch_pending_upload() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+
+        let upload = repository
+            .track_result_upload("S123", "HIS", "{\"SampleNo\":\"S123\"}", UploadPriority::Routine)
+            .await
+            .unwrap();
+
+        let pending = repository.get_pending_uploads(10).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, upload.id);
+        assert_eq!(pending[0].status, UploadStatus::Pending);
+        assert_eq!(pending[0].payload, "{\"SampleNo\":\"S123\"}");
+    }
+
+    #[tokio::test]
+    async fn test_requeue_in_flight_uploads() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+
+        let upload = repository.track_result_upload("S1", "HIS", "{}", UploadPriority::Routine).await.unwrap();
+        repository
+            .update_upload_status(&upload.id, UploadStatus::Uploading, None, None)
+            .await
+            .unwrap();
+        assert!(repository.get_pending_uploads(10).await.unwrap().is_empty());
+
+        // Another worker's live claim stays in flight
+        let claimed = repository.track_result_upload("S2", "HIS", "{}", UploadPriority::Routine).await.unwrap();
+        repository.claim_pending_uploads("worker-b", 10, Utc::now()).await.unwrap();
+        repository
+            .update_upload_status(&claimed.id, UploadStatus::Uploading, None, None)
+            .await
+            .unwrap();
+        let claimed_before = Utc::now() - chrono::Duration::minutes(5);
+        assert_eq!(repository.requeue_in_flight_uploads("worker-a", claimed_before).await.unwrap(), 1);
+        assert_eq!(repository.get_pending_uploads(10).await.unwrap().len(), 1);
+
+        // Once its lease has expired it is requeued too
+        assert_eq!(repository.requeue_in_flight_uploads("worker-a", Utc::now()).await.unwrap(), 1);
+        assert_eq!(repository.get_pending_uploads(10).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sample_arrivals_only_move_forward() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        assert_eq!(repository.get_sample_arrival("A1", "S1").await.unwrap(), None);
+        assert_eq!(repository.get_latest_arrival_sequence().await.unwrap(), 0);
+
+        repository.record_sample_arrival("A1", "S1", 7).await.unwrap();
+        repository.record_sample_arrival("A1", "S1", 5).await.unwrap();
+        repository.record_sample_arrival("A2", "S1", 9).await.unwrap();
+
+        assert_eq!(repository.get_sample_arrival("A1", "S1").await.unwrap(), Some(7));
+        assert_eq!(repository.get_sample_arrival("A2", "S1").await.unwrap(), Some(9));
+        assert_eq!(repository.get_latest_arrival_sequence().await.unwrap(), 9);
+    }
+
+    #[tokio::test]
+    async fn test_result_status_round_trips_through_database() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let now = Utc::now();
+
+        for (index, status) in [ResultStatus::Preliminary, ResultStatus::Correction, ResultStatus::Final]
+            .into_iter()
+            .enumerate()
+        {
+            let result = TestResult {
+                test_id: "ALB".to_string(),
+                value: "3.5".to_string(),
+                units: Some("g/dL".to_string()),
+                status,
+                completed_date_time: Some(now),
+                metadata: TestResultMetadata { sequence_number: index as u32 + 1, ..Default::default() },
+                ..TestResult::fixture(&format!("R{}", index))
+            };
+            repository.save_test_result(&result, "P1", &DataSource::Analyzer).await.unwrap();
+        }
+
+        let statuses: Vec<ResultStatus> = repository
+            .get_patient_results("P1")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![ResultStatus::Preliminary, ResultStatus::Correction, ResultStatus::Final]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_patient_results_filtered_to_one_analyzer() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let now = Utc::now();
+
+        for (id, analyzer_id, value) in [
+            ("R1", Some("MERIL"), "95"),
+            ("R2", Some("BF6900"), "96"),
+            ("R3", Some("MERIL"), "97"),
+            ("R4", None, "98"),
+        ] {
+            let result = TestResult {
+                value: value.to_string(),
+                units: None,
+                completed_date_time: Some(now),
+                analyzer_id: analyzer_id.map(str::to_string),
+                ..TestResult::fixture(id)
+            };
+            repository.save_test_result(&result, "P1", &DataSource::Analyzer).await.unwrap();
+        }
+
+        let mut meril: Vec<String> = repository
+            .get_patient_results_by_analyzers("P1", &["MERIL".to_string()])
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.id)
+            .collect();
+        meril.sort();
+        assert_eq!(meril, vec!["R1", "R3"]);
+        assert_eq!(
+            repository
+                .get_patient_results_by_analyzers("P1", &["MERIL".to_string(), "BF6900".to_string()])
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+        assert!(repository.get_patient_results_by_analyzers("P1", &[]).await.unwrap().is_empty());
+        assert_eq!(repository.get_patient_results("P1").await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_patient_results_stream_row_by_row() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let now = Utc::now();
+
+        for index in 0..250u32 {
+            let result = TestResult {
+                sample_id: format!("S{}", index),
+                units: None,
+                completed_date_time: Some(now - chrono::Duration::minutes(index as i64)),
+                ..TestResult::fixture(&format!("R{}", index))
+            };
+            repository.save_test_result(&result, "P1", &DataSource::Analyzer).await.unwrap();
+        }
+
+        // Results are counted as they arrive, one in memory at a time
+        let mut stream = repository.stream_patient_results("P1");
+        let mut count = 0;
+        let mut previous: Option<DateTime<Utc>> = None;
+        while let Some(result) = stream.next().await {
+            let completed = result.unwrap().completed_date_time.unwrap();
+            assert!(previous.map_or(true, |previous| completed <= previous));
+            previous = Some(completed);
+            count += 1;
+        }
+        assert_eq!(count, 250);
+
+        // A consumer can stop early without reading the rest
+        let first: Vec<String> = repository
+            .stream_patient_results("P1")
+            .take(3)
+            .map(|result| result.unwrap().id)
+            .collect()
+            .await;
+        assert_eq!(first, vec!["R0", "R1", "R2"]);
+        assert_eq!(repository.stream_patient_results("P2").count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_message_timeline_includes_upload_stage() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let received_at = Utc::now() - chrono::Duration::seconds(5);
+
+        let mut timeline = ProcessingTimeline::started_at(received_at);
+        timeline.mark_at(ProcessingStage::Framed, received_at + chrono::Duration::milliseconds(100));
+        timeline.mark_at(ProcessingStage::Persisted, received_at + chrono::Duration::milliseconds(200));
+
+        let upload = repository.track_result_upload("S1", "HIS", "{}", UploadPriority::Routine).await.unwrap();
+        let message = RawMessage {
+            id: "M1".to_string(),
+            analyzer_id: "A1".to_string(),
+            protocol: "ASTM".to_string(),
+            message_type: "Result".to_string(),
+            raw_data: "1H|\\^&".to_string(),
+            timeline,
+            upload_id: Some(upload.id.clone()),
+            received_at,
+            created_at: received_at,
+            updated_at: received_at,
+        };
+        repository.save_raw_message(&message).await.unwrap();
+
+        // Not uploaded yet: the timeline stops at Persisted
+        let view = repository.get_message_timeline("M1").await.unwrap();
+        assert_eq!(view.last().unwrap().stage, ProcessingStage::Persisted);
+
+        repository
+            .update_upload_status(&upload.id, UploadStatus::Uploaded, None, None)
+            .await
+            .unwrap();
+        let view = repository.get_message_timeline("M1").await.unwrap();
+        assert_eq!(view.len(), 4);
+        assert_eq!(view.last().unwrap().stage, ProcessingStage::Uploaded);
+        assert!(view.last().unwrap().duration_ms > 0);
+    }
+
+    #[tokio::test]
+    async fn test_analyzer_event_summary_groups_by_type() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let from = Utc::now() - chrono::Duration::minutes(1);
+
+        let events = [
+            ("A1", AnalyzerEventType::ChecksumError, "Checksum mismatch in frame 1"),
+            ("A1", AnalyzerEventType::ParseError, "Failed to parse R record"),
+            ("A1", AnalyzerEventType::ChecksumError, "Checksum mismatch in frame 3"),
+            ("A1", AnalyzerEventType::ChecksumError, "Checksum mismatch in frame 5"),
+            ("A2", AnalyzerEventType::Error, "Connection reset"),
+        ];
+        for (analyzer_id, event_type, message) in &events {
+            repository
+                .record_analyzer_event(analyzer_id, event_type, message)
+                .await
+                .unwrap();
+        }
+        let to = Utc::now() + chrono::Duration::minutes(1);
+
+        let summary = repository.analyzer_event_summary("A1", from, to).await.unwrap();
+        assert_eq!(summary.total, 4);
+        assert_eq!(summary.by_type.len(), 2);
+        assert_eq!(summary.by_type[0].event_type, AnalyzerEventType::ChecksumError);
+        assert_eq!(summary.by_type[0].count, 3);
+        assert_eq!(summary.by_type[0].last_message, "Checksum mismatch in frame 5");
+        assert_eq!(summary.by_type[1].event_type, AnalyzerEventType::ParseError);
+        assert_eq!(summary.by_type[1].count, 1);
+
+        // Events outside the window are not counted
+        let summary = repository.analyzer_event_summary("A1", to, to).await.unwrap();
+        assert_eq!(summary.total, 0);
+        assert!(summary.by_type.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_conformance_report_groups_by_rule() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let from = Utc::now() - chrono::Duration::minutes(1);
+        let warning = |rule_id: &str, detail: &str| ConformanceWarning {
+            rule_id: rule_id.to_string(),
+            description: format!("{} description", rule_id),
+            detail: detail.to_string(),
+        };
+
+        repository
+            .save_conformance_warnings(
+                "A1",
+                "HL7",
+                Some("42"),
+                &[warning("HL7_VERSION", "MSH-12 is '2.4'"), warning("HL7_PROCESSING_ID", "MSH-11 is 'T'")],
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        repository
+            .save_conformance_warnings("A1", "HL7", Some("43"), &[warning("HL7_VERSION", "MSH-12 is '2.5'")])
+            .await
+            .unwrap();
+        repository
+            .save_conformance_warnings("A2", "ASTM", None, &[warning("ASTM_TERMINATOR_LAST", "last record is 'R'")])
+            .await
+            .unwrap();
+        // Stored last but dated first, e.g. by a restore; it is not the latest detail
+        sqlx::query(
+            r#"
+            INSERT INTO conformance_warnings (id, analyzer_id, protocol, rule_id, description, detail, created_at)
+            VALUES ('W-old', 'A1', 'HL7', 'HL7_VERSION', 'HL7_VERSION description', 'MSH-12 is ''2.3''', ?)
+            "#,
+        )
+        .bind(from + chrono::Duration::seconds(1))
+        .execute(repository.pool())
+        .await
+        .unwrap();
+        let to = Utc::now() + chrono::Duration::minutes(1);
+
+        let report = repository.get_conformance_report("A1", from, to).await.unwrap();
+        assert_eq!(report.total, 4);
+        assert_eq!(report.by_rule.len(), 2);
+        assert_eq!(report.by_rule[0].rule_id, "HL7_VERSION");
+        assert_eq!(report.by_rule[0].protocol, "HL7");
+        assert_eq!(report.by_rule[0].count, 3);
+        assert_eq!(report.by_rule[0].last_detail, "MSH-12 is '2.5'");
+        assert!(report.by_rule[0].first_seen < report.by_rule[0].last_seen);
+        assert_eq!(report.by_rule[1].rule_id, "HL7_PROCESSING_ID");
+
+        // Warnings outside the window are not counted
+        let report = repository.get_conformance_report("A1", to, to).await.unwrap();
+        assert_eq!(report.total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_dashboard_counts_split_today_from_earlier_days() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let now = Utc::now();
+        let two_days_ago = now - chrono::Duration::days(2);
+
+        for id in ["P1", "P2", "P3"] {
+            let patient = Patient {
+                name: PatientName {
+                    last_name: Some("Doe".to_string()),
+                    first_name: Some(id.to_string()),
+                    ..Default::default()
+                },
+                created_at: two_days_ago,
+                updated_at: two_days_ago,
+                ..Patient::fixture(id)
+            };
+            repository.save_patient(&patient, &DataSource::Analyzer).await.unwrap();
+        }
+
+        // Two samples two days ago (one with two results), two samples today
+        let seeded = [
+            ("R1", "S1", "P1", two_days_ago),
+            ("R2", "S1", "P1", two_days_ago),
+            ("R3", "S2", "P2", two_days_ago),
+            ("R4", "S3", "P2", now),
+            ("R5", "S4", "P3", now),
+            ("R6", "S4", "P3", now),
+            ("R7", "S4", "P3", now),
+        ];
+        for (index, (id, sample_id, patient_id, at)) in seeded.into_iter().enumerate() {
+            let result = TestResult {
+                sample_id: sample_id.to_string(),
+                // Distinct values so no result is an exact duplicate of another
+                value: (90 + index).to_string(),
+                units: None,
+                completed_date_time: Some(at),
+                metadata: TestResultMetadata { sequence_number: index as u32 + 1, ..Default::default() },
+                created_at: at,
+                updated_at: at,
+                ..TestResult::fixture(id)
+            };
+            repository.save_test_result(&result, patient_id, &DataSource::Analyzer).await.unwrap();
+        }
+
+        let uploaded = repository.track_result_upload("S1", "HIS", "{}", UploadPriority::Routine).await.unwrap();
+        repository
+            .update_upload_status(&uploaded.id, UploadStatus::Uploaded, None, None)
+            .await
+            .unwrap();
+        let in_flight = repository.track_result_upload("S3", "HIS", "{}", UploadPriority::Routine).await.unwrap();
+        repository
+            .update_upload_status(&in_flight.id, UploadStatus::Uploading, None, None)
+            .await
+            .unwrap();
+        repository.track_result_upload("S4", "HIS", "{}", UploadPriority::Routine).await.unwrap();
+
+        // Routine closes after a transmission are not disconnections
+        let terminations = [
+            (ConnectionTermination::Completed, now),
+            (ConnectionTermination::Completed, now),
+            (ConnectionTermination::RemoteClosed, now),
+            (ConnectionTermination::Shutdown, now),
+            (ConnectionTermination::Timeout, two_days_ago),
+        ];
+        for (index, (termination, closed_at)) in terminations.into_iter().enumerate() {
+            repository
+                .archive_connection(&ConnectionSummary {
+                    connection_id: format!("C{}", index),
+                    analyzer_id: "A1".to_string(),
+                    protocol: "ASTM".to_string(),
+                    remote_address: "192.168.1.50:4000".to_string(),
+                    opened_at: closed_at,
+                    closed_at: Some(closed_at),
+                    entries: 4,
+                    dropped_entries: 0,
+                    termination: Some(termination),
+                })
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            repository.dashboard_counts().await.unwrap(),
+            DashboardCounts {
+                total_patients: 3,
+                total_samples: 4,
+                samples_today: 2,
+                results_today: 4,
+                pending_uploads: 2,
+                connection_terminations_today: TerminationCounts {
+                    completed: 2,
+                    remote_closed: 1,
+                    shutdown: 1,
+                    ..TerminationCounts::default()
+                },
+                abnormal_disconnections_today: 1,
+            }
+        );
+        let history = repository.get_connection_history("A1", 10).await.unwrap();
+        assert_eq!(history.len(), 5);
+        assert!(history.iter().all(|summary| summary.termination.is_some()));
+    }
+
+    #[tokio::test]
+    async fn test_result_without_upload_row_is_untracked() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let now = Utc::now();
+        let result = |id: &str, sample_id: &str| TestResult {
+            test_id: "^^^GLU".to_string(),
+            sample_id: sample_id.to_string(),
+            value: "5.4".to_string(),
+            units: Some("mmol/L".to_string()),
+            completed_date_time: Some(now),
+            ..TestResult::fixture(id)
+        };
+
+        repository.save_test_result(&result("R1", "S1"), "P1", &DataSource::Analyzer).await.unwrap();
+        repository.save_test_result(&result("R2", "S2"), "P1", &DataSource::Analyzer).await.unwrap();
+        repository.track_result_upload("S1", "HIS", "{}", UploadPriority::Routine).await.unwrap();
+
+        let window = chrono::Duration::minutes(1);
+        assert_eq!(
+            repository.find_untracked_results(now - window, now + window).await.unwrap(),
+            vec!["R2".to_string()]
+        );
+        assert!(repository
+            .find_untracked_results(now + window, now + window * 2)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_same_logical_result_hashes_equal_and_is_deduped() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let completed = Utc::now();
+        let result = |id: &str, value: &str| TestResult {
+            test_id: "^^^ALB".to_string(),
+            value: value.to_string(),
+            units: Some("g/dL".to_string()),
+            completed_date_time: Some(completed),
+            ..TestResult::fixture(id)
+        };
+
+        // A retransmission gets a new id but carries the same content
+        let original = result("R1", "3.5");
+        let retransmitted = result("R2", " 3.5 ");
+        assert_eq!(original.content_hash(), retransmitted.content_hash());
+        assert_ne!(original.content_hash(), result("R3", "3.6").content_hash());
+
+        assert!(repository.save_test_result(&original, "P1", &DataSource::Analyzer).await.unwrap());
+        assert!(!repository.save_test_result(&retransmitted, "P1", &DataSource::Analyzer).await.unwrap());
+        assert_eq!(repository.get_patient_results("P1").await.unwrap(), vec![original]);
+
+        // The same content for another patient or from another analyzer is another result
+        let other_analyzer = TestResult { analyzer_id: Some("meril-2".to_string()), ..result("R4", "3.5") };
+        assert!(repository.save_test_result(&other_analyzer, "P1", &DataSource::Analyzer).await.unwrap());
+        assert!(repository.save_test_result(&result("R5", "3.5"), "P2", &DataSource::Analyzer).await.unwrap());
+
+        let report = repository.verify_result_hashes().await.unwrap();
+        assert_eq!(report, ResultIntegrityReport { checked: 3, unhashed: 0, mismatched: Vec::new() });
+
+        sqlx::query("UPDATE test_results SET value = '9.9' WHERE id = 'R1'")
+            .execute(repository.pool())
+            .await
+            .unwrap();
+        assert_eq!(repository.verify_result_hashes().await.unwrap().mismatched, vec!["R1".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_results_without_a_completion_time_are_never_deduped() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let result = |id: &str| TestResult { completed_date_time: None, ..TestResult::fixture(id) };
+        assert_eq!(result("R1").content_hash(), None);
+
+        // Two runs of the same test without a timestamp cannot be told apart from a retransmission
+        assert!(repository.save_test_result(&result("R1"), "P1", &DataSource::Analyzer).await.unwrap());
+        assert!(repository.save_test_result(&result("R2"), "P1", &DataSource::Analyzer).await.unwrap());
+
+        let report = repository.verify_result_hashes().await.unwrap();
+        assert_eq!(report, ResultIntegrityReport { checked: 2, unhashed: 2, mismatched: Vec::new() });
+    }
+
+    #[tokio::test]
+    async fn test_unique_hash_migration_keeps_stored_hashes() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let completed = Utc::now();
+        let result = |id: &str, analyzer_id: &str, value: &str| TestResult {
+            analyzer_id: Some(analyzer_id.to_string()),
+            value: value.to_string(),
+            completed_date_time: Some(completed),
+            ..TestResult::fixture(id)
+        };
+        let original = result("R1", "meril-1", "95");
+        let undated = TestResult { completed_date_time: None, ..result("R4", "meril-1", "97") };
+        for stored in [&original, &result("R2", "meril-1", "96"), &result("R3", "meril-2", "95"), &undated] {
+            repository.save_test_result(stored, "P1", &DataSource::Analyzer).await.unwrap();
+        }
+
+        // Rows as stored before the hash was unique: a copy that slipped past the old
+        // lookup, and a result hashed without a completion time
+        sqlx::query("DROP INDEX idx_test_results_content_hash")
+            .execute(repository.pool())
+            .await
+            .unwrap();
+        sqlx::query("UPDATE test_results SET value = '95', content_hash = ? WHERE id = 'R2'")
+            .bind(original.content_hash())
+            .execute(repository.pool())
+            .await
+            .unwrap();
+        sqlx::query("UPDATE test_results SET content_hash = 'undated' WHERE id = 'R4'")
+            .execute(repository.pool())
+            .await
+            .unwrap();
+
+        sqlx::raw_sql(crate::migrations::get_unique_content_hash_migration().sql)
+            .execute(repository.pool())
+            .await
+            .unwrap();
+
+        let hashes: Vec<(String, Option<String>)> = sqlx::query_as("SELECT id, content_hash FROM test_results ORDER BY id")
+            .fetch_all(repository.pool())
+            .await
+            .unwrap();
+        assert_eq!(
+            hashes,
+            vec![
+                ("R1".to_string(), original.content_hash()),
+                ("R2".to_string(), None),
+                ("R3".to_string(), original.content_hash()),
+                ("R4".to_string(), None),
+            ]
+        );
+        let report = repository.verify_result_hashes().await.unwrap();
+        assert_eq!(report, ResultIntegrityReport { checked: 4, unhashed: 2, mismatched: Vec::new() });
+    }
+
+    #[tokio::test]
+    async fn test_replaying_result_changes_rebuilds_the_final_state() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let completed = Utc::now();
+        let result = |id: &str, value: &str| TestResult {
+            test_id: "^^^GLU".to_string(),
+            sample_id: format!("S-{}", id),
+            value: value.to_string(),
+            completed_date_time: Some(completed),
+            created_at: completed,
+            updated_at: completed,
+            ..TestResult::fixture(id)
+        };
+
+        repository.save_test_result(&result("R1", "95"), "P1", &DataSource::Analyzer).await.unwrap();
+        repository.save_test_result(&result("R2", "110"), "P1", &DataSource::Analyzer).await.unwrap();
+        sqlx::query("UPDATE test_results SET value = '112' WHERE id = 'R2'")
+            .execute(repository.pool())
+            .await
+            .unwrap();
+        repository.save_test_result(&result("R3", "70"), "P2", &DataSource::Analyzer).await.unwrap();
+        sqlx::query("DELETE FROM test_results WHERE id = 'R1'")
+            .execute(repository.pool())
+            .await
+            .unwrap();
+        repository.supersede_test_result("R2", &result("R4", "115"), &DataSource::Analyzer).await.unwrap();
+        sqlx::query("DELETE FROM test_results WHERE id = 'R3'")
+            .execute(repository.pool())
+            .await
+            .unwrap();
+        repository.save_test_result(&result("R3", "72"), "P2", &DataSource::Analyzer).await.unwrap();
+
+        // Replay in small pages from the start, as a polling consumer would
+        let mut replayed: HashMap<String, TestResult> = HashMap::new();
+        let mut seen = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let page = repository.get_result_changes(cursor, 2).await.unwrap();
+            if page.changes.is_empty() {
+                assert_eq!(page.next_seq, cursor);
+                break;
+            }
+            for change in page.changes {
+                assert!(change.seq > cursor);
+                cursor = change.seq;
+                seen.push(change.change_type);
+                if change.change_type == ResultChangeType::Delete {
+                    // R3 is stored again later, but its delete stays a tombstone
+                    assert!(change.result.is_none(), "delete of {} carries a snapshot", change.result_id);
+                }
+                match change.result {
+                    Some(result) => replayed.insert(change.result_id, result),
+                    None => replayed.remove(&change.result_id),
+                };
+            }
+            assert_eq!(page.next_seq, cursor);
+        }
+        use ResultChangeType::*;
+        assert_eq!(seen, vec![Insert, Insert, Update, Insert, Delete, Insert, Update, Delete, Insert]);
+
+        let mut current = HashMap::new();
+        for id in ["R1", "R2", "R3", "R4"] {
+            if let Some(result) = repository.get_test_result(id).await.unwrap() {
+                current.insert(id.to_string(), result);
+            }
+        }
+        assert_eq!(replayed, current);
+        assert_eq!(replayed["R3"].value, "72");
+        assert!(!replayed.contains_key("R1"));
+
+        // A consumer at the head only sees what changes next
+        repository.save_test_result(&result("R5", "88"), "P3", &DataSource::Analyzer).await.unwrap();
+        let page = repository.get_result_changes(cursor, 100).await.unwrap();
+        assert_eq!(page.changes.len(), 1);
+        assert_eq!(page.changes[0].result_id, "R5");
+        assert_eq!(page.next_seq, cursor + 1);
+    }
+
+    #[tokio::test]
+    async fn test_patient_contact_details_round_trip() {
+        use crate::models::patient::{ContactInfo, PatientAddress};
+        use crate::protocol::contact::{normalize_address_text, parse_xtn};
+
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let now = Utc::now();
+        let patient = Patient {
+            name: PatientName {
+                last_name: Some("Doe".to_string()),
+                first_name: Some("Jane".to_string()),
+                ..Default::default()
+            },
+            sex: Sex::Female,
+            address: Some(PatientAddress {
+                other_designation: Some("Apt 4".to_string()),
+                ..normalize_address_text("12 Main St, Springfield IL 62704")
+            }),
+            telephone: vec!["(555)123-4567".to_string()],
+            contacts: vec![
+                parse_xtn("^PRN^PH^^^555^1234567"),
+                ContactInfo {
+                    raw: "ask at reception".to_string(),
+                    unparsed: true,
+                    ..Default::default()
+                },
+            ],
+            ..Patient::fixture("P1")
+        };
+        repository.save_patient(&patient, &DataSource::Imported).await.unwrap();
+
+        let stored = repository.get_patient("P1").await.unwrap().unwrap();
+        assert_eq!(stored.address, patient.address);
+        assert_eq!(stored.contacts, patient.contacts);
+
+        // A raw-only address is kept even though none of its components are known
+        let raw_only = Patient {
+            id: "P2".to_string(),
+            address: Some(normalize_address_text("behind the old mill")),
+            contacts: Vec::new(),
+            ..patient
+        };
+        repository.save_patient(&raw_only, &DataSource::Imported).await.unwrap();
+        let stored = repository.get_patient("P2").await.unwrap().unwrap();
+        assert!(stored.address.as_ref().unwrap().unparsed);
+        assert_eq!(stored.address.unwrap().raw.as_deref(), Some("behind the old mill"));
+    }
+
+    #[tokio::test]
+    async fn test_patients_found_by_their_sample_ids() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let now = Utc::now();
+
+        for (index, (patient_id, sample_id)) in [("P1", "S1"), ("P1", "S2"), ("P2", "S3")].into_iter().enumerate() {
+            let patient = Patient {
+                name: PatientName { last_name: Some(format!("Doe {}", patient_id)), ..Default::default() },
+                created_at: now + chrono::Duration::seconds(index as i64),
+                updated_at: now,
+                ..Patient::fixture(patient_id)
+            };
+            repository.save_patient(&patient, &DataSource::Analyzer).await.unwrap();
+            let result = TestResult {
+                sample_id: sample_id.to_string(),
+                units: None,
+                completed_date_time: Some(now),
+                ..TestResult::fixture(&format!("R{}", index))
+            };
+            repository.save_test_result(&result, patient_id, &DataSource::Analyzer).await.unwrap();
+        }
+
+        let ids = |patients: Vec<Patient>| patients.into_iter().map(|patient| patient.id).collect::<Vec<_>>();
+        let samples = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+
+        // A patient with two of the samples comes back once
+        let found = repository.get_patients_by_sample_ids(&samples(&["S1", "S2"])).await.unwrap();
+        assert_eq!(found[0].name.last_name.as_deref(), Some("Doe P1"));
+        assert_eq!(ids(found), vec!["P1"]);
+        let found = repository.get_patients_by_sample_ids(&samples(&["S3", "S1", "S9"])).await.unwrap();
+        assert_eq!(ids(found), vec!["P1", "P2"]);
+        assert!(repository.get_patients_by_sample_ids(&samples(&["S9"])).await.unwrap().is_empty());
+        assert!(repository.get_patients_by_sample_ids(&[]).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_result_detail_with_note_and_image() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let received = Utc::now() - chrono::Duration::minutes(5);
+        let result = TestResult {
+            test_id: "WBC".to_string(),
+            value: "12.4".to_string(),
+            units: Some("10^9/L".to_string()),
+            reference_range: Some(ReferenceRange { lower_limit: Some(4.0), upper_limit: Some(10.0) }),
+            flags: Some(ResultFlags { abnormal_flag: Some("H".to_string()), nature_of_abnormality: None }),
+            completed_date_time: Some(received),
+            analyzer_id: Some("BF-6900".to_string()),
+            created_at: received,
+            updated_at: received,
+            ..TestResult::fixture("R1")
+        };
+        repository.save_test_result(&result, "P1", &DataSource::Analyzer).await.unwrap();
+        assert!(repository.get_result_detail("R404").await.unwrap().is_none());
+
+        let note = ResultNote {
+            id: "N1".to_string(),
+            result_id: "R1".to_string(),
+            author: Some("tech1".to_string()),
+            text: "Smear reviewed, count confirmed".to_string(),
+            created_at: received + chrono::Duration::minutes(2),
+        };
+        let image = ResultImage {
+            id: "I1".to_string(),
+            result_id: "R1".to_string(),
+            kind: "histogram".to_string(),
+            mime_type: "image/png".to_string(),
+            reference: "images/S1/WBC_histogram.png".to_string(),
+            created_at: received + chrono::Duration::minutes(1),
+        };
+        repository.add_result_note(&note).await.unwrap();
+        repository.save_result_image(&image).await.unwrap();
+        assert_eq!(repository.get_sample_images(&result.sample_id).await.unwrap(), vec![image.clone()]);
+        assert!(repository.get_sample_images("S404").await.unwrap().is_empty());
+
+        let detail = repository.get_result_detail("R1").await.unwrap().unwrap();
+        assert_eq!(detail.result, result);
+        assert_eq!(detail.result.reference_range, result.reference_range);
+        assert_eq!(detail.result.flags, result.flags);
+        assert_eq!(detail.notes, vec![note]);
+        assert_eq!(detail.images, vec![image]);
+        assert!(detail.uploads.is_empty());
+        assert_eq!((detail.replaces, detail.superseded_by), (None, None));
+        let actions: Vec<ResultAuditAction> = detail.audit.iter().map(|entry| entry.action).collect();
+        assert_eq!(
+            actions,
+            vec![ResultAuditAction::Received, ResultAuditAction::ImageAttached, ResultAuditAction::NoteAdded]
+        );
+        assert_eq!(detail.audit[0].detail, "Received from BF-6900 (analyzer)");
+    }
+}
@@ -0,0 +1,572 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+/// Primary SQLite result codes that only mean another connection holds a lock
+const SQLITE_BUSY: i64 = 5;
+const SQLITE_LOCKED: i64 = 6;
+
+// ============================================================================
+// SETTINGS
+// ============================================================================
+
+/// How long a statement that hit SQLITE_BUSY/SQLITE_LOCKED is retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    /// Total time allowed for all attempts, including backoff
+    pub budget: Duration,
+    /// Backoff before the second attempt; doubles for each later one
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            budget: Duration::from_millis(250),
+            base_delay: Duration::from_millis(5),
+        }
+    }
+}
+
+/// When the circuit breaker opens and for how long it fails fast
+#[derive(Debug, Clone)]
+pub struct BreakerSettings {
+    /// Length of one measurement window
+    pub window: Duration,
+    /// Windows with fewer operations are never counted as failing
+    pub min_operations: u32,
+    /// Share of operations that exhausted their retries for a window to count as failing
+    pub failure_ratio: f64,
+    /// Consecutive failing windows that open the breaker
+    pub failing_windows: u32,
+    /// Time spent failing fast before a trial operation is let through
+    pub open_for: Duration,
+}
+
+impl Default for BreakerSettings {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(5),
+            min_operations: 20,
+            failure_ratio: 0.5,
+            failing_windows: 3,
+            open_for: Duration::from_secs(10),
+        }
+    }
+}
+
+// ============================================================================
+// ERRORS AND METRICS
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    /// Database operations fail fast
+    Open,
+    /// One trial operation decides whether to close again
+    HalfOpen,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RetryError {
+    #[error("{0}")]
+    Database(#[from] sqlx::Error),
+    #[error("database unavailable: too many busy/locked failures, failing fast")]
+    CircuitOpen,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetryMetrics {
+    /// Attempts repeated after a busy/locked error
+    pub retries: u64,
+    /// Operations that succeeded after at least one retry
+    pub recovered: u64,
+    /// Operations that were still busy/locked when the budget ran out
+    pub exhausted: u64,
+    /// Constraint, data and other errors returned without retrying
+    pub not_retried: u64,
+    /// Operations refused while the breaker was open
+    pub rejected: u64,
+    pub breaker_trips: u64,
+    pub breaker_state: BreakerState,
+}
+
+/// Whether an error is lock contention that is worth retrying.
+/// SQLite reports extended codes (e.g. 517 SQLITE_BUSY_SNAPSHOT); the low byte is the primary code.
+pub fn is_transient(error: &sqlx::Error) -> bool {
+    let Some(code) = error
+        .as_database_error()
+        .and_then(|e| e.code())
+        .and_then(|code| code.parse::<i64>().ok())
+    else {
+        return false;
+    };
+    matches!(code & 0xFF, SQLITE_BUSY | SQLITE_LOCKED)
+}
+
+// ============================================================================
+// CIRCUIT BREAKER
+// ============================================================================
+
+#[derive(Debug)]
+struct Breaker {
+    state: BreakerState,
+    window_started: Instant,
+    operations: u32,
+    failures: u32,
+    failing_windows: u32,
+    opened_at: Instant,
+    trial_in_flight: bool,
+}
+
+impl Breaker {
+    fn new(now: Instant) -> Self {
+        Self {
+            state: BreakerState::Closed,
+            window_started: now,
+            operations: 0,
+            failures: 0,
+            failing_windows: 0,
+            opened_at: now,
+            trial_in_flight: false,
+        }
+    }
+
+    /// Whether an operation may run now
+    fn allow(&mut self, now: Instant, settings: &BreakerSettings) -> bool {
+        match self.state {
+            BreakerState::Closed => true,
+            BreakerState::Open if now.duration_since(self.opened_at) >= settings.open_for => {
+                self.state = BreakerState::HalfOpen;
+                self.trial_in_flight = true;
+                true
+            }
+            BreakerState::Open => false,
+            BreakerState::HalfOpen if self.trial_in_flight => false,
+            BreakerState::HalfOpen => {
+                self.trial_in_flight = true;
+                true
+            }
+        }
+    }
+
+    /// Forgets an admitted operation that was cancelled before it finished; a
+    /// cancelled trial lets the next operation be the trial instead
+    fn abandon(&mut self) {
+        if self.state == BreakerState::HalfOpen {
+            self.trial_in_flight = false;
+        }
+    }
+
+    /// Records an admitted operation; `failed` means it ran out of retries
+    fn record(&mut self, now: Instant, settings: &BreakerSettings, failed: bool) {
+        match self.state {
+            BreakerState::HalfOpen => {
+                self.trial_in_flight = false;
+                if failed {
+                    self.open(now);
+                } else {
+                    self.close(now);
+                }
+                return;
+            }
+            // Admitted before the breaker opened
+            BreakerState::Open => return,
+            BreakerState::Closed => {}
+        }
+
+        let elapsed = now.duration_since(self.window_started);
+        if elapsed >= settings.window {
+            let window_failing = self.operations >= settings.min_operations
+                && f64::from(self.failures) >= settings.failure_ratio * f64::from(self.operations);
+            // An idle window in between breaks the streak
+            if window_failing && elapsed < settings.window * 2 {
+                self.failing_windows += 1;
+            } else {
+                self.failing_windows = 0;
+            }
+            self.window_started = now;
+            self.operations = 0;
+            self.failures = 0;
+
+            if self.failing_windows >= settings.failing_windows {
+                self.open(now);
+                return;
+            }
+        }
+
+        self.operations += 1;
+        if failed {
+            self.failures += 1;
+        }
+    }
+
+    fn open(&mut self, now: Instant) {
+        self.state = BreakerState::Open;
+        self.opened_at = now;
+        self.failing_windows = 0;
+    }
+
+    fn close(&mut self, now: Instant) {
+        *self = Self::new(now);
+    }
+}
+
+// ============================================================================
+// RETRY LAYER
+// ============================================================================
+
+/// An operation the breaker let through. Dropping it unrecorded, e.g. when the
+/// caller's future is cancelled, gives up its claim so a half-open trial cannot
+/// wedge the breaker.
+struct Admission<'a> {
+    layer: &'a RetryLayer,
+    recorded: bool,
+}
+
+impl Admission<'_> {
+    fn record(mut self, failed: bool) {
+        self.recorded = true;
+        self.layer.record(failed);
+    }
+}
+
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        if !self.recorded {
+            self.layer.update_breaker(|breaker, _| breaker.abandon());
+        }
+    }
+}
+
+/// Retries lock contention inside the repository so a few milliseconds of
+/// SQLITE_BUSY never reach the protocol layer as a NAK
+#[derive(Debug)]
+pub struct RetryLayer {
+    policy: RetryPolicy,
+    settings: BreakerSettings,
+    breaker: Mutex<Breaker>,
+    state_sender: watch::Sender<BreakerState>,
+    retries: AtomicU64,
+    recovered: AtomicU64,
+    exhausted: AtomicU64,
+    not_retried: AtomicU64,
+    rejected: AtomicU64,
+    breaker_trips: AtomicU64,
+}
+
+impl Default for RetryLayer {
+    fn default() -> Self {
+        Self::new(RetryPolicy::default(), BreakerSettings::default())
+    }
+}
+
+impl RetryLayer {
+    pub fn new(policy: RetryPolicy, settings: BreakerSettings) -> Self {
+        let (state_sender, _) = watch::channel(BreakerState::Closed);
+        Self {
+            policy,
+            settings,
+            breaker: Mutex::new(Breaker::new(Instant::now())),
+            state_sender,
+            retries: AtomicU64::new(0),
+            recovered: AtomicU64::new(0),
+            exhausted: AtomicU64::new(0),
+            not_retried: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            breaker_trips: AtomicU64::new(0),
+        }
+    }
+
+    /// Receives every breaker state change
+    pub fn subscribe(&self) -> watch::Receiver<BreakerState> {
+        self.state_sender.subscribe()
+    }
+
+    pub fn metrics(&self) -> RetryMetrics {
+        RetryMetrics {
+            retries: self.retries.load(Ordering::Relaxed),
+            recovered: self.recovered.load(Ordering::Relaxed),
+            exhausted: self.exhausted.load(Ordering::Relaxed),
+            not_retried: self.not_retried.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            breaker_trips: self.breaker_trips.load(Ordering::Relaxed),
+            breaker_state: self.breaker.lock().unwrap().state,
+        }
+    }
+
+    /// Runs `operation`, retrying busy/locked errors with jittered exponential
+    /// backoff within the policy's attempt and time budget. Other errors are
+    /// returned at once. `operation` must be safe to repeat (a statement or a
+    /// whole transaction).
+    pub async fn run<T, F, Fut>(&self, name: &str, mut operation: F) -> Result<T, RetryError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, sqlx::Error>>,
+    {
+        if !self.update_breaker(|breaker, settings| breaker.allow(Instant::now(), settings)) {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(RetryError::CircuitOpen);
+        }
+        let admission = Admission {
+            layer: self,
+            recorded: false,
+        };
+
+        let started = Instant::now();
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Ok(value) => {
+                    if attempt > 1 {
+                        self.recovered.fetch_add(1, Ordering::Relaxed);
+                        log::debug!("{} succeeded after {} attempts", name, attempt);
+                    }
+                    admission.record(false);
+                    return Ok(value);
+                }
+                Err(e) if is_transient(&e) => {
+                    let delay = self.backoff(attempt);
+                    if attempt >= self.policy.max_attempts || started.elapsed() + delay > self.policy.budget {
+                        self.exhausted.fetch_add(1, Ordering::Relaxed);
+                        log::warn!(
+                            "{} still busy after {} attempts in {:?}: {}",
+                            name,
+                            attempt,
+                            started.elapsed(),
+                            e
+                        );
+                        admission.record(true);
+                        return Err(e.into());
+                    }
+
+                    self.retries.fetch_add(1, Ordering::Relaxed);
+                    log::debug!("{} busy (attempt {}), retrying in {:?}: {}", name, attempt, delay, e);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    // The database answered; constraint and data errors say nothing about contention
+                    self.not_retried.fetch_add(1, Ordering::Relaxed);
+                    admission.record(false);
+                    return Err(e.into());
+                }
+            }
+        }
+    }
+
+    /// Exponential backoff with jitter in the upper half, so concurrent writers spread out
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self.policy.base_delay.saturating_mul(1 << (attempt - 1).min(16));
+        let half = ceiling / 2;
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        let spread = half.as_nanos().max(1) as u64;
+        half + Duration::from_nanos(u64::from(nanos) % spread)
+    }
+
+    fn record(&self, failed: bool) {
+        self.update_breaker(|breaker, settings| breaker.record(Instant::now(), settings, failed));
+    }
+
+    /// Applies `change` to the breaker and publishes any state change
+    fn update_breaker<R>(&self, change: impl FnOnce(&mut Breaker, &BreakerSettings) -> R) -> R {
+        let mut breaker = self.breaker.lock().unwrap();
+        let before = breaker.state;
+        let outcome = change(&mut breaker, &self.settings);
+        let after = breaker.state;
+        drop(breaker);
+
+        if before != after {
+            if after == BreakerState::Open {
+                self.breaker_trips.fetch_add(1, Ordering::Relaxed);
+                log::error!("Database circuit breaker opened after sustained busy/locked failures");
+            } else {
+                log::info!("Database circuit breaker {:?}", after);
+            }
+            self.state_sender.send_replace(after);
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+    use std::borrow::Cow;
+    use std::sync::atomic::AtomicU32;
+
+    /// Error a real connection returns while another one holds the write lock
+    #[derive(Debug)]
+    struct InjectedSqliteError(&'static str);
+
+    impl std::fmt::Display for InjectedSqliteError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "injected SQLite error {}", self.0)
+        }
+    }
+
+    impl std::error::Error for InjectedSqliteError {}
+
+    impl sqlx::error::DatabaseError for InjectedSqliteError {
+        fn message(&self) -> &str {
+            "database is locked"
+        }
+
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed(self.0))
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> sqlx::error::ErrorKind {
+            sqlx::error::ErrorKind::Other
+        }
+    }
+
+    fn busy() -> sqlx::Error {
+        sqlx::Error::Database(Box::new(InjectedSqliteError("5")))
+    }
+
+    #[tokio::test]
+    async fn test_busy_errors_retried_until_success() {
+        let pool = establish_test_connection().await;
+        let layer = RetryLayer::default();
+        let calls = AtomicU32::new(0);
+
+        // Busy three times, then the statement runs on the real connection
+        let value: i64 = layer
+            .run("select", || {
+                let call = calls.fetch_add(1, Ordering::Relaxed);
+                let pool = &pool;
+                async move {
+                    if call < 3 {
+                        return Err(busy());
+                    }
+                    sqlx::query_scalar("SELECT 42").fetch_one(pool).await
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(value, 42);
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+        let metrics = layer.metrics();
+        assert_eq!((metrics.retries, metrics.recovered, metrics.exhausted), (3, 1, 0));
+
+        // Never succeeding stops at the attempt budget
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = layer
+            .run("always busy", || {
+                calls.fetch_add(1, Ordering::Relaxed);
+                async { Err(busy()) }
+            })
+            .await;
+        assert!(matches!(result, Err(RetryError::Database(ref e)) if is_transient(e)));
+        assert_eq!(calls.load(Ordering::Relaxed), RetryPolicy::default().max_attempts);
+        assert_eq!(layer.metrics().exhausted, 1);
+    }
+
+    #[tokio::test]
+    async fn test_constraint_violation_not_retried() {
+        let pool = establish_test_connection().await;
+        sqlx::query("CREATE TABLE retry_probe (id TEXT PRIMARY KEY)").execute(&pool).await.unwrap();
+        sqlx::query("INSERT INTO retry_probe (id) VALUES ('A')").execute(&pool).await.unwrap();
+        let layer = RetryLayer::default();
+        let calls = AtomicU32::new(0);
+
+        let result = layer
+            .run("insert", || {
+                calls.fetch_add(1, Ordering::Relaxed);
+                sqlx::query("INSERT INTO retry_probe (id) VALUES ('A')").execute(&pool)
+            })
+            .await;
+
+        let Err(RetryError::Database(error)) = result else {
+            panic!("Expected the constraint violation to be returned");
+        };
+        assert!(!is_transient(&error));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        let metrics = layer.metrics();
+        assert_eq!((metrics.retries, metrics.not_retried), (0, 1));
+        assert!(is_transient(&busy()));
+        assert!(is_transient(&sqlx::Error::Database(Box::new(InjectedSqliteError("517")))));
+    }
+
+    #[test]
+    fn test_breaker_opens_on_sustained_failures_and_recovers() {
+        let settings = BreakerSettings {
+            window: Duration::from_secs(1),
+            min_operations: 4,
+            failure_ratio: 0.5,
+            failing_windows: 2,
+            open_for: Duration::from_secs(10),
+        };
+        let start = Instant::now();
+        let mut breaker = Breaker::new(start);
+        let at = |millis: u64| start + Duration::from_millis(millis);
+
+        // One bad window is not enough
+        for i in 0..4 {
+            breaker.record(at(i * 100), &settings, true);
+        }
+        for i in 0..4 {
+            breaker.record(at(1000 + i * 100), &settings, i % 2 == 0);
+        }
+        assert_eq!(breaker.state, BreakerState::Closed);
+
+        // The second failing window trips the breaker when it ends
+        breaker.record(at(2000), &settings, false);
+        assert_eq!(breaker.state, BreakerState::Open);
+        assert!(!breaker.allow(at(5000), &settings));
+
+        // After open_for a single trial is let through
+        assert!(breaker.allow(at(12_000), &settings));
+        assert_eq!(breaker.state, BreakerState::HalfOpen);
+        assert!(!breaker.allow(at(12_001), &settings));
+        breaker.record(at(12_010), &settings, false);
+        assert_eq!(breaker.state, BreakerState::Closed);
+        assert!(breaker.allow(at(12_020), &settings));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_trial_does_not_wedge_the_breaker() {
+        let layer = RetryLayer::new(
+            RetryPolicy::default(),
+            BreakerSettings {
+                open_for: Duration::ZERO,
+                ..BreakerSettings::default()
+            },
+        );
+        layer.update_breaker(|breaker, _| breaker.open(Instant::now()));
+
+        // The trial is cancelled mid-flight, e.g. its caller timed out
+        let trial = layer.run("trial", || std::future::pending::<Result<(), sqlx::Error>>());
+        assert!(tokio::time::timeout(Duration::from_millis(10), trial).await.is_err());
+        assert_eq!(layer.metrics().breaker_state, BreakerState::HalfOpen);
+
+        // The next operation becomes the trial and closes the breaker
+        layer.run("next", || async { Ok::<_, sqlx::Error>(()) }).await.unwrap();
+        assert_eq!(layer.metrics().breaker_state, BreakerState::Closed);
+    }
+}
@@ -1,13 +1,17 @@
 use tauri::async_runtime::block_on;
+use tauri::Manager;
 
 use crate::services::setup;
 
 pub mod api;
 pub mod app_state;
+pub mod db;
 pub mod migrations;
 pub mod models;
 pub mod protocol;
 pub mod services;
+#[cfg(test)]
+mod test_support;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -55,12 +59,119 @@ pub fn run() {
             api::commands::meril_handler::get_meril_service_status,
             api::commands::meril_handler::start_meril_service,
             api::commands::meril_handler::stop_meril_service,
+            api::commands::meril_handler::queue_meril_host_order,
+            api::commands::meril_handler::send_meril_host_orders,
+            api::commands::meril_handler::send_meril_orders,
             api::commands::bf6900_handler::fetch_bf6900_config,
             api::commands::bf6900_handler::update_bf6900_config,
             api::commands::bf6900_handler::get_bf6900_service_status,
             api::commands::bf6900_handler::start_bf6900_service,
             api::commands::bf6900_handler::stop_bf6900_service,
+            api::commands::bf6900_handler::get_parameter_catalog,
+            api::commands::import_handler::import_results_csv,
+            api::commands::export_handler::export_results,
+            api::commands::export_handler::export_results_package,
+            api::commands::import_handler::import_results_package,
+            api::commands::his_handler::preview_his_upload,
+            api::commands::his_handler::get_upload_queue_summary,
+            api::commands::his_handler::get_his_correction_pattern,
+            api::commands::his_handler::set_his_correction_pattern,
+            api::commands::his_handler::remap_and_reupload,
+            api::commands::his_handler::find_untracked_results,
+            api::commands::his_handler::set_his_simulated_failure,
+            api::commands::order_handler::list_test_orders,
+            api::commands::patient_handler::get_duplicate_detection_settings,
+            api::commands::patient_handler::set_duplicate_detection_settings,
+            api::commands::patient_handler::scan_for_duplicate_patients,
+            api::commands::patient_handler::get_duplicate_candidates,
+            api::commands::patient_handler::accept_duplicate_candidate,
+            api::commands::patient_handler::dismiss_duplicate_candidate,
+            api::commands::patient_handler::get_demographics_policy,
+            api::commands::patient_handler::set_demographics_policy,
+            api::commands::patient_handler::get_results_pending_demographics,
+            api::commands::patient_handler::update_patient_demographics,
+            api::commands::patient_handler::release_demographics_hold,
+            api::commands::patient_handler::get_verification_rules,
+            api::commands::patient_handler::save_verification_rule,
+            api::commands::patient_handler::delete_verification_rule,
+            api::commands::patient_handler::get_verification_rule_history,
+            api::commands::patient_handler::dry_run_verification_rules,
+            api::commands::patient_handler::get_results_pending_verification,
+            api::commands::patient_handler::release_verification_hold,
+            api::commands::patient_handler::get_stale_results,
+            api::commands::patient_handler::approve_results,
+            api::commands::patient_handler::get_stale_result_settings,
+            api::commands::patient_handler::set_stale_result_settings,
+            api::commands::patient_handler::get_patient_results,
+            api::commands::patient_handler::get_results_by_message_control_id,
+            api::commands::patient_handler::get_result_detail,
+            api::commands::patient_handler::get_result_changes,
+            api::commands::patient_handler::add_result_note,
+            api::commands::patient_handler::edit_test_result,
+            api::commands::system_handler::get_disk_status,
+            api::commands::system_handler::get_dashboard_counts,
+            api::commands::system_handler::get_message_timeline,
+            api::commands::system_handler::get_latency_stats,
+            api::commands::system_handler::get_analyzer_event_summary,
+            api::commands::system_handler::get_conformance_report,
+            api::commands::system_handler::get_conformance_metrics,
+            api::commands::system_handler::get_firmware_history,
+            api::commands::system_handler::get_firmware_change_conformance_mode,
+            api::commands::system_handler::set_firmware_change_conformance_mode,
+            api::commands::system_handler::get_shadow_mode,
+            api::commands::system_handler::set_shadow_mode,
+            api::commands::system_handler::get_id_normalization,
+            api::commands::system_handler::set_id_normalization,
+            api::commands::system_handler::get_forwarding_rules,
+            api::commands::system_handler::set_forwarding_rules,
+            api::commands::system_handler::test_forwarding_rules,
+            api::commands::system_handler::list_remote_addresses,
+            api::commands::system_handler::approve_remote_address,
+            api::commands::system_handler::generate_setup_sheet,
+            api::commands::system_handler::get_autostart_analyzers,
+            api::commands::system_handler::set_autostart,
+            api::commands::system_handler::generate_sample_report,
+            api::commands::system_handler::get_report_locale_settings,
+            api::commands::system_handler::set_report_locale_settings,
+            api::commands::system_handler::get_sample_attachments,
+            api::commands::system_handler::get_report_graph_settings,
+            api::commands::system_handler::set_report_graph_settings,
+            api::commands::system_handler::enter_maintenance_mode,
+            api::commands::system_handler::get_maintenance_status,
+            api::commands::system_handler::exit_maintenance_mode,
+            api::commands::system_handler::verify_database_integrity,
+            api::commands::system_handler::verify_result_hashes,
+            api::commands::system_handler::get_database_retry_metrics,
+            api::commands::system_handler::get_database_recovery_report,
+            api::commands::system_handler::get_directory_checks,
+            api::commands::system_handler::get_conversation,
+            api::commands::system_handler::get_recent_connections,
+            api::commands::system_handler::get_connection_history_settings,
+            api::commands::system_handler::set_connection_history_settings,
+            api::commands::system_handler::get_connection_history,
+            api::commands::system_handler::get_connection_retention_metrics,
+            api::commands::system_handler::get_developer_console_settings,
+            api::commands::system_handler::set_developer_console_settings,
+            api::commands::system_handler::send_raw_message,
+            api::commands::system_handler::get_setup_state,
+            api::commands::system_handler::validate_setup_step,
+            api::commands::system_handler::apply_setup_step,
+            api::commands::system_handler::complete_setup,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Let in-flight transmissions and queued uploads settle before exiting
+            if let tauri::RunEvent::ExitRequested { .. } = event {
+                if let Some(app_state) = app.try_state::<crate::app_state::AppState<tauri::Wry>>() {
+                    match block_on(app_state.prepare_shutdown()) {
+                        Ok(status) => log::info!(
+                            "Shutdown quiesced ({} connections dropped)",
+                            status.forced_disconnects
+                        ),
+                        Err(e) => log::error!("Shutdown quiesce incomplete: {}", e),
+                    }
+                }
+            }
+        });
 }
@@ -82,6 +82,781 @@ pub fn get_test_results_migration() -> Migration {
     }
 }
 
+pub fn get_result_upload_status_migration() -> Migration {
+    Migration {
+        version: 3,
+        description: "create_result_upload_status_table",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS result_upload_status (
+                id TEXT PRIMARY KEY NOT NULL,
+                result_id TEXT NOT NULL,
+                external_system_id TEXT NOT NULL,
+                status TEXT NOT NULL CHECK (status IN ('PENDING', 'UPLOADING', 'UPLOADED', 'FAILED')),
+                upload_date TEXT,
+                response_code TEXT,
+                response_message TEXT,
+                retry_count INTEGER NOT NULL DEFAULT 0,
+                payload TEXT NOT NULL, -- Serialized outbound message, so a restart can resend it
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+
+            -- Create indexes for better query performance
+            CREATE INDEX IF NOT EXISTS idx_result_upload_status_result_id ON result_upload_status(result_id);
+            CREATE INDEX IF NOT EXISTS idx_result_upload_status_status ON result_upload_status(status);
+            CREATE INDEX IF NOT EXISTS idx_result_upload_status_created_at ON result_upload_status(created_at);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_record_source_migration() -> Migration {
+    Migration {
+        version: 4,
+        description: "add_source_to_patients_and_test_results",
+        sql: r#"
+            -- Where a record came from: 'analyzer' (live interface) or 'imported' (historical import)
+            ALTER TABLE patients ADD COLUMN source TEXT NOT NULL DEFAULT 'analyzer';
+            ALTER TABLE test_results ADD COLUMN source TEXT NOT NULL DEFAULT 'analyzer';
+
+            CREATE INDEX IF NOT EXISTS idx_test_results_source ON test_results(source);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_result_operator_migration() -> Migration {
+    Migration {
+        version: 5,
+        description: "add_operator_to_test_results",
+        sql: r#"
+            -- Operator identification reported by the analyzer (ASTM R field 11)
+            ALTER TABLE test_results ADD COLUMN operator TEXT;
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_result_status_fix_migration() -> Migration {
+    Migration {
+        version: 6,
+        description: "normalize_test_result_status_codes",
+        sql: r#"
+            -- Translate any full-word statuses to the canonical single-letter codes
+            UPDATE test_results SET status = 'C' WHERE UPPER(status) IN ('CORRECTION', 'CORRECTED');
+            UPDATE test_results SET status = 'P' WHERE UPPER(status) = 'PRELIMINARY';
+            UPDATE test_results SET status = 'F' WHERE UPPER(status) = 'FINAL';
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_raw_messages_migration() -> Migration {
+    Migration {
+        version: 7,
+        description: "create_raw_messages_table",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS raw_messages (
+                id TEXT PRIMARY KEY NOT NULL,
+                analyzer_id TEXT NOT NULL,
+                protocol TEXT NOT NULL,
+                message_type TEXT NOT NULL,
+                raw_data TEXT NOT NULL,
+                timeline TEXT NOT NULL, -- JSON ProcessingTimeline
+                upload_id TEXT,
+                received_at TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+
+            -- Create indexes for better query performance
+            CREATE INDEX IF NOT EXISTS idx_raw_messages_analyzer_id ON raw_messages(analyzer_id);
+            CREATE INDEX IF NOT EXISTS idx_raw_messages_received_at ON raw_messages(received_at);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_analyzer_events_migration() -> Migration {
+    Migration {
+        version: 8,
+        description: "create_analyzer_events_table",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS analyzer_events (
+                id TEXT PRIMARY KEY NOT NULL,
+                analyzer_id TEXT NOT NULL,
+                event_type TEXT NOT NULL, -- ERROR, PARSE_ERROR, UNSUPPORTED_MESSAGE, CHECKSUM_ERROR, CORRUPT_IMAGE
+                message TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+
+            -- Create indexes for better query performance
+            CREATE INDEX IF NOT EXISTS idx_analyzer_events_analyzer_id ON analyzer_events(analyzer_id);
+            CREATE INDEX IF NOT EXISTS idx_analyzer_events_created_at ON analyzer_events(created_at);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_order_dispatch_queue_migration() -> Migration {
+    Migration {
+        version: 9,
+        description: "create_order_dispatch_queue_table",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS order_dispatch_queue (
+                id TEXT PRIMARY KEY NOT NULL,
+                order_id TEXT NOT NULL,
+                analyzer_id TEXT NOT NULL,
+                priority INTEGER NOT NULL, -- 0 = STAT/ASAP, 1 = routine
+                payload TEXT NOT NULL, -- JSON TestOrder
+                status TEXT NOT NULL DEFAULT 'PENDING', -- PENDING, SENT, ACKED, FAILED
+                attempts INTEGER NOT NULL DEFAULT 0,
+                last_error TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+
+            -- Create indexes for better query performance
+            CREATE INDEX IF NOT EXISTS idx_order_dispatch_queue_analyzer_status ON order_dispatch_queue(analyzer_id, status);
+            CREATE INDEX IF NOT EXISTS idx_order_dispatch_queue_order_id ON order_dispatch_queue(order_id);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_result_dilution_migration() -> Migration {
+    Migration {
+        version: 10,
+        description: "add_dilution_to_test_results",
+        sql: r#"
+            -- Analyzer-reported dilution factor and, when the LIS applied it, the value as received
+            ALTER TABLE test_results ADD COLUMN dilution_factor REAL;
+            ALTER TABLE test_results ADD COLUMN raw_value TEXT;
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_remote_addresses_migration() -> Migration {
+    Migration {
+        version: 11,
+        description: "create_remote_address_tables",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS analyzer_remote_addresses (
+                analyzer_id TEXT NOT NULL,
+                ip_address TEXT NOT NULL,
+                approved INTEGER NOT NULL DEFAULT 1,
+                first_seen TEXT NOT NULL,
+                last_seen TEXT NOT NULL,
+                PRIMARY KEY (analyzer_id, ip_address)
+            );
+
+            -- Messages ACKed but not ingested while their address awaits approval
+            CREATE TABLE IF NOT EXISTS held_messages (
+                id TEXT PRIMARY KEY NOT NULL,
+                analyzer_id TEXT NOT NULL,
+                ip_address TEXT NOT NULL,
+                protocol TEXT NOT NULL, -- ASTM, HL7
+                payload TEXT NOT NULL, -- JSON service event
+                received_at TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_held_messages_address ON held_messages(analyzer_id, ip_address);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_duplicate_candidates_migration() -> Migration {
+    Migration {
+        version: 12,
+        description: "create_duplicate_candidates_table",
+        sql: r#"
+            -- Suggested duplicate patient pairs; dismissed rows are kept so the pair is not suggested again
+            CREATE TABLE IF NOT EXISTS duplicate_candidates (
+                id TEXT PRIMARY KEY NOT NULL,
+                patient_id_a TEXT NOT NULL,
+                patient_id_b TEXT NOT NULL,
+                score REAL NOT NULL,
+                reasons TEXT NOT NULL, -- JSON array of matched heuristics
+                status TEXT NOT NULL DEFAULT 'PENDING' CHECK (status IN ('PENDING', 'ACCEPTED', 'DISMISSED')),
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                UNIQUE (patient_id_a, patient_id_b)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_duplicate_candidates_status_score ON duplicate_candidates(status, score);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_demographics_holds_migration() -> Migration {
+    Migration {
+        version: 13,
+        description: "create_demographics_holds_table",
+        sql: r#"
+            -- Results kept from HIS auto-upload until the patient's mandatory demographics are complete
+            CREATE TABLE IF NOT EXISTS demographics_holds (
+                id TEXT PRIMARY KEY NOT NULL,
+                patient_id TEXT,
+                sample_id TEXT NOT NULL,
+                analyzer_id TEXT NOT NULL,
+                missing_fields TEXT NOT NULL, -- JSON array of required fields that were missing
+                payload TEXT NOT NULL, -- Serialized HIS payload, queued on release
+                status TEXT NOT NULL DEFAULT 'PENDING_DEMOGRAPHICS' CHECK (status IN ('PENDING_DEMOGRAPHICS', 'RELEASED')),
+                upload_id TEXT,
+                created_at TEXT NOT NULL,
+                released_at TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_demographics_holds_patient_status ON demographics_holds(patient_id, status);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_result_content_hash_migration() -> Migration {
+    Migration {
+        version: 14,
+        description: "add_content_hash_to_test_results",
+        sql: r#"
+            -- SHA-256 of the normalized result content; NULL for results stored before hashing
+            ALTER TABLE test_results ADD COLUMN content_hash TEXT;
+
+            CREATE INDEX IF NOT EXISTS idx_test_results_content_hash ON test_results(content_hash);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_upload_priority_migration() -> Migration {
+    Migration {
+        version: 15,
+        description: "add_priority_and_claims_to_result_upload_status",
+        sql: r#"
+            -- 0 = critical, 1 = STAT, 2 = routine; rows queued before this are routine
+            ALTER TABLE result_upload_status ADD COLUMN priority INTEGER NOT NULL DEFAULT 2;
+            ALTER TABLE result_upload_status ADD COLUMN claimed_by TEXT;
+            ALTER TABLE result_upload_status ADD COLUMN claimed_at TEXT;
+
+            ALTER TABLE demographics_holds ADD COLUMN priority INTEGER NOT NULL DEFAULT 2;
+
+            CREATE INDEX IF NOT EXISTS idx_result_upload_status_dispatch
+                ON result_upload_status(status, priority, created_at);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_conformance_warnings_migration() -> Migration {
+    Migration {
+        version: 16,
+        description: "create_conformance_warnings_table",
+        sql: r#"
+            -- Spec deviations in messages that still parsed; message_ref is the HL7 control ID or ASTM transmission ID
+            CREATE TABLE IF NOT EXISTS conformance_warnings (
+                id TEXT PRIMARY KEY,
+                analyzer_id TEXT NOT NULL,
+                protocol TEXT NOT NULL,
+                rule_id TEXT NOT NULL,
+                description TEXT NOT NULL,
+                detail TEXT NOT NULL,
+                message_ref TEXT,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_conformance_warnings_analyzer_created
+                ON conformance_warnings(analyzer_id, created_at);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_upload_remediations_migration() -> Migration {
+    Migration {
+        version: 17,
+        description: "create_upload_remediations_table",
+        sql: r#"
+            -- Corrections sent for uploads that went out under a wrong HIS test mapping
+            CREATE TABLE IF NOT EXISTS upload_remediations (
+                id TEXT PRIMARY KEY,
+                original_upload_id TEXT NOT NULL,
+                sample_id TEXT NOT NULL,
+                test_code TEXT NOT NULL,
+                pattern TEXT NOT NULL,
+                cancel_upload_id TEXT,
+                new_upload_id TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY(original_upload_id) REFERENCES result_upload_status(id)
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_upload_remediations_original ON upload_remediations(original_upload_id);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_firmware_changes_migration() -> Migration {
+    Migration {
+        version: 18,
+        description: "create_firmware_changes_table",
+        sql: r#"
+            -- Changes in the identity (MSH-3/4/12, ASTM H.5/H.13) an analyzer declares; identities are JSON
+            CREATE TABLE IF NOT EXISTS firmware_changes (
+                id TEXT PRIMARY KEY,
+                analyzer_id TEXT NOT NULL,
+                protocol TEXT NOT NULL,
+                previous_identity TEXT NOT NULL,
+                current_identity TEXT NOT NULL,
+                previous_since TEXT,
+                changed_at TEXT NOT NULL,
+                conformance_report_until TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_firmware_changes_analyzer_changed
+                ON firmware_changes(analyzer_id, changed_at);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_analyzer_autostart_migration() -> Migration {
+    Migration {
+        version: 19,
+        description: "create_analyzer_autostart_table",
+        sql: r#"
+            -- Analyzers started with the LIS; analyzers without a row use their activate_on_start flag
+            CREATE TABLE IF NOT EXISTS analyzer_autostart (
+                analyzer_id TEXT PRIMARY KEY,
+                enabled INTEGER NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_result_source_control_id_migration() -> Migration {
+    Migration {
+        version: 20,
+        description: "add_source_message_control_id_to_test_results",
+        sql: r#"
+            -- MSH-10 (HL7) or H.3 (ASTM) of the inbound message that carried the result
+            ALTER TABLE test_results ADD COLUMN source_message_control_id TEXT;
+
+            CREATE INDEX IF NOT EXISTS idx_test_results_source_message_control_id
+                ON test_results(source_message_control_id);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_patient_contact_details_migration() -> Migration {
+    Migration {
+        version: 21,
+        description: "add_structured_contact_details_to_patients",
+        sql: r#"
+            -- XAD-2 other designation (apartment, building, ...)
+            ALTER TABLE patients ADD COLUMN address_other_designation TEXT;
+            -- Address as received, and whether it could be split into components
+            ALTER TABLE patients ADD COLUMN address_raw TEXT;
+            ALTER TABLE patients ADD COLUMN address_unparsed INTEGER NOT NULL DEFAULT 0;
+            -- JSON array of structured phone numbers (ContactInfo)
+            ALTER TABLE patients ADD COLUMN contacts TEXT;
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_result_supersede_chain_migration() -> Migration {
+    Migration {
+        version: 22,
+        description: "add_superseded_by_to_test_results",
+        sql: r#"
+            -- Id of the result that replaced this one (manual edit or analyzer correction)
+            ALTER TABLE test_results ADD COLUMN superseded_by TEXT;
+
+            CREATE INDEX IF NOT EXISTS idx_test_results_sample_test
+                ON test_results(sample_id, test_id);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_result_notes_and_images_migration() -> Migration {
+    Migration {
+        version: 23,
+        description: "create_result_notes_and_images_tables",
+        sql: r#"
+            -- Free-text notes technologists attach to a result
+            CREATE TABLE IF NOT EXISTS result_notes (
+                id TEXT PRIMARY KEY NOT NULL,
+                result_id TEXT NOT NULL,
+                author TEXT,
+                text TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+
+            -- Images kept for a result (histograms, scattergrams); the bytes live at `reference`
+            CREATE TABLE IF NOT EXISTS result_images (
+                id TEXT PRIMARY KEY NOT NULL,
+                result_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                mime_type TEXT NOT NULL,
+                reference TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_result_notes_result_id ON result_notes(result_id);
+            CREATE INDEX IF NOT EXISTS idx_result_images_result_id ON result_images(result_id);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_connection_events_migration() -> Migration {
+    Migration {
+        version: 24,
+        description: "create_connection_events_table",
+        sql: r#"
+            -- Every closed analyzer connection; only the most recent ones stay in memory
+            CREATE TABLE IF NOT EXISTS connection_events (
+                connection_id TEXT PRIMARY KEY NOT NULL,
+                analyzer_id TEXT NOT NULL,
+                protocol TEXT NOT NULL,
+                remote_address TEXT NOT NULL,
+                opened_at TEXT NOT NULL,
+                closed_at TEXT,
+                entries INTEGER NOT NULL,
+                dropped_entries INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_connection_events_analyzer_closed
+                ON connection_events(analyzer_id, closed_at);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_connection_termination_migration() -> Migration {
+    Migration {
+        version: 25,
+        description: "add_connection_termination",
+        sql: r#"
+            -- How each archived connection ended (COMPLETED, REMOTE_CLOSED, TIMEOUT, ERROR, SHUTDOWN)
+            ALTER TABLE connection_events ADD COLUMN termination TEXT;
+
+            CREATE INDEX IF NOT EXISTS idx_connection_events_closed_termination
+                ON connection_events(closed_at, termination);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_verification_rules_migration() -> Migration {
+    Migration {
+        version: 26,
+        description: "create_verification_rules_and_holds",
+        sql: r#"
+            -- Every version of every auto-verification rule; a saved edit adds a version
+            CREATE TABLE IF NOT EXISTS verification_rules (
+                id TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                enabled INTEGER NOT NULL,
+                priority INTEGER NOT NULL,
+                definition TEXT NOT NULL, -- JSON scope, condition and decision
+                created_at TEXT NOT NULL,
+                retired_at TEXT,
+                PRIMARY KEY (id, version)
+            );
+
+            -- Samples the verification rules did not release, waiting for technologist review
+            CREATE TABLE IF NOT EXISTS verification_holds (
+                id TEXT PRIMARY KEY NOT NULL,
+                patient_id TEXT,
+                sample_id TEXT NOT NULL,
+                analyzer_id TEXT NOT NULL,
+                decisions TEXT NOT NULL, -- JSON array of [test id, stamp]
+                payload TEXT NOT NULL, -- Serialized HIS payload, submitted on release
+                priority INTEGER NOT NULL DEFAULT 2,
+                status TEXT NOT NULL DEFAULT 'PENDING_VERIFICATION' CHECK (status IN ('PENDING_VERIFICATION', 'RELEASED')),
+                upload_id TEXT,
+                created_at TEXT NOT NULL,
+                released_at TEXT
+            );
+
+            -- Which rule version released or held a stored result
+            ALTER TABLE test_results ADD COLUMN verification_decision TEXT;
+            ALTER TABLE test_results ADD COLUMN verification_rule_id TEXT;
+            ALTER TABLE test_results ADD COLUMN verification_rule_version INTEGER;
+            ALTER TABLE test_results ADD COLUMN verified_at TEXT;
+
+            CREATE INDEX IF NOT EXISTS idx_verification_holds_status ON verification_holds(status, created_at);
+            CREATE INDEX IF NOT EXISTS idx_test_results_patient_test ON test_results(patient_id, test_id);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_review_reason_migration() -> Migration {
+    Migration {
+        version: 27,
+        description: "add_review_reason_to_verification_holds",
+        sql: r#"
+            -- Held results are reviewed in separate queues by why they were held
+            ALTER TABLE verification_holds ADD COLUMN reason TEXT NOT NULL DEFAULT 'VERIFICATION'
+                CHECK (reason IN ('VERIFICATION', 'STALE'));
+            ALTER TABLE verification_holds ADD COLUMN reason_detail TEXT;
+
+            CREATE INDEX IF NOT EXISTS idx_verification_holds_reason ON verification_holds(reason, status, created_at);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_result_comments_migration() -> Migration {
+    Migration {
+        version: 28,
+        description: "add_comments_to_test_results",
+        sql: r#"
+            -- Comments the analyzer attached to a result (instrument flags, operator remarks)
+            ALTER TABLE test_results ADD COLUMN comments TEXT; -- JSON array of strings
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_upload_backoff_migration() -> Migration {
+    Migration {
+        version: 29,
+        description: "add_next_attempt_at_to_result_upload_status",
+        sql: r#"
+            -- Earliest time a failed upload is sent again; NULL sends it on the next poll
+            ALTER TABLE result_upload_status ADD COLUMN next_attempt_at TEXT;
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_result_changes_migration() -> Migration {
+    Migration {
+        version: 30,
+        description: "create_result_changes_feed",
+        sql: r#"
+            -- Change feed for external consumers. AUTOINCREMENT never reuses a seq, even
+            -- after the highest row is deleted, so cursors stay valid across restarts.
+            CREATE TABLE IF NOT EXISTS result_changes (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                result_id TEXT NOT NULL,
+                change_type TEXT NOT NULL CHECK (change_type IN ('INSERT', 'UPDATE', 'DELETE')),
+                changed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+            );
+
+            -- Triggers catch every writer, including upserts and bulk updates
+            CREATE TRIGGER IF NOT EXISTS trg_test_results_insert_change AFTER INSERT ON test_results
+            BEGIN
+                INSERT INTO result_changes (result_id, change_type) VALUES (NEW.id, 'INSERT');
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS trg_test_results_update_change AFTER UPDATE ON test_results
+            BEGIN
+                INSERT INTO result_changes (result_id, change_type) VALUES (NEW.id, 'UPDATE');
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS trg_test_results_delete_change AFTER DELETE ON test_results
+            BEGIN
+                INSERT INTO result_changes (result_id, change_type) VALUES (OLD.id, 'DELETE');
+            END;
+
+            -- Results stored before the feed existed are reported as inserted
+            INSERT INTO result_changes (result_id, change_type, changed_at)
+            SELECT id, 'INSERT', created_at FROM test_results ORDER BY rowid;
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_unassigned_results_migration() -> Migration {
+    Migration {
+        version: 31,
+        description: "create_unassigned_results_and_patient_comments",
+        sql: r#"
+            -- Results received without a patient id, held against their sample until
+            -- a later message or a technologist names the patient
+            CREATE TABLE IF NOT EXISTS unassigned_results (
+                id TEXT PRIMARY KEY NOT NULL,     -- Id of the held result
+                sample_id TEXT NOT NULL,
+                analyzer_id TEXT,
+                source TEXT NOT NULL,
+                result TEXT NOT NULL,             -- JSON TestResult
+                created_at TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_unassigned_results_sample ON unassigned_results(sample_id, created_at);
+
+            -- Comment records the analyzer attached to the patient
+            ALTER TABLE patients ADD COLUMN comments TEXT; -- JSON array of strings
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_out_of_order_review_migration() -> Migration {
+    Migration {
+        version: 32,
+        description: "add_out_of_order_review_reason",
+        sql: r#"
+            -- Messages that reach ingestion after a newer one for their sample are held
+            -- for review; SQLite cannot alter a CHECK, so the table is rebuilt
+            CREATE TABLE verification_holds_new (
+                id TEXT PRIMARY KEY NOT NULL,
+                patient_id TEXT,
+                sample_id TEXT NOT NULL,
+                analyzer_id TEXT NOT NULL,
+                decisions TEXT NOT NULL, -- JSON array of [test id, stamp]
+                payload TEXT NOT NULL, -- Serialized HIS payload, submitted on release
+                priority INTEGER NOT NULL DEFAULT 2,
+                status TEXT NOT NULL DEFAULT 'PENDING_VERIFICATION' CHECK (status IN ('PENDING_VERIFICATION', 'RELEASED')),
+                upload_id TEXT,
+                created_at TEXT NOT NULL,
+                released_at TEXT,
+                reason TEXT NOT NULL DEFAULT 'VERIFICATION'
+                    CHECK (reason IN ('VERIFICATION', 'STALE', 'OUT_OF_ORDER')),
+                reason_detail TEXT
+            );
+
+            INSERT INTO verification_holds_new (
+                id, patient_id, sample_id, analyzer_id, decisions, payload, priority, status,
+                upload_id, created_at, released_at, reason, reason_detail
+            )
+            SELECT
+                id, patient_id, sample_id, analyzer_id, decisions, payload, priority, status,
+                upload_id, created_at, released_at, reason, reason_detail
+            FROM verification_holds;
+
+            DROP TABLE verification_holds;
+            ALTER TABLE verification_holds_new RENAME TO verification_holds;
+
+            CREATE INDEX IF NOT EXISTS idx_verification_holds_status ON verification_holds(status, created_at);
+            CREATE INDEX IF NOT EXISTS idx_verification_holds_reason ON verification_holds(reason, status, created_at);
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_unique_content_hash_migration() -> Migration {
+    Migration {
+        version: 33,
+        description: "make_test_result_content_hash_unique",
+        sql: r#"
+            -- Retransmissions are rejected by the database itself: a hash is unique per
+            -- analyzer and patient, so stored hashes stay valid and are never recomputed.
+            -- A result without a completion time is no longer hashed, as a rerun cannot be
+            -- told apart from a retransmission.
+            UPDATE test_results SET content_hash = NULL WHERE completed_date_time IS NULL;
+
+            -- Copies that slipped past the old lookup keep their row but lose the hash
+            UPDATE test_results SET content_hash = NULL
+            WHERE content_hash IS NOT NULL
+              AND rowid NOT IN (
+                  SELECT MIN(rowid) FROM test_results
+                  WHERE content_hash IS NOT NULL
+                  GROUP BY IFNULL(analyzer_id, ''), patient_id, content_hash
+              );
+
+            DROP INDEX IF EXISTS idx_test_results_content_hash;
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_test_results_content_hash
+                ON test_results(IFNULL(analyzer_id, ''), patient_id, content_hash)
+                WHERE content_hash IS NOT NULL AND content_hash != '';
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_hematology_result_context_migration() -> Migration {
+    Migration {
+        version: 34,
+        description: "add_hematology_context_to_test_results",
+        sql: r#"
+            -- Hematology results keep the analyzer's parameter name and the run they came from
+            ALTER TABLE test_results ADD COLUMN parameter_name TEXT;
+            ALTER TABLE test_results ADD COLUMN panel TEXT;
+            ALTER TABLE test_results ADD COLUMN analysis_mode TEXT;
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_order_dispatch_patient_migration() -> Migration {
+    Migration {
+        version: 35,
+        description: "add_patient_to_order_dispatch_queue",
+        sql: r#"
+            -- Patient the order was queued for; checked before the order is sent under a P record
+            ALTER TABLE order_dispatch_queue ADD COLUMN patient_id TEXT;
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
+pub fn get_sample_arrivals_migration() -> Migration {
+    Migration {
+        version: 36,
+        description: "create_sample_arrivals",
+        sql: r#"
+            -- Latest arrival sequence applied per analyzer and sample, so message order
+            -- is still checked across reconnects and restarts
+            CREATE TABLE IF NOT EXISTS sample_arrivals (
+                analyzer_id TEXT NOT NULL,
+                sample_id TEXT NOT NULL,
+                arrival_sequence INTEGER NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (analyzer_id, sample_id)
+            );
+        "#,
+        kind: MigrationKind::Up,
+    }
+}
+
 pub fn get_migrations() -> Vec<Migration> {
-    vec![get_patients_migration(), get_test_results_migration()]
+    vec![
+        get_patients_migration(),
+        get_test_results_migration(),
+        get_result_upload_status_migration(),
+        get_record_source_migration(),
+        get_result_operator_migration(),
+        get_result_status_fix_migration(),
+        get_raw_messages_migration(),
+        get_analyzer_events_migration(),
+        get_order_dispatch_queue_migration(),
+        get_result_dilution_migration(),
+        get_remote_addresses_migration(),
+        get_duplicate_candidates_migration(),
+        get_demographics_holds_migration(),
+        get_result_content_hash_migration(),
+        get_upload_priority_migration(),
+        get_conformance_warnings_migration(),
+        get_upload_remediations_migration(),
+        get_firmware_changes_migration(),
+        get_analyzer_autostart_migration(),
+        get_result_source_control_id_migration(),
+        get_patient_contact_details_migration(),
+        get_result_supersede_chain_migration(),
+        get_result_notes_and_images_migration(),
+        get_connection_events_migration(),
+        get_connection_termination_migration(),
+        get_verification_rules_migration(),
+        get_review_reason_migration(),
+        get_result_comments_migration(),
+        get_upload_backoff_migration(),
+        get_result_changes_migration(),
+        get_unassigned_results_migration(),
+        get_out_of_order_review_migration(),
+        get_unique_content_hash_migration(),
+        get_hematology_result_context_migration(),
+        get_order_dispatch_patient_migration(),
+        get_sample_arrivals_migration(),
+    ]
 }
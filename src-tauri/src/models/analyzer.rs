@@ -1,6 +1,9 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::patient::SexCodeMap;
+use super::result::DilutionMode;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ConnectionType {
     Serial,
@@ -58,6 +61,7 @@ pub enum Protocol {
     Hl7,
     Hl7V24, // HL7 version 2.4 for BF-6900 Hematology analyzer
     Hl7V231, // HL7 version 2.3.1 for BF-6900 Hematology analyzer (CQ 5 Plus)
+    DelimitedText, // Proprietary delimited ASCII lines, decoded with the analyzer's `delimited_layout`
 }
 
 impl ToString for Protocol {
@@ -67,16 +71,29 @@ impl ToString for Protocol {
             Protocol::Hl7 => "HL7".to_string(),
             Protocol::Hl7V24 => "HL7_V24".to_string(),
             Protocol::Hl7V231 => "HL7_V231".to_string(),
+            Protocol::DelimitedText => "DELIMITED_TEXT".to_string(),
         }
     }
 }
 
+impl Protocol {
+    pub fn is_hl7(&self) -> bool {
+        matches!(self, Protocol::Hl7 | Protocol::Hl7V24 | Protocol::Hl7V231)
+    }
+
+    /// True for two HL7 versions, or the same protocol otherwise
+    pub fn same_family(&self, other: &Protocol) -> bool {
+        (self.is_hl7() && other.is_hl7()) || self == other
+    }
+}
+
 impl From<&str> for Protocol {
     fn from(s: &str) -> Self {
         match s.to_uppercase().as_str() {
             "HL7" => Protocol::Hl7,
             "HL7_V24" => Protocol::Hl7V24,
             "HL7_V231" => Protocol::Hl7V231,
+            "DELIMITED_TEXT" => Protocol::DelimitedText,
             _ => Protocol::Astm,
         }
     }
@@ -103,6 +120,271 @@ pub struct Analyzer {
     pub protocol: Protocol,
     pub status: AnalyzerStatus,
     pub activate_on_start: bool,
+    /// Whether reported values still need the analyzer's dilution factor applied
+    #[serde(default)]
+    pub dilution_mode: DilutionMode,
+    /// Holds ingestion from previously unseen remote addresses until an operator approves them
+    #[serde(default)]
+    pub strict_remote_address: bool,
+    /// Sender id (H.5) declared in the analyzer's last ASTM header record
+    #[serde(default)]
+    pub astm_sender_id: Option<String>,
+    /// ASTM version (H.13) declared in the analyzer's last header record, e.g. "E 1394-97"
+    #[serde(default)]
+    pub astm_version: Option<String>,
+    /// Streams each parsed result to the UI as provisional before the transmission completes
+    #[serde(default)]
+    pub stream_provisional_results: bool,
+    /// Local address the listener binds to (IPv4 or IPv6 literal); `None` means `0.0.0.0`
+    #[serde(default)]
+    pub bind_address: Option<String>,
+    /// Accept IPv4 clients on the IPv6 wildcard `::` as well
+    #[serde(default)]
+    pub dual_stack: bool,
+    /// Pending connections the OS may queue before the listener accepts them
+    #[serde(default = "default_listen_backlog")]
+    pub listen_backlog: u32,
+    /// Open connections allowed from one remote IP; `None` means no limit. The count
+    /// includes a connection a reconnect would replace, so a limit of 1 refuses a
+    /// reconnect until the analyzer's previous socket has closed.
+    #[serde(default)]
+    pub max_connections_per_ip: Option<u32>,
+    /// ASTM: milliseconds to wait after ACKing an EOT before answering the next ENQ
+    #[serde(default)]
+    pub post_eot_delay_ms: u64,
+    /// ASTM: receive timers that abort a stalled transmission
+    #[serde(default)]
+    pub astm_timeouts: AstmTimeouts,
+    /// ASTM: start a transmission on STX for senders that never send ENQ
+    #[serde(default)]
+    pub accept_frames_without_enq: bool,
+    /// ASTM: the host bids for the line (ENQ) on connect to download queued orders
+    #[serde(default)]
+    pub host_initiated: bool,
+    /// ASTM: times a NAKed frame is retransmitted before the transfer is abandoned, both
+    /// for frames the host sends and for frames the host rejects on receipt
+    #[serde(default = "default_astm_frame_retries")]
+    pub astm_frame_retries: u32,
+    /// ASTM: whether a frame with a bad checksum is NAKed or accepted with a warning
+    #[serde(default)]
+    pub checksum_policy: ChecksumPolicy,
+    /// Seconds the analyzer's clock runs ahead of server time (negative when behind),
+    /// subtracted from the completion dates it reports. Only used for messages whose
+    /// header (ASTM H.14, HL7 MSH-7) carries no date to measure the skew from.
+    #[serde(default)]
+    pub clock_skew_seconds: i64,
+    /// ASTM: how far a result's completion date may lie from server time before it is rejected
+    #[serde(default)]
+    pub result_acceptance: ResultAcceptanceWindow,
+    /// Patient class (e.g. I inpatient, O outpatient) uploaded to the HIS when the message
+    /// carries none; HL7 messages carry it in PV1-2
+    #[serde(default)]
+    pub default_patient_class: Option<String>,
+    /// ASTM: non-standard patient sex codes (e.g. 1/2/0) and the sex they stand for
+    #[serde(default)]
+    pub sex_codes: SexCodeMap,
+    /// Delimited text: which column holds which result field
+    #[serde(default)]
+    pub delimited_layout: DelimitedLayout,
+    /// Logs 1 in N processed messages at info (1 logs every message); errors are always logged
+    #[serde(default = "default_log_sample_rate")]
+    pub log_sample_rate: u32,
+    /// Identity declared by the instrument's messages, compared on every message
+    #[serde(default)]
+    pub last_seen_identity: Option<AnalyzerIdentity>,
+    /// When `last_seen_identity` was first declared
+    #[serde(default)]
+    pub identity_since: Option<DateTime<Utc>>,
+    /// After a firmware change, every message is logged in full until then
+    #[serde(default)]
+    pub conformance_report_until: Option<DateTime<Utc>>,
+    /// When an analyzer last connected to the listener
+    #[serde(default)]
+    pub last_connected_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
+
+impl Analyzer {
+    /// Applies a settings save on top of this analyzer. Fields present in `update`
+    /// (as serialized) replace the stored ones; fields the caller left out keep their
+    /// stored values instead of falling back to their defaults.
+    pub fn merged_with(&self, update: serde_json::Value) -> Result<Analyzer, String> {
+        let serde_json::Value::Object(update) = update else {
+            return Err("Analyzer settings must be an object".to_string());
+        };
+        let mut merged =
+            serde_json::to_value(self).map_err(|e| format!("Failed to serialize analyzer {}: {}", self.id, e))?;
+        if let serde_json::Value::Object(fields) = &mut merged {
+            fields.extend(update);
+        }
+        serde_json::from_value(merged).map_err(|e| format!("Invalid analyzer settings: {}", e))
+    }
+}
+
+fn default_log_sample_rate() -> u32 {
+    1
+}
+
+fn default_astm_frame_retries() -> u32 {
+    6
+}
+
+fn default_listen_backlog() -> u32 {
+    128
+}
+
+/// Identity an instrument declares in its messages; a change usually means new firmware
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnalyzerIdentity {
+    pub protocol: String,
+    pub application: Option<String>, // MSH-3, or ASTM sender id (H.5)
+    pub facility: Option<String>,    // MSH-4; not declared by ASTM
+    pub version: Option<String>,     // MSH-12, or ASTM version (H.13)
+}
+
+/// What the ASTM receiver does with a frame whose checksum does not match
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ChecksumPolicy {
+    /// ACK and keep the frame, recording the mismatch on the message
+    Lenient,
+    /// NAK the frame so the analyzer retransmits it; the frame is not kept
+    #[default]
+    Strict,
+}
+
+/// Completion dates accepted on results, relative to server time. Results dated
+/// outside the window come from an analyzer with a wrong clock and are rejected;
+/// `None` leaves that side unbounded.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ResultAcceptanceWindow {
+    /// Oldest accepted completion date, in days before now
+    pub max_past_days: Option<u32>,
+    /// Latest accepted completion date, in days after now
+    pub max_future_days: Option<u32>,
+}
+
+impl ResultAcceptanceWindow {
+    /// Why a result completed at `completed_at` falls outside the window at server
+    /// time `now`, or `None` when it is accepted
+    pub fn rejection(&self, completed_at: DateTime<Utc>, now: DateTime<Utc>) -> Option<String> {
+        if let Some(days) = self.max_past_days {
+            if completed_at < now - chrono::Duration::days(days as i64) {
+                return Some(format!(
+                    "Result dated {} is more than {} days before server time {}",
+                    completed_at.to_rfc3339(),
+                    days,
+                    now.to_rfc3339()
+                ));
+            }
+        }
+        if let Some(days) = self.max_future_days {
+            if completed_at > now + chrono::Duration::days(days as i64) {
+                return Some(format!(
+                    "Result dated {} is more than {} days after server time {}",
+                    completed_at.to_rfc3339(),
+                    days,
+                    now.to_rfc3339()
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// ASTM receive timers, in milliseconds
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct AstmTimeouts {
+    /// Longest gap between two bytes of the same frame
+    pub inter_byte_ms: u64,
+    /// Longest wait for the next STX or EOT after a frame is acknowledged
+    pub inter_frame_ms: u64,
+    /// Longest a whole transmission may take, from ENQ to EOT
+    pub transmission_ms: u64,
+    /// Longest our ACK or NAK for a frame may take after the frame's LF
+    pub ack_ms: u64,
+}
+
+impl Default for AstmTimeouts {
+    /// Receiver timeouts of ASTM E1381: 15 s within a frame, 30 s between frames.
+    /// The AutoQuant gives up on an ACK after 15 s, so frames are answered within 10 s.
+    fn default() -> Self {
+        Self {
+            inter_byte_ms: 15_000,
+            inter_frame_ms: 30_000,
+            transmission_ms: 600_000,
+            ack_ms: 10_000,
+        }
+    }
+}
+
+impl AstmTimeouts {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.inter_byte_ms == 0 || self.inter_frame_ms == 0 || self.transmission_ms == 0 || self.ack_ms == 0 {
+            return Err("ASTM timeouts must be greater than zero".to_string());
+        }
+        if self.inter_byte_ms > self.transmission_ms || self.inter_frame_ms > self.transmission_ms {
+            return Err(format!(
+                "ASTM transmission timeout ({} ms) must not be shorter than the inter-byte ({} ms) or inter-frame ({} ms) timeout",
+                self.transmission_ms, self.inter_byte_ms, self.inter_frame_ms
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Column layout of a delimited-text result line (0-based columns), e.g.
+/// `S1001,GLU,95.2,mg/dL,N,20250101120500`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DelimitedLayout {
+    pub delimiter: char,
+    /// Skip the first line of every message
+    pub has_header: bool,
+    pub sample_id_column: usize,
+    pub test_column: usize,
+    pub value_column: usize,
+    pub units_column: Option<usize>,
+    pub flag_column: Option<usize>,
+    /// Required: lines without a completion time are rejected
+    pub completed_at_column: Option<usize>,
+    /// chrono format of `completed_at_column`, read as UTC
+    pub completed_at_format: String,
+}
+
+impl Default for DelimitedLayout {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            has_header: false,
+            sample_id_column: 0,
+            test_column: 1,
+            value_column: 2,
+            units_column: Some(3),
+            flag_column: Some(4),
+            completed_at_column: Some(5),
+            completed_at_format: "%Y%m%d%H%M%S".to_string(),
+        }
+    }
+}
+
+impl DelimitedLayout {
+    pub fn validate(&self) -> Result<(), String> {
+        if matches!(self.delimiter, '\r' | '\n' | '"') {
+            return Err(format!("{:?} cannot be used as the delimiter", self.delimiter));
+        }
+        let required = [self.sample_id_column, self.test_column, self.value_column];
+        if required[0] == required[1] || required[0] == required[2] || required[1] == required[2] {
+            return Err("Sample id, test and value must be in different columns".to_string());
+        }
+        if self.completed_at_column.is_none() {
+            return Err("Results need a completion time column".to_string());
+        }
+        if self.completed_at_format.trim().is_empty() {
+            return Err("The completion time column needs a time format".to_string());
+        }
+        Ok(())
+    }
+}
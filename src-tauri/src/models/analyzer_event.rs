@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// ANALYZER EVENTS
+// ============================================================================
+
+/// Kind of a stored analyzer error/event, used for the per-analyzer health panel
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum AnalyzerEventType {
+    Error,              // Generic processing/connection error
+    ParseError,         // Record/segment could not be parsed
+    UnsupportedMessage, // Message or record type the LIS does not handle
+    ChecksumError,      // Frame checksum mismatch
+    CorruptImage,       // ED image payload failed validation
+    NewRemoteAddress,   // Connection from a previously unseen remote IP
+    Timeout,            // Protocol timer expired and the transmission was aborted
+    RawMessageSent,     // Developer console sent a hand-written message (audit)
+}
+
+impl AnalyzerEventType {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            AnalyzerEventType::Error => "ERROR",
+            AnalyzerEventType::ParseError => "PARSE_ERROR",
+            AnalyzerEventType::UnsupportedMessage => "UNSUPPORTED_MESSAGE",
+            AnalyzerEventType::ChecksumError => "CHECKSUM_ERROR",
+            AnalyzerEventType::CorruptImage => "CORRUPT_IMAGE",
+            AnalyzerEventType::NewRemoteAddress => "NEW_REMOTE_ADDRESS",
+            AnalyzerEventType::Timeout => "TIMEOUT",
+            AnalyzerEventType::RawMessageSent => "RAW_MESSAGE_SENT",
+        }
+    }
+
+    pub fn from_db_str(s: &str) -> Self {
+        match s.to_uppercase().as_str() {
+            "PARSE_ERROR" => AnalyzerEventType::ParseError,
+            "UNSUPPORTED_MESSAGE" => AnalyzerEventType::UnsupportedMessage,
+            "CHECKSUM_ERROR" => AnalyzerEventType::ChecksumError,
+            "CORRUPT_IMAGE" => AnalyzerEventType::CorruptImage,
+            "NEW_REMOTE_ADDRESS" => AnalyzerEventType::NewRemoteAddress,
+            "TIMEOUT" => AnalyzerEventType::Timeout,
+            "RAW_MESSAGE_SENT" => AnalyzerEventType::RawMessageSent,
+            _ => AnalyzerEventType::Error,
+        }
+    }
+
+    /// Classifies a service error message into an event type
+    pub fn classify(error: &str) -> Self {
+        let error = error.to_lowercase();
+        if error.contains("checksum") {
+            AnalyzerEventType::ChecksumError
+        } else if error.contains("timeout") {
+            AnalyzerEventType::Timeout
+        } else if error.contains("unsupported") {
+            AnalyzerEventType::UnsupportedMessage
+        } else if error.contains("parse") || error.contains("invalid") {
+            AnalyzerEventType::ParseError
+        } else {
+            AnalyzerEventType::Error
+        }
+    }
+}
+
+/// Count of one event type within a summary window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventTypeCount {
+    pub event_type: AnalyzerEventType,
+    pub count: u64,
+    pub last_message: String,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Recent events of an analyzer grouped by type, most frequent first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventSummary {
+    pub analyzer_id: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub total: u64,
+    pub by_type: Vec<EventTypeCount>,
+}
@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// PROTOCOL CONFORMANCE
+// ============================================================================
+
+/// A spec deviation found in a message that otherwise parsed; never fatal
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConformanceWarning {
+    pub rule_id: String,
+    pub description: String,
+    pub detail: String,
+}
+
+/// How often one rule was violated by an analyzer within a date range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConformanceRuleCount {
+    pub rule_id: String,
+    pub description: String,
+    pub protocol: String,
+    pub count: u64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub last_detail: String,
+}
+
+/// Deviations an analyzer's firmware produced between `from` and `to`, most frequent first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConformanceReport {
+    pub analyzer_id: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub total: u64,
+    pub by_rule: Vec<ConformanceRuleCount>,
+}
@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// CONNECTIONS
+// ============================================================================
+
+/// One analyzer connection, open or closed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConnectionSummary {
+    pub connection_id: String,
+    pub analyzer_id: String,
+    pub protocol: String,
+    pub remote_address: String,
+    pub opened_at: DateTime<Utc>,
+    /// `None` while the connection is open
+    pub closed_at: Option<DateTime<Utc>>,
+    pub entries: usize,
+    /// Entries pushed out of the ring
+    pub dropped_entries: u64,
+    /// How the connection ended; `None` while open
+    #[serde(default)]
+    pub termination: Option<ConnectionTermination>,
+}
+
+/// Why a connection ended. Many analyzers close the socket after every transmission,
+/// so only the abnormal kinds are worth an alarm.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConnectionTermination {
+    /// The analyzer closed after its last transmission ended normally (ASTM EOT,
+    /// every HL7 message acknowledged)
+    Completed,
+    /// The analyzer closed with a transmission unfinished, or without sending one
+    RemoteClosed,
+    /// A protocol timer aborted the last transmission
+    Timeout,
+    /// The socket failed or the connection was dropped for repeated errors
+    Error,
+    /// The LIS closed it: service stop or a reconnect replacing it
+    Shutdown,
+}
+
+impl ConnectionTermination {
+    pub const ALL: [ConnectionTermination; 5] = [
+        ConnectionTermination::Completed,
+        ConnectionTermination::RemoteClosed,
+        ConnectionTermination::Timeout,
+        ConnectionTermination::Error,
+        ConnectionTermination::Shutdown,
+    ];
+
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            ConnectionTermination::Completed => "COMPLETED",
+            ConnectionTermination::RemoteClosed => "REMOTE_CLOSED",
+            ConnectionTermination::Timeout => "TIMEOUT",
+            ConnectionTermination::Error => "ERROR",
+            ConnectionTermination::Shutdown => "SHUTDOWN",
+        }
+    }
+
+    pub fn from_db_str(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|termination| termination.as_db_str() == s)
+    }
+
+    /// Counts toward the disconnection alarm
+    pub fn is_abnormal(&self) -> bool {
+        matches!(
+            self,
+            ConnectionTermination::RemoteClosed | ConnectionTermination::Timeout | ConnectionTermination::Error
+        )
+    }
+}
+
+/// Connections closed in a period, by how they ended
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TerminationCounts {
+    pub completed: u64,
+    pub remote_closed: u64,
+    pub timeout: u64,
+    pub error: u64,
+    pub shutdown: u64,
+}
+
+impl TerminationCounts {
+    pub fn add(&mut self, termination: ConnectionTermination, count: u64) {
+        match termination {
+            ConnectionTermination::Completed => self.completed += count,
+            ConnectionTermination::RemoteClosed => self.remote_closed += count,
+            ConnectionTermination::Timeout => self.timeout += count,
+            ConnectionTermination::Error => self.error += count,
+            ConnectionTermination::Shutdown => self.shutdown += count,
+        }
+    }
+
+    /// Terminations that count toward the disconnection alarm
+    pub fn abnormal(&self) -> u64 {
+        self.remote_closed + self.timeout + self.error
+    }
+}
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+use super::connection::TerminationCounts;
+
+/// Counters behind the dashboard tiles. "Today" is the local calendar day.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DashboardCounts {
+    pub total_patients: u64,
+    /// Distinct sample ids with at least one result
+    pub total_samples: u64,
+    pub samples_today: u64,
+    pub results_today: u64,
+    /// Uploads not yet delivered to the HIS (pending or in flight)
+    pub pending_uploads: u64,
+    /// Analyzer connections closed today, by how they ended
+    #[serde(default)]
+    pub connection_terminations_today: TerminationCounts,
+    /// Closed today without completing; what the disconnection alarm counts
+    #[serde(default)]
+    pub abnormal_disconnections_today: u64,
+}
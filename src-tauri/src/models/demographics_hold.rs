@@ -0,0 +1,69 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::upload::UploadPriority;
+
+// ============================================================================
+// DEMOGRAPHICS HOLDS
+// ============================================================================
+
+/// Patient fields a mandatory-demographics policy can require
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DemographicField {
+    Name,
+    BirthDate,
+    Sex,
+}
+
+/// Review state of held results
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum HoldStatus {
+    /// Waiting for the patient record to be completed
+    PendingDemographics,
+    /// Waiting for a technologist to verify results the verification rules did not release
+    PendingVerification,
+    /// Released and queued for upload
+    Released,
+}
+
+impl ToString for HoldStatus {
+    fn to_string(&self) -> String {
+        match self {
+            HoldStatus::PendingDemographics => "PENDING_DEMOGRAPHICS".to_string(),
+            HoldStatus::PendingVerification => "PENDING_VERIFICATION".to_string(),
+            HoldStatus::Released => "RELEASED".to_string(),
+        }
+    }
+}
+
+impl HoldStatus {
+    /// Parses the storage representation, rejecting anything unknown
+    pub fn from_db_str(s: &str) -> Result<Self, String> {
+        match s {
+            "PENDING_DEMOGRAPHICS" => Ok(HoldStatus::PendingDemographics),
+            "PENDING_VERIFICATION" => Ok(HoldStatus::PendingVerification),
+            "RELEASED" => Ok(HoldStatus::Released),
+            other => Err(format!("Unknown hold status in database: '{}'", other)),
+        }
+    }
+}
+
+/// Results of one sample kept from HIS auto-upload until the patient's
+/// mandatory demographics are complete
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DemographicsHold {
+    pub id: String,
+    pub patient_id: Option<String>,
+    pub sample_id: String,
+    pub analyzer_id: String,
+    pub missing_fields: Vec<DemographicField>,
+    /// Serialized HIS payload, queued as-is on release
+    pub payload: String,
+    /// Dispatch priority the payload is queued with on release
+    #[serde(default)]
+    pub priority: UploadPriority,
+    pub status: HoldStatus,
+    pub upload_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub released_at: Option<DateTime<Utc>>,
+}
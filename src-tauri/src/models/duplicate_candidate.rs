@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// DUPLICATE PATIENT CANDIDATES
+// ============================================================================
+
+/// Review state of a suggested duplicate pair
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CandidateStatus {
+    Pending,
+    /// Merged into one patient
+    Accepted,
+    /// Reviewed as different patients; never suggested again
+    Dismissed,
+}
+
+impl ToString for CandidateStatus {
+    fn to_string(&self) -> String {
+        match self {
+            CandidateStatus::Pending => "PENDING".to_string(),
+            CandidateStatus::Accepted => "ACCEPTED".to_string(),
+            CandidateStatus::Dismissed => "DISMISSED".to_string(),
+        }
+    }
+}
+
+impl From<&str> for CandidateStatus {
+    fn from(s: &str) -> Self {
+        match s.to_uppercase().as_str() {
+            "ACCEPTED" => CandidateStatus::Accepted,
+            "DISMISSED" => CandidateStatus::Dismissed,
+            _ => CandidateStatus::Pending,
+        }
+    }
+}
+
+/// Two patient records that probably belong to the same person.
+/// `patient_id_a` always sorts before `patient_id_b` so a pair is stored once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateCandidate {
+    pub id: String,
+    pub patient_id_a: String,
+    pub patient_id_b: String,
+    /// Similarity between 0.0 and 1.0
+    pub score: f64,
+    /// Heuristics that matched, e.g. "exact_name", "birth_date"
+    pub reasons: Vec<String>,
+    pub status: CandidateStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::analyzer::AnalyzerIdentity;
+
+// ============================================================================
+// FIRMWARE CHANGES
+// ============================================================================
+
+/// An instrument started declaring a different identity than in its earlier messages
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FirmwareChange {
+    pub id: String,
+    pub analyzer_id: String,
+    pub previous: AnalyzerIdentity,
+    pub current: AnalyzerIdentity,
+    pub previous_since: Option<DateTime<Utc>>, // When the previous identity was first declared
+    pub changed_at: DateTime<Utc>,
+    pub conformance_report_until: Option<DateTime<Utc>>, // Set if the change started conformance-report mode
+}
@@ -1,13 +1,20 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use super::result::{TestResult, TestResultMetadata, ReferenceRange, ResultFlags, ResultStatus};
+use super::connection::ConnectionTermination;
+use super::patient::{ContactInfo, Patient, PatientAddress, PatientName, Sex};
+use super::result::{
+    apply_dilution, DilutionMode, TestResult, TestResultMetadata, ReferenceRange, ResultFlags, ResultStatus,
+    DILUTED_FLAG,
+};
+use super::verification::VerificationStamp;
+use crate::protocol::hl7_parser::parse_hl7_datetime;
 
 // ============================================================================
 // HL7 PATIENT DATA STRUCTURE
 // ============================================================================
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PatientData {
     pub id: String,
     pub name: String,
@@ -18,6 +25,43 @@ pub struct PatientData {
     pub physicians: Option<String>,
     pub height: Option<String>,
     pub weight: Option<String>,
+    #[serde(default)]
+    pub structured_address: Option<PatientAddress>,
+    #[serde(default)]
+    pub contacts: Vec<ContactInfo>,
+}
+
+impl PatientData {
+    /// The patient as stored under `patient_id`, from PID-5 (`last^first^middle`),
+    /// PID-7 and PID-8
+    pub fn to_patient(&self, patient_id: &str, now: DateTime<Utc>) -> Patient {
+        let name: Vec<Option<String>> = self
+            .name
+            .split('^')
+            .map(|part| Some(part.trim().to_string()).filter(|part| !part.is_empty()))
+            .collect();
+        let part = |index: usize| name.get(index).cloned().flatten();
+
+        Patient {
+            id: patient_id.to_string(),
+            name: PatientName {
+                last_name: part(0),
+                first_name: part(1),
+                middle_name: part(2),
+                title: None,
+            },
+            birth_date: self.birth_date.as_deref().and_then(parse_hl7_datetime),
+            sex: self.sex.as_deref().map(Sex::from).unwrap_or(Sex::Other),
+            address: self.structured_address.clone(),
+            telephone: self.telephone.iter().cloned().collect(),
+            contacts: self.contacts.clone(),
+            physicians: None,
+            physical_attributes: None,
+            comments: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
 }
 
 // ============================================================================
@@ -35,6 +79,14 @@ pub enum BF6900Event {
     /// Analyzer disconnected
     AnalyzerDisconnected {
         analyzer_id: String,
+        termination: ConnectionTermination,
+        timestamp: DateTime<Utc>,
+    },
+    /// Connection closed on accept because its IP reached the per-IP limit
+    ConnectionRejected {
+        analyzer_id: String,
+        remote_addr: String,
+        reason: String,
         timestamp: DateTime<Utc>,
     },
     /// HL7 message received
@@ -47,9 +99,34 @@ pub enum BF6900Event {
     /// Hematology result processed
     HematologyResultProcessed {
         analyzer_id: String,
+        remote_addr: String,
         patient_id: Option<String>,
         patient_data: Option<PatientData>,
+        /// Patient class uploaded to the HIS: PV1-2, else the analyzer's default
+        #[serde(default)]
+        patient_class: Option<String>,
         test_results: Vec<HematologyResult>,
+        #[serde(default)]
+        run: HematologyRunInfo,
+        /// Set when the message must be held for review rather than released
+        #[serde(default)]
+        review_detail: Option<String>,
+        /// Reported once the results are stored; the accept ACK waits for it in protective mode
+        #[serde(skip)]
+        store_receipt: crate::services::disk_monitor::StoreReceipt,
+        timestamp: DateTime<Utc>,
+    },
+    /// Message parsed but deviated from the CQ 5 Plus spec
+    ConformanceWarnings {
+        analyzer_id: String,
+        message_control_id: String,
+        warnings: Vec<crate::models::ConformanceWarning>,
+        timestamp: DateTime<Utc>,
+    },
+    /// Identity (MSH-3/4/12) declared by a message, compared against the last one
+    IdentityDeclared {
+        analyzer_id: String,
+        identity: crate::models::AnalyzerIdentity,
         timestamp: DateTime<Utc>,
     },
     /// Analyzer status updated
@@ -74,6 +151,14 @@ pub enum BF6900Event {
         remote_port: Option<u16>,
         timestamp: DateTime<Utc>,
     },
+    /// ED image payload failed validation and was not kept
+    CorruptImage {
+        analyzer_id: String,
+        sample_id: String,
+        parameter_code: String,
+        reason: String,
+        timestamp: DateTime<Utc>,
+    },
     /// External address captured from connection
     ExternalAddressCaptured {
         external_ip: String,
@@ -82,6 +167,106 @@ pub enum BF6900Event {
     },
 }
 
+// ============================================================================
+// CQ 5 PLUS RUN INFORMATION
+// ============================================================================
+
+/// Analysis mode (OBX 2001 MODE), sent as its §A.8 enumeration number
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum HematologyAnalysisMode {
+    WholeBlood,
+    TraceWholeBlood,
+    PreDilution,
+}
+
+impl HematologyAnalysisMode {
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim() {
+            "0" => Some(HematologyAnalysisMode::WholeBlood),
+            "1" => Some(HematologyAnalysisMode::TraceWholeBlood),
+            "2" => Some(HematologyAnalysisMode::PreDilution),
+            _ => None,
+        }
+    }
+
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            HematologyAnalysisMode::WholeBlood => "WHOLE_BLOOD",
+            HematologyAnalysisMode::TraceWholeBlood => "TRACE_WHOLE_BLOOD",
+            HematologyAnalysisMode::PreDilution => "PRE_DILUTION",
+        }
+    }
+}
+
+/// Measurement mode (OBX 2002 MODE_EX), i.e. the panel measured, sent as its §A.8
+/// enumeration number
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum HematologyPanel {
+    Cbc,
+    CbcDiff,
+    CbcDiffCrp,
+    Crp,
+}
+
+impl HematologyPanel {
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim() {
+            "0" => Some(HematologyPanel::Cbc),
+            "1" => Some(HematologyPanel::CbcDiff),
+            "2" => Some(HematologyPanel::CbcDiffCrp),
+            "3" => Some(HematologyPanel::Crp),
+            _ => None,
+        }
+    }
+
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            HematologyPanel::Cbc => "CBC",
+            HematologyPanel::CbcDiff => "CBC_DIFF",
+            HematologyPanel::CbcDiffCrp => "CBC_DIFF_CRP",
+            HematologyPanel::Crp => "CRP",
+        }
+    }
+}
+
+/// Run information the CQ 5 Plus sends as OBX 2001-2005 alongside the results
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct HematologyRunInfo {
+    pub analysis_mode: Option<HematologyAnalysisMode>, // 2001 MODE
+    pub panel: Option<HematologyPanel>,                // 2002 MODE_EX
+    pub reference_group: Option<String>,               // 2003 Ref
+    pub remarks: Option<String>,                       // 2004 Note
+    pub qc_level: Option<String>,                      // 2005 Level
+}
+
+impl HematologyRunInfo {
+    /// Records a run-information value; returns false for codes that are not run
+    /// information. A mode outside the §A.8 enumeration is logged and left unset.
+    pub fn set(&mut self, parameter_code: &str, value: &str) -> bool {
+        let value = value.trim();
+        let text = (!value.is_empty()).then(|| value.to_string());
+        match parameter_code {
+            "2001" => {
+                self.analysis_mode = HematologyAnalysisMode::from_code(value);
+                if self.analysis_mode.is_none() && !value.is_empty() {
+                    log::warn!("Unknown analysis mode {:?} in OBX 2001", value);
+                }
+            }
+            "2002" => {
+                self.panel = HematologyPanel::from_code(value);
+                if self.panel.is_none() && !value.is_empty() {
+                    log::warn!("Unknown measurement mode {:?} in OBX 2002", value);
+                }
+            }
+            "2003" => self.reference_group = text,
+            "2004" => self.remarks = text,
+            "2005" => self.qc_level = text,
+            _ => return false,
+        }
+        true
+    }
+}
+
 // ============================================================================
 // HEMATOLOGY-SPECIFIC RESULT DATA
 // ============================================================================
@@ -100,10 +285,52 @@ pub struct HematologyResult {
     pub analyzer_id: Option<String>,
     pub sample_id: String,
     pub test_id: String,
+    #[serde(default)]
+    pub sub_id: Option<String>,       // OBX-4; tells repeated measurements of a parameter apart
+    #[serde(default)]
+    pub dilution_factor: Option<f64>, // From an OBX-5 SN value "^<value>^*^<factor>"
+    #[serde(default)]
+    pub raw_value: Option<String>,    // Value as received, when the LIS applied the dilution
+    #[serde(default)]
+    pub source_message_control_id: Option<String>, // MSH-10 of the message that carried the result
+    #[serde(default)]
+    pub panel: Option<HematologyPanel>, // MODE_EX (OBX 2002) of the run
+    #[serde(default)]
+    pub analysis_mode: Option<HematologyAnalysisMode>, // MODE (OBX 2001) of the run
+    #[serde(default)]
+    pub verification: Option<VerificationStamp>, // Auto-verification decision and the rule version behind it
+    #[serde(default)]
+    pub comments: Vec<String>,        // NTE segments following the OBX
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+impl HematologyResult {
+    /// Applies the analyzer's dilution convention: pre-dilution values are multiplied
+    /// by the reported factor (keeping the raw value), and every diluted result is flagged
+    pub fn apply_dilution_mode(&mut self, mode: DilutionMode) {
+        let factor = match self.dilution_factor {
+            Some(factor) => factor,
+            None => return,
+        };
+
+        let corrected = apply_dilution(&self.value, factor, mode);
+        if corrected != self.value {
+            self.raw_value = Some(std::mem::replace(&mut self.value, corrected));
+        }
+        self.flags.push(DILUTED_FLAG.to_string());
+    }
+
+    /// Parameter name qualified by the sub-id, e.g. `WBC.2`, for reporting a
+    /// repeated measurement next to the others of the same parameter
+    pub fn qualified_parameter(&self) -> String {
+        match &self.sub_id {
+            Some(sub_id) => format!("{}.{}", self.parameter, sub_id),
+            None => self.parameter.clone(),
+        }
+    }
+}
+
 impl From<HematologyResult> for TestResult {
     fn from(hematology_result: HematologyResult) -> Self {
         // Parse reference range from string to ReferenceRange struct
@@ -122,11 +349,16 @@ impl From<HematologyResult> for TestResult {
             }
         });
 
-        // Convert flags from Vec<String> to ResultFlags
-        let flags = if !hematology_result.flags.is_empty() {
+        // Convert flags from Vec<String> to ResultFlags; dilution is carried in the metadata
+        let abnormal_flags: Vec<&String> = hematology_result
+            .flags
+            .iter()
+            .filter(|flag| flag.as_str() != DILUTED_FLAG)
+            .collect();
+        let flags = if !abnormal_flags.is_empty() {
             Some(ResultFlags {
-                abnormal_flag: hematology_result.flags.first().cloned(),
-                nature_of_abnormality: hematology_result.flags.get(1).cloned(),
+                abnormal_flag: abnormal_flags.first().map(|flag| flag.to_string()),
+                nature_of_abnormality: abnormal_flags.get(1).map(|flag| flag.to_string()),
             })
         } else {
             None
@@ -146,10 +378,25 @@ impl From<HematologyResult> for TestResult {
             status,
             completed_date_time: hematology_result.completed_date_time,
             metadata: TestResultMetadata {
-                sequence_number: 1, // Default sequence number
+                // Repeated measurements stay distinct sub-results of the same test
+                sequence_number: hematology_result
+                    .sub_id
+                    .as_deref()
+                    .and_then(|sub_id| sub_id.parse().ok())
+                    .unwrap_or(1),
                 instrument: hematology_result.analyzer_id.clone(),
+                operator: None,
+                dilution_factor: hematology_result.dilution_factor,
+                raw_value: hematology_result.raw_value,
+                source_message_control_id: hematology_result.source_message_control_id,
+                verification: hematology_result.verification,
+                parameter_name: Some(hematology_result.parameter).filter(|name| !name.is_empty()),
+                panel: hematology_result.panel.map(|panel| panel.as_db_str().to_string()),
+                analysis_mode: hematology_result.analysis_mode.map(|mode| mode.as_db_str().to_string()),
+                unrecognized_status: None,
             },
             analyzer_id: hematology_result.analyzer_id,
+            comments: hematology_result.comments,
             created_at: hematology_result.created_at,
             updated_at: hematology_result.updated_at,
         }
@@ -448,6 +695,14 @@ mod tests {
             analyzer_id: Some("bf6900-001".to_string()),
             sample_id: "S123".to_string(),
             test_id: "T123".to_string(),
+            sub_id: None,
+            dilution_factor: None,
+            raw_value: None,
+            source_message_control_id: None,
+            panel: Some(HematologyPanel::Cbc),
+            analysis_mode: Some(HematologyAnalysisMode::WholeBlood),
+            verification: None,
+            comments: Vec::new(),
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -455,5 +710,26 @@ mod tests {
         let test_result: TestResult = hematology_result.into();
         assert_eq!(test_result.value, "8.5");
         assert_eq!(test_result.units, Some("10^9/L".to_string()));
+        // The run the result came from is kept with it
+        assert_eq!(test_result.metadata.panel.as_deref(), Some("CBC"));
+        assert_eq!(test_result.metadata.analysis_mode.as_deref(), Some("WHOLE_BLOOD"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_run_info_decodes_mode_enumerations() {
+        let mut run = HematologyRunInfo::default();
+        assert!(run.set("2001", "2"));
+        assert!(run.set("2002", " 1 "));
+        assert!(run.set("2004", "  "));
+        assert!(!run.set("2006", "8.5"));
+        assert_eq!(run.analysis_mode, Some(HematologyAnalysisMode::PreDilution));
+        assert_eq!(run.panel, Some(HematologyPanel::CbcDiff));
+        assert_eq!(run.remarks, None);
+
+        // Values outside §A.8 are not guessed at
+        assert!(run.set("2001", "BF"));
+        assert!(run.set("2002", "7"));
+        assert_eq!(run.analysis_mode, None);
+        assert_eq!(run.panel, None);
+    }
+}
@@ -1,15 +1,55 @@
 pub mod analyzer;
+pub mod analyzer_event;
+pub mod conformance;
+pub mod connection;
+pub mod dashboard;
+pub mod demographics_hold;
+pub mod duplicate_candidate;
+pub mod firmware_change;
 pub mod patient;
+pub mod raw_message;
+pub mod remote_address;
 pub mod result;
+pub mod result_change;
+pub mod result_detail;
+pub mod results_package;
 pub mod sample;
 pub mod test_order;
 pub mod upload;
+pub mod verification;
 pub mod hematology;
 
-pub use analyzer::{Analyzer, AnalyzerStatus, ConnectionType, Protocol};
-pub use patient::Patient;
-pub use result::{ResultStatus, TestResult};
+pub use analyzer::{
+    Analyzer, AnalyzerIdentity, AnalyzerStatus, AstmTimeouts, ChecksumPolicy, ConnectionType, DelimitedLayout,
+    Protocol, ResultAcceptanceWindow,
+};
+pub use analyzer_event::{AnalyzerEventType, EventSummary, EventTypeCount};
+pub use connection::{ConnectionSummary, ConnectionTermination, TerminationCounts};
+pub use conformance::{ConformanceReport, ConformanceRuleCount, ConformanceWarning};
+pub use dashboard::DashboardCounts;
+pub use demographics_hold::{DemographicField, DemographicsHold, HoldStatus};
+pub use duplicate_candidate::{CandidateStatus, DuplicateCandidate};
+pub use firmware_change::FirmwareChange;
+pub use patient::{ContactInfo, Patient, PatientAddress, SexCodeMap};
+pub use raw_message::{
+    LatencyBucket, LatencyHistogram, LatencyStats, ProcessingStage, ProcessingTimeline, RawMessage, TimelineStageView,
+};
+pub use remote_address::{HeldMessage, RemoteAddress};
+pub use result::{DataSource, DilutionMode, ResultFilter, ResultIntegrityReport, ResultStatus, TestResult};
+pub use result_change::{ResultChange, ResultChangePage, ResultChangeType};
+pub use result_detail::{ResultAuditAction, ResultAuditEntry, ResultDetail, ResultImage, ResultNote};
+pub use results_package::{
+    EntityImportCounts, PackageContent, PackageImportReport, PackageManifest, PackagedResult, PatientImport,
+    RemappedPatient, ResultImport,
+};
 pub use sample::{Sample, SampleStatus};
-pub use test_order::TestOrder;
-pub use upload::{ResultUploadStatus, UploadStatus};
-pub use hematology::{BF6900Event, HematologyResult, HL7Settings, BF6900Config};
+pub use test_order::{DispatchStatus, OrderDispatch, OrderPriority, TestOrder};
+pub use upload::{
+    CorrectionPattern, RemediationCandidate, RemediationReport, ResultUploadStatus, UploadPriority, UploadQueueEntry,
+    UploadQueueSummary, UploadRemediation, UploadStatus,
+};
+pub use verification::{
+    ReviewReason, RuleScope, VerificationCondition, VerificationDecision, VerificationHold, VerificationRule,
+    VerificationStamp,
+};
+pub use hematology::{BF6900Event, HematologyAnalysisMode, HematologyPanel, HematologyResult, HematologyRunInfo, HL7Settings, BF6900Config};
@@ -1,9 +1,10 @@
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter, Result};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PatientName {
     pub last_name: Option<String>,
     pub first_name: Option<String>,
@@ -11,13 +12,32 @@ pub struct PatientName {
     pub title: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct PatientAddress {
     pub street: Option<String>,
+    #[serde(default)]
+    pub other_designation: Option<String>, // XAD-2, e.g. apartment or building
     pub city: Option<String>,
     pub state: Option<String>,
     pub zip: Option<String>,
     pub country_code: Option<String>,
+    #[serde(default)]
+    pub raw: Option<String>, // Address as received
+    #[serde(default)]
+    pub unparsed: bool, // Could not be split; only `raw` is meaningful
+}
+
+/// A telephone number split into its parts, with the value as received
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ContactInfo {
+    pub use_code: Option<String>,       // XTN-2, e.g. PRN (home) or WPN (work)
+    pub equipment_type: Option<String>, // XTN-3, e.g. PH, CP (cell) or FX
+    pub country_code: Option<String>,
+    pub area_code: Option<String>,
+    pub local_number: Option<String>,
+    pub extension: Option<String>,
+    pub raw: String,
+    pub unparsed: bool, // Could not be split; only `raw` is meaningful
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,7 +59,7 @@ pub struct PhysicalAttributes {
     pub weight: Option<PhysicalAttribute>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Sex {
     Male,
     Female,
@@ -76,6 +96,31 @@ impl From<Sex> for String {
     }
 }
 
+/// Instrument-specific sex codes mapped onto `Sex`, e.g. "1" → Male, "2" → Female.
+/// Codes not listed are left to the standard M/F/U handling of `Sex::from`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(transparent)]
+pub struct SexCodeMap(pub HashMap<String, Sex>);
+
+impl SexCodeMap {
+    /// Sex of a configured code (case-insensitive), if the code is mapped
+    pub fn get(&self, code: &str) -> Option<Sex> {
+        let code = code.trim();
+        self.0
+            .iter()
+            .find(|(mapped, _)| mapped.trim().eq_ignore_ascii_case(code))
+            .map(|(_, sex)| sex.clone())
+    }
+
+    /// Rewrites a mapped code to its standard code (M/F/U); other codes pass through
+    pub fn normalize(&self, code: &str) -> String {
+        match self.get(code) {
+            Some(sex) => String::from(sex),
+            None => code.to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Patient {
     pub id: String,                        // Practice assigned patient ID (max 40 chars)
@@ -84,8 +129,35 @@ pub struct Patient {
     pub sex: Sex,                          // M/F/U (Male/Female/Other)
     pub address: Option<PatientAddress>,   // Components separated by ^ in protocol
     pub telephone: Vec<String>,            // Multiple phone numbers
+    #[serde(default)]
+    pub contacts: Vec<ContactInfo>,        // Structured phone numbers
     pub physicians: Option<PatientPhysicians>, // From Attending Physician ID field
     pub physical_attributes: Option<PhysicalAttributes>, // Height and weight information
+    #[serde(default)]
+    pub comments: Vec<String>,             // Comment records attached to the patient
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
+
+#[cfg(test)]
+impl Patient {
+    /// A patient with nothing but an id, registered now.
+    /// Tests fill in the demographics they need with struct-update syntax.
+    pub fn fixture(id: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            id: id.to_string(),
+            name: PatientName::default(),
+            birth_date: None,
+            sex: Sex::Other,
+            address: None,
+            telephone: Vec::new(),
+            contacts: Vec::new(),
+            physicians: None,
+            physical_attributes: None,
+            comments: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// PROCESSING TIMELINE
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ProcessingStage {
+    Received,  // First byte of the transmission arrived
+    Framed,    // Framing completed (EOT / MLLP end block)
+    Parsed,    // Records/segments parsed
+    Validated, // Checksums and required fields checked
+    Persisted, // Stored in the database
+    Acked,     // Acknowledgment sent to the analyzer
+    Uploaded,  // Delivered to the HIS
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageRecord {
+    pub stage: ProcessingStage,
+    pub at: DateTime<Utc>,
+    pub warnings: Vec<String>,
+}
+
+/// Stage timestamps accumulated while a message moves through the pipeline.
+/// Stored as JSON on the raw message row.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProcessingTimeline {
+    pub stages: Vec<StageRecord>,
+}
+
+/// One stage of a timeline as shown to the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineStageView {
+    pub stage: ProcessingStage,
+    pub at: DateTime<Utc>,
+    /// Milliseconds since the previous recorded stage
+    pub duration_ms: i64,
+    /// Milliseconds since the message was received
+    pub elapsed_ms: i64,
+    pub warnings: Vec<String>,
+}
+
+impl ProcessingTimeline {
+    /// Starts a timeline with the `Received` stage
+    pub fn started_at(at: DateTime<Utc>) -> Self {
+        let mut timeline = Self::default();
+        timeline.mark_at(ProcessingStage::Received, at);
+        timeline
+    }
+
+    /// Records a stage at the current time
+    pub fn mark(&mut self, stage: ProcessingStage) {
+        self.mark_at(stage, Utc::now());
+    }
+
+    /// Records a stage at the given time. Re-marking a stage moves its timestamp.
+    pub fn mark_at(&mut self, stage: ProcessingStage, at: DateTime<Utc>) {
+        match self.stages.iter_mut().find(|record| record.stage == stage) {
+            Some(record) => record.at = at,
+            None => self.stages.push(StageRecord {
+                stage,
+                at,
+                warnings: Vec::new(),
+            }),
+        }
+    }
+
+    /// Attaches a warning to a stage, recording the stage now if it is missing
+    pub fn warn(&mut self, stage: ProcessingStage, warning: impl Into<String>) {
+        if !self.has_stage(stage) {
+            self.mark(stage);
+        }
+        if let Some(record) = self.stages.iter_mut().find(|record| record.stage == stage) {
+            record.warnings.push(warning.into());
+        }
+    }
+
+    pub fn has_stage(&self, stage: ProcessingStage) -> bool {
+        self.stages.iter().any(|record| record.stage == stage)
+    }
+
+    /// Gets the time a stage was recorded
+    pub fn stage_at(&self, stage: ProcessingStage) -> Option<DateTime<Utc>> {
+        self.stages
+            .iter()
+            .find(|record| record.stage == stage)
+            .map(|record| record.at)
+    }
+
+    /// Milliseconds between two recorded stages
+    pub fn duration_between(&self, from: ProcessingStage, to: ProcessingStage) -> Option<i64> {
+        Some((self.stage_at(to)? - self.stage_at(from)?).num_milliseconds())
+    }
+
+    /// Stages in chronological order with computed durations
+    pub fn to_view(&self) -> Vec<TimelineStageView> {
+        let mut stages = self.stages.clone();
+        stages.sort_by_key(|record| record.at);
+
+        let started = self
+            .stage_at(ProcessingStage::Received)
+            .or_else(|| stages.first().map(|record| record.at));
+        let mut previous = started;
+
+        stages
+            .into_iter()
+            .map(|record| {
+                let view = TimelineStageView {
+                    stage: record.stage,
+                    at: record.at,
+                    duration_ms: previous.map(|p| (record.at - p).num_milliseconds()).unwrap_or(0),
+                    elapsed_ms: started.map(|s| (record.at - s).num_milliseconds()).unwrap_or(0),
+                    warnings: record.warnings,
+                };
+                previous = Some(record.at);
+                view
+            })
+            .collect()
+    }
+}
+
+// ============================================================================
+// LATENCY STATS
+// ============================================================================
+
+/// Upper bounds of the latency histogram buckets; slower messages land in a
+/// final open-ended bucket
+pub const LATENCY_BUCKETS_MS: [i64; 8] = [10, 50, 100, 250, 500, 1_000, 5_000, 30_000];
+
+/// Pipeline segments reported by `get_latency_stats`: receive → parse → store →
+/// upload, and the end-to-end spans
+pub const LATENCY_SEGMENTS: [(ProcessingStage, ProcessingStage); 5] = [
+    (ProcessingStage::Received, ProcessingStage::Parsed),
+    (ProcessingStage::Parsed, ProcessingStage::Persisted),
+    (ProcessingStage::Persisted, ProcessingStage::Uploaded),
+    (ProcessingStage::Received, ProcessingStage::Persisted),
+    (ProcessingStage::Received, ProcessingStage::Uploaded),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LatencyBucket {
+    /// Inclusive upper bound; `None` for the open-ended last bucket
+    pub upper_ms: Option<i64>,
+    pub count: u64,
+}
+
+/// Latency distribution between two stages over the messages that reached both
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LatencyHistogram {
+    pub from: ProcessingStage,
+    pub to: ProcessingStage,
+    pub count: u64,
+    pub min_ms: Option<i64>,
+    pub max_ms: Option<i64>,
+    pub mean_ms: Option<f64>,
+    pub p50_ms: Option<i64>,
+    pub p95_ms: Option<i64>,
+    pub buckets: Vec<LatencyBucket>,
+}
+
+impl LatencyHistogram {
+    pub fn from_samples(from: ProcessingStage, to: ProcessingStage, samples: &[i64]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        // Nearest-rank percentile
+        let percentile = |p: usize| {
+            let rank = (sorted.len() * p).div_ceil(100).max(1);
+            sorted.get(rank - 1).copied()
+        };
+
+        let mut buckets: Vec<LatencyBucket> = LATENCY_BUCKETS_MS
+            .iter()
+            .map(|&upper| Some(upper))
+            .chain(std::iter::once(None))
+            .map(|upper_ms| LatencyBucket { upper_ms, count: 0 })
+            .collect();
+        for &sample in &sorted {
+            let index = LATENCY_BUCKETS_MS
+                .iter()
+                .position(|&upper| sample <= upper)
+                .unwrap_or(LATENCY_BUCKETS_MS.len());
+            buckets[index].count += 1;
+        }
+
+        Self {
+            from,
+            to,
+            count: sorted.len() as u64,
+            min_ms: sorted.first().copied(),
+            max_ms: sorted.last().copied(),
+            mean_ms: (!sorted.is_empty()).then(|| sorted.iter().sum::<i64>() as f64 / sorted.len() as f64),
+            p50_ms: percentile(50),
+            p95_ms: percentile(95),
+            buckets,
+        }
+    }
+}
+
+/// Per-message processing latency over a period
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LatencyStats {
+    pub since: DateTime<Utc>,
+    /// Raw messages received in the period
+    pub messages: u64,
+    pub segments: Vec<LatencyHistogram>,
+}
+
+impl LatencyStats {
+    /// Builds the histograms of every segment from message timelines
+    pub fn from_timelines(since: DateTime<Utc>, timelines: &[ProcessingTimeline]) -> Self {
+        let segments = LATENCY_SEGMENTS
+            .iter()
+            .map(|&(from, to)| {
+                let samples: Vec<i64> = timelines
+                    .iter()
+                    .filter_map(|timeline| timeline.duration_between(from, to))
+                    .collect();
+                LatencyHistogram::from_samples(from, to, &samples)
+            })
+            .collect();
+
+        Self {
+            since,
+            messages: timelines.len() as u64,
+            segments,
+        }
+    }
+
+    /// The histogram of one segment
+    pub fn segment(&self, from: ProcessingStage, to: ProcessingStage) -> Option<&LatencyHistogram> {
+        self.segments.iter().find(|histogram| histogram.from == from && histogram.to == to)
+    }
+}
+
+// ============================================================================
+// RAW MESSAGE
+// ============================================================================
+
+/// A complete message as received from an analyzer, kept for audit and support
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawMessage {
+    pub id: String,
+    pub analyzer_id: String,
+    pub protocol: String,     // "ASTM" or "HL7"
+    pub message_type: String, // e.g. "Result", "ORU^R01"
+    pub raw_data: String,
+    pub timeline: ProcessingTimeline,
+    pub upload_id: Option<String>, // Upload queue row created for this message
+    pub received_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_timeline_ordering_and_durations() {
+        let start = Utc::now();
+        let mut timeline = ProcessingTimeline::started_at(start);
+        timeline.mark_at(ProcessingStage::Framed, start + Duration::milliseconds(120));
+        timeline.mark_at(ProcessingStage::Acked, start + Duration::milliseconds(125));
+        timeline.mark_at(ProcessingStage::Parsed, start + Duration::milliseconds(130));
+        timeline.mark_at(ProcessingStage::Validated, start + Duration::milliseconds(140));
+        timeline.mark_at(ProcessingStage::Persisted, start + Duration::milliseconds(190));
+        timeline.mark_at(ProcessingStage::Uploaded, start + Duration::milliseconds(1190));
+
+        let view = timeline.to_view();
+        let stages: Vec<ProcessingStage> = view.iter().map(|v| v.stage).collect();
+        assert_eq!(
+            stages,
+            vec![
+                ProcessingStage::Received,
+                ProcessingStage::Framed,
+                ProcessingStage::Acked,
+                ProcessingStage::Parsed,
+                ProcessingStage::Validated,
+                ProcessingStage::Persisted,
+                ProcessingStage::Uploaded,
+            ]
+        );
+
+        let durations: Vec<i64> = view.iter().map(|v| v.duration_ms).collect();
+        assert_eq!(durations, vec![0, 120, 5, 5, 10, 50, 1000]);
+        assert_eq!(view.last().unwrap().elapsed_ms, 1190);
+        assert_eq!(
+            timeline.duration_between(ProcessingStage::Received, ProcessingStage::Persisted),
+            Some(190)
+        );
+    }
+
+    #[test]
+    fn test_latency_histogram_buckets_and_percentiles() {
+        let start = Utc::now();
+        let timelines: Vec<ProcessingTimeline> = [5, 40, 90, 400, 45_000]
+            .iter()
+            .map(|&ms| {
+                let mut timeline = ProcessingTimeline::started_at(start);
+                timeline.mark_at(ProcessingStage::Persisted, start + Duration::milliseconds(ms));
+                timeline
+            })
+            .collect();
+        let stats = LatencyStats::from_timelines(start, &timelines);
+        assert_eq!(stats.messages, 5);
+
+        let stored = stats.segment(ProcessingStage::Received, ProcessingStage::Persisted).unwrap();
+        assert_eq!(stored.count, 5);
+        assert_eq!((stored.min_ms, stored.max_ms), (Some(5), Some(45_000)));
+        assert_eq!((stored.p50_ms, stored.p95_ms), (Some(90), Some(45_000)));
+        let counts: Vec<u64> = stored.buckets.iter().map(|bucket| bucket.count).collect();
+        assert_eq!(counts, vec![1, 1, 1, 0, 1, 0, 0, 0, 1]);
+        assert_eq!(stored.buckets.last().unwrap().upper_ms, None);
+
+        // No message was uploaded, so that segment is empty
+        let uploaded = stats.segment(ProcessingStage::Received, ProcessingStage::Uploaded).unwrap();
+        assert_eq!((uploaded.count, uploaded.mean_ms, uploaded.p50_ms), (0, None, None));
+    }
+
+    #[test]
+    fn test_timeline_failed_validation_has_no_later_stages() {
+        let start = Utc::now();
+        let mut timeline = ProcessingTimeline::started_at(start);
+        timeline.mark_at(ProcessingStage::Framed, start + Duration::milliseconds(50));
+        timeline.mark_at(ProcessingStage::Parsed, start + Duration::milliseconds(60));
+        timeline.mark_at(ProcessingStage::Validated, start + Duration::milliseconds(65));
+        timeline.warn(ProcessingStage::Validated, "Checksum mismatch in frame 2");
+
+        let view = timeline.to_view();
+        assert_eq!(view.len(), 4);
+        assert_eq!(view[3].stage, ProcessingStage::Validated);
+        assert_eq!(view[3].warnings, vec!["Checksum mismatch in frame 2".to_string()]);
+        assert!(!timeline.has_stage(ProcessingStage::Persisted));
+        assert!(!timeline.has_stage(ProcessingStage::Uploaded));
+        assert_eq!(
+            timeline.duration_between(ProcessingStage::Received, ProcessingStage::Uploaded),
+            None
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// ANALYZER REMOTE ADDRESSES
+// ============================================================================
+
+/// A remote IP an analyzer has connected from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RemoteAddress {
+    pub analyzer_id: String,
+    pub ip_address: String,
+    /// False while a strict-mode analyzer's new address awaits operator approval
+    pub approved: bool,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// A processed message received from an unapproved address, kept for replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeldMessage {
+    pub id: String,
+    pub analyzer_id: String,
+    pub ip_address: String,
+    pub protocol: String, // ASTM, HL7
+    pub payload: String,  // JSON of the service event that was held
+    pub received_at: DateTime<Utc>,
+}
@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::result::TestResult;
+
+// ============================================================================
+// RESULT CHANGE FEED
+// ============================================================================
+
+/// What happened to a result, as recorded by the `test_results` triggers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ResultChangeType {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl ResultChangeType {
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            ResultChangeType::Insert => "INSERT",
+            ResultChangeType::Update => "UPDATE",
+            ResultChangeType::Delete => "DELETE",
+        }
+    }
+
+    pub fn from_db_str(s: &str) -> Option<Self> {
+        [ResultChangeType::Insert, ResultChangeType::Update, ResultChangeType::Delete]
+            .into_iter()
+            .find(|change_type| change_type.as_db_str() == s)
+    }
+}
+
+/// One entry of the change feed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResultChange {
+    pub seq: i64,
+    pub change_type: ResultChangeType,
+    pub result_id: String,
+    pub changed_at: DateTime<Utc>,
+    /// The result as it is now; `None` is a tombstone: always for a delete, and for
+    /// an insert or update of a result that no longer exists
+    pub result: Option<TestResult>,
+}
+
+/// A page of the change feed; pass `next_seq` as `since_seq` to read on
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResultChangePage {
+    pub changes: Vec<ResultChange>,
+    pub next_seq: i64,
+}
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::result::TestResult;
+use super::upload::ResultUploadStatus;
+
+// ============================================================================
+// RESULT DETAIL
+// ============================================================================
+
+/// Free-text note attached to a result
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResultNote {
+    pub id: String,
+    pub result_id: String,
+    pub author: Option<String>,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Image kept for a result, e.g. a histogram; `reference` is where the bytes live
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResultImage {
+    pub id: String,
+    pub result_id: String,
+    pub kind: String, // e.g. "histogram", "scattergram"
+    pub mime_type: String,
+    pub reference: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What happened to a result, for its audit trail
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ResultAuditAction {
+    Received,
+    Replaced,   // This result replaced an earlier one
+    Superseded, // A later result replaced this one
+    NoteAdded,
+    ImageAttached,
+    UploadQueued,
+    Uploaded,
+    UploadFailed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResultAuditEntry {
+    pub at: DateTime<Utc>,
+    pub action: ResultAuditAction,
+    pub detail: String,
+}
+
+/// Everything shown for one result: the result itself (with its reference range
+/// and flags), notes, images, uploads and the audit trail, oldest entry first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultDetail {
+    pub result: TestResult,
+    /// Result this one replaced, if it is a correction
+    pub replaces: Option<String>,
+    /// Result that replaced this one, if any
+    pub superseded_by: Option<String>,
+    pub notes: Vec<ResultNote>,
+    pub images: Vec<ResultImage>,
+    pub uploads: Vec<ResultUploadStatus>,
+    pub audit: Vec<ResultAuditEntry>,
+}
@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::patient::Patient;
+use super::result::TestResult;
+
+/// Identifies a results package file
+pub const RESULTS_PACKAGE_FORMAT: &str = "nramh-lis-results-package";
+/// Newest package layout this build reads and the one it writes
+pub const RESULTS_PACKAGE_VERSION: u32 = 1;
+
+// ============================================================================
+// RESULTS PACKAGES
+// ============================================================================
+
+/// Describes a package; stored ahead of the content it hashes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PackageManifest {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub patients: usize,
+    /// Distinct sample ids among the results
+    pub samples: usize,
+    pub results: usize,
+    /// Hex SHA-256 of the serialized content
+    pub content_sha256: String,
+}
+
+/// A result with the patient it belongs to. The analyzer, instrument and
+/// operator recorded on the result travel with it as provenance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackagedResult {
+    pub patient_id: String,
+    pub result: TestResult,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PackageContent {
+    pub patients: Vec<Patient>,
+    pub results: Vec<PackagedResult>,
+}
+
+/// How one packaged patient is applied to the database
+#[derive(Debug, Clone)]
+pub enum PatientImport {
+    Insert(Patient),
+    /// Fills demographics missing on the stored patient with the same id
+    FillGaps(Patient),
+}
+
+/// How one packaged result is applied to the database
+#[derive(Debug, Clone)]
+pub enum ResultImport {
+    Insert(PackagedResult),
+    /// Overwrites an older copy of the same result
+    Replace(PackagedResult),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct EntityImportCounts {
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+}
+
+/// A packaged patient whose id was taken by a different person here
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RemappedPatient {
+    pub package_patient_id: String,
+    pub imported_as: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageImportReport {
+    pub manifest: PackageManifest,
+    pub patients: EntityImportCounts,
+    pub results: EntityImportCounts,
+    pub remapped_patients: Vec<RemappedPatient>,
+    /// Set when a batch failed; that batch was rolled back and the import stopped
+    pub error: Option<String>,
+}
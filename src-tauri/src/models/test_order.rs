@@ -43,6 +43,18 @@ impl From<&str> for ActionCode {
     }
 }
 
+impl ActionCode {
+    /// Action code sent in the order record
+    pub fn code(&self) -> &'static str {
+        match self {
+            ActionCode::Add => "A",
+            ActionCode::New => "N",
+            ActionCode::Pending => "P",
+            ActionCode::Cancel => "C",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SchedulingInfo {
     pub collection_date: Option<DateTime<Utc>>,
@@ -62,3 +74,96 @@ pub struct TestOrder {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
+
+#[cfg(test)]
+impl TestOrder {
+    /// A new routine glucose order on `specimen_id`, created now.
+    /// Tests change what they need with struct-update syntax.
+    pub fn fixture(id: &str, specimen_id: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            id: id.to_string(),
+            sequence_number: 1,
+            specimen_id: specimen_id.to_string(),
+            tests: vec![Test {
+                universal_id: "^^^GLU".to_string(),
+                name: "Glucose".to_string(),
+            }],
+            priority: OrderPriority::Routine,
+            action_code: ActionCode::New,
+            ordering_provider: None,
+            scheduling_info: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+impl OrderPriority {
+    /// Priority code sent in the order record
+    pub fn code(&self) -> &'static str {
+        match self {
+            OrderPriority::Routine => "R",
+            OrderPriority::Stat => "S",
+            OrderPriority::AsapEmergency => "A",
+        }
+    }
+
+    /// Dispatch rank; lower ranks are sent to the analyzer first
+    pub fn dispatch_rank(&self) -> i64 {
+        match self {
+            OrderPriority::Stat | OrderPriority::AsapEmergency => 0,
+            OrderPriority::Routine => 1,
+        }
+    }
+}
+
+// ============================================================================
+// ORDER DISPATCH QUEUE
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum DispatchStatus {
+    Pending, // Waiting for the analyzer connection / a free in-flight slot
+    Sent,    // Transmitted, waiting for the analyzer to accept it
+    Acked,   // Accepted by the analyzer
+    Failed,  // Gave up after the maximum number of attempts
+}
+
+impl ToString for DispatchStatus {
+    fn to_string(&self) -> String {
+        match self {
+            DispatchStatus::Pending => "PENDING".to_string(),
+            DispatchStatus::Sent => "SENT".to_string(),
+            DispatchStatus::Acked => "ACKED".to_string(),
+            DispatchStatus::Failed => "FAILED".to_string(),
+        }
+    }
+}
+
+impl From<&str> for DispatchStatus {
+    fn from(s: &str) -> Self {
+        match s.to_uppercase().as_str() {
+            "SENT" => DispatchStatus::Sent,
+            "ACKED" => DispatchStatus::Acked,
+            "FAILED" => DispatchStatus::Failed,
+            _ => DispatchStatus::Pending,
+        }
+    }
+}
+
+/// A worklist download waiting for (or sent to) an analyzer.
+/// The full order is stored with the entry so the queue survives restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderDispatch {
+    pub id: String,
+    pub order_id: String,
+    pub analyzer_id: String,
+    pub patient_id: Option<String>, // None for entries queued before patients were recorded
+    pub order: TestOrder,
+    pub status: DispatchStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
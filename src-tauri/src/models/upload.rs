@@ -1,6 +1,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::test_order::OrderPriority;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum UploadStatus {
     Pending,
@@ -41,6 +43,169 @@ pub struct ResultUploadStatus {
     pub response_code: Option<String>,
     pub response_message: Option<String>,
     pub retry_count: u32,
+    pub payload: String, // Serialized outbound message (JSON)
+    #[serde(default)]
+    pub priority: UploadPriority,
+    /// Worker instance holding the row, `None` while unclaimed
+    #[serde(default)]
+    pub claimed_by: Option<String>,
+    #[serde(default)]
+    pub claimed_at: Option<DateTime<Utc>>,
+    /// Earliest time a failed upload is sent again, `None` when it may go now
+    #[serde(default)]
+    pub next_attempt_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
+
+// ============================================================================
+// DISPATCH PRIORITY
+// ============================================================================
+
+/// Abnormal flags marking a value in the critical (panic) range
+const CRITICAL_FLAGS: [&str; 3] = ["HH", "LL", "AA"];
+
+/// Order in which queued uploads are sent; critical results go first
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UploadPriority {
+    Critical,
+    Stat,
+    #[default]
+    Routine,
+}
+
+impl UploadPriority {
+    /// Derives the priority from the sample's order priority and whether any
+    /// of its results carries a critical flag
+    pub fn derive(order_priority: Option<&OrderPriority>, critical: bool) -> Self {
+        if critical {
+            return UploadPriority::Critical;
+        }
+        match order_priority {
+            Some(OrderPriority::Stat | OrderPriority::AsapEmergency) => UploadPriority::Stat,
+            _ => UploadPriority::Routine,
+        }
+    }
+
+    /// Whether an abnormal flag marks a critical value
+    pub fn is_critical_flag(flag: &str) -> bool {
+        CRITICAL_FLAGS.contains(&flag.trim().to_uppercase().as_str())
+    }
+
+    /// Dispatch rank stored in the queue; lower ranks are sent first
+    pub fn rank(&self) -> i64 {
+        match self {
+            UploadPriority::Critical => 0,
+            UploadPriority::Stat => 1,
+            UploadPriority::Routine => 2,
+        }
+    }
+
+    pub fn from_rank(rank: i64) -> Self {
+        match rank {
+            0 => UploadPriority::Critical,
+            1 => UploadPriority::Stat,
+            _ => UploadPriority::Routine,
+        }
+    }
+}
+
+// ============================================================================
+// QUEUE SUMMARY
+// ============================================================================
+
+/// An upload waiting to be sent, in dispatch order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadQueueEntry {
+    pub upload_id: String,
+    pub result_id: String,
+    pub priority: UploadPriority,
+    pub retry_count: u32,
+    pub claimed_by: Option<String>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    /// When a failed upload is due to be sent again
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadQueueSummary {
+    pub pending: u64,
+    pub in_flight: u64,
+    /// Pending uploads reserved by a worker
+    pub claimed: u64,
+    pub critical: u64,
+    pub stat: u64,
+    pub routine: u64,
+    /// Head of the queue in the order the worker sends it
+    pub next: Vec<UploadQueueEntry>,
+}
+
+// ============================================================================
+// REMEDIATION
+// ============================================================================
+
+/// How the HIS accepts a fix for a result it already holds
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum CorrectionPattern {
+    /// One corrected message replaces the earlier one
+    #[default]
+    CorrectedResult,
+    /// The earlier message is cancelled, then the fixed one is sent as new
+    CancelAndNew,
+}
+
+impl ToString for CorrectionPattern {
+    fn to_string(&self) -> String {
+        match self {
+            CorrectionPattern::CorrectedResult => "CORRECTED_RESULT".to_string(),
+            CorrectionPattern::CancelAndNew => "CANCEL_AND_NEW".to_string(),
+        }
+    }
+}
+
+impl From<&str> for CorrectionPattern {
+    fn from(s: &str) -> Self {
+        match s.to_uppercase().as_str() {
+            "CANCEL_AND_NEW" => CorrectionPattern::CancelAndNew,
+            _ => CorrectionPattern::CorrectedResult,
+        }
+    }
+}
+
+/// Links an upload sent with a wrong test mapping to the uploads that fixed it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadRemediation {
+    pub id: String,
+    pub original_upload_id: String,
+    pub sample_id: String,
+    pub test_code: String,
+    pub pattern: CorrectionPattern,
+    pub cancel_upload_id: Option<String>,
+    pub new_upload_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An uploaded sample whose payload carried a test under the wrong HIS name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemediationCandidate {
+    pub upload_id: String,
+    pub sample_id: String,
+    pub uploaded_at: Option<DateTime<Utc>>,
+    /// Name the value was sent under, when it can be told apart from the other values
+    pub sent_as: Option<String>,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemediationReport {
+    pub test_code: String,
+    pub corrected_name: String,
+    pub pattern: CorrectionPattern,
+    pub dry_run: bool,
+    pub affected: Vec<RemediationCandidate>,
+    /// Samples whose correction was queued for the HIS
+    pub queued: usize,
+    /// Samples left alone, e.g. while shadow mode is on
+    pub skipped: usize,
+}
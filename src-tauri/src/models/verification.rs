@@ -0,0 +1,170 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::demographics_hold::HoldStatus;
+use super::upload::UploadPriority;
+
+// ============================================================================
+// AUTO-VERIFICATION
+// ============================================================================
+
+/// What the verification rules decided for a result
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum VerificationDecision {
+    /// Released to the HIS without technologist review
+    AutoVerify,
+    /// Kept for technologist review
+    Hold,
+}
+
+impl ToString for VerificationDecision {
+    fn to_string(&self) -> String {
+        match self {
+            VerificationDecision::AutoVerify => "AUTO_VERIFY".to_string(),
+            VerificationDecision::Hold => "HOLD".to_string(),
+        }
+    }
+}
+
+impl From<&str> for VerificationDecision {
+    fn from(s: &str) -> Self {
+        match s.to_uppercase().as_str() {
+            "AUTO_VERIFY" => VerificationDecision::AutoVerify,
+            _ => VerificationDecision::Hold,
+        }
+    }
+}
+
+/// What a result, or a whole panel, must satisfy for a verification rule to apply
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum VerificationCondition {
+    /// One of the listed tests (case-insensitive)
+    Test { test_ids: Vec<String> },
+    /// Numeric value within the inclusive limits; non-numeric values never match
+    ValueBetween { min: Option<f64>, max: Option<f64> },
+    /// No abnormal or suspect flag
+    NoFlags,
+    /// A specific flag, e.g. "H"
+    Flag { flag: String },
+    /// Change from the patient's previous result of the test below `max_percent`;
+    /// never matches without a numeric previous result
+    DeltaBelow { max_percent: f64 },
+    /// Whether the patient has a previous result of the test
+    PriorResult { exists: bool },
+    /// Patient age in whole years within the inclusive limits; never matches when unknown
+    PatientAge { min_years: Option<u32>, max_years: Option<u32> },
+    /// Patient sex as coded in the patient record ("M", "F", "U")
+    PatientSex { sex: String },
+    /// Results produced by one of the listed analyzers
+    Analyzer { analyzer_ids: Vec<String> },
+    /// Every nested condition holds
+    All { conditions: Vec<VerificationCondition> },
+    /// At least one nested condition holds
+    Any { conditions: Vec<VerificationCondition> },
+    /// The nested condition does not hold
+    Not { condition: Box<VerificationCondition> },
+    /// The nested condition holds for every result of the panel
+    EveryResult { condition: Box<VerificationCondition> },
+    /// The nested condition holds for at least one result of the panel
+    AnyResult { condition: Box<VerificationCondition> },
+    /// The panel has a result of `test_id` and the nested condition holds for it
+    ResultFor { test_id: String, condition: Box<VerificationCondition> },
+}
+
+/// Whether a rule decides results one at a time or a sample's panel as a whole
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RuleScope {
+    #[default]
+    Result,
+    Panel,
+}
+
+/// One version of an auto-verification rule. Rules apply in `priority` order
+/// (lowest first); the first whose condition holds decides the result.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VerificationRule {
+    pub id: String,
+    /// Assigned when the rule is saved; every saved edit gets the next version
+    #[serde(default)]
+    pub version: u32,
+    pub name: String,
+    pub enabled: bool,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub scope: RuleScope,
+    pub condition: VerificationCondition,
+    pub decision: VerificationDecision,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Which rule version decided a result. `rule_id` is `None` when no rule
+/// matched and the result was held by default.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VerificationStamp {
+    pub decision: VerificationDecision,
+    pub rule_id: Option<String>,
+    pub rule_version: Option<u32>,
+    pub evaluated_at: DateTime<Utc>,
+}
+
+/// Why results of a sample wait for review instead of uploading
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ReviewReason {
+    /// A result was not auto-verified
+    #[default]
+    Verification,
+    /// A result was completed longer ago than the stale-result threshold
+    Stale,
+    /// The message reached ingestion after a newer message for the same sample
+    OutOfOrder,
+}
+
+impl ToString for ReviewReason {
+    fn to_string(&self) -> String {
+        match self {
+            ReviewReason::Verification => "VERIFICATION".to_string(),
+            ReviewReason::Stale => "STALE".to_string(),
+            ReviewReason::OutOfOrder => "OUT_OF_ORDER".to_string(),
+        }
+    }
+}
+
+impl ReviewReason {
+    /// Parses a stored reason; an unknown value is an error rather than a
+    /// silent `Verification`, so a corrupt row does not hide why it was held
+    pub fn from_db_str(s: &str) -> Result<Self, String> {
+        match s {
+            "VERIFICATION" => Ok(ReviewReason::Verification),
+            "STALE" => Ok(ReviewReason::Stale),
+            "OUT_OF_ORDER" => Ok(ReviewReason::OutOfOrder),
+            other => Err(format!("Unknown review reason in database: '{}'", other)),
+        }
+    }
+}
+
+/// Results of one sample kept from HIS upload until a technologist reviews them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationHold {
+    pub id: String,
+    pub patient_id: Option<String>,
+    pub sample_id: String,
+    pub analyzer_id: String,
+    #[serde(default)]
+    pub reason: ReviewReason,
+    /// Why the reason applies, e.g. how old the stale results were
+    #[serde(default)]
+    pub reason_detail: Option<String>,
+    /// Decision per result, keyed by test id
+    pub decisions: Vec<(String, VerificationStamp)>,
+    /// Serialized HIS payload, submitted as-is on release
+    pub payload: String,
+    #[serde(default)]
+    pub priority: UploadPriority,
+    pub status: HoldStatus,
+    pub upload_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub released_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,773 @@
+// ============================================================================
+// ASTM RECORD DELIMITERS
+// ============================================================================
+//
+// An ASTM header declares the session's delimiters in its first field, e.g.
+// `H|\^&`: field `|`, repeat `\`, component `^`, escape `&`. Records are split
+// with those delimiters; a delimiter directly preceded by the escape delimiter
+// is data and stays inside its field or component.
+//
+// Delimiters inside data are sent as escape sequences: `&F&` field, `&S&`
+// component, `&R&` repeat, `&E&` escape. Splitting keeps the sequences intact so
+// a field can still be split into components; text is decoded at the leaf, by
+// `split_components` or `unescape`, and `escape` encodes it again for sending.
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Patient, TestOrder};
+
+/// Delimiters in effect for an ASTM session
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AstmDelimiters {
+    pub field: char,
+    pub repeat: char,
+    pub component: char,
+    pub escape: char,
+}
+
+impl Default for AstmDelimiters {
+    fn default() -> Self {
+        Self {
+            field: '|',
+            repeat: '\\',
+            component: '^',
+            escape: '&',
+        }
+    }
+}
+
+impl AstmDelimiters {
+    /// Reads the delimiters declared by a header record (with or without its frame number).
+    /// Returns None when the record is not a header or the delimiters are not four distinct characters.
+    pub fn from_header(record: &str) -> Option<Self> {
+        let mut chars = record.trim_start_matches(|c: char| c.is_ascii_digit()).chars();
+        if chars.next() != Some('H') {
+            return None;
+        }
+
+        let declared: Vec<char> = chars.take(4).collect();
+        let [field, repeat, component, escape] = declared[..] else {
+            return None;
+        };
+        let distinct = [repeat, component, escape].iter().all(|c| *c != field)
+            && repeat != component
+            && repeat != escape
+            && component != escape;
+
+        distinct.then_some(Self {
+            field,
+            repeat,
+            component,
+            escape,
+        })
+    }
+
+    /// Splits a record into fields. The delimiter definition of a header record
+    /// is returned verbatim as field 1.
+    pub fn split_fields(&self, record: &str) -> Vec<String> {
+        let body = record.trim_start_matches(|c: char| c.is_ascii_digit());
+        let prefix_len = record.len() - body.len();
+        let definition: String = [self.field, self.repeat, self.component, self.escape].iter().collect();
+
+        if let Some(rest) = body.strip_prefix('H').and_then(|rest| rest.strip_prefix(&definition)) {
+            let mut fields = vec![
+                record[..prefix_len + 1].to_string(),
+                definition[self.field.len_utf8()..].to_string(),
+            ];
+            if let Some(rest) = rest.strip_prefix(self.field) {
+                fields.extend(self.split(rest, self.field));
+            }
+            return fields;
+        }
+
+        self.split(record, self.field)
+    }
+
+    /// Splits a field into repeats
+    pub fn split_repeats(&self, field: &str) -> Vec<String> {
+        self.split(field, self.repeat)
+    }
+
+    /// Splits a field into components, decoding their escape sequences
+    pub fn split_components(&self, field: &str) -> Vec<String> {
+        self.split(field, self.component)
+            .iter()
+            .map(|component| self.unescape(component))
+            .collect()
+    }
+
+    /// Decodes `&F&`, `&S&`, `&R&` and `&E&` into the delimiter characters they
+    /// stand for. Other sequences (`&H&`, `&X0D&`, ...) are kept as sent.
+    pub fn unescape(&self, text: &str) -> String {
+        let mut decoded = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(start) = rest.find(self.escape) {
+            decoded.push_str(&rest[..start]);
+            let after = &rest[start + self.escape.len_utf8()..];
+            let Some(len) = self.sequence_len(after) else {
+                decoded.push(self.escape);
+                rest = after;
+                continue;
+            };
+            match &after[..len - self.escape.len_utf8()] {
+                "F" => decoded.push(self.field),
+                "S" => decoded.push(self.component),
+                "R" => decoded.push(self.repeat),
+                "E" => decoded.push(self.escape),
+                _ => {
+                    decoded.push(self.escape);
+                    decoded.push_str(&after[..len]);
+                }
+            }
+            rest = &after[len..];
+        }
+        decoded.push_str(rest);
+        decoded
+    }
+
+    /// Encodes delimiter characters in text for use as a field or component value
+    pub fn escape(&self, text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            let code = match c {
+                c if c == self.field => 'F',
+                c if c == self.component => 'S',
+                c if c == self.repeat => 'R',
+                c if c == self.escape => 'E',
+                c => {
+                    escaped.push(c);
+                    continue;
+                }
+            };
+            escaped.extend([self.escape, code, self.escape]);
+        }
+        escaped
+    }
+
+    /// Splits on `delimiter` unless it is escaped. A delimiter directly after the
+    /// escape character loses the escape; escape sequences are copied whole.
+    fn split(&self, text: &str, delimiter: char) -> Vec<String> {
+        let mut parts = Vec::new();
+        let mut current = String::new();
+        let mut rest = text;
+
+        while let Some(c) = rest.chars().next() {
+            rest = &rest[c.len_utf8()..];
+            if c == self.escape {
+                if let Some(after) = rest.strip_prefix(delimiter) {
+                    current.push(delimiter);
+                    rest = after;
+                } else if let Some(len) = self.sequence_len(rest) {
+                    // The closing escape of `&E&` must not escape a delimiter that follows
+                    current.push(c);
+                    current.push_str(&rest[..len]);
+                    rest = &rest[len..];
+                } else {
+                    current.push(c);
+                }
+            } else if c == delimiter {
+                parts.push(std::mem::take(&mut current));
+            } else {
+                current.push(c);
+            }
+        }
+        parts.push(current);
+        parts
+    }
+
+    /// Length of the escape sequence body and closing escape at the start of
+    /// `text` (`F&` of `&F&`), if it starts with one
+    fn sequence_len(&self, text: &str) -> Option<usize> {
+        let end = text.find(self.escape)?;
+        let body = &text[..end];
+        let valid = !body.is_empty() && body.chars().all(|c| c.is_ascii_alphanumeric());
+        valid.then_some(end + self.escape.len_utf8())
+    }
+}
+
+// ============================================================================
+// FRAME CHECKSUM
+// ============================================================================
+
+/// ASTM E1381 frame checksum: the byte sum of everything from the frame number
+/// through ETX/ETB inclusive, modulo 256, sent as two hex characters
+pub fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+}
+
+/// Parses the two checksum characters of a frame. Upper- and lowercase hex are
+/// accepted, since some middleware re-frames messages with lowercase checksums.
+pub fn parse_checksum(chars: &[u8]) -> Option<u8> {
+    if chars.len() != 2 || !chars.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    u8::from_str_radix(std::str::from_utf8(chars).ok()?, 16).ok()
+}
+
+// ============================================================================
+// OUTBOUND FRAMES
+// ============================================================================
+
+/// Most characters of record text one frame carries; a longer record continues in
+/// the next frame (ASTM E1381 allows 247 characters per frame including framing)
+pub const MAX_FRAME_TEXT: usize = 240;
+
+/// Frames one record for sending: STX, frame number, record, CR, ETX, the two
+/// checksum characters (sum of frame number..ETX modulo 256) and CR LF
+pub fn encode_frame(frame_number: u8, record: &str) -> Vec<u8> {
+    frame_bytes(frame_number, &format!("{}\r", record), ETX)
+}
+
+/// Frames a transmission's records, numbered from 1. A record longer than
+/// `MAX_FRAME_TEXT` is split into intermediate frames ending in ETB and a final
+/// frame ending in ETX; the record's CR is only sent in its final frame.
+pub fn encode_records(records: &[String]) -> Vec<Vec<u8>> {
+    let mut frames = Vec::new();
+    for record in records {
+        let text = format!("{}\r", record);
+        let mut rest = text.as_str();
+        while !rest.is_empty() {
+            let mut end = rest.len().min(MAX_FRAME_TEXT);
+            while !rest.is_char_boundary(end) {
+                end -= 1;
+            }
+            let (chunk, remaining) = rest.split_at(end);
+            let terminator = if remaining.is_empty() { ETX } else { ETB };
+            frames.push(frame_bytes(((frames.len() + 1) % 8) as u8, chunk, terminator));
+            rest = remaining;
+        }
+    }
+    frames
+}
+
+fn frame_bytes(frame_number: u8, text: &str, terminator: u8) -> Vec<u8> {
+    let mut frame = vec![STX, b'0' + frame_number % 8];
+    frame.extend_from_slice(text.as_bytes());
+    frame.push(terminator);
+    let sum = checksum(&frame[1..]);
+    frame.extend_from_slice(format!("{:02X}", sum).as_bytes());
+    frame.extend_from_slice(&[CR, 0x0A]);
+    frame
+}
+
+/// Records of a worklist download: a header, one patient and order record per
+/// order, and the terminator. Free-text ids are escaped; test ids are sent as
+/// the components they are stored as.
+pub fn order_records(sender_id: &str, orders: &[TestOrder], delimiters: &AstmDelimiters) -> Vec<String> {
+    let f = delimiters.field;
+    let definition: String = [delimiters.repeat, delimiters.component, delimiters.escape].iter().collect();
+    let sender_id = delimiters.escape(sender_id);
+    let mut records = vec![format!("H{f}{definition}{f}{f}{f}{sender_id}{f}{f}{f}{f}{f}{f}{f}P{f}1")];
+
+    for (index, order) in orders.iter().enumerate() {
+        let tests = order
+            .tests
+            .iter()
+            .map(|test| test.universal_id.as_str())
+            .collect::<Vec<_>>()
+            .join(&delimiters.repeat.to_string());
+        records.push(format!("P{f}{}", index + 1));
+        records.push(format!(
+            "O{f}1{f}{}{f}{f}{}{f}{}{f}{f}{f}{f}{f}{f}{}",
+            delimiters.escape(&order.specimen_id),
+            tests,
+            order.priority.code(),
+            order.action_code.code()
+        ));
+    }
+
+    records.push(format!("L{f}1{f}N"));
+    records
+}
+
+/// Records downloading one patient's orders: a header, the patient record with
+/// the id in the field the AutoQuant reports it in (P.4), name, birth date and
+/// sex, one order record per order, and the terminator
+pub fn patient_order_records(
+    sender_id: &str,
+    patient: &Patient,
+    orders: &[TestOrder],
+    delimiters: &AstmDelimiters,
+) -> Vec<String> {
+    let f = delimiters.field;
+    let c = delimiters.component.to_string();
+    let definition: String = [delimiters.repeat, delimiters.component, delimiters.escape].iter().collect();
+    let sender_id = delimiters.escape(sender_id);
+    let name = [&patient.name.last_name, &patient.name.first_name, &patient.name.middle_name]
+        .iter()
+        .map(|part| delimiters.escape(part.as_deref().unwrap_or("")))
+        .collect::<Vec<_>>()
+        .join(&c);
+    let name = name.trim_end_matches(delimiters.component);
+    let birth_date = patient
+        .birth_date
+        .map(|date| date.format("%Y%m%d").to_string())
+        .unwrap_or_default();
+    let sex = String::from(patient.sex.clone());
+
+    let mut records = vec![
+        format!("H{f}{definition}{f}{f}{f}{sender_id}{f}{f}{f}{f}{f}{f}{f}P{f}1"),
+        format!(
+            "P{f}1{f}{f}{}{f}{f}{name}{f}{f}{birth_date}{f}{sex}",
+            delimiters.escape(&patient.id)
+        ),
+    ];
+    for (index, order) in orders.iter().enumerate() {
+        let tests = order
+            .tests
+            .iter()
+            .map(|test| test.universal_id.as_str())
+            .collect::<Vec<_>>()
+            .join(&delimiters.repeat.to_string());
+        records.push(format!(
+            "O{f}{}{f}{}{f}{f}{}{f}{}{f}{f}{f}{f}{f}{f}{}",
+            index + 1,
+            delimiters.escape(&order.specimen_id),
+            tests,
+            order.priority.code(),
+            order.action_code.code()
+        ));
+    }
+    records.push(format!("L{f}1{f}N"));
+    records
+}
+
+/// Records answering a host query (Q) record: the pending orders as a worklist
+/// download, or the query sent back with status X (no information available)
+/// when there are none. The range fields are echoed as received.
+pub fn query_reply_records(
+    sender_id: &str,
+    starting_range: &str,
+    ending_range: &str,
+    orders: &[TestOrder],
+    delimiters: &AstmDelimiters,
+) -> Vec<String> {
+    if !orders.is_empty() {
+        return order_records(sender_id, orders, delimiters);
+    }
+
+    let f = delimiters.field;
+    let definition: String = [delimiters.repeat, delimiters.component, delimiters.escape].iter().collect();
+    let sender_id = delimiters.escape(sender_id);
+    vec![
+        format!("H{f}{definition}{f}{f}{f}{sender_id}{f}{f}{f}{f}{f}{f}{f}P{f}1"),
+        format!("Q{f}1{f}{starting_range}{f}{ending_range}{f}{f}{f}{f}{f}{f}{f}{f}{f}X"),
+        format!("L{f}1{f}N"),
+    ]
+}
+
+// ============================================================================
+// MESSAGE ASSEMBLY
+// ============================================================================
+//
+// A record too long for one frame is sent as intermediate frames ending in ETB
+// followed by a final frame ending in ETX. Frame text is joined until the final
+// frame arrives and only then split into records at CR, so a record broken
+// mid-field is parsed whole. Records are returned prefixed with the number of the
+// frame they started in, the same shape as the text of a single-record frame.
+
+const STX: u8 = 0x02;
+const ETX: u8 = 0x03;
+const ETB: u8 = 0x17;
+const CR: u8 = 0x0D;
+
+/// Why a frame could not be added to the message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssemblyError {
+    /// No STX, frame number 0-7, or ETX/ETB
+    Malformed(String),
+    /// The frame number does not follow the previous frame's
+    OutOfSequence { expected: u8, received: u8 },
+    /// The frame repeats the number of the last frame accepted: the sender missed
+    /// our ACK and sent it again. It is ACKed again but its text is not added.
+    Retransmitted { frame_number: u8 },
+}
+
+impl std::fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssemblyError::Malformed(reason) => write!(f, "Invalid frame structure: {}", reason),
+            AssemblyError::OutOfSequence { expected, received } => {
+                write!(f, "Frame {} out of sequence, expected frame {}", received, expected)
+            }
+            AssemblyError::Retransmitted { frame_number } => {
+                write!(f, "Frame {} retransmitted, already accepted", frame_number)
+            }
+        }
+    }
+}
+
+/// Number of the first frame of a transmission; numbers then run 1..7, 0, 1...
+pub const FIRST_FRAME_NUMBER: u8 = 1;
+
+/// Joins the frames of one message into records
+#[derive(Debug, Clone, Default)]
+pub struct MessageAssembler {
+    /// Number of the last frame accepted; `None` until the transmission's first frame
+    last_frame_number: Option<u8>,
+    /// Text of intermediate frames waiting for the final frame, with the
+    /// number of the frame it started in
+    pending: Option<(u8, Vec<u8>)>,
+}
+
+impl MessageAssembler {
+    /// Adds a complete frame (STX through CR LF). A final frame returns the records
+    /// it completes; an intermediate frame returns none. A frame that is malformed,
+    /// out of sequence or a retransmission of the last frame is rejected and leaves
+    /// the assembler unchanged.
+    pub fn push(&mut self, frame: &[u8]) -> Result<Vec<Vec<u8>>, AssemblyError> {
+        let (frame_number, text, last) = Self::frame_parts(frame)?;
+        if self.last_frame_number == Some(frame_number) {
+            return Err(AssemblyError::Retransmitted { frame_number });
+        }
+        let expected = self.expected_frame_number();
+        if frame_number != expected {
+            return Err(AssemblyError::OutOfSequence {
+                expected,
+                received: frame_number,
+            });
+        }
+        self.last_frame_number = Some(frame_number);
+
+        let (started_in, mut joined) = self.pending.take().unwrap_or((frame_number, Vec::new()));
+        joined.extend_from_slice(text);
+        if !last {
+            self.pending = Some((started_in, joined));
+            return Ok(Vec::new());
+        }
+
+        // Records after the first in the same frame started in this frame
+        let records = joined
+            .split(|&byte| byte == CR)
+            .filter(|record| !record.is_empty())
+            .enumerate()
+            .map(|(index, record)| {
+                let number = if index == 0 { started_in } else { frame_number };
+                let mut numbered = vec![b'0' + number];
+                numbered.extend_from_slice(record);
+                numbered
+            })
+            .collect();
+        Ok(records)
+    }
+
+    /// Number the next frame must carry: 1 for the first frame, then one more than
+    /// the last frame accepted, wrapping from 7 to 0
+    pub fn expected_frame_number(&self) -> u8 {
+        self.last_frame_number
+            .map_or(FIRST_FRAME_NUMBER, |previous| (previous + 1) % 8)
+    }
+
+    /// Whether intermediate frames are still waiting for their final frame
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Frame number, text and whether the frame is final (ETX)
+    fn frame_parts(frame: &[u8]) -> Result<(u8, &[u8], bool), AssemblyError> {
+        let start = frame
+            .iter()
+            .position(|&byte| byte == STX)
+            .ok_or_else(|| AssemblyError::Malformed("missing STX".to_string()))?;
+        let end = frame
+            .iter()
+            .rposition(|&byte| byte == ETX || byte == ETB)
+            .filter(|&end| end > start)
+            .ok_or_else(|| AssemblyError::Malformed("missing ETX or ETB".to_string()))?;
+
+        let frame_number = match frame.get(start + 1).copied() {
+            Some(digit @ b'0'..=b'7') if start + 1 < end => digit - b'0',
+            _ => return Err(AssemblyError::Malformed("missing frame number".to_string())),
+        };
+        Ok((frame_number, &frame[start + 2..end], frame[end] == ETX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escaped_field_separator_stays_in_patient_name() {
+        let delimiters = AstmDelimiters::default();
+        let fields = delimiters.split_fields("3P|1||PAT001|||O&|Brien^Mary||19800512|F");
+
+        assert_eq!(fields.len(), 10);
+        assert_eq!(fields[3], "PAT001");
+        assert_eq!(fields[6], "O|Brien^Mary");
+        assert_eq!(delimiters.split_components(&fields[6]), vec!["O|Brien", "Mary"]);
+        assert_eq!(fields[8], "19800512");
+    }
+
+    #[test]
+    fn test_escape_sequences_round_trip_through_parse_and_encode() {
+        let delimiters = AstmDelimiters::default();
+        let record = "3P|1||PAT&F&7|||O'Brien&S&Anne &E& Co^Mary||19800512|F";
+        let fields = delimiters.split_fields(record);
+
+        assert_eq!(fields.len(), 10);
+        assert_eq!(delimiters.unescape(&fields[3]), "PAT|7");
+        let name = delimiters.split_components(&fields[6]);
+        assert_eq!(name, vec!["O'Brien^Anne & Co", "Mary"]);
+
+        // Encoding the decoded values gives back the record as sent
+        let encoded: Vec<String> = name.iter().map(|component| delimiters.escape(component)).collect();
+        assert_eq!(encoded.join("^"), fields[6]);
+        assert_eq!(delimiters.escape("PAT|7"), fields[3]);
+        assert_eq!(fields.join("|"), record);
+
+        // The closing escape of &E& does not escape the field delimiter after it
+        assert_eq!(delimiters.split_fields("4R|1|A&E&|B"), vec!["4R", "1", "A&E&", "B"]);
+        assert_eq!(delimiters.unescape("A&E&"), "A&");
+        // Unknown sequences and a lone escape character are kept as sent
+        assert_eq!(delimiters.unescape("10&H&5 & up"), "10&H&5 & up");
+        assert_eq!(delimiters.escape("a\\b"), "a&R&b");
+    }
+
+    #[test]
+    fn test_order_records_escape_free_text_ids() {
+        use crate::models::test_order::{ActionCode, OrderPriority, Test};
+        use chrono::Utc;
+
+        let delimiters = AstmDelimiters::default();
+        let order = TestOrder {
+            id: "O1".to_string(),
+            sequence_number: 1,
+            specimen_id: "S|42^B".to_string(),
+            tests: vec![Test { universal_id: "^^^GLU".to_string(), name: "Glucose".to_string() }],
+            priority: OrderPriority::Routine,
+            action_code: ActionCode::New,
+            ordering_provider: None,
+            scheduling_info: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let records = order_records("LIS&1", &[order], &delimiters);
+        assert_eq!(records[0], "H|\\^&|||LIS&E&1|||||||P|1");
+        let fields = delimiters.split_fields(&records[2]);
+        assert_eq!(fields[2], "S&F&42&S&B");
+        assert_eq!(delimiters.unescape(&fields[2]), "S|42^B");
+        assert_eq!(delimiters.split_components(&fields[4]), vec!["", "", "", "GLU"]);
+    }
+
+    #[test]
+    fn test_header_declares_session_delimiters() {
+        let header = "1H!@#$!!!AutoQuant@200i";
+        let delimiters = AstmDelimiters::from_header(header).unwrap();
+        assert_eq!(
+            delimiters,
+            AstmDelimiters {
+                field: '!',
+                repeat: '@',
+                component: '#',
+                escape: '$',
+            }
+        );
+
+        let fields = delimiters.split_fields(header);
+        assert_eq!(fields[0], "1H");
+        assert_eq!(fields[1], "@#$");
+        assert_eq!(fields[4], "AutoQuant@200i");
+        assert_eq!(delimiters.split_fields("4R!1!Na$!K!140"), vec!["4R", "1", "Na!K", "140"]);
+
+        let standard = AstmDelimiters::from_header("1H|\\^&|||AutoQuant").unwrap();
+        assert_eq!(standard, AstmDelimiters::default());
+        assert_eq!(standard.split_fields("1H|\\^&|||AutoQuant")[4], "AutoQuant");
+        assert!(AstmDelimiters::from_header("1P|1").is_none());
+        assert!(AstmDelimiters::from_header("1H||^&").is_none());
+    }
+
+    #[test]
+    fn test_encoded_frame_carries_its_checksum() {
+        let frame = encode_frame(1, "L|1|N");
+        assert_eq!(frame, b"\x021L|1|N\r\x0304\r\n".to_vec());
+        assert_eq!(encode_frame(8, "L|1|N")[1], b'0');
+    }
+
+    #[test]
+    fn test_long_record_continues_in_etb_frames() {
+        let short = "L|1|N".to_string();
+        assert_eq!(encode_records(&[short.clone()]), vec![encode_frame(1, &short)]);
+
+        // 300 characters plus CR: one full ETB frame, then the rest with ETX
+        let long = format!("O|1|S42||{}", "X".repeat(288));
+        assert_eq!(long.len(), 297);
+        let frames = encode_records(&[short.clone(), long.clone(), short]);
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[1].len(), MAX_FRAME_TEXT + 6);
+        assert_eq!(frames[1][MAX_FRAME_TEXT + 2], ETB);
+        assert_eq!(frames[2][frames[2].len() - 5], ETX);
+        assert_eq!(frames[3][1], b'4');
+
+        // The receiving side joins them back into the record
+        let mut assembler = MessageAssembler::default();
+        assembler.push(&frames[0]).unwrap();
+        assert!(assembler.push(&frames[1]).unwrap().is_empty());
+        assert_eq!(assembler.push(&frames[2]).unwrap(), vec![format!("2{}", long).into_bytes()]);
+    }
+
+    #[test]
+    fn test_patient_order_records_carry_the_patient() {
+        use crate::models::patient::{PatientName, Sex};
+        use crate::models::test_order::{ActionCode, OrderPriority, Test};
+        use chrono::{TimeZone, Utc};
+
+        let delimiters = AstmDelimiters::default();
+        let now = Utc::now();
+        let patient = Patient {
+            name: PatientName {
+                last_name: Some("Rao".to_string()),
+                first_name: Some("Asha".to_string()),
+                ..Default::default()
+            },
+            birth_date: Some(Utc.with_ymd_and_hms(1984, 2, 29, 0, 0, 0).unwrap()),
+            sex: Sex::Female,
+            ..Patient::fixture("P|7")
+        };
+        let order = |id: &str, specimen: &str| TestOrder {
+            id: id.to_string(),
+            sequence_number: 1,
+            specimen_id: specimen.to_string(),
+            tests: vec![Test { universal_id: "^^^GLU".to_string(), name: "Glucose".to_string() }],
+            priority: OrderPriority::Stat,
+            action_code: ActionCode::New,
+            ordering_provider: None,
+            scheduling_info: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let records =
+            patient_order_records("LIS", &patient, &[order("O1", "S1"), order("O2", "S2")], &delimiters);
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[1], "P|1||P&F&7||Rao^Asha||19840229|F");
+        assert_eq!(records[2], "O|1|S1||^^^GLU|S||||||N");
+        assert!(records[3].starts_with("O|2|S2|"));
+        assert_eq!(records[4], "L|1|N");
+    }
+
+    /// Frames captured from a Meril AutoQuant, checksum characters as sent
+    const MERIL_FRAMES: [&[u8]; 3] = [
+        b"\x021H|\\^&|||AutoQuant^01^1.0|||||||P|E1394-97|20250101120000\r\x03B4\r\n",
+        b"\x022R|1|S42|^^^GLU|95.2|mg/dL|70^110|N||F||||20250101120500\r\x03EB\r\n",
+        b"\x023L|1|N\r\x0306\r\n",
+    ];
+
+    #[test]
+    fn test_checksum_is_sum_modulo_256_of_meril_frames() {
+        for frame in MERIL_FRAMES {
+            let etx = frame.iter().position(|&b| b == 0x03).unwrap();
+            let sent = parse_checksum(&frame[etx + 1..etx + 3]).unwrap();
+            assert_eq!(checksum(&frame[1..=etx]), sent, "{:?}", String::from_utf8_lossy(frame));
+            // The sum modulo 8 would not match what the analyzer sends
+            let sum: u32 = frame[1..=etx].iter().map(|&b| b as u32).sum();
+            assert_ne!((sum % 8) as u8, sent);
+        }
+        assert_eq!(checksum(&[0xFF, 0x02]), 0x01);
+        assert_eq!(checksum(&[]), 0);
+    }
+
+    #[test]
+    fn test_encode_frame_matches_meril_frames() {
+        for (index, frame) in MERIL_FRAMES.iter().enumerate() {
+            let record = std::str::from_utf8(&frame[2..frame.len() - 6]).unwrap();
+            assert_eq!(encode_frame(index as u8 + 1, record), frame.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_checksum_hex_is_case_insensitive() {
+        assert_eq!(parse_checksum(b"A3"), Some(0xA3));
+        assert_eq!(parse_checksum(b"a3"), Some(0xA3));
+        assert_eq!(parse_checksum(b"0f"), Some(0x0F));
+        assert_eq!(parse_checksum(b"+a"), None);
+        assert_eq!(parse_checksum(b"A"), None);
+        assert_eq!(parse_checksum(b"G0"), None);
+    }
+
+    /// Frames text with the given terminator (ETX or ETB) and its checksum
+    fn frame_with(frame_number: u8, text: &str, terminator: u8) -> Vec<u8> {
+        let mut frame = vec![STX, b'0' + frame_number];
+        frame.extend_from_slice(text.as_bytes());
+        frame.push(terminator);
+        let sum = checksum(&frame[1..]);
+        frame.extend_from_slice(format!("{:02X}\r\n", sum).as_bytes());
+        frame
+    }
+
+    #[test]
+    fn test_result_record_split_mid_field_is_assembled_whole() {
+        let mut assembler = MessageAssembler::default();
+        let header = assembler.push(&encode_frame(1, "H|\\^&|||AutoQuant")).unwrap();
+        assert_eq!(header, vec![b"1H|\\^&|||AutoQuant".to_vec()]);
+
+        // The result value 95.2 is broken across the intermediate and final frame
+        assert!(assembler.push(&frame_with(2, "R|1|S42|^^^GLU|9", ETB)).unwrap().is_empty());
+        assert!(assembler.is_pending());
+        let records = assembler.push(&frame_with(3, "5.2|mg/dL|70^110|N||F\r", ETX)).unwrap();
+        assert!(!assembler.is_pending());
+        assert_eq!(records, vec![b"2R|1|S42|^^^GLU|95.2|mg/dL|70^110|N||F".to_vec()]);
+
+        let fields = AstmDelimiters::default().split_fields(&String::from_utf8_lossy(&records[0]));
+        assert_eq!(fields[4], "95.2");
+        assert_eq!(fields[5], "mg/dL");
+
+        assert_eq!(assembler.push(&encode_frame(4, "L|1|N")).unwrap(), vec![b"4L|1|N".to_vec()]);
+    }
+
+    #[test]
+    fn test_frames_out_of_sequence_are_rejected_and_numbers_wrap() {
+        let mut assembler = MessageAssembler::default();
+        for number in 1..=5 {
+            assert_eq!(assembler.push(&frame_with(number, "C|1|||note\r", ETX)).unwrap().len(), 1);
+        }
+        assert!(assembler.push(&frame_with(6, "R|1|S42|^^^GLU|", ETB)).unwrap().is_empty());
+
+        // Skipped and repeated frames are rejected without touching the partial record
+        assert_eq!(
+            assembler.push(&frame_with(0, "95|mg/dL\r", ETX)),
+            Err(AssemblyError::OutOfSequence { expected: 7, received: 0 })
+        );
+        assert_eq!(
+            assembler.push(&frame_with(6, "R|1|S42|^^^GLU|", ETB)),
+            Err(AssemblyError::Retransmitted { frame_number: 6 })
+        );
+        assert_eq!(
+            assembler.push(&frame_with(5, "C|1|||note\r", ETX)),
+            Err(AssemblyError::OutOfSequence { expected: 7, received: 5 })
+        );
+        assert_eq!(assembler.expected_frame_number(), 7);
+
+        assert!(assembler.push(&frame_with(7, "95|mg/dL", ETB)).unwrap().is_empty());
+        let records = assembler.push(&frame_with(0, "|70^110\rL|1|N\r", ETX)).unwrap();
+        assert_eq!(records, vec![b"6R|1|S42|^^^GLU|95|mg/dL|70^110".to_vec(), b"0L|1|N".to_vec()]);
+    }
+
+    #[test]
+    fn test_transmission_starts_at_frame_one() {
+        let mut assembler = MessageAssembler::default();
+        assert_eq!(assembler.expected_frame_number(), FIRST_FRAME_NUMBER);
+
+        // A lost first frame is detected; frame 0 is not mistaken for a retransmission
+        assert_eq!(
+            assembler.push(&encode_frame(2, "P|1")),
+            Err(AssemblyError::OutOfSequence { expected: 1, received: 2 })
+        );
+        assert_eq!(
+            assembler.push(&encode_frame(0, "P|1")),
+            Err(AssemblyError::OutOfSequence { expected: 1, received: 0 })
+        );
+        assert_eq!(assembler.push(&encode_frame(1, "H|\\^&")).unwrap(), vec![b"1H|\\^&".to_vec()]);
+    }
+
+    #[test]
+    fn test_malformed_frames_are_rejected() {
+        let mut assembler = MessageAssembler::default();
+        assert!(matches!(assembler.push(&[STX, ETX, b'0', b'0', CR, b'\n']), Err(AssemblyError::Malformed(_))));
+        assert!(matches!(assembler.push(b"\x029L|1|N\x03\r\n"), Err(AssemblyError::Malformed(_))));
+        assert!(matches!(assembler.push(b"\x021L|1|N\r\n"), Err(AssemblyError::Malformed(_))));
+        assert!(!assembler.is_pending());
+    }
+}
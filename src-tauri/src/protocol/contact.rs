@@ -0,0 +1,428 @@
+use crate::models::patient::{ContactInfo, PatientAddress};
+use crate::protocol::astm::AstmDelimiters;
+
+// ============================================================================
+// HL7 XTN / XAD
+// ============================================================================
+
+fn component(components: &[&str], index: usize) -> Option<String> {
+    components
+        .get(index)
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty())
+        .map(|c| c.to_string())
+}
+
+/// Parses one HL7 XTN value (use `parse_xtn_repeats` for a whole field).
+/// Prefers the structured components (XTN-5..8) and falls back to normalizing
+/// the free-text XTN-1 when they are absent.
+pub fn parse_xtn(value: &str) -> ContactInfo {
+    let raw = value.trim().to_string();
+    let components: Vec<&str> = raw.split('^').collect();
+    let use_code = component(&components, 1);
+    let equipment_type = component(&components, 2);
+
+    if let Some(local_number) = component(&components, 6) {
+        return ContactInfo {
+            use_code,
+            equipment_type,
+            country_code: component(&components, 4),
+            area_code: component(&components, 5),
+            local_number: Some(local_number),
+            extension: component(&components, 7),
+            raw,
+            unparsed: false,
+        };
+    }
+
+    match component(&components, 0) {
+        Some(text) => ContactInfo {
+            use_code,
+            equipment_type,
+            raw,
+            ..normalize_phone_text(&text)
+        },
+        None => ContactInfo {
+            raw,
+            unparsed: true,
+            ..Default::default()
+        },
+    }
+}
+
+/// Parses every repetition of an XTN field, skipping empty ones
+pub fn parse_xtn_repeats(field: &str) -> Vec<ContactInfo> {
+    field
+        .split('~')
+        .filter(|repeat| !repeat.trim().is_empty())
+        .map(parse_xtn)
+        .collect()
+}
+
+/// Parses the first repetition of an HL7 XAD field. Returns None for an empty field.
+pub fn parse_xad(field: &str) -> Option<PatientAddress> {
+    let raw = field.split('~').next().unwrap_or("").trim().to_string();
+    if raw.is_empty() {
+        return None;
+    }
+
+    let components: Vec<&str> = raw.split('^').collect();
+    // XAD-1 is a SAD: street address & street name & dwelling number
+    let street = components.first().and_then(|sad| {
+        let parts: Vec<&str> = sad.split('&').map(str::trim).collect();
+        match parts.first() {
+            Some(full) if !full.is_empty() => Some(full.to_string()),
+            _ => {
+                let joined = parts[1..]
+                    .iter()
+                    .rev()
+                    .filter(|p| !p.is_empty())
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                (!joined.is_empty()).then_some(joined)
+            }
+        }
+    });
+
+    let address = PatientAddress {
+        street,
+        other_designation: component(&components, 1),
+        city: component(&components, 2),
+        state: component(&components, 3),
+        zip: component(&components, 4),
+        country_code: component(&components, 5),
+        raw: Some(raw.clone()),
+        unparsed: false,
+    };
+
+    if address.street.is_none()
+        && address.other_designation.is_none()
+        && address.city.is_none()
+        && address.state.is_none()
+        && address.zip.is_none()
+        && address.country_code.is_none()
+    {
+        return Some(unparsed_address(raw));
+    }
+    Some(address)
+}
+
+// ============================================================================
+// ASTM FIELDS
+// ============================================================================
+
+/// Reads ASTM patient field 11. A component-structured value is read as
+/// street^city^state^zip^country; anything else is treated as free text.
+pub fn astm_address(field: &str, delimiters: &AstmDelimiters) -> Option<PatientAddress> {
+    let raw = field.trim();
+    if raw.is_empty() {
+        return None;
+    }
+
+    let components = delimiters.split_components(raw);
+    if components.len() < 2 {
+        return Some(PatientAddress {
+            raw: Some(raw.to_string()),
+            ..normalize_address_text(&components[0])
+        });
+    }
+
+    let components: Vec<&str> = components.iter().map(String::as_str).collect();
+    Some(PatientAddress {
+        street: component(&components, 0),
+        other_designation: None,
+        city: component(&components, 1),
+        state: component(&components, 2),
+        zip: component(&components, 3),
+        country_code: component(&components, 4),
+        raw: Some(raw.to_string()),
+        unparsed: false,
+    })
+}
+
+/// Reads ASTM patient field 13, one contact per repeat
+pub fn astm_contacts(field: &str, delimiters: &AstmDelimiters) -> Vec<ContactInfo> {
+    delimiters
+        .split_repeats(field)
+        .iter()
+        .filter(|repeat| !repeat.trim().is_empty())
+        .map(|repeat| {
+            let text = delimiters.split_components(repeat).join(" ");
+            ContactInfo {
+                raw: repeat.trim().to_string(),
+                ..normalize_phone_text(&text)
+            }
+        })
+        .collect()
+}
+
+// ============================================================================
+// FREE-TEXT NORMALIZATION
+// ============================================================================
+
+fn unparsed_address(raw: String) -> PatientAddress {
+    PatientAddress {
+        raw: Some(raw),
+        unparsed: true,
+        ..Default::default()
+    }
+}
+
+/// Best-effort split of a free-text phone number such as "+44 20 7946 0958",
+/// "(555) 123-4567 ext. 89" or "5551234567". Text that does not look like a
+/// phone number is kept raw-only with `unparsed` set.
+pub fn normalize_phone_text(text: &str) -> ContactInfo {
+    let raw = text.trim().to_string();
+    let unparsed = || ContactInfo {
+        raw: raw.clone(),
+        unparsed: true,
+        ..Default::default()
+    };
+
+    // Extension: everything from the first letter on, e.g. "x89", "ext. 89"
+    let (number, extension) = match raw.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(at) => {
+            let marker = raw[at..].to_ascii_lowercase();
+            let digits = ["ext.", "ext", "x"]
+                .iter()
+                .find_map(|m| marker.strip_prefix(m))
+                .map(str::trim);
+            match digits {
+                Some(d) if !d.is_empty() && d.chars().all(|c| c.is_ascii_digit()) => {
+                    (&raw[..at], Some(d.to_string()))
+                }
+                _ => return unparsed(),
+            }
+        }
+        None => (raw.as_str(), None),
+    };
+
+    if !number.chars().all(|c| c.is_ascii_digit() || " -.()+/".contains(c)) {
+        return unparsed();
+    }
+
+    // Digit groups in order, noting which one was parenthesized
+    let mut groups: Vec<(String, bool)> = Vec::new();
+    let mut current = String::new();
+    let mut in_paren = false;
+    for c in number.chars() {
+        if c.is_ascii_digit() {
+            current.push(c);
+            continue;
+        }
+        if !current.is_empty() {
+            groups.push((std::mem::take(&mut current), in_paren));
+        }
+        match c {
+            '(' => in_paren = true,
+            ')' => in_paren = false,
+            _ => {}
+        }
+    }
+    if !current.is_empty() {
+        groups.push((current, in_paren));
+    }
+
+    let mut groups = groups.into_iter();
+    let mut country_code = None;
+    if number.trim_start().starts_with('+') {
+        country_code = groups.next().map(|(g, _)| g);
+    }
+
+    let rest: Vec<(String, bool)> = groups.collect();
+    let mut area_code = None;
+    let joined = |groups: &[(String, bool)]| groups.iter().map(|(g, _)| g.as_str()).collect::<String>();
+
+    let local_number = if let Some(pos) = rest.iter().position(|(_, paren)| *paren) {
+        if pos != 0 {
+            return unparsed();
+        }
+        area_code = Some(rest[0].0.clone());
+        joined(&rest[1..])
+    } else if rest.len() == 1 {
+        let digits = &rest[0].0;
+        if digits.len() == 11 && digits.starts_with('1') && country_code.is_none() {
+            country_code = Some("1".to_string());
+            area_code = Some(digits[1..4].to_string());
+            digits[4..].to_string()
+        } else if digits.len() == 10 {
+            area_code = Some(digits[..3].to_string());
+            digits[3..].to_string()
+        } else {
+            digits.clone()
+        }
+    } else if joined(&rest).len() > 7 {
+        area_code = Some(rest[0].0.clone());
+        joined(&rest[1..])
+    } else {
+        joined(&rest)
+    };
+
+    if !(4..=12).contains(&local_number.len()) {
+        return unparsed();
+    }
+
+    ContactInfo {
+        use_code: None,
+        equipment_type: None,
+        country_code,
+        area_code,
+        local_number: Some(local_number),
+        extension,
+        raw,
+        unparsed: false,
+    }
+}
+
+/// Best-effort split of a free-text address such as
+/// "12 Main St, Springfield IL 62704, USA". The first comma- or line-separated
+/// part is the street; a trailing token with digits is taken as the postal code
+/// and the remaining parts fill city, state and country in that order.
+/// Single-part text is kept raw-only with `unparsed` set.
+pub fn normalize_address_text(text: &str) -> PatientAddress {
+    let raw = text.trim().to_string();
+    let parts: Vec<&str> = raw
+        .split([',', ';', '\n'])
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect();
+    if parts.len() < 2 {
+        return unparsed_address(raw);
+    }
+
+    let mut zip = None;
+    let mut state = None;
+    let mut rest: Vec<String> = Vec::new();
+    for part in &parts[1..] {
+        let mut words: Vec<&str> = part.split_whitespace().collect();
+        if zip.is_none() && words.last().is_some_and(|w| w.chars().any(|c| c.is_ascii_digit())) {
+            zip = words.pop().map(str::to_string);
+            // "Springfield IL 62704": a short upper-case word before the code is the state
+            let looks_like_state = |w: &&str| (2..=3).contains(&w.len()) && w.chars().all(|c| c.is_ascii_uppercase());
+            if words.len() >= 2 && words.last().is_some_and(looks_like_state) {
+                state = words.pop().map(str::to_string);
+            }
+        }
+        if !words.is_empty() {
+            rest.push(words.join(" "));
+        }
+    }
+
+    let mut rest = rest.into_iter();
+    let city = rest.next();
+    if state.is_none() {
+        state = rest.next();
+    }
+    let country_code = rest.next();
+
+    PatientAddress {
+        street: Some(parts[0].to_string()),
+        other_designation: None,
+        city,
+        state,
+        zip,
+        country_code,
+        raw: Some(raw),
+        unparsed: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xtn_with_components() {
+        let local_only = parse_xtn("^^^^^^5551234");
+        assert_eq!(local_only.local_number.as_deref(), Some("5551234"));
+        assert_eq!(local_only.area_code, None);
+        assert!(!local_only.unparsed);
+
+        let full = parse_xtn("^WPN^PH^^1^555^1234567^89");
+        assert_eq!(full.use_code.as_deref(), Some("WPN"));
+        assert_eq!(full.equipment_type.as_deref(), Some("PH"));
+        assert_eq!(full.country_code.as_deref(), Some("1"));
+        assert_eq!(full.area_code.as_deref(), Some("555"));
+        assert_eq!(full.local_number.as_deref(), Some("1234567"));
+        assert_eq!(full.extension.as_deref(), Some("89"));
+        assert_eq!(full.raw, "^WPN^PH^^1^555^1234567^89");
+
+        let repeats = parse_xtn_repeats("^PRN^PH^^^555^1111111~^PRN^CP^^^555^2222222");
+        assert_eq!(repeats.len(), 2);
+        assert_eq!(repeats[1].equipment_type.as_deref(), Some("CP"));
+    }
+
+    #[test]
+    fn test_xtn_without_components() {
+        let legacy = parse_xtn("(555)123-4567 X89");
+        assert_eq!(legacy.area_code.as_deref(), Some("555"));
+        assert_eq!(legacy.local_number.as_deref(), Some("1234567"));
+        assert_eq!(legacy.extension.as_deref(), Some("89"));
+        assert_eq!(legacy.raw, "(555)123-4567 X89");
+
+        let with_use = parse_xtn("555-1234^PRN^PH");
+        assert_eq!(with_use.use_code.as_deref(), Some("PRN"));
+        assert_eq!(with_use.local_number.as_deref(), Some("5551234"));
+
+        let empty = parse_xtn("^PRN^PH");
+        assert!(empty.unparsed);
+        assert_eq!(empty.raw, "^PRN^PH");
+    }
+
+    #[test]
+    fn test_xad_components() {
+        let address = parse_xad("123 Main St^Apt 4^Springfield^IL^62704^USA").unwrap();
+        assert_eq!(address.street.as_deref(), Some("123 Main St"));
+        assert_eq!(address.other_designation.as_deref(), Some("Apt 4"));
+        assert_eq!(address.city.as_deref(), Some("Springfield"));
+        assert_eq!(address.state.as_deref(), Some("IL"));
+        assert_eq!(address.zip.as_deref(), Some("62704"));
+        assert_eq!(address.country_code.as_deref(), Some("USA"));
+        assert!(!address.unparsed);
+
+        let sad = parse_xad("&Main St&123^^Springfield").unwrap();
+        assert_eq!(sad.street.as_deref(), Some("123 Main St"));
+
+        assert!(parse_xad("^^^^").unwrap().unparsed);
+        assert!(parse_xad("").is_none());
+    }
+
+    #[test]
+    fn test_messy_free_text() {
+        let international = normalize_phone_text(" +44 20 7946 0958 ");
+        assert_eq!(international.country_code.as_deref(), Some("44"));
+        assert_eq!(international.area_code.as_deref(), Some("20"));
+        assert_eq!(international.local_number.as_deref(), Some("79460958"));
+
+        let run_on = normalize_phone_text("15551234567");
+        assert_eq!(run_on.country_code.as_deref(), Some("1"));
+        assert_eq!(run_on.area_code.as_deref(), Some("555"));
+
+        let words = normalize_phone_text("call mother after 5pm");
+        assert!(words.unparsed);
+        assert_eq!(words.local_number, None);
+        assert_eq!(words.raw, "call mother after 5pm");
+
+        let address = normalize_address_text("12 Main St, Springfield IL 62704, USA");
+        assert_eq!(address.street.as_deref(), Some("12 Main St"));
+        assert_eq!(address.city.as_deref(), Some("Springfield"));
+        assert_eq!(address.state.as_deref(), Some("IL"));
+        assert_eq!(address.zip.as_deref(), Some("62704"));
+        assert_eq!(address.country_code.as_deref(), Some("USA"));
+
+        let one_line = normalize_address_text("behind the old mill");
+        assert!(one_line.unparsed);
+        assert_eq!(one_line.raw.as_deref(), Some("behind the old mill"));
+
+        let delimiters = AstmDelimiters::default();
+        let contacts = astm_contacts("555 1234\\(555) 987-6543", &delimiters);
+        assert_eq!(contacts.len(), 2);
+        assert_eq!(contacts[0].local_number.as_deref(), Some("5551234"));
+        assert_eq!(contacts[1].area_code.as_deref(), Some("555"));
+
+        let structured = astm_address("1 High St^Leeds^^LS1 4AP^GB", &delimiters).unwrap();
+        assert_eq!(structured.city.as_deref(), Some("Leeds"));
+        assert_eq!(structured.zip.as_deref(), Some("LS1 4AP"));
+    }
+}
@@ -0,0 +1,229 @@
+use chrono::{NaiveDateTime, Utc};
+
+use crate::models::result::{ResultFlags, TestResultMetadata};
+use crate::models::{Analyzer, DelimitedLayout, Protocol, ResultStatus, TestResult};
+
+// ============================================================================
+// RESULT DECODERS
+// ============================================================================
+
+/// Turns one complete message from an instrument into results. ASTM and HL7 are
+/// decoded by their services' session state machines; this covers instruments that
+/// simply dump text.
+pub trait ResultDecoder: Send + Sync + std::fmt::Debug {
+    /// Protocol name for logs
+    fn protocol(&self) -> &'static str;
+
+    /// Decodes a message into one entry per result line: the result, or why the line
+    /// was rejected. `analyzer_id` is left for the caller to set.
+    fn decode(&self, message: &[u8]) -> Vec<Result<TestResult, String>>;
+}
+
+/// The decoder for an analyzer's protocol, if it uses one
+pub fn decoder_for(analyzer: &Analyzer) -> Option<Box<dyn ResultDecoder>> {
+    match analyzer.protocol {
+        Protocol::DelimitedText => Some(Box::new(DelimitedTextDecoder::new(analyzer.delimited_layout.clone()))),
+        Protocol::Astm | Protocol::Hl7 | Protocol::Hl7V24 | Protocol::Hl7V231 => None,
+    }
+}
+
+// ============================================================================
+// DELIMITED TEXT
+// ============================================================================
+
+/// One result per line, columns as described by the analyzer's [`DelimitedLayout`]
+#[derive(Debug)]
+pub struct DelimitedTextDecoder {
+    layout: DelimitedLayout,
+}
+
+impl DelimitedTextDecoder {
+    pub fn new(layout: DelimitedLayout) -> Self {
+        Self { layout }
+    }
+
+    /// Splits a line on the delimiter; a field in double quotes may contain the
+    /// delimiter, and `""` inside quotes is a literal quote
+    fn split_line(&self, line: &str) -> Vec<String> {
+        let mut fields = Vec::new();
+        let mut current = String::new();
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    current.push('"');
+                    chars.next();
+                }
+                '"' => quoted = !quoted,
+                c if c == self.layout.delimiter && !quoted => fields.push(std::mem::take(&mut current)),
+                c => current.push(c),
+            }
+        }
+        fields.push(current);
+        fields.into_iter().map(|field| field.trim().to_string()).collect()
+    }
+
+    fn decode_line(&self, line_number: usize, line: &str) -> Result<TestResult, String> {
+        let fields = self.split_line(line);
+        let required = |column: usize, name: &str| -> Result<String, String> {
+            match fields.get(column) {
+                Some(value) if !value.is_empty() => Ok(value.clone()),
+                _ => Err(format!("Line {}: no {} in column {}", line_number, name, column)),
+            }
+        };
+        let optional = |column: Option<usize>| {
+            column
+                .and_then(|column| fields.get(column))
+                .filter(|value| !value.is_empty())
+                .cloned()
+        };
+
+        // A result without its completion time cannot be placed in the patient's history
+        let completed_at = optional(self.layout.completed_at_column)
+            .ok_or_else(|| format!("Line {}: no completion time", line_number))?;
+        let completed_date_time = NaiveDateTime::parse_from_str(&completed_at, &self.layout.completed_at_format)
+            .map_err(|e| format!("Line {}: invalid completion time {}: {}", line_number, completed_at, e))?
+            .and_utc();
+
+        let now = Utc::now();
+        Ok(TestResult {
+            id: uuid::Uuid::new_v4().to_string(),
+            test_id: required(self.layout.test_column, "test")?,
+            sample_id: required(self.layout.sample_id_column, "sample id")?,
+            value: required(self.layout.value_column, "value")?,
+            units: optional(self.layout.units_column),
+            reference_range: None,
+            flags: ResultFlags::from_parts(&optional(self.layout.flag_column).into_iter().collect::<Vec<_>>(), None),
+            status: ResultStatus::Final,
+            completed_date_time: Some(completed_date_time),
+            metadata: TestResultMetadata {
+                sequence_number: line_number as u32,
+                instrument: None,
+                operator: None,
+                dilution_factor: None,
+                raw_value: None,
+                source_message_control_id: None,
+                verification: None,
+                parameter_name: None,
+                panel: None,
+                analysis_mode: None,
+                unrecognized_status: None,
+            },
+            analyzer_id: None,
+            comments: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        })
+    }
+}
+
+impl ResultDecoder for DelimitedTextDecoder {
+    fn protocol(&self) -> &'static str {
+        "DELIMITED_TEXT"
+    }
+
+    fn decode(&self, message: &[u8]) -> Vec<Result<TestResult, String>> {
+        let text = String::from_utf8_lossy(message);
+        let lines = text
+            .split('\n')
+            .map(|line| line.trim_end_matches('\r'))
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .skip(usize::from(self.layout.has_header));
+
+        lines
+            .map(|(index, line)| self.decode_line(index + 1, line))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn delimited_analyzer(layout: DelimitedLayout) -> Analyzer {
+        let mut analyzer = crate::test_support::meril_analyzer();
+        analyzer.protocol = Protocol::DelimitedText;
+        analyzer.delimited_layout = layout;
+        analyzer
+    }
+
+    #[test]
+    fn test_delimited_record_decodes_into_test_results() {
+        let decoder = decoder_for(&delimited_analyzer(DelimitedLayout::default())).unwrap();
+        assert_eq!(decoder.protocol(), "DELIMITED_TEXT");
+
+        let message = [
+            "S1001,GLU,95.2,mg/dL,N,20250101120500",
+            "S1001,\"NA, serum\",141,mmol/L,,20250101120510",
+            "",
+        ]
+        .join("\r\n");
+        let results = decoder
+            .decode(message.as_bytes())
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(results.len(), 2);
+
+        assert_eq!(results[0].sample_id, "S1001");
+        assert_eq!(results[0].test_id, "GLU");
+        assert_eq!(results[0].value, "95.2");
+        assert_eq!(results[0].units.as_deref(), Some("mg/dL"));
+        assert_eq!(results[0].flag_list(), vec!["N".to_string()]);
+        assert_eq!(results[0].status, ResultStatus::Final);
+        assert_eq!(
+            results[0].completed_date_time,
+            Some(Utc.with_ymd_and_hms(2025, 1, 1, 12, 5, 0).unwrap())
+        );
+
+        assert_eq!(results[1].test_id, "NA, serum");
+        assert!(results[1].flags.is_none());
+        assert!(results.iter().all(|result| result.analyzer_id.is_none()));
+    }
+
+    #[test]
+    fn test_layout_with_header_and_other_delimiter() {
+        let layout = DelimitedLayout {
+            delimiter: ';',
+            has_header: true,
+            sample_id_column: 1,
+            test_column: 0,
+            value_column: 2,
+            units_column: None,
+            flag_column: None,
+            completed_at_column: Some(3),
+            completed_at_format: "%d/%m/%Y %H:%M".to_string(),
+        };
+        let decoder = decoder_for(&delimited_analyzer(layout)).unwrap();
+
+        let results = decoder.decode(b"Test;Sample;Result;Time\nHB;S77;13.4;02/01/2025 08:30\n");
+        assert_eq!(results.len(), 1);
+        let result = results[0].as_ref().unwrap();
+        assert_eq!((result.sample_id.as_str(), result.test_id.as_str()), ("S77", "HB"));
+        assert!(result.units.is_none());
+        assert_eq!(result.completed_date_time, Some(Utc.with_ymd_and_hms(2025, 1, 2, 8, 30, 0).unwrap()));
+
+        // Bad lines are rejected one by one; the good lines around them still decode
+        let results = decoder.decode(b"Test;Sample;Result;Time\nHB;;13.4;02/01/2025 08:30\nWBC;S77;6.1;\nPLT;S77;250;02/01/2025 08:31\n");
+        assert_eq!(results.len(), 3);
+        let error = results[0].as_ref().unwrap_err();
+        assert!(error.contains("no sample id"), "{}", error);
+        // A missing completion time is never replaced with the time of receipt
+        let error = results[1].as_ref().unwrap_err();
+        assert!(error.contains("no completion time"), "{}", error);
+        assert_eq!(results[2].as_ref().unwrap().test_id, "PLT");
+    }
+
+    #[test]
+    fn test_astm_and_hl7_analyzers_have_no_text_decoder() {
+        let mut analyzer = delimited_analyzer(DelimitedLayout::default());
+        analyzer.protocol = Protocol::Astm;
+        assert!(decoder_for(&analyzer).is_none());
+        assert!(DelimitedLayout { value_column: 0, ..DelimitedLayout::default() }.validate().is_err());
+        assert!(DelimitedLayout { completed_at_column: None, ..DelimitedLayout::default() }.validate().is_err());
+        assert!(DelimitedLayout::default().validate().is_ok());
+    }
+}
@@ -0,0 +1,239 @@
+// ============================================================================
+// ED (ENCAPSULATED DATA) IMAGE PAYLOADS
+// ============================================================================
+//
+// CQ 5 Plus sends histograms and scattergrams as OBX segments of type ED, with
+// OBX-5 shaped `^Image^PNG^Base64^<data>`. Payloads can arrive truncated when
+// MLLP reassembly goes wrong, so images are checked before they are kept.
+// Large scattergrams may be split over several OBX segments with the same
+// identifier and increasing sub-ids; the base64 pieces are joined in sub-id
+// order before decoding.
+
+/// PNG file signature
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// Decodes the PNG carried in an ED observation value and checks it is intact
+pub fn decode_ed_png(observation_value: &str) -> Result<Vec<u8>, String> {
+    let encoded = observation_value.rsplit('^').next().unwrap_or("").trim();
+    if encoded.is_empty() {
+        return Err("ED observation has no image data".to_string());
+    }
+
+    let bytes = decode_base64(encoded)?;
+    validate_png(&bytes)?;
+    Ok(bytes)
+}
+
+/// Sort key of a multi-part ED sub-id ("2", "1.2", ...); non-numeric parts sort last
+pub fn ed_part_order(sub_id: &str) -> Vec<u32> {
+    sub_id
+        .split('.')
+        .map(|part| part.trim().parse::<u32>().unwrap_or(u32::MAX))
+        .collect()
+}
+
+/// Joins the values of a multi-part ED observation, already in sub-id order, into
+/// one value. The encapsulation header of the first part is kept; continuation
+/// parts contribute only their data.
+pub fn join_ed_parts(values: &[&str]) -> String {
+    let header = values
+        .first()
+        .and_then(|first| first.rfind('^').map(|end| &first[..=end]))
+        .unwrap_or("");
+    let data: String = values
+        .iter()
+        .map(|value| value.rsplit('^').next().unwrap_or("").trim())
+        .collect();
+    format!("{}{}", header, data)
+}
+
+/// Checks the PNG signature, walks every chunk verifying its length and CRC,
+/// and requires the image to end with an IEND chunk
+pub fn validate_png(bytes: &[u8]) -> Result<(), String> {
+    if bytes.len() < PNG_SIGNATURE.len() || bytes[..PNG_SIGNATURE.len()] != PNG_SIGNATURE {
+        return Err("Missing PNG signature".to_string());
+    }
+
+    let mut offset = PNG_SIGNATURE.len();
+    while offset < bytes.len() {
+        if offset + 8 > bytes.len() {
+            return Err(format!("Truncated chunk header at byte {}", offset));
+        }
+        let length = u32::from_be_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ]) as usize;
+        let chunk_type = &bytes[offset + 4..offset + 8];
+        let chunk_end = offset + 8 + length + 4;
+        if chunk_end > bytes.len() {
+            return Err(format!(
+                "Truncated {} chunk: needs {} bytes, {} available",
+                String::from_utf8_lossy(chunk_type),
+                length + 12,
+                bytes.len() - offset
+            ));
+        }
+
+        let stored_crc = u32::from_be_bytes([
+            bytes[chunk_end - 4],
+            bytes[chunk_end - 3],
+            bytes[chunk_end - 2],
+            bytes[chunk_end - 1],
+        ]);
+        if crc32(&bytes[offset + 4..chunk_end - 4]) != stored_crc {
+            return Err(format!(
+                "CRC mismatch in {} chunk at byte {}",
+                String::from_utf8_lossy(chunk_type),
+                offset
+            ));
+        }
+
+        if chunk_type == b"IEND" {
+            return Ok(());
+        }
+        offset = chunk_end;
+    }
+
+    Err("Missing IEND chunk".to_string())
+}
+
+/// Decodes standard base64, ignoring whitespace
+fn decode_base64(encoded: &str) -> Result<Vec<u8>, String> {
+    fn value_of(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a') as u32 + 26),
+            b'0'..=b'9' => Some((c - b'0') as u32 + 52),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+
+    let symbols: Vec<u8> = encoded.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+    let data = match symbols.iter().position(|&c| c == b'=') {
+        Some(padding_start) => {
+            if symbols[padding_start..].iter().any(|&c| c != b'=') {
+                return Err("Invalid base64: data after padding".to_string());
+            }
+            &symbols[..padding_start]
+        }
+        None => &symbols[..],
+    };
+    if data.len() % 4 == 1 {
+        return Err("Invalid base64: truncated input".to_string());
+    }
+
+    let mut output = Vec::with_capacity(data.len() * 3 / 4);
+    for group in data.chunks(4) {
+        let mut buffer = 0u32;
+        for (i, &c) in group.iter().enumerate() {
+            let value = value_of(c).ok_or_else(|| format!("Invalid base64 character '{}'", c as char))?;
+            buffer |= value << (18 - 6 * i);
+        }
+        let bytes = buffer.to_be_bytes();
+        output.extend_from_slice(&bytes[1..group.len()]);
+    }
+    Ok(output)
+}
+
+/// Encodes bytes as standard padded base64, e.g. for a `data:` URI
+pub fn encode_base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let buffer = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |buffer, (i, &byte)| buffer | ((byte as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= group.len() {
+                output.push(ALPHABET[((buffer >> (18 - 6 * i)) & 0x3F) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+/// CRC-32 (ISO-HDLC) as used by PNG chunks
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1x1 PNG
+    const VALID_PNG_BASE64: &str =
+        "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+
+    #[test]
+    fn test_valid_ed_png_is_accepted() {
+        let value = format!("^Image^PNG^Base64^{}", VALID_PNG_BASE64);
+        let png = decode_ed_png(&value).unwrap();
+        assert_eq!(png.len(), 70);
+        assert_eq!(&png[..8], &PNG_SIGNATURE);
+    }
+
+    #[test]
+    fn test_multi_part_ed_is_joined_before_decoding() {
+        // Split inside a base64 quantum, so neither part decodes on its own
+        let (first, second) = VALID_PNG_BASE64.split_at(30);
+        let first = format!("^Image^PNG^Base64^{}", first);
+        assert!(decode_ed_png(&first).is_err());
+
+        let joined = join_ed_parts(&[&first, second]);
+        assert_eq!(joined, format!("^Image^PNG^Base64^{}", VALID_PNG_BASE64));
+        assert_eq!(decode_ed_png(&joined).unwrap().len(), 70);
+
+        let mut sub_ids = vec!["10", "2", "1.1", "1"];
+        sub_ids.sort_by_key(|sub_id| ed_part_order(sub_id));
+        assert_eq!(sub_ids, vec!["1", "1.1", "2", "10"]);
+    }
+
+    #[test]
+    fn test_base64_round_trips() {
+        let png = decode_base64(VALID_PNG_BASE64).unwrap();
+        assert_eq!(encode_base64(&png), VALID_PNG_BASE64);
+        assert_eq!(encode_base64(&[0x89, b'P', b'N', b'G']), "iVBORw==");
+        assert_eq!(encode_base64(b"ab"), "YWI=");
+        assert_eq!(encode_base64(b""), "");
+    }
+
+    #[test]
+    fn test_truncated_png_is_rejected() {
+        let png = decode_base64(VALID_PNG_BASE64).unwrap();
+
+        // Cut inside the IDAT chunk, as a short MLLP read would
+        let truncated = &png[..45];
+        let error = validate_png(truncated).unwrap_err();
+        assert!(error.contains("Truncated IDAT chunk"), "{}", error);
+
+        // Cut exactly before IEND
+        let error = validate_png(&png[..png.len() - 12]).unwrap_err();
+        assert_eq!(error, "Missing IEND chunk");
+    }
+
+    #[test]
+    fn test_corrupt_png_bytes_are_rejected() {
+        let mut png = decode_base64(VALID_PNG_BASE64).unwrap();
+        png[20] ^= 0xFF; // Inside IHDR data
+        assert!(validate_png(&png).unwrap_err().contains("CRC mismatch in IHDR"));
+
+        assert_eq!(validate_png(b"not a png").unwrap_err(), "Missing PNG signature");
+    }
+}
@@ -1,7 +1,9 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use super::message_profile::MessageProfile;
+
 // ============================================================================
 // MLLP PROTOCOL CONSTANTS
 // ============================================================================
@@ -213,6 +215,17 @@ pub struct HL7Message {
     pub timestamp: DateTime<Utc>,
 }
 
+impl HL7Message {
+    /// MSH-7, when the sender dated the message; the sender's clock
+    pub fn message_time(&self) -> Option<DateTime<Utc>> {
+        self.segments
+            .iter()
+            .find(|segment| segment.segment_type == "MSH")
+            .and_then(|segment| parse_msh_segment(segment).ok())
+            .and_then(|msh| parse_hl7_datetime(&msh.date_time_of_message))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HL7Segment {
     pub segment_type: String,
@@ -255,6 +268,24 @@ pub struct PIDSegment {
     pub primary_language: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PV1Segment {
+    pub set_id: String,
+    pub patient_class: String,
+    pub assigned_patient_location: String,
+    pub admission_type: String,
+    pub attending_doctor: String,
+    pub hospital_service: String,
+}
+
+impl PV1Segment {
+    /// PV1-2 code (e.g. I inpatient, O outpatient, E emergency), when the analyzer sent one
+    pub fn patient_class(&self) -> Option<String> {
+        let code = self.patient_class.split('^').next().unwrap_or_default().trim();
+        (!code.is_empty()).then(|| code.to_string())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OBRSegment {
     pub set_id: String,
@@ -275,6 +306,18 @@ pub struct OBRSegment {
     pub ordering_provider: String,
 }
 
+impl OBRSegment {
+    /// Sample the order group's results belong to: OBR-3 (sample number), or
+    /// OBR-2 (bar code) when the analyzer sent no sample number
+    pub fn sample_id(&self) -> Option<String> {
+        [&self.filler_order_number, &self.placer_order_number]
+            .into_iter()
+            .map(|field| field.trim())
+            .find(|field| !field.is_empty())
+            .map(str::to_string)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OBXSegment {
     pub set_id: String,
@@ -293,6 +336,27 @@ pub struct OBXSegment {
     pub date_time_of_observation: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NTESegment {
+    pub set_id: String,
+    pub source_of_comment: String,
+    pub comment: String,
+}
+
+impl NTESegment {
+    /// NTE-3 with its repetitions joined by spaces, when it has any text
+    pub fn comment_text(&self) -> Option<String> {
+        let text = self
+            .comment
+            .split('~')
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        (!text.is_empty()).then_some(text)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MSASegment {
     pub acknowledgment_code: String,
@@ -457,10 +521,16 @@ pub fn parse_hl7_segment(segment_line: &str) -> Result<HL7Segment, String> {
     let segment_type = &segment_line[0..3];
     
     // Split by field separator (|)
-    let fields: Vec<String> = segment_line
+    let mut fields: Vec<String> = segment_line
         .split(HL7_FIELD_SEPARATOR)
         .map(|s| s.to_string())
         .collect();
+
+    // MSH-1 is the field separator itself, so it is not a piece of the split. It is
+    // put back so that fields[n] is MSH-n, as fields[n] is PID-n for other segments.
+    if segment_type == "MSH" && fields.len() > 1 {
+        fields.insert(1, HL7_FIELD_SEPARATOR.to_string());
+    }
     
     Ok(HL7Segment {
         segment_type: segment_type.to_string(),
@@ -475,23 +545,24 @@ pub fn parse_msh_segment(segment: &HL7Segment) -> Result<MSHSegment, String> {
         return Err("Not an MSH segment".to_string());
     }
     
-    if segment.fields.len() < 12 {
+    // fields[n] is MSH-n; MSH-12 is the last required field
+    if segment.fields.len() < 13 {
         return Err("MSH segment has insufficient fields".to_string());
     }
     
     Ok(MSHSegment {
-        field_separator: segment.fields.get(1).unwrap_or(&String::new()).clone(),
-        encoding_characters: segment.fields.get(1).unwrap_or(&String::new()).clone(), // MSH.2 is actually field separator + encoding chars
-        sending_application: segment.fields.get(2).unwrap_or(&String::new()).clone(), // MSH.3
-        sending_facility: segment.fields.get(3).unwrap_or(&String::new()).clone(),     // MSH.4
-        receiving_application: segment.fields.get(4).unwrap_or(&String::new()).clone(), // MSH.5
-        receiving_facility: segment.fields.get(5).unwrap_or(&String::new()).clone(),    // MSH.6
-        date_time_of_message: segment.fields.get(6).unwrap_or(&String::new()).clone(),  // MSH.7
-        security: segment.fields.get(7).unwrap_or(&String::new()).clone(),              // MSH.8
-        message_type: segment.fields.get(8).unwrap_or(&String::new()).clone(),          // MSH.9
-        message_control_id: segment.fields.get(9).unwrap_or(&String::new()).clone(),    // MSH.10
-        processing_id: segment.fields.get(10).unwrap_or(&String::new()).clone(),        // MSH.11
-        version_id: segment.fields.get(11).unwrap_or(&String::new()).clone(),           // MSH.12
+        field_separator: segment.fields.get(1).unwrap_or(&String::new()).clone(),       // MSH.1
+        encoding_characters: segment.fields.get(2).unwrap_or(&String::new()).clone(),   // MSH.2
+        sending_application: segment.fields.get(3).unwrap_or(&String::new()).clone(),   // MSH.3
+        sending_facility: segment.fields.get(4).unwrap_or(&String::new()).clone(),      // MSH.4
+        receiving_application: segment.fields.get(5).unwrap_or(&String::new()).clone(), // MSH.5
+        receiving_facility: segment.fields.get(6).unwrap_or(&String::new()).clone(),    // MSH.6
+        date_time_of_message: segment.fields.get(7).unwrap_or(&String::new()).clone(),  // MSH.7
+        security: segment.fields.get(8).unwrap_or(&String::new()).clone(),              // MSH.8
+        message_type: segment.fields.get(9).unwrap_or(&String::new()).clone(),          // MSH.9
+        message_control_id: segment.fields.get(10).unwrap_or(&String::new()).clone(),   // MSH.10
+        processing_id: segment.fields.get(11).unwrap_or(&String::new()).clone(),        // MSH.11
+        version_id: segment.fields.get(12).unwrap_or(&String::new()).clone(),           // MSH.12
     })
 }
 
@@ -520,6 +591,22 @@ pub fn parse_pid_segment(segment: &HL7Segment) -> Result<PIDSegment, String> {
     })
 }
 
+/// Parses PV1 (Patient Visit) segment
+pub fn parse_pv1_segment(segment: &HL7Segment) -> Result<PV1Segment, String> {
+    if segment.segment_type != "PV1" {
+        return Err("Not a PV1 segment".to_string());
+    }
+
+    Ok(PV1Segment {
+        set_id: segment.fields.get(1).unwrap_or(&String::new()).clone(),
+        patient_class: segment.fields.get(2).unwrap_or(&String::new()).clone(),
+        assigned_patient_location: segment.fields.get(3).unwrap_or(&String::new()).clone(),
+        admission_type: segment.fields.get(4).unwrap_or(&String::new()).clone(),
+        attending_doctor: segment.fields.get(7).unwrap_or(&String::new()).clone(),
+        hospital_service: segment.fields.get(10).unwrap_or(&String::new()).clone(),
+    })
+}
+
 /// Parses OBR (Observation Request) segment
 pub fn parse_obr_segment(segment: &HL7Segment) -> Result<OBRSegment, String> {
     if segment.segment_type != "OBR" {
@@ -570,6 +657,19 @@ pub fn parse_obx_segment(segment: &HL7Segment) -> Result<OBXSegment, String> {
     })
 }
 
+/// Parses NTE (Notes and Comments) segment
+pub fn parse_nte_segment(segment: &HL7Segment) -> Result<NTESegment, String> {
+    if segment.segment_type != "NTE" {
+        return Err("Not an NTE segment".to_string());
+    }
+
+    Ok(NTESegment {
+        set_id: segment.fields.get(1).unwrap_or(&String::new()).clone(),
+        source_of_comment: segment.fields.get(2).unwrap_or(&String::new()).clone(),
+        comment: segment.fields.get(3).unwrap_or(&String::new()).clone(),
+    })
+}
+
 /// Parses MSA (Message Acknowledgment) segment
 pub fn parse_msa_segment(segment: &HL7Segment) -> Result<MSASegment, String> {
     if segment.segment_type != "MSA" {
@@ -608,27 +708,198 @@ pub fn parse_orc_segment(segment: &HL7Segment) -> Result<ORCSegment, String> {
     })
 }
 
+// ============================================================================
+// ACKNOWLEDGMENT ERRORS
+// ============================================================================
+
+/// Describes an MSA-6 error condition (HL7 table 0357), accepting a bare code or a coded element
+pub fn describe_error_condition(error_condition: &str) -> Option<&'static str> {
+    let code = error_condition.split('^').next().unwrap_or_default().trim();
+    let description = match code {
+        "0" => "Message accepted",
+        "100" => "Segment sequence error: a segment is missing, out of order or repeated",
+        "101" => "Required field missing",
+        "102" => "Data type error: a field does not match its data type",
+        "103" => "Table value not found: a coded field holds an unknown value",
+        "200" => "Unsupported message type",
+        "201" => "Unsupported event code",
+        "202" => "Unsupported processing ID",
+        "203" => "Unsupported version ID",
+        "204" => "Unknown key identifier: the patient or order is not known to the receiver",
+        "205" => "Duplicate key identifier: the record already exists",
+        "206" => "Application record locked",
+        "207" => "Application internal error",
+        _ => return None,
+    };
+    Some(description)
+}
+
+impl MSASegment {
+    /// AE/AR (original mode) or CE/CR (enhanced mode)
+    pub fn is_negative(&self) -> bool {
+        matches!(self.acknowledgment_code.trim(), "AE" | "AR" | "CE" | "CR")
+    }
+
+    /// Acknowledgment code, mapped error condition and the sender's own text, for logs and upload records
+    pub fn error_summary(&self) -> String {
+        let mut summary = self.acknowledgment_code.trim().to_string();
+        let code = self.error_condition.split('^').next().unwrap_or_default().trim();
+        match describe_error_condition(code) {
+            Some(description) => summary.push_str(&format!(" {}: {}", code, description)),
+            None if !code.is_empty() => summary.push_str(&format!(" {}: Unknown error condition", code)),
+            None => {}
+        }
+        if !self.text_message.trim().is_empty() {
+            summary.push_str(&format!(" ({})", self.text_message.trim()));
+        }
+        summary
+    }
+}
+
+/// Finds a negative MSA in a raw ACK (segments split by CR or LF), e.g. a HIS response body
+pub fn find_negative_acknowledgment(message: &str) -> Option<MSASegment> {
+    let line = message.split(['\r', '\n']).find(|line| line.starts_with("MSA"))?;
+    let msa = parse_hl7_segment(line).and_then(|segment| parse_msa_segment(&segment)).ok()?;
+    msa.is_negative().then_some(msa)
+}
+
+// ============================================================================
+// ACKNOWLEDGMENT MODES
+// ============================================================================
+
+/// When the sender wants an acknowledgment (MSH-15 accept / MSH-16 application)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AckCondition {
+    Always,      // AL (also assumed when the field is empty)
+    Never,       // NE
+    ErrorOnly,   // ER
+    SuccessOnly, // SU
+}
+
+impl AckCondition {
+    fn from_field(value: &str) -> Self {
+        match value.trim().to_uppercase().as_str() {
+            "NE" => AckCondition::Never,
+            "ER" => AckCondition::ErrorOnly,
+            "SU" => AckCondition::SuccessOnly,
+            _ => AckCondition::Always,
+        }
+    }
+
+    fn on_success(&self) -> bool {
+        matches!(self, AckCondition::Always | AckCondition::SuccessOnly)
+    }
+
+    fn on_error(&self) -> bool {
+        matches!(self, AckCondition::Always | AckCondition::ErrorOnly)
+    }
+}
+
+/// Acknowledgment mode requested by the sender
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AcknowledgmentMode {
+    /// MSH-15/16 empty: a single application ACK (AA/AE/AR)
+    Original,
+    /// MSH-15 and/or MSH-16 set: a commit ACK (CA/CE/CR), then an application ACK if requested
+    Enhanced {
+        accept: AckCondition,
+        application: AckCondition,
+    },
+}
+
+impl AcknowledgmentMode {
+    /// Reads MSH-15/16 from a raw message, so it also works for messages that failed parsing
+    pub fn from_raw(message: &str) -> Self {
+        let msh = message
+            .split(['\r', '\n'])
+            .find(|line| line.starts_with("MSH"))
+            .unwrap_or("");
+        // MSH-1 is the field separator itself, so MSH-n is the (n-1)th piece
+        let fields: Vec<&str> = msh.split(HL7_FIELD_SEPARATOR).collect();
+        let accept = fields.get(14).map(|f| f.trim()).unwrap_or("");
+        let application = fields.get(15).map(|f| f.trim()).unwrap_or("");
+
+        if accept.is_empty() && application.is_empty() {
+            AcknowledgmentMode::Original
+        } else {
+            AcknowledgmentMode::Enhanced {
+                accept: AckCondition::from_field(accept),
+                application: AckCondition::from_field(application),
+            }
+        }
+    }
+
+    /// ACK code for a message that was received and accepted, or `None` when the sender
+    /// asked not to be acknowledged
+    pub fn accept_code(&self) -> Option<&'static str> {
+        match self {
+            AcknowledgmentMode::Original => Some("AA"),
+            AcknowledgmentMode::Enhanced { accept, .. } => accept.on_success().then_some("CA"),
+        }
+    }
+
+    /// ACK code for a message that could not be accepted, or `None` when not requested
+    pub fn error_code(&self) -> Option<&'static str> {
+        match self {
+            AcknowledgmentMode::Original => Some("AE"),
+            AcknowledgmentMode::Enhanced { accept, .. } => accept.on_error().then_some("CE"),
+        }
+    }
+
+    /// Application ACK sent after processing in enhanced mode; in original mode
+    /// the accept ACK already is the application ACK
+    pub fn application_accept_code(&self) -> Option<&'static str> {
+        match self {
+            AcknowledgmentMode::Original => None,
+            AcknowledgmentMode::Enhanced { application, .. } => application.on_success().then_some("AA"),
+        }
+    }
+}
+
 /// Creates HL7 ACK (Acknowledgment) message for CQ 5 Plus (HL7 v2.3.1)
 pub fn create_hl7_acknowledgment(
     original_message: &HL7Message,
     ack_code: &str,
     text_message: Option<&str>,
 ) -> String {
-    let timestamp = Utc::now().format("%Y%m%d%H%M%S").to_string();
-    let control_id = format!("ACK{}", timestamp);
-    
-    // MSH segment for ACK (HL7 v2.3.1)
-    let msh = format!(
-        "MSH|^~\\&|LIS|HOSPITAL|{}|{}|{}||ACK^{}^ACK|{}|P|2.3.1||||||UTF-8",
+    create_hl7_acknowledgment_with_profile(original_message, ack_code, text_message, &MessageProfile::default())
+}
+
+/// Creates HL7 ACK message shaped by a destination's message profile
+pub fn create_hl7_acknowledgment_with_profile(
+    original_message: &HL7Message,
+    ack_code: &str,
+    text_message: Option<&str>,
+    profile: &MessageProfile,
+) -> String {
+    let now = Utc::now();
+    let control_id = format!("ACK{}", now.format("%Y%m%d%H%M%S"));
+
+    // Reply to the original sender unless the profile names the receiver
+    let receiving_application = profile.receiving_application.clone().unwrap_or_else(|| {
         original_message.segments.first()
             .and_then(|s| s.fields.get(3))
-            .unwrap_or(&"SENDER".to_string()),
+            .cloned()
+            .unwrap_or_else(|| "SENDER".to_string())
+    });
+    let receiving_facility = profile.receiving_facility.clone().unwrap_or_else(|| {
         original_message.segments.first()
             .and_then(|s| s.fields.get(4))
-            .unwrap_or(&"FACILITY".to_string()),
-        timestamp,
+            .cloned()
+            .unwrap_or_else(|| "FACILITY".to_string())
+    });
+
+    // MSH segment for ACK
+    let msh = format!(
+        "MSH|^~\\&|{}|{}|{}|{}|{}||ACK^{}^ACK|{}|P|{}||||||UTF-8",
+        profile.sending_application,
+        profile.sending_facility,
+        receiving_application,
+        receiving_facility,
+        profile.format_timestamp(&now),
         original_message.message_type.split('^').next().unwrap_or("R01"),
-        control_id
+        control_id,
+        profile.version
     );
     
     // MSA segment for acknowledgment
@@ -706,6 +977,59 @@ pub fn extract_parameter_code(observation_identifier: &str) -> String {
     }
 }
 
+/// Parses an HL7 TS/DTM value: `YYYYMMDD`, `YYYYMMDDHH`, `YYYYMMDDHHMM` or `YYYYMMDDHHMMSS`,
+/// optionally followed by up to four fractional second digits (`.SSSS`) and a `+/-ZZZZ` offset.
+/// Values without an offset are the analyzer's clock, read as UTC.
+pub fn parse_hl7_datetime(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    let (value, offset) = match value.get(8..).and_then(|rest| rest.find(['+', '-'])) {
+        Some(index) => {
+            let (value, zone) = value.split_at(index + 8);
+            let digits = &zone[1..];
+            if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let seconds = digits[..2].parse::<i32>().ok()? * 3600 + digits[2..].parse::<i32>().ok()? * 60;
+            let offset = if zone.starts_with('-') {
+                FixedOffset::west_opt(seconds)?
+            } else {
+                FixedOffset::east_opt(seconds)?
+            };
+            (value, offset)
+        }
+        None => (value, FixedOffset::east_opt(0)?),
+    };
+    let (digits, fraction) = match value.split_once('.') {
+        Some((digits, fraction)) => (digits, Some(fraction)),
+        None => (value, None),
+    };
+    if !matches!(digits.len(), 8 | 10 | 12 | 14) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let number = |range: std::ops::Range<usize>| digits.get(range).map_or(Some(0), |part| part.parse::<u32>().ok());
+    let date = NaiveDate::from_ymd_opt(digits[..4].parse().ok()?, number(4..6)?, number(6..8)?)?;
+    let nanos = match fraction {
+        // Fractions only follow whole seconds
+        Some(fraction) => {
+            if digits.len() != 14
+                || fraction.is_empty()
+                || fraction.len() > 4
+                || !fraction.bytes().all(|b| b.is_ascii_digit())
+            {
+                return None;
+            }
+            format!("{:0<9}", fraction).parse::<u32>().ok()?
+        }
+        None => 0,
+    };
+    let naive = date.and_hms_nano_opt(number(8..10)?, number(10..12)?, number(12..14)?, nanos)?;
+    offset
+        .from_local_datetime(&naive)
+        .single()
+        .map(|local| local.with_timezone(&Utc))
+}
+
 /// Checks if parameter is a CRP-related test (new in CQ 5 Plus)
 pub fn is_crp_parameter(parameter_code: &str) -> bool {
     matches!(parameter_code, "2031" | "2032")
@@ -716,6 +1040,126 @@ pub fn is_histogram_parameter(parameter_code: &str) -> bool {
     matches!(parameter_code, "2101" | "2102" | "2033" | "2034")
 }
 
+/// Checks if parameter is run information (mode, reference group, remarks, QC level)
+/// rather than a measured result
+pub fn is_metadata_parameter(parameter_code: &str) -> bool {
+    matches!(parameter_code, "2001" | "2002" | "2003" | "2004" | "2005")
+}
+
+// ============================================================================
+// PARAMETER CATALOG
+// ============================================================================
+
+/// How a parameter's observation value should be displayed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ParameterKind {
+    /// Run information (mode, reference group, remarks, QC level)
+    Metadata,
+    Numeric,
+    /// C-reactive protein, measured by the CRP module
+    Crp,
+    /// Histogram or scattergram PNG
+    Image,
+}
+
+/// A CQ 5 Plus parameter as shown in the UI
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ParameterInfo {
+    pub code: String,
+    /// Analyzer name, e.g. "V_WBC"
+    pub name: String,
+    pub display_name: String,
+    pub kind: ParameterKind,
+    pub units: Option<String>,
+}
+
+/// Lists every known CQ 5 Plus parameter with its classification, ordered by code
+pub fn get_parameter_catalog() -> Vec<ParameterInfo> {
+    let mut catalog: Vec<ParameterInfo> = get_cq5_parameter_codes()
+        .into_iter()
+        .map(|(code, name)| {
+            let kind = if is_histogram_parameter(&code) {
+                ParameterKind::Image
+            } else if is_crp_parameter(&code) {
+                ParameterKind::Crp
+            } else if is_metadata_parameter(&code) {
+                ParameterKind::Metadata
+            } else {
+                ParameterKind::Numeric
+            };
+
+            ParameterInfo {
+                display_name: parameter_display_name(&code).map(str::to_string).unwrap_or_else(|| name.clone()),
+                units: parameter_units(&code).map(|units| units.to_string()),
+                code,
+                name,
+                kind,
+            }
+        })
+        .collect();
+    catalog.sort_by(|a, b| a.code.cmp(&b.code));
+    catalog
+}
+
+/// Human-readable name of a parameter code
+pub fn parameter_display_name(parameter_code: &str) -> Option<&'static str> {
+    let name = match parameter_code {
+        "2001" => "Analysis mode",
+        "2002" => "Measurement mode",
+        "2003" => "Reference group",
+        "2004" => "Remarks",
+        "2005" => "QC level",
+        "2006" => "White blood cells",
+        "2007" => "Neutrophils %",
+        "2008" => "Lymphocytes %",
+        "2009" => "Monocytes %",
+        "2010" => "Eosinophils %",
+        "2011" => "Basophils %",
+        "2012" => "Neutrophils #",
+        "2013" => "Lymphocytes #",
+        "2014" => "Monocytes #",
+        "2015" => "Eosinophils #",
+        "2016" => "Basophils #",
+        "2017" => "Red blood cells",
+        "2018" => "Hemoglobin",
+        "2019" => "Mean corpuscular volume",
+        "2020" => "Hematocrit",
+        "2021" => "Mean corpuscular hemoglobin",
+        "2022" => "Mean corpuscular hemoglobin concentration",
+        "2023" => "RDW-SD",
+        "2024" => "RDW-CV",
+        "2025" => "Platelets",
+        "2026" => "Mean platelet volume",
+        "2027" => "Plateletcrit",
+        "2028" => "Platelet distribution width",
+        "2029" => "Platelet large cell ratio",
+        "2030" => "Platelet large cell count",
+        "2031" => "C-reactive protein",
+        "2032" => "High-sensitivity C-reactive protein",
+        "2033" => "BASO scattergram",
+        "2034" => "DIFF scattergram",
+        "2101" => "RBC histogram",
+        "2102" => "PLT histogram",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Units the CQ 5 Plus reports a numeric parameter in
+fn parameter_units(parameter_code: &str) -> Option<&'static str> {
+    let units = match parameter_code {
+        "2006" | "2012" | "2013" | "2014" | "2015" | "2016" | "2025" | "2030" => "10^9/L",
+        "2007" | "2008" | "2009" | "2010" | "2011" | "2020" | "2024" | "2027" | "2029" => "%",
+        "2017" => "10^12/L",
+        "2018" | "2022" => "g/L",
+        "2019" | "2023" | "2026" | "2028" => "fL",
+        "2021" => "pg",
+        "2031" | "2032" => "mg/L",
+        _ => return None,
+    };
+    Some(units)
+}
+
 /// Extracts flags from abnormal flags field
 pub fn extract_abnormal_flags(abnormal_flags: &str) -> Vec<String> {
     if abnormal_flags.is_empty() {
@@ -769,8 +1213,11 @@ mod tests {
         let segment = parse_hl7_segment(segment_line).unwrap();
         
         assert_eq!(segment.segment_type, "MSH");
-        assert_eq!(segment.fields.len(), 12);
+        assert_eq!(segment.fields.len(), 19);
         assert_eq!(segment.fields[0], "MSH");
+        assert_eq!(segment.fields[1], "|");
+        assert_eq!(segment.fields[2], "^~\\&");
+        assert_eq!(segment.fields[18], "UTF-8");
     }
 
     #[test]
@@ -786,6 +1233,49 @@ mod tests {
         assert_eq!(msh.version_id, "2.3.1");
     }
 
+    /// MSH lines as sent by a CQ 5 Plus, with and without the trailing MSH-18 character set
+    const CQ5_PLUS_MSH: [&str; 2] = [
+        "MSH|^~\\&|CQ5Plus|Meril|LIS|NRAMH|20240101120000||ORU^R01|42|P|2.3.1||||||UNICODE",
+        "MSH|^~\\&|CQ5Plus|Meril|LIS|NRAMH|20240101120000||ORU^R01|42|P|2.3.1",
+    ];
+
+    #[test]
+    fn test_cq5_plus_msh_fields_follow_hl7_numbering() {
+        for line in CQ5_PLUS_MSH {
+            let msh = parse_msh_segment(&parse_hl7_segment(line).unwrap()).unwrap();
+
+            assert_eq!(msh.field_separator, "|");
+            assert_eq!(msh.encoding_characters, "^~\\&");
+            assert_eq!(msh.sending_application, "CQ5Plus");
+            assert_eq!(msh.sending_facility, "Meril");
+            assert_eq!(msh.receiving_application, "LIS");
+            assert_eq!(msh.receiving_facility, "NRAMH");
+            assert_eq!(msh.date_time_of_message, "20240101120000");
+            assert_eq!(msh.security, "");
+            assert_eq!(msh.message_type, "ORU^R01");
+            assert_eq!(msh.message_control_id, "42");
+            assert_eq!(msh.processing_id, "P");
+            assert_eq!(msh.version_id, "2.3.1");
+        }
+
+        // Without MSH-12 the header is incomplete
+        let short = parse_hl7_segment("MSH|^~\\&|CQ5Plus|Meril|LIS|NRAMH|20240101120000||ORU^R01|42|P").unwrap();
+        assert!(parse_msh_segment(&short).is_err());
+    }
+
+    #[test]
+    fn test_ack_replies_to_cq5_plus_sending_application() {
+        let message = parse_hl7_message(&format!("{}\rPID|1||P1", CQ5_PLUS_MSH[0])).unwrap();
+        assert_eq!(message.message_control_id, "42");
+        assert_eq!(message.version_id, "2.3.1");
+
+        let ack = create_hl7_acknowledgment_with_profile(&message, "AA", None, &MessageProfile::default());
+        let msh_fields: Vec<&str> = ack.split('\r').next().unwrap().split('|').collect();
+        assert_eq!(msh_fields[4], "CQ5Plus");
+        assert_eq!(msh_fields[5], "Meril");
+        assert!(ack.contains("MSA|AA|42|"));
+    }
+
     #[test]
     fn test_obx_segment_parsing() {
         let segment_line = "OBX|1|NM|2006^V_WBC^LOCAL|1|8.5|10^9/L|4.0-10.0|N|||F|||20240101120000";
@@ -855,6 +1345,77 @@ mod tests {
         assert!(ack.contains("UTF-8")); // Check character set
     }
 
+    #[test]
+    fn test_hl7_ack_uses_message_profile() {
+        let message = HL7Message {
+            message_type: "ORU^R01".to_string(),
+            message_control_id: "123456".to_string(),
+            processing_id: "P".to_string(),
+            version_id: "2.3.1".to_string(),
+            segments: vec![
+                HL7Segment {
+                    segment_type: "MSH".to_string(),
+                    fields: vec![
+                        "MSH".to_string(),
+                        "|".to_string(),
+                        "^~\\&".to_string(),
+                        "LAB".to_string(),
+                        "HOSPITAL".to_string(),
+                    ],
+                    raw_segment: "".to_string(),
+                }
+            ],
+            raw_message: "".to_string(),
+            timestamp: Utc::now(),
+        };
+        let profile = MessageProfile {
+            sending_application: "NRAMH-LIS".to_string(),
+            receiving_application: Some("CQ5".to_string()),
+            version: "2.5.1".to_string(),
+            ..MessageProfile::default()
+        };
+
+        let ack = create_hl7_acknowledgment_with_profile(&message, "AA", None, &profile);
+        let msh_fields: Vec<&str> = ack.split('\r').next().unwrap().split('|').collect();
+
+        assert_eq!(msh_fields[2], "NRAMH-LIS");
+        assert_eq!(msh_fields[3], "HOSPITAL");
+        assert_eq!(msh_fields[4], "CQ5");
+        assert_eq!(msh_fields[11], "2.5.1");
+        assert!(ack.contains("MSA|AA|123456|"));
+    }
+
+    #[test]
+    fn test_enhanced_mode_request_uses_commit_ack() {
+        let raw = "MSH|^~\\&|BF-6900|20180613001|LIS|RECEIVER|20240101120000||ORU^R01|123456|P|2.3.1|||AL|NE||UTF-8\rPID|1||P123456\r";
+        let mode = AcknowledgmentMode::from_raw(raw);
+        assert_eq!(
+            mode,
+            AcknowledgmentMode::Enhanced {
+                accept: AckCondition::Always,
+                application: AckCondition::Never,
+            }
+        );
+        assert_eq!(mode.accept_code(), Some("CA"));
+        assert_eq!(mode.error_code(), Some("CE"));
+        assert_eq!(mode.application_accept_code(), None);
+
+        let message = parse_hl7_message(raw).unwrap();
+        let ack = create_hl7_acknowledgment(&message, mode.accept_code().unwrap(), Some("Message accepted"));
+        assert!(ack.contains("MSA|CA|123456|Message accepted"));
+
+        // No MSH-15/16: original mode, as before
+        let original = AcknowledgmentMode::from_raw("MSH|^~\\&|BF-6900|X|LIS|R|20240101120000||ORU^R01|1|P|2.3.1\r");
+        assert_eq!(original, AcknowledgmentMode::Original);
+        assert_eq!(original.accept_code(), Some("AA"));
+
+        // Commit ACK only on error
+        let errors_only = AcknowledgmentMode::from_raw("MSH|^~\\&|A|B|C|D|20240101120000||ORU^R01|1|P|2.5|||ER|AL\r");
+        assert_eq!(errors_only.accept_code(), None);
+        assert_eq!(errors_only.error_code(), Some("CE"));
+        assert_eq!(errors_only.application_accept_code(), Some("AA"));
+    }
+
     #[test]
     fn test_cq5_parameter_codes() {
         let codes = get_cq5_parameter_codes();
@@ -917,6 +1478,86 @@ mod tests {
         assert_eq!(msa.error_condition, "0");
     }
 
+    #[test]
+    fn test_msa_error_condition_is_mapped_to_description() {
+        assert_eq!(describe_error_condition("207"), Some("Application internal error"));
+        assert_eq!(describe_error_condition("204^Unknown key identifier^HL70357"), describe_error_condition("204"));
+        assert_eq!(describe_error_condition("999"), None);
+
+        let ack = "MSH|^~\\&|HIS|HOSPITAL|LIS|LAB|20240101120000||ACK^R01|900|P|2.3.1\r\
+MSA|AE|42|Patient not registered|||204";
+        let msa = find_negative_acknowledgment(ack).unwrap();
+        assert_eq!(
+            msa.error_summary(),
+            "AE 204: Unknown key identifier: the patient or order is not known to the receiver (Patient not registered)"
+        );
+
+        // Positive ACKs are not reported
+        assert!(find_negative_acknowledgment("MSA|AA|42|||0").is_none());
+    }
+
+    #[test]
+    fn test_hl7_datetime_precisions() {
+        let at = |y, mo, d, h, mi, s| Utc.with_ymd_and_hms(y, mo, d, h, mi, s).unwrap();
+        assert_eq!(parse_hl7_datetime("20240315"), Some(at(2024, 3, 15, 0, 0, 0)));
+        assert_eq!(parse_hl7_datetime("2024031509"), Some(at(2024, 3, 15, 9, 0, 0)));
+        assert_eq!(parse_hl7_datetime("202403150945"), Some(at(2024, 3, 15, 9, 45, 0)));
+        assert_eq!(parse_hl7_datetime("20240315094512"), Some(at(2024, 3, 15, 9, 45, 12)));
+        assert_eq!(
+            parse_hl7_datetime("20240315094512.25"),
+            Some(at(2024, 3, 15, 9, 45, 12) + chrono::Duration::milliseconds(250))
+        );
+        // Offsets are converted to UTC
+        assert_eq!(parse_hl7_datetime("20240315094512+0530"), Some(at(2024, 3, 15, 4, 15, 12)));
+        assert_eq!(parse_hl7_datetime("202403150945-0100"), Some(at(2024, 3, 15, 10, 45, 0)));
+        assert_eq!(parse_hl7_datetime("20240315094512.1234+0000"), parse_hl7_datetime(" 20240315094512.1234 "));
+    }
+
+    #[test]
+    fn test_malformed_hl7_datetime_is_rejected() {
+        for value in [
+            "",
+            "2024",
+            "2024031",
+            "20240315094",
+            "20241315",
+            "20240230",
+            "20240315256000",
+            "2024-03-15",
+            "20240315094512.",
+            "20240315094512.12345",
+            "202403150945.5",
+            "20240315094512+05",
+            "20240315094512+05:30",
+            "2024031509451X",
+        ] {
+            assert_eq!(parse_hl7_datetime(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_pv1_segment_parsing() {
+        let segment = parse_hl7_segment("PV1|1|I^Inpatient|WARD3^12^B|||||||MED").unwrap();
+        let pv1 = parse_pv1_segment(&segment).unwrap();
+        assert_eq!(pv1.patient_class(), Some("I".to_string()));
+        assert_eq!(pv1.assigned_patient_location, "WARD3^12^B");
+        assert_eq!(pv1.hospital_service, "MED");
+
+        let segment = parse_hl7_segment("PV1|1|").unwrap();
+        assert_eq!(parse_pv1_segment(&segment).unwrap().patient_class(), None);
+    }
+
+    #[test]
+    fn test_nte_segment_parsing() {
+        let segment = parse_hl7_segment("NTE|1|L|Platelet clumps~ suspected").unwrap();
+        let nte = parse_nte_segment(&segment).unwrap();
+        assert_eq!(nte.source_of_comment, "L");
+        assert_eq!(nte.comment_text(), Some("Platelet clumps suspected".to_string()));
+
+        let segment = parse_hl7_segment("NTE|1|L|").unwrap();
+        assert_eq!(parse_nte_segment(&segment).unwrap().comment_text(), None);
+    }
+
     #[test]
     fn test_orc_segment_parsing() {
         let segment_line = "ORC|RF||SampleID||IP";
@@ -977,4 +1618,30 @@ mod tests {
         assert!(message_content.contains("MSA|AA|1|Device identification acknowledged"));
         assert!(message_content.contains("2.3.1"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parameter_catalog_classifies_wbc_and_images() {
+        let catalog = get_parameter_catalog();
+        assert_eq!(catalog.len(), get_cq5_parameter_codes().len());
+        let find = |code: &str| catalog.iter().find(|p| p.code == code).unwrap();
+
+        let wbc = find("2006");
+        assert_eq!(wbc.name, "V_WBC");
+        assert_eq!(wbc.display_name, "White blood cells");
+        assert_eq!(wbc.kind, ParameterKind::Numeric);
+        assert_eq!(wbc.units.as_deref(), Some("10^9/L"));
+
+        for code in ["2101", "2102", "2033", "2034"] {
+            assert_eq!(find(code).kind, ParameterKind::Image);
+            assert_eq!(find(code).units, None);
+        }
+        assert_eq!(find("2031").kind, ParameterKind::Crp);
+        assert_eq!(find("2032").units.as_deref(), Some("mg/L"));
+        assert_eq!(find("2001").kind, ParameterKind::Metadata);
+        assert!(catalog.windows(2).all(|pair| pair[0].code < pair[1].code));
+
+        // Only the codes of §A.7 are known
+        assert!(catalog.iter().all(|p| p.code.as_str() <= "2034" || p.code == "2101" || p.code == "2102"));
+        assert!(is_metadata_parameter("2005") && !is_metadata_parameter("2006"));
+    }
+}
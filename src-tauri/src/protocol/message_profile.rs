@@ -0,0 +1,122 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// MESSAGE PROFILE
+// ============================================================================
+
+/// How OBX-6 units are coded
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum UnitsCoding {
+    Local, // "g/dL"
+    Iso,   // "g/dL^^ISO+"
+}
+
+/// How OBX-3 observation identifiers are coded
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ObservationCoding {
+    Local, // "ALB^ALB"
+    Loinc, // "1751-7^ALB^LN", falling back to local when no LOINC code is known
+}
+
+/// How OBR-3 filler order numbers are formatted
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum FillerOrderFormat {
+    SampleId,          // "S200"
+    SampleIdNamespace, // "S200^<sending application>"
+}
+
+/// Precision of HL7 TS fields
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum TimestampPrecision {
+    Minute, // YYYYMMDDHHMM
+    Second, // YYYYMMDDHHMMSS
+}
+
+/// Per-destination shape of outbound HL7 messages (ORU/ACK).
+/// The default reproduces the messages the LIS has always sent.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MessageProfile {
+    pub sending_application: String, // MSH-3
+    pub sending_facility: String,    // MSH-4
+    /// MSH-5; for ACKs `None` echoes the sender of the original message
+    pub receiving_application: Option<String>,
+    /// MSH-6; for ACKs `None` echoes the sender of the original message
+    pub receiving_facility: Option<String>,
+    pub version: String, // MSH-12
+    pub units_coding: UnitsCoding,
+    pub observation_coding: ObservationCoding,
+    pub filler_order_format: FillerOrderFormat,
+    pub include_nte: bool,
+    pub timestamp_precision: TimestampPrecision,
+    /// MSA-3 text of automatic accept ACKs
+    #[serde(default = "default_acknowledgment_text")]
+    pub acknowledgment_text: String,
+}
+
+fn default_acknowledgment_text() -> String {
+    "Message accepted".to_string()
+}
+
+impl Default for MessageProfile {
+    fn default() -> Self {
+        Self {
+            sending_application: "LIS".to_string(),
+            sending_facility: "HOSPITAL".to_string(),
+            receiving_application: None,
+            receiving_facility: None,
+            version: "2.3.1".to_string(),
+            units_coding: UnitsCoding::Local,
+            observation_coding: ObservationCoding::Local,
+            filler_order_format: FillerOrderFormat::SampleId,
+            include_nte: false,
+            timestamp_precision: TimestampPrecision::Second,
+            acknowledgment_text: default_acknowledgment_text(),
+        }
+    }
+}
+
+impl MessageProfile {
+    /// Formats a timestamp for an HL7 TS field
+    pub fn format_timestamp(&self, at: &DateTime<Utc>) -> String {
+        match self.timestamp_precision {
+            TimestampPrecision::Minute => at.format("%Y%m%d%H%M").to_string(),
+            TimestampPrecision::Second => at.format("%Y%m%d%H%M%S").to_string(),
+        }
+    }
+
+    /// Formats the OBX-6 units field
+    pub fn format_units(&self, units: &str) -> String {
+        if units.is_empty() {
+            return String::new();
+        }
+        match self.units_coding {
+            UnitsCoding::Local => units.to_string(),
+            UnitsCoding::Iso => format!("{}^^ISO+", units),
+        }
+    }
+
+    /// Formats the OBR-3 filler order number
+    pub fn format_filler_order(&self, sample_id: &str) -> String {
+        match self.filler_order_format {
+            FillerOrderFormat::SampleId => sample_id.to_string(),
+            FillerOrderFormat::SampleIdNamespace => {
+                format!("{}^{}", sample_id, self.sending_application)
+            }
+        }
+    }
+}
+
+/// Key in an analyzer's store (e.g. `bf6900.json`) holding its message profile override
+pub const MESSAGE_PROFILE_STORE_KEY: &str = "message_profile";
+
+/// Reads a stored profile, falling back to the default when missing or invalid
+pub fn message_profile_from_store(stored: Option<serde_json::Value>) -> MessageProfile {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid message profile in config: {}", e);
+            MessageProfile::default()
+        }),
+        None => MessageProfile::default(),
+    }
+}
@@ -1,3 +1,13 @@
+pub mod astm;
+pub mod contact;
+pub mod decoder;
+pub mod ed_image;
 pub mod hl7_parser;
+pub mod message_profile;
 
-pub use hl7_parser::*;
\ No newline at end of file
+pub use astm::*;
+pub use contact::*;
+pub use decoder::*;
+pub use ed_image::*;
+pub use hl7_parser::*;
+pub use message_profile::*;
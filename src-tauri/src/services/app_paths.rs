@@ -0,0 +1,198 @@
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, Runtime};
+
+// ============================================================================
+// APP DIRECTORIES
+// ============================================================================
+//
+// Every path the app writes to is built from the directories Tauri's path
+// resolver returns, joined as `PathBuf`s. Paths are never formatted into
+// strings and parsed back, which breaks on roaming profiles and user names
+// outside ASCII; `display()` is only used in messages.
+
+/// Directories the app cannot run without, resolved once at startup
+#[derive(Debug, Clone)]
+pub struct AppDirectories {
+    /// Holds the database, shared with the frontend SQL plugin
+    pub config: PathBuf,
+    /// Holds the settings stores and the result images
+    pub data: PathBuf,
+    /// Holds the log files
+    pub logs: PathBuf,
+}
+
+impl AppDirectories {
+    pub fn resolve<R: Runtime>(app: &AppHandle<R>) -> Result<Self, String> {
+        let path = app.path();
+        Ok(Self {
+            config: path
+                .app_config_dir()
+                .map_err(|e| format!("Error resolving app config dir: {}", e))?,
+            data: path
+                .app_data_dir()
+                .map_err(|e| format!("Error resolving app data dir: {}", e))?,
+            logs: path
+                .app_log_dir()
+                .map_err(|e| format!("Error resolving app log dir: {}", e))?,
+        })
+    }
+
+    /// Each directory with what it holds, for the startup check
+    pub fn required(&self) -> Vec<(&'static str, &Path)> {
+        vec![
+            ("database", self.config.as_path()),
+            ("settings stores", self.data.as_path()),
+            ("logs", self.logs.as_path()),
+        ]
+    }
+}
+
+/// Creates a directory and its missing parents
+pub fn ensure_dir(dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create directory {}: {}", dir.display(), e))
+}
+
+/// Creates the missing parent directories of a file about to be written
+pub fn ensure_parent_dir(path: &Path) -> Result<(), String> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => ensure_dir(parent),
+        _ => Ok(()),
+    }
+}
+
+/// The sibling of `path` whose file name has `suffix` appended, e.g. `meril.json`
+/// to `meril.json.corrupt-20250101120000`. The name is extended as an OS string,
+/// so names that are not valid Unicode are kept as they are.
+pub fn with_file_name_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name: OsString = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+// ============================================================================
+// STARTUP CHECK
+// ============================================================================
+
+/// File written and removed again by the startup check
+const PROBE_FILE_NAME: &str = ".nramh-lis-write-probe";
+
+/// Outcome of the startup check of one required directory
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DirectoryCheck {
+    pub purpose: String,
+    pub path: String,
+    /// Why the directory is unusable; `None` when the probe succeeded
+    pub error: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Results of the startup check, kept for the frontend and for the setup readiness checks
+pub struct DirectoryCheckState {
+    pub checks: Vec<DirectoryCheck>,
+}
+
+/// Creates `dir` when missing, then creates, writes, reads back and deletes a probe
+/// file in it. Every error names the full path that failed.
+pub fn probe_directory(dir: &Path) -> Result<(), String> {
+    ensure_dir(dir)?;
+
+    let probe = dir.join(PROBE_FILE_NAME);
+    let contents = Utc::now().to_rfc3339();
+    let written = std::fs::File::create(&probe)
+        .and_then(|mut file| file.write_all(contents.as_bytes()).and_then(|_| file.sync_all()))
+        .map_err(|e| format!("Failed to write {}: {}", probe.display(), e));
+    let read_back = written.and_then(|_| {
+        std::fs::read_to_string(&probe).map_err(|e| format!("Failed to read {}: {}", probe.display(), e))
+    });
+    // Removed even when writing failed half-way
+    let removed = match std::fs::remove_file(&probe) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("Failed to delete {}: {}", probe.display(), e))
+        }
+        _ => Ok(()),
+    };
+
+    if read_back? != contents {
+        return Err(format!("{} did not read back as written", probe.display()));
+    }
+    removed
+}
+
+/// Probes each directory, logging the ones that cannot be written
+pub fn check_directories(directories: &[(&str, &Path)]) -> Vec<DirectoryCheck> {
+    directories
+        .iter()
+        .map(|(purpose, dir)| {
+            let error = probe_directory(dir).err();
+            if let Some(error) = &error {
+                log::error!("Directory for {} is not writable: {}", purpose, error);
+            }
+            DirectoryCheck {
+                purpose: purpose.to_string(),
+                path: dir.display().to_string(),
+                error,
+                checked_at: Utc::now(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the temp dir shaped like a roaming profile with a
+    /// Cyrillic user name and spaces
+    fn profile_dir() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("nramh-lis-paths-{}", uuid::Uuid::new_v4()))
+            .join("Пользователь Иванов")
+            .join("AppData")
+            .join("Roaming")
+            .join("com.nramh lis")
+    }
+
+    #[test]
+    fn test_probe_creates_missing_non_ascii_directories() {
+        let dir = profile_dir();
+        assert!(!dir.exists());
+
+        probe_directory(&dir).unwrap();
+        assert!(dir.is_dir());
+        assert!(!dir.join(PROBE_FILE_NAME).exists());
+
+        let nested = dir.join("exports").join("отчёт 1.csv");
+        ensure_parent_dir(&nested).unwrap();
+        assert!(dir.join("exports").is_dir());
+
+        let copy = with_file_name_suffix(&dir.join("meril.json"), ".corrupt-20250101120000");
+        assert_eq!(copy, dir.join("meril.json.corrupt-20250101120000"));
+
+        let _ = std::fs::remove_dir_all(dir.ancestors().nth(4).unwrap());
+    }
+
+    #[test]
+    fn test_failed_probe_names_the_full_path() {
+        let root = profile_dir();
+        std::fs::create_dir_all(&root).unwrap();
+        // A file where a directory is expected can be neither created nor written into
+        let blocked = root.join("logs dir");
+        std::fs::write(&blocked, b"not a directory").unwrap();
+
+        let checks = check_directories(&[("stores", root.as_path()), ("logs", blocked.join("Журнал").as_path())]);
+        assert_eq!(checks[0].error, None);
+        assert_eq!(checks[0].path, root.display().to_string());
+
+        let error = checks[1].error.as_deref().unwrap();
+        assert!(error.starts_with("Failed to create directory"), "{}", error);
+        assert!(error.contains(&blocked.join("Журнал").display().to_string()), "{}", error);
+        assert_eq!(checks[1].purpose, "logs");
+
+        let _ = std::fs::remove_dir_all(root.ancestors().nth(4).unwrap());
+    }
+}
@@ -1,17 +1,45 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
-use tauri::Runtime;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{mpsc, Mutex, RwLock};
-use tokio::time::timeout;
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
+use tokio::time::{timeout, Instant};
 
-use crate::models::{Analyzer, AnalyzerStatus};
+use crate::models::patient::{PatientName, Sex};
+use crate::models::result::{
+    apply_dilution, parse_dilution_factor, DilutionMode, ReferenceRange, ResultFlags, TestResultMetadata,
+};
+use crate::db::SqliteRepository;
+use crate::models::{
+    Analyzer, AnalyzerIdentity, AnalyzerStatus, AstmTimeouts, ChecksumPolicy, ConnectionTermination, ContactInfo,
+    DispatchStatus, FirmwareChange, Patient, PatientAddress, ProcessingStage, ProcessingTimeline, ResultAcceptanceWindow, ResultStatus, SexCodeMap,
+    TestOrder, TestResult,
+};
+use crate::protocol::astm::{
+    checksum, encode_records, order_records, parse_checksum, patient_order_records, query_reply_records, AssemblyError, AstmDelimiters, MessageAssembler,
+};
+use crate::protocol::contact::{astm_address, astm_contacts};
+use crate::protocol::decoder::{decoder_for, ResultDecoder};
+use crate::protocol::hl7_parser::parse_hl7_datetime;
+use crate::services::config_persistence::{
+    reset_stale_status, ConfigPersistence, StatusPersister, STATUS_PERSIST_INTERVAL,
+};
+use crate::services::conformance::check_astm;
+use crate::services::developer_console::{describe_bytes, RawExchange};
+use crate::services::firmware_tracking::{self, conformance_review_detail, IdentityObservation};
+use crate::services::conversation_log::{
+    ConversationElement, ConversationLog, ConversationRecorder, Direction, EntryOutcome,
+};
+use crate::services::ingestion_lanes::next_arrival_sequence;
+use crate::services::listen_address::{bind_tcp_listener, ConnectionLimiter, ConnectionPermit};
+use crate::services::log_sampling::LogSampler;
+use crate::services::disk_monitor::{wait_until_stored, ProtectiveMode, StoreReceipt, STORE_CONFIRMATION_TIMEOUT};
+use crate::services::shadow_mode::ShadowMode;
 
 // ============================================================================
 // EVENT TYPES
@@ -28,6 +56,48 @@ pub enum MerilEvent {
     /// Analyzer disconnected
     AnalyzerDisconnected {
         analyzer_id: String,
+        termination: ConnectionTermination,
+        timestamp: DateTime<Utc>,
+    },
+    /// Header record received, with the sender and ASTM version it declares
+    HeaderReceived {
+        analyzer_id: String,
+        header: AstmHeader,
+        timestamp: DateTime<Utc>,
+    },
+    /// Host query (Q) record received; answered once the analyzer's transmission ends
+    HostQueryReceived {
+        analyzer_id: String,
+        query: HostQuery,
+        timestamp: DateTime<Utc>,
+    },
+    /// Pending orders sent in answer to a host query; empty when none were found
+    HostQueryAnswered {
+        analyzer_id: String,
+        query: HostQuery,
+        orders: Vec<TestOrder>,
+        timestamp: DateTime<Utc>,
+    },
+    /// A patient's orders downloaded to the analyzer on request
+    OrdersSent {
+        analyzer_id: String,
+        patient_id: String,
+        order_ids: Vec<String>,
+        timestamp: DateTime<Utc>,
+    },
+    /// A requested order download that did not reach the analyzer
+    OrdersSendFailed {
+        analyzer_id: String,
+        patient_id: String,
+        order_ids: Vec<String>,
+        error: String,
+        timestamp: DateTime<Utc>,
+    },
+    /// Connection closed on accept because its IP reached the per-IP limit
+    ConnectionRejected {
+        analyzer_id: String,
+        remote_addr: String,
+        reason: String,
         timestamp: DateTime<Utc>,
     },
     /// ASTM message received
@@ -37,12 +107,59 @@ pub enum MerilEvent {
         raw_data: String,
         timestamp: DateTime<Utc>,
     },
-    /// Lab result processed
+    /// Running counts after each frame of a transmission
+    TransmissionProgress {
+        analyzer_id: String,
+        progress: TransmissionProgress,
+        timestamp: DateTime<Utc>,
+    },
+    /// Result parsed mid-transmission; confirmed or superseded by the final `LabResultProcessed`
+    ProvisionalResult {
+        analyzer_id: String,
+        transmission_id: String,
+        result: TestResult,
+        timestamp: DateTime<Utc>,
+    },
+    /// Lab result processed; the authoritative result set of a transmission
     LabResultProcessed {
         analyzer_id: String,
+        remote_addr: String,
+        /// Connection the message arrived on; messages are ordered per connection and sample
+        #[serde(default)]
+        connection_id: String,
+        transmission_id: String,
         patient_id: Option<String>,
         patient_data: Option<PatientData>,
+        /// Patient class uploaded to the HIS; ASTM carries none, so the analyzer's default
+        #[serde(default)]
+        patient_class: Option<String>,
         test_results: Vec<TestResult>,
+        raw_data: String,
+        timeline: ProcessingTimeline,
+        /// Why the results wait for review before upload, e.g. records after the terminator
+        #[serde(default)]
+        review_detail: Option<String>,
+        /// Order the message was completed in; later messages for a sample supersede earlier ones
+        #[serde(default)]
+        arrival_sequence: u64,
+        /// Reported once the results are stored; the EOT ACK waits for it in protective mode
+        #[serde(skip)]
+        store_receipt: StoreReceipt,
+        timestamp: DateTime<Utc>,
+    },
+    /// Transmission parsed but deviated from the ASTM spec
+    ConformanceWarnings {
+        analyzer_id: String,
+        transmission_id: String,
+        warnings: Vec<crate::models::ConformanceWarning>,
+        timestamp: DateTime<Utc>,
+    },
+    /// Result dated outside the analyzer's acceptance window; it is not part of `LabResultProcessed`
+    RejectedStaleResult {
+        analyzer_id: String,
+        transmission_id: String,
+        result: TestResult,
+        reason: String,
         timestamp: DateTime<Utc>,
     },
     /// Analyzer status updated
@@ -59,23 +176,77 @@ pub enum MerilEvent {
     },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TestResult {
-    pub id: String,
-    pub test_id: String,
-    pub sample_id: String,
-    pub value: String,
-    pub units: Option<String>,
-    pub reference_range: Option<String>,
-    pub flags: Vec<String>,
-    pub status: String,
-    pub completed_date_time: Option<DateTime<Utc>>,
-    pub analyzer_id: Option<String>,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
+/// Running counts for the transmission in progress on a connection
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TransmissionProgress {
+    pub transmission_id: String,
+    pub frames_received: u32,
+    pub patients_seen: u32,
+    pub results_parsed: u32,
+    #[serde(default)]
+    pub retransmissions_requested: u32, // Frames NAKed so the analyzer sends them again
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl TransmissionProgress {
+    /// Starts counting a new transmission
+    pub fn start() -> Self {
+        Self {
+            transmission_id: uuid::Uuid::new_v4().to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Control id, sender, version and message date declared in an inbound ASTM header (H) record
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AstmHeader {
+    #[serde(default)]
+    pub control_id: Option<String>, // H.3
+    pub sender_id: Option<String>,  // H.5
+    pub version: Option<String>,    // H.13, e.g. "E 1394-97" or "LIS2-A2"
+    #[serde(default)]
+    pub message_time: Option<DateTime<Utc>>, // H.14, the analyzer's clock read as UTC
+}
+
+/// Specimen range of an inbound ASTM host query (Q) record
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct HostQuery {
+    pub starting_range: String, // Q.3 as received, e.g. "^S42"
+    pub ending_range: String,   // Q.4 as received
+    /// Specimen id of Q.3; None when every pending order is requested (ALL)
+    pub first_specimen_id: Option<String>,
+    /// Specimen id of Q.4; the first id when the range is a single sample
+    pub last_specimen_id: Option<String>,
+}
+
+impl HostQuery {
+    /// Inclusive specimen id range asked for, or None for all pending orders
+    pub fn specimen_range(&self) -> Option<(&str, &str)> {
+        let first = self.first_specimen_id.as_deref()?;
+        Some((first, self.last_specimen_id.as_deref().unwrap_or(first)))
+    }
+}
+
+/// ASTM revision declared by the sender, used to branch on known parsing differences
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AstmVersion {
+    /// E 1394-97, also assumed when no version is declared
+    #[default]
+    E1394,
+    /// CLSI LIS2-A2: multiple result flags are separated by the repeat delimiter
+    Lis2A2,
+}
+
+impl AstmVersion {
+    pub fn from_declared(version: Option<&str>) -> Self {
+        match version {
+            Some(version) if version.to_uppercase().replace(['-', ' '], "").contains("LIS2") => AstmVersion::Lis2A2,
+            _ => AstmVersion::E1394,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PatientData {
     pub id: String,
     pub name: String,
@@ -86,6 +257,47 @@ pub struct PatientData {
     pub physicians: Option<String>,
     pub height: Option<String>,
     pub weight: Option<String>,
+    #[serde(default)]
+    pub structured_address: Option<PatientAddress>,
+    #[serde(default)]
+    pub contacts: Vec<ContactInfo>,
+    /// Comment (C) records following the patient record
+    #[serde(default)]
+    pub comments: Vec<String>,
+    /// Components of the name field (`last^first^middle^title`)
+    #[serde(default)]
+    pub structured_name: PatientName,
+}
+
+impl PatientData {
+    /// The patient as stored under `patient_id`, with the structured name, address
+    /// and telephone numbers of the P record and the comments that followed it
+    pub fn to_patient(&self, patient_id: &str, now: DateTime<Utc>) -> Patient {
+        Patient {
+            id: patient_id.to_string(),
+            name: self.structured_name.clone(),
+            birth_date: self.birth_date.as_deref().and_then(parse_hl7_datetime),
+            sex: self.sex.as_deref().map(Sex::from).unwrap_or(Sex::Other),
+            address: self.structured_address.clone(),
+            telephone: self.telephone.iter().filter(|number| !number.is_empty()).cloned().collect(),
+            contacts: self.contacts.clone(),
+            physicians: None,
+            physical_attributes: None,
+            comments: self.comments.clone(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Record a comment (C) record attaches to, per the ASTM record hierarchy
+#[derive(Debug, Clone, Copy)]
+enum CommentTarget {
+    Patient,
+    /// Index into the message's parsed results
+    Result(usize),
+    /// Header, order or an unparsable record; its comments are not stored
+    None,
 }
 
 // ============================================================================
@@ -102,10 +314,40 @@ const ASTM_ETB: u8 = 0x17; // ETB - End of Transmission Block
 const ASTM_CR: u8 = 0x0D; // CR - Carriage Return
 const ASTM_LF: u8 = 0x0A; // LF - Line Feed
 
+/// Longest a single read waits before the connection lock is released and the timers rechecked
+const READ_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Pause after which a delimited-text dump is taken as complete; it is not framed
+const TEXT_MESSAGE_IDLE: Duration = Duration::from_secs(2);
+
+/// Longest the host waits for the analyzer to answer its ENQ or a frame (ASTM E1381 sender timer)
+const HOST_REPLY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Sender name the host declares in the header of a worklist download
+const HOST_SENDER_ID: &str = "LIS";
+
 // ============================================================================
 // CONNECTION STATE
 // ============================================================================
 
+/// ASTM receive timer that can abort a transmission
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AstmTimer {
+    InterByte,
+    InterFrame,
+    Transmission,
+}
+
+impl AstmTimer {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AstmTimer::InterByte => "inter-byte",
+            AstmTimer::InterFrame => "inter-frame",
+            AstmTimer::Transmission => "transmission",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ConnectionState {
     WaitingForEnq,
@@ -124,14 +366,59 @@ pub struct Connection {
     pub state: ConnectionState,
     pub frame_buffer: Vec<Vec<u8>>, // Store multiple frames
     pub current_frame: Vec<u8>,     // Current frame being built
+    pub assembler: MessageAssembler, // Joins frame text into records across ETB frames
+    pub records: Vec<Vec<u8>>,      // Records completed in the current transmission
     pub analyzer_id: String,
+    pub timeline: Option<ProcessingTimeline>, // Stage timestamps for the current transmission
+    pub shadow_mode: ShadowMode,              // Never NAK while shadow mode is on
+    pub protective_mode: ProtectiveMode,      // Low on disk: ACK the EOT only once the results are stored
+    pub pending_receipts: Vec<oneshot::Receiver<Result<(), String>>>, // Store reports the EOT ACK waits for
+    pub dilution_mode: DilutionMode,          // Whether reported values still need the dilution applied
+    pub astm_version: AstmVersion,            // Declared by the last header record
+    pub delimiters: AstmDelimiters,           // Declared by the last header record
+    pub progress: TransmissionProgress,       // Counts for the transmission in progress
+    pub provisional_results: bool,            // Emit each result as provisional while receiving
+    pub conversation: ConversationRecorder,   // Protocol elements exchanged, for sequence diagrams
+    pub post_eot_delay: Duration,             // Pause after the EOT ACK before the next ENQ is answered
+    pub quiet_until: Option<Instant>,         // End of the current post-EOT pause
+    pub timeouts: AstmTimeouts,               // Receive timers that abort a stalled transmission
+    pub transmission_started: Option<Instant>, // When the ENQ of the transmission in progress was accepted
+    pub log_sampler: Arc<LogSampler>,         // Shared by the analyzer's connections; picks which messages log at info
+    pub analyzer: Arc<RwLock<Analyzer>>,      // Shared with the service; its conformance-report window holds deviating messages
+    pub accept_frames_without_enq: bool,      // Start a transmission on STX when the sender skips ENQ
+    pub host_initiated: bool,                 // Bid for the line on connect and send queued orders first
+    pub frame_retries: u32,                   // Times a NAKed frame is resent before the transfer is abandoned
+    pub frame_naks: u32,                      // NAKs sent since the last frame ACKed in the current transmission
+    pub sex_codes: SexCodeMap,                // Non-standard patient sex codes of the instrument
+    pub checksum_policy: ChecksumPolicy,      // NAK frames with a bad checksum instead of accepting them
+    pub clock_skew_seconds: i64,              // Configured skew, used when a header carries no date
+    pub measured_clock_skew: Option<i64>,     // Skew measured from the H.14 date of the current transmission
+    pub result_acceptance: ResultAcceptanceWindow, // Completion dates accepted relative to server time
+    pub default_patient_class: Option<String>, // Patient class uploaded to the HIS with the analyzer's results
+    pub order_repository: Option<Arc<SqliteRepository>>, // Pending orders answered to host queries
+    pub pending_queries: Vec<HostQuery>,      // Host queries waiting for the analyzer to release the line
+    pub session_outcome: Option<ConnectionTermination>, // How the last transmission ended, None while one is open
+    pub permit: Option<ConnectionPermit>,     // Slot of the remote IP under the per-IP limit, freed with the connection
+    pub decoder: Option<Arc<dyn ResultDecoder>>, // Set for text-dumping devices, which bypass the ASTM state machine
+    pub text_message: Vec<u8>,                // Text received since the last decoded dump
+}
+
+/// How the analyzer answered a host bid for the line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostBid {
+    /// Line won and every record acknowledged
+    Sent,
+    /// The analyzer sent ENQ at the same time; it keeps the line
+    Contention,
+    /// The analyzer is busy (NAK) or answered with something else
+    Refused,
 }
 
 // ============================================================================
 // MAIN SERVICE
 // ============================================================================
 
-pub struct AutoQuantMerilService<R: Runtime> {
+pub struct AutoQuantMerilService {
     /// Analyzer configuration
     analyzer: Arc<RwLock<Analyzer>>,
     /// TCP listener for incoming connections
@@ -143,16 +430,36 @@ pub struct AutoQuantMerilService<R: Runtime> {
     /// Service status
     is_running: Arc<RwLock<bool>>,
     /// Store for configuration persistence
-    store: Arc<tauri_plugin_store::Store<R>>,
+    store: Arc<dyn ConfigPersistence>,
+    /// Global store-only switch
+    shadow_mode: ShadowMode,
+    /// Set by the disk monitor when space is critical
+    protective_mode: ProtectiveMode,
+    /// Per-connection conversations, including recently closed ones
+    conversation_log: ConversationLog,
+    /// Picks which processed messages are logged at info, shared by all connections
+    log_sampler: Arc<LogSampler>,
+    /// Coalesces status writes so a flapping connection does not rewrite the store
+    status_persister: StatusPersister,
+    /// Orders waiting for the host to bid for the line
+    host_outbox: Arc<Mutex<VecDeque<TestOrder>>>,
+    /// Pending orders answered to host queries; queries get a "no information" reply without it
+    order_repository: Option<Arc<SqliteRepository>>,
 }
 
-impl<R: Runtime> AutoQuantMerilService<R> {
+impl AutoQuantMerilService {
     /// Creates a new AutoQuantMeril service
     pub fn new(
         analyzer: Analyzer,
         event_sender: mpsc::Sender<MerilEvent>,
-        store: Arc<tauri_plugin_store::Store<R>>,
+        store: Arc<dyn ConfigPersistence>,
+        shadow_mode: ShadowMode,
     ) -> Self {
+        let mut analyzer = analyzer;
+        reset_stale_status(&mut analyzer);
+        let log_sampler = Arc::new(LogSampler::new(analyzer.log_sample_rate));
+        log_sampler.capture_until(analyzer.conformance_report_until);
+        let status_persister = StatusPersister::new(store.clone(), STATUS_PERSIST_INTERVAL);
         Self {
             analyzer: Arc::new(RwLock::new(analyzer)),
             listener: Arc::new(Mutex::new(None)),
@@ -160,29 +467,44 @@ impl<R: Runtime> AutoQuantMerilService<R> {
             event_sender,
             is_running: Arc::new(RwLock::new(false)),
             store,
+            shadow_mode,
+            protective_mode: ProtectiveMode::default(),
+            conversation_log: ConversationLog::default(),
+            log_sampler,
+            status_persister,
+            host_outbox: Arc::new(Mutex::new(VecDeque::new())),
+            order_repository: None,
         }
     }
 
+    /// Answers host queries with the pending orders of the order dispatch queue
+    pub fn with_order_repository(mut self, repository: Arc<SqliteRepository>) -> Self {
+        self.order_repository = Some(repository);
+        self
+    }
+
+    /// Holds back the EOT ACK until the results are stored while the disk monitor's
+    /// protective mode is on
+    pub fn with_protective_mode(mut self, protective_mode: ProtectiveMode) -> Self {
+        self.protective_mode = protective_mode;
+        self
+    }
+
+    /// Conversations of open and recently closed connections
+    pub fn conversation_log(&self) -> &ConversationLog {
+        &self.conversation_log
+    }
+
     /// Starts the service
     pub async fn start(&self) -> Result<(), String> {
         let port = {
             let analyzer = self.analyzer.read().await;
             analyzer.port.ok_or("No port configured")?
         };
-        let bind_addr = format!("0.0.0.0:{}", port);
-
-        log::info!("Starting AutoQuantMeril service on {}", bind_addr);
 
-        // Create TCP listener
-        let listener = TcpListener::bind(&bind_addr)
-            .await
-            .map_err(|e| format!("Failed to bind to {}: {}", bind_addr, e))?;
+        log::info!("Starting AutoQuantMeril service on port {}", port);
 
-        // Store listener in mutex
-        {
-            let mut listener_guard = self.listener.lock().await;
-            *listener_guard = Some(listener);
-        }
+        self.bind_listener(port).await?;
 
         *self.is_running.write().await = true;
 
@@ -194,8 +516,8 @@ impl<R: Runtime> AutoQuantMerilService<R> {
             analyzer.id.clone()
         };
 
-        // Save updated analyzer to store
-        self.save_analyzer_to_store().await?;
+        // Persist the status change, coalesced with any other within the interval
+        self.persist_status().await?;
 
         // Emit status update event
         let _ = self
@@ -212,15 +534,41 @@ impl<R: Runtime> AutoQuantMerilService<R> {
             port
         );
 
-        // Start the connection handler in a separate thread
+        self.spawn_accept_loop().await;
+
+        Ok(())
+    }
+
+    /// Binds the listener on the given port and the configured bind address
+    async fn bind_listener(&self, port: u16) -> Result<(), String> {
+        let (bind_address, dual_stack, backlog) = {
+            let analyzer = self.analyzer.read().await;
+            (analyzer.bind_address.clone(), analyzer.dual_stack, analyzer.listen_backlog)
+        };
+
+        // Create TCP listener
+        let (listener, family) = bind_tcp_listener(bind_address.as_deref(), port, dual_stack, backlog)?;
+        log::info!("AutoQuantMeril listener bound on port {} ({:?})", port, family);
+
+        // Store listener in mutex
+        *self.listener.lock().await = Some(listener);
+        Ok(())
+    }
+
+    /// Starts the connection handler in a separate task
+    async fn spawn_accept_loop(&self) {
         let connections = self.connections.clone();
         let is_running = self.is_running.clone();
         let event_sender = self.event_sender.clone();
-        let analyzer_id = {
-            let analyzer = self.analyzer.read().await;
-            analyzer.id.clone()
-        };
+        let analyzer = self.analyzer.read().await.clone();
+        let shared_analyzer = self.analyzer.clone();
         let listener = self.listener.clone();
+        let shadow_mode = self.shadow_mode.clone();
+        let protective_mode = self.protective_mode.clone();
+        let conversation_log = self.conversation_log.clone();
+        let log_sampler = self.log_sampler.clone();
+        let host_outbox = self.host_outbox.clone();
+        let order_repository = self.order_repository.clone();
 
         tokio::spawn(async move {
             Self::handle_connections_loop(
@@ -228,27 +576,71 @@ impl<R: Runtime> AutoQuantMerilService<R> {
                 connections,
                 is_running,
                 event_sender,
-                analyzer_id,
+                analyzer,
+                shared_analyzer,
+                shadow_mode,
+                protective_mode,
+                conversation_log,
+                log_sampler,
+                host_outbox,
+                order_repository,
             )
             .await;
         });
+    }
 
-        Ok(())
+    /// Closes the listener so new connections are refused while established
+    /// ones keep running. Returns false if the service was not listening.
+    pub async fn pause_listening(&self) -> bool {
+        let paused = self.listener.lock().await.take().is_some();
+        if paused {
+            log::info!("AutoQuantMeril service stopped accepting new connections");
+        }
+        paused
     }
 
-    /// Stops the service
-    pub async fn stop(&self) -> Result<(), String> {
-        log::info!("Stopping AutoQuantMeril service");
+    /// Rebinds the listener after [`pause_listening`](Self::pause_listening)
+    pub async fn resume_listening(&self) -> Result<(), String> {
+        if !*self.is_running.read().await || self.listener.lock().await.is_some() {
+            return Ok(());
+        }
 
-        *self.is_running.write().await = false;
+        let port = self.analyzer.read().await.port.ok_or("No port configured")?;
+        self.bind_listener(port).await?;
+        self.spawn_accept_loop().await;
 
-        // Close all connections
+        log::info!("AutoQuantMeril service accepting connections again on port {}", port);
+        Ok(())
+    }
+
+    /// Local address of the listener, if listening
+    pub async fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener
+            .lock()
+            .await
+            .as_ref()
+            .and_then(|listener| listener.local_addr().ok())
+    }
+
+    /// Shuts down every established connection
+    pub async fn close_connections(&self) {
         let mut connections = self.connections.write().await;
         for (analyzer_id, mut connection) in connections.drain() {
             if let Err(e) = connection.stream.shutdown().await {
                 log::warn!("Error shutting down connection for {}: {}", analyzer_id, e);
             }
+            connection.conversation.close(ConnectionTermination::Shutdown);
         }
+    }
+
+    /// Stops the service
+    pub async fn stop(&self) -> Result<(), String> {
+        log::info!("Stopping AutoQuantMeril service");
+
+        *self.is_running.write().await = false;
+
+        // Close all connections
+        self.close_connections().await;
 
         // Clear listener
         {
@@ -264,8 +656,9 @@ impl<R: Runtime> AutoQuantMerilService<R> {
             analyzer.id.clone()
         };
 
-        // Save updated analyzer to store
-        self.save_analyzer_to_store().await?;
+        // The final state is written now rather than at the end of the interval
+        self.persist_status().await?;
+        self.status_persister.flush()?;
 
         // Emit status update event
         let _ = self
@@ -292,20 +685,31 @@ impl<R: Runtime> AutoQuantMerilService<R> {
         let json_value = serde_json::to_value(store_data)
             .map_err(|e| format!("Failed to serialize analyzer configuration: {}", e))?;
 
-        self.store.set("config".to_string(), json_value);
+        self.store.set("config", json_value);
+        self.store.save()?;
 
         log::debug!("Analyzer configuration saved to store");
         Ok(())
     }
 
     /// Main connection handling loop
+    #[allow(clippy::too_many_arguments)]
     async fn handle_connections_loop(
         listener: Arc<Mutex<Option<TcpListener>>>,
         connections: Arc<RwLock<HashMap<String, Connection>>>,
         is_running: Arc<RwLock<bool>>,
         event_sender: mpsc::Sender<MerilEvent>,
-        analyzer_id: String,
+        analyzer: Analyzer,
+        shared_analyzer: Arc<RwLock<Analyzer>>,
+        shadow_mode: ShadowMode,
+        protective_mode: ProtectiveMode,
+        conversation_log: ConversationLog,
+        log_sampler: Arc<LogSampler>,
+        host_outbox: Arc<Mutex<VecDeque<TestOrder>>>,
+        order_repository: Option<Arc<SqliteRepository>>,
     ) {
+        let analyzer_id = analyzer.id.clone();
+        let limiter = ConnectionLimiter::new(analyzer.max_connections_per_ip);
         loop {
             // Check if service should stop
             if !*is_running.read().await {
@@ -317,30 +721,89 @@ impl<R: Runtime> AutoQuantMerilService<R> {
             let listener_ref = match &*listener_guard {
                 Some(l) => l,
                 None => {
-                    log::error!("No TCP listener available");
+                    log::info!("TCP listener closed, stopping accept loop");
                     break;
                 }
             };
 
             // Accept incoming connections
             match timeout(Duration::from_secs(1), listener_ref.accept()).await {
-                Ok(Ok((stream, addr))) => {
+                Ok(Ok((mut stream, addr))) => {
                     log::info!("New connection from {}", addr);
 
+                    let permit = match limiter.admit(addr.ip()) {
+                        Ok(permit) => permit,
+                        Err(reason) => {
+                            log::warn!("Rejected connection from {} to {}: {}", addr, analyzer_id, reason);
+                            let _ = stream.shutdown().await;
+                            let _ = event_sender
+                                .send(MerilEvent::ConnectionRejected {
+                                    analyzer_id: analyzer_id.clone(),
+                                    remote_addr: addr.to_string(),
+                                    reason,
+                                    timestamp: Utc::now(),
+                                })
+                                .await;
+                            continue;
+                        }
+                    };
+
                     let connection = Connection {
                         stream,
                         remote_addr: addr,
                         state: ConnectionState::WaitingForEnq,
                         frame_buffer: Vec::new(),
                         current_frame: Vec::new(),
+                        assembler: MessageAssembler::default(),
+                        records: Vec::new(),
                         analyzer_id: analyzer_id.clone(),
+                        timeline: None,
+                        shadow_mode: shadow_mode.clone(),
+                        protective_mode: protective_mode.clone(),
+                        pending_receipts: Vec::new(),
+                        dilution_mode: analyzer.dilution_mode,
+                        astm_version: AstmVersion::default(),
+                        delimiters: AstmDelimiters::default(),
+                        progress: TransmissionProgress::default(),
+                        provisional_results: analyzer.stream_provisional_results,
+                        conversation: conversation_log.open(&analyzer_id, "ASTM", addr),
+                        post_eot_delay: Duration::from_millis(analyzer.post_eot_delay_ms),
+                        quiet_until: None,
+                        timeouts: analyzer.astm_timeouts,
+                        transmission_started: None,
+                        log_sampler: log_sampler.clone(),
+                        analyzer: shared_analyzer.clone(),
+                        accept_frames_without_enq: analyzer.accept_frames_without_enq,
+                        host_initiated: analyzer.host_initiated,
+                        frame_retries: analyzer.astm_frame_retries,
+                        frame_naks: 0,
+                        sex_codes: analyzer.sex_codes.clone(),
+                        checksum_policy: analyzer.checksum_policy,
+                        clock_skew_seconds: analyzer.clock_skew_seconds,
+                        measured_clock_skew: None,
+                        result_acceptance: analyzer.result_acceptance,
+                        default_patient_class: analyzer.default_patient_class.clone(),
+                        order_repository: order_repository.clone(),
+                        pending_queries: Vec::new(),
+                        session_outcome: None,
+                        permit: Some(permit),
+                        decoder: decoder_for(&analyzer).map(Arc::from),
+                        text_message: Vec::new(),
                     };
 
-                    // Store connection
-                    connections
-                        .write()
-                        .await
-                        .insert(analyzer_id.clone(), connection);
+                    // Store connection; a reconnect replaces the analyzer's previous socket,
+                    // which is closed so the map only ever holds open connections
+                    let replaced = connections.write().await.insert(analyzer_id.clone(), connection);
+                    if let Some(mut replaced) = replaced {
+                        log::warn!(
+                            "{} reconnected from {}, closing its previous connection from {}",
+                            analyzer_id,
+                            addr,
+                            replaced.remote_addr
+                        );
+                        let _ = replaced.stream.shutdown().await;
+                        replaced.conversation.close(ConnectionTermination::Shutdown);
+                    }
 
                     // Send connection event
                     let _ = event_sender
@@ -355,12 +818,14 @@ impl<R: Runtime> AutoQuantMerilService<R> {
                     let connections_clone = connections.clone();
                     let event_sender_clone = event_sender.clone();
                     let analyzer_id_clone = analyzer_id.clone();
+                    let host_outbox_clone = host_outbox.clone();
 
                     tokio::spawn(async move {
                         Self::handle_connection(
                             connections_clone,
                             event_sender_clone,
                             analyzer_id_clone,
+                            host_outbox_clone,
                         )
                         .await;
                     });
@@ -381,30 +846,74 @@ impl<R: Runtime> AutoQuantMerilService<R> {
         connections: Arc<RwLock<HashMap<String, Connection>>>,
         event_sender: mpsc::Sender<MerilEvent>,
         analyzer_id: String,
+        host_outbox: Arc<Mutex<VecDeque<TestOrder>>>,
     ) {
         let mut buffer = [0u8; 1024];
+        let mut last_received = Instant::now();
 
-        loop {
+        // In host-initiated mode the analyzer waits for the host to bid first
+        if let Some(connection) = connections.write().await.get_mut(&analyzer_id) {
+            if connection.host_initiated {
+                if let Err(e) = Self::bid_for_line(connection, &host_outbox, &event_sender).await {
+                    log::error!("Host-initiated transmission to {} failed: {}", analyzer_id, e);
+                    let _ = event_sender
+                        .send(MerilEvent::Error {
+                            analyzer_id: analyzer_id.clone(),
+                            error: e,
+                            timestamp: Utc::now(),
+                        })
+                        .await;
+                }
+            }
+        }
+
+        let termination = loop {
             // Get connection
             let mut connections_guard = connections.write().await;
             let connection = match connections_guard.get_mut(&analyzer_id) {
                 Some(conn) => conn,
                 None => {
+                    // Closed by the service
                     log::warn!("Connection not found for {}", analyzer_id);
-                    break;
+                    break ConnectionTermination::Shutdown;
                 }
             };
 
-            // Read data
-            match timeout(Duration::from_secs(5), connection.stream.read(&mut buffer)).await {
+            // Abort a transmission whose timer has run out
+            let now = Instant::now();
+            let deadline = Self::next_deadline(connection, last_received);
+            if let Some((expires_at, timer)) = deadline {
+                if expires_at <= now {
+                    Self::abort_transmission(connection, timer, &event_sender).await;
+                    continue;
+                }
+            }
+
+            // Read data, waking up for the next timer deadline or when a text dump goes quiet
+            let mut wait = deadline.map_or(READ_POLL_INTERVAL, |(expires_at, _)| {
+                (expires_at - now).min(READ_POLL_INTERVAL)
+            });
+            if !connection.text_message.is_empty() {
+                wait = wait.min(TEXT_MESSAGE_IDLE);
+            }
+            match timeout(wait, connection.stream.read(&mut buffer)).await {
                 Ok(Ok(0)) => {
-                    // Connection closed
-                    log::info!("Connection closed by {}", connection.remote_addr);
-                    break;
+                    // Connection closed; a text dump ends with it
+                    Self::process_text_message(connection, &event_sender).await;
+                    let termination = Self::classify_remote_close(connection);
+                    log::info!("Connection closed by {} ({})", connection.remote_addr, termination.as_db_str());
+                    break termination;
                 }
                 Ok(Ok(n)) => {
+                    last_received = Instant::now();
                     let data = &buffer[..n];
 
+                    // Text dumps are neither framed nor acknowledged
+                    if connection.decoder.is_some() {
+                        connection.text_message.extend_from_slice(data);
+                        continue;
+                    }
+
                     // Process ASTM protocol
                     if let Err(e) = Self::process_astm_data(connection, data, &event_sender).await {
                         log::error!("Error processing ASTM data: {}", e);
@@ -417,49 +926,106 @@ impl<R: Runtime> AutoQuantMerilService<R> {
                             })
                             .await;
                     }
+
+                    // Queries are answered once the analyzer has released the line
+                    if let Err(e) = Self::answer_host_queries(connection, &event_sender).await {
+                        log::error!("Failed to answer host query from {}: {}", analyzer_id, e);
+
+                        let _ = event_sender
+                            .send(MerilEvent::Error {
+                                analyzer_id: analyzer_id.clone(),
+                                error: e,
+                                timestamp: Utc::now(),
+                            })
+                            .await;
+                    }
                 }
                 Ok(Err(e)) => {
                     log::error!("Error reading from connection: {}", e);
-                    break;
+                    break ConnectionTermination::Error;
                 }
                 Err(_) => {
-                    // Timeout, continue
+                    // Timeout; the timers are checked before the next read
+                    if last_received.elapsed() >= TEXT_MESSAGE_IDLE {
+                        Self::process_text_message(connection, &event_sender).await;
+                    }
                     continue;
                 }
             }
-        }
+        };
 
         // Remove connection
-        connections.write().await.remove(&analyzer_id);
+        if let Some(connection) = connections.write().await.remove(&analyzer_id) {
+            connection.conversation.close(termination);
+        }
 
         // Send disconnection event
         let _ = event_sender
             .send(MerilEvent::AnalyzerDisconnected {
                 analyzer_id,
+                termination,
                 timestamp: Utc::now(),
             })
             .await;
     }
 
-    /// Processes ASTM protocol data
+    /// Classifies a close by the analyzer. The AutoQuant closes the socket after
+    /// every transmission, so a close while idle after an acknowledged EOT is a
+    /// normal end; anything else cut a session short.
+    fn classify_remote_close(connection: &Connection) -> ConnectionTermination {
+        match (&connection.state, connection.session_outcome) {
+            (ConnectionState::WaitingForEnq, Some(outcome)) => outcome,
+            _ => ConnectionTermination::RemoteClosed,
+        }
+    }
+
+    /// Feeds received bytes through the protocol state machine. The state, including
+    /// a partial frame, carries over between calls, so elements split across reads
+    /// or several elements in one read are handled alike. A rejected frame does not
+    /// stop the bytes after it; the first frame error is returned once all are consumed.
     async fn process_astm_data(
         connection: &mut Connection,
         data: &[u8],
         event_sender: &mpsc::Sender<MerilEvent>,
     ) -> Result<(), String> {
+        let mut frame_error: Option<String> = None;
         for &byte in data {
+            // Control characters never appear in frame text, so handle them per
+            // protocol even if they arrive while a frame is being read
+            if Self::is_reading_frame(&connection.state)
+                && matches!(byte, ASTM_ENQ | ASTM_ACK | ASTM_NAK | ASTM_EOT)
+            {
+                Self::handle_control_byte_in_frame(connection, byte, event_sender).await?;
+                continue;
+            }
+
             match connection.state {
                 ConnectionState::WaitingForEnq => {
                     if byte == ASTM_ENQ {
+                        connection.conversation.received(ConversationElement::Enq, 1);
+                        if let Some(quiet_until) = connection.quiet_until.take() {
+                            // Answering too soon after the EOT ACK confuses some analyzers
+                            tokio::time::sleep_until(quiet_until).await;
+                        }
+                        Self::begin_transmission(connection);
+
                         // Send ACK
-                        connection
-                            .stream
-                            .write_all(&[ASTM_ACK])
-                            .await
-                            .map_err(|e| format!("Failed to send ACK: {}", e))?;
+                        Self::send_control(connection, ASTM_ACK, "ACK").await?;
 
                         connection.state = ConnectionState::WaitingForFrame;
                         log::debug!("Received ENQ, sent ACK, waiting for frame");
+                    } else if byte == ASTM_STX && connection.accept_frames_without_enq {
+                        // Tolerant mode: the sender skipped line establishment, so treat
+                        // the STX as the start of both the transmission and its first frame
+                        log::warn!(
+                            "STX without ENQ from {}, starting transmission (frames without ENQ accepted)",
+                            connection.remote_addr
+                        );
+                        connection.quiet_until = None;
+                        Self::begin_transmission(connection);
+                        connection.current_frame.clear();
+                        connection.current_frame.push(byte);
+                        connection.state = ConnectionState::ProcessingFrame;
                     }
                 }
                 ConnectionState::WaitingForFrame => {
@@ -471,28 +1037,26 @@ impl<R: Runtime> AutoQuantMerilService<R> {
                     } else if byte == ASTM_EOT {
                         // End of transmission
                         log::info!("Received EOT, transmission complete");
+                        connection.conversation.received(ConversationElement::Eot, 1);
+                        if let Some(timeline) = connection.timeline.as_mut() {
+                            timeline.mark(ProcessingStage::Framed);
+                        }
 
                         // Process complete message
                         Self::process_complete_message(connection, event_sender).await?;
 
                         // Send ACK for EOT
-                        connection
-                            .stream
-                            .write_all(&[ASTM_ACK])
-                            .await
-                            .map_err(|e| format!("Failed to send ACK for EOT: {}", e))?;
+                        Self::acknowledge_eot(connection).await?;
+                        Self::start_post_eot_delay(connection);
 
                         // Clear frame buffer for next transmission
                         connection.frame_buffer.clear();
                         connection.current_frame.clear();
 
-                        // Reset state for next transmission
+                        // Reset state for next transmission; bytes after the EOT in the
+                        // same read (the next ENQ) are handled by the following iterations
                         connection.state = ConnectionState::WaitingForEnq;
                         log::info!("Transmission complete, ready for next transmission");
-
-                        // Break out of the loop - transmission is complete
-                        // The connection will be ready for the next transmission when it receives ENQ again
-                        break;
                     } else {
                         log::debug!(
                             "Unexpected byte in WaitingForFrame: 0x{:02X} ('{}')",
@@ -501,6 +1065,17 @@ impl<R: Runtime> AutoQuantMerilService<R> {
                         );
                     }
                 }
+                ConnectionState::ProcessingFrame if byte == ASTM_STX => {
+                    // The end of the previous frame was lost; start over with the new frame
+                    log::warn!(
+                        "STX mid-frame from {}, discarding {} bytes of partial frame",
+                        connection.remote_addr,
+                        connection.current_frame.len()
+                    );
+                    Self::record_frame(connection, EntryOutcome::Rejected, Some("Interrupted by STX".to_string()));
+                    connection.current_frame.clear();
+                    connection.current_frame.push(byte);
+                }
                 ConnectionState::ProcessingFrame => {
                     connection.current_frame.push(byte);
 
@@ -510,49 +1085,60 @@ impl<R: Runtime> AutoQuantMerilService<R> {
                     }
                 }
                 ConnectionState::WaitingForChecksum => {
-                    // Store checksum byte
+                    // Store checksum characters (two hex digits)
                     connection.current_frame.push(byte);
-                    log::debug!("Received checksum: 0x{:02X}, waiting for CR", byte);
-                    connection.state = ConnectionState::WaitingForCR;
+                    if Self::checksum_chars_received(&connection.current_frame) >= 2 {
+                        log::debug!("Received checksum, waiting for CR");
+                        connection.state = ConnectionState::WaitingForCR;
+                    }
                 }
                 ConnectionState::WaitingForCR => {
                     if byte == ASTM_CR {
                         connection.current_frame.push(byte);
                         log::debug!("Received CR, waiting for LF");
                         connection.state = ConnectionState::WaitingForLF;
+                    } else if byte == b' ' && connection.current_frame.last() != Some(&b' ') {
+                        // Some middleware pads a single space between checksum and CR
+                        connection.current_frame.push(byte);
                     } else {
                         log::error!("Expected CR (0x0D), got 0x{:02X}", byte);
-                        return Err("Invalid frame format: expected CR".to_string());
+                        let error = "Invalid frame format: expected CR".to_string();
+                        Self::reject_frame(connection, error, Instant::now(), &mut frame_error).await?;
                     }
                 }
                 ConnectionState::WaitingForLF => {
                     if byte == ASTM_LF {
                         connection.current_frame.push(byte);
                         log::debug!("Received LF, processing complete frame");
+                        let frame_ended = Instant::now();
 
-                        // Now process the complete frame
-                        if let Err(e) = Self::process_frame(connection, event_sender).await {
-                            // Send NAK on error
-                            connection
-                                .stream
-                                .write_all(&[ASTM_NAK])
-                                .await
-                                .map_err(|e| format!("Failed to send NAK: {}", e))?;
-                            return Err(e);
-                        }
+                        // Only framing and checksum decide between ACK and NAK
+                        let records = match Self::validate_frame(connection) {
+                            Ok(records) => records,
+                            Err(e) => {
+                                Self::reject_frame(connection, e, frame_ended, &mut frame_error).await?;
+                                continue;
+                            }
+                        };
+                        Self::record_frame(connection, EntryOutcome::Accepted, None);
+                        connection.frame_naks = 0;
 
-                        // Send ACK
-                        connection
-                            .stream
-                            .write_all(&[ASTM_ACK])
-                            .await
-                            .map_err(|e| format!("Failed to send ACK: {}", e))?;
+                        // Send ACK before the records are parsed, so the answer never waits on them
+                        Self::send_control(connection, ASTM_ACK, "ACK").await?;
+                        Self::check_ack_time(connection, frame_ended);
+                        if let Some(timeline) = connection.timeline.as_mut() {
+                            timeline.mark(ProcessingStage::Acked);
+                        }
+                        if let Some(records) = records {
+                            Self::process_frame_records(connection, records, event_sender).await;
+                        }
 
                         connection.current_frame.clear();
                         connection.state = ConnectionState::WaitingForFrame;
                     } else {
                         log::error!("Expected LF (0x0A), got 0x{:02X}", byte);
-                        return Err("Invalid frame format: expected LF".to_string());
+                        let error = "Invalid frame format: expected LF".to_string();
+                        Self::reject_frame(connection, error, Instant::now(), &mut frame_error).await?;
                     }
                 }
                 ConnectionState::Complete => {
@@ -567,331 +1153,4062 @@ impl<R: Runtime> AutoQuantMerilService<R> {
             }
         }
 
+        frame_error.map_or(Ok(()), Err)
+    }
+
+    /// Answers a frame that failed validation with NAK so the sender retransmits it, and
+    /// goes back to waiting for the next frame. In shadow mode the frame is ACKed instead.
+    /// Once the sender has used up its retransmissions the transmission is abandoned.
+    /// The error to report is left in `frame_error`.
+    async fn reject_frame(
+        connection: &mut Connection,
+        error: String,
+        frame_ended: Instant,
+        frame_error: &mut Option<String>,
+    ) -> Result<(), String> {
+        Self::record_frame(connection, EntryOutcome::Rejected, Some(error.clone()));
+        if connection.shadow_mode.is_enabled() {
+            // Shadow mode: ACK anyway so the analyzer never retransmits
+            log::warn!("Shadow mode: acknowledging invalid frame instead of NAK: {}", error);
+            Self::send_control(connection, ASTM_ACK, "ACK").await?;
+            Self::check_ack_time(connection, frame_ended);
+            Self::discard_frame(connection);
+            return Ok(());
+        }
+
+        Self::send_control(connection, ASTM_NAK, "NAK").await?;
+        Self::check_ack_time(connection, frame_ended);
+        connection.progress.retransmissions_requested += 1;
+        connection.frame_naks += 1;
+        if connection.frame_naks > connection.frame_retries {
+            // The sender has used up its retransmissions and ends with EOT;
+            // whatever it still sends is ignored until the next ENQ
+            let abandoned = format!(
+                "Frame from {} rejected {} times, transmission abandoned and {} frames discarded: {}",
+                connection.remote_addr,
+                connection.frame_naks,
+                connection.frame_buffer.len(),
+                error
+            );
+            log::warn!("{}", abandoned);
+            Self::reset_transmission(connection);
+            connection.session_outcome = Some(ConnectionTermination::Error);
+            *frame_error = Some(abandoned);
+            return Ok(());
+        }
+        Self::discard_frame(connection);
+        frame_error.get_or_insert(error);
         Ok(())
     }
 
-    /// Processes a single ASTM frame
-    async fn process_frame(
+    /// Drops the frame being read; bytes up to the next STX or EOT are ignored
+    fn discard_frame(connection: &mut Connection) {
+        connection.current_frame.clear();
+        connection.state = ConnectionState::WaitingForFrame;
+    }
+
+    /// Whether the connection is part-way through reading a frame
+    fn is_reading_frame(state: &ConnectionState) -> bool {
+        matches!(
+            state,
+            ConnectionState::ProcessingFrame
+                | ConnectionState::WaitingForChecksum
+                | ConnectionState::WaitingForCR
+                | ConnectionState::WaitingForLF
+        )
+    }
+
+    /// Handles a control character received while reading a frame.
+    /// The partial frame is discarded on EOT and ENQ.
+    async fn handle_control_byte_in_frame(
         connection: &mut Connection,
+        byte: u8,
         event_sender: &mpsc::Sender<MerilEvent>,
     ) -> Result<(), String> {
-        // Debug: Log the raw frame
-        log::debug!("Processing frame: {:?}", connection.current_frame);
+        match byte {
+            ASTM_EOT => {
+                log::warn!(
+                    "Received EOT mid-frame from {}, discarding {} bytes of partial frame",
+                    connection.remote_addr,
+                    connection.current_frame.len()
+                );
+                connection.conversation.record(
+                    Direction::Inbound,
+                    ConversationElement::Eot,
+                    1,
+                    EntryOutcome::Accepted,
+                    Some(format!("Partial frame of {} bytes discarded", connection.current_frame.len())),
+                );
+                connection.current_frame.clear();
+                if let Some(timeline) = connection.timeline.as_mut() {
+                    timeline.mark(ProcessingStage::Framed);
+                    timeline.warn(ProcessingStage::Framed, "EOT received mid-frame; partial frame discarded");
+                }
 
-        // Log frame structure for debugging
-        if connection.current_frame.len() >= 6 {
-            let frame_number = connection.current_frame[0];
-            let stx = connection.current_frame[1];
-            let etx_pos = connection.current_frame.len() - 4;
-            let etx = connection.current_frame[etx_pos];
-            let checksum = connection.current_frame[connection.current_frame.len() - 3];
-            let cr = connection.current_frame[connection.current_frame.len() - 2];
-            let lf = connection.current_frame[connection.current_frame.len() - 1];
+                // Frames completed before the EOT were already acknowledged
+                Self::process_complete_message(connection, event_sender).await?;
 
-            log::debug!(
-                "Frame structure: FN=0x{:02X}, STX=0x{:02X}, ETX=0x{:02X}, CS=0x{:02X}, CR=0x{:02X}, LF=0x{:02X}",
-                frame_number, stx, etx, checksum, cr, lf
-            );
-        }
+                Self::acknowledge_eot(connection).await?;
+                Self::start_post_eot_delay(connection);
 
-        // Validate checksum
-        if !Self::validate_checksum(&connection.current_frame) {
-            log::error!(
-                "Checksum validation failed for frame: {:?}",
-                connection.current_frame
-            );
+                connection.frame_buffer.clear();
+                connection.state = ConnectionState::WaitingForEnq;
+                Ok(())
+            }
+            ASTM_ENQ => {
+                // The sender restarted the establishment phase; the interrupted message is abandoned
+                log::warn!(
+                    "Received ENQ mid-frame from {}, discarding partial frame and {} buffered frames",
+                    connection.remote_addr,
+                    connection.frame_buffer.len()
+                );
+                connection.conversation.record(
+                    Direction::Inbound,
+                    ConversationElement::Enq,
+                    1,
+                    EntryOutcome::Accepted,
+                    Some(format!(
+                        "Partial frame and {} buffered frames discarded",
+                        connection.frame_buffer.len()
+                    )),
+                );
+                connection.current_frame.clear();
+                connection.frame_buffer.clear();
+                connection.assembler = MessageAssembler::default();
+                connection.records.clear();
+                connection.timeline = Some(ProcessingTimeline::started_at(Utc::now()));
+                connection.progress = TransmissionProgress::start();
+                connection.transmission_started = Some(Instant::now());
+                connection.session_outcome = None;
+
+                Self::send_control(connection, ASTM_ACK, "ACK").await?;
+
+                connection.state = ConnectionState::WaitingForFrame;
+                Ok(())
+            }
+            _ => {
+                // Stray ACK/NAK from a full-duplex peer; not part of the frame
+                log::debug!(
+                    "Ignoring control byte 0x{:02X} received mid-frame from {}",
+                    byte,
+                    connection.remote_addr
+                );
+                if let Some(element) = Self::control_element(byte) {
+                    connection.conversation.record(
+                        Direction::Inbound,
+                        element,
+                        1,
+                        EntryOutcome::Ignored,
+                        Some("Received mid-frame".to_string()),
+                    );
+                }
+                Ok(())
+            }
         }
+    }
 
-        // Extract frame data (remove frame number, STX, ETX, checksum, CR, LF)
-        let frame_data = Self::extract_frame_data(&connection.current_frame)?;
+    /// Starts the configured pause before the next ENQ may be answered
+    fn start_post_eot_delay(connection: &mut Connection) {
+        connection.transmission_started = None;
+        if !connection.post_eot_delay.is_zero() {
+            connection.quiet_until = Some(Instant::now() + connection.post_eot_delay);
+        }
+    }
 
-        // Parse ASTM record
-        let record_type = Self::parse_record_type(&frame_data)?;
+    /// Earliest timer deadline of the transmission in progress, `None` while idle.
+    /// The gap timers run from the last bytes received.
+    fn next_deadline(connection: &Connection, last_received: Instant) -> Option<(Instant, AstmTimer)> {
+        let started = connection.transmission_started?;
+        let timeouts = &connection.timeouts;
+        let gap = if Self::is_reading_frame(&connection.state) {
+            (last_received + Duration::from_millis(timeouts.inter_byte_ms), AstmTimer::InterByte)
+        } else {
+            (last_received + Duration::from_millis(timeouts.inter_frame_ms), AstmTimer::InterFrame)
+        };
+        let transmission = started + Duration::from_millis(timeouts.transmission_ms);
+        Some(if transmission <= gap.0 {
+            (transmission, AstmTimer::Transmission)
+        } else {
+            gap
+        })
+    }
 
-        log::debug!(
-            "Processed ASTM frame: {} - {}",
-            record_type,
-            String::from_utf8_lossy(&frame_data)
+    /// Abandons a transmission whose timer expired. A frame cut off mid-way is
+    /// NAKed so the sender repeats it; otherwise the link simply returns to idle.
+    /// The expiry is reported as an error naming the timer, which is stored as an analyzer event.
+    async fn abort_transmission(
+        connection: &mut Connection,
+        timer: AstmTimer,
+        event_sender: &mpsc::Sender<MerilEvent>,
+    ) {
+        let timeouts = &connection.timeouts;
+        let limit_ms = match timer {
+            AstmTimer::InterByte => timeouts.inter_byte_ms,
+            AstmTimer::InterFrame => timeouts.inter_frame_ms,
+            AstmTimer::Transmission => timeouts.transmission_ms,
+        };
+        let error = format!(
+            "ASTM {} timeout ({} ms) expired on {}: transmission aborted, {} frames discarded",
+            timer.name(),
+            limit_ms,
+            connection.remote_addr,
+            connection.frame_buffer.len()
         );
+        log::warn!("{}", error);
 
-        // Store the completed frame for later processing
-        connection
-            .frame_buffer
-            .push(connection.current_frame.clone());
+        if Self::is_reading_frame(&connection.state) {
+            Self::record_frame(connection, EntryOutcome::Rejected, Some(format!("{} timeout", timer.name())));
+            if let Err(e) = Self::send_control(connection, ASTM_NAK, "NAK").await {
+                log::error!("{}", e);
+            }
+        }
+
+        Self::reset_transmission(connection);
+        connection.session_outcome = Some(ConnectionTermination::Timeout);
 
-        // Send event
         let _ = event_sender
-            .send(MerilEvent::AstmMessageReceived {
+            .send(MerilEvent::Error {
                 analyzer_id: connection.analyzer_id.clone(),
-                message_type: record_type,
-                raw_data: String::from_utf8_lossy(&frame_data).to_string(),
+                error,
                 timestamp: Utc::now(),
             })
             .await;
+    }
 
-        Ok(())
+    /// Drops everything received in the transmission so the next ENQ starts afresh
+    fn reset_transmission(connection: &mut Connection) {
+        connection.current_frame.clear();
+        connection.frame_buffer.clear();
+        connection.assembler = MessageAssembler::default();
+        connection.records.clear();
+        connection.timeline = None;
+        connection.transmission_started = None;
+        connection.frame_naks = 0;
+        connection.state = ConnectionState::WaitingForEnq;
     }
 
-    /// Processes complete ASTM message
-    async fn process_complete_message(
-        connection: &mut Connection,
-        event_sender: &mpsc::Sender<MerilEvent>,
+    /// Writes a control character and records it in the conversation
+    async fn send_control(connection: &mut Connection, byte: u8, what: &str) -> Result<(), String> {
+        connection
+            .stream
+            .write_all(&[byte])
+            .await
+            .map_err(|e| format!("Failed to send {}: {}", what, e))?;
+        if let Some(element) = Self::control_element(byte) {
+            connection.conversation.sent(element, 1);
+        }
+        Ok(())
+    }
+
+    fn control_element(byte: u8) -> Option<ConversationElement> {
+        match byte {
+            ASTM_ENQ => Some(ConversationElement::Enq),
+            ASTM_ACK => Some(ConversationElement::Ack),
+            ASTM_NAK => Some(ConversationElement::Nak),
+            ASTM_EOT => Some(ConversationElement::Eot),
+            _ => None,
+        }
+    }
+
+    /// Bids for the line and sends every queued order. Orders go back to the front of
+    /// the queue when the analyzer does not give up the line or the transmission fails.
+    /// Returns the number of orders sent.
+    async fn bid_for_line(
+        connection: &mut Connection,
+        host_outbox: &Mutex<VecDeque<TestOrder>>,
+        event_sender: &mpsc::Sender<MerilEvent>,
+    ) -> Result<usize, String> {
+        // Never bid while the analyzer holds the line
+        if !matches!(connection.state, ConnectionState::WaitingForEnq) {
+            return Ok(0);
+        }
+
+        let orders: Vec<TestOrder> = host_outbox.lock().await.drain(..).collect();
+        if orders.is_empty() {
+            return Ok(0);
+        }
+
+        let requeue = |orders: Vec<TestOrder>| async move {
+            let mut outbox = host_outbox.lock().await;
+            for order in orders.into_iter().rev() {
+                outbox.push_front(order);
+            }
+        };
+
+        let records = order_records(HOST_SENDER_ID, &orders, &connection.delimiters);
+        match Self::transmit_as_host(connection, &records).await {
+            Ok(HostBid::Sent) => {
+                log::info!("Sent {} orders to {}", orders.len(), connection.analyzer_id);
+                Ok(orders.len())
+            }
+            Ok(HostBid::Contention) => {
+                // The analyzer keeps the line; answer its ENQ as the receiver
+                log::info!("Line contention with {}, receiving first", connection.analyzer_id);
+                requeue(orders).await;
+                Self::process_astm_data(connection, &[ASTM_ENQ], event_sender).await?;
+                Ok(0)
+            }
+            Ok(HostBid::Refused) => {
+                log::info!("{} refused the line, orders stay queued", connection.analyzer_id);
+                requeue(orders).await;
+                Ok(0)
+            }
+            Err(e) => {
+                requeue(orders).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Answers the host queries of the transmission that just ended with the pending
+    /// orders of each queried specimen range. Orders sent are marked SENT in the
+    /// dispatch queue. On line contention the analyzer is received first and the
+    /// queries wait for the line to be released again.
+    async fn answer_host_queries(
+        connection: &mut Connection,
+        event_sender: &mpsc::Sender<MerilEvent>,
     ) -> Result<(), String> {
-        log::info!(
-            "Processing complete ASTM message from {}",
-            connection.remote_addr
-        );
+        if connection.pending_queries.is_empty() || !matches!(connection.state, ConnectionState::WaitingForEnq) {
+            return Ok(());
+        }
+        if let Some(quiet_until) = connection.quiet_until.take() {
+            tokio::time::sleep_until(quiet_until).await;
+        }
 
-        // Parse all collected frames to extract patient and test result data
-        let mut patient_data: Option<PatientData> = None;
-        let mut test_results = Vec::new();
+        while !connection.pending_queries.is_empty() {
+            let query = connection.pending_queries.remove(0);
+            let dispatches = match &connection.order_repository {
+                Some(repository) => {
+                    repository
+                        .get_pending_orders_for_specimens(&connection.analyzer_id, query.specimen_range())
+                        .await?
+                }
+                None => Vec::new(),
+            };
+            let orders: Vec<TestOrder> = dispatches.iter().map(|dispatch| dispatch.order.clone()).collect();
+            let records = query_reply_records(
+                HOST_SENDER_ID,
+                &query.starting_range,
+                &query.ending_range,
+                &orders,
+                &connection.delimiters,
+            );
 
-        // Process each frame to extract patient and result data
-        for frame in &connection.frame_buffer {
-            if let Ok(frame_data) = Self::extract_frame_data(frame) {
-                let record_type = Self::parse_record_type(&frame_data)?;
+            match Self::transmit_as_host(connection, &records).await? {
+                HostBid::Sent => {}
+                HostBid::Contention => {
+                    // The analyzer keeps the line; answer its ENQ as the receiver
+                    log::info!("Line contention with {}, receiving before the query reply", connection.analyzer_id);
+                    connection.pending_queries.insert(0, query);
+                    return Self::process_astm_data(connection, &[ASTM_ENQ], event_sender).await;
+                }
+                HostBid::Refused => {
+                    return Err(format!(
+                        "{} refused the line for the reply to its query for {}",
+                        connection.analyzer_id, query.starting_range
+                    ));
+                }
+            }
 
-                match record_type.as_str() {
-                    "Patient" => {
-                        if let Ok(patient) = Self::parse_patient_record(&frame_data) {
-                            log::debug!("Patient data: {:?}", patient);
-                            patient_data = Some(patient);
-                        }
-                    }
-                    "Result" => {
-                        if let Ok(mut result) = Self::parse_result_record(&frame_data) {
-                            result.analyzer_id = Some(connection.analyzer_id.clone());
-                            test_results.push(result);
-                        }
-                    }
-                    _ => {
-                        // Log other record types for debugging
-                        log::debug!("Skipping record type: {}", record_type);
+            if let Some(repository) = &connection.order_repository {
+                for dispatch in &dispatches {
+                    repository
+                        .update_order_dispatch_status(&dispatch.id, DispatchStatus::Sent, None)
+                        .await?;
+                }
+            }
+            log::info!(
+                "Answered host query from {} with {} orders",
+                connection.analyzer_id,
+                orders.len()
+            );
+            let _ = event_sender
+                .send(MerilEvent::HostQueryAnswered {
+                    analyzer_id: connection.analyzer_id.clone(),
+                    query,
+                    orders,
+                    timestamp: Utc::now(),
+                })
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Sends records as the ASTM sender: ENQ, the acknowledged frames of each record
+    /// (a NAKed frame is resent up to `frame_retries` times) and EOT
+    async fn transmit_as_host(connection: &mut Connection, records: &[String]) -> Result<HostBid, String> {
+        Self::send_control(connection, ASTM_ENQ, "ENQ").await?;
+        match Self::read_host_reply(connection).await? {
+            ASTM_ACK => {}
+            ASTM_ENQ => return Ok(HostBid::Contention),
+            _ => return Ok(HostBid::Refused),
+        }
+
+        for (index, frame) in encode_records(records).iter().enumerate() {
+            let number = ((index + 1) % 8) as u8;
+            let mut retries = 0;
+            loop {
+                connection
+                    .stream
+                    .write_all(frame)
+                    .await
+                    .map_err(|e| format!("Failed to send frame {}: {}", number, e))?;
+                connection.conversation.sent(ConversationElement::Frame { number }, frame.len());
+
+                match Self::read_host_reply(connection).await? {
+                    ASTM_ACK => break,
+                    ASTM_NAK if retries < connection.frame_retries => retries += 1,
+                    reply => {
+                        Self::send_control(connection, ASTM_EOT, "EOT").await?;
+                        return Err(format!(
+                            "Frame {} not accepted by {} (reply 0x{:02X} after {} retries)",
+                            number, connection.analyzer_id, reply, retries
+                        ));
                     }
                 }
             }
         }
 
-        // Send the processed data as an event
-        let _ = event_sender
-            .send(MerilEvent::LabResultProcessed {
-                analyzer_id: connection.analyzer_id.clone(),
-                patient_id: patient_data.as_ref().map(|p| p.id.clone()),
-                patient_data,
-                test_results,
-                timestamp: Utc::now(),
-            })
-            .await;
+        Self::send_control(connection, ASTM_EOT, "EOT").await?;
+        connection.session_outcome = Some(ConnectionTermination::Completed);
+        Ok(HostBid::Sent)
+    }
 
-        Ok(())
+    /// Reads the analyzer's single-byte answer to the host's ENQ or frame
+    async fn read_host_reply(connection: &mut Connection) -> Result<u8, String> {
+        let mut reply = [0u8; 1];
+        match timeout(HOST_REPLY_TIMEOUT, connection.stream.read(&mut reply)).await {
+            Ok(Ok(0)) => Err(format!("{} closed the connection", connection.remote_addr)),
+            Ok(Ok(_)) => {
+                if let Some(element) = Self::control_element(reply[0]) {
+                    connection.conversation.received(element, 1);
+                }
+                Ok(reply[0])
+            }
+            Ok(Err(e)) => Err(format!("Failed to read reply: {}", e)),
+            Err(_) => Err(format!("No reply from {} within {:?}", connection.remote_addr, HOST_REPLY_TIMEOUT)),
+        }
     }
 
-    /// Validates ASTM frame checksum
-    fn validate_checksum(frame: &[u8]) -> bool {
-        if frame.len() < 6 {
-            return false;
+    /// Sends a patient record and its orders as the line's sender. The line must be
+    /// idle; on contention the analyzer's transmission is received and the download fails.
+    async fn download_patient_orders(
+        connection: &mut Connection,
+        patient: &Patient,
+        orders: &[TestOrder],
+        event_sender: &mpsc::Sender<MerilEvent>,
+    ) -> Result<(), String> {
+        if !matches!(connection.state, ConnectionState::WaitingForEnq) {
+            return Err(format!("{} is transmitting, try again once the line is idle", connection.analyzer_id));
         }
 
-        // ASTM frame format: FrameNumber + STX + Data + ETX + Checksum + CR + LF
-        // Frame number is ASCII digit (0x30-0x39)
-        // STX is at index 1
-        // ETX is at frame.len() - 4
-        // Checksum is at frame.len() - 3
-        // CR is at frame.len() - 2
-        // LF is at frame.len() - 1
+        let records = patient_order_records(HOST_SENDER_ID, patient, orders, &connection.delimiters);
+        match Self::transmit_as_host(connection, &records).await? {
+            HostBid::Sent => {
+                log::info!(
+                    "Sent {} orders of patient {} to {}",
+                    orders.len(),
+                    patient.id,
+                    connection.analyzer_id
+                );
+                Ok(())
+            }
+            HostBid::Contention => {
+                Self::process_astm_data(connection, &[ASTM_ENQ], event_sender).await?;
+                Err(format!("{} started a transmission of its own, try again", connection.analyzer_id))
+            }
+            HostBid::Refused => Err(format!("{} refused the line", connection.analyzer_id)),
+        }
+    }
 
-        let mut sum = 0u8;
-        let start_idx = 0; // Start from frame number (including it)
-        let end_idx = frame.len() - 3; // End at ETX (inclusive)
+    /// Sends hand-written records as the line's sender, then answers and collects
+    /// whatever the analyzer transmits within `window`
+    async fn exchange_raw_records(
+        connection: &mut Connection,
+        records: &[String],
+        window: Duration,
+        event_sender: &mpsc::Sender<MerilEvent>,
+    ) -> Result<RawExchange, String> {
+        if !matches!(connection.state, ConnectionState::WaitingForEnq) {
+            return Err(format!("{} is transmitting, try again once the line is idle", connection.analyzer_id));
+        }
 
-        for &byte in &frame[start_idx..end_idx] {
-            sum = sum.wrapping_add(byte);
+        let sent_at = Utc::now();
+        match Self::transmit_as_host(connection, records).await? {
+            HostBid::Sent => {}
+            HostBid::Contention => {
+                Self::process_astm_data(connection, &[ASTM_ENQ], event_sender).await?;
+                return Err(format!("{} started a transmission of its own, try again", connection.analyzer_id));
+            }
+            HostBid::Refused => return Err(format!("{} refused the line", connection.analyzer_id)),
         }
+        // ENQ and EOT plus the frames; NAKed frames resent are not counted
+        let bytes_sent = 2 + encode_records(records).iter().map(Vec::len).sum::<usize>();
 
-        let expected_checksum = sum % 8;
-        let actual_checksum = frame[frame.len() - 3]; // Checksum byte
+        // Replies are handled as usual (ACKed, logged, ingested) and copied for the caller
+        let mut responses = Vec::new();
+        let mut buffer = [0u8; 1024];
+        let deadline = Instant::now() + window;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match timeout(remaining, connection.stream.read(&mut buffer)).await {
+                Ok(Ok(0)) | Err(_) => break,
+                Ok(Ok(n)) => {
+                    responses.push(describe_bytes(&buffer[..n]));
+                    Self::process_astm_data(connection, &buffer[..n], event_sender).await?;
+                }
+                Ok(Err(e)) => return Err(format!("Failed to read reply: {}", e)),
+            }
+        }
 
-        log::debug!(
-            "Checksum validation: sum={}, expected={}, actual={}, valid={}",
-            sum,
-            expected_checksum,
-            actual_checksum,
-            expected_checksum == actual_checksum
-        );
+        Ok(RawExchange {
+            analyzer_id: connection.analyzer_id.clone(),
+            protocol: "ASTM".to_string(),
+            bytes_sent,
+            responses,
+            sent_at,
+        })
+    }
 
-        expected_checksum == actual_checksum
+    /// Records the frame being read as an inbound conversation entry
+    fn record_frame(connection: &Connection, outcome: EntryOutcome, detail: Option<String>) {
+        // The frame number follows STX
+        let number = connection
+            .current_frame
+            .get(1)
+            .filter(|b| b.is_ascii_digit())
+            .map_or(0, |b| b - b'0');
+        connection.conversation.record(
+            Direction::Inbound,
+            ConversationElement::Frame { number },
+            connection.current_frame.len(),
+            outcome,
+            detail,
+        );
     }
 
-    /// Extracts frame data from ASTM frame
-    fn extract_frame_data(frame: &[u8]) -> Result<Vec<u8>, String> {
-        if frame.len() < 6 {
-            return Err("Frame too short".to_string());
-        }
+    /// Validates a complete frame and assembles the records it completes, doing no
+    /// more work than the ACK/NAK decision needs; `None` for a frame already accepted
+    fn validate_frame(connection: &mut Connection) -> Result<Option<Vec<Vec<u8>>>, String> {
+        // Debug: Log the raw frame
+        log::debug!("Processing frame: {:?}", connection.current_frame);
 
-        // Find STX and ETX positions
-        let stx_pos = frame.iter().position(|&b| b == ASTM_STX);
-        let etx_pos = frame.iter().position(|&b| b == ASTM_ETX);
+        // Log frame structure for debugging
+        if connection.current_frame.len() >= 6 {
+            let frame = &connection.current_frame;
+            let checksum = Self::checksum_field(frame)
+                .map(|(chars, _)| String::from_utf8_lossy(chars).to_string())
+                .unwrap_or_default();
 
-        match (stx_pos, etx_pos) {
-            (Some(stx), Some(etx)) if stx < etx => {
-                // Extract data between STX and ETX (exclusive)
-                let start_idx = stx + 1; // After STX
-                let end_idx = etx; // Before ETX
+            log::debug!(
+                "Frame structure: STX=0x{:02X}, FN=0x{:02X}, CS={:?}, CR=0x{:02X}, LF=0x{:02X}",
+                frame[0],
+                frame[1],
+                checksum,
+                frame[frame.len() - 2],
+                frame[frame.len() - 1]
+            );
+        }
 
-                let extracted_data = frame[start_idx..end_idx].to_vec();
+        if let Some((_, true)) = Self::checksum_field(&connection.current_frame) {
+            log::warn!(
+                "Tolerating space between checksum and CR in frame from {}",
+                connection.remote_addr
+            );
+            if let Some(timeline) = connection.timeline.as_mut() {
+                timeline.warn(
+                    ProcessingStage::Validated,
+                    format!("Space before CR in frame {}", connection.frame_buffer.len() + 1),
+                );
+            }
+        }
 
-                // Verify frame ends with CR and LF
-                if frame.len() >= 2 {
-                    let cr_pos = frame.len() - 2;
-                    let lf_pos = frame.len() - 1;
+        // Validate checksum; under the strict policy the frame is NAKed and not kept
+        if !Self::validate_checksum(&connection.current_frame) {
+            log::error!(
+                "Checksum validation failed for frame: {:?}",
+                connection.current_frame
+            );
+            let mismatch = format!("Checksum mismatch in frame {}", connection.frame_buffer.len() + 1);
+            if connection.checksum_policy == ChecksumPolicy::Strict {
+                return Err(mismatch);
+            }
+            if let Some(timeline) = connection.timeline.as_mut() {
+                timeline.warn(ProcessingStage::Validated, mismatch);
+            }
+        }
 
-                    if frame[cr_pos] != ASTM_CR || frame[lf_pos] != ASTM_LF {
-                        log::warn!(
-                            "Frame does not end with CR+LF: CR=0x{:02X}, LF=0x{:02X}",
-                            frame[cr_pos],
-                            frame[lf_pos]
-                        );
-                    }
+        // Intermediate (ETB) frames complete no records; their text waits for the final frame
+        let records = match connection.assembler.push(&connection.current_frame) {
+            Ok(records) => records,
+            Err(e @ AssemblyError::OutOfSequence { .. }) => {
+                log::warn!("{} from {}", e, connection.remote_addr);
+                if let Some(timeline) = connection.timeline.as_mut() {
+                    timeline.warn(ProcessingStage::Validated, e.to_string());
                 }
-
-                Ok(extracted_data)
+                return Err(e.to_string());
             }
-            _ => {
-                log::error!("Could not find STX or ETX in frame: {:?}", frame);
-                Err("Invalid frame structure: missing STX or ETX".to_string())
+            Err(e @ AssemblyError::Retransmitted { .. }) => {
+                // Our ACK was lost; ACK the frame again without keeping its records twice
+                log::info!("{} from {}", e, connection.remote_addr);
+                if let Some(timeline) = connection.timeline.as_mut() {
+                    timeline.warn(ProcessingStage::Validated, e.to_string());
+                }
+                return Ok(None);
             }
+            Err(e) => return Err(e.to_string()),
+        };
+        for record in &records {
+            Self::parse_record_type(record)?;
         }
+
+        Ok(Some(records))
     }
 
-    /// Parses ASTM record type
-    fn parse_record_type(frame_data: &[u8]) -> Result<String, String> {
-        if frame_data.is_empty() {
-            return Err("Empty frame data".to_string());
+    /// Warns when the answer to a frame took longer than the analyzer's ACK budget
+    fn check_ack_time(connection: &mut Connection, frame_ended: Instant) {
+        let elapsed = frame_ended.elapsed();
+        if elapsed <= Duration::from_millis(connection.timeouts.ack_ms) {
+            return;
         }
+        let warning = format!(
+            "Frame answered after {} ms, over the {} ms ACK budget",
+            elapsed.as_millis(),
+            connection.timeouts.ack_ms
+        );
+        log::warn!("{} ({})", warning, connection.remote_addr);
+        if let Some(timeline) = connection.timeline.as_mut() {
+            timeline.warn(ProcessingStage::Acked, warning);
+        }
+    }
 
-        let first_char: char = frame_data[1] as char;
-        let record_type = match first_char {
-            'H' => "Header",
-            'P' => "Patient",
-            'O' => "Order",
-            'R' => "Result",
-            'C' => "Comment",
-            'Q' => "Request",
-            'L' => "Terminator",
-            _ => "Unknown",
-        };
+    /// Handles the records of an acknowledged frame: header delimiters, record events,
+    /// buffering for the complete message and progress reporting
+    async fn process_frame_records(
+        connection: &mut Connection,
+        records: Vec<Vec<u8>>,
+        event_sender: &mpsc::Sender<MerilEvent>,
+    ) {
+        for record in &records {
+            let Ok(record_type) = Self::parse_record_type(record) else {
+                continue;
+            };
+            log::debug!(
+                "Processed ASTM record: {} - {}",
+                record_type,
+                String::from_utf8_lossy(record)
+            );
 
-        log::debug!("Parsing record type: {}", record_type);
+            // The header declares the sender's delimiters and ASTM version, which later records are parsed with
+            if record_type == "Header" {
+                let text = String::from_utf8_lossy(record);
+                connection.delimiters = AstmDelimiters::from_header(&text).unwrap_or_else(|| {
+                    log::warn!("Invalid delimiter definition in ASTM header, using the defaults: {}", text);
+                    AstmDelimiters::default()
+                });
+                let header = Self::parse_header_record(record, &connection.delimiters);
+                connection.astm_version = AstmVersion::from_declared(header.version.as_deref());
+                // The analyzer dates the header as it sends it, so its offset from the
+                // moment it arrived is how far the analyzer's clock is off
+                if let Some(message_time) = header.message_time {
+                    let skew = (message_time - Utc::now()).num_seconds();
+                    log::debug!("Analyzer {} clock is {}s off server time", connection.analyzer_id, skew);
+                    connection.measured_clock_skew = Some(skew);
+                }
+                log::info!(
+                    "ASTM header from {}: sender {:?}, version {:?}",
+                    connection.remote_addr,
+                    header.sender_id,
+                    header.version
+                );
 
-        Ok(record_type.to_string())
+                let _ = event_sender
+                    .send(MerilEvent::HeaderReceived {
+                        analyzer_id: connection.analyzer_id.clone(),
+                        header,
+                        timestamp: Utc::now(),
+                    })
+                    .await;
+            }
+
+            // The analyzer asks for its worklist; the reply waits until it releases the line
+            if record_type == "Request" {
+                let query = Self::parse_query_record(record, &connection.delimiters);
+                log::info!(
+                    "Host query from {} for specimens {:?}",
+                    connection.remote_addr,
+                    query.specimen_range()
+                );
+                connection.pending_queries.push(query.clone());
+
+                let _ = event_sender
+                    .send(MerilEvent::HostQueryReceived {
+                        analyzer_id: connection.analyzer_id.clone(),
+                        query,
+                        timestamp: Utc::now(),
+                    })
+                    .await;
+            }
+
+            // Send event
+            let _ = event_sender
+                .send(MerilEvent::AstmMessageReceived {
+                    analyzer_id: connection.analyzer_id.clone(),
+                    message_type: record_type,
+                    raw_data: String::from_utf8_lossy(record).to_string(),
+                    timestamp: Utc::now(),
+                })
+                .await;
+        }
+
+        // Store the completed frame and its records for later processing
+        connection
+            .frame_buffer
+            .push(connection.current_frame.clone());
+        connection.records.extend(records.iter().cloned());
+
+        Self::report_progress(connection, &records, event_sender).await;
     }
 
-    /// Gets service status
-    pub async fn get_status(&self) -> AnalyzerStatus {
-        if *self.is_running.read().await {
-            AnalyzerStatus::Active
-        } else {
-            AnalyzerStatus::Inactive
+    /// Updates the transmission counts for a received frame and reports them, along
+    /// with the frame's results as provisional when the analyzer streams results
+    async fn report_progress(
+        connection: &mut Connection,
+        records: &[Vec<u8>],
+        event_sender: &mpsc::Sender<MerilEvent>,
+    ) {
+        connection.progress.frames_received += 1;
+        for record in records {
+            match Self::parse_record_type(record).as_deref() {
+                Ok("Patient") => connection.progress.patients_seen += 1,
+                Ok("Result") => {
+                    let parsed = Self::parse_result_record(
+                        record,
+                        connection.astm_version,
+                        &connection.delimiters,
+                        Self::clock_skew(connection),
+                    );
+                    if let Ok(mut result) = parsed {
+                        connection.progress.results_parsed += 1;
+
+                        if connection.provisional_results {
+                            result.analyzer_id = Some(connection.analyzer_id.clone());
+                            Self::apply_dilution_mode(&mut result, connection.dilution_mode);
+                            let _ = event_sender
+                                .send(MerilEvent::ProvisionalResult {
+                                    analyzer_id: connection.analyzer_id.clone(),
+                                    transmission_id: connection.progress.transmission_id.clone(),
+                                    result,
+                                    timestamp: Utc::now(),
+                                })
+                                .await;
+                        }
+                    }
+                }
+                _ => {}
+            }
         }
+
+        let _ = event_sender
+            .send(MerilEvent::TransmissionProgress {
+                analyzer_id: connection.analyzer_id.clone(),
+                progress: connection.progress.clone(),
+                timestamp: Utc::now(),
+            })
+            .await;
     }
 
-    /// Gets active connections count
-    pub async fn get_connections_count(&self) -> usize {
-        self.connections.read().await.len()
+    /// Resets the per-transmission state when a transmission starts
+    fn begin_transmission(connection: &mut Connection) {
+        connection.timeline = Some(ProcessingTimeline::started_at(Utc::now()));
+        connection.progress = TransmissionProgress::start();
+        connection.transmission_started = Some(Instant::now());
+        connection.session_outcome = None;
+        connection.assembler = MessageAssembler::default();
+        connection.records.clear();
+        connection.frame_naks = 0;
+        connection.measured_clock_skew = None;
     }
 
-    /// Gets the current analyzer configuration
-    pub async fn get_analyzer_config(&self) -> Analyzer {
-        self.analyzer.read().await.clone()
+    /// Seconds the analyzer's clock runs ahead of server time: measured from the header
+    /// of the transmission when it is dated, the configured skew otherwise
+    fn clock_skew(connection: &Connection) -> i64 {
+        connection.measured_clock_skew.unwrap_or(connection.clock_skew_seconds)
     }
 
-    /// Parses a patient record from ASTM data
-    fn parse_patient_record(frame_data: &[u8]) -> Result<PatientData, String> {
-        let data_str = String::from_utf8_lossy(frame_data);
-        let fields: Vec<&str> = data_str.split('|').collect();
+    /// Answers the EOT of a transmission. In protective mode the ACK waits until its
+    /// results are reported stored; if they are not, the EOT is NAKed (ACKed in shadow
+    /// mode) and the transmission ends in error, so the analyzer keeps the results.
+    async fn acknowledge_eot(connection: &mut Connection) -> Result<(), String> {
+        for receipt in std::mem::take(&mut connection.pending_receipts) {
+            let Err(e) = wait_until_stored(receipt, STORE_CONFIRMATION_TIMEOUT).await else {
+                continue;
+            };
+            log::error!("Results from {} were not stored: {}", connection.remote_addr, e);
+            connection.session_outcome = Some(ConnectionTermination::Error);
+            if connection.shadow_mode.is_enabled() {
+                return Self::send_control(connection, ASTM_ACK, "ACK for EOT").await;
+            }
+            return Self::send_control(connection, ASTM_NAK, "NAK for EOT").await;
+        }
+        connection.session_outcome = Some(ConnectionTermination::Completed);
+        Self::send_control(connection, ASTM_ACK, "ACK for EOT").await
+    }
 
-        if fields.len() < 2 {
-            return Err("Invalid patient record format".to_string());
+    /// Processes a complete ASTM transmission, message by message
+    async fn process_complete_message(
+        connection: &mut Connection,
+        event_sender: &mpsc::Sender<MerilEvent>,
+    ) -> Result<(), String> {
+        // Errors and warnings below are never sampled
+        if connection.log_sampler.sample() {
+            log::info!(
+                "Processing complete ASTM message from {}",
+                connection.remote_addr
+            );
         }
 
-        // Parse patient name (field 6) - format: LastName^FirstName^MiddleName^Title
-        let name_parts: Vec<&str> = fields.get(6).unwrap_or(&"").split('^').collect();
-        let name = if name_parts.len() >= 2 {
-            format!(
-                "{} {}",
-                name_parts.get(1).unwrap_or(&""),
-                name_parts.get(0).unwrap_or(&"")
-            )
-        } else {
-            fields.get(6).unwrap_or(&"").to_string()
-        };
+        // Records were joined across ETB frames as the frames arrived; a record whose
+        // final frame never came is incomplete and dropped
+        let assembled = std::mem::take(&mut connection.records);
+        if std::mem::take(&mut connection.assembler).is_pending() {
+            log::warn!(
+                "Transmission from {} ended inside a record split across frames, discarding it",
+                connection.remote_addr
+            );
+            if let Some(timeline) = connection.timeline.as_mut() {
+                timeline.warn(ProcessingStage::Framed, "Incomplete record discarded: no final (ETX) frame");
+            }
+        }
 
-        Ok(PatientData {
-            id: fields.get(3).unwrap_or(&"").to_string(),
-            name,
-            birth_date: fields.get(8).map(|s| s.to_string()),
-            sex: fields.get(9).map(|s| s.to_string()),
-            address: fields.get(11).map(|s| s.to_string()),
-            telephone: fields.get(13).map(|s| s.to_string()),
-            physicians: fields.get(14).map(|s| s.to_string()),
-            height: fields.get(17).map(|s| s.to_string()),
-            weight: fields.get(18).map(|s| s.to_string()),
-        })
+        // ASTM allows several H…L messages in one transmission; each is processed on its own
+        let timeline = connection
+            .timeline
+            .take()
+            .unwrap_or_else(|| ProcessingTimeline::started_at(Utc::now()));
+        for message in Self::split_messages(&assembled) {
+            Self::process_message(connection, message, timeline.clone(), event_sender).await?;
+        }
+        Ok(())
     }
 
-    /// Parses a result record from ASTM data
-    fn parse_result_record(frame_data: &[u8]) -> Result<TestResult, String> {
-        let data_str = String::from_utf8_lossy(frame_data);
-        let fields: Vec<&str> = data_str.split('|').collect();
+    /// Splits a transmission's records into messages, each starting at a header (H)
+    /// record. Records before the first header form a message of their own.
+    fn split_messages(records: &[Vec<u8>]) -> Vec<&[Vec<u8>]> {
+        let mut messages = Vec::new();
+        let mut start = 0;
+        for (index, record) in records.iter().enumerate() {
+            if index > start && Self::parse_record_type(record).ok().as_deref() == Some("Header") {
+                messages.push(&records[start..index]);
+                start = index;
+            }
+        }
+        if start < records.len() || messages.is_empty() {
+            messages.push(&records[start..]);
+        }
+        messages
+    }
 
-        if fields.len() < 4 {
-            return Err("Invalid result record format".to_string());
+    /// Parses one message of a transmission and reports its results. A message whose
+    /// structure is broken is still reported, with its results held for review.
+    async fn process_message(
+        connection: &mut Connection,
+        assembled: &[Vec<u8>],
+        mut timeline: ProcessingTimeline,
+        event_sender: &mpsc::Sender<MerilEvent>,
+    ) -> Result<(), String> {
+        // Parse the records to extract patient and test result data
+        let mut patient_data: Option<PatientData> = None;
+        let mut test_results = Vec::new();
+        let mut records = Vec::new();
+        // H.3 of the header governing the records that follow it
+        let mut control_id: Option<String> = None;
+        // Comment records belong to the record before them: a result, or the patient
+        let mut comment_target = CommentTarget::None;
+
+        for record in assembled {
+            records.push(String::from_utf8_lossy(record).to_string());
+            let record_type = Self::parse_record_type(record)?;
+
+            match record_type.as_str() {
+                "Header" => {
+                    comment_target = CommentTarget::None;
+                    control_id = Self::parse_header_record(record, &connection.delimiters).control_id;
+                }
+                "Patient" => {
+                    let parsed = Self::parse_patient_record(record, &connection.delimiters, &connection.sex_codes);
+                    comment_target = CommentTarget::None;
+                    if let Ok(patient) = parsed {
+                        log::debug!("Patient data: {:?}", patient);
+                        patient_data = Some(patient);
+                        comment_target = CommentTarget::Patient;
+                    }
+                }
+                "Result" => {
+                    let parsed = Self::parse_result_record(
+                        record,
+                        connection.astm_version,
+                        &connection.delimiters,
+                        Self::clock_skew(connection),
+                    );
+                    comment_target = CommentTarget::None;
+                    if let Ok(mut result) = parsed {
+                        if let Some(code) = &result.metadata.unrecognized_status {
+                            let warning = format!("{} has unknown result status '{}', held for review", result.test_id, code);
+                            log::warn!("Result from {}: {}", connection.remote_addr, warning);
+                            timeline.warn(ProcessingStage::Parsed, warning);
+                        }
+                        result.analyzer_id = Some(connection.analyzer_id.clone());
+                        result.metadata.source_message_control_id = control_id.clone();
+                        Self::apply_dilution_mode(&mut result, connection.dilution_mode);
+                        test_results.push(result);
+                        comment_target = CommentTarget::Result(test_results.len() - 1);
+                    }
+                }
+                "Comment" => {
+                    let Some(comment) = Self::parse_comment_record(record, &connection.delimiters) else {
+                        continue;
+                    };
+                    match comment_target {
+                        CommentTarget::Patient => {
+                            if let Some(patient) = patient_data.as_mut() {
+                                patient.comments.push(comment);
+                            }
+                        }
+                        CommentTarget::Result(index) => test_results[index].comments.push(comment),
+                        CommentTarget::None => log::debug!("Skipping comment on no stored record: {}", comment),
+                    }
+                }
+                _ => {
+                    comment_target = CommentTarget::None;
+                    // Log other record types for debugging
+                    log::debug!("Skipping record type: {}", record_type);
+                }
+            }
         }
 
-        // Parse test ID (field 3) - format: ^^^TEST_NAME
-        let test_id_parts: Vec<&str> = fields.get(3).unwrap_or(&"").split('^').collect();
-        let test_name = test_id_parts.last().unwrap_or(&"").to_string();
+        timeline.mark(ProcessingStage::Parsed);
 
-        // Parse reference range (field 6) - format: lower^upper
-        let reference_range = fields.get(6).and_then(|range_str| {
-            if !range_str.is_empty() {
-                let parts: Vec<&str> = range_str.split('^').collect();
-                if parts.len() >= 2 {
-                    Some(format!("{}-{}", parts[0], parts[1]))
-                } else {
-                    Some(range_str.to_string())
-                }
-            } else {
+        // Spec deviations are recorded on the audit row but never reject the transmission;
+        // in conformance-report mode they hold the message for review
+        let conformance_warnings = check_astm(&records);
+        let conformance_detail =
+            conformance_review_detail(&*connection.analyzer.read().await, &conformance_warnings, Utc::now());
+        for warning in &conformance_warnings {
+            log::warn!(
+                "ASTM conformance {} from {}: {}",
+                warning.rule_id,
+                connection.remote_addr,
+                warning.detail
+            );
+            timeline.warn(
+                ProcessingStage::Validated,
+                format!("{}: {}", warning.rule_id, warning.detail),
+            );
+        }
+        if !conformance_warnings.is_empty() {
+            let _ = event_sender
+                .send(MerilEvent::ConformanceWarnings {
+                    analyzer_id: connection.analyzer_id.clone(),
+                    transmission_id: connection.progress.transmission_id.clone(),
+                    warnings: conformance_warnings,
+                    timestamp: Utc::now(),
+                })
+                .await;
+        }
+
+        // One terminator must close the message: a missing one is tolerated, extra ones or
+        // records after it hold the message for review. The frames were all ACKed, so the
+        // analyzer counts the data as delivered and it must not be dropped.
+        let review_detail = match Self::check_terminator(&records) {
+            Ok(None) => None,
+            Ok(Some(warning)) => {
+                log::warn!("Message from {} accepted with {}", connection.remote_addr, warning);
+                timeline.warn(ProcessingStage::Validated, warning);
                 None
             }
-        });
-
-        // Parse flags (field 7)
-        let flags = fields
-            .get(7)
-            .map(|flag_str| {
-                if !flag_str.is_empty() {
-                    vec![flag_str.to_string()]
-                } else {
-                    vec![]
-                }
-            })
-            .unwrap_or_default();
+            Err(error) => {
+                log::warn!("Holding message from {} for review: {}", connection.remote_addr, error);
+                timeline.warn(ProcessingStage::Validated, format!("Held for review: {}", error));
+                Some(format!("Malformed message: {}", error))
+            }
+        };
+        let review_detail = review_detail.or(conformance_detail);
 
+        // Results dated far from server time come from a wrong analyzer clock and would pollute trends
         let now = Utc::now();
-        Ok(TestResult {
-            id: format!("result_{}", now.timestamp()),
-            test_id: test_name.clone(),
-            sample_id: fields.get(2).unwrap_or(&"").to_string(), // Sequence number as sample ID
-            value: fields.get(4).unwrap_or(&"").to_string(),
-            units: fields.get(5).map(|s| s.to_string()),
+        let mut accepted = Vec::with_capacity(test_results.len());
+        for result in test_results {
+            let rejection = result
+                .completed_date_time
+                .and_then(|completed_at| connection.result_acceptance.rejection(completed_at, now));
+            let Some(reason) = rejection else {
+                accepted.push(result);
+                continue;
+            };
+            log::warn!(
+                "Rejected {} result for sample {} from {}: {}",
+                result.test_id,
+                result.sample_id,
+                connection.remote_addr,
+                reason
+            );
+            timeline.warn(ProcessingStage::Validated, format!("{} rejected: {}", result.test_id, reason));
+            let _ = event_sender
+                .send(MerilEvent::RejectedStaleResult {
+                    analyzer_id: connection.analyzer_id.clone(),
+                    transmission_id: connection.progress.transmission_id.clone(),
+                    result,
+                    reason,
+                    timestamp: now,
+                })
+                .await;
+        }
+        let test_results = accepted;
+
+        // Checksum failures were attached while framing; the message is still accepted
+        timeline.mark(ProcessingStage::Validated);
+
+        // In protective mode the EOT ACK waits for ingestion to store the results
+        let store_receipt = if connection.protective_mode.is_enabled() {
+            let (receipt, stored) = StoreReceipt::requested();
+            connection.pending_receipts.push(stored);
+            receipt
+        } else {
+            StoreReceipt::default()
+        };
+
+        // Send the processed data as an event
+        let _ = event_sender
+            .send(MerilEvent::LabResultProcessed {
+                analyzer_id: connection.analyzer_id.clone(),
+                remote_addr: connection.remote_addr.to_string(),
+                connection_id: connection.conversation.connection_id().to_string(),
+                transmission_id: connection.progress.transmission_id.clone(),
+                patient_id: patient_data.as_ref().map(|p| p.id.clone()),
+                patient_data,
+                patient_class: connection.default_patient_class.clone(),
+                test_results,
+                raw_data: records.join("\r"),
+                timeline,
+                review_detail,
+                arrival_sequence: next_arrival_sequence(),
+                store_receipt,
+                timestamp: Utc::now(),
+            })
+            .await;
+
+        Ok(())
+    }
+
+    /// Decodes a delimited-text dump and reports its results, one message per sample.
+    /// Lines the decoder rejects, e.g. without a completion time, are reported as errors
+    /// and the rest of the dump is still processed.
+    async fn process_text_message(connection: &mut Connection, event_sender: &mpsc::Sender<MerilEvent>) {
+        let Some(decoder) = connection.decoder.clone() else {
+            return;
+        };
+        let message = std::mem::take(&mut connection.text_message);
+        if message.iter().all(u8::is_ascii_whitespace) {
+            return;
+        }
+
+        let mut timeline = ProcessingTimeline::started_at(Utc::now());
+        connection.progress = TransmissionProgress::start();
+        let mut samples: Vec<(String, Vec<TestResult>)> = Vec::new();
+        for decoded in decoder.decode(&message) {
+            match decoded {
+                Ok(mut result) => {
+                    result.analyzer_id = Some(connection.analyzer_id.clone());
+                    Self::apply_dilution_mode(&mut result, connection.dilution_mode);
+                    match samples.iter_mut().find(|(sample_id, _)| *sample_id == result.sample_id) {
+                        Some((_, results)) => results.push(result),
+                        None => samples.push((result.sample_id.clone(), vec![result])),
+                    }
+                }
+                Err(reason) => {
+                    log::warn!("Rejected {} line from {}: {}", decoder.protocol(), connection.remote_addr, reason);
+                    timeline.warn(ProcessingStage::Parsed, format!("Rejected: {}", reason));
+                    let _ = event_sender
+                        .send(MerilEvent::Error {
+                            analyzer_id: connection.analyzer_id.clone(),
+                            error: format!("Rejected {} line: {}", decoder.protocol(), reason),
+                            timestamp: Utc::now(),
+                        })
+                        .await;
+                }
+            }
+        }
+        timeline.mark(ProcessingStage::Parsed);
+        timeline.mark(ProcessingStage::Validated);
+
+        let raw_data = String::from_utf8_lossy(&message).into_owned();
+        for (_, test_results) in samples {
+            let _ = event_sender
+                .send(MerilEvent::LabResultProcessed {
+                    analyzer_id: connection.analyzer_id.clone(),
+                    remote_addr: connection.remote_addr.to_string(),
+                    connection_id: connection.conversation.connection_id().to_string(),
+                    transmission_id: connection.progress.transmission_id.clone(),
+                    patient_id: None,
+                    patient_data: None,
+                    patient_class: connection.default_patient_class.clone(),
+                    test_results,
+                    raw_data: raw_data.clone(),
+                    timeline: timeline.clone(),
+                    review_detail: None,
+                    arrival_sequence: next_arrival_sequence(),
+                    store_receipt: StoreReceipt::default(),
+                    timestamp: Utc::now(),
+                })
+                .await;
+        }
+    }
+
+    /// Checks that a message ends with exactly one terminator (L) record. Returns a
+    /// warning when it has none, and an error when it has several or records follow
+    /// it. A message without records needs no terminator.
+    fn check_terminator(records: &[String]) -> Result<Option<String>, String> {
+        let terminators: Vec<usize> = records
+            .iter()
+            .enumerate()
+            .filter(|(_, record)| record.trim_start_matches(|c: char| c.is_ascii_digit()).starts_with('L'))
+            .map(|(index, _)| index + 1)
+            .collect();
+
+        match terminators[..] {
+            [] if records.is_empty() => Ok(None),
+            [] => Ok(Some("no terminator record".to_string())),
+            [position] if position == records.len() => Ok(None),
+            [position] => Err(format!(
+                "{} records follow the terminator at position {}",
+                records.len() - position,
+                position
+            )),
+            _ => Err(format!("{} terminator records at positions {:?}", terminators.len(), terminators)),
+        }
+    }
+
+    /// Number of checksum characters received after the frame's ETX/ETB
+    fn checksum_chars_received(frame: &[u8]) -> usize {
+        frame
+            .iter()
+            .rposition(|&b| b == ASTM_ETX || b == ASTM_ETB)
+            .map(|end| frame.len() - end - 1)
+            .unwrap_or(0)
+    }
+
+    /// Checksum characters of a complete frame (between ETX/ETB and CR), and
+    /// whether a single space padded them before the CR
+    fn checksum_field(frame: &[u8]) -> Option<(&[u8], bool)> {
+        let end = frame.iter().rposition(|&b| b == ASTM_ETX || b == ASTM_ETB)?;
+        let field = frame.get(end + 1..frame.len().checked_sub(2)?)?;
+        Some(match field.strip_suffix(b" ") {
+            Some(chars) => (chars, true),
+            None => (field, false),
+        })
+    }
+
+    /// Validates ASTM frame checksum
+    fn validate_checksum(frame: &[u8]) -> bool {
+        if frame.len() < 6 {
+            return false;
+        }
+
+        // ASTM frame format: STX + FrameNumber + Data + ETX/ETB + Checksum (2 hex chars) + CR + LF
+        let Some(end) = frame.iter().rposition(|&b| b == ASTM_ETX || b == ASTM_ETB) else {
+            return false;
+        };
+        let sum = checksum(&frame[1..=end]);
+
+        let actual_checksum = Self::checksum_field(frame).and_then(|(chars, _)| parse_checksum(chars));
+
+        log::debug!(
+            "Checksum validation: expected={:02X}, actual={:?}, valid={}",
+            sum,
+            actual_checksum,
+            actual_checksum == Some(sum)
+        );
+
+        actual_checksum == Some(sum)
+    }
+
+    /// Parses ASTM record type
+    fn parse_record_type(frame_data: &[u8]) -> Result<String, String> {
+        if frame_data.len() < 2 {
+            return Err("Empty frame data".to_string());
+        }
+
+        let first_char: char = frame_data[1] as char;
+        let record_type = match first_char {
+            'H' => "Header",
+            'P' => "Patient",
+            'O' => "Order",
+            'R' => "Result",
+            'C' => "Comment",
+            'Q' => "Request",
+            'L' => "Terminator",
+            _ => "Unknown",
+        };
+
+        log::debug!("Parsing record type: {}", record_type);
+
+        Ok(record_type.to_string())
+    }
+
+    /// Queues an order for the next host bid for the line
+    pub async fn queue_host_order(&self, order: TestOrder) {
+        log::info!("Queued order {} for host-initiated download", order.id);
+        self.host_outbox.lock().await.push_back(order);
+    }
+
+    /// Bids for the line on the live connection and sends the queued orders.
+    /// Returns the number of orders sent.
+    pub async fn send_host_orders(&self) -> Result<usize, String> {
+        let analyzer_id = self.analyzer.read().await.id.clone();
+        let mut connections = self.connections.write().await;
+        let connection = connections
+            .get_mut(&analyzer_id)
+            .ok_or_else(|| format!("Analyzer {} is not connected", analyzer_id))?;
+        Self::bid_for_line(connection, &self.host_outbox, &self.event_sender).await
+    }
+
+    /// Downloads a patient's orders to the live connection as the line's sender and
+    /// reports the outcome as `OrdersSent` or `OrdersSendFailed`
+    pub async fn send_message(&self, patient: &Patient, orders: &[TestOrder]) -> Result<(), String> {
+        let analyzer_id = self.analyzer.read().await.id.clone();
+        let outcome = {
+            let mut connections = self.connections.write().await;
+            match connections.get_mut(&analyzer_id) {
+                Some(connection) => {
+                    Self::download_patient_orders(connection, patient, orders, &self.event_sender).await
+                }
+                None => Err(format!("Analyzer {} is not connected", analyzer_id)),
+            }
+        };
+
+        let order_ids = orders.iter().map(|order| order.id.clone()).collect();
+        let event = match &outcome {
+            Ok(()) => MerilEvent::OrdersSent {
+                analyzer_id,
+                patient_id: patient.id.clone(),
+                order_ids,
+                timestamp: Utc::now(),
+            },
+            Err(error) => MerilEvent::OrdersSendFailed {
+                analyzer_id,
+                patient_id: patient.id.clone(),
+                order_ids,
+                error: error.clone(),
+                timestamp: Utc::now(),
+            },
+        };
+        let _ = self.event_sender.send(event).await;
+        outcome
+    }
+
+    /// Sends hand-written records to the live connection (developer console)
+    pub async fn send_raw_records(&self, records: &[String], window: Duration) -> Result<RawExchange, String> {
+        let analyzer_id = self.analyzer.read().await.id.clone();
+        let mut connections = self.connections.write().await;
+        let connection = connections
+            .get_mut(&analyzer_id)
+            .ok_or_else(|| format!("Analyzer {} is not connected", analyzer_id))?;
+        Self::exchange_raw_records(connection, records, window, &self.event_sender).await
+    }
+
+    /// Number of orders waiting for a host bid
+    pub async fn host_outbox_len(&self) -> usize {
+        self.host_outbox.lock().await.len()
+    }
+
+    /// Gets service status
+    pub async fn get_status(&self) -> AnalyzerStatus {
+        if *self.is_running.read().await {
+            AnalyzerStatus::Active
+        } else {
+            AnalyzerStatus::Inactive
+        }
+    }
+
+    /// Gets active connections count
+    pub async fn get_connections_count(&self) -> usize {
+        self.connections.read().await.len()
+    }
+
+    /// Gets the current analyzer configuration
+    pub async fn get_analyzer_config(&self) -> Analyzer {
+        self.analyzer.read().await.clone()
+    }
+
+    /// Stores the sender and version declared in a header record against the analyzer.
+    /// Returns true if they changed.
+    pub async fn record_astm_header(&self, header: &AstmHeader) -> Result<bool, String> {
+        {
+            let mut analyzer = self.analyzer.write().await;
+            if analyzer.astm_sender_id == header.sender_id && analyzer.astm_version == header.version {
+                return Ok(false);
+            }
+
+            log::info!(
+                "Analyzer {} declares ASTM sender {:?}, version {:?}",
+                analyzer.id,
+                header.sender_id,
+                header.version
+            );
+            analyzer.astm_sender_id = header.sender_id.clone();
+            analyzer.astm_version = header.version.clone();
+            analyzer.updated_at = Utc::now();
+        }
+
+        self.save_analyzer_to_store().await?;
+        Ok(true)
+    }
+
+    /// Compares the identity the instrument declared with the last one and stores it if new.
+    /// Returns the change, if any; see [`firmware_tracking::record_identity`].
+    pub async fn record_identity(
+        &self,
+        identity: AnalyzerIdentity,
+        conformance_mode: bool,
+    ) -> Result<Option<FirmwareChange>, String> {
+        let observation =
+            firmware_tracking::record_identity(&self.analyzer, &self.log_sampler, identity, conformance_mode).await;
+        if observation != IdentityObservation::Unchanged {
+            self.save_analyzer_to_store().await?;
+        }
+        Ok(observation.into_change())
+    }
+
+    /// Sets whether the analyzer starts with the LIS and persists it
+    pub async fn set_activate_on_start(&self, enabled: bool) -> Result<(), String> {
+        {
+            let mut analyzer = self.analyzer.write().await;
+            analyzer.activate_on_start = enabled;
+            analyzer.updated_at = Utc::now();
+        }
+        self.save_analyzer_to_store().await
+    }
+
+    /// Queues the analyzer's current status for persistence
+    async fn persist_status(&self) -> Result<(), String> {
+        self.status_persister.record(&*self.analyzer.read().await)
+    }
+
+    /// Records that the analyzer connected at `at`
+    pub async fn record_connected(&self, at: DateTime<Utc>) -> Result<(), String> {
+        self.analyzer.write().await.last_connected_at = Some(at);
+        self.persist_status().await
+    }
+
+    /// Writes any status change still waiting for its interval, e.g. on shutdown
+    pub fn flush_status(&self) -> Result<(), String> {
+        self.status_persister.flush()
+    }
+
+    /// Parses the control id (H.3), sender id (H.5), version (H.13) and message date (H.14)
+    /// from a header record
+    fn parse_header_record(frame_data: &[u8], delimiters: &AstmDelimiters) -> AstmHeader {
+        let data_str = String::from_utf8_lossy(frame_data);
+        let fields = delimiters.split_fields(&data_str);
+        let field = |index: usize| {
+            fields
+                .get(index)
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .map(|value| delimiters.unescape(value))
+        };
+
+        AstmHeader {
+            control_id: field(2),
+            sender_id: field(4),
+            version: field(12),
+            message_time: field(13).and_then(|date| {
+                NaiveDateTime::parse_from_str(date.get(..14)?, "%Y%m%d%H%M%S")
+                    .ok()
+                    .map(|naive| naive.and_utc())
+            }),
+        }
+    }
+
+    /// Parses the specimen range (Q.3/Q.4) of a host query record. The specimen id is
+    /// the second component of a range field (the first when only one is sent), and
+    /// ALL or an empty range asks for every pending order.
+    fn parse_query_record(frame_data: &[u8], delimiters: &AstmDelimiters) -> HostQuery {
+        let data_str = String::from_utf8_lossy(frame_data);
+        let fields = delimiters.split_fields(&data_str);
+        let raw = |index: usize| fields.get(index).map(|value| value.trim().to_string()).unwrap_or_default();
+        let specimen_id = |range: &str| {
+            let components = delimiters.split_components(range);
+            components
+                .get(1)
+                .filter(|id| !id.trim().is_empty())
+                .or_else(|| components.first())
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty() && !id.eq_ignore_ascii_case("ALL"))
+        };
+
+        let starting_range = raw(2);
+        let ending_range = raw(3);
+        let first_specimen_id = specimen_id(&starting_range);
+        let last_specimen_id = first_specimen_id.as_ref().and_then(|_| specimen_id(&ending_range));
+        HostQuery {
+            starting_range,
+            ending_range,
+            first_specimen_id,
+            last_specimen_id,
+        }
+    }
+
+    /// Reads the text (C.4) of a comment record, its components joined by spaces.
+    /// Returns None for a comment without text.
+    fn parse_comment_record(frame_data: &[u8], delimiters: &AstmDelimiters) -> Option<String> {
+        let data_str = String::from_utf8_lossy(frame_data);
+        let fields = delimiters.split_fields(&data_str);
+        let text = delimiters
+            .split_components(fields.get(3)?)
+            .iter()
+            .map(|component| component.trim())
+            .filter(|component| !component.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Parses a patient record from ASTM data, rewriting configured sex codes to M/F/U
+    fn parse_patient_record(
+        frame_data: &[u8],
+        delimiters: &AstmDelimiters,
+        sex_codes: &SexCodeMap,
+    ) -> Result<PatientData, String> {
+        let data_str = String::from_utf8_lossy(frame_data);
+        let fields = delimiters.split_fields(&data_str);
+
+        if fields.len() < 2 {
+            return Err("Invalid patient record format".to_string());
+        }
+
+        // Fields as sent, for splitting into components, and decoded
+        let raw = |index: usize| fields.get(index).map(String::as_str);
+        let field = |index: usize| raw(index).map(|value| delimiters.unescape(value));
+
+        // Parse patient name (field 6) - format: LastName^FirstName^MiddleName^Title
+        let name_parts = delimiters.split_components(raw(6).unwrap_or(""));
+        let name = if name_parts.len() >= 2 {
+            format!("{} {}", name_parts[1], name_parts[0])
+        } else {
+            field(6).unwrap_or_default()
+        };
+
+        Ok(PatientData {
+            id: field(3).unwrap_or_default(),
+            name,
+            birth_date: field(8),
+            sex: field(9).map(|code| sex_codes.normalize(&code)),
+            address: field(11),
+            telephone: field(13),
+            physicians: field(14),
+            height: field(17),
+            weight: field(18),
+            structured_address: astm_address(raw(11).unwrap_or(""), delimiters),
+            contacts: astm_contacts(raw(13).unwrap_or(""), delimiters),
+            comments: Vec::new(),
+            structured_name: PatientName {
+                last_name: name_parts.first().cloned().filter(|part| !part.is_empty()),
+                first_name: name_parts.get(1).cloned().filter(|part| !part.is_empty()),
+                middle_name: name_parts.get(2).cloned().filter(|part| !part.is_empty()),
+                title: name_parts.get(3).cloned().filter(|part| !part.is_empty()),
+            },
+        })
+    }
+
+    /// Parses a result record from ASTM data. `clock_skew_seconds` is taken off the
+    /// reported completion date.
+    fn parse_result_record(
+        frame_data: &[u8],
+        version: AstmVersion,
+        delimiters: &AstmDelimiters,
+        clock_skew_seconds: i64,
+    ) -> Result<TestResult, String> {
+        let data_str = String::from_utf8_lossy(frame_data);
+        let fields = delimiters.split_fields(&data_str);
+
+        if fields.len() < 4 {
+            return Err("Invalid result record format".to_string());
+        }
+
+        // Fields as sent, for splitting into components, and decoded
+        let raw = |index: usize| fields.get(index).map(String::as_str);
+        let field = |index: usize| raw(index).map(|value| delimiters.unescape(value));
+
+        // Parse test ID (field 3) - format: ^^^TEST_NAME[^DILUTION]
+        let test_id_parts = delimiters.split_components(raw(3).unwrap_or(""));
+        let test_name = test_id_parts
+            .get(3)
+            .filter(|name| !name.is_empty())
+            .or_else(|| test_id_parts.last())
+            .cloned()
+            .unwrap_or_default();
+        let dilution_factor = test_id_parts.get(4).and_then(|factor| parse_dilution_factor(factor));
+
+        // Parse reference range (field 6) - format: lower^upper
+        let reference_range = raw(6).and_then(ReferenceRange::parse);
+
+        // Parse flags (field 7); LIS2-A2 senders repeat them with the repeat delimiter.
+        // E1394 flags are kept whole; nature of abnormality testing is field 8.
+        let abnormal_flags: Vec<String> = raw(7)
+            .map(|flag_str| match version {
+                AstmVersion::Lis2A2 => delimiters
+                    .split_repeats(flag_str)
+                    .iter()
+                    .filter(|flag| !flag.is_empty())
+                    .map(|flag| delimiters.unescape(flag))
+                    .collect(),
+                AstmVersion::E1394 if !flag_str.is_empty() => vec![delimiters.unescape(flag_str)],
+                AstmVersion::E1394 => vec![],
+            })
+            .unwrap_or_default();
+        let flags = ResultFlags::from_parts(&abnormal_flags, field(8).map(|nature| nature.trim().to_string()));
+
+        // Parse operator identification (field 11) - format: operator^verifier
+        let operator = raw(11)
+            .map(|op| delimiters.split_components(op))
+            .and_then(|parts| parts.into_iter().find(|part| !part.trim().is_empty()))
+            .map(|op| op.trim().to_string());
+
+        // Date and time the test was completed (field 13); the analyzer's clock, read as UTC
+        // and corrected by how far that clock is known to run ahead of server time
+        let completed_at = field(13).and_then(|date| {
+            NaiveDateTime::parse_from_str(date.trim().get(..14)?, "%Y%m%d%H%M%S")
+                .ok()
+                .map(|naive| naive.and_utc() - chrono::Duration::seconds(clock_skew_seconds))
+        });
+
+        // Parse instrument identification (field 14)
+        let instrument = field(14)
+            .map(|inst| inst.trim().to_string())
+            .filter(|inst| !inst.is_empty());
+
+        // Result status (field 9): F final, P preliminary, C correction. An unknown or
+        // missing code is never read as final; the result is kept as preliminary and
+        // held for review
+        let status_code = field(9).unwrap_or_default();
+        let (status, unrecognized_status) = match ResultStatus::from_astm_code(&status_code) {
+            Ok(status) => (status, None),
+            Err(_) => (ResultStatus::Preliminary, Some(status_code.trim().to_string())),
+        };
+
+        let now = Utc::now();
+        Ok(TestResult {
+            id: uuid::Uuid::new_v4().to_string(),
+            test_id: test_name,
+            sample_id: field(2).unwrap_or_default(), // Sequence number as sample ID
+            value: field(4).unwrap_or_default(),
+            units: field(5),
             reference_range,
             flags,
-            status: fields.get(9).unwrap_or(&"F").to_string(), // Result status (F=Final, P=Preliminary, C=Correction)
-            completed_date_time: Some(now),
+            status,
+            completed_date_time: Some(completed_at.unwrap_or(now)),
+            metadata: TestResultMetadata {
+                sequence_number: field(2).and_then(|s| s.parse().ok()).unwrap_or(1),
+                instrument,
+                operator,
+                dilution_factor,
+                raw_value: None,
+                source_message_control_id: None,
+                verification: None,
+                parameter_name: None,
+                panel: None,
+                analysis_mode: None,
+                unrecognized_status,
+            },
             analyzer_id: None, // Will be set by the caller
+            comments: Vec::new(),
             created_at: now,
             updated_at: now,
         })
     }
+
+    /// Applies the analyzer's dilution convention to a parsed result: pre-dilution
+    /// values are multiplied by the reported factor (keeping the raw value). Results
+    /// from a diluted run are flagged through their dilution factor (see `TestResult::flag_list`).
+    fn apply_dilution_mode(result: &mut TestResult, mode: DilutionMode) {
+        let factor = match result.metadata.dilution_factor {
+            Some(factor) => factor,
+            None => return,
+        };
+
+        let corrected = apply_dilution(&result.value, factor, mode);
+        if corrected != result.value {
+            result.metadata.raw_value = Some(std::mem::replace(&mut result.value, corrected));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::result::DILUTED_FLAG;
+    use crate::protocol::astm::encode_frame;
+    use crate::services::config_persistence::InMemoryConfigStore;
+
+    /// Builds a connection backed by a loopback socket, returning the peer end
+    async fn test_connection() -> (Connection, TcpStream) {
+        let (stream, remote_addr, peer) = crate::test_support::loopback_connection().await;
+
+        let connection = Connection {
+            stream,
+            remote_addr,
+            state: ConnectionState::WaitingForEnq,
+            frame_buffer: Vec::new(),
+            current_frame: Vec::new(),
+            assembler: MessageAssembler::default(),
+            records: Vec::new(),
+            analyzer_id: "test-analyzer".to_string(),
+            timeline: None,
+            shadow_mode: ShadowMode::default(),
+            protective_mode: ProtectiveMode::default(),
+            pending_receipts: Vec::new(),
+            dilution_mode: DilutionMode::PostDilution,
+            astm_version: AstmVersion::default(),
+            delimiters: AstmDelimiters::default(),
+            progress: TransmissionProgress::default(),
+            provisional_results: false,
+            conversation: ConversationLog::default().open("test-analyzer", "ASTM", remote_addr),
+            post_eot_delay: Duration::ZERO,
+            quiet_until: None,
+            timeouts: AstmTimeouts::default(),
+            transmission_started: None,
+            log_sampler: Arc::new(LogSampler::default()),
+            analyzer: Arc::new(RwLock::new(crate::test_support::meril_analyzer())),
+            accept_frames_without_enq: false,
+            host_initiated: false,
+            frame_retries: 6,
+            frame_naks: 0,
+            sex_codes: SexCodeMap::default(),
+            checksum_policy: ChecksumPolicy::default(),
+            clock_skew_seconds: 0,
+            measured_clock_skew: None,
+            result_acceptance: ResultAcceptanceWindow::default(),
+            default_patient_class: None,
+            order_repository: None,
+            pending_queries: Vec::new(),
+            session_outcome: None,
+            permit: None,
+            decoder: None,
+            text_message: Vec::new(),
+        };
+        (connection, peer)
+    }
+
+    fn parse_result(frame_data: &[u8], version: AstmVersion) -> TestResult {
+        AutoQuantMerilService::parse_result_record(frame_data, version, &AstmDelimiters::default(), 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_eot_during_frame_reading_ends_transmission() {
+        let (mut connection, mut peer) = test_connection().await;
+        let (sender, mut receiver) = mpsc::channel(10);
+
+        let mut data = vec![ASTM_ENQ, ASTM_STX];
+        data.extend_from_slice(b"1H|\\^&|||");
+        data.push(ASTM_EOT);
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+
+        assert!(matches!(connection.state, ConnectionState::WaitingForEnq));
+        assert!(connection.current_frame.is_empty());
+        assert!(connection.frame_buffer.is_empty());
+
+        // ACK for ENQ, then ACK for EOT
+        let mut replies = [0u8; 2];
+        peer.read_exact(&mut replies).await.unwrap();
+        assert_eq!(replies, [ASTM_ACK, ASTM_ACK]);
+
+        match receiver.try_recv() {
+            Ok(MerilEvent::LabResultProcessed { test_results, .. }) => assert!(test_results.is_empty()),
+            other => panic!("Expected LabResultProcessed, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_log_sampling_logs_every_tenth_message_at_info() {
+        crate::services::log_sampling::test_logger::install();
+        let (mut connection, _peer) = test_connection().await;
+        connection.log_sampler = Arc::new(LogSampler::new(10));
+        let (sender, _receiver) = mpsc::channel(100);
+
+        for _ in 0..30 {
+            AutoQuantMerilService::process_complete_message(&mut connection, &sender)
+                .await
+                .unwrap();
+        }
+
+        // The remote address is unique to this test, so parallel tests don't skew the count
+        let line = format!("Processing complete ASTM message from {}", connection.remote_addr);
+        assert_eq!(
+            crate::services::log_sampling::test_logger::count(log::Level::Info, &line),
+            3
+        );
+    }
+
+    /// Wraps record text in STX/ETX framing with its checksum
+    fn frame(text: &str) -> Vec<u8> {
+        let mut data = vec![ASTM_STX];
+        data.extend_from_slice(text.as_bytes());
+        data.push(ASTM_ETX);
+        let sum = checksum(&data[1..]);
+        data.extend_from_slice(format!("{:02X}", sum).as_bytes());
+        data.extend_from_slice(&[ASTM_CR, ASTM_LF]);
+        data
+    }
+
+    /// Wraps the first part of a record in STX/ETB framing with its checksum
+    fn intermediate_frame(text: &str) -> Vec<u8> {
+        let mut data = frame(text);
+        let etx = data.iter().rposition(|&b| b == ASTM_ETX).unwrap();
+        data.truncate(etx);
+        data.push(ASTM_ETB);
+        let sum = checksum(&data[1..]);
+        data.extend_from_slice(format!("{:02X}", sum).as_bytes());
+        data.extend_from_slice(&[ASTM_CR, ASTM_LF]);
+        data
+    }
+
+    #[tokio::test]
+    async fn test_result_record_split_across_frames_is_parsed_whole() {
+        let (mut connection, mut peer) = test_connection().await;
+        connection.provisional_results = true;
+        let (sender, mut receiver) = mpsc::channel(50);
+
+        // The value 95.2 is broken between the intermediate frame and the final frame
+        let mut data = vec![ASTM_ENQ];
+        data.extend(frame("1H|\\^&|||AutoQuant"));
+        data.extend(intermediate_frame("2R|1|S42|^^^GLU|9"));
+        data.extend(frame("35.2|mg/dL|70^110|N||F"));
+        data.extend(frame("4L|1|N"));
+        data.push(ASTM_EOT);
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+
+        // ENQ, every frame and the EOT acknowledged
+        let mut replies = [0u8; 6];
+        peer.read_exact(&mut replies).await.unwrap();
+        assert_eq!(replies, [ASTM_ACK; 6]);
+
+        let mut provisional = Vec::new();
+        let mut record_types = Vec::new();
+        let (results, raw_data) = loop {
+            match receiver.try_recv() {
+                Ok(MerilEvent::ProvisionalResult { result, .. }) => provisional.push(result),
+                Ok(MerilEvent::AstmMessageReceived { message_type, .. }) => record_types.push(message_type),
+                Ok(MerilEvent::LabResultProcessed { test_results, raw_data, .. }) => break (test_results, raw_data),
+                Ok(_) => continue,
+                Err(e) => panic!("Expected LabResultProcessed, got {:?}", e),
+            }
+        };
+
+        // One result record, not two broken ones
+        assert_eq!(record_types, vec!["Header", "Result", "Terminator"]);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].test_id, "GLU");
+        assert_eq!(results[0].value, "95.2");
+        assert_eq!(results[0].units.as_deref(), Some("mg/dL"));
+        assert_eq!(provisional.len(), 1);
+        assert_eq!(provisional[0].value, "95.2");
+        assert_eq!(raw_data, "1H|\\^&|||AutoQuant\r2R|1|S42|^^^GLU|95.2|mg/dL|70^110|N||F\r4L|1|N");
+    }
+
+    /// Sends a transmission of `records` and returns its events once the EOT is processed
+    async fn transmit(records: &[&str]) -> Vec<MerilEvent> {
+        let (mut connection, mut peer) = test_connection().await;
+        let (sender, mut receiver) = mpsc::channel(50);
+        let mut data = vec![ASTM_ENQ];
+        for record in records {
+            data.extend(frame(record));
+        }
+        data.push(ASTM_EOT);
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+
+        // The link layer answers every frame and the EOT whatever the records hold
+        let mut replies = vec![0u8; records.len() + 2];
+        peer.read_exact(&mut replies).await.unwrap();
+        assert!(replies.iter().all(|&reply| reply == ASTM_ACK));
+
+        let mut events = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event);
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn test_missing_terminator_warns_and_duplicate_holds_for_review() {
+        // No terminator: accepted, with a warning on the timeline
+        let events = transmit(&["1H|\\^&|||AutoQuant", "2R|1|S42|^^^GLU|95|mg/dL|70^110|N||F"]).await;
+        let (results, timeline, review_detail) = events
+            .into_iter()
+            .find_map(|event| match event {
+                MerilEvent::LabResultProcessed { test_results, timeline, review_detail, .. } => {
+                    Some((test_results, timeline, review_detail))
+                }
+                _ => None,
+            })
+            .expect("missing terminator is accepted");
+        assert_eq!(results.len(), 1);
+        assert_eq!(review_detail, None);
+        let warnings: Vec<String> = timeline.stages.iter().flat_map(|record| record.warnings.clone()).collect();
+        assert!(warnings.contains(&"no terminator record".to_string()));
+
+        // Two terminators: every frame was ACKed, so the message is kept with its results
+        // and raw data, and held for review
+        let events = transmit(&[
+            "1H|\\^&|||AutoQuant",
+            "2R|1|S42|^^^GLU|95|mg/dL|70^110|N||F",
+            "3L|1|N",
+            "4L|1|N",
+        ])
+        .await;
+        let (results, raw_data, review_detail) = events
+            .into_iter()
+            .find_map(|event| match event {
+                MerilEvent::LabResultProcessed { test_results, raw_data, review_detail, .. } => {
+                    Some((test_results, raw_data, review_detail))
+                }
+                _ => None,
+            })
+            .expect("duplicate terminator is kept");
+        assert_eq!(results.len(), 1);
+        assert!(raw_data.ends_with("3L|1|N\r4L|1|N"), "{}", raw_data);
+        assert_eq!(
+            review_detail.as_deref(),
+            Some("Malformed message: 2 terminator records at positions [3, 4]")
+        );
+
+        // Records after the terminator are malformed too; a lone terminator is fine
+        assert_eq!(
+            AutoQuantMerilService::check_terminator(&["1H|".to_string(), "2L|1".to_string(), "3R|1".to_string()]),
+            Err("1 records follow the terminator at position 2".to_string())
+        );
+        assert_eq!(AutoQuantMerilService::check_terminator(&["1H|".to_string(), "2L|1".to_string()]), Ok(None));
+        assert_eq!(AutoQuantMerilService::check_terminator(&[]), Ok(None));
+    }
+
+    #[tokio::test]
+    async fn test_each_message_of_a_transmission_is_processed_on_its_own() {
+        let events = transmit(&[
+            "1H|\\^&|MSG-1||AutoQuant",
+            "2P|1||P001||Doe^John",
+            "3R|1|S42|^^^GLU|95|mg/dL|70^110|N||F",
+            "4L|1|N",
+            "5H|\\^&|MSG-2||AutoQuant",
+            "6P|1||P002||Roe^Jane",
+            "7R|1|S43|^^^GLU|102|mg/dL|70^110|N||F",
+            "0L|1|N",
+        ])
+        .await;
+        let messages: Vec<(Option<String>, Vec<String>, String, Option<String>)> = events
+            .into_iter()
+            .filter_map(|event| match event {
+                MerilEvent::LabResultProcessed { patient_id, test_results, raw_data, review_detail, .. } => Some((
+                    patient_id,
+                    test_results.iter().map(|result| result.sample_id.clone()).collect(),
+                    raw_data,
+                    review_detail,
+                )),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    Some("P001".to_string()),
+                    vec!["S42".to_string()],
+                    "1H|\\^&|MSG-1||AutoQuant\r2P|1||P001||Doe^John\r3R|1|S42|^^^GLU|95|mg/dL|70^110|N||F\r4L|1|N".to_string(),
+                    None,
+                ),
+                (
+                    Some("P002".to_string()),
+                    vec!["S43".to_string()],
+                    "5H|\\^&|MSG-2||AutoQuant\r6P|1||P002||Roe^Jane\r7R|1|S43|^^^GLU|102|mg/dL|70^110|N||F\r0L|1|N".to_string(),
+                    None,
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_result_status_is_never_final() {
+        let events = transmit(&[
+            "1H|\\^&|||AutoQuant",
+            "2R|1|S42|^^^GLU|95|mg/dL|70^110|N||X",
+            "3R|2|S42|^^^CRE|0.9|mg/dL|0.6^1.2|N",
+            "4R|3|S42|^^^ALB|3.5|g/dL|3.4^5.4|N||c",
+            "5L|1|N",
+        ])
+        .await;
+        let (results, timeline) = events
+            .into_iter()
+            .find_map(|event| match event {
+                MerilEvent::LabResultProcessed { test_results, timeline, .. } => Some((test_results, timeline)),
+                _ => None,
+            })
+            .unwrap();
+
+        // Unknown and missing codes are kept as preliminary and flagged for review
+        let statuses: Vec<(ResultStatus, Option<&str>)> = results
+            .iter()
+            .map(|result| (result.status.clone(), result.metadata.unrecognized_status.as_deref()))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (ResultStatus::Preliminary, Some("X")),
+                (ResultStatus::Preliminary, Some("")),
+                (ResultStatus::Correction, None),
+            ]
+        );
+        let warnings: Vec<String> = timeline.stages.iter().flat_map(|record| record.warnings.clone()).collect();
+        assert!(warnings.contains(&"GLU has unknown result status 'X', held for review".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_clock_skew_is_measured_from_the_header_date() {
+        // The analyzer's clock runs an hour fast; it dated the header as it sent it and
+        // ran the test ten minutes before that by its own clock
+        let analyzer_now = Utc::now() + chrono::Duration::hours(1);
+        let header = format!("1H|\\^&|||AutoQuant|||||||P|E1394-97|{}", analyzer_now.format("%Y%m%d%H%M%S"));
+        let completed = (analyzer_now - chrono::Duration::minutes(10)).format("%Y%m%d%H%M%S");
+        let result = format!("2R|1|S42|^^^GLU|95|mg/dL|70^110|N||F||||{}", completed);
+        let events = transmit(&[&header, &result, "3L|1|N"]).await;
+
+        let results = events
+            .into_iter()
+            .find_map(|event| match event {
+                MerilEvent::LabResultProcessed { test_results, .. } => Some(test_results),
+                _ => None,
+            })
+            .unwrap();
+        let lag = Utc::now() - results[0].completed_date_time.unwrap();
+        assert!(
+            lag >= chrono::Duration::minutes(9) && lag <= chrono::Duration::minutes(11),
+            "completed {} before receipt",
+            lag
+        );
+    }
+
+    #[tokio::test]
+    async fn test_comment_records_attach_to_preceding_result_and_persist() {
+        use crate::app_state::AppState;
+        use crate::db::{establish_test_connection, SqliteRepository};
+        use crate::services::ingestion_lanes::ArrivalOrder;
+
+        let (mut connection, mut peer) = test_connection().await;
+        let (sender, mut receiver) = mpsc::channel(50);
+        let mut data = vec![ASTM_ENQ];
+        for record in [
+            "1H|\\^&|||AutoQuant",
+            "2P|1||PID001||Doe^John",
+            "3C|1|I|Fasting sample|G",
+            "4R|1|S42|^^^GLU|95|mg/dL|70^110|N||F",
+            "5C|1|I|Hemolyzed^rerun advised|I",
+            "6C|2|L|Checked by OP01|G",
+            "7R|2|S42|^^^ALB|4.1|g/dL|3.5^5.2|N||F",
+            "0L|1|N",
+        ] {
+            data.extend(frame(record));
+        }
+        data.push(ASTM_EOT);
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+        let mut replies = [0u8; 10];
+        peer.read_exact(&mut replies).await.unwrap();
+
+        let (patient_id, patient_data, results) = loop {
+            match receiver.try_recv() {
+                Ok(MerilEvent::LabResultProcessed {
+                    patient_id, patient_data, test_results, ..
+                }) => break (patient_id, patient_data, test_results),
+                Ok(_) => continue,
+                Err(e) => panic!("Expected LabResultProcessed, got {:?}", e),
+            }
+        };
+
+        assert_eq!(patient_data.as_ref().unwrap().comments, vec!["Fasting sample"]);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].comments, vec!["Hemolyzed rerun advised", "Checked by OP01"]);
+        assert!(results[1].comments.is_empty());
+
+        // Stored the way the ingestion lane stores them
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        AppState::<tauri::Wry>::store_meril_results(
+            &repository,
+            "S42",
+            patient_id.as_deref(),
+            patient_data.as_ref(),
+            &results,
+            ArrivalOrder::InOrder,
+        )
+        .await
+        .unwrap();
+        let patient = repository.get_patient("PID001").await.unwrap().unwrap();
+        assert_eq!(patient.comments, vec!["Fasting sample"]);
+        assert_eq!(patient.name.last_name.as_deref(), Some("Doe"));
+        assert_eq!(patient.name.first_name.as_deref(), Some("John"));
+        let stored = repository.get_patient_results("PID001").await.unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored.iter().find(|result| result.test_id == results[0].test_id).unwrap().comments, results[0].comments);
+    }
+
+    #[tokio::test]
+    async fn test_patient_address_and_telephone_are_stored_and_refreshed() {
+        use crate::app_state::AppState;
+        use crate::db::{establish_test_connection, SqliteRepository};
+        use crate::services::ingestion_lanes::ArrivalOrder;
+
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let store = |record: &'static [u8], sample_id: &'static str| {
+            let repository = &repository;
+            async move {
+                let patient =
+                    AutoQuantMerilService::parse_patient_record(record, &AstmDelimiters::default(), &SexCodeMap::default())
+                        .unwrap();
+                let mut result = parse_result(b"1R|1|S1|^^^GLU|95|mg/dL|70^110|N||F", AstmVersion::Lis2A2);
+                result.sample_id = sample_id.to_string();
+                AppState::<tauri::Wry>::store_meril_results(
+                    repository,
+                    sample_id,
+                    Some("PID001"),
+                    Some(&patient),
+                    &[result],
+                    ArrivalOrder::InOrder,
+                )
+                .await
+                .unwrap();
+            }
+        };
+
+        // First sight registers the patient with the P record's address and telephone
+        store(b"2P|1||PID001|||Doe^John||19800101|M||12 Main St^Pune^MH^411001||9876543210", "S1").await;
+        let patient = repository.get_patient("PID001").await.unwrap().unwrap();
+        let address = patient.address.unwrap();
+        assert_eq!(address.street.as_deref(), Some("12 Main St"));
+        assert_eq!(address.city.as_deref(), Some("Pune"));
+        assert_eq!(patient.contacts.len(), 1);
+        assert_eq!(patient.contacts[0].raw, "9876543210");
+
+        // A later message moves the patient; the telephone it does not carry is kept
+        store(b"2P|1||PID001|||Doe^John||19800101|M||4 Lake Rd^Nagpur^MH^440001", "S2").await;
+        let patient = repository.get_patient("PID001").await.unwrap().unwrap();
+        let address = patient.address.unwrap();
+        assert_eq!(address.street.as_deref(), Some("4 Lake Rd"));
+        assert_eq!(address.city.as_deref(), Some("Nagpur"));
+        assert_eq!(address.zip.as_deref(), Some("440001"));
+        assert_eq!(patient.contacts.len(), 1);
+        assert_eq!(patient.contacts[0].raw, "9876543210");
+    }
+
+    #[tokio::test]
+    async fn test_results_without_a_patient_wait_for_their_sample_to_be_identified() {
+        use crate::app_state::AppState;
+        use crate::db::{establish_test_connection, SqliteRepository};
+        use crate::services::ingestion_lanes::ArrivalOrder;
+
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let result = parse_result(b"1R|1|S7|^^^GLU|95|mg/dL|70^110|N||F", AstmVersion::Lis2A2);
+
+        // No P record: the result is held against its sample, not dropped
+        AppState::<tauri::Wry>::store_meril_results(
+            &repository,
+            "S7",
+            None,
+            None,
+            std::slice::from_ref(&result),
+            ArrivalOrder::InOrder,
+        )
+        .await
+        .unwrap();
+        assert_eq!(repository.get_unassigned_results("S7").await.unwrap(), vec![result.clone()]);
+
+        // A later message for the sample names the patient and both results join them
+        let rerun = TestResult { id: "R-rerun".to_string(), value: "97".to_string(), ..result.clone() };
+        let patient = PatientData { id: "PID9".to_string(), ..Default::default() };
+        AppState::<tauri::Wry>::store_meril_results(
+            &repository,
+            "S7",
+            Some("PID9"),
+            Some(&patient),
+            &[rerun],
+            ArrivalOrder::InOrder,
+        )
+        .await
+        .unwrap();
+        assert!(repository.get_unassigned_results("S7").await.unwrap().is_empty());
+        let mut values: Vec<String> = repository
+            .get_patient_results("PID9")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|stored| stored.value)
+            .collect();
+        values.sort();
+        assert_eq!(values, vec!["95", "97"]);
+    }
+
+    #[tokio::test]
+    async fn test_late_message_is_stored_behind_the_newer_result() {
+        use crate::app_state::AppState;
+        use crate::db::{establish_test_connection, SqliteRepository};
+        use crate::services::ingestion_lanes::ArrivalOrder;
+
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let patient = PatientData { id: "PID3".to_string(), ..Default::default() };
+        let store = |result: TestResult, order: ArrivalOrder| {
+            let (repository, patient) = (&repository, &patient);
+            async move {
+                AppState::<tauri::Wry>::store_meril_results(repository, "S3", Some("PID3"), Some(patient), &[result], order)
+                    .await
+                    .unwrap()
+            }
+        };
+        let first = parse_result(b"1R|1|S3|^^^GLU|90|mg/dL|70^110|N||F", AstmVersion::Lis2A2);
+        let corrected = TestResult {
+            id: "R-corrected".to_string(),
+            value: "97".to_string(),
+            status: ResultStatus::Correction,
+            ..first.clone()
+        };
+        let late = TestResult { id: "R-late".to_string(), value: "95".to_string(), ..first.clone() };
+
+        store(first.clone(), ArrivalOrder::InOrder).await;
+        store(corrected.clone(), ArrivalOrder::InOrder).await;
+        // Sent before the correction but ingested after it: kept, and never current
+        store(late.clone(), ArrivalOrder::OutOfOrder { latest: 2 }).await;
+
+        let chain = repository.get_result_chain("S3", "GLU").await.unwrap();
+        assert_eq!(
+            chain.iter().map(|result| result.id.as_str()).collect::<Vec<_>>(),
+            vec![first.id.as_str(), "R-late", "R-corrected"]
+        );
+        let current = repository.get_current_result("S3", "GLU").await.unwrap().unwrap();
+        assert_eq!(current.id, "R-corrected");
+    }
+
+    #[tokio::test]
+    async fn test_out_of_sequence_frame_is_naked_and_not_stored() {
+        let (mut connection, mut peer) = test_connection().await;
+        let (sender, _receiver) = mpsc::channel(50);
+
+        let mut data = vec![ASTM_ENQ];
+        data.extend(frame("1H|\\^&|||AutoQuant"));
+        data.extend(frame("3L|1|N"));
+        assert!(AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .is_err());
+
+        let mut replies = [0u8; 3];
+        peer.read_exact(&mut replies).await.unwrap();
+        assert_eq!(replies, [ASTM_ACK, ASTM_ACK, ASTM_NAK]);
+        assert_eq!(connection.frame_buffer.len(), 1);
+        assert!(matches!(connection.state, ConnectionState::WaitingForFrame));
+
+        // The frame in sequence is accepted when it follows
+        AutoQuantMerilService::process_astm_data(&mut connection, &frame("2L|1|N"), &sender)
+            .await
+            .unwrap();
+        assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+        assert_eq!(connection.records.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_retransmitted_frame_is_acked_and_not_stored_twice() {
+        let (mut connection, mut peer) = test_connection().await;
+        let (sender, mut receiver) = mpsc::channel(50);
+
+        // The analyzer missed the ACK for frame 2 and sends it again
+        let result = frame("2R|1|S42|^^^GLU|95|mg/dL|70^110|N||F");
+        let mut data = vec![ASTM_ENQ];
+        data.extend(frame("1H|\\^&|||AutoQuant"));
+        data.extend(result.clone());
+        data.extend(result);
+        data.extend(frame("3L|1|N"));
+        data.push(ASTM_EOT);
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+
+        assert_eq!(written_bytes(&mut peer).await, vec![ASTM_ACK; 6]);
+        assert_eq!(connection.progress.retransmissions_requested, 0);
+        let results = loop {
+            match receiver.try_recv() {
+                Ok(MerilEvent::LabResultProcessed { test_results, .. }) => break test_results,
+                Ok(_) => continue,
+                Err(e) => panic!("Expected LabResultProcessed, got {:?}", e),
+            }
+        };
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].value, "95");
+    }
+
+    #[tokio::test]
+    async fn test_frame_numbers_wrap_from_seven_to_zero() {
+        let (mut connection, mut peer) = test_connection().await;
+        let (sender, mut receiver) = mpsc::channel(50);
+
+        // Header, eight results and the terminator: frames 1..7, then 0, 1, 2
+        let mut records = vec!["H|\\^&|||AutoQuant".to_string()];
+        records.extend((1..=8).map(|n| format!("R|{n}|S42|^^^T{n}|{n}|mg/dL||N||F")));
+        records.push("L|1|N".to_string());
+        let mut data = vec![ASTM_ENQ];
+        for (index, record) in records.iter().enumerate() {
+            data.extend(encode_frame(((index + 1) % 8) as u8, record));
+        }
+        data.push(ASTM_EOT);
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+
+        assert_eq!(written_bytes(&mut peer).await, vec![ASTM_ACK; 12]);
+        let results = loop {
+            match receiver.try_recv() {
+                Ok(MerilEvent::LabResultProcessed { test_results, .. }) => break test_results,
+                Ok(_) => continue,
+                Err(e) => panic!("Expected LabResultProcessed, got {:?}", e),
+            }
+        };
+        let values: Vec<&str> = results.iter().map(|result| result.value.as_str()).collect();
+        assert_eq!(values, vec!["1", "2", "3", "4", "5", "6", "7", "8"]);
+    }
+
+    /// Feeds a byte stream to a fresh connection in chunks of the given size and
+    /// returns the records and results it parsed, and the replies it sent
+    async fn parse_in_chunks(stream: &[u8], chunk_size: usize) -> (Vec<String>, Vec<(String, String)>, Vec<u8>) {
+        let (mut connection, mut peer) = test_connection().await;
+        let (sender, mut receiver) = mpsc::channel(100);
+        for chunk in stream.chunks(chunk_size) {
+            AutoQuantMerilService::process_astm_data(&mut connection, chunk, &sender)
+                .await
+                .unwrap();
+        }
+
+        let mut records = Vec::new();
+        let mut results = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            match event {
+                MerilEvent::AstmMessageReceived { raw_data, .. } => records.push(raw_data),
+                MerilEvent::LabResultProcessed { test_results, .. } => {
+                    results.extend(test_results.into_iter().map(|result| (result.test_id, result.value)))
+                }
+                _ => {}
+            }
+        }
+
+        let mut replies = vec![0u8; 12];
+        peer.read_exact(&mut replies).await.unwrap();
+        assert!(matches!(connection.state, ConnectionState::WaitingForEnq));
+        (records, results, replies)
+    }
+
+    #[tokio::test]
+    async fn test_stream_parsed_identically_regardless_of_packet_boundaries() {
+        // Two transmissions back to back, the second starting in the same packet as the first EOT
+        let mut stream = vec![ASTM_ENQ];
+        stream.extend(frame("1H|\\^&|||AutoQuant"));
+        stream.extend(frame("2P|1||P001||Doe^John"));
+        stream.extend(intermediate_frame("3R|1|S42|^^^GLU|95.2|mg/dL|70^1"));
+        stream.extend(frame("410|N||F"));
+        stream.extend(frame("5L|1|N"));
+        stream.extend([ASTM_EOT, ASTM_ENQ]);
+        stream.extend(frame("1H|\\^&|||AutoQuant"));
+        stream.extend(frame("2R|1|S43|^^^ALB|3.5|g/dL|3.4^5.4|N||F"));
+        stream.extend(frame("3L|1|N"));
+        stream.push(ASTM_EOT);
+
+        let whole = parse_in_chunks(&stream, stream.len()).await;
+        assert_eq!(
+            whole.0,
+            vec![
+                "1H|\\^&|||AutoQuant",
+                "2P|1||P001||Doe^John",
+                "3R|1|S42|^^^GLU|95.2|mg/dL|70^110|N||F",
+                "5L|1|N",
+                "1H|\\^&|||AutoQuant",
+                "2R|1|S43|^^^ALB|3.5|g/dL|3.4^5.4|N||F",
+                "3L|1|N",
+            ]
+        );
+        assert_eq!(
+            whole.1,
+            vec![("GLU".to_string(), "95.2".to_string()), ("ALB".to_string(), "3.5".to_string())]
+        );
+        // ENQ, five frames and EOT, then ENQ, three frames and EOT, all ACKed
+        assert_eq!(whole.2, vec![ASTM_ACK; 12]);
+
+        for chunk_size in [1, 7] {
+            assert_eq!(parse_in_chunks(&stream, chunk_size).await, whole, "{}-byte chunks", chunk_size);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_frame_first_transmission_in_tolerant_mode() {
+        let mut data = frame("1H|\\^&|||AutoQuant");
+        data.extend(frame("2R|1|2|^^^GLU|5.4|mmol/L|3.9^6.1|N||F"));
+        data.extend(frame("3L|1|N"));
+        data.push(ASTM_EOT);
+
+        // Strict mode waits for an ENQ that never comes
+        let (mut strict, _strict_peer) = test_connection().await;
+        let (sender, mut receiver) = mpsc::channel(50);
+        AutoQuantMerilService::process_astm_data(&mut strict, &data, &sender)
+            .await
+            .unwrap();
+        assert!(matches!(strict.state, ConnectionState::WaitingForEnq));
+        assert!(receiver.try_recv().is_err());
+
+        let (mut connection, mut peer) = test_connection().await;
+        connection.accept_frames_without_enq = true;
+        let (sender, mut receiver) = mpsc::channel(50);
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+
+        // Every frame and the EOT are acknowledged; there was no ENQ to answer
+        let mut replies = [0u8; 4];
+        peer.read_exact(&mut replies).await.unwrap();
+        assert_eq!(replies, [ASTM_ACK; 4]);
+        assert!(matches!(connection.state, ConnectionState::WaitingForEnq));
+
+        let results = loop {
+            match receiver.try_recv() {
+                Ok(MerilEvent::LabResultProcessed { test_results, .. }) => break test_results,
+                Ok(_) => continue,
+                Err(e) => panic!("Expected LabResultProcessed, got {:?}", e),
+            }
+        };
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].value, "5.4");
+    }
+
+    #[tokio::test]
+    async fn test_encoded_frames_round_trip_through_state_machine() {
+        let records = [
+            "H|\\^&|||AutoQuant^01^1.0|||||||P|E1394-97|20250101120000",
+            "P|1||P001||Doe^John",
+            "R|1|S42|^^^GLU|95.2|mg/dL|70^110|N||F||||20250101120500",
+            "L|1|N",
+        ];
+        let mut data = vec![ASTM_ENQ];
+        for (index, record) in records.iter().enumerate() {
+            let encoded = encode_frame(index as u8 + 1, record);
+            assert!(AutoQuantMerilService::validate_checksum(&encoded));
+            data.extend(encoded);
+        }
+        data.push(ASTM_EOT);
+
+        let (mut connection, mut peer) = test_connection().await;
+        let (sender, mut receiver) = mpsc::channel(50);
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+
+        // ENQ and every frame are acknowledged, none rejected for its checksum
+        let mut replies = [0u8; 5];
+        peer.read_exact(&mut replies).await.unwrap();
+        assert_eq!(replies, [ASTM_ACK; 5]);
+
+        let results = loop {
+            match receiver.try_recv() {
+                Ok(MerilEvent::LabResultProcessed { test_results, .. }) => break test_results,
+                Ok(_) => continue,
+                Err(e) => panic!("Expected LabResultProcessed, got {:?}", e),
+            }
+        };
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].value, "95.2");
+    }
+
+    #[tokio::test]
+    async fn test_result_carries_control_id_of_its_message() {
+        use crate::db::{establish_test_connection, SqliteRepository};
+        use crate::models::DataSource;
+
+        let (mut connection, _peer) = test_connection().await;
+        let (sender, mut receiver) = mpsc::channel(50);
+        let mut data = vec![ASTM_ENQ];
+        for record in [
+            "1H|\\^&|MSG-0042||AutoQuant",
+            "2P|1||P001||Doe^John",
+            "3R|1|S42|^^^GLU|95|mg/dL|70^110|N||F",
+            "4L|1|N",
+        ] {
+            data.extend(frame(record));
+        }
+        data.push(ASTM_EOT);
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+
+        let results = loop {
+            match receiver.try_recv() {
+                Ok(MerilEvent::LabResultProcessed { test_results, .. }) => break test_results,
+                Ok(_) => continue,
+                Err(e) => panic!("Expected LabResultProcessed, got {:?}", e),
+            }
+        };
+        assert_eq!(results[0].metadata.source_message_control_id.as_deref(), Some("MSG-0042"));
+
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        repository.save_test_result(&results[0], "P001", &DataSource::Analyzer).await.unwrap();
+        let traced = repository.get_results_by_message_control_id("MSG-0042").await.unwrap();
+        assert_eq!(traced, results);
+    }
+
+    #[tokio::test]
+    async fn test_post_eot_delay_observed_before_next_enq() {
+        let (mut connection, mut peer) = test_connection().await;
+        connection.post_eot_delay = Duration::from_millis(100);
+        let (sender, _receiver) = mpsc::channel(10);
+
+        let mut data = vec![ASTM_ENQ];
+        data.extend(frame("1H|\\^&|||AutoQuant"));
+        data.extend(frame("2L|1|N"));
+        data.push(ASTM_EOT);
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+        let eot_acked_at = Instant::now();
+        let mut replies = [0u8; 4];
+        peer.read_exact(&mut replies).await.unwrap();
+
+        // The analyzer starts its next transmission straight away
+        AutoQuantMerilService::process_astm_data(&mut connection, &[ASTM_ENQ], &sender)
+            .await
+            .unwrap();
+        let mut reply = [0u8; 1];
+        peer.read_exact(&mut reply).await.unwrap();
+
+        assert_eq!(reply, [ASTM_ACK]);
+        assert!(eot_acked_at.elapsed() >= Duration::from_millis(100));
+        assert!(matches!(connection.state, ConnectionState::WaitingForFrame));
+        assert!(connection.quiet_until.is_none());
+
+        // Once the pause has passed the next ENQ is answered at once
+        AutoQuantMerilService::process_astm_data(&mut connection, &[ASTM_EOT], &sender)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        let started = Instant::now();
+        AutoQuantMerilService::process_astm_data(&mut connection, &[ASTM_ENQ], &sender)
+            .await
+            .unwrap();
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+
+    /// Runs the connection loop on a test connection with the given timers
+    async fn spawn_connection_loop(timeouts: AstmTimeouts) -> (TcpStream, mpsc::Receiver<MerilEvent>) {
+        let (mut connection, peer) = test_connection().await;
+        connection.timeouts = timeouts;
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        connections.write().await.insert("test-analyzer".to_string(), connection);
+        let (sender, receiver) = mpsc::channel(100);
+        tokio::spawn(AutoQuantMerilService::handle_connection(
+            connections,
+            sender,
+            "test-analyzer".to_string(),
+            Arc::new(Mutex::new(VecDeque::new())),
+        ));
+        (peer, receiver)
+    }
+
+    /// Writes one byte at a time with a pause after each, like a slow serial-to-TCP converter
+    async fn send_slowly(peer: &mut TcpStream, bytes: &[u8], gap: Duration) {
+        for &byte in bytes {
+            peer.write_all(&[byte]).await.unwrap();
+            tokio::time::sleep(gap).await;
+        }
+    }
+
+    async fn read_reply(peer: &mut TcpStream) -> u8 {
+        let mut reply = [0u8; 1];
+        timeout(Duration::from_secs(2), peer.read_exact(&mut reply))
+            .await
+            .expect("No reply from the LIS")
+            .unwrap();
+        reply[0]
+    }
+
+    async fn next_error(receiver: &mut mpsc::Receiver<MerilEvent>) -> String {
+        loop {
+            match timeout(Duration::from_secs(2), receiver.recv()).await {
+                Ok(Some(MerilEvent::Error { error, .. })) => return error,
+                Ok(Some(_)) => continue,
+                other => panic!("Expected an error event, got {:?}", other),
+            }
+        }
+    }
+
+    /// Reads one frame sent by the LIS, up to and including its LF
+    async fn read_frame(peer: &mut TcpStream) -> Vec<u8> {
+        let mut frame = Vec::new();
+        while frame.last() != Some(&ASTM_LF) {
+            frame.push(read_reply(peer).await);
+        }
+        frame
+    }
+
+    #[tokio::test]
+    async fn test_host_initiated_handshake_against_mock_analyzer() {
+        use crate::models::test_order::{ActionCode, OrderPriority, Test};
+
+        let (mut connection, mut analyzer) = test_connection().await;
+        connection.host_initiated = true;
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        connections.write().await.insert("test-analyzer".to_string(), connection);
+        let outbox = Arc::new(Mutex::new(VecDeque::from([TestOrder {
+            id: "O1".to_string(),
+            sequence_number: 1,
+            specimen_id: "S42".to_string(),
+            tests: vec![
+                Test { universal_id: "^^^GLU".to_string(), name: "Glucose".to_string() },
+                Test { universal_id: "^^^ALB".to_string(), name: "Albumin".to_string() },
+            ],
+            priority: OrderPriority::Stat,
+            action_code: ActionCode::New,
+            ordering_provider: None,
+            scheduling_info: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }])));
+        let (sender, mut receiver) = mpsc::channel(100);
+        tokio::spawn(AutoQuantMerilService::handle_connection(
+            connections,
+            sender,
+            "test-analyzer".to_string(),
+            outbox.clone(),
+        ));
+
+        // The host bids first and the analyzer grants the line
+        assert_eq!(read_reply(&mut analyzer).await, ASTM_ENQ);
+        analyzer.write_all(&[ASTM_ACK]).await.unwrap();
+
+        // Frames arrive one at a time; the order frame is NAKed once and resent
+        let mut received = Vec::new();
+        let mut naked = false;
+        loop {
+            let first = read_reply(&mut analyzer).await;
+            if first == ASTM_EOT {
+                break;
+            }
+            let mut frame = vec![first];
+            frame.extend(read_frame(&mut analyzer).await);
+            assert!(AutoQuantMerilService::validate_checksum(&frame));
+            let text = String::from_utf8_lossy(&frame[2..frame.len() - 6]).to_string();
+            if text.starts_with('O') && !naked {
+                naked = true;
+                analyzer.write_all(&[ASTM_NAK]).await.unwrap();
+                continue;
+            }
+            received.push((frame[1], text));
+            analyzer.write_all(&[ASTM_ACK]).await.unwrap();
+        }
+        assert_eq!(
+            received,
+            vec![
+                (b'1', "H|\\^&|||LIS|||||||P|1".to_string()),
+                (b'2', "P|1".to_string()),
+                (b'3', "O|1|S42||^^^GLU\\^^^ALB|S||||||N".to_string()),
+                (b'4', "L|1|N".to_string()),
+            ]
+        );
+        assert!(naked);
+        assert!(outbox.lock().await.is_empty());
+
+        // Then the analyzer sends its results as usual
+        analyzer.write_all(&[ASTM_ENQ]).await.unwrap();
+        assert_eq!(read_reply(&mut analyzer).await, ASTM_ACK);
+        for record in [
+            "1H|\\^&|||AutoQuant",
+            "2P|1||P001",
+            "3R|1|S42|^^^GLU|95|mg/dL|70^110|N||F",
+            "4L|1|N",
+        ] {
+            analyzer.write_all(&frame(record)).await.unwrap();
+            assert_eq!(read_reply(&mut analyzer).await, ASTM_ACK);
+        }
+        analyzer.write_all(&[ASTM_EOT]).await.unwrap();
+        assert_eq!(read_reply(&mut analyzer).await, ASTM_ACK);
+
+        let results = loop {
+            match timeout(Duration::from_secs(2), receiver.recv()).await {
+                Ok(Some(MerilEvent::LabResultProcessed { test_results, .. })) => break test_results,
+                Ok(Some(_)) => continue,
+                other => panic!("Expected LabResultProcessed, got {:?}", other),
+            }
+        };
+        assert_eq!(results[0].sample_id, "S42");
+    }
+
+    #[test]
+    fn test_query_record_specimen_range() {
+        let delimiters = AstmDelimiters::default();
+        let single = AutoQuantMerilService::parse_query_record(b"2Q|1|^S42||^^^ALL||||||||O", &delimiters);
+        assert_eq!(single.starting_range, "^S42");
+        assert_eq!(single.specimen_range(), Some(("S42", "S42")));
+
+        let range = AutoQuantMerilService::parse_query_record(b"2Q|1|P1^S40|P9^S49|^^^ALL", &delimiters);
+        assert_eq!(range.specimen_range(), Some(("S40", "S49")));
+
+        let bare = AutoQuantMerilService::parse_query_record(b"2Q|1|S42", &delimiters);
+        assert_eq!(bare.specimen_range(), Some(("S42", "S42")));
+
+        let all = AutoQuantMerilService::parse_query_record(b"2Q|1|ALL||^^^ALL", &delimiters);
+        assert_eq!(all.specimen_range(), None);
+    }
+
+    /// Reads a host transmission sent by the LIS, ACKing every frame, and returns the frame texts
+    async fn receive_host_transmission(analyzer: &mut TcpStream) -> Vec<String> {
+        assert_eq!(read_reply(analyzer).await, ASTM_ENQ);
+        analyzer.write_all(&[ASTM_ACK]).await.unwrap();
+        let mut received = Vec::new();
+        loop {
+            let first = read_reply(analyzer).await;
+            if first == ASTM_EOT {
+                break received;
+            }
+            let mut frame = vec![first];
+            frame.extend(read_frame(analyzer).await);
+            received.push(String::from_utf8_lossy(&frame[2..frame.len() - 6]).to_string());
+            analyzer.write_all(&[ASTM_ACK]).await.unwrap();
+        }
+    }
+
+    /// Sends a transmission from the analyzer, checking every element is ACKed
+    async fn send_transmission(analyzer: &mut TcpStream, records: &[&str]) {
+        analyzer.write_all(&[ASTM_ENQ]).await.unwrap();
+        assert_eq!(read_reply(analyzer).await, ASTM_ACK);
+        for (index, record) in records.iter().enumerate() {
+            analyzer.write_all(&encode_frame(((index + 1) % 8) as u8, record)).await.unwrap();
+            assert_eq!(read_reply(analyzer).await, ASTM_ACK);
+        }
+        analyzer.write_all(&[ASTM_EOT]).await.unwrap();
+        assert_eq!(read_reply(analyzer).await, ASTM_ACK);
+    }
+
+    #[tokio::test]
+    async fn test_host_query_answered_with_pending_orders() {
+        use crate::db::establish_test_connection;
+        use crate::models::test_order::{ActionCode, OrderPriority, Test};
+
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let order = |id: &str, specimen_id: &str| TestOrder {
+            id: id.to_string(),
+            sequence_number: 1,
+            specimen_id: specimen_id.to_string(),
+            tests: vec![Test { universal_id: "^^^GLU".to_string(), name: "Glucose".to_string() }],
+            priority: OrderPriority::Routine,
+            action_code: ActionCode::New,
+            ordering_provider: None,
+            scheduling_info: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        repository.enqueue_order_dispatch("test-analyzer", "P1", &order("O1", "S42")).await.unwrap();
+        repository.enqueue_order_dispatch("test-analyzer", "P1", &order("O2", "S43")).await.unwrap();
+
+        let (mut connection, mut analyzer) = test_connection().await;
+        connection.order_repository = Some(repository.clone());
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        connections.write().await.insert("test-analyzer".to_string(), connection);
+        let (sender, mut receiver) = mpsc::channel(100);
+        tokio::spawn(AutoQuantMerilService::handle_connection(
+            connections,
+            sender,
+            "test-analyzer".to_string(),
+            Arc::new(Mutex::new(VecDeque::new())),
+        ));
+
+        // The analyzer asks for the worklist of S42, then releases the line
+        send_transmission(&mut analyzer, &["H|\\^&|||AutoQuant", "Q|1|^S42||^^^ALL||||||||O", "L|1|N"]).await;
+        assert_eq!(
+            receive_host_transmission(&mut analyzer).await,
+            vec![
+                "H|\\^&|||LIS|||||||P|1".to_string(),
+                "P|1".to_string(),
+                "O|1|S42||^^^GLU|R||||||N".to_string(),
+                "L|1|N".to_string(),
+            ]
+        );
+
+        // A sample without pending orders gets the query back as "no information"
+        send_transmission(&mut analyzer, &["H|\\^&|||AutoQuant", "Q|1|^S99||^^^ALL||||||||O", "L|1|N"]).await;
+        assert_eq!(
+            receive_host_transmission(&mut analyzer).await,
+            vec![
+                "H|\\^&|||LIS|||||||P|1".to_string(),
+                "Q|1|^S99||||||||||X".to_string(),
+                "L|1|N".to_string(),
+            ]
+        );
+
+        let mut answered = Vec::new();
+        while answered.len() < 2 {
+            match timeout(Duration::from_secs(2), receiver.recv()).await {
+                Ok(Some(MerilEvent::HostQueryAnswered { query, orders, .. })) => answered.push((query, orders)),
+                Ok(Some(_)) => continue,
+                other => panic!("Expected HostQueryAnswered, got {:?}", other),
+            }
+        }
+        assert_eq!(answered[0].0.first_specimen_id.as_deref(), Some("S42"));
+        assert_eq!(answered[0].1.iter().map(|order| order.id.as_str()).collect::<Vec<_>>(), vec!["O1"]);
+        assert!(answered[1].1.is_empty());
+
+        // Only the order sent leaves the pending queue
+        let pending = repository.get_pending_order_dispatches("test-analyzer", 10).await.unwrap();
+        assert_eq!(pending.iter().map(|dispatch| dispatch.order_id.as_str()).collect::<Vec<_>>(), vec!["O2"]);
+    }
+
+    #[tokio::test]
+    async fn test_patient_orders_download_retries_naked_frames() {
+        use crate::models::patient::{PatientName, Sex};
+        use crate::models::test_order::{ActionCode, OrderPriority, Test};
+
+        let now = Utc::now();
+        let patient = Patient {
+            name: PatientName {
+                last_name: Some("Rao".to_string()),
+                first_name: Some("Asha".to_string()),
+                ..Default::default()
+            },
+            sex: Sex::Female,
+            ..Patient::fixture("PAT-1")
+        };
+        // Enough tests that the order record continues in a second frame
+        let order = TestOrder {
+            id: "O1".to_string(),
+            sequence_number: 1,
+            specimen_id: "S42".to_string(),
+            tests: (0..40)
+                .map(|n| Test { universal_id: format!("^^^T{:02}", n), name: format!("Test {}", n) })
+                .collect(),
+            priority: OrderPriority::Routine,
+            action_code: ActionCode::New,
+            ordering_provider: None,
+            scheduling_info: None,
+            created_at: now,
+            updated_at: now,
+        };
+        let records = patient_order_records(HOST_SENDER_ID, &patient, &[order.clone()], &AstmDelimiters::default());
+        let expected = encode_records(&records);
+        assert_eq!(expected.len(), records.len() + 1);
+
+        let (mut connection, mut analyzer) = test_connection().await;
+        connection.frame_retries = 1;
+        let (sender, _receiver) = mpsc::channel(100);
+        let download = {
+            let (patient, order) = (patient.clone(), order.clone());
+            tokio::spawn(async move {
+                let result =
+                    AutoQuantMerilService::download_patient_orders(&mut connection, &patient, &[order], &sender)
+                        .await;
+                (result, connection)
+            })
+        };
+
+        // The first frame is NAKed once and resent; every frame arrives in order
+        assert_eq!(read_reply(&mut analyzer).await, ASTM_ENQ);
+        analyzer.write_all(&[ASTM_ACK]).await.unwrap();
+        let mut received = Vec::new();
+        let mut naked = false;
+        loop {
+            let first = read_reply(&mut analyzer).await;
+            if first == ASTM_EOT {
+                break;
+            }
+            let mut frame = vec![first];
+            frame.extend(read_frame(&mut analyzer).await);
+            if !naked {
+                naked = true;
+                analyzer.write_all(&[ASTM_NAK]).await.unwrap();
+                continue;
+            }
+            received.push(frame);
+            analyzer.write_all(&[ASTM_ACK]).await.unwrap();
+        }
+        assert_eq!(received, expected);
+        let (result, mut connection) = download.await.unwrap();
+        assert_eq!(result, Ok(()));
+
+        // A frame NAKed beyond the limit ends the transmission with EOT
+        let (sender, _receiver) = mpsc::channel(100);
+        let download = tokio::spawn(async move {
+            AutoQuantMerilService::download_patient_orders(&mut connection, &patient, &[order], &sender).await
+        });
+        assert_eq!(read_reply(&mut analyzer).await, ASTM_ENQ);
+        analyzer.write_all(&[ASTM_ACK]).await.unwrap();
+        for _ in 0..2 {
+            assert_eq!(read_reply(&mut analyzer).await, ASTM_STX);
+            read_frame(&mut analyzer).await;
+            analyzer.write_all(&[ASTM_NAK]).await.unwrap();
+        }
+        assert_eq!(read_reply(&mut analyzer).await, ASTM_EOT);
+        let error = download.await.unwrap().unwrap_err();
+        assert!(error.contains("after 1 retries"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_raw_records_are_framed_sequenced_and_logged() {
+        let (mut connection, mut analyzer) = test_connection().await;
+        let conversation_log = ConversationLog::default();
+        connection.conversation = conversation_log.open("test-analyzer", "ASTM", connection.remote_addr);
+        let connection_id = connection.conversation.connection_id().to_string();
+
+        // Nine records, so the frame number wraps from 7 to 0
+        let mut records = vec!["H|\\^&|||LIS".to_string()];
+        records.extend((1..=7).map(|n| format!("C|{}|L|worklist reset", n)));
+        records.push("L|1|N".to_string());
+        let (sender, _receiver) = mpsc::channel(100);
+        let exchange = {
+            let records = records.clone();
+            tokio::spawn(async move {
+                let result = AutoQuantMerilService::exchange_raw_records(
+                    &mut connection,
+                    &records,
+                    Duration::from_millis(300),
+                    &sender,
+                )
+                .await;
+                (result, connection)
+            })
+        };
+
+        assert_eq!(read_reply(&mut analyzer).await, ASTM_ENQ);
+        analyzer.write_all(&[ASTM_ACK]).await.unwrap();
+        for (index, record) in records.iter().enumerate() {
+            let mut frame = vec![read_reply(&mut analyzer).await];
+            frame.extend(read_frame(&mut analyzer).await);
+            assert_eq!(frame, encode_frame(((index + 1) % 8) as u8, record));
+            assert!(AutoQuantMerilService::validate_checksum(&frame));
+            analyzer.write_all(&[ASTM_ACK]).await.unwrap();
+        }
+        assert_eq!(read_reply(&mut analyzer).await, ASTM_EOT);
+
+        // The analyzer answers within the window and is served as usual
+        analyzer.write_all(&[ASTM_ENQ]).await.unwrap();
+        assert_eq!(read_reply(&mut analyzer).await, ASTM_ACK);
+
+        let (result, connection) = exchange.await.unwrap();
+        let exchange = result.unwrap();
+        assert_eq!(exchange.protocol, "ASTM");
+        assert_eq!(exchange.responses, vec!["<ENQ>".to_string()]);
+        assert_eq!(
+            exchange.bytes_sent,
+            2 + records.iter().enumerate().map(|(i, r)| encode_frame(((i + 1) % 8) as u8, r).len()).sum::<usize>()
+        );
+        assert!(matches!(connection.state, ConnectionState::WaitingForFrame));
+
+        let outbound: Vec<ConversationElement> = conversation_log
+            .get_conversation(&connection_id, 100)
+            .unwrap()
+            .into_iter()
+            .filter(|entry| entry.direction == Direction::Outbound)
+            .map(|entry| entry.element)
+            .collect();
+        let mut expected = vec![ConversationElement::Enq];
+        expected.extend((1..=9).map(|n| ConversationElement::Frame { number: (n % 8) as u8 }));
+        expected.extend([ConversationElement::Eot, ConversationElement::Ack]);
+        assert_eq!(outbound, expected);
+    }
+
+    #[tokio::test]
+    async fn test_tight_timers_abort_stalled_frame_and_transmission() {
+        let timeouts = AstmTimeouts {
+            inter_byte_ms: 100,
+            inter_frame_ms: 300,
+            transmission_ms: 5_000,
+            ..AstmTimeouts::default()
+        };
+        let (mut peer, mut receiver) = spawn_connection_loop(timeouts).await;
+        let header = frame("1H|\\^&|||AutoQuant");
+
+        // The sender stalls part-way through a frame: the partial frame is NAKed
+        send_slowly(&mut peer, &[ASTM_ENQ], Duration::ZERO).await;
+        assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+        send_slowly(&mut peer, &header[..6], Duration::from_millis(10)).await;
+        assert_eq!(read_reply(&mut peer).await, ASTM_NAK);
+        assert!(next_error(&mut receiver).await.contains("inter-byte timeout"));
+
+        // The sender never follows a frame with the next one or EOT: the link goes idle without a NAK
+        send_slowly(&mut peer, &[ASTM_ENQ], Duration::ZERO).await;
+        assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+        peer.write_all(&header).await.unwrap();
+        assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+        assert!(next_error(&mut receiver).await.contains("inter-frame timeout"));
+        let mut stray = [0u8; 1];
+        assert!(timeout(Duration::from_millis(100), peer.read_exact(&mut stray)).await.is_err());
+
+        // A new transmission is accepted afterwards
+        send_slowly(&mut peer, &[ASTM_ENQ], Duration::ZERO).await;
+        assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+    }
+
+    #[tokio::test]
+    async fn test_transmission_timer_aborts_trickling_sender() {
+        let timeouts = AstmTimeouts {
+            inter_byte_ms: 200,
+            inter_frame_ms: 200,
+            transmission_ms: 300,
+            ..AstmTimeouts::default()
+        };
+        let (mut peer, mut receiver) = spawn_connection_loop(timeouts).await;
+
+        send_slowly(&mut peer, &[ASTM_ENQ], Duration::ZERO).await;
+        assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+        // Every gap is within the byte timer, but the whole frame takes too long
+        send_slowly(&mut peer, &frame("1H|\\^&|||AutoQuant"), Duration::from_millis(30)).await;
+
+        assert_eq!(read_reply(&mut peer).await, ASTM_NAK);
+        assert!(next_error(&mut receiver).await.contains("transmission timeout (300 ms)"));
+    }
+
+    #[tokio::test]
+    async fn test_every_frame_is_answered_well_within_the_ack_budget() {
+        let (mut peer, mut receiver) = spawn_connection_loop(AstmTimeouts::default()).await;
+        // Events are consumed as the app would, at their own pace
+        let consumer = tokio::spawn(async move {
+            let mut results = 0;
+            while let Some(event) = receiver.recv().await {
+                if let MerilEvent::LabResultProcessed { test_results, .. } = event {
+                    results += test_results.len();
+                }
+            }
+            results
+        });
+
+        // 500 frames: header, 498 results and the terminator, like a full AutoQuant run
+        let mut records = vec!["H|\\^&|||AutoQuant".to_string()];
+        records.extend((1..=498).map(|n| format!("R|{n}|S{n}|^^^GLU|{n}|mg/dL||N||F")));
+        records.push("L|1|N".to_string());
+
+        peer.write_all(&[ASTM_ENQ]).await.unwrap();
+        assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+        let mut slowest = Duration::ZERO;
+        for (index, record) in records.iter().enumerate() {
+            peer.write_all(&encode_frame(((index + 1) % 8) as u8, record)).await.unwrap();
+            let frame_ended = Instant::now();
+            assert_eq!(read_reply(&mut peer).await, ASTM_ACK, "frame {}", index + 1);
+            slowest = slowest.max(frame_ended.elapsed());
+        }
+        assert!(slowest < Duration::from_millis(500), "slowest ACK took {:?}", slowest);
+
+        peer.write_all(&[ASTM_EOT]).await.unwrap();
+        assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+        drop(peer);
+        let results = timeout(Duration::from_secs(5), consumer).await.unwrap().unwrap();
+        assert_eq!(results, 498);
+    }
+
+    #[tokio::test]
+    async fn test_generous_timers_accept_slow_serial_converter() {
+        let timeouts = AstmTimeouts {
+            inter_byte_ms: 300,
+            inter_frame_ms: 1_000,
+            transmission_ms: 10_000,
+            ..AstmTimeouts::default()
+        };
+        let (mut peer, mut receiver) = spawn_connection_loop(timeouts).await;
+
+        send_slowly(&mut peer, &[ASTM_ENQ], Duration::ZERO).await;
+        assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+        for text in ["1H|\\^&|||AutoQuant", "2L|1|N"] {
+            send_slowly(&mut peer, &frame(text), Duration::from_millis(20)).await;
+            assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+            tokio::time::sleep(Duration::from_millis(400)).await;
+        }
+        send_slowly(&mut peer, &[ASTM_EOT], Duration::ZERO).await;
+        assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+
+        loop {
+            match timeout(Duration::from_secs(2), receiver.recv()).await {
+                Ok(Some(MerilEvent::LabResultProcessed { .. })) => break,
+                Ok(Some(MerilEvent::Error { error, .. })) => panic!("Unexpected error: {}", error),
+                Ok(Some(_)) => continue,
+                other => panic!("Expected the transmission to complete, got {:?}", other),
+            }
+        }
+    }
+
+    /// Waits for the disconnected event and returns how the connection ended
+    async fn next_termination(receiver: &mut mpsc::Receiver<MerilEvent>) -> ConnectionTermination {
+        loop {
+            match timeout(Duration::from_secs(2), receiver.recv()).await {
+                Ok(Some(MerilEvent::AnalyzerDisconnected { termination, .. })) => return termination,
+                Ok(Some(_)) => continue,
+                other => panic!("Expected a disconnected event, got {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disconnects_are_classified_by_session_state() {
+        let timeouts = AstmTimeouts::default();
+
+        // The AutoQuant's routine close after an acknowledged EOT
+        let (mut peer, mut receiver) = spawn_connection_loop(timeouts).await;
+        peer.write_all(&[ASTM_ENQ]).await.unwrap();
+        assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+        for record in ["1H|\\^&|||AutoQuant", "2L|1|N"] {
+            peer.write_all(&frame(record)).await.unwrap();
+            assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+        }
+        peer.write_all(&[ASTM_EOT]).await.unwrap();
+        assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+        drop(peer);
+        assert_eq!(next_termination(&mut receiver).await, ConnectionTermination::Completed);
+
+        // Closed part-way through a frame
+        let (mut peer, mut receiver) = spawn_connection_loop(timeouts).await;
+        peer.write_all(&[ASTM_ENQ]).await.unwrap();
+        assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+        peer.write_all(&frame("1H|\\^&|||AutoQuant")[..6]).await.unwrap();
+        drop(peer);
+        assert_eq!(next_termination(&mut receiver).await, ConnectionTermination::RemoteClosed);
+
+        // Closed after the transmission timer gave up on the sender
+        let tight = AstmTimeouts { inter_byte_ms: 50, inter_frame_ms: 50, transmission_ms: 100, ..AstmTimeouts::default() };
+        let (mut peer, mut receiver) = spawn_connection_loop(tight).await;
+        peer.write_all(&[ASTM_ENQ]).await.unwrap();
+        assert_eq!(read_reply(&mut peer).await, ASTM_ACK);
+        assert!(next_error(&mut receiver).await.contains("timeout"));
+        drop(peer);
+        assert_eq!(next_termination(&mut receiver).await, ConnectionTermination::Timeout);
+
+        // Reset by the peer
+        let (peer, mut receiver) = spawn_connection_loop(timeouts).await;
+        socket2::SockRef::from(&peer).set_linger(Some(Duration::ZERO)).unwrap();
+        drop(peer);
+        assert_eq!(next_termination(&mut receiver).await, ConnectionTermination::Error);
+
+        // Closed by the service; a stray byte wakes the read so the handler sees it
+        let (connection, mut peer) = test_connection().await;
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        connections.write().await.insert("test-analyzer".to_string(), connection);
+        let (sender, mut receiver) = mpsc::channel(100);
+        tokio::spawn(AutoQuantMerilService::handle_connection(
+            connections.clone(),
+            sender,
+            "test-analyzer".to_string(),
+            Arc::new(Mutex::new(VecDeque::new())),
+        ));
+        let remover = {
+            let connections = connections.clone();
+            tokio::spawn(async move { connections.write().await.remove("test-analyzer") })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        peer.write_all(&[ASTM_ACK]).await.unwrap();
+        assert!(remover.await.unwrap().is_some());
+        assert_eq!(next_termination(&mut receiver).await, ConnectionTermination::Shutdown);
+    }
+
+    #[tokio::test]
+    async fn test_astm_session_recorded_as_conversation() {
+        let log = ConversationLog::default();
+        let (mut connection, mut peer) = test_connection().await;
+        connection.conversation = log.open("test-analyzer", "ASTM", connection.remote_addr);
+        let (sender, _receiver) = mpsc::channel(10);
+
+        let header = frame("1H|\\^&|||AutoQuant");
+        let terminator = frame("2L|1|N");
+        let mut data = vec![ASTM_ENQ];
+        data.extend(&header);
+        data.extend(&terminator);
+        data.push(ASTM_EOT);
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+        let mut replies = [0u8; 4];
+        peer.read_exact(&mut replies).await.unwrap();
+        connection.conversation.close(ConnectionTermination::Completed);
+
+        let entries = log
+            .get_conversation(connection.conversation.connection_id(), 100)
+            .unwrap();
+        let sequence: Vec<(Direction, ConversationElement, usize)> = entries
+            .iter()
+            .map(|e| (e.direction, e.element.clone(), e.length))
+            .collect();
+        assert_eq!(
+            sequence,
+            vec![
+                (Direction::Inbound, ConversationElement::Enq, 1),
+                (Direction::Outbound, ConversationElement::Ack, 1),
+                (Direction::Inbound, ConversationElement::Frame { number: 1 }, header.len()),
+                (Direction::Outbound, ConversationElement::Ack, 1),
+                (Direction::Inbound, ConversationElement::Frame { number: 2 }, terminator.len()),
+                (Direction::Outbound, ConversationElement::Ack, 1),
+                (Direction::Inbound, ConversationElement::Eot, 1),
+                (Direction::Outbound, ConversationElement::Ack, 1),
+            ]
+        );
+        assert!(entries.iter().all(|e| e.outcome == EntryOutcome::Accepted));
+
+        let recent = log.recent_connections();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].entries, 8);
+        assert!(recent[0].closed_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_transmission_progress_and_final_reconciliation() {
+        let (mut connection, _peer) = test_connection().await;
+        connection.provisional_results = true;
+        let (sender, mut receiver) = mpsc::channel(50);
+
+        let mut data = vec![ASTM_ENQ];
+        for record in [
+            "1H|\\^&|||AutoQuant",
+            "2P|1||P001||Doe^John",
+            "3R|1|2|^^^GLU|95|mg/dL|70^110|N||F",
+            "4R|2|3|^^^ALB|3.5|g/dL|3.4^5.4|N||F",
+            "5L|1|N",
+        ] {
+            data.extend(frame(record));
+        }
+        data.push(ASTM_EOT);
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+
+        let mut progress = Vec::new();
+        let mut provisional = Vec::new();
+        let mut finals = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            match event {
+                MerilEvent::TransmissionProgress { progress: p, .. } => progress.push(p),
+                MerilEvent::ProvisionalResult { transmission_id, result, .. } => provisional.push((transmission_id, result)),
+                MerilEvent::LabResultProcessed { transmission_id, test_results, .. } => finals.push((transmission_id, test_results)),
+                _ => {}
+            }
+        }
+
+        // One progress event per frame, with running counts
+        assert_eq!(progress.len(), 5);
+        let counts: Vec<(u32, u32, u32)> = progress
+            .iter()
+            .map(|p| (p.frames_received, p.patients_seen, p.results_parsed))
+            .collect();
+        assert_eq!(counts, vec![(1, 0, 0), (2, 1, 0), (3, 1, 1), (4, 1, 2), (5, 1, 2)]);
+
+        // Provisional results arrive before the final event, which carries the authoritative set
+        assert_eq!(provisional.len(), 2);
+        assert_eq!(finals.len(), 1);
+        let (transmission_id, test_results) = &finals[0];
+        assert!(!transmission_id.is_empty());
+        assert!(progress.iter().all(|p| &p.transmission_id == transmission_id));
+        assert!(provisional.iter().all(|(id, _)| id == transmission_id));
+        let final_tests: Vec<&str> = test_results.iter().map(|r| r.test_id.as_str()).collect();
+        let provisional_tests: Vec<&str> = provisional.iter().map(|(_, r)| r.test_id.as_str()).collect();
+        assert_eq!(final_tests, vec!["GLU", "ALB"]);
+        assert_eq!(provisional_tests, final_tests);
+    }
+
+    /// Frames as re-emitted by the lab's ASTM middleware: lowercase checksums,
+    /// some padded with a space before CR
+    const MIDDLEWARE_FRAMES: [&[u8]; 5] = [
+        b"\x021H|\\^&|||MW-Bridge^2.1|||||||P|LIS2-A2|20250314091502\x0381\r\n",
+        b"\x022P|1||P778||Rao^Anita||19790221|F\x03de \r\n",
+        b"\x023R|1|2|^^^GLU|132|mg/dL|70^110|H||F\x0377\r\n",
+        b"\x024R|2|3|^^^CRE|0.9|mg/dL|0.6^1.2|N||F\x039f \r\n",
+        b"\x025L|1|N\x03fb\r\n",
+    ];
+
+    #[test]
+    fn test_middleware_checksums_validate_regardless_of_case_and_padding() {
+        for fixture in MIDDLEWARE_FRAMES {
+            assert!(AutoQuantMerilService::validate_checksum(fixture), "{:?}", String::from_utf8_lossy(fixture));
+            let mut upper = fixture.to_vec();
+            let etx = upper.iter().rposition(|&b| b == ASTM_ETX).unwrap();
+            upper[etx..].make_ascii_uppercase();
+            assert!(AutoQuantMerilService::validate_checksum(&upper));
+        }
+        assert_eq!(
+            AutoQuantMerilService::checksum_field(MIDDLEWARE_FRAMES[1]),
+            Some((&b"de"[..], true))
+        );
+        assert_eq!(
+            AutoQuantMerilService::checksum_field(MIDDLEWARE_FRAMES[2]),
+            Some((&b"77"[..], false))
+        );
+
+        let mut corrupted = MIDDLEWARE_FRAMES[2].to_vec();
+        corrupted[10] = b'X';
+        assert!(!AutoQuantMerilService::validate_checksum(&corrupted));
+    }
+
+    /// One transmission captured from an AutoQuant 200i, checksum characters as sent
+    const AUTOQUANT_200I_FRAMES: [&[u8]; 4] = [
+        b"\x021H|\\^&|||AutoQuant^200i^AQ-01|||||||P|LIS2-A2|20250101100000\r\x03E4\r\n",
+        b"\x022P|1||P001||Doe^John||19800512|M\r\x0370\r\n",
+        b"\x023R|1|S42|^^^GLU|95.2|mg/dL|70^110|N||F||||20250101120500|AQ-200i-01\r\x03B0\r\n",
+        b"\x024L|1|N\r\x0307\r\n",
+    ];
+
+    /// Replaces the two checksum characters of a frame
+    fn with_checksum(frame: &[u8], chars: &[u8]) -> Vec<u8> {
+        let etx = frame.iter().rposition(|&b| b == ASTM_ETX).unwrap();
+        let mut replaced = frame[..=etx].to_vec();
+        replaced.extend_from_slice(chars);
+        replaced.extend_from_slice(&[ASTM_CR, ASTM_LF]);
+        replaced
+    }
+
+    #[test]
+    fn test_autoquant_200i_checksums_are_two_hex_characters_modulo_256() {
+        for frame in AUTOQUANT_200I_FRAMES {
+            assert!(AutoQuantMerilService::validate_checksum(frame), "{:?}", String::from_utf8_lossy(frame));
+        }
+
+        // A changed data byte, a changed checksum character, or the byte sum modulo 8
+        // sent as a single character never validates
+        let mut corrupted = AUTOQUANT_200I_FRAMES[2].to_vec();
+        let value = corrupted.windows(4).position(|window| window == b"95.2").unwrap();
+        corrupted[value] = b'8';
+        assert!(!AutoQuantMerilService::validate_checksum(&corrupted));
+        assert!(!AutoQuantMerilService::validate_checksum(&with_checksum(AUTOQUANT_200I_FRAMES[2], b"B1")));
+        assert!(!AutoQuantMerilService::validate_checksum(&with_checksum(AUTOQUANT_200I_FRAMES[3], b"7")));
+        assert!(!AutoQuantMerilService::validate_checksum(&with_checksum(AUTOQUANT_200I_FRAMES[0], b"\x04")));
+    }
+
+    #[tokio::test]
+    async fn test_autoquant_200i_frame_with_bad_checksum_is_naked_until_resent_intact() {
+        let (mut connection, mut peer) = test_connection().await;
+        let (sender, mut receiver) = mpsc::channel(50);
+
+        let mut data = vec![ASTM_ENQ];
+        data.extend_from_slice(AUTOQUANT_200I_FRAMES[0]);
+        data.extend_from_slice(AUTOQUANT_200I_FRAMES[1]);
+        data.extend(with_checksum(AUTOQUANT_200I_FRAMES[2], b"0B"));
+        assert_eq!(
+            AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+                .await
+                .unwrap_err(),
+            "Checksum mismatch in frame 3"
+        );
+        assert_eq!(written_bytes(&mut peer).await, vec![ASTM_ACK, ASTM_ACK, ASTM_ACK, ASTM_NAK]);
+
+        let mut resent = AUTOQUANT_200I_FRAMES[2].to_vec();
+        resent.extend_from_slice(AUTOQUANT_200I_FRAMES[3]);
+        resent.push(ASTM_EOT);
+        AutoQuantMerilService::process_astm_data(&mut connection, &resent, &sender)
+            .await
+            .unwrap();
+        assert_eq!(written_bytes(&mut peer).await, vec![ASTM_ACK; 3]);
+        assert_eq!(connection.progress.retransmissions_requested, 1);
+
+        let results = loop {
+            match receiver.try_recv() {
+                Ok(MerilEvent::LabResultProcessed { test_results, .. }) => break test_results,
+                Ok(_) => continue,
+                Err(e) => panic!("Expected LabResultProcessed, got {:?}", e),
+            }
+        };
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].value, "95.2");
+    }
+
+    #[tokio::test]
+    async fn test_frame_naked_past_the_retry_limit_abandons_the_transmission() {
+        let (mut connection, mut peer) = test_connection().await;
+        connection.frame_retries = 2;
+        let (sender, mut receiver) = mpsc::channel(50);
+
+        // The corrupted frame is sent once and retransmitted twice, then the sender gives up
+        let mut data = vec![ASTM_ENQ];
+        data.extend_from_slice(AUTOQUANT_200I_FRAMES[0]);
+        data.extend_from_slice(AUTOQUANT_200I_FRAMES[1]);
+        for _ in 0..3 {
+            data.extend(with_checksum(AUTOQUANT_200I_FRAMES[2], b"0B"));
+        }
+        data.push(ASTM_EOT);
+        let error = AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap_err();
+        assert!(error.contains("rejected 3 times"), "{}", error);
+        assert!(error.ends_with("Checksum mismatch in frame 3"), "{}", error);
+        // The EOT after the abort is not acknowledged and nothing is processed
+        assert_eq!(
+            written_bytes(&mut peer).await,
+            vec![ASTM_ACK, ASTM_ACK, ASTM_ACK, ASTM_NAK, ASTM_NAK, ASTM_NAK]
+        );
+        assert!(matches!(connection.state, ConnectionState::WaitingForEnq));
+        assert!(connection.records.is_empty());
+        assert_eq!(connection.session_outcome, Some(ConnectionTermination::Error));
+        while let Ok(event) = receiver.try_recv() {
+            assert!(!matches!(event, MerilEvent::LabResultProcessed { .. }), "{:?}", event);
+        }
+
+        // The next ENQ starts a fresh transmission
+        let mut retry = vec![ASTM_ENQ];
+        for fixture in AUTOQUANT_200I_FRAMES {
+            retry.extend_from_slice(fixture);
+        }
+        retry.push(ASTM_EOT);
+        AutoQuantMerilService::process_astm_data(&mut connection, &retry, &sender)
+            .await
+            .unwrap();
+        assert_eq!(written_bytes(&mut peer).await, vec![ASTM_ACK; 6]);
+        assert_eq!(connection.frame_naks, 0);
+
+        let results = loop {
+            match receiver.try_recv() {
+                Ok(MerilEvent::LabResultProcessed { test_results, .. }) => break test_results,
+                Ok(_) => continue,
+                Err(e) => panic!("Expected LabResultProcessed, got {:?}", e),
+            }
+        };
+        assert_eq!(results.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_middleware_transmission_is_accepted_and_padding_flagged() {
+        let (mut connection, mut peer) = test_connection().await;
+        let (sender, mut receiver) = mpsc::channel(50);
+
+        let mut data = vec![ASTM_ENQ];
+        for fixture in MIDDLEWARE_FRAMES {
+            data.extend_from_slice(fixture);
+        }
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+
+        // ENQ and every frame ACKed; no frame was NAKed
+        let mut replies = [0u8; 6];
+        peer.read_exact(&mut replies).await.unwrap();
+        assert_eq!(replies, [ASTM_ACK; 6]);
+        assert_eq!(connection.frame_buffer.len(), 5);
+
+        let warnings: Vec<String> = connection
+            .timeline
+            .as_ref()
+            .unwrap()
+            .stages
+            .iter()
+            .flat_map(|record| record.warnings.clone())
+            .collect();
+        assert_eq!(warnings, vec!["Space before CR in frame 2", "Space before CR in frame 4"]);
+
+        AutoQuantMerilService::process_astm_data(&mut connection, &[ASTM_EOT], &sender)
+            .await
+            .unwrap();
+        let results = loop {
+            match receiver.try_recv() {
+                Ok(MerilEvent::LabResultProcessed { test_results, .. }) => break test_results,
+                Ok(_) => continue,
+                Err(e) => panic!("Expected LabResultProcessed, got {:?}", e),
+            }
+        };
+        let tests: Vec<&str> = results.iter().map(|r| r.test_id.as_str()).collect();
+        assert_eq!(tests, vec!["GLU", "CRE"]);
+    }
+
+    /// Every byte the LIS has written so far
+    async fn written_bytes(peer: &mut TcpStream) -> Vec<u8> {
+        let mut written = Vec::new();
+        let mut buffer = [0u8; 64];
+        while let Ok(Ok(n)) = timeout(Duration::from_millis(100), peer.read(&mut buffer)).await {
+            if n == 0 {
+                break;
+            }
+            written.extend_from_slice(&buffer[..n]);
+        }
+        written
+    }
+
+    /// The result frame with its value changed after the checksum was computed
+    fn corrupted_result_frame() -> Vec<u8> {
+        let good = frame("2R|1|S42|^^^GLU|95|mg/dL|70^110|N||F");
+        let value = good.windows(2).position(|pair| pair == b"95").unwrap();
+        let mut corrupted = good.clone();
+        corrupted[value + 1] = b'6';
+        corrupted
+    }
+
+    #[tokio::test]
+    async fn test_both_checksum_characters_read_before_cr() {
+        let (mut connection, mut peer) = test_connection().await;
+        let (sender, _receiver) = mpsc::channel(50);
+
+        // Captured from an AutoQuant, checksum "B4"
+        let captured: &[u8] = b"\x021H|\\^&|||AutoQuant^01^1.0|||||||P|E1394-97|20250101120000\r\x03B4\r\n";
+        let etx = captured.iter().position(|&b| b == ASTM_ETX).unwrap();
+        AutoQuantMerilService::process_astm_data(&mut connection, &[ASTM_ENQ], &sender)
+            .await
+            .unwrap();
+        AutoQuantMerilService::process_astm_data(&mut connection, &captured[..=etx + 1], &sender)
+            .await
+            .unwrap();
+        assert!(matches!(connection.state, ConnectionState::WaitingForChecksum));
+
+        AutoQuantMerilService::process_astm_data(&mut connection, &captured[etx + 2..etx + 3], &sender)
+            .await
+            .unwrap();
+        assert!(matches!(connection.state, ConnectionState::WaitingForCR));
+
+        // The checksum validates, so the default (strict) policy ACKs the frame
+        AutoQuantMerilService::process_astm_data(&mut connection, &captured[etx + 3..], &sender)
+            .await
+            .unwrap();
+        assert!(AutoQuantMerilService::validate_checksum(captured));
+        assert_eq!(written_bytes(&mut peer).await, vec![ASTM_ACK, ASTM_ACK]);
+        assert_eq!(connection.frame_buffer, vec![captured.to_vec()]);
+        assert_eq!(connection.progress.retransmissions_requested, 0);
+    }
+
+    #[tokio::test]
+    async fn test_lenient_checksum_policy_acks_and_keeps_corrupted_frame() {
+        let (mut connection, mut peer) = test_connection().await;
+        connection.checksum_policy = ChecksumPolicy::Lenient;
+        let (sender, _receiver) = mpsc::channel(50);
+
+        let mut data = vec![ASTM_ENQ];
+        data.extend(frame("1H|\\^&|||AutoQuant"));
+        data.extend(corrupted_result_frame());
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+
+        assert_eq!(written_bytes(&mut peer).await, vec![ASTM_ACK, ASTM_ACK, ASTM_ACK]);
+        assert_eq!(connection.frame_buffer.len(), 2);
+        assert_eq!(connection.progress.retransmissions_requested, 0);
+        let warnings: Vec<String> = connection
+            .timeline
+            .as_ref()
+            .unwrap()
+            .stages
+            .iter()
+            .flat_map(|record| record.warnings.clone())
+            .collect();
+        assert_eq!(warnings, vec!["Checksum mismatch in frame 2"]);
+    }
+
+    #[tokio::test]
+    async fn test_default_checksum_policy_naks_and_drops_corrupted_frame() {
+        let (mut connection, mut peer) = test_connection().await;
+        let (sender, _receiver) = mpsc::channel(50);
+
+        let mut data = vec![ASTM_ENQ];
+        data.extend(frame("1H|\\^&|||AutoQuant"));
+        data.extend(corrupted_result_frame());
+        let error = AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error, "Checksum mismatch in frame 2");
+        assert_eq!(written_bytes(&mut peer).await, vec![ASTM_ACK, ASTM_ACK, ASTM_NAK]);
+        assert_eq!(connection.frame_buffer.len(), 1);
+        assert_eq!(connection.progress.retransmissions_requested, 1);
+
+        // The retransmitted frame is accepted and kept once
+        let retransmitted = frame("2R|1|S42|^^^GLU|95|mg/dL|70^110|N||F");
+        AutoQuantMerilService::process_astm_data(&mut connection, &retransmitted, &sender)
+            .await
+            .unwrap();
+        assert_eq!(written_bytes(&mut peer).await, vec![ASTM_ACK]);
+        assert_eq!(connection.frame_buffer.len(), 2);
+        assert_eq!(connection.records.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_frame_with_a_broken_trailer_is_naked_and_resent() {
+        let (mut connection, mut peer) = test_connection().await;
+        let (sender, _receiver) = mpsc::channel(50);
+        let result = frame("2R|1|S42|^^^GLU|95|mg/dL|70^110|N||F");
+        let trailer = result.len() - 2;
+
+        let mut data = vec![ASTM_ENQ];
+        data.extend(frame("1H|\\^&|||AutoQuant"));
+        // Checksum followed straight by LF
+        data.extend_from_slice(&result[..trailer]);
+        data.push(ASTM_LF);
+        let error = AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap_err();
+        assert_eq!(error, "Invalid frame format: expected CR");
+        assert_eq!(written_bytes(&mut peer).await, vec![ASTM_ACK, ASTM_ACK, ASTM_NAK]);
+        assert!(matches!(connection.state, ConnectionState::WaitingForFrame));
+
+        // Retransmitted with CR but something other than LF after it
+        let mut data = result[..=trailer].to_vec();
+        data.push(ASTM_CR);
+        let error = AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap_err();
+        assert_eq!(error, "Invalid frame format: expected LF");
+        assert_eq!(written_bytes(&mut peer).await, vec![ASTM_NAK]);
+        assert!(matches!(connection.state, ConnectionState::WaitingForFrame));
+        assert_eq!(connection.progress.retransmissions_requested, 2);
+
+        // The intact retransmission is accepted
+        AutoQuantMerilService::process_astm_data(&mut connection, &result, &sender)
+            .await
+            .unwrap();
+        assert_eq!(written_bytes(&mut peer).await, vec![ASTM_ACK]);
+        assert_eq!(connection.frame_buffer.len(), 2);
+        assert_eq!(connection.frame_naks, 0);
+    }
+
+    #[tokio::test]
+    async fn test_shadow_mode_acks_invalid_frame() {
+        let (mut connection, mut peer) = test_connection().await;
+        connection.shadow_mode = ShadowMode::new(true);
+        let (sender, _receiver) = mpsc::channel(10);
+
+        // Truncated frame that fails processing
+        let data = [ASTM_ENQ, ASTM_STX, ASTM_ETX, b'0', b'0', ASTM_CR, ASTM_LF];
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+
+        assert!(matches!(connection.state, ConnectionState::WaitingForFrame));
+        assert!(connection.current_frame.is_empty());
+
+        // ACK for ENQ, then a benign ACK instead of NAK
+        let mut replies = [0u8; 2];
+        peer.read_exact(&mut replies).await.unwrap();
+        assert_eq!(replies, [ASTM_ACK, ASTM_ACK]);
+    }
+
+    #[tokio::test]
+    async fn test_protective_mode_acks_eot_only_once_results_are_stored() {
+        let (mut connection, mut peer) = test_connection().await;
+        connection.protective_mode = ProtectiveMode::new(true);
+        let (sender, mut receiver) = mpsc::channel(10);
+
+        // Ingestion stores the first transmission and fails to store the second
+        let ingestion = tokio::spawn(async move {
+            let mut outcomes = vec![Ok(()), Err("disk full".to_string())].into_iter();
+            while let Some(event) = receiver.recv().await {
+                if let MerilEvent::LabResultProcessed { store_receipt, .. } = event {
+                    store_receipt.report(outcomes.next().unwrap());
+                }
+            }
+        });
+
+        AutoQuantMerilService::process_astm_data(&mut connection, &[ASTM_ENQ, ASTM_EOT], &sender)
+            .await
+            .unwrap();
+        assert_eq!(connection.session_outcome, Some(ConnectionTermination::Completed));
+        AutoQuantMerilService::process_astm_data(&mut connection, &[ASTM_ENQ, ASTM_EOT], &sender)
+            .await
+            .unwrap();
+        assert_eq!(connection.session_outcome, Some(ConnectionTermination::Error));
+        assert!(connection.pending_receipts.is_empty());
+
+        // The unstored transmission's EOT is NAKed so the analyzer keeps its results
+        let mut replies = [0u8; 4];
+        peer.read_exact(&mut replies).await.unwrap();
+        assert_eq!(replies, [ASTM_ACK, ASTM_ACK, ASTM_ACK, ASTM_NAK]);
+
+        drop(sender);
+        ingestion.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stray_ack_during_frame_is_not_appended() {
+        let (mut connection, _peer) = test_connection().await;
+        let (sender, _receiver) = mpsc::channel(10);
+
+        let mut data = vec![ASTM_ENQ, ASTM_STX];
+        data.extend_from_slice(b"1H|");
+        data.push(ASTM_NAK);
+        data.push(ASTM_ACK);
+        data.extend_from_slice(b"||");
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+
+        assert!(matches!(connection.state, ConnectionState::ProcessingFrame));
+        let mut expected = vec![ASTM_STX];
+        expected.extend_from_slice(b"1H|||");
+        assert_eq!(connection.current_frame, expected);
+    }
+
+    #[tokio::test]
+    async fn test_header_version_detected_and_stored() {
+        let (mut connection, _peer) = test_connection().await;
+        let (sender, mut receiver) = mpsc::channel(10);
+
+        let mut data = vec![ASTM_ENQ];
+        data.extend(frame("1H|\\^&|||AutoQuant^200i^AQ-01|||||||P|LIS2-A2|20250101100000"));
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+        assert_eq!(connection.astm_version, AstmVersion::Lis2A2);
+
+        let header = loop {
+            match receiver.try_recv() {
+                Ok(MerilEvent::HeaderReceived { header, .. }) => break header,
+                Ok(_) => continue,
+                Err(e) => panic!("Expected HeaderReceived, got {:?}", e),
+            }
+        };
+        assert_eq!(header.sender_id.as_deref(), Some("AutoQuant^200i^AQ-01"));
+        assert_eq!(header.version.as_deref(), Some("LIS2-A2"));
+
+        // Repeated flags are split (dropping empty repeats) for LIS2-A2 senders only
+        let frame_data = b"1R|1|2|^^^GLU|250|mg/dL|70^110|H\\\\A||F";
+        let lis2 = parse_result(frame_data, connection.astm_version);
+        assert_eq!(lis2.flag_list(), vec!["H".to_string(), "A".to_string()]);
+        assert_eq!(lis2.flags.unwrap().abnormal_flag.as_deref(), Some("H\\A"));
+        let e1394 = parse_result(frame_data, AstmVersion::E1394);
+        assert_eq!(e1394.flags.unwrap().abnormal_flag.as_deref(), Some("H\\\\A"));
+
+        // The declared identity is stored against the analyzer
+        let store = Arc::new(InMemoryConfigStore::new());
+        let analyzer = crate::test_support::meril_analyzer();
+        let (service_sender, _service_receiver) = mpsc::channel(10);
+        let service = AutoQuantMerilService::new(analyzer, service_sender, store.clone(), ShadowMode::default());
+
+        assert!(service.record_astm_header(&header).await.unwrap());
+        assert!(!service.record_astm_header(&header).await.unwrap());
+        let stored: crate::api::commands::meril_handler::MerilStoreData =
+            serde_json::from_value(store.get("config").unwrap()).unwrap();
+        assert_eq!(stored.analyzer.unwrap().astm_version.as_deref(), Some("LIS2-A2"));
+    }
+
+    #[tokio::test]
+    async fn test_parsed_result_survives_event_serialization_and_persistence() {
+        use crate::db::{establish_test_connection, SqliteRepository};
+        use crate::models::{DataSource, Patient};
+
+        let frame_data = b"1R|1|S100|^^^GLU^10|35.2|mg/dL|70^110|H\\L|N|P||OP01^SUP02|||AQ-200i-01";
+        let mut result = parse_result(frame_data, AstmVersion::Lis2A2);
+        AutoQuantMerilService::apply_dilution_mode(&mut result, DilutionMode::PreDilution);
+        result.analyzer_id = Some("meril-1".to_string());
+
+        assert_eq!(result.reference_range, ReferenceRange::parse("70^110"));
+        assert_eq!(result.status, ResultStatus::Preliminary);
+        assert_eq!(result.flags.as_ref().unwrap().nature_of_abnormality.as_deref(), Some("N"));
+        assert_eq!(result.flag_list(), vec!["H", "L", DILUTED_FLAG]);
+
+        // Event payloads keep the range and flags readable for the frontend
+        let event = MerilEvent::ProvisionalResult {
+            analyzer_id: "meril-1".to_string(),
+            transmission_id: "T1".to_string(),
+            result: result.clone(),
+            timestamp: Utc::now(),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        let payload = &json["ProvisionalResult"]["result"];
+        assert_eq!(payload["reference_range"], "70-110");
+        assert_eq!(payload["flags"]["abnormal_flag"], "H\\L");
+        match serde_json::from_value::<MerilEvent>(json).unwrap() {
+            MerilEvent::ProvisionalResult { result: decoded, .. } => assert_eq!(decoded, result),
+            other => panic!("Expected ProvisionalResult, got {:?}", other),
+        }
+
+        // ...and the repository stores every field
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        repository.save_patient(&Patient::fixture("P1"), &DataSource::Analyzer).await.unwrap();
+        repository.save_test_result(&result, "P1", &DataSource::Analyzer).await.unwrap();
+
+        let stored = repository.get_results_by_sample_id("S100").await.unwrap();
+        assert_eq!(stored, vec![result]);
+    }
+
+    #[tokio::test]
+    async fn test_latency_recorded_for_processed_message() {
+        use crate::db::{establish_test_connection, SqliteRepository};
+        use crate::models::RawMessage;
+
+        let (mut connection, mut peer) = test_connection().await;
+        let (sender, mut receiver) = mpsc::channel(50);
+        let mut data = vec![ASTM_ENQ];
+        for record in ["1H|\\^&|||AutoQuant", "2R|1|S42|^^^GLU|95|mg/dL|70^110|N||F", "3L|1|N"] {
+            data.extend(frame(record));
+        }
+        data.push(ASTM_EOT);
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+        let mut replies = [0u8; 5];
+        peer.read_exact(&mut replies).await.unwrap();
+
+        let (raw_data, mut timeline) = loop {
+            match receiver.try_recv() {
+                Ok(MerilEvent::LabResultProcessed { raw_data, timeline, .. }) => break (raw_data, timeline),
+                Ok(_) => continue,
+                Err(e) => panic!("Expected LabResultProcessed: {}", e),
+            }
+        };
+
+        // Stored the way the ingestion lane stores it
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let received_at = timeline.stage_at(ProcessingStage::Received).unwrap();
+        let message = RawMessage {
+            id: "M1".to_string(),
+            analyzer_id: "test-analyzer".to_string(),
+            protocol: "ASTM".to_string(),
+            message_type: "Result".to_string(),
+            raw_data,
+            timeline: timeline.clone(),
+            upload_id: None,
+            received_at,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        repository.save_raw_message(&message).await.unwrap();
+        timeline.mark(ProcessingStage::Persisted);
+        repository.update_raw_message_timeline("M1", &timeline, None).await.unwrap();
+
+        let stats = repository.get_latency_stats(received_at - chrono::Duration::seconds(1)).await.unwrap();
+        assert_eq!(stats.messages, 1);
+        for (from, to) in [
+            (ProcessingStage::Received, ProcessingStage::Parsed),
+            (ProcessingStage::Parsed, ProcessingStage::Persisted),
+            (ProcessingStage::Received, ProcessingStage::Persisted),
+        ] {
+            let histogram = stats.segment(from, to).unwrap();
+            assert_eq!(histogram.count, 1, "{:?} -> {:?}", from, to);
+            assert!(histogram.min_ms.unwrap() >= 0);
+            assert_eq!(histogram.buckets.iter().map(|bucket| bucket.count).sum::<u64>(), 1);
+        }
+        // Not delivered to the HIS yet
+        assert_eq!(stats.segment(ProcessingStage::Received, ProcessingStage::Uploaded).unwrap().count, 0);
+    }
+
+    #[test]
+    fn test_escaped_field_separator_in_patient_name() {
+        let frame_data = b"2P|1||PAT001|||O&|Brien^Mary||19800512|F";
+
+        let patient =
+            AutoQuantMerilService::parse_patient_record(frame_data, &AstmDelimiters::default(), &SexCodeMap::default())
+                .unwrap();
+
+        assert_eq!(patient.id, "PAT001");
+        assert_eq!(patient.name, "Mary O|Brien");
+        assert_eq!(patient.birth_date.as_deref(), Some("19800512"));
+        assert_eq!(patient.sex.as_deref(), Some("F"));
+    }
+
+    #[test]
+    fn test_escape_sequences_decoded_into_patient_and_result() {
+        let delimiters = AstmDelimiters::default();
+        let patient = AutoQuantMerilService::parse_patient_record(
+            b"2P|1||PAT&F&7|||O'Brien&S&Anne &E& Co^Mary||19800512|F",
+            &delimiters,
+            &SexCodeMap::default(),
+        )
+        .unwrap();
+        assert_eq!(patient.id, "PAT|7");
+        assert_eq!(patient.name, "Mary O'Brien^Anne & Co");
+
+        let result = parse_result(b"3R|1|S&R&42|^^^HBSAG|POS&S&1:40|mg&F&dL||N||F", AstmVersion::E1394);
+        assert_eq!(result.sample_id, "S\\42");
+        assert_eq!(result.value, "POS^1:40");
+        assert_eq!(result.units.as_deref(), Some("mg|dL"));
+        assert_eq!(result.test_id, "HBSAG");
+    }
+
+    #[test]
+    fn test_configured_numeric_sex_codes() {
+        use crate::models::patient::Sex;
+
+        let sex_codes: SexCodeMap = serde_json::from_str(r#"{"1": "Male", "2": "Female"}"#).unwrap();
+        let parse = |frame_data: &[u8]| {
+            AutoQuantMerilService::parse_patient_record(frame_data, &AstmDelimiters::default(), &sex_codes)
+                .unwrap()
+                .sex
+                .map(|code| Sex::from(code.as_str()))
+        };
+
+        assert_eq!(parse(b"2P|1||PAT001|||Doe^John||19800512|1"), Some(Sex::Male));
+        assert_eq!(parse(b"2P|1||PAT002|||Doe^Jane||19800512|2"), Some(Sex::Female));
+        // Standard codes still work next to the configured ones
+        assert_eq!(parse(b"2P|1||PAT003|||Doe^Jane||19800512|F"), Some(Sex::Female));
+        assert_eq!(parse(b"2P|1||PAT004|||Doe^Jo||19800512|0"), Some(Sex::Other));
+    }
+
+    #[test]
+    fn test_parse_result_record_operator_and_instrument() {
+        let frame_data =
+            b"1R|1|2|^^^ALB|3.5|g/dL|3.4^5.4|N||F||OP01^SUP02|20250101100000|20250101101500|AQ-200i-01";
+
+        let result = parse_result(frame_data, AstmVersion::E1394);
+
+        assert_eq!(result.metadata.operator.as_deref(), Some("OP01"));
+        assert_eq!(result.metadata.instrument.as_deref(), Some("AQ-200i-01"));
+        assert_eq!(result.metadata.sequence_number, 2);
+    }
+
+    #[tokio::test]
+    async fn test_results_dated_outside_acceptance_window_are_rejected() {
+        let (mut connection, _peer) = test_connection().await;
+        connection.result_acceptance = ResultAcceptanceWindow {
+            max_past_days: Some(30),
+            max_future_days: Some(1),
+        };
+        let (sender, mut receiver) = mpsc::channel(50);
+
+        let today = Utc::now().format("%Y%m%d%H%M%S");
+        let mut data = vec![ASTM_ENQ];
+        data.extend(frame("1H|\\^&|||AutoQuant"));
+        data.extend(frame("2R|1|S42|^^^GLU|95|mg/dL|70^110|N||F||||20150101120500"));
+        data.extend(frame("3R|2|S42|^^^ALB|3.5|g/dL|3.4^5.4|N||F||||20990101120500"));
+        data.extend(frame(&format!("4R|3|S42|^^^CRE|0.9|mg/dL|0.6^1.2|N||F||||{}", today)));
+        data.extend(frame("5L|1|N"));
+        data.push(ASTM_EOT);
+        AutoQuantMerilService::process_astm_data(&mut connection, &data, &sender)
+            .await
+            .unwrap();
+
+        let mut rejected = Vec::new();
+        let results = loop {
+            match receiver.try_recv() {
+                Ok(MerilEvent::RejectedStaleResult { result, reason, .. }) => rejected.push((result.test_id, reason)),
+                Ok(MerilEvent::LabResultProcessed { test_results, .. }) => break test_results,
+                Ok(_) => continue,
+                Err(e) => panic!("Expected LabResultProcessed, got {:?}", e),
+            }
+        };
+
+        // The far-past and far-future results are reported and dropped; the current one is kept
+        assert_eq!(rejected.len(), 2);
+        assert_eq!(rejected[0].0, "GLU");
+        assert!(rejected[0].1.contains("more than 30 days before"), "{}", rejected[0].1);
+        assert_eq!(rejected[1].0, "ALB");
+        assert!(rejected[1].1.contains("more than 1 days after"), "{}", rejected[1].1);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].test_id, "CRE");
+    }
+
+    #[test]
+    fn test_completion_date_read_from_result_record() {
+        let result = parse_result(
+            b"1R|1|2|^^^ALB|3.5|g/dL|3.4^5.4|N||F||OP01^SUP02|20250101100000|20250101101500|AQ-200i-01",
+            AstmVersion::E1394,
+        );
+        assert_eq!(result.completed_date_time.unwrap().to_rfc3339(), "2025-01-01T10:15:00+00:00");
+
+        // An analyzer clock running 90 seconds fast is corrected
+        let corrected = AutoQuantMerilService::parse_result_record(
+            b"1R|1|2|^^^ALB|3.5|g/dL|3.4^5.4|N||F||OP01^SUP02|20250101100000|20250101101500|AQ-200i-01",
+            AstmVersion::E1394,
+            &AstmDelimiters::default(),
+            90,
+        )
+        .unwrap();
+        assert_eq!(corrected.completed_date_time.unwrap().to_rfc3339(), "2025-01-01T10:13:30+00:00");
+
+        // Without a completion date the result is dated on receipt
+        let undated = parse_result(b"1R|1|2|^^^ALB|3.5|g/dL|3.4^5.4|N||F", AstmVersion::E1394);
+        let window = ResultAcceptanceWindow {
+            max_past_days: Some(1),
+            max_future_days: Some(1),
+        };
+        assert!(window.rejection(undated.completed_date_time.unwrap(), Utc::now()).is_none());
+        assert!(ResultAcceptanceWindow::default()
+            .rejection(result.completed_date_time.unwrap(), Utc::now())
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_result_record_without_operator_and_instrument() {
+        let frame_data = b"1R|1|2|^^^ALB|3.5|g/dL|3.4^5.4|N||F";
+
+        let result = parse_result(frame_data, AstmVersion::E1394);
+
+        assert!(result.metadata.operator.is_none());
+        assert!(result.metadata.instrument.is_none());
+    }
+
+    #[test]
+    fn test_dilution_factor_applied_for_pre_dilution_analyzer() {
+        let frame_data = b"1R|1|2|^^^GLU^10|35.2|mg/dL|70^110|H||F";
+
+        let mut result = parse_result(frame_data, AstmVersion::E1394);
+        assert_eq!(result.test_id, "GLU");
+        assert_eq!(result.metadata.dilution_factor, Some(10.0));
+
+        AutoQuantMerilService::apply_dilution_mode(&mut result, DilutionMode::PreDilution);
+        assert_eq!(result.value, "352.0");
+        assert_eq!(result.metadata.raw_value.as_deref(), Some("35.2"));
+        assert_eq!(result.flag_list(), vec!["H".to_string(), DILUTED_FLAG.to_string()]);
+    }
+
+    #[test]
+    fn test_dilution_factor_kept_for_post_dilution_analyzer() {
+        let frame_data = b"1R|1|2|^^^GLU^10|352|mg/dL|70^110|H||F";
+
+        let mut result = parse_result(frame_data, AstmVersion::E1394);
+        AutoQuantMerilService::apply_dilution_mode(&mut result, DilutionMode::PostDilution);
+
+        assert_eq!(result.value, "352");
+        assert_eq!(result.metadata.dilution_factor, Some(10.0));
+        assert!(result.metadata.raw_value.is_none());
+        assert!(result.flag_list().contains(&DILUTED_FLAG.to_string()));
+    }
+
+    #[test]
+    fn test_result_without_dilution_factor_is_unchanged() {
+        let frame_data = b"1R|1|2|^^^GLU|95|mg/dL|70^110|N||F";
+
+        let mut result = parse_result(frame_data, AstmVersion::E1394);
+        AutoQuantMerilService::apply_dilution_mode(&mut result, DilutionMode::PreDilution);
+
+        assert_eq!(result.value, "95");
+        assert!(result.metadata.dilution_factor.is_none());
+        assert!(result.metadata.raw_value.is_none());
+        assert_eq!(result.flag_list(), vec!["N".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_service_start_and_stop_persist_config_in_memory() {
+        let store = Arc::new(InMemoryConfigStore::new());
+        let mut analyzer = crate::test_support::meril_analyzer();
+        analyzer.port = Some(0);
+        let (sender, mut receiver) = mpsc::channel(10);
+        let service = AutoQuantMerilService::new(analyzer, sender, store.clone(), ShadowMode::default());
+
+        service.start().await.unwrap();
+        let stored: crate::api::commands::meril_handler::MerilStoreData = serde_json::from_value(store.get("config").unwrap()).unwrap();
+        assert_eq!(stored.analyzer.unwrap().status, AnalyzerStatus::Active);
+        assert_eq!(service.get_status().await, AnalyzerStatus::Active);
+
+        service.stop().await.unwrap();
+        let stored: crate::api::commands::meril_handler::MerilStoreData = serde_json::from_value(store.get("config").unwrap()).unwrap();
+        assert_eq!(stored.analyzer.unwrap().status, AnalyzerStatus::Inactive);
+        assert_eq!(service.get_status().await, AnalyzerStatus::Inactive);
+
+        assert!(matches!(
+            receiver.recv().await,
+            Some(MerilEvent::AnalyzerStatusUpdated { status: AnalyzerStatus::Active, .. })
+        ));
+        assert!(matches!(
+            receiver.recv().await,
+            Some(MerilEvent::AnalyzerStatusUpdated { status: AnalyzerStatus::Inactive, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_paused_listener_refuses_new_connections() {
+        let store = Arc::new(InMemoryConfigStore::new());
+        let mut analyzer = crate::test_support::meril_analyzer();
+        analyzer.port = Some(0);
+        let (sender, _receiver) = mpsc::channel(10);
+        let service = AutoQuantMerilService::new(analyzer, sender, store, ShadowMode::default());
+        service.start().await.unwrap();
+
+        let port = service.local_addr().await.unwrap().port();
+        assert!(service.pause_listening().await);
+        assert!(!service.pause_listening().await);
+        assert!(service.local_addr().await.is_none());
+        assert!(TcpStream::connect(("127.0.0.1", port)).await.is_err());
+
+        service.resume_listening().await.unwrap();
+        let port = service.local_addr().await.unwrap().port();
+        assert!(TcpStream::connect(("127.0.0.1", port)).await.is_ok());
+
+        service.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connections_over_the_per_ip_limit_are_rejected() {
+        let store = Arc::new(InMemoryConfigStore::new());
+        let mut analyzer = crate::test_support::meril_analyzer();
+        analyzer.port = Some(0);
+        analyzer.max_connections_per_ip = Some(1);
+        let (sender, mut receiver) = mpsc::channel(10);
+        let service = AutoQuantMerilService::new(analyzer, sender, store, ShadowMode::default());
+        service.start().await.unwrap();
+        let port = service.local_addr().await.unwrap().port();
+
+        let _first = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        loop {
+            match timeout(Duration::from_secs(5), receiver.recv()).await.unwrap() {
+                Some(MerilEvent::AnalyzerConnected { .. }) => break,
+                Some(MerilEvent::ConnectionRejected { reason, .. }) => panic!("first connection rejected: {}", reason),
+                Some(_) => continue,
+                None => panic!("event channel closed"),
+            }
+        }
+
+        // The second connection from the same IP is closed straight away
+        let mut second = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let (remote_addr, reason) = loop {
+            match timeout(Duration::from_secs(5), receiver.recv()).await.unwrap() {
+                Some(MerilEvent::ConnectionRejected { remote_addr, reason, .. }) => break (remote_addr, reason),
+                Some(MerilEvent::AnalyzerConnected { remote_addr, .. }) => panic!("{} was admitted", remote_addr),
+                Some(_) => continue,
+                None => panic!("event channel closed"),
+            }
+        };
+        assert_eq!(remote_addr, second.local_addr().unwrap().to_string());
+        assert!(reason.contains("the limit per IP is 1"), "{}", reason);
+        let mut buffer = [0u8; 8];
+        let read = timeout(Duration::from_secs(5), second.read(&mut buffer)).await.unwrap();
+        assert!(matches!(read, Ok(0) | Err(_)));
+        assert_eq!(service.get_connections_count().await, 1);
+
+        service.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delimited_text_dump_is_decoded_per_sample_without_inventing_times() {
+        let (mut connection, mut peer) = test_connection().await;
+        let mut analyzer = crate::test_support::meril_analyzer();
+        analyzer.protocol = crate::models::Protocol::DelimitedText;
+        connection.decoder = decoder_for(&analyzer).map(Arc::from);
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        connections.write().await.insert("test-analyzer".to_string(), connection);
+        let (sender, mut receiver) = mpsc::channel(100);
+        tokio::spawn(AutoQuantMerilService::handle_connection(
+            connections,
+            sender,
+            "test-analyzer".to_string(),
+            Arc::new(Mutex::new(VecDeque::new())),
+        ));
+
+        // The device dumps its results and hangs up; nothing is acknowledged
+        peer.write_all(b"S1,GLU,95,mg/dL,N,20250101120500\r\nS2,NA,141,mmol/L,,20250101120510\r\n")
+            .await
+            .unwrap();
+        peer.write_all(b"S1,UREA,30,mg/dL,,\r\n").await.unwrap();
+        peer.shutdown().await.unwrap();
+
+        let mut samples = Vec::new();
+        let mut errors = Vec::new();
+        loop {
+            match timeout(Duration::from_secs(2), receiver.recv()).await.unwrap() {
+                Some(MerilEvent::LabResultProcessed { test_results, patient_id, .. }) => {
+                    assert!(patient_id.is_none());
+                    assert!(test_results.iter().all(|result| result.analyzer_id.as_deref() == Some("test-analyzer")));
+                    let tests: Vec<String> = test_results.iter().map(|result| result.test_id.clone()).collect();
+                    samples.push((test_results[0].sample_id.clone(), tests));
+                }
+                Some(MerilEvent::Error { error, .. }) => errors.push(error),
+                Some(MerilEvent::AnalyzerDisconnected { .. }) => break,
+                Some(_) => continue,
+                None => panic!("event channel closed"),
+            }
+        }
+        assert_eq!(
+            samples,
+            vec![
+                ("S1".to_string(), vec!["GLU".to_string()]),
+                ("S2".to_string(), vec!["NA".to_string()])
+            ]
+        );
+        // The UREA line has no completion time; it is rejected, not dated on receipt
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("no completion time"), "{}", errors[0]);
+        let mut reply = [0u8; 1];
+        assert_eq!(peer.read(&mut reply).await.unwrap(), 0);
+    }
 }
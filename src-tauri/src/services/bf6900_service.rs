@@ -4,21 +4,39 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use chrono::{DateTime, Utc};
-use tauri::Runtime;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{mpsc, Mutex, RwLock};
 use tokio::time::timeout;
 
-use crate::models::{Analyzer, AnalyzerStatus};
-use crate::models::hematology::{BF6900Event, HematologyResult, PatientData};
+use crate::models::{Analyzer, AnalyzerIdentity, AnalyzerStatus, ConnectionTermination, DilutionMode, FirmwareChange};
+use crate::models::result::parse_dilution_factor;
+use crate::models::hematology::{BF6900Event, HematologyResult, HematologyRunInfo, PatientData};
 use crate::api::commands::bf6900_handler::BF6900StoreData;
+use crate::protocol::contact::{parse_xad, parse_xtn_repeats};
+use crate::protocol::ed_image::{decode_ed_png, ed_part_order, join_ed_parts};
+use crate::protocol::message_profile::{message_profile_from_store, MessageProfile, MESSAGE_PROFILE_STORE_KEY};
+use crate::services::config_persistence::{
+    reset_stale_status, ConfigPersistence, StatusPersister, STATUS_PERSIST_INTERVAL,
+};
+use crate::services::conformance::check_hl7;
+use crate::services::developer_console::{describe_bytes, RawExchange};
+use crate::services::firmware_tracking::{self, conformance_review_detail, hl7_identity, IdentityObservation};
+use crate::services::log_sampling::LogSampler;
+use crate::services::conversation_log::{
+    ConversationElement, ConversationLog, ConversationRecorder, Direction, EntryOutcome,
+};
+use crate::services::listen_address::{bind_tcp_listener, ConnectionLimiter, ConnectionPermit};
+use crate::services::disk_monitor::{wait_until_stored, ProtectiveMode, StoreReceipt, STORE_CONFIRMATION_TIMEOUT};
+use crate::services::shadow_mode::ShadowMode;
 use crate::protocol::hl7_parser::{
-    HL7ConnectionState, HL7Message, OBXSegment, PIDSegment, CelquantIdentificationMessage,
-    parse_hl7_message, create_hl7_acknowledgment,
+    AcknowledgmentMode, HL7ConnectionState, HL7Message, OBXSegment, PIDSegment, CelquantIdentificationMessage,
+    parse_hl7_message, create_hl7_acknowledgment_with_profile,
     extract_parameter_name, extract_parameter_code, extract_abnormal_flags, 
-    parse_pid_segment, parse_obx_segment, parse_msa_segment, parse_orc_segment,
-    is_supported_message_type, is_celquant_identification, parse_celquant_identification, create_celquant_ack
+    parse_hl7_datetime, parse_pid_segment, parse_pv1_segment, parse_obx_segment, parse_nte_segment, parse_msa_segment,
+    parse_obr_segment, parse_orc_segment,
+    is_supported_message_type, is_celquant_identification, is_histogram_parameter, is_metadata_parameter,
+    parse_celquant_identification, create_celquant_ack
 };
 
 // ============================================================================
@@ -36,6 +54,17 @@ pub struct HL7Connection {
     pub last_activity: DateTime<Utc>, // Track connection activity
     pub retry_count: u32,            // Track retry attempts
     pub health_status: ConnectionHealthStatus,
+    pub shadow_mode: ShadowMode,     // Answer AA instead of AE while shadow mode is on
+    pub protective_mode: ProtectiveMode, // Low on disk: accept a message only once its results are stored
+    pub message_profile: MessageProfile, // Shape of outbound ACKs for this analyzer
+    pub dilution_mode: DilutionMode, // Whether reported values still need the dilution applied
+    pub default_patient_class: Option<String>, // Uploaded to the HIS when a message has no PV1-2
+    pub clock_skew_seconds: i64,     // Configured skew, used when a message carries no MSH-7
+    pub conversation: ConversationRecorder, // Protocol elements exchanged, for sequence diagrams
+    pub log_sampler: Arc<LogSampler>, // Shared by the analyzer's connections; picks which messages log at info
+    pub analyzer: Arc<RwLock<Analyzer>>, // Shared with the service; its conformance-report window holds deviating messages
+    pub session_outcome: Option<ConnectionTermination>, // Completed once every message received has been answered
+    pub permit: Option<ConnectionPermit>, // Slot of the remote IP under the per-IP limit, freed with the connection
 }
 
 #[derive(Debug, Clone)]
@@ -49,7 +78,7 @@ pub enum ConnectionHealthStatus {
 // MAIN BF-6900 SERVICE (CQ 5 Plus)
 // ============================================================================
 
-pub struct BF6900Service<R: Runtime> {
+pub struct BF6900Service {
     /// Analyzer configuration
     analyzer: Arc<RwLock<Analyzer>>,
     /// TCP listener for incoming connections
@@ -61,16 +90,32 @@ pub struct BF6900Service<R: Runtime> {
     /// Service status
     is_running: Arc<RwLock<bool>>,
     /// Store for configuration persistence
-    store: Arc<tauri_plugin_store::Store<R>>,
+    store: Arc<dyn ConfigPersistence>,
+    /// Global store-only switch
+    shadow_mode: ShadowMode,
+    /// Set by the disk monitor when space is critical
+    protective_mode: ProtectiveMode,
+    /// Per-connection conversations, including recently closed ones
+    conversation_log: ConversationLog,
+    /// Picks which processed messages are logged at info, shared by all connections
+    log_sampler: Arc<LogSampler>,
+    /// Coalesces status writes so a flapping connection does not rewrite the store
+    status_persister: StatusPersister,
 }
 
-impl<R: Runtime> BF6900Service<R> {
+impl BF6900Service {
     /// Creates a new BF6900 service
     pub fn new(
         analyzer: Analyzer,
         event_sender: mpsc::Sender<BF6900Event>,
-        store: Arc<tauri_plugin_store::Store<R>>,
+        store: Arc<dyn ConfigPersistence>,
+        shadow_mode: ShadowMode,
     ) -> Self {
+        let mut analyzer = analyzer;
+        reset_stale_status(&mut analyzer);
+        let log_sampler = Arc::new(LogSampler::new(analyzer.log_sample_rate));
+        log_sampler.capture_until(analyzer.conformance_report_until);
+        let status_persister = StatusPersister::new(store.clone(), STATUS_PERSIST_INTERVAL);
         Self {
             analyzer: Arc::new(RwLock::new(analyzer)),
             listener: Arc::new(Mutex::new(None)),
@@ -78,38 +123,38 @@ impl<R: Runtime> BF6900Service<R> {
             event_sender,
             is_running: Arc::new(RwLock::new(false)),
             store,
+            shadow_mode,
+            protective_mode: ProtectiveMode::default(),
+            conversation_log: ConversationLog::default(),
+            log_sampler,
+            status_persister,
         }
     }
 
+    /// Holds back the accept ACK until the results are stored while the disk monitor's
+    /// protective mode is on
+    pub fn with_protective_mode(mut self, protective_mode: ProtectiveMode) -> Self {
+        self.protective_mode = protective_mode;
+        self
+    }
+
+    /// Conversations of open and recently closed connections
+    pub fn conversation_log(&self) -> &ConversationLog {
+        &self.conversation_log
+    }
+
     /// Starts the service
     pub async fn start(&self) -> Result<(), String> {
         let port = {
             let analyzer = self.analyzer.read().await;
             analyzer.port.ok_or("No port configured")?
         };
-        let bind_addr = format!("0.0.0.0:{}", port);
 
         log::info!("🚀 STARTING BF-6900 EXTERNAL CONNECTION SERVICE");
-        log::info!("   🌐 Bind Address: {}", bind_addr);
+        log::info!("   🔌 Port: {}", port);
         log::info!("   🔗 Protocol: HL7 v2.4 with MLLP framing");
 
-        // Create TCP listener
-        let listener = TcpListener::bind(&bind_addr)
-            .await
-            .map_err(|e| {
-                log::error!("❌ FAILED TO START EXTERNAL CONNECTION SERVICE");
-                log::error!("   🌐 Address: {}", bind_addr);
-                log::error!("   🚨 Error: {}", e);
-                format!("Failed to bind to {}: {}", bind_addr, e)
-            })?;
-
-        log::info!("✅ TCP LISTENER READY FOR EXTERNAL CONNECTIONS");
-
-        // Store listener in mutex
-        {
-            let mut listener_guard = self.listener.lock().await;
-            *listener_guard = Some(listener);
-        }
+        self.bind_listener(port).await?;
 
         *self.is_running.write().await = true;
 
@@ -121,8 +166,8 @@ impl<R: Runtime> BF6900Service<R> {
             analyzer.id.clone()
         };
 
-        // Save updated analyzer to store
-        self.save_analyzer_to_store().await?;
+        // Persist the status change, coalesced with any other within the interval
+        self.persist_status().await?;
 
         // Emit status update event
         let _ = self
@@ -139,15 +184,48 @@ impl<R: Runtime> BF6900Service<R> {
         log::info!("   🔗 Ready for external laboratory system connections");
         log::info!("   📡 HL7 v2.4 protocol active with MLLP framing");
 
-        // Start the connection handler in a separate thread
+        self.spawn_accept_loop().await;
+
+        Ok(())
+    }
+
+    /// Binds the listener on the given port and the configured bind address
+    async fn bind_listener(&self, port: u16) -> Result<(), String> {
+        let (bind_address, dual_stack, backlog) = {
+            let analyzer = self.analyzer.read().await;
+            (analyzer.bind_address.clone(), analyzer.dual_stack, analyzer.listen_backlog)
+        };
+
+        // Create TCP listener
+        let (listener, family) = bind_tcp_listener(bind_address.as_deref(), port, dual_stack, backlog)
+            .map_err(|e| {
+                log::error!("❌ FAILED TO START EXTERNAL CONNECTION SERVICE");
+                log::error!("   🌐 Address: {}", bind_address.as_deref().unwrap_or("0.0.0.0"));
+                log::error!("   🚨 Error: {}", e);
+                e
+            })?;
+
+        log::info!("✅ TCP LISTENER READY FOR EXTERNAL CONNECTIONS");
+        log::info!("   🌐 Address family: {:?}", family);
+
+        // Store listener in mutex
+        *self.listener.lock().await = Some(listener);
+        Ok(())
+    }
+
+    /// Starts the connection handler in a separate task
+    async fn spawn_accept_loop(&self) {
         let connections = self.connections.clone();
         let is_running = self.is_running.clone();
         let event_sender = self.event_sender.clone();
-        let analyzer_id = {
-            let analyzer = self.analyzer.read().await;
-            analyzer.id.clone()
-        };
+        let analyzer = self.analyzer.read().await.clone();
+        let shared_analyzer = self.analyzer.clone();
         let listener = self.listener.clone();
+        let shadow_mode = self.shadow_mode.clone();
+        let protective_mode = self.protective_mode.clone();
+        let message_profile = message_profile_from_store(self.store.get(MESSAGE_PROFILE_STORE_KEY));
+        let conversation_log = self.conversation_log.clone();
+        let log_sampler = self.log_sampler.clone();
 
         tokio::spawn(async move {
             Self::handle_connections_loop(
@@ -155,25 +233,57 @@ impl<R: Runtime> BF6900Service<R> {
                 connections,
                 is_running,
                 event_sender,
-                analyzer_id,
+                analyzer,
+                shared_analyzer,
+                shadow_mode,
+                protective_mode,
+                message_profile,
+                conversation_log,
+                log_sampler,
             )
             .await;
         });
+    }
 
-        Ok(())
+    /// Closes the listener so new connections are refused while established
+    /// ones keep running. Returns false if the service was not listening.
+    pub async fn pause_listening(&self) -> bool {
+        let paused = self.listener.lock().await.take().is_some();
+        if paused {
+            log::info!("⏸️  BF-6900 service stopped accepting new connections");
+        }
+        paused
     }
 
-    /// Stops the service
-    pub async fn stop(&self) -> Result<(), String> {
-        log::info!("🛑 STOPPING BF-6900 EXTERNAL CONNECTION SERVICE");
+    /// Rebinds the listener after [`pause_listening`](Self::pause_listening)
+    pub async fn resume_listening(&self) -> Result<(), String> {
+        if !*self.is_running.read().await || self.listener.lock().await.is_some() {
+            return Ok(());
+        }
 
-        *self.is_running.write().await = false;
+        let port = self.analyzer.read().await.port.ok_or("No port configured")?;
+        self.bind_listener(port).await?;
+        self.spawn_accept_loop().await;
 
-        // Close all connections
+        log::info!("▶️  BF-6900 service accepting connections again on port {}", port);
+        Ok(())
+    }
+
+    /// Local address of the listener, if listening
+    pub async fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener
+            .lock()
+            .await
+            .as_ref()
+            .and_then(|listener| listener.local_addr().ok())
+    }
+
+    /// Shuts down every established connection
+    pub async fn close_connections(&self) {
         let mut connections = self.connections.write().await;
         let connection_count = connections.len();
         log::info!("🔌 CLOSING {} ACTIVE EXTERNAL CONNECTIONS", connection_count);
-        
+
         for (analyzer_id, mut connection) in connections.drain() {
             log::info!("   🔗 Closing connection: {} ({})", connection.remote_addr, analyzer_id);
             if let Err(e) = connection.stream.shutdown().await {
@@ -181,7 +291,18 @@ impl<R: Runtime> BF6900Service<R> {
             } else {
                 log::info!("   ✅ Connection closed successfully: {}", connection.remote_addr);
             }
+            connection.conversation.close(ConnectionTermination::Shutdown);
         }
+    }
+
+    /// Stops the service
+    pub async fn stop(&self) -> Result<(), String> {
+        log::info!("🛑 STOPPING BF-6900 EXTERNAL CONNECTION SERVICE");
+
+        *self.is_running.write().await = false;
+
+        // Close all connections
+        self.close_connections().await;
 
         // Clear listener
         {
@@ -197,8 +318,9 @@ impl<R: Runtime> BF6900Service<R> {
             analyzer.id.clone()
         };
 
-        // Save updated analyzer to store
-        self.save_analyzer_to_store().await?;
+        // The final state is written now rather than at the end of the interval
+        self.persist_status().await?;
+        self.status_persister.flush()?;
 
         // Emit status update event
         let _ = self
@@ -228,7 +350,8 @@ impl<R: Runtime> BF6900Service<R> {
         let json_value = serde_json::to_value(store_data)
             .map_err(|e| format!("Failed to serialize analyzer configuration: {}", e))?;
 
-        self.store.set("config".to_string(), json_value);
+        self.store.set("config", json_value);
+        self.store.save()?;
 
         log::debug!("BF-6900 analyzer configuration saved to store");
         Ok(())
@@ -296,13 +419,22 @@ impl<R: Runtime> BF6900Service<R> {
     }
 
     /// Main connection handling loop
+    #[allow(clippy::too_many_arguments)]
     async fn handle_connections_loop(
         listener: Arc<Mutex<Option<TcpListener>>>,
         connections: Arc<RwLock<HashMap<String, HL7Connection>>>,
         is_running: Arc<RwLock<bool>>,
         event_sender: mpsc::Sender<BF6900Event>,
-        analyzer_id: String,
+        analyzer: Analyzer,
+        shared_analyzer: Arc<RwLock<Analyzer>>,
+        shadow_mode: ShadowMode,
+        protective_mode: ProtectiveMode,
+        message_profile: MessageProfile,
+        conversation_log: ConversationLog,
+        log_sampler: Arc<LogSampler>,
     ) {
+        let analyzer_id = analyzer.id.clone();
+        let limiter = ConnectionLimiter::new(analyzer.max_connections_per_ip);
         loop {
             // Check if service should stop
             if !*is_running.read().await {
@@ -314,14 +446,14 @@ impl<R: Runtime> BF6900Service<R> {
             let listener_ref = match &*listener_guard {
                 Some(l) => l,
                 None => {
-                    log::error!("No TCP listener available");
+                    log::info!("TCP listener closed, stopping accept loop");
                     break;
                 }
             };
 
             // Accept incoming connections
             match timeout(Duration::from_secs(1), listener_ref.accept()).await {
-                Ok(Ok((stream, addr))) => {
+                Ok(Ok((mut stream, addr))) => {
                     // Extract IP address from socket address
                     let ip_address = addr.ip();
                     let port = addr.port();
@@ -333,6 +465,23 @@ impl<R: Runtime> BF6900Service<R> {
                     log::info!("   🏥 Analyzer ID: {}", analyzer_id);
                     log::info!("   🔧 Protocol: HL7 v2.4 with MLLP framing");
 
+                    let permit = match limiter.admit(ip_address) {
+                        Ok(permit) => permit,
+                        Err(reason) => {
+                            log::warn!("Rejected connection from {} to {}: {}", addr, analyzer_id, reason);
+                            let _ = stream.shutdown().await;
+                            let _ = event_sender
+                                .send(BF6900Event::ConnectionRejected {
+                                    analyzer_id: analyzer_id.clone(),
+                                    remote_addr: addr.to_string(),
+                                    reason,
+                                    timestamp: Utc::now(),
+                                })
+                                .await;
+                            continue;
+                        }
+                    };
+
                     let connection = HL7Connection {
                         stream,
                         remote_addr: addr,
@@ -343,13 +492,32 @@ impl<R: Runtime> BF6900Service<R> {
                         last_activity: Utc::now(),
                         retry_count: 0,
                         health_status: ConnectionHealthStatus::Healthy,
+                        shadow_mode: shadow_mode.clone(),
+                        protective_mode: protective_mode.clone(),
+                        message_profile: message_profile.clone(),
+                        dilution_mode: analyzer.dilution_mode,
+                        default_patient_class: analyzer.default_patient_class.clone(),
+                        clock_skew_seconds: analyzer.clock_skew_seconds,
+                        conversation: conversation_log.open(&analyzer_id, "HL7", addr),
+                        log_sampler: log_sampler.clone(),
+                        analyzer: shared_analyzer.clone(),
+                        session_outcome: None,
+                        permit: Some(permit),
                     };
 
-                    // Store connection
-                    connections
-                        .write()
-                        .await
-                        .insert(analyzer_id.clone(), connection);
+                    // Store connection; a reconnect replaces the analyzer's previous socket,
+                    // which is closed so the map only ever holds open connections
+                    let replaced = connections.write().await.insert(analyzer_id.clone(), connection);
+                    if let Some(mut replaced) = replaced {
+                        log::warn!(
+                            "{} reconnected from {}, closing its previous connection from {}",
+                            analyzer_id,
+                            addr,
+                            replaced.remote_addr
+                        );
+                        let _ = replaced.stream.shutdown().await;
+                        replaced.conversation.close(ConnectionTermination::Shutdown);
+                    }
 
                     // Send connection event
                     let _ = event_sender
@@ -393,14 +561,15 @@ impl<R: Runtime> BF6900Service<R> {
     ) {
         let mut buffer = [0u8; 1024];
 
-        loop {
+        let termination = loop {
             // Get connection
             let mut connections_guard = connections.write().await;
             let connection = match connections_guard.get_mut(&analyzer_id) {
                 Some(conn) => conn,
                 None => {
+                    // Closed by the service
                     log::warn!("Connection not found for {}", analyzer_id);
-                    break;
+                    break ConnectionTermination::Shutdown;
                 }
             };
 
@@ -414,7 +583,19 @@ impl<R: Runtime> BF6900Service<R> {
                 Ok(Ok(0)) => {
                     // Connection closed
                     log::info!("HL7 connection closed by {}", connection.remote_addr);
-                    break;
+
+                    // Fire-and-forget analyzers close right after sending; don't drop their message
+                    if let Err(e) = Self::process_buffered_on_close(connection, &event_sender).await {
+                        log::error!("Error processing buffered HL7 data on close: {}", e);
+                        let _ = event_sender
+                            .send(BF6900Event::Error {
+                                analyzer_id: analyzer_id.clone(),
+                                error: e,
+                                timestamp: Utc::now(),
+                            })
+                            .await;
+                    }
+                    break Self::classify_remote_close(connection);
                 }
                 Ok(Ok(n)) => {
                     let data = &buffer[..n];
@@ -453,38 +634,51 @@ impl<R: Runtime> BF6900Service<R> {
                         // Check if connection should be dropped due to repeated errors
                         if connection.retry_count > 5 {
                             log::error!("Connection {} exceeded retry limit, dropping connection", connection.remote_addr);
-                            break;
+                            break ConnectionTermination::Error;
                         }
                     }
                 }
                 Ok(Err(e)) => {
                     log::error!("Error reading from HL7 connection: {}", e);
-                    break;
+                    break ConnectionTermination::Error;
                 }
                 Err(_) => {
                     // Timeout, continue
                     continue;
                 }
             }
-        }
+        };
 
         // Log connection termination
         log::info!("🔌 EXTERNAL CONNECTION TERMINATED");
         log::info!("   🏥 Analyzer ID: {}", analyzer_id);
+        log::info!("   🏁 Termination: {}", termination.as_db_str());
         
         // Remove connection
-        connections.write().await.remove(&analyzer_id);
+        if let Some(connection) = connections.write().await.remove(&analyzer_id) {
+            connection.conversation.close(termination);
+        }
 
         // Send disconnection event
         log::info!("📡 EMITTING DISCONNECTION EVENT");
         let _ = event_sender
             .send(BF6900Event::AnalyzerDisconnected {
                 analyzer_id,
+                termination,
                 timestamp: Utc::now(),
             })
             .await;
     }
 
+    /// Classifies a close by the analyzer: a normal end when every message it sent
+    /// was answered and nothing is left part-way through the buffer
+    fn classify_remote_close(connection: &HL7Connection) -> ConnectionTermination {
+        match connection.session_outcome {
+            Some(outcome) if connection.message_buffer.is_empty() => outcome,
+            _ => ConnectionTermination::RemoteClosed,
+        }
+    }
+
     /// Processes HL7/MLLP protocol data
     async fn process_hl7_data(
         connection: &mut HL7Connection,
@@ -500,6 +694,13 @@ impl<R: Runtime> BF6900Service<R> {
             
             match parse_celquant_identification(&connection.message_buffer) {
                 Ok(identification) => {
+                    connection.conversation.record(
+                        Direction::Inbound,
+                        ConversationElement::MllpFrame,
+                        connection.message_buffer.len(),
+                        EntryOutcome::Accepted,
+                        Some("Celquant identification".to_string()),
+                    );
                     log::info!("📋 CELQUANT IDENTIFICATION PARSED");
                     log::info!("   🏥 Device: {}", identification.device_name);
                     log::info!("   📊 Version: {}", identification.version);
@@ -545,9 +746,14 @@ impl<R: Runtime> BF6900Service<R> {
                         log::error!("❌ Failed to send Celquant ACK: {}", e);
                         return Err(format!("Failed to send acknowledgment: {}", e));
                     }
+                    connection.conversation.sent(
+                        ConversationElement::MllpAck { code: Self::msa_code(&String::from_utf8_lossy(&ack)) },
+                        ack.len(),
+                    );
                     
                     // Clear the buffer since we processed the identification message
                     connection.message_buffer.clear();
+                    connection.session_outcome = Some(ConnectionTermination::Completed);
                     return Ok(());
                 }
                 Err(e) => {
@@ -559,29 +765,36 @@ impl<R: Runtime> BF6900Service<R> {
 
         // Process complete MLLP frames
         while let Some(message_data) = Self::extract_complete_mllp_message(&mut connection.message_buffer)? {
+            // Open until the message is answered below
+            connection.session_outcome = None;
+
             // Parse HL7 message
             let message_str = String::from_utf8_lossy(&message_data);
+            let wire_length = message_data.len() + 3; // VT + FS CR
             
-            // Comprehensive HL7 message logging
-            log::info!("📋 COMPLETE HL7 MESSAGE EXTRACTED");
-            log::info!("   🔗 Source: {}", connection.remote_addr);
-            log::info!("   📏 Message Length: {} bytes", message_data.len());
-            log::info!("   📄 Full HL7 Message:\n{}", message_str);
+            // Comprehensive HL7 message logging, sampled on high-throughput analyzers
+            let sampled = connection.log_sampler.sample();
+            if sampled {
+                log::info!("📋 COMPLETE HL7 MESSAGE EXTRACTED");
+                log::info!("   🔗 Source: {}", connection.remote_addr);
+                log::info!("   📏 Message Length: {} bytes", message_data.len());
+                log::info!("   📄 Full HL7 Message:\n{}", message_str);
             
-            // Log message segments for detailed analysis
-            let segments: Vec<&str> = message_str.split('\r').filter(|s| !s.is_empty()).collect();
-            log::info!("   📊 Segment Count: {}", segments.len());
-            for (i, segment) in segments.iter().enumerate() {
-                let segment_type = segment.split('|').next().unwrap_or("UNKNOWN");
-                log::info!("   📋 Segment {}: {} = {}", i + 1, segment_type, segment);
+                // Log message segments for detailed analysis
+                let segments: Vec<&str> = message_str.split('\r').filter(|s| !s.is_empty()).collect();
+                log::info!("   📊 Segment Count: {}", segments.len());
+                for (i, segment) in segments.iter().enumerate() {
+                    let segment_type = segment.split('|').next().unwrap_or("UNKNOWN");
+                    log::info!("   📋 Segment {}: {} = {}", i + 1, segment_type, segment);
+                }
+
+                // Log event emission
+                log::info!("📡 EMITTING HL7 MESSAGE EVENT");
+                log::info!("   🎯 Event Type: BF6900Event::HL7MessageReceived");
+                log::info!("   🏥 Analyzer ID: {}", connection.analyzer_id);
+                log::info!("   📄 Message Type: HL7");
             }
 
-            // Log event emission
-            log::info!("📡 EMITTING HL7 MESSAGE EVENT");
-            log::info!("   🎯 Event Type: BF6900Event::HL7MessageReceived");
-            log::info!("   🏥 Analyzer ID: {}", connection.analyzer_id);
-            log::info!("   📄 Message Type: HL7");
-            
             // Emit raw message event
             let _ = event_sender
                 .send(BF6900Event::HL7MessageReceived {
@@ -592,25 +805,73 @@ impl<R: Runtime> BF6900Service<R> {
                 })
                 .await;
 
+            // The sender picks original (AA/AE) or enhanced (CA/CE) acknowledgment via MSH-15/16
+            let ack_mode = AcknowledgmentMode::from_raw(&message_str);
+
             // Parse HL7 message
             match parse_hl7_message(&message_str) {
                 Ok(hl7_message) => {
                     // Validate message content
                     match Self::validate_hl7_message_content(&hl7_message) {
                         Ok(()) => {
-                            log::info!("✅ HL7 MESSAGE VALIDATION SUCCESSFUL");
-                            log::info!("   📋 Message Type: {}", hl7_message.message_type);
-                            log::info!("   📊 Segment Count: {}", hl7_message.segments.len());
+                            connection.conversation.received(ConversationElement::MllpFrame, wire_length);
+                            if sampled {
+                                log::info!("✅ HL7 MESSAGE VALIDATION SUCCESSFUL");
+                                log::info!("   📋 Message Type: {}", hl7_message.message_type);
+                                log::info!("   📊 Segment Count: {}", hl7_message.segments.len());
+                            }
                             
-                            // Send ACK for valid message
-                            let ack = create_hl7_acknowledgment(&hl7_message, "AA", Some("Message accepted"));
-                            log::info!("📤 SENDING ACKNOWLEDGMENT TO EXTERNAL SYSTEM");
-                            log::info!("   🎯 ACK Type: AA (Application Accept)");
-                            log::info!("   📄 ACK Message: {}", ack);
-                            Self::send_hl7_response(connection, &ack).await?;
+                            // Send ACK for valid message; in protective mode only once its results are stored
+                            let (store_receipt, stored) = if connection.protective_mode.is_enabled() {
+                                let (receipt, stored) = StoreReceipt::requested();
+                                (receipt, Some(stored))
+                            } else {
+                                (StoreReceipt::default(), None)
+                            };
+                            if let (None, Some(ack_code)) = (&stored, ack_mode.accept_code()) {
+                                Self::send_accept_ack(connection, &hl7_message, ack_code).await;
+                            }
+
+                            // Spec deviations are reported but never reject the message;
+                            // in conformance-report mode they hold it for review
+                            let review_detail = Self::report_conformance(connection, &hl7_message, event_sender).await;
+
+                            // A changed MSH-3/4/12 usually means a firmware update
+                            if let Some(identity) = hl7_identity(&hl7_message) {
+                                let _ = event_sender
+                                    .send(BF6900Event::IdentityDeclared {
+                                        analyzer_id: connection.analyzer_id.clone(),
+                                        identity,
+                                        timestamp: Utc::now(),
+                                    })
+                                    .await;
+                            }
 
                             // Process message content
-                            Self::process_hl7_message(connection, &hl7_message, event_sender).await?;
+                            Self::process_hl7_message(connection, &hl7_message, review_detail, store_receipt, event_sender)
+                                .await?;
+
+                            if let Some(stored) = stored {
+                                if let Err(e) = wait_until_stored(stored, STORE_CONFIRMATION_TIMEOUT).await {
+                                    log::error!("Results from {} were not stored: {}", connection.remote_addr, e);
+                                    if connection.shadow_mode.is_enabled() {
+                                        Self::send_shadow_mode_ack(connection, &message_str, ack_mode).await?;
+                                    } else if let Some(nak_code) = ack_mode.error_code() {
+                                        let nak = Self::create_hl7_nak_response(&message_str, nak_code, &e).await;
+                                        Self::send_hl7_response(connection, &nak).await?;
+                                    }
+                                    connection.session_outcome = Some(ConnectionTermination::Error);
+                                    continue;
+                                }
+                                if let Some(ack_code) = ack_mode.accept_code() {
+                                    Self::send_accept_ack(connection, &hl7_message, ack_code).await;
+                                }
+                            }
+
+                            // Enhanced mode: application ACK once the content is processed
+                            if let Some(ack_code) = ack_mode.application_accept_code() {
+                                Self::send_accept_ack(connection, &hl7_message, ack_code).await;
+                            }
                             
                             // Reset retry count on successful processing
                             connection.retry_count = 0;
@@ -619,12 +880,26 @@ impl<R: Runtime> BF6900Service<R> {
                             log::error!("❌ HL7 MESSAGE VALIDATION FAILED");
                             log::error!("   🚨 Validation Error: {}", validation_error);
                             log::error!("   🔗 Connection: {}", connection.remote_addr);
+                            connection.conversation.record(
+                                Direction::Inbound,
+                                ConversationElement::MllpFrame,
+                                wire_length,
+                                EntryOutcome::Rejected,
+                                Some(validation_error.clone()),
+                            );
                             let enhanced_error = Self::handle_hl7_processing_error(&validation_error, connection);
-                            let nak = Self::create_hl7_nak_response(&message_str, &enhanced_error).await;
-                            log::info!("📤 SENDING NAK TO EXTERNAL SYSTEM");
-                            log::info!("   🎯 NAK Type: AE (Application Error)");
-                            log::info!("   📄 NAK Message: {}", nak);
-                            Self::send_hl7_response(connection, &nak).await?;
+                            if connection.shadow_mode.is_enabled() {
+                                Self::send_shadow_mode_ack(connection, &message_str, ack_mode).await?;
+                                connection.session_outcome = Some(ConnectionTermination::Completed);
+                                continue;
+                            }
+                            if let Some(nak_code) = ack_mode.error_code() {
+                                let nak = Self::create_hl7_nak_response(&message_str, nak_code, &enhanced_error).await;
+                                log::info!("📤 SENDING NAK TO EXTERNAL SYSTEM");
+                                log::info!("   🎯 NAK Type: {}", nak_code);
+                                log::info!("   📄 NAK Message: {}", nak);
+                                Self::send_hl7_response(connection, &nak).await?;
+                            }
                         }
                     }
                 }
@@ -633,19 +908,120 @@ impl<R: Runtime> BF6900Service<R> {
                     log::error!("   🚨 Parse Error: {}", parse_error);
                     log::error!("   📄 Raw Message: {}", message_str);
                     log::error!("   🔗 Connection: {}", connection.remote_addr);
+                    connection.conversation.record(
+                        Direction::Inbound,
+                        ConversationElement::MllpFrame,
+                        wire_length,
+                        EntryOutcome::Rejected,
+                        Some(parse_error.clone()),
+                    );
                     let enhanced_error = Self::handle_hl7_processing_error(&parse_error, connection);
-                    let nak = Self::create_hl7_nak_response(&message_str, &enhanced_error).await;
-                    log::info!("📤 SENDING NAK TO EXTERNAL SYSTEM");
-                    log::info!("   🎯 NAK Type: AE (Application Error)");
-                    log::info!("   📄 NAK Message: {}", nak);
-                    Self::send_hl7_response(connection, &nak).await?;
+                    if connection.shadow_mode.is_enabled() {
+                        Self::send_shadow_mode_ack(connection, &message_str, ack_mode).await?;
+                        connection.session_outcome = Some(ConnectionTermination::Completed);
+                        continue;
+                    }
+                    if let Some(nak_code) = ack_mode.error_code() {
+                        let nak = Self::create_hl7_nak_response(&message_str, nak_code, &enhanced_error).await;
+                        log::info!("📤 SENDING NAK TO EXTERNAL SYSTEM");
+                        log::info!("   🎯 NAK Type: {}", nak_code);
+                        log::info!("   📄 NAK Message: {}", nak);
+                        Self::send_hl7_response(connection, &nak).await?;
+                    }
                 }
             }
+
+            connection.session_outcome = Some(ConnectionTermination::Completed);
         }
 
         Ok(())
     }
 
+    /// Processes messages still buffered when the peer closes the connection.
+    /// At end of stream a trailing FS is enough to end the message, since some
+    /// analyzers close without sending the final CR.
+    async fn process_buffered_on_close(
+        connection: &mut HL7Connection,
+        event_sender: &mpsc::Sender<BF6900Event>,
+    ) -> Result<(), String> {
+        if connection.message_buffer.last() == Some(&0x1C) {
+            connection.message_buffer.push(0x0D);
+        }
+        if !connection.message_buffer.is_empty() {
+            log::info!(
+                "Processing {} buffered bytes from {} after close",
+                connection.message_buffer.len(),
+                connection.remote_addr
+            );
+        }
+
+        Self::process_hl7_data(connection, &[], event_sender).await
+    }
+
+    /// Sends a hand-written message in MLLP framing and waits up to `window` for the
+    /// analyzer's reply. Reading stops at the first complete reply so later messages
+    /// are left to the connection loop.
+    async fn exchange_raw_message(
+        connection: &mut HL7Connection,
+        message: &str,
+        window: Duration,
+    ) -> Result<RawExchange, String> {
+        let frame = Self::mllp_frame(message);
+        let sent_at = Utc::now();
+        connection
+            .stream
+            .write_all(&frame)
+            .await
+            .map_err(|e| format!("Failed to send raw HL7 message: {}", e))?;
+        connection.conversation.record(
+            Direction::Outbound,
+            ConversationElement::MllpFrame,
+            frame.len(),
+            EntryOutcome::Accepted,
+            Some("developer console".to_string()),
+        );
+        log::warn!("Sent raw HL7 message to {}: {:?}", connection.remote_addr, message);
+
+        let mut responses = Vec::new();
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 1024];
+        let deadline = tokio::time::Instant::now() + window;
+        while let Some(remaining) = deadline.checked_duration_since(tokio::time::Instant::now()) {
+            match timeout(remaining, connection.stream.read(&mut buffer)).await {
+                Ok(Ok(0)) | Err(_) => break,
+                Ok(Ok(n)) => {
+                    received.extend_from_slice(&buffer[..n]);
+                    if let Some(reply) = Self::extract_complete_mllp_message(&mut received)? {
+                        let text = String::from_utf8_lossy(&reply).to_string();
+                        let element = if text.contains("MSA|") {
+                            ConversationElement::MllpAck { code: Self::msa_code(&text) }
+                        } else {
+                            ConversationElement::MllpFrame
+                        };
+                        connection.conversation.received(element, reply.len() + 2);
+                        responses.push(describe_bytes(&reply));
+                        break;
+                    }
+                }
+                Ok(Err(e)) => return Err(format!("Failed to read reply: {}", e)),
+            }
+        }
+        // A partial reply is still worth showing; anything after the reply goes back to the loop
+        if responses.is_empty() && !received.is_empty() {
+            responses.push(describe_bytes(&received));
+        } else {
+            connection.message_buffer.extend_from_slice(&received);
+        }
+
+        Ok(RawExchange {
+            analyzer_id: connection.analyzer_id.clone(),
+            protocol: "HL7".to_string(),
+            bytes_sent: frame.len(),
+            responses,
+            sent_at,
+        })
+    }
+
     /// Extracts complete MLLP message from buffer
     fn extract_complete_mllp_message(buffer: &mut Vec<u8>) -> Result<Option<Vec<u8>>, String> {
         if buffer.is_empty() {
@@ -653,28 +1029,82 @@ impl<R: Runtime> BF6900Service<R> {
         }
 
         // Look for MLLP start block (VT = 0x0B)
-        if let Some(start_pos) = buffer.iter().position(|&b| b == 0x0B) {
-            // Look for MLLP end sequence (FS CR = 0x1C 0x0D)
-            for i in start_pos + 1..buffer.len() - 1 {
-                if buffer[i] == 0x1C && buffer[i + 1] == 0x0D {
-                    // Found complete message
-                    let message_data = buffer[start_pos + 1..i].to_vec();
-                    
-                    // Remove processed data from buffer
-                    buffer.drain(..i + 2);
-                    
-                    return Ok(Some(message_data));
-                }
-            }
+        let Some(start_pos) = buffer.iter().position(|&b| b == 0x0B) else {
+            return Ok(None);
+        };
+
+        // Look for MLLP end sequence (FS CR = 0x1C 0x0D) after the start block.
+        // `windows(2)` is empty for 0-1 trailing bytes, and an FS in the last byte
+        // waits for its CR in the next read.
+        let body_start = start_pos + 1;
+        let Some(fs_offset) = buffer[body_start..]
+            .windows(2)
+            .position(|pair| pair == [0x1C, 0x0D])
+        else {
+            return Ok(None);
+        };
+
+        // Found complete message
+        let fs_pos = body_start + fs_offset;
+        let message_data = buffer[body_start..fs_pos].to_vec();
+
+        // Remove processed data from buffer
+        buffer.drain(..fs_pos + 2);
+
+        Ok(Some(message_data))
+    }
+
+    /// Sends the accept ACK for a valid message, using the profile's acknowledgment text
+    async fn send_accept_ack(connection: &mut HL7Connection, hl7_message: &HL7Message, ack_code: &str) {
+        let ack = create_hl7_acknowledgment_with_profile(
+            hl7_message,
+            ack_code,
+            Some(&connection.message_profile.acknowledgment_text),
+            &connection.message_profile,
+        );
+        log::info!("📤 SENDING ACKNOWLEDGMENT TO EXTERNAL SYSTEM");
+        log::info!("   🎯 ACK Type: {}", ack_code);
+        log::info!("   📄 ACK Message: {}", ack);
+        if let Err(e) = Self::send_hl7_response(connection, &ack).await {
+            // The peer may already have closed (fire-and-forget); keep the message
+            log::warn!("ACK not delivered to {}: {}", connection.remote_addr, e);
         }
+    }
+
+    /// Creates a proper HL7 NAK response (AE, or CE in enhanced mode) for parsing errors
+    async fn create_hl7_nak_response(original_message: &str, nak_code: &str, error: &str) -> String {
+        Self::create_hl7_raw_response(original_message, nak_code, "NAK", error)
+    }
 
-        Ok(None)
+    /// Answers a message that failed parsing or validation with an accept ACK while in
+    /// shadow mode, so the analyzer does not retransmit or change its behavior
+    async fn send_shadow_mode_ack(
+        connection: &mut HL7Connection,
+        original_message: &str,
+        ack_mode: AcknowledgmentMode,
+    ) -> Result<(), String> {
+        let ack_code = match ack_mode.accept_code() {
+            Some(ack_code) => ack_code,
+            None => return Ok(()),
+        };
+        log::warn!(
+            "Shadow mode: acknowledging rejected HL7 message from {} with {}",
+            connection.remote_addr,
+            ack_code
+        );
+        let ack = Self::create_hl7_raw_response(
+            original_message,
+            ack_code,
+            "ACK",
+            &connection.message_profile.acknowledgment_text,
+        );
+        Self::send_hl7_response(connection, &ack).await
     }
 
-    /// Creates a proper HL7 NAK response for parsing errors
-    async fn create_hl7_nak_response(original_message: &str, error: &str) -> String {
+    /// Builds an ACK for a message that may not have parsed, reading the control ID from the raw MSH
+    fn create_hl7_raw_response(original_message: &str, ack_code: &str, control_prefix: &str, text: &str) -> String {
         let timestamp = Utc::now().format("%Y%m%d%H%M%S").to_string();
-        let control_id = format!("NAK{}", Utc::now().timestamp());
+        let control_id = format!("{}{}", control_prefix, Utc::now().timestamp());
         
         // Try to extract message control ID from original message
         let original_control_id = original_message
@@ -686,24 +1116,30 @@ impl<R: Runtime> BF6900Service<R> {
             })
             .unwrap_or_else(|| "UNKNOWN".to_string());
 
-        // Create proper NAK response (CQ 5 Plus format)
+        // Create proper response (CQ 5 Plus format)
         format!(
-            "MSH|^~\\&|LIS|HOSPITAL|BF-6900|FACILITY|{}||ACK^R01^ACK|{}|P|2.3.1||||||UTF-8\rMSA|AE|{}|{}",
+            "MSH|^~\\&|LIS|HOSPITAL|BF-6900|FACILITY|{}||ACK^R01^ACK|{}|P|2.3.1||||||UTF-8\rMSA|{}|{}|{}",
             timestamp,
             control_id,
+            ack_code,
             original_control_id,
-            error
+            text
         )
     }
 
+    /// Wraps an HL7 message in MLLP framing: VT + message + FS + CR
+    fn mllp_frame(message: &str) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(message.len() + 3);
+        frame.push(0x0B); // VT
+        frame.extend_from_slice(message.as_bytes());
+        frame.push(0x1C); // FS
+        frame.push(0x0D); // CR
+        frame
+    }
+
     /// Sends HL7 response (ACK/NAK) back to analyzer
     async fn send_hl7_response(connection: &mut HL7Connection, response: &str) -> Result<(), String> {
-        // Wrap response in MLLP framing
-        let mut mllp_response = Vec::new();
-        mllp_response.push(0x0B); // VT
-        mllp_response.extend_from_slice(response.as_bytes());
-        mllp_response.push(0x1C); // FS
-        mllp_response.push(0x0D); // CR
+        let mllp_response = Self::mllp_frame(response);
 
         // Log outgoing data transmission
         log::info!("📤 SENDING DATA TO EXTERNAL SYSTEM");
@@ -723,6 +1159,10 @@ impl<R: Runtime> BF6900Service<R> {
                 log::error!("   🔗 Connection: {}", connection.remote_addr);
                 format!("Failed to send HL7 response: {}", e)
             })?;
+        connection.conversation.sent(
+            ConversationElement::MllpAck { code: Self::msa_code(response) },
+            mllp_response.len(),
+        );
 
         log::info!("✅ DATA SUCCESSFULLY SENT TO EXTERNAL SYSTEM");
         log::info!("   🔗 Connection: {}", connection.remote_addr);
@@ -730,19 +1170,79 @@ impl<R: Runtime> BF6900Service<R> {
         Ok(())
     }
 
+    /// Acknowledgment code (MSA-1) of an outbound response
+    fn msa_code(response: &str) -> String {
+        response
+            .split(['\r', '\n'])
+            .find(|segment| segment.starts_with("MSA|"))
+            .and_then(|segment| segment.split('|').nth(1))
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// Runs the conformance rules on a parsed message and emits any warnings. Returns the
+    /// review detail when conformance-report mode holds the message for them.
+    async fn report_conformance(
+        connection: &HL7Connection,
+        hl7_message: &HL7Message,
+        event_sender: &mpsc::Sender<BF6900Event>,
+    ) -> Option<String> {
+        let warnings = check_hl7(hl7_message);
+        if warnings.is_empty() {
+            return None;
+        }
+        for warning in &warnings {
+            log::warn!(
+                "HL7 conformance {} in message {} from {}: {}",
+                warning.rule_id,
+                hl7_message.message_control_id,
+                connection.remote_addr,
+                warning.detail
+            );
+        }
+        let review_detail = conformance_review_detail(&*connection.analyzer.read().await, &warnings, Utc::now());
+        let _ = event_sender
+            .send(BF6900Event::ConformanceWarnings {
+                analyzer_id: connection.analyzer_id.clone(),
+                message_control_id: hl7_message.message_control_id.clone(),
+                warnings,
+                timestamp: Utc::now(),
+            })
+            .await;
+        review_detail
+    }
+
     /// Processes parsed HL7 message and extracts hematology data
     async fn process_hl7_message(
         connection: &HL7Connection,
         hl7_message: &HL7Message,
+        review_detail: Option<String>,
+        store_receipt: StoreReceipt,
         event_sender: &mpsc::Sender<BF6900Event>,
     ) -> Result<(), String> {
         log::info!("Processing HL7 message type: {}", hl7_message.message_type);
 
         let mut patient_data: Option<PatientData> = None;
+        let mut patient_class: Option<String> = None;
+        // Observations of each order group, under the sample its OBR names
+        let mut order_groups: Vec<(String, Vec<OBXSegment>)> = Vec::new();
         let mut test_results = Vec::new();
+        let mut run = HematologyRunInfo::default();
+        // NTE segments comment on the OBX before them, keyed by its sample, identifier and sub-ID
+        let mut comments: HashMap<(String, String, String), Vec<String>> = HashMap::new();
+        let mut last_observation: Option<(String, String, String)> = None;
+        // The analyzer dates MSH-7 as it sends the message, so its offset from receipt
+        // is how far the analyzer's clock is off; the configured skew covers undated ones
+        let clock_skew_seconds = hl7_message
+            .message_time()
+            .map(|sent| (sent - hl7_message.timestamp).num_seconds())
+            .unwrap_or(connection.clock_skew_seconds);
 
         // Process segments to extract patient and test result data
         for segment in &hl7_message.segments {
+            if segment.segment_type != "NTE" {
+                last_observation = None;
+            }
             match segment.segment_type.as_str() {
                 "PID" => {
                     if let Ok(pid_segment) = parse_pid_segment(segment) {
@@ -750,17 +1250,57 @@ impl<R: Runtime> BF6900Service<R> {
                         log::debug!("Extracted patient data: {:?}", patient_data);
                     }
                 }
+                "PV1" => {
+                    if let Ok(pv1_segment) = parse_pv1_segment(segment) {
+                        patient_class = pv1_segment.patient_class();
+                        log::debug!("Extracted patient class: {:?}", patient_class);
+                    }
+                }
+                "OBR" => {
+                    if let Ok(obr_segment) = parse_obr_segment(segment) {
+                        let sample_id = obr_segment.sample_id().unwrap_or_default();
+                        log::debug!("Order group for sample {:?}", sample_id);
+                        order_groups.push((sample_id, Vec::new()));
+                    }
+                }
                 "OBX" => {
                     if let Ok(obx_segment) = parse_obx_segment(segment) {
-                        if let Ok(result) = Self::convert_obx_to_hematology_result(&obx_segment, &connection.analyzer_id) {
-                            test_results.push(result);
+                        // Run information (mode, reference group, remarks, QC level) is not a result
+                        let parameter_code = extract_parameter_code(&obx_segment.observation_identifier);
+                        if is_metadata_parameter(&parameter_code) {
+                            run.set(&parameter_code, &obx_segment.observation_value);
+                        } else {
+                            if order_groups.is_empty() {
+                                order_groups.push((String::new(), Vec::new()));
+                            }
+                            let (sample_id, observations) = order_groups.last_mut().expect("order group exists");
+                            last_observation = Some((
+                                sample_id.clone(),
+                                obx_segment.observation_identifier.clone(),
+                                obx_segment.observation_sub_id.clone(),
+                            ));
+                            observations.push(obx_segment);
                         }
                     }
                 }
+                "NTE" => {
+                    let comment = parse_nte_segment(segment).ok().and_then(|nte| nte.comment_text());
+                    match (&last_observation, comment) {
+                        (Some(observation), Some(comment)) => {
+                            comments.entry(observation.clone()).or_default().push(comment);
+                        }
+                        (None, Some(comment)) => log::debug!("Skipping comment on no result: {}", comment),
+                        (_, None) => {}
+                    }
+                }
                 "MSA" => {
                     if let Ok(msa_segment) = parse_msa_segment(segment) {
                         log::debug!("Received acknowledgment: code={}, control_id={}", 
                                    msa_segment.acknowledgment_code, msa_segment.message_control_id);
+                        if msa_segment.is_negative() {
+                            log::warn!("Analyzer rejected message {}: {}",
+                                       msa_segment.message_control_id, msa_segment.error_summary());
+                        }
                     }
                 }
                 "ORC" => {
@@ -778,6 +1318,63 @@ impl<R: Runtime> BF6900Service<R> {
 
         }
 
+        for (sample_id, observations) in order_groups {
+            if sample_id.is_empty() && !observations.is_empty() {
+                log::warn!(
+                    "{} observations from {} are not in an order group naming a sample",
+                    observations.len(),
+                    connection.remote_addr
+                );
+            }
+            for obx_segment in Self::reassemble_multipart_images(observations) {
+                if let Err(reason) = Self::validate_image_observation(&obx_segment) {
+                    let parameter_code = extract_parameter_code(&obx_segment.observation_identifier);
+                    log::warn!(
+                        "Discarding corrupt image {} for sample {} from {}: {}",
+                        parameter_code,
+                        sample_id,
+                        connection.remote_addr,
+                        reason
+                    );
+                    let _ = event_sender
+                        .send(BF6900Event::CorruptImage {
+                            analyzer_id: connection.analyzer_id.clone(),
+                            sample_id: sample_id.clone(),
+                            parameter_code,
+                            reason,
+                            timestamp: Utc::now(),
+                        })
+                        .await;
+                    continue;
+                }
+
+                if let Ok(mut result) =
+                    Self::convert_obx_to_hematology_result(
+                    &obx_segment,
+                    &sample_id,
+                    &connection.analyzer_id,
+                    clock_skew_seconds,
+                )
+                {
+                    result.apply_dilution_mode(connection.dilution_mode);
+                    result.source_message_control_id =
+                        Some(hl7_message.message_control_id.clone()).filter(|id| !id.is_empty());
+                    let observation = (
+                        sample_id.clone(),
+                        obx_segment.observation_identifier.clone(),
+                        obx_segment.observation_sub_id.clone(),
+                    );
+                    result.comments = comments.remove(&observation).unwrap_or_default();
+                    test_results.push(result);
+                }
+            }
+        }
+
+        for result in &mut test_results {
+            result.panel = run.panel;
+            result.analysis_mode = run.analysis_mode;
+        }
+
         // Log processing results
         log::info!("🧪 HEMATOLOGY RESULTS PROCESSED");
         log::info!("   🏥 Analyzer ID: {}", connection.analyzer_id);
@@ -786,6 +1383,7 @@ impl<R: Runtime> BF6900Service<R> {
             log::info!("   👤 Patient ID: {}", patient.id);
             log::info!("   👤 Patient Name: {}", patient.name);
         }
+        log::info!("   🧪 Analysis Mode: {:?} ({:?})", run.analysis_mode, run.panel);
         log::info!("   🧪 Test Results Count: {}", test_results.len());
         for (i, result) in test_results.iter().enumerate() {
             log::info!("   🧪 Result {}: {} = {} {} ({})", 
@@ -798,9 +1396,14 @@ impl<R: Runtime> BF6900Service<R> {
         let _ = event_sender
             .send(BF6900Event::HematologyResultProcessed {
                 analyzer_id: connection.analyzer_id.clone(),
+                remote_addr: connection.remote_addr.to_string(),
                 patient_id: patient_data.as_ref().map(|p| p.id.clone()),
                 patient_data,
+                patient_class: patient_class.or_else(|| connection.default_patient_class.clone()),
                 test_results,
+                run,
+                review_detail,
+                store_receipt,
                 timestamp: Utc::now(),
             })
             .await;
@@ -836,24 +1439,93 @@ impl<R: Runtime> BF6900Service<R> {
             physicians: None, // Not typically in PID segment
             height: None,     // Not typically in PID segment
             weight: None,     // Not typically in PID segment
+            structured_address: parse_xad(&pid.patient_address),
+            contacts: parse_xtn_repeats(&pid.phone_number_home)
+                .into_iter()
+                .chain(parse_xtn_repeats(&pid.phone_number_business))
+                .collect(),
+        }
+    }
+
+    /// Merges image observations split over several OBX segments (same identifier,
+    /// increasing sub-ids) into one, joining the parts in sub-id order. The merged
+    /// observation takes the place of its first segment; everything else is unchanged.
+    fn reassemble_multipart_images(observations: Vec<OBXSegment>) -> Vec<OBXSegment> {
+        let is_image = |obx: &OBXSegment| {
+            obx.value_type == "ED" || is_histogram_parameter(&extract_parameter_code(&obx.observation_identifier))
+        };
+        let mut parts: HashMap<String, Vec<OBXSegment>> = HashMap::new();
+        for obx in observations.iter().filter(|obx| is_image(obx)) {
+            parts
+                .entry(obx.observation_identifier.clone())
+                .or_default()
+                .push(obx.clone());
+        }
+
+        let mut reassembled = Vec::with_capacity(observations.len());
+        for obx in observations {
+            if !is_image(&obx) {
+                reassembled.push(obx);
+                continue;
+            }
+            // Emitted once, at the first segment of the identifier
+            let Some(mut group) = parts.remove(&obx.observation_identifier) else {
+                continue;
+            };
+            if group.len() == 1 {
+                reassembled.extend(group);
+                continue;
+            }
+
+            group.sort_by_key(|part| ed_part_order(&part.observation_sub_id));
+            log::debug!(
+                "Reassembling {} from {} OBX parts",
+                obx.observation_identifier,
+                group.len()
+            );
+            let values: Vec<&str> = group.iter().map(|part| part.observation_value.as_str()).collect();
+            let mut merged = group[0].clone();
+            merged.observation_value = join_ed_parts(&values);
+            reassembled.push(merged);
+        }
+        reassembled
+    }
+
+    /// Checks that an ED/histogram observation carries an intact PNG; other observations pass
+    fn validate_image_observation(obx: &OBXSegment) -> Result<(), String> {
+        let parameter_code = extract_parameter_code(&obx.observation_identifier);
+        if obx.value_type != "ED" && !is_histogram_parameter(&parameter_code) {
+            return Ok(());
         }
+        decode_ed_png(&obx.observation_value).map(|_| ())
     }
 
-    /// Converts OBX segment to HematologyResult (CQ 5 Plus parameter codes)
+    /// Converts OBX segment to HematologyResult (CQ 5 Plus parameter codes). The sample
+    /// comes from the OBR of the OBX's order group; OBX-4 only numbers repeated measurements.
+    /// `clock_skew_seconds` is taken off the reported observation date.
     fn convert_obx_to_hematology_result(
         obx: &OBXSegment,
+        sample_id: &str,
         analyzer_id: &str,
+        clock_skew_seconds: i64,
     ) -> Result<HematologyResult, String> {
         let parameter_name = extract_parameter_name(&obx.observation_identifier);
         let parameter_code = extract_parameter_code(&obx.observation_identifier);
         let flags = extract_abnormal_flags(&obx.abnormal_flags);
+        let (value, dilution_factor) = Self::split_sn_dilution(obx);
+        let sub_id = Some(obx.observation_sub_id.trim())
+            .filter(|sub_id| !sub_id.is_empty())
+            .map(str::to_string);
         let now = Utc::now();
 
         Ok(HematologyResult {
-            id: format!("hematology_{}", now.timestamp()),
+            id: match &sub_id {
+                Some(sub_id) => format!("hematology_{}_{}_{}", now.timestamp(), parameter_code, sub_id),
+                None => format!("hematology_{}_{}", now.timestamp(), parameter_code),
+            },
             parameter: parameter_name,
             parameter_code,
-            value: obx.observation_value.clone(),
+            value,
             units: if !obx.units.is_empty() {
                 Some(obx.units.clone())
             } else {
@@ -866,20 +1538,53 @@ impl<R: Runtime> BF6900Service<R> {
             },
             flags,
             status: obx.observation_result_status.clone(),
-            completed_date_time: if !obx.date_time_of_observation.is_empty() {
-                // Parse HL7 datetime format
-                Some(now) // Simplified for now
-            } else {
-                Some(now)
-            },
+            // OBX-14, when the analyzer sent a valid one, corrected for the analyzer's clock
+            completed_date_time: parse_hl7_datetime(&obx.date_time_of_observation)
+                .map(|observed| observed - chrono::Duration::seconds(clock_skew_seconds))
+                .or(Some(now)),
             analyzer_id: Some(analyzer_id.to_string()),
-            sample_id: obx.observation_sub_id.clone(),
+            sample_id: sample_id.to_string(),
             test_id: obx.observation_identifier.clone(),
+            sub_id,
+            dilution_factor,
+            raw_value: None,
+            source_message_control_id: None,
+            panel: None,
+            analysis_mode: None,
+            verification: None,
+            comments: Vec::new(),
             created_at: now,
             updated_at: now,
         })
     }
 
+    /// Splits an SN observation of the form `<comparator>^<value>^*^<factor>` into the
+    /// value and its dilution factor; other observations are returned unchanged
+    fn split_sn_dilution(obx: &OBXSegment) -> (String, Option<f64>) {
+        if obx.value_type != "SN" {
+            return (obx.observation_value.clone(), None);
+        }
+
+        let components: Vec<&str> = obx.observation_value.split('^').collect();
+        match (components.get(2), components.get(3)) {
+            (Some(&"*"), Some(factor)) => {
+                let value = format!("{}{}", components[0], components.get(1).unwrap_or(&""));
+                (value, parse_dilution_factor(factor))
+            }
+            _ => (obx.observation_value.clone(), None),
+        }
+    }
+
+    /// Sends a hand-written HL7 message to the live connection (developer console)
+    pub async fn send_raw_message(&self, message: &str, window: Duration) -> Result<RawExchange, String> {
+        let analyzer_id = self.analyzer.read().await.id.clone();
+        let mut connections = self.connections.write().await;
+        let connection = connections
+            .get_mut(&analyzer_id)
+            .ok_or_else(|| format!("Analyzer {} is not connected", analyzer_id))?;
+        Self::exchange_raw_message(connection, message, window).await
+    }
+
     /// Gets service status
     pub async fn get_status(&self) -> AnalyzerStatus {
         if *self.is_running.read().await {
@@ -899,6 +1604,47 @@ impl<R: Runtime> BF6900Service<R> {
         self.analyzer.read().await.clone()
     }
 
+    /// Compares the identity the instrument declared with the last one and stores it if new.
+    /// Returns the change, if any; see [`firmware_tracking::record_identity`].
+    pub async fn record_identity(
+        &self,
+        identity: AnalyzerIdentity,
+        conformance_mode: bool,
+    ) -> Result<Option<FirmwareChange>, String> {
+        let observation =
+            firmware_tracking::record_identity(&self.analyzer, &self.log_sampler, identity, conformance_mode).await;
+        if observation != IdentityObservation::Unchanged {
+            self.save_analyzer_to_store().await?;
+        }
+        Ok(observation.into_change())
+    }
+
+    /// Sets whether the analyzer starts with the LIS and persists it
+    pub async fn set_activate_on_start(&self, enabled: bool) -> Result<(), String> {
+        {
+            let mut analyzer = self.analyzer.write().await;
+            analyzer.activate_on_start = enabled;
+            analyzer.updated_at = Utc::now();
+        }
+        self.save_analyzer_to_store().await
+    }
+
+    /// Queues the analyzer's current status for persistence
+    async fn persist_status(&self) -> Result<(), String> {
+        self.status_persister.record(&*self.analyzer.read().await)
+    }
+
+    /// Records that the analyzer connected at `at`
+    pub async fn record_connected(&self, at: DateTime<Utc>) -> Result<(), String> {
+        self.analyzer.write().await.last_connected_at = Some(at);
+        self.persist_status().await
+    }
+
+    /// Writes any status change still waiting for its interval, e.g. on shutdown
+    pub fn flush_status(&self) -> Result<(), String> {
+        self.status_persister.flush()
+    }
+
     /// Updates analyzer configuration with external address from CELQUANT identification
     pub async fn update_external_address(&self, external_ip: String, external_port: u16) -> Result<(), String> {
         log::info!("🌐 UPDATING ANALYZER CONFIGURATION WITH EXTERNAL ADDRESS");
@@ -1055,6 +1801,38 @@ impl<R: Runtime> BF6900Service<R> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::TestResult;
+    use crate::services::config_persistence::InMemoryConfigStore;
+    use crate::services::his_client::HisClient;
+
+    /// A connection from a local peer, registered as ANALYZER001 and waiting for a start block.
+    async fn test_connection() -> (HL7Connection, TcpStream) {
+        let (stream, remote_addr, peer) = crate::test_support::loopback_connection().await;
+
+        let connection = HL7Connection {
+            stream,
+            remote_addr,
+            state: HL7ConnectionState::WaitingForStartBlock,
+            message_buffer: Vec::new(),
+            current_message: Vec::new(),
+            analyzer_id: "ANALYZER001".to_string(),
+            last_activity: Utc::now(),
+            retry_count: 0,
+            health_status: ConnectionHealthStatus::Healthy,
+            shadow_mode: ShadowMode::default(),
+            protective_mode: ProtectiveMode::default(),
+            message_profile: MessageProfile::default(),
+            dilution_mode: DilutionMode::PostDilution,
+            default_patient_class: None,
+            clock_skew_seconds: 0,
+            conversation: ConversationLog::default().open("ANALYZER001", "HL7", remote_addr),
+            log_sampler: Arc::new(LogSampler::default()),
+            analyzer: Arc::new(RwLock::new(crate::test_support::bf6900_analyzer())),
+            session_outcome: None,
+            permit: None,
+        };
+        (connection, peer)
+    }
 
     #[test]
     fn test_mllp_message_extraction() {
@@ -1063,7 +1841,7 @@ mod tests {
         buffer.push(0x1C); // FS
         buffer.push(0x0D); // CR
 
-        let result = BF6900Service::<tauri::Wry>::extract_complete_mllp_message(&mut buffer).unwrap();
+        let result = BF6900Service::extract_complete_mllp_message(&mut buffer).unwrap();
         assert!(result.is_some());
         let message = result.unwrap();
         assert_eq!(String::from_utf8_lossy(&message), "MSH|^~\\&|BF6900|LAB|LIS|HOSPITAL||");
@@ -1076,11 +1854,32 @@ mod tests {
         buffer.extend_from_slice(b"MSH|^~\\&|BF6900|LAB|LIS|HOSPITAL||");
         // No end sequence
 
-        let result = BF6900Service::<tauri::Wry>::extract_complete_mllp_message(&mut buffer).unwrap();
+        let result = BF6900Service::extract_complete_mllp_message(&mut buffer).unwrap();
         assert!(result.is_none());
         assert!(!buffer.is_empty()); // Buffer should retain data
     }
 
+    #[test]
+    fn test_mllp_extraction_on_minimal_buffers() {
+        let mut buffer = vec![0x0B];
+        assert!(BF6900Service::extract_complete_mllp_message(&mut buffer).unwrap().is_none());
+        assert_eq!(buffer, vec![0x0B]);
+
+        // FS as the final byte waits for its CR
+        let mut buffer = vec![0x0B, 0x1C];
+        assert!(BF6900Service::extract_complete_mllp_message(&mut buffer).unwrap().is_none());
+        assert_eq!(buffer, vec![0x0B, 0x1C]);
+
+        buffer.push(0x0D);
+        let message = BF6900Service::extract_complete_mllp_message(&mut buffer).unwrap();
+        assert_eq!(message, Some(Vec::new()));
+        assert!(buffer.is_empty());
+
+        let mut buffer = vec![0x0B, 0x1C, 0x0D];
+        assert_eq!(BF6900Service::extract_complete_mllp_message(&mut buffer).unwrap(), Some(Vec::new()));
+        assert!(buffer.is_empty());
+    }
+
     #[test]
     fn test_connection_health_status() {
         // Test connection health status values
@@ -1091,9 +1890,9 @@ mod tests {
 
     #[test]
     fn test_connection_timeout_adjustment() {
-        let healthy_timeout = BF6900Service::<tauri::Wry>::get_connection_timeout(&ConnectionHealthStatus::Healthy);
-        let degraded_timeout = BF6900Service::<tauri::Wry>::get_connection_timeout(&ConnectionHealthStatus::Degraded);
-        let unhealthy_timeout = BF6900Service::<tauri::Wry>::get_connection_timeout(&ConnectionHealthStatus::Unhealthy);
+        let healthy_timeout = BF6900Service::get_connection_timeout(&ConnectionHealthStatus::Healthy);
+        let degraded_timeout = BF6900Service::get_connection_timeout(&ConnectionHealthStatus::Degraded);
+        let unhealthy_timeout = BF6900Service::get_connection_timeout(&ConnectionHealthStatus::Unhealthy);
 
         assert!(healthy_timeout > degraded_timeout);
         assert!(degraded_timeout > unhealthy_timeout);
@@ -1137,7 +1936,7 @@ mod tests {
             primary_language: "".to_string(),
         };
 
-        let patient_data = BF6900Service::<tauri::Wry>::convert_pid_to_patient_data(&pid);
+        let patient_data = BF6900Service::convert_pid_to_patient_data(&pid);
         assert_eq!(patient_data.id, "P123456");
         assert_eq!(patient_data.name, "DOE^JOHN^MIDDLE");
         assert_eq!(patient_data.sex, Some("M".to_string()));
@@ -1163,13 +1962,164 @@ mod tests {
             date_time_of_observation: "".to_string(),
         };
 
-        let result = BF6900Service::<tauri::Wry>::convert_obx_to_hematology_result(&obx, "ANALYZER001").unwrap();
+        let result = BF6900Service::convert_obx_to_hematology_result(&obx, "SMP-1", "ANALYZER001", 0).unwrap();
         assert_eq!(result.parameter, "V_WBC");
         assert_eq!(result.parameter_code, "2006"); // CQ 5 Plus parameter code
         assert_eq!(result.value, "6.8");
         assert_eq!(result.units, Some("10^9/L".to_string()));
         assert_eq!(result.reference_range, Some("4-10".to_string()));
         assert_eq!(result.status, "F");
+
+        // OBX-14 is the analyzer's observation time, not the time it was received
+        let observed = OBXSegment {
+            date_time_of_observation: "20240101120000".to_string(),
+            ..obx
+        };
+        let result = BF6900Service::convert_obx_to_hematology_result(&observed, "SMP-1", "ANALYZER001", 0).unwrap();
+        assert_eq!(result.completed_date_time, parse_hl7_datetime("20240101120000"));
+        assert_eq!(result.completed_date_time.unwrap().to_rfc3339(), "2024-01-01T12:00:00+00:00");
+    }
+
+    #[tokio::test]
+    async fn test_hematology_result_stored_and_read_back() {
+        use crate::db::{establish_test_connection, SqliteRepository};
+        use crate::models::{DataSource, ResultStatus};
+        use crate::protocol::hl7_parser::parse_hl7_segment;
+
+        let segment = parse_hl7_segment("OBX|1|NM|2006^V_WBC^LOCAL|1|12.4|10^9/L|4-10|H|||F|||20240101120000").unwrap();
+        let obx = parse_obx_segment(&segment).unwrap();
+        let result = BF6900Service::convert_obx_to_hematology_result(&obx, "SMP-1", "ANALYZER001", 0).unwrap();
+
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let patient = PatientData {
+            id: "P1".to_string(),
+            name: "Doe^Jane".to_string(),
+            sex: Some("F".to_string()),
+            ..Default::default()
+        }
+        .to_patient("P1", Utc::now());
+        assert_eq!(patient.name.first_name.as_deref(), Some("Jane"));
+        repository.save_patient(&patient, &DataSource::Analyzer).await.unwrap();
+        assert!(repository
+            .save_hematology_result(&result, "P1", &DataSource::Analyzer)
+            .await
+            .unwrap());
+        // A retransmission of the same result is not stored twice
+        assert!(!repository
+            .save_hematology_result(&result, "P1", &DataSource::Analyzer)
+            .await
+            .unwrap());
+
+        let stored = repository.get_patient_results("P1").await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].test_id, "2006^V_WBC^LOCAL");
+        // The sample is the order's, not OBX-4's measurement number
+        assert_eq!(stored[0].sample_id, "SMP-1");
+        assert_eq!(stored[0].value, "12.4");
+        assert_eq!(stored[0].units.as_deref(), Some("10^9/L"));
+        let range = stored[0].reference_range.as_ref().unwrap();
+        assert_eq!((range.lower_limit, range.upper_limit), (Some(4.0), Some(10.0)));
+        assert_eq!(stored[0].flags.as_ref().unwrap().abnormal_flag.as_deref(), Some("H"));
+        assert_eq!(stored[0].status, ResultStatus::Final);
+        assert_eq!(stored[0].analyzer_id.as_deref(), Some("ANALYZER001"));
+        assert_eq!(stored[0].completed_date_time, parse_hl7_datetime("20240101120000"));
+        assert_eq!(stored[0].metadata.parameter_name.as_deref(), Some("V_WBC"));
+        assert_eq!(stored[0].metadata.panel, None);
+    }
+
+    #[tokio::test]
+    async fn test_hematology_results_without_a_patient_are_held_for_their_sample() {
+        use crate::app_state::AppState;
+        use crate::db::{establish_test_connection, SqliteRepository};
+        use crate::models::{HematologyAnalysisMode, HematologyPanel};
+        use crate::protocol::hl7_parser::parse_hl7_segment;
+
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let segment = parse_hl7_segment("OBX|1|NM|2006^V_WBC^LOCAL||12.4|10^9/L|4-10|H|||F|||20240101120000").unwrap();
+        let mut result =
+            BF6900Service::convert_obx_to_hematology_result(&parse_obx_segment(&segment).unwrap(), "17", "ANALYZER001", 0)
+                .unwrap();
+        result.panel = Some(HematologyPanel::CbcDiff);
+        result.analysis_mode = Some(HematologyAnalysisMode::PreDilution);
+
+        // No PID: the result waits for its sample's patient instead of being dropped
+        AppState::<tauri::Wry>::store_hematology_results(&repository, None, None, std::slice::from_ref(&result))
+            .await
+            .unwrap();
+        assert_eq!(repository.get_unassigned_results("17").await.unwrap().len(), 1);
+
+        // A later message for the sample names the patient and the held result joins them
+        let segment = parse_hl7_segment("OBX|2|NM|2007^V_RBC^LOCAL||4.5|10^12/L|3.5-5.5||||F|||20240101120000").unwrap();
+        let rbc =
+            BF6900Service::convert_obx_to_hematology_result(&parse_obx_segment(&segment).unwrap(), "17", "ANALYZER001", 0)
+                .unwrap();
+        let patient = PatientData { id: "P1".to_string(), ..Default::default() };
+        AppState::<tauri::Wry>::store_hematology_results(&repository, Some("P1"), Some(&patient), &[rbc])
+            .await
+            .unwrap();
+        assert!(repository.get_unassigned_results("17").await.unwrap().is_empty());
+
+        let mut stored: Vec<(Option<String>, Option<String>, Option<String>)> = repository
+            .get_patient_results("P1")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|stored| (stored.metadata.parameter_name, stored.metadata.panel, stored.metadata.analysis_mode))
+            .collect();
+        stored.sort();
+        assert_eq!(
+            stored,
+            vec![
+                (Some("V_RBC".to_string()), None, None),
+                (Some("V_WBC".to_string()), Some("CBC_DIFF".to_string()), Some("PRE_DILUTION".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_obx_sub_ids_keep_repeated_measurements_distinct() {
+        let obx = |sub_id: &str, value: &str| OBXSegment {
+            set_id: "1".to_string(),
+            value_type: "NM".to_string(),
+            observation_identifier: "2006^V_WBC^LOCAL".to_string(),
+            observation_sub_id: sub_id.to_string(),
+            observation_value: value.to_string(),
+            units: "10^9/L".to_string(),
+            references_range: "4-10".to_string(),
+            abnormal_flags: "".to_string(),
+            probability: "".to_string(),
+            nature_of_abnormal_test: "".to_string(),
+            observation_result_status: "F".to_string(),
+            effective_date_of_reference_range: "".to_string(),
+            user_defined_access_checks: "".to_string(),
+            date_time_of_observation: "".to_string(),
+        };
+
+        // Same parameter before and after dilution
+        let results: Vec<HematologyResult> = [obx("1", "6.8"), obx("2", "13.6")]
+            .iter()
+            .map(|obx| BF6900Service::convert_obx_to_hematology_result(obx, "SMP-1", "ANALYZER001", 0).unwrap())
+            .collect();
+        assert!(results.iter().all(|result| result.parameter == "V_WBC"));
+        assert_eq!(results[0].sub_id.as_deref(), Some("1"));
+        assert_eq!(results[1].sub_id.as_deref(), Some("2"));
+        assert_ne!(results[0].id, results[1].id);
+
+        let his_client = HisClient::with_default_config();
+        let payload = his_client.build_hematology_payload("ANALYZER001", Some("P1"), &results, Utc::now());
+        let names: Vec<&str> = payload.values.iter().map(|value| value.name.as_str()).collect();
+        assert_eq!(names, vec!["V_WBC.1", "V_WBC.2"]);
+
+        let stored: Vec<TestResult> = results.into_iter().map(TestResult::from).collect();
+        assert_eq!(stored[0].test_id, stored[1].test_id);
+        assert_eq!(stored[0].metadata.sequence_number, 1);
+        assert_eq!(stored[1].metadata.sequence_number, 2);
+
+        // A parameter reported once keeps its plain name
+        let single = BF6900Service::convert_obx_to_hematology_result(&obx("", "6.8"), "SMP-1", "ANALYZER001", 0).unwrap();
+        assert_eq!(single.sub_id, None);
+        let payload = his_client.build_hematology_payload("ANALYZER001", Some("P1"), &[single], Utc::now());
+        assert_eq!(payload.values[0].name, "V_WBC");
     }
 
     #[test]
@@ -1191,10 +2141,508 @@ mod tests {
             date_time_of_observation: "".to_string(),
         };
 
-        let result = BF6900Service::<tauri::Wry>::convert_obx_to_hematology_result(&obx_crp, "ANALYZER001").unwrap();
+        let result = BF6900Service::convert_obx_to_hematology_result(&obx_crp, "SMP-1", "ANALYZER001", 0).unwrap();
         assert_eq!(result.parameter, "V_CRP");
         assert_eq!(result.parameter_code, "2031");
         assert_eq!(result.value, "3.2");
         assert_eq!(result.units, Some("mg/L".to_string()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_sn_dilution_factor_by_analyzer_mode() {
+        let obx = OBXSegment {
+            set_id: "1".to_string(),
+            value_type: "SN".to_string(),
+            observation_identifier: "2031^V_CRP^LOCAL".to_string(),
+            observation_sub_id: "".to_string(),
+            observation_value: "^18.4^*^5".to_string(),
+            units: "mg/L".to_string(),
+            references_range: "0-6".to_string(),
+            abnormal_flags: "H".to_string(),
+            probability: "".to_string(),
+            nature_of_abnormal_test: "".to_string(),
+            observation_result_status: "F".to_string(),
+            effective_date_of_reference_range: "".to_string(),
+            user_defined_access_checks: "".to_string(),
+            date_time_of_observation: "".to_string(),
+        };
+
+        let mut pre = BF6900Service::convert_obx_to_hematology_result(&obx, "SMP-1", "ANALYZER001", 0).unwrap();
+        assert_eq!(pre.value, "18.4");
+        assert_eq!(pre.dilution_factor, Some(5.0));
+        pre.apply_dilution_mode(DilutionMode::PreDilution);
+        assert_eq!(pre.value, "92.0");
+        assert_eq!(pre.raw_value.as_deref(), Some("18.4"));
+
+        let mut post = BF6900Service::convert_obx_to_hematology_result(&obx, "SMP-1", "ANALYZER001", 0).unwrap();
+        post.apply_dilution_mode(DilutionMode::PostDilution);
+        assert_eq!(post.value, "18.4");
+        assert!(post.raw_value.is_none());
+
+        // Downstream, the corrected value is used and the dilution is kept in the metadata
+        let stored: crate::models::TestResult = pre.into();
+        assert_eq!(stored.value, "92.0");
+        assert_eq!(stored.metadata.dilution_factor, Some(5.0));
+        assert_eq!(stored.flags.unwrap().abnormal_flag.as_deref(), Some("H"));
+    }
+
+    #[test]
+    fn test_truncated_histogram_image_is_rejected() {
+        let mut obx = OBXSegment {
+            set_id: "30".to_string(),
+            value_type: "ED".to_string(),
+            observation_identifier: "2101^RBCHistogram.PNG^LOCAL".to_string(),
+            observation_sub_id: "".to_string(),
+            observation_value: "^Image^PNG^Base64^iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==".to_string(),
+            units: "".to_string(),
+            references_range: "".to_string(),
+            abnormal_flags: "".to_string(),
+            probability: "".to_string(),
+            nature_of_abnormal_test: "".to_string(),
+            observation_result_status: "F".to_string(),
+            effective_date_of_reference_range: "".to_string(),
+            user_defined_access_checks: "".to_string(),
+            date_time_of_observation: "".to_string(),
+        };
+        assert!(BF6900Service::validate_image_observation(&obx).is_ok());
+
+        // Payload cut off part-way through, as after a bad MLLP reassembly
+        obx.observation_value = "^Image^PNG^Base64^iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhf".to_string();
+        assert!(BF6900Service::validate_image_observation(&obx).is_err());
+    }
+
+    #[test]
+    fn test_two_part_scattergram_reassembles_into_valid_png() {
+        let png = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+        let (first, second) = png.split_at(37);
+        let part = |set_id: &str, sub_id: &str, value: String| OBXSegment {
+            set_id: set_id.to_string(),
+            value_type: "ED".to_string(),
+            observation_identifier: "2034^DIFF.PNG^LOCAL".to_string(),
+            observation_sub_id: sub_id.to_string(),
+            observation_value: value,
+            units: "".to_string(),
+            references_range: "".to_string(),
+            abnormal_flags: "".to_string(),
+            probability: "".to_string(),
+            nature_of_abnormal_test: "".to_string(),
+            observation_result_status: "F".to_string(),
+            effective_date_of_reference_range: "".to_string(),
+            user_defined_access_checks: "".to_string(),
+            date_time_of_observation: "".to_string(),
+        };
+        let mut wbc = part("1", "", "7.5".to_string());
+        wbc.value_type = "NM".to_string();
+        wbc.observation_identifier = "6690-2^WBC^LN".to_string();
+
+        // Second part arrives first; each part alone is not a decodable image
+        let observations = vec![
+            wbc,
+            part("31", "2", format!("^Image^PNG^Base64^{}", second)),
+            part("30", "1", format!("^Image^PNG^Base64^{}", first)),
+        ];
+        assert!(BF6900Service::validate_image_observation(&observations[2]).is_err());
+
+        let reassembled = BF6900Service::reassemble_multipart_images(observations);
+        assert_eq!(reassembled.len(), 2);
+        assert_eq!(reassembled[0].observation_identifier, "6690-2^WBC^LN");
+        assert_eq!(reassembled[1].set_id, "30");
+        assert_eq!(reassembled[1].observation_value, format!("^Image^PNG^Base64^{}", png));
+        assert!(BF6900Service::validate_image_observation(&reassembled[1]).is_ok());
+        assert_eq!(decode_ed_png(&reassembled[1].observation_value).unwrap().len(), 70);
+    }
+
+    #[tokio::test]
+    async fn test_message_processed_when_peer_closes_after_sending() {
+        let (connection, mut peer) = test_connection().await;
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        connections.write().await.insert("ANALYZER001".to_string(), connection);
+        let (sender, mut receiver) = mpsc::channel(10);
+
+        // Full message without the final CR, then close without waiting for the ACK
+        let message = "MSH|^~\\&|BF-6900|LAB|LIS|HOSPITAL|20240101120000||ORU^R01|42|P|2.3.1\rOBX|1|NM|2006^V_WBC^LOCAL||6.8|10^9/L|4-10||||F\r";
+        let mut frame = vec![0x0B];
+        frame.extend_from_slice(message.as_bytes());
+        frame.push(0x1C);
+        peer.write_all(&frame).await.unwrap();
+        drop(peer);
+
+        BF6900Service::handle_connection(connections.clone(), sender, "ANALYZER001".to_string()).await;
+
+        match receiver.recv().await {
+            Some(BF6900Event::HL7MessageReceived { raw_data, .. }) => assert_eq!(raw_data, message),
+            other => panic!("Expected HL7MessageReceived, got {:?}", other),
+        }
+        assert!(connections.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_close_mid_message_is_not_a_completed_session() {
+        let (connection, mut peer) = test_connection().await;
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        connections.write().await.insert("ANALYZER001".to_string(), connection);
+        let (sender, mut receiver) = mpsc::channel(100);
+
+        // The analyzer drops the link before the end block
+        let mut frame = vec![0x0B];
+        frame.extend_from_slice(b"MSH|^~\\&|BF-6900|LAB|LIS|HOSPITAL|20240101120000||ORU^R01|43|P|2.3.1\rOBX|1|NM");
+        peer.write_all(&frame).await.unwrap();
+        drop(peer);
+
+        BF6900Service::handle_connection(connections, sender, "ANALYZER001".to_string()).await;
+
+        let mut termination = None;
+        while let Ok(event) = receiver.try_recv() {
+            if let BF6900Event::AnalyzerDisconnected { termination: t, .. } = event {
+                termination = Some(t);
+            }
+        }
+        assert_eq!(termination, Some(ConnectionTermination::RemoteClosed));
+        assert!(termination.unwrap().is_abnormal());
+    }
+
+    #[tokio::test]
+    async fn test_run_metadata_is_decoded_and_not_reported_as_results() {
+        use crate::models::{HematologyAnalysisMode, HematologyPanel};
+
+        let (connection, mut peer) = test_connection().await;
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        connections.write().await.insert("ANALYZER001".to_string(), connection);
+        let (sender, mut receiver) = mpsc::channel(100);
+
+        // Run information first, as in the protocol's patient sample example: MODE and
+        // MODE_EX are §A.8 enumeration numbers (pre-dilution, CBC+DIFF+CRP)
+        let message = "MSH|^~\\&|BF-6900|LAB|LIS|HOSPITAL|20240101120000||ORU^R01|51|P|2.3.1\r\
+            PID|1||P1\r\
+            OBR|1||5\r\
+            OBX|1|IS|2001^MODE^LOCAL||2||||||F\r\
+            OBX|2|IS|2002^MODE_EX^LOCAL||2||||||F\r\
+            OBX|3|IS|2003^Ref^LOCAL||0||||||F\r\
+            OBX|4|ST|2004^Note^LOCAL||Repeat draw||||||F\r\
+            OBX|5|IS|2005^Level^LOCAL||||||||F\r\
+            OBX|6|NM|2006^V_WBC^LOCAL||6.8|10^9/L|4-10||||F\r\
+            OBX|7|NM|2007^V_NEU_p^LOCAL||60.1|%|50-70||||F\r\
+            OBX|8|NM|2031^V_CRP^LOCAL||3.2|mg/L|0-6||||F\r";
+        let mut frame = vec![0x0B];
+        frame.extend_from_slice(message.as_bytes());
+        frame.extend_from_slice(&[0x1C, 0x0D]);
+        peer.write_all(&frame).await.unwrap();
+        peer.shutdown().await.unwrap();
+
+        BF6900Service::handle_connection(connections, sender, "ANALYZER001".to_string()).await;
+
+        let mut processed = None;
+        while let Ok(event) = receiver.try_recv() {
+            if let BF6900Event::HematologyResultProcessed { test_results, run, .. } = event {
+                processed = Some((test_results, run));
+            }
+        }
+        let (test_results, run) = processed.expect("results processed");
+
+        assert_eq!(run.analysis_mode, Some(HematologyAnalysisMode::PreDilution));
+        assert_eq!(run.panel, Some(HematologyPanel::CbcDiffCrp));
+        assert_eq!(run.reference_group.as_deref(), Some("0"));
+        assert_eq!(run.remarks.as_deref(), Some("Repeat draw"));
+        assert_eq!(run.qc_level, None);
+
+        let codes: Vec<&str> = test_results.iter().map(|result| result.parameter_code.as_str()).collect();
+        assert_eq!(codes, vec!["2006", "2007", "2031"]);
+        assert!(test_results.iter().all(|result| result.panel == Some(HematologyPanel::CbcDiffCrp)
+            && result.analysis_mode == Some(HematologyAnalysisMode::PreDilution)));
+    }
+
+    #[tokio::test]
+    async fn test_results_take_their_sample_from_the_order_group() {
+        let (connection, mut peer) = test_connection().await;
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        connections.write().await.insert("ANALYZER001".to_string(), connection);
+        let (sender, mut receiver) = mpsc::channel(100);
+
+        // OBR-3 is the sample number; the second order only has its bar code in OBR-2
+        let message = "MSH|^~\\&|BF-6900|LAB|LIS|HOSPITAL|20240101120000||ORU^R01|52|P|2.3.1\r\
+            PID|1||P1\r\
+            OBR|1|BC-1|17\r\
+            OBX|1|NM|2006^V_WBC^LOCAL|1|6.8|10^9/L|||||F\r\
+            OBR|2|BC-2|\r\
+            OBX|2|NM|2006^V_WBC^LOCAL|1|7.1|10^9/L|||||F\r\
+            NTE|1||Repeat\r";
+        let mut frame = vec![0x0B];
+        frame.extend_from_slice(message.as_bytes());
+        frame.extend_from_slice(&[0x1C, 0x0D]);
+        peer.write_all(&frame).await.unwrap();
+        peer.shutdown().await.unwrap();
+
+        BF6900Service::handle_connection(connections, sender, "ANALYZER001".to_string()).await;
+
+        let mut processed = None;
+        while let Ok(event) = receiver.try_recv() {
+            if let BF6900Event::HematologyResultProcessed { test_results, .. } = event {
+                processed = Some(test_results);
+            }
+        }
+        let test_results = processed.expect("results processed");
+
+        let samples: Vec<(&str, &str)> = test_results
+            .iter()
+            .map(|result| (result.sample_id.as_str(), result.value.as_str()))
+            .collect();
+        assert_eq!(samples, vec![("17", "6.8"), ("BC-2", "7.1")]);
+        assert!(test_results[0].comments.is_empty());
+        assert_eq!(test_results[1].comments, vec!["Repeat".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_clock_skew_is_measured_from_the_message_date() {
+        let (connection, mut peer) = test_connection().await;
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        connections.write().await.insert("ANALYZER001".to_string(), connection);
+        let (sender, mut receiver) = mpsc::channel(100);
+
+        // The analyzer's clock runs an hour fast; it dated MSH-7 as it sent the message
+        // and observed the count ten minutes before that by its own clock
+        let analyzer_now = Utc::now() + chrono::Duration::hours(1);
+        let observed = analyzer_now - chrono::Duration::minutes(10);
+        let message = format!(
+            "MSH|^~\\&|BF-6900|LAB|LIS|HOSPITAL|{}||ORU^R01|53|P|2.3.1\r\
+            PID|1||P1\r\
+            OBR|1|BC-1|17\r\
+            OBX|1|NM|2006^V_WBC^LOCAL|1|6.8|10^9/L|||||F|||{}\r",
+            analyzer_now.format("%Y%m%d%H%M%S"),
+            observed.format("%Y%m%d%H%M%S")
+        );
+        let mut frame = vec![0x0B];
+        frame.extend_from_slice(message.as_bytes());
+        frame.extend_from_slice(&[0x1C, 0x0D]);
+        peer.write_all(&frame).await.unwrap();
+        peer.shutdown().await.unwrap();
+
+        BF6900Service::handle_connection(connections, sender, "ANALYZER001".to_string()).await;
+
+        let mut processed = None;
+        while let Ok(event) = receiver.try_recv() {
+            if let BF6900Event::HematologyResultProcessed { test_results, .. } = event {
+                processed = Some(test_results);
+            }
+        }
+        let test_results = processed.expect("results processed");
+        let lag = Utc::now() - test_results[0].completed_date_time.unwrap();
+        assert!(
+            lag >= chrono::Duration::minutes(9) && lag <= chrono::Duration::minutes(11),
+            "observed {} before receipt",
+            lag
+        );
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_image_is_reported_against_its_order_sample() {
+        let (connection, mut peer) = test_connection().await;
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        connections.write().await.insert("ANALYZER001".to_string(), connection);
+        let (sender, mut receiver) = mpsc::channel(100);
+
+        let message = "MSH|^~\\&|BF-6900|LAB|LIS|HOSPITAL|20240101120000||ORU^R01|53|P|2.3.1\r\
+            PID|1||P1\r\
+            OBR|1||17\r\
+            OBX|1|ED|2101^RBCHistogram.PNG^LOCAL|1|^Image^PNG^Base64^iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhf||||||F\r";
+        let mut frame = vec![0x0B];
+        frame.extend_from_slice(message.as_bytes());
+        frame.extend_from_slice(&[0x1C, 0x0D]);
+        peer.write_all(&frame).await.unwrap();
+        peer.shutdown().await.unwrap();
+
+        BF6900Service::handle_connection(connections, sender, "ANALYZER001".to_string()).await;
+
+        let mut corrupt = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            if let BF6900Event::CorruptImage { sample_id, parameter_code, .. } = event {
+                corrupt.push((sample_id, parameter_code));
+            }
+        }
+        assert_eq!(corrupt, vec![("17".to_string(), "2101".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_uploaded_payload_includes_patient_class() {
+        let (mut connection, mut peer) = test_connection().await;
+        connection.default_patient_class = Some("O".to_string());
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        connections.write().await.insert("ANALYZER001".to_string(), connection);
+        let (sender, mut receiver) = mpsc::channel(100);
+
+        // The first sample comes from a ward (PV1-2), the second carries no visit
+        let messages = [
+            "MSH|^~\\&|BF-6900|LAB|LIS|HOSPITAL|20240101120000||ORU^R01|61|P|2.3.1\r\
+            PID|1||P1\r\
+            PV1|1|I^Inpatient|WARD3\r\
+            OBR|1||S1\r\
+            OBX|1|NM|2006^V_WBC^LOCAL||6.8|10^9/L|||||F\r",
+            "MSH|^~\\&|BF-6900|LAB|LIS|HOSPITAL|20240101120100||ORU^R01|62|P|2.3.1\r\
+            PID|1||P2\r\
+            OBR|1||S2\r\
+            OBX|1|NM|2006^V_WBC^LOCAL||7.1|10^9/L|||||F\r",
+        ];
+        for message in messages {
+            let mut frame = vec![0x0B];
+            frame.extend_from_slice(message.as_bytes());
+            frame.extend_from_slice(&[0x1C, 0x0D]);
+            peer.write_all(&frame).await.unwrap();
+        }
+        peer.shutdown().await.unwrap();
+
+        BF6900Service::handle_connection(connections, sender, "ANALYZER001".to_string()).await;
+
+        let his_client = HisClient::with_default_config();
+        let mut uploaded = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            if let BF6900Event::HematologyResultProcessed { patient_id, patient_class, test_results, .. } = event {
+                let mut payload =
+                    his_client.build_hematology_payload("ANALYZER001", patient_id.as_deref(), &test_results, Utc::now());
+                payload.patient_class = patient_class;
+                uploaded.push(serde_json::to_value(&payload).unwrap());
+            }
+        }
+
+        assert_eq!(uploaded.len(), 2);
+        assert_eq!(uploaded[0]["PatientClass"], "I");
+        assert_eq!(uploaded[1]["PatientClass"], "O");
+    }
+
+    #[tokio::test]
+    async fn test_raw_message_is_mllp_framed_and_reply_returned() {
+        let conversation_log = ConversationLog::default();
+        let (mut connection, mut analyzer) = test_connection().await;
+        connection.conversation = conversation_log.open("ANALYZER001", "HL7", connection.remote_addr);
+        let connection_id = connection.conversation.connection_id().to_string();
+
+        let message = "MSH|^~\\&|LIS|HOSPITAL|BF-6900|LAB|20240101120000||ORM^O01|DBG1|P|2.3.1\rORC|XO|W1\r";
+        let exchange = tokio::spawn(async move {
+            BF6900Service::exchange_raw_message(&mut connection, message, Duration::from_secs(5)).await
+        });
+
+        // The analyzer sees exactly VT + message + FS + CR
+        let mut frame = vec![0u8; message.len() + 3];
+        timeout(Duration::from_secs(2), analyzer.read_exact(&mut frame)).await.unwrap().unwrap();
+        assert_eq!(frame, BF6900Service::mllp_frame(message));
+        assert_eq!((frame[0], &frame[frame.len() - 2..]), (0x0B, &[0x1C, 0x0D][..]));
+
+        let ack = "MSH|^~\\&|BF-6900|LAB|LIS|HOSPITAL|20240101120001||ACK^O01|A1|P|2.3.1\rMSA|AA|DBG1\r";
+        analyzer.write_all(&BF6900Service::mllp_frame(ack)).await.unwrap();
+
+        // Returns on the reply instead of waiting out the window
+        let exchange = timeout(Duration::from_secs(2), exchange).await.unwrap().unwrap().unwrap();
+        assert_eq!(exchange.protocol, "HL7");
+        assert_eq!(exchange.bytes_sent, message.len() + 3);
+        assert_eq!(exchange.responses, vec![ack.replace('\r', "<CR>")]);
+
+        let entries = conversation_log.get_conversation(&connection_id, 10).unwrap();
+        let elements: Vec<(Direction, ConversationElement)> =
+            entries.into_iter().map(|entry| (entry.direction, entry.element)).collect();
+        assert_eq!(
+            elements,
+            vec![
+                (Direction::Outbound, ConversationElement::MllpFrame),
+                (Direction::Inbound, ConversationElement::MllpAck { code: "AA".to_string() }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_identity_change_mid_session_is_recorded() {
+        use crate::db::{establish_test_connection, SqliteRepository};
+
+        let (connection, mut peer) = test_connection().await;
+        let connections = Arc::new(RwLock::new(HashMap::new()));
+        connections.write().await.insert("ANALYZER001".to_string(), connection);
+        let (sender, mut receiver) = mpsc::channel(100);
+
+        // The second message arrives after a firmware update changed MSH-4
+        let mut frames = Vec::new();
+        for (control_id, facility) in [("41", "LAB"), ("42", "LAB-FW2")] {
+            let message = format!(
+                "MSH|^~\\&|BF-6900|{}|LIS|HOSPITAL|20240101120000||ORU^R01|{}|P|2.3.1\rPID|1||P1\rOBR|1||S1\rOBX|1|NM|2006^V_WBC^LOCAL||6.8|10^9/L|4-10||||F\r",
+                facility, control_id
+            );
+            frames.push(0x0B);
+            frames.extend_from_slice(message.as_bytes());
+            frames.extend_from_slice(&[0x1C, 0x0D]);
+        }
+        peer.write_all(&frames).await.unwrap();
+        peer.shutdown().await.unwrap();
+
+        BF6900Service::handle_connection(connections, sender, "ANALYZER001".to_string()).await;
+
+        let mut declared = Vec::new();
+        let mut termination = None;
+        while let Ok(event) = receiver.try_recv() {
+            match event {
+                BF6900Event::IdentityDeclared { identity, .. } => declared.push(identity),
+                BF6900Event::AnalyzerDisconnected { termination: t, .. } => termination = Some(t),
+                _ => {}
+            }
+        }
+        assert_eq!(declared.len(), 2);
+        // Both messages were answered before the analyzer closed
+        assert_eq!(termination, Some(ConnectionTermination::Completed));
+
+        let store = Arc::new(InMemoryConfigStore::new());
+        let analyzer = crate::test_support::bf6900_analyzer();
+        let analyzer_id = analyzer.id.clone();
+        let (service_sender, _service_receiver) = mpsc::channel(10);
+        let service = BF6900Service::new(analyzer, service_sender, store.clone(), ShadowMode::default());
+
+        assert!(service.record_identity(declared[0].clone(), true).await.unwrap().is_none());
+        let change = service
+            .record_identity(declared[1].clone(), true)
+            .await
+            .unwrap()
+            .expect("changed MSH-4 is a firmware change");
+        assert_eq!(change.previous.facility.as_deref(), Some("LAB"));
+        assert_eq!(change.current.facility.as_deref(), Some("LAB-FW2"));
+        assert!(change.previous_since.is_some());
+        assert!(change.conformance_report_until.is_some());
+
+        // The identity and conformance-report window are persisted with the analyzer
+        let stored: BF6900StoreData = serde_json::from_value(store.get("config").unwrap()).unwrap();
+        let stored = stored.analyzer.unwrap();
+        assert_eq!(stored.last_seen_identity, Some(change.current.clone()));
+        assert_eq!(stored.conformance_report_until, change.conformance_report_until);
+
+        // The change is what app_state records and emits as lis:analyzer-identity-changed
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        repository.save_firmware_change(&change).await.unwrap();
+        let history = repository.get_firmware_history(&analyzer_id).await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!((history[0].id.as_str(), &history[0].current), (change.id.as_str(), &change.current));
+        assert_eq!(history[0].previous, change.previous);
+        let payload = serde_json::to_value(&change).unwrap();
+        assert_eq!(payload["current"]["facility"], "LAB-FW2");
+    }
+
+    #[tokio::test]
+    async fn test_service_start_and_stop_persist_config_in_memory() {
+        let store = Arc::new(InMemoryConfigStore::new());
+        let mut analyzer = crate::test_support::bf6900_analyzer();
+        analyzer.port = Some(0);
+        let (sender, mut receiver) = mpsc::channel(10);
+        let service = BF6900Service::new(analyzer, sender, store.clone(), ShadowMode::default());
+
+        service.start().await.unwrap();
+        let stored: BF6900StoreData = serde_json::from_value(store.get("config").unwrap()).unwrap();
+        assert_eq!(stored.analyzer.unwrap().status, AnalyzerStatus::Active);
+        assert_eq!(service.get_status().await, AnalyzerStatus::Active);
+
+        service.stop().await.unwrap();
+        let stored: BF6900StoreData = serde_json::from_value(store.get("config").unwrap()).unwrap();
+        assert_eq!(stored.analyzer.unwrap().status, AnalyzerStatus::Inactive);
+        assert_eq!(service.get_status().await, AnalyzerStatus::Inactive);
+
+        assert!(matches!(
+            receiver.recv().await,
+            Some(BF6900Event::AnalyzerStatusUpdated { status: AnalyzerStatus::Active, .. })
+        ));
+        assert!(matches!(
+            receiver.recv().await,
+            Some(BF6900Event::AnalyzerStatusUpdated { status: AnalyzerStatus::Inactive, .. })
+        ));
+    }
+}
@@ -1,19 +1,90 @@
-use tauri::{AppHandle, Manager};
-use tauri_plugin_store::StoreExt;
+use tauri::{AppHandle, Emitter, Manager};
 
 use crate::app_state::AppState;
+use crate::db::{open_database_with_recovery, RecoveryReport, SqliteRepository};
+use crate::services::app_paths::{check_directories, AppDirectories, DirectoryCheckState};
+use crate::services::disk_monitor::DiskMonitorSettings;
+use crate::services::his_client::{his_config_from_store, HIS_CONFIG_STORE_KEY};
+use crate::services::ingestion_lanes::seed_arrival_sequence;
+use crate::services::result_feed::{result_feed_from_store, start_result_feed, RESULT_FEED_STORE_KEY};
+use crate::services::shadow_mode::{ShadowMode, SHADOW_MODE_STORE_KEY};
+use crate::services::store_recovery::open_store;
+use crate::services::upload_worker::{upload_retry_from_store, UPLOAD_RETRY_STORE_KEY};
+
+/// Database file shared with the frontend SQL plugin (`sqlite:nramh-lis.db`)
+const DB_FILE_NAME: &str = "nramh-lis.db";
+
+/// Outcome of the startup integrity check, kept for the frontend's recovery dialog
+pub struct DatabaseRecoveryState {
+    pub report: Option<RecoveryReport>,
+}
 
 pub async fn setup<R: tauri::Runtime>(app: AppHandle<R>) -> Result<(), String> {
-    let meril_store = app
-        .store("meril.json")
-        .map_err(|e| format!("Error getting Meril store: {}", e))?;
+    // Check every required directory can be written before anything is opened in it
+    let directories = AppDirectories::resolve(&app)?;
+    let checks = check_directories(&directories.required());
+    if checks.iter().any(|check| check.error.is_some()) {
+        let _ = app.emit("startup:directory-problems", &checks);
+    }
+    app.manage(DirectoryCheckState { checks });
+
+    let meril_store = open_store(&app, "meril.json")?;
+
+    let bf6900_store = open_store(&app, "bf6900.json")?;
 
-    let bf6900_store = app
-        .store("bf6900.json")
-        .map_err(|e| format!("Error getting BF-6900 store: {}", e))?;
+    let settings_store = open_store(&app, "settings.json")?;
+
+    let disk_monitor_settings = settings_store
+        .get("disk_monitor")
+        .and_then(|value| serde_json::from_value::<DiskMonitorSettings>(value).ok())
+        .unwrap_or_default();
+
+    let shadow_mode = ShadowMode::new(
+        settings_store
+            .get(SHADOW_MODE_STORE_KEY)
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false),
+    );
+    if shadow_mode.is_enabled() {
+        log::warn!("Starting in shadow mode: results are stored but nothing is sent to the HIS");
+    }
+
+    // Open the LIS database (same file the SQL plugin resolves in the app config dir)
+    let data_dir = directories.config;
+    let db_path = data_dir.join(DB_FILE_NAME);
+    let (pool, recovery_report) = open_database_with_recovery(&db_path).await?;
+    if let Some(report) = &recovery_report {
+        let _ = app.emit("db:recovered", report);
+    }
+    app.manage(DatabaseRecoveryState {
+        report: recovery_report,
+    });
+    let repository = std::sync::Arc::new(SqliteRepository::new(pool));
+
+    // Messages received from now on rank after every message applied before the restart
+    match repository.get_latest_arrival_sequence().await {
+        Ok(latest) => seed_arrival_sequence(latest),
+        Err(e) => log::error!("Arrival sequence not restored: {}", e),
+    }
 
     // Initialize AppState with both services
-    let mut app_state = AppState::<R>::new(app.clone(), meril_store, bf6900_store)?;
+    let app_state = AppState::<R>::new(
+        app.clone(),
+        meril_store,
+        bf6900_store,
+        repository,
+        data_dir,
+        disk_monitor_settings,
+        shadow_mode,
+        his_config_from_store(settings_store.get(HIS_CONFIG_STORE_KEY)),
+        upload_retry_from_store(settings_store.get(UPLOAD_RETRY_STORE_KEY)),
+    )?;
+
+    // Serve the change feed to consumers outside the app when enabled
+    let result_feed = result_feed_from_store(settings_store.get(RESULT_FEED_STORE_KEY));
+    if let Err(e) = start_result_feed(app_state.get_repository().clone(), &result_feed).await {
+        log::error!("Result feed not started: {}", e);
+    }
 
     // Initialize the AppState (handles async operations like auto-starting services)
     app_state.initialize().await?;
@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tauri::Runtime;
+use tauri_plugin_store::Store;
+
+use crate::models::{Analyzer, AnalyzerStatus};
+
+// ============================================================================
+// CONFIG PERSISTENCE ABSTRACTION
+// ============================================================================
+
+/// Key/value persistence for analyzer configuration.
+///
+/// Production uses the Tauri store plugin; tests and headless runs use
+/// [`InMemoryConfigStore`], which needs no app handle.
+pub trait ConfigPersistence: Send + Sync {
+    fn get(&self, key: &str) -> Option<JsonValue>;
+    fn set(&self, key: &str, value: JsonValue);
+    fn save(&self) -> Result<(), String>;
+}
+
+impl<R: Runtime> ConfigPersistence for Store<R> {
+    fn get(&self, key: &str) -> Option<JsonValue> {
+        Store::get(self, key)
+    }
+
+    fn set(&self, key: &str, value: JsonValue) {
+        Store::set(self, key, value)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        Store::save(self).map_err(|e| format!("Failed to save store: {}", e))
+    }
+}
+
+/// Config persistence kept in memory, for tests and runs without a Tauri app
+#[derive(Debug, Default)]
+pub struct InMemoryConfigStore {
+    values: Mutex<HashMap<String, JsonValue>>,
+}
+
+impl InMemoryConfigStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ConfigPersistence for InMemoryConfigStore {
+    fn get(&self, key: &str) -> Option<JsonValue> {
+        self.values.lock().unwrap().get(key).cloned()
+    }
+
+    fn set(&self, key: &str, value: JsonValue) {
+        self.values.lock().unwrap().insert(key.to_string(), value);
+    }
+
+    fn save(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// ============================================================================
+// COALESCED STATUS PERSISTENCE
+// ============================================================================
+
+/// Store key of the analyzer config (`{ "analyzer": ... }`) the status is kept in
+pub const ANALYZER_CONFIG_STORE_KEY: &str = "config";
+
+/// Minimum time between two status writes of one analyzer
+pub const STATUS_PERSIST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The analyzer fields that change at runtime, written into the stored config
+/// without re-serializing the rest of it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzerStatusRecord {
+    pub status: AnalyzerStatus,
+    pub updated_at: DateTime<Utc>,
+    pub last_connected_at: Option<DateTime<Utc>>,
+}
+
+impl AnalyzerStatusRecord {
+    pub fn of(analyzer: &Analyzer) -> Self {
+        Self {
+            status: analyzer.status.clone(),
+            updated_at: analyzer.updated_at,
+            last_connected_at: analyzer.last_connected_at,
+        }
+    }
+}
+
+/// Resets a status left `Active` by a run that ended without stopping its service,
+/// e.g. a crash; no listener is running when the config is loaded
+pub fn reset_stale_status(analyzer: &mut Analyzer) {
+    if analyzer.status == AnalyzerStatus::Active {
+        log::warn!("Analyzer {} was left active by the previous run; resetting it to inactive", analyzer.id);
+        analyzer.status = AnalyzerStatus::Inactive;
+    }
+}
+
+#[derive(Debug, Default)]
+struct PendingStatus {
+    latest: Option<Analyzer>,
+    last_write: Option<Instant>,
+    flush_scheduled: bool,
+    /// Failure of a deferred write, reported by the next call
+    failed: Option<String>,
+}
+
+/// Writes an analyzer's status at most once per `interval`.
+///
+/// A change inside the interval replaces any pending one and is written when the
+/// interval ends, so a flapping connection costs one store write per interval
+/// and the last state always wins. [`StatusPersister::flush`] writes the pending
+/// state immediately, for service stop and app shutdown. Only the fields of
+/// [`AnalyzerStatusRecord`] are written into a stored config; the whole analyzer
+/// is stored when there is none yet.
+#[derive(Clone)]
+pub struct StatusPersister {
+    store: Arc<dyn ConfigPersistence>,
+    interval: Duration,
+    pending: Arc<Mutex<PendingStatus>>,
+}
+
+impl StatusPersister {
+    pub fn new(store: Arc<dyn ConfigPersistence>, interval: Duration) -> Self {
+        Self {
+            store,
+            interval,
+            pending: Arc::new(Mutex::new(PendingStatus::default())),
+        }
+    }
+
+    /// Queues the status of `analyzer`; written now if the interval has passed,
+    /// otherwise when it ends. Fails with the error of this write, or of a deferred
+    /// write that failed since the last call.
+    pub fn record(&self, analyzer: &Analyzer) -> Result<(), String> {
+        let mut pending = self.pending.lock().unwrap();
+        pending.latest = Some(analyzer.clone());
+
+        let since_write = pending.last_write.map(|at| at.elapsed());
+        match since_write {
+            Some(elapsed) if elapsed < self.interval => {
+                if !pending.flush_scheduled {
+                    pending.flush_scheduled = true;
+                    let persister = self.clone();
+                    let delay = self.interval - elapsed;
+                    tokio::spawn(async move {
+                        tokio::time::sleep(delay).await;
+                        persister.flush_deferred();
+                    });
+                }
+                pending.failed.take().map_or(Ok(()), Err)
+            }
+            _ => {
+                pending.failed = None;
+                self.write(&mut pending)
+            }
+        }
+    }
+
+    /// Writes the pending status, if any, right away
+    pub fn flush(&self) -> Result<(), String> {
+        let mut pending = self.pending.lock().unwrap();
+        pending.flush_scheduled = false;
+        let failed = pending.failed.take();
+        self.write(&mut pending)?;
+        failed.map_or(Ok(()), Err)
+    }
+
+    /// Deferred write at the end of an interval; a failure is kept for the next call
+    fn flush_deferred(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.flush_scheduled = false;
+        if let Err(e) = self.write(&mut pending) {
+            log::error!("Failed to persist analyzer status: {}", e);
+            pending.failed = Some(e);
+        }
+    }
+
+    fn write(&self, pending: &mut PendingStatus) -> Result<(), String> {
+        let Some(analyzer) = pending.latest.clone() else {
+            return Ok(());
+        };
+        let mut config = self
+            .store
+            .get(ANALYZER_CONFIG_STORE_KEY)
+            .filter(JsonValue::is_object)
+            .unwrap_or_else(|| serde_json::json!({}));
+        match config.get_mut("analyzer").and_then(JsonValue::as_object_mut) {
+            Some(stored) => {
+                let record = serde_json::to_value(AnalyzerStatusRecord::of(&analyzer))
+                    .map_err(|e| format!("Failed to serialize analyzer status: {}", e))?;
+                if let JsonValue::Object(fields) = record {
+                    stored.extend(fields);
+                }
+            }
+            None => {
+                config["analyzer"] = serde_json::to_value(&analyzer)
+                    .map_err(|e| format!("Failed to serialize analyzer status: {}", e))?;
+            }
+        }
+
+        pending.last_write = Some(Instant::now());
+        self.store.set(ANALYZER_CONFIG_STORE_KEY, config);
+        self.store.save()?;
+        // Kept until written, so a failed save is retried by the next write
+        pending.latest = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts saves, standing in for rewrites of the store file; fails them on demand
+    #[derive(Default)]
+    struct CountingStore {
+        inner: InMemoryConfigStore,
+        saves: std::sync::atomic::AtomicUsize,
+        failing: std::sync::atomic::AtomicBool,
+    }
+
+    impl ConfigPersistence for CountingStore {
+        fn get(&self, key: &str) -> Option<JsonValue> {
+            self.inner.get(key)
+        }
+
+        fn set(&self, key: &str, value: JsonValue) {
+            self.inner.set(key, value)
+        }
+
+        fn save(&self) -> Result<(), String> {
+            if self.failing.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err("Failed to save store: disk full".to_string());
+            }
+            self.saves.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn analyzer_with(status: AnalyzerStatus, at: DateTime<Utc>) -> Analyzer {
+        Analyzer {
+            status,
+            updated_at: at,
+            last_connected_at: Some(at),
+            ..crate::test_support::bf6900_analyzer()
+        }
+    }
+
+    fn stored_analyzer(store: &dyn ConfigPersistence) -> Analyzer {
+        serde_json::from_value(store.get(ANALYZER_CONFIG_STORE_KEY).unwrap()["analyzer"].clone()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_flapping_status_writes_are_bounded() {
+        let store = Arc::new(CountingStore::default());
+        let interval = Duration::from_millis(50);
+        let persister = StatusPersister::new(store.clone(), interval);
+        let saves = || store.saves.load(std::sync::atomic::Ordering::SeqCst);
+
+        let started = Instant::now();
+        let mut last = None;
+        for i in 0..100 {
+            let status = if i % 2 == 0 { AnalyzerStatus::Active } else { AnalyzerStatus::Inactive };
+            let analyzer = analyzer_with(status, Utc::now());
+            persister.record(&analyzer).unwrap();
+            last = Some(AnalyzerStatusRecord::of(&analyzer));
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+        let bound = (started.elapsed().as_millis() / interval.as_millis()) as usize + 2;
+        assert!(saves() <= bound, "{} writes for {} allowed", saves(), bound);
+        assert!(saves() < 100);
+
+        // The pending state is written once the interval ends, without a flush
+        tokio::time::sleep(interval * 2).await;
+        assert_eq!(Some(AnalyzerStatusRecord::of(&stored_analyzer(store.as_ref()))), last);
+
+        // A final state recorded just before stop is written by the flush
+        let stopped = analyzer_with(AnalyzerStatus::Inactive, Utc::now());
+        persister.record(&analyzer_with(AnalyzerStatus::Active, Utc::now())).unwrap();
+        persister.record(&stopped).unwrap();
+        persister.flush().unwrap();
+        assert_eq!(
+            AnalyzerStatusRecord::of(&stored_analyzer(store.as_ref())),
+            AnalyzerStatusRecord::of(&stopped)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_is_written_into_the_stored_config() {
+        let store = Arc::new(CountingStore::default());
+        let persister = StatusPersister::new(store.clone(), Duration::from_millis(50));
+
+        // The rest of the stored config is left as it was saved
+        let mut saved = analyzer_with(AnalyzerStatus::Inactive, Utc::now());
+        saved.name = "Saved name".to_string();
+        store.set(
+            ANALYZER_CONFIG_STORE_KEY,
+            serde_json::json!({ "analyzer": saved, "hl7_settings": { "encoding": "UTF-8" } }),
+        );
+        let running = analyzer_with(AnalyzerStatus::Active, Utc::now());
+        persister.record(&running).unwrap();
+        let config = store.get(ANALYZER_CONFIG_STORE_KEY).unwrap();
+        assert_eq!(config["hl7_settings"]["encoding"], "UTF-8");
+        let stored = stored_analyzer(store.as_ref());
+        assert_eq!(stored.name, "Saved name");
+        assert_eq!(AnalyzerStatusRecord::of(&stored), AnalyzerStatusRecord::of(&running));
+
+        // Left active by a crash: reset when loaded
+        let mut loaded = stored;
+        reset_stale_status(&mut loaded);
+        assert_eq!(loaded.status, AnalyzerStatus::Inactive);
+        let mut maintenance = analyzer_with(AnalyzerStatus::Maintenance, Utc::now());
+        reset_stale_status(&mut maintenance);
+        assert_eq!(maintenance.status, AnalyzerStatus::Maintenance);
+
+        // A failed deferred write is reported by the next call and retried
+        store.failing.store(true, std::sync::atomic::Ordering::SeqCst);
+        let stopped = analyzer_with(AnalyzerStatus::Inactive, Utc::now());
+        persister.record(&stopped).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(persister.record(&stopped).unwrap_err().contains("disk full"));
+        assert!(persister.flush().is_err());
+        store.failing.store(false, std::sync::atomic::Ordering::SeqCst);
+        persister.flush().unwrap();
+        assert_eq!(stored_analyzer(store.as_ref()).status, AnalyzerStatus::Inactive);
+    }
+
+    #[test]
+    fn test_in_memory_store_round_trip() {
+        let store = InMemoryConfigStore::new();
+        assert!(store.get("config").is_none());
+
+        store.set("config", serde_json::json!({ "analyzer": null }));
+        store.save().unwrap();
+        assert_eq!(store.get("config"), Some(serde_json::json!({ "analyzer": null })));
+    }
+}
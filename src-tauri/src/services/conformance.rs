@@ -0,0 +1,344 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::ConformanceWarning;
+use crate::protocol::astm::AstmDelimiters;
+use crate::protocol::hl7_parser::HL7Message;
+
+// ============================================================================
+// CONFORMANCE RULES
+// ============================================================================
+
+/// One CQ 5 Plus spec check. `check` returns what was wrong, or None when the message conforms.
+pub struct ConformanceRule<M: ?Sized> {
+    pub id: &'static str,
+    pub description: &'static str,
+    pub check: fn(&M) -> Option<String>,
+}
+
+/// Checks run on every HL7 message that parsed and validated
+pub static HL7_RULES: &[ConformanceRule<HL7Message>] = &[
+    ConformanceRule {
+        id: "HL7_VERSION",
+        description: "MSH-12 version ID is not 2.3.1",
+        check: hl7_version,
+    },
+    ConformanceRule {
+        id: "HL7_PROCESSING_ID",
+        description: "MSH-11 processing ID is neither P nor Q",
+        check: hl7_processing_id,
+    },
+    ConformanceRule {
+        id: "HL7_CONTROL_ID",
+        description: "MSH-10 message control ID is empty",
+        check: hl7_control_id,
+    },
+    ConformanceRule {
+        id: "HL7_OBX_WITHOUT_OBR",
+        description: "OBX segment sent before any OBR",
+        check: hl7_obx_without_obr,
+    },
+    ConformanceRule {
+        id: "HL7_RESULT_WITHOUT_PID",
+        description: "ORU message has no PID segment",
+        check: hl7_result_without_pid,
+    },
+];
+
+/// Checks run on the records of every complete ASTM transmission
+pub static ASTM_RULES: &[ConformanceRule<[String]>] = &[
+    ConformanceRule {
+        id: "ASTM_HEADER_FIRST",
+        description: "First record is not a header (H) record",
+        check: astm_header_first,
+    },
+    ConformanceRule {
+        id: "ASTM_HEADER_DELIMITERS",
+        description: "Header record is missing the delimiter definition",
+        check: astm_header_delimiters,
+    },
+    ConformanceRule {
+        id: "ASTM_RESULT_WITHOUT_ORDER",
+        description: "Result (R) record sent before any order (O) record",
+        check: astm_result_without_order,
+    },
+    ConformanceRule {
+        id: "ASTM_TERMINATOR_LAST",
+        description: "Last record is not a terminator (L) record",
+        check: astm_terminator_last,
+    },
+];
+
+/// Runs the HL7 rules and counts any deviations
+pub fn check_hl7(message: &HL7Message) -> Vec<ConformanceWarning> {
+    run_rules(HL7_RULES, message)
+}
+
+/// Runs the ASTM rules over a transmission's records (with or without frame numbers)
+pub fn check_astm(records: &[String]) -> Vec<ConformanceWarning> {
+    run_rules(ASTM_RULES, records)
+}
+
+fn run_rules<M: ?Sized>(rules: &[ConformanceRule<M>], message: &M) -> Vec<ConformanceWarning> {
+    let warnings: Vec<ConformanceWarning> = rules
+        .iter()
+        .filter_map(|rule| {
+            (rule.check)(message).map(|detail| ConformanceWarning {
+                rule_id: rule.id.to_string(),
+                description: rule.description.to_string(),
+                detail,
+            })
+        })
+        .collect();
+    record_metrics(&warnings);
+    warnings
+}
+
+// ----------------------------------------------------------------------------
+// HL7 checks
+// ----------------------------------------------------------------------------
+
+fn first_component(field: &str) -> &str {
+    field.split('^').next().unwrap_or_default().trim()
+}
+
+fn hl7_version(message: &HL7Message) -> Option<String> {
+    let version = first_component(&message.version_id);
+    (version != "2.3.1").then(|| format!("MSH-12 is '{}'", message.version_id))
+}
+
+fn hl7_processing_id(message: &HL7Message) -> Option<String> {
+    let processing_id = first_component(&message.processing_id);
+    (!matches!(processing_id, "P" | "Q")).then(|| format!("MSH-11 is '{}'", message.processing_id))
+}
+
+fn hl7_control_id(message: &HL7Message) -> Option<String> {
+    message
+        .message_control_id
+        .trim()
+        .is_empty()
+        .then(|| format!("{} message has no control ID to acknowledge", message.message_type))
+}
+
+fn hl7_obx_without_obr(message: &HL7Message) -> Option<String> {
+    for (index, segment) in message.segments.iter().enumerate() {
+        match segment.segment_type.as_str() {
+            "OBR" => return None,
+            "OBX" => {
+                let set_id = segment.fields.get(1).map(String::as_str).unwrap_or_default();
+                return Some(format!("OBX {} at segment {} has no preceding OBR", set_id, index + 1));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn hl7_result_without_pid(message: &HL7Message) -> Option<String> {
+    let is_result = message.message_type.starts_with("ORU");
+    let has_pid = message.segments.iter().any(|s| s.segment_type == "PID");
+    (is_result && !has_pid).then(|| format!("{} has no PID", message.message_type))
+}
+
+// ----------------------------------------------------------------------------
+// ASTM checks
+// ----------------------------------------------------------------------------
+
+/// Record text after its frame number
+fn record_body(record: &str) -> &str {
+    record.trim_start_matches(|c: char| c.is_ascii_digit())
+}
+
+fn record_type(record: &str) -> Option<char> {
+    record_body(record).chars().next()
+}
+
+fn astm_header_first(records: &[String]) -> Option<String> {
+    // An ENQ/EOT pair with no frames is a valid (empty) transmission
+    let first = records.first()?;
+    (record_type(first) != Some('H')).then(|| format!("first record is '{}'", record_type(first).unwrap_or(' ')))
+}
+
+fn astm_header_delimiters(records: &[String]) -> Option<String> {
+    let header = records.iter().find(|r| record_type(r) == Some('H'))?;
+    if AstmDelimiters::from_header(header).is_some() {
+        return None;
+    }
+    let declared: String = record_body(header).chars().skip(1).take(4).collect();
+    Some(format!("header declares '{}' instead of four distinct delimiters", declared))
+}
+
+fn astm_result_without_order(records: &[String]) -> Option<String> {
+    for (index, record) in records.iter().enumerate() {
+        match record_type(record) {
+            Some('O') => return None,
+            Some('R') => return Some(format!("R record at position {} has no preceding O record", index + 1)),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn astm_terminator_last(records: &[String]) -> Option<String> {
+    let last = records.last()?;
+    (record_type(last) != Some('L')).then(|| format!("last record is '{}'", record_type(last).unwrap_or(' ')))
+}
+
+// ============================================================================
+// CONFORMANCE METRICS
+// ============================================================================
+
+static MESSAGES_CHECKED: AtomicU64 = AtomicU64::new(0);
+static MESSAGES_WITH_WARNINGS: AtomicU64 = AtomicU64::new(0);
+static WARNINGS_BY_RULE: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+
+/// Conformance checks run since startup
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConformanceMetrics {
+    pub messages_checked: u64,
+    pub messages_with_warnings: u64,
+    pub warnings_by_rule: BTreeMap<String, u64>,
+}
+
+fn record_metrics(warnings: &[ConformanceWarning]) {
+    MESSAGES_CHECKED.fetch_add(1, Ordering::Relaxed);
+    if warnings.is_empty() {
+        return;
+    }
+    MESSAGES_WITH_WARNINGS.fetch_add(1, Ordering::Relaxed);
+    let mut by_rule = WARNINGS_BY_RULE.lock().unwrap();
+    for warning in warnings {
+        *by_rule.entry(warning.rule_id.clone()).or_default() += 1;
+    }
+}
+
+pub fn conformance_metrics() -> ConformanceMetrics {
+    ConformanceMetrics {
+        messages_checked: MESSAGES_CHECKED.load(Ordering::Relaxed),
+        messages_with_warnings: MESSAGES_WITH_WARNINGS.load(Ordering::Relaxed),
+        warnings_by_rule: WARNINGS_BY_RULE.lock().unwrap().clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::hl7_parser::parse_hl7_message;
+
+    const CONFORMING_HL7: &str = "MSH|^~\\&|CQ5Plus|Meril|LIS||20240101120000||ORU^R01|42|P|2.3.1||||||UNICODE\r\
+PID|1||P001||Doe^John\r\
+OBR|1||S001\r\
+OBX|1|NM|6690-2^WBC^LN||7.5|10*3/uL|4.0-10.0|N|||F";
+
+    const CONFORMING_ASTM: [&str; 5] = [
+        "1H|\\^&|||CQ5Plus",
+        "2P|1||P001",
+        "3O|1|S001",
+        "4R|1|^^^GLU|5.4|mmol/L",
+        "5L|1|N",
+    ];
+
+    fn hl7(raw: &str) -> HL7Message {
+        parse_hl7_message(raw).expect("fixture parses")
+    }
+
+    fn astm(records: &[&str]) -> Vec<String> {
+        records.iter().map(|r| r.to_string()).collect()
+    }
+
+    fn rule_ids(warnings: &[ConformanceWarning]) -> Vec<&str> {
+        warnings.iter().map(|w| w.rule_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_conforming_fixtures_produce_no_warnings() {
+        assert!(check_hl7(&hl7(CONFORMING_HL7)).is_empty());
+        assert!(check_astm(&astm(&CONFORMING_ASTM)).is_empty());
+    }
+
+    #[test]
+    fn test_hl7_version_and_processing_id() {
+        let message = hl7(&CONFORMING_HL7.replace("|42|P|2.3.1|", "|42|T|2.4|"));
+
+        let warnings = check_hl7(&message);
+
+        assert_eq!(rule_ids(&warnings), vec!["HL7_VERSION", "HL7_PROCESSING_ID"]);
+        assert_eq!(warnings[0].detail, "MSH-12 is '2.4'");
+        assert_eq!(warnings[1].detail, "MSH-11 is 'T'");
+
+        // Q (query) is a valid processing ID too
+        let query = hl7(&CONFORMING_HL7.replace("|42|P|", "|42|Q|"));
+        assert!(check_hl7(&query).is_empty());
+    }
+
+    #[test]
+    fn test_hl7_obx_before_obr() {
+        let message = hl7(
+            "MSH|^~\\&|CQ5Plus|Meril|LIS||20240101120000||ORU^R01|42|P|2.3.1\r\
+PID|1||P001\r\
+OBX|1|NM|6690-2^WBC^LN||7.5\r\
+OBR|1||S001",
+        );
+
+        let warnings = check_hl7(&message);
+
+        assert_eq!(rule_ids(&warnings), vec!["HL7_OBX_WITHOUT_OBR"]);
+        assert_eq!(warnings[0].detail, "OBX 1 at segment 3 has no preceding OBR");
+    }
+
+    #[test]
+    fn test_hl7_result_without_pid() {
+        let message = hl7(&CONFORMING_HL7.replace("PID|1||P001||Doe^John\r", ""));
+
+        assert_eq!(rule_ids(&check_hl7(&message)), vec!["HL7_RESULT_WITHOUT_PID"]);
+    }
+
+    #[test]
+    fn test_astm_header_missing_delimiter_definition() {
+        let mut records = CONFORMING_ASTM;
+        records[0] = "1H||||CQ5Plus";
+
+        let warnings = check_astm(&astm(&records));
+
+        assert_eq!(rule_ids(&warnings), vec!["ASTM_HEADER_DELIMITERS"]);
+        assert_eq!(warnings[0].detail, "header declares '||||' instead of four distinct delimiters");
+    }
+
+    #[test]
+    fn test_astm_record_order() {
+        // No header, result before its order, no terminator
+        let records = astm(&["1P|1||P001", "2R|1|^^^GLU|5.4", "3O|1|S001"]);
+
+        let warnings = check_astm(&records);
+
+        assert_eq!(
+            rule_ids(&warnings),
+            vec!["ASTM_HEADER_FIRST", "ASTM_RESULT_WITHOUT_ORDER", "ASTM_TERMINATOR_LAST"]
+        );
+        assert_eq!(warnings[0].detail, "first record is 'P'");
+        assert_eq!(warnings[2].detail, "last record is 'O'");
+    }
+
+    #[test]
+    fn test_empty_astm_transmission_conforms() {
+        assert!(check_astm(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_warnings_are_counted_in_metrics() {
+        let before = conformance_metrics();
+        let mut records = CONFORMING_ASTM;
+        records[4] = "5C|1|trailing comment";
+
+        check_astm(&astm(&records));
+
+        let after = conformance_metrics();
+        assert!(after.messages_checked > before.messages_checked);
+        assert!(after.messages_with_warnings > before.messages_with_warnings);
+        let count = |m: &ConformanceMetrics| m.warnings_by_rule.get("ASTM_TERMINATOR_LAST").copied().unwrap_or(0);
+        assert!(count(&after) > count(&before));
+    }
+}
@@ -0,0 +1,439 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::db::SqliteRepository;
+pub use crate::models::{ConnectionSummary, ConnectionTermination};
+
+/// Entries kept per connection; older entries are dropped first
+pub const CONVERSATION_ENTRY_LIMIT: usize = 500;
+/// Closed connections whose last conversation stays retrievable
+pub const RECENT_CONVERSATION_LIMIT: usize = 20;
+/// Key in the app settings store (`settings.json`) holding the connection history settings
+pub const CONNECTION_HISTORY_STORE_KEY: &str = "connection_history";
+
+/// How many closed connections keep their summary and conversation in memory;
+/// older ones are only in the `connection_events` table
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ConnectionHistorySettings {
+    pub recent_limit: usize,
+}
+
+impl Default for ConnectionHistorySettings {
+    fn default() -> Self {
+        Self { recent_limit: RECENT_CONVERSATION_LIMIT }
+    }
+}
+
+/// Reads stored settings, falling back to the default when missing or invalid
+pub fn connection_history_from_store(stored: Option<serde_json::Value>) -> ConnectionHistorySettings {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid connection history settings: {}", e);
+            ConnectionHistorySettings::default()
+        }),
+        None => ConnectionHistorySettings::default(),
+    }
+}
+
+/// What a conversation log holds in memory
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConversationRetention {
+    /// Conversations of open connections
+    pub live: usize,
+    /// Closed conversations kept for post-mortems
+    pub retained_closed: usize,
+    pub recent_limit: usize,
+}
+
+/// Live vs retained connection counts across the analyzer services
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ConnectionRetentionMetrics {
+    /// Sockets in the services' connection maps
+    pub live_connections: usize,
+    pub live_conversations: usize,
+    pub retained_closed: usize,
+    pub recent_limit: usize,
+    /// Closed connections in the `connection_events` table
+    pub archived: u64,
+}
+
+// ============================================================================
+// CONVERSATION ENTRIES
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Direction {
+    /// Analyzer to LIS
+    Inbound,
+    /// LIS to analyzer
+    Outbound,
+}
+
+/// Protocol element exchanged on a connection
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub enum ConversationElement {
+    Enq,
+    Ack,
+    Nak,
+    Eot,
+    /// ASTM frame with its frame number (0-7)
+    Frame { number: u8 },
+    /// HL7 message in MLLP framing
+    MllpFrame,
+    /// HL7 acknowledgment in MLLP framing, with its MSA code
+    MllpAck { code: String },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum EntryOutcome {
+    Accepted,
+    Rejected,
+    /// Arrived where the protocol does not expect it and was dropped
+    Ignored,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConversationEntry {
+    /// Position in the conversation, kept across dropped entries
+    pub sequence: u64,
+    pub direction: Direction,
+    pub element: ConversationElement,
+    /// Bytes on the wire
+    pub length: usize,
+    pub at: DateTime<Utc>,
+    pub outcome: EntryOutcome,
+    pub detail: Option<String>,
+}
+
+// ============================================================================
+// CONVERSATION LOG
+// ============================================================================
+
+#[derive(Debug)]
+struct Conversation {
+    summary: ConnectionSummary,
+    entries: VecDeque<ConversationEntry>,
+    next_sequence: u64,
+}
+
+#[derive(Debug, Default)]
+struct ConversationLogState {
+    active: HashMap<String, Conversation>,
+    /// Closed conversations, newest last
+    closed: VecDeque<Conversation>,
+    recent_limit: usize,
+    /// Receives the summary of every closed connection, for the database history
+    archive: Option<mpsc::UnboundedSender<ConnectionSummary>>,
+}
+
+/// Structured per-connection record of protocol elements, used to draw
+/// sequence diagrams. Every entry is also written to the trace log.
+#[derive(Debug, Clone)]
+pub struct ConversationLog {
+    state: Arc<Mutex<ConversationLogState>>,
+    entry_limit: usize,
+}
+
+impl Default for ConversationLog {
+    fn default() -> Self {
+        Self::new(CONVERSATION_ENTRY_LIMIT, RECENT_CONVERSATION_LIMIT)
+    }
+}
+
+impl ConversationLog {
+    pub fn new(entry_limit: usize, recent_limit: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ConversationLogState {
+                recent_limit,
+                ..Default::default()
+            })),
+            entry_limit: entry_limit.max(1),
+        }
+    }
+
+    /// Changes how many closed conversations stay in memory, dropping the oldest
+    pub fn set_recent_limit(&self, recent_limit: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.recent_limit = recent_limit;
+        while state.closed.len() > recent_limit {
+            state.closed.pop_front();
+        }
+    }
+
+    /// Sends the summary of every connection closed from now on to `archive`
+    pub fn set_archive(&self, archive: mpsc::UnboundedSender<ConnectionSummary>) {
+        self.state.lock().unwrap().archive = Some(archive);
+    }
+
+    /// Counts of the conversations held in memory
+    pub fn retention(&self) -> ConversationRetention {
+        let state = self.state.lock().unwrap();
+        ConversationRetention {
+            live: state.active.len(),
+            retained_closed: state.closed.len(),
+            recent_limit: state.recent_limit,
+        }
+    }
+
+    /// Starts a conversation for a newly accepted connection
+    pub fn open(
+        &self,
+        analyzer_id: &str,
+        protocol: &str,
+        remote_address: SocketAddr,
+    ) -> ConversationRecorder {
+        let connection_id = Uuid::new_v4().to_string();
+        let conversation = Conversation {
+            summary: ConnectionSummary {
+                connection_id: connection_id.clone(),
+                analyzer_id: analyzer_id.to_string(),
+                protocol: protocol.to_string(),
+                remote_address: remote_address.to_string(),
+                opened_at: Utc::now(),
+                closed_at: None,
+                entries: 0,
+                dropped_entries: 0,
+                termination: None,
+            },
+            entries: VecDeque::new(),
+            next_sequence: 0,
+        };
+        self.state
+            .lock()
+            .unwrap()
+            .active
+            .insert(connection_id.clone(), conversation);
+
+        ConversationRecorder {
+            log: self.clone(),
+            connection_id,
+        }
+    }
+
+    /// Last `limit` entries of an open or recently closed connection, oldest first
+    pub fn get_conversation(
+        &self,
+        connection_id: &str,
+        limit: usize,
+    ) -> Option<Vec<ConversationEntry>> {
+        let state = self.state.lock().unwrap();
+        let conversation = state.active.get(connection_id).or_else(|| {
+            state
+                .closed
+                .iter()
+                .find(|c| c.summary.connection_id == connection_id)
+        })?;
+        let skip = conversation.entries.len().saturating_sub(limit);
+        Some(conversation.entries.iter().skip(skip).cloned().collect())
+    }
+
+    /// Open connections followed by closed ones, each newest first
+    pub fn recent_connections(&self) -> Vec<ConnectionSummary> {
+        let state = self.state.lock().unwrap();
+        let mut open: Vec<ConnectionSummary> =
+            state.active.values().map(|c| c.summary.clone()).collect();
+        open.sort_by(|a, b| b.opened_at.cmp(&a.opened_at));
+        open.extend(state.closed.iter().rev().map(|c| c.summary.clone()));
+        open
+    }
+
+    fn record(
+        &self,
+        connection_id: &str,
+        direction: Direction,
+        element: ConversationElement,
+        length: usize,
+        outcome: EntryOutcome,
+        detail: Option<String>,
+    ) {
+        log::trace!(
+            "Conversation {}: {:?} {:?} ({} bytes) {:?}{}",
+            connection_id,
+            direction,
+            element,
+            length,
+            outcome,
+            detail.as_deref().map(|d| format!(": {}", d)).unwrap_or_default()
+        );
+
+        let mut state = self.state.lock().unwrap();
+        let Some(conversation) = state.active.get_mut(connection_id) else {
+            return;
+        };
+        let entry = ConversationEntry {
+            sequence: conversation.next_sequence,
+            direction,
+            element,
+            length,
+            at: Utc::now(),
+            outcome,
+            detail,
+        };
+        conversation.next_sequence += 1;
+        if conversation.entries.len() == self.entry_limit {
+            conversation.entries.pop_front();
+            conversation.summary.dropped_entries += 1;
+        }
+        conversation.entries.push_back(entry);
+        conversation.summary.entries = conversation.entries.len();
+    }
+
+    fn close(&self, connection_id: &str, termination: ConnectionTermination) {
+        let mut state = self.state.lock().unwrap();
+        let Some(mut conversation) = state.active.remove(connection_id) else {
+            return;
+        };
+        conversation.summary.closed_at = Some(Utc::now());
+        conversation.summary.termination = Some(termination);
+        if let Some(archive) = &state.archive {
+            let _ = archive.send(conversation.summary.clone());
+        }
+        state.closed.push_back(conversation);
+        while state.closed.len() > state.recent_limit {
+            state.closed.pop_front();
+        }
+    }
+}
+
+/// Writes archived connection summaries to the `connection_events` table until
+/// every sender is gone. Summaries closed while `paused` says so are not written;
+/// the in-memory history still has the recent ones.
+pub async fn archive_connections(
+    repository: Arc<SqliteRepository>,
+    paused: impl Fn() -> bool,
+    mut receiver: mpsc::UnboundedReceiver<ConnectionSummary>,
+) {
+    while let Some(summary) = receiver.recv().await {
+        if paused() {
+            log::debug!("Connection archive paused; not archiving {}", summary.connection_id);
+            continue;
+        }
+        if let Err(e) = repository.archive_connection(&summary).await {
+            log::error!("Failed to archive connection {}: {}", summary.connection_id, e);
+        }
+    }
+}
+
+/// Handle a connection uses to append to its conversation
+#[derive(Debug, Clone)]
+pub struct ConversationRecorder {
+    log: ConversationLog,
+    connection_id: String,
+}
+
+impl ConversationRecorder {
+    pub fn connection_id(&self) -> &str {
+        &self.connection_id
+    }
+
+    pub fn record(
+        &self,
+        direction: Direction,
+        element: ConversationElement,
+        length: usize,
+        outcome: EntryOutcome,
+        detail: Option<String>,
+    ) {
+        self.log
+            .record(&self.connection_id, direction, element, length, outcome, detail);
+    }
+
+    /// Accepted inbound element
+    pub fn received(&self, element: ConversationElement, length: usize) {
+        self.record(Direction::Inbound, element, length, EntryOutcome::Accepted, None);
+    }
+
+    /// Outbound element written to the analyzer
+    pub fn sent(&self, element: ConversationElement, length: usize) {
+        self.record(Direction::Outbound, element, length, EntryOutcome::Accepted, None);
+    }
+
+    /// Moves the conversation to the recent-connections list, recording how it ended
+    pub fn close(&self, termination: ConnectionTermination) {
+        self.log.close(&self.connection_id, termination);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr() -> SocketAddr {
+        "192.168.1.50:4000".parse().unwrap()
+    }
+
+    #[test]
+    fn test_ring_drops_oldest_and_closed_conversation_stays_retrievable() {
+        let log = ConversationLog::new(3, 1);
+        let recorder = log.open("analyzer-1", "ASTM", addr());
+        for number in 1..=5u8 {
+            recorder.received(ConversationElement::Frame { number }, 10);
+        }
+
+        let entries = log.get_conversation(recorder.connection_id(), 10).unwrap();
+        let sequences: Vec<u64> = entries.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, vec![2, 3, 4]);
+        assert_eq!(log.get_conversation(recorder.connection_id(), 1).unwrap()[0].sequence, 4);
+
+        recorder.close(ConnectionTermination::Completed);
+        let summaries = log.recent_connections();
+        assert_eq!(summaries.len(), 1);
+        assert!(summaries[0].closed_at.is_some());
+        assert_eq!(summaries[0].dropped_entries, 2);
+        assert_eq!(log.get_conversation(recorder.connection_id(), 10).unwrap().len(), 3);
+
+        // Only the newest closed conversation is kept
+        let next = log.open("analyzer-1", "ASTM", addr());
+        next.close(ConnectionTermination::Completed);
+        assert!(log.get_conversation(recorder.connection_id(), 10).is_none());
+        assert!(log.get_conversation(next.connection_id(), 10).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_churn_keeps_memory_bounded_and_database_complete() {
+        use crate::db::establish_test_connection;
+
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let log = ConversationLog::new(CONVERSATION_ENTRY_LIMIT, 25);
+        let (archive_sender, archive_receiver) = mpsc::unbounded_channel();
+        log.set_archive(archive_sender);
+        let archiver = tokio::spawn(archive_connections(repository.clone(), || false, archive_receiver));
+
+        for n in 0..10_000 {
+            let recorder = log.open("analyzer-1", "ASTM", addr());
+            recorder.received(ConversationElement::Enq, 1);
+            recorder.sent(ConversationElement::Ack, 1);
+            if n % 1000 == 0 {
+                let retention = log.retention();
+                assert_eq!(retention.live, 1);
+                assert!(retention.retained_closed <= 25);
+            }
+            recorder.close(ConnectionTermination::Completed);
+        }
+
+        assert_eq!(
+            log.retention(),
+            ConversationRetention { live: 0, retained_closed: 25, recent_limit: 25 }
+        );
+        assert_eq!(log.recent_connections().len(), 25);
+
+        // Lowering the bound drops the oldest retained conversations at once
+        log.set_recent_limit(10);
+        assert_eq!(log.retention().retained_closed, 10);
+
+        drop(log);
+        archiver.await.unwrap();
+        assert_eq!(repository.count_archived_connections().await.unwrap(), 10_000);
+        let history = repository.get_connection_history("analyzer-1", 5).await.unwrap();
+        assert_eq!(history.len(), 5);
+        assert!(history.iter().all(|summary| summary.closed_at.is_some() && summary.entries == 2));
+    }
+}
@@ -0,0 +1,479 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+use uuid::Uuid;
+
+use crate::db::SqliteRepository;
+use crate::models::patient::{PatientName, Sex};
+use crate::models::result::TestResultMetadata;
+use crate::models::{DataSource, Patient, ResultStatus, TestResult};
+
+// ============================================================================
+// IMPORT CONFIGURATION
+// ============================================================================
+
+/// Maps CSV header names onto the fields of a historical result.
+/// `test_codes` translates the codes used in the file to the test ids stored by the LIS;
+/// rows with a code missing from the table are rejected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportMappingProfile {
+    pub patient_id_column: String,
+    pub patient_name_column: Option<String>,
+    pub sample_id_column: String,
+    pub test_code_column: String,
+    pub value_column: String,
+    pub units_column: Option<String>,
+    pub date_column: String,
+    /// chrono format string, e.g. "%d/%m/%Y" or "%Y-%m-%d %H:%M"
+    pub date_format: String,
+    pub delimiter: char,
+    pub test_codes: HashMap<String, String>,
+}
+
+impl Default for ImportMappingProfile {
+    fn default() -> Self {
+        Self {
+            patient_id_column: "patient_id".to_string(),
+            patient_name_column: Some("patient_name".to_string()),
+            sample_id_column: "sample_id".to_string(),
+            test_code_column: "test_code".to_string(),
+            value_column: "value".to_string(),
+            units_column: Some("units".to_string()),
+            date_column: "date".to_string(),
+            date_format: "%Y-%m-%d".to_string(),
+            delimiter: ',',
+            test_codes: HashMap::new(),
+        }
+    }
+}
+
+// ============================================================================
+// IMPORT REPORT
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRowError {
+    pub line: usize,
+    pub column: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub rows_read: usize,
+    pub rows_valid: usize,
+    pub patients_created: usize,
+    pub results_created: usize,
+    pub errors: Vec<ImportRowError>,
+}
+
+/// A validated CSV row ready to be stored
+#[derive(Debug, Clone)]
+struct ImportRow {
+    patient_id: String,
+    patient_name: Option<String>,
+    sample_id: String,
+    test_id: String,
+    value: String,
+    units: Option<String>,
+    completed_date_time: DateTime<Utc>,
+}
+
+/// Column positions resolved from the header line
+struct ColumnIndex {
+    patient_id: usize,
+    patient_name: Option<usize>,
+    sample_id: usize,
+    test_code: usize,
+    value: usize,
+    units: Option<usize>,
+    date: usize,
+}
+
+// ============================================================================
+// IMPORT
+// ============================================================================
+
+/// Imports historical results from a CSV file.
+///
+/// Rows are validated and stored one at a time; a bad row is reported and skipped
+/// rather than aborting the file. Imported records are tagged `source = imported`
+/// and are never queued for HIS upload. With `dry_run` nothing is written.
+pub async fn import_results_csv(
+    repository: &SqliteRepository,
+    path: &Path,
+    profile: &ImportMappingProfile,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| format!("Failed to open import file {}: {}", path.display(), e))?;
+
+    log::info!("Importing historical results from {} (dry run: {})", path.display(), dry_run);
+    import_results_from_reader(repository, BufReader::new(file), profile, dry_run).await
+}
+
+/// Imports historical results from any buffered CSV source
+pub async fn import_results_from_reader<B: AsyncBufRead + Unpin>(
+    repository: &SqliteRepository,
+    reader: B,
+    profile: &ImportMappingProfile,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    let mut lines = reader.lines();
+    let mut report = ImportReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    let header = lines
+        .next_line()
+        .await
+        .map_err(|e| format!("Failed to read import header: {}", e))?
+        .ok_or_else(|| "Import file is empty".to_string())?;
+    let columns = resolve_columns(&split_csv_line(&header, profile.delimiter), profile)?;
+
+    let mut seen_patients = HashSet::new();
+    let mut line_number = 1;
+
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|e| format!("Failed to read import line {}: {}", line_number + 1, e))?
+    {
+        line_number += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        report.rows_read += 1;
+
+        let fields = split_csv_line(&line, profile.delimiter);
+        let row = match validate_row(&fields, &columns, profile) {
+            Ok(row) => row,
+            Err((column, message)) => {
+                report.errors.push(ImportRowError {
+                    line: line_number,
+                    column,
+                    message,
+                });
+                continue;
+            }
+        };
+        report.rows_valid += 1;
+
+        if dry_run {
+            if seen_patients.insert(row.patient_id.clone()) {
+                report.patients_created += 1;
+            }
+            report.results_created += 1;
+            continue;
+        }
+
+        match store_row(repository, &row, line_number).await {
+            Ok(patient_created) => {
+                if patient_created {
+                    report.patients_created += 1;
+                }
+                report.results_created += 1;
+            }
+            Err(e) => report.errors.push(ImportRowError {
+                line: line_number,
+                column: None,
+                message: e,
+            }),
+        }
+    }
+
+    log::info!(
+        "Import finished: {} rows read, {} valid, {} results created, {} errors",
+        report.rows_read,
+        report.rows_valid,
+        report.results_created,
+        report.errors.len()
+    );
+    Ok(report)
+}
+
+/// Creates the patient (if new) and the result for a validated row.
+/// Returns true when a new patient was created.
+async fn store_row(
+    repository: &SqliteRepository,
+    row: &ImportRow,
+    line_number: usize,
+) -> Result<bool, String> {
+    let now = Utc::now();
+    let (first_name, last_name) = split_patient_name(row.patient_name.as_deref());
+
+    let patient = Patient {
+        id: row.patient_id.clone(),
+        name: PatientName {
+            last_name,
+            first_name,
+            middle_name: None,
+            title: None,
+        },
+        birth_date: None,
+        sex: Sex::Other,
+        address: None,
+        telephone: Vec::new(),
+        contacts: Vec::new(),
+        physicians: None,
+        physical_attributes: None,
+        comments: Vec::new(),
+        created_at: now,
+        updated_at: now,
+    };
+    let patient_created = repository.save_patient(&patient, &DataSource::Imported).await?;
+
+    let result = TestResult {
+        id: Uuid::new_v4().to_string(),
+        test_id: row.test_id.clone(),
+        sample_id: row.sample_id.clone(),
+        value: row.value.clone(),
+        units: row.units.clone(),
+        reference_range: None,
+        flags: None,
+        status: ResultStatus::Final,
+        completed_date_time: Some(row.completed_date_time),
+        metadata: TestResultMetadata {
+            sequence_number: line_number as u32,
+            instrument: None,
+            operator: None,
+            dilution_factor: None,
+            raw_value: None,
+            source_message_control_id: None,
+            verification: None,
+            parameter_name: None,
+            panel: None,
+            analysis_mode: None,
+            unrecognized_status: None,
+        },
+        analyzer_id: None,
+        comments: Vec::new(),
+        created_at: now,
+        updated_at: now,
+    };
+    repository
+        .save_test_result(&result, &row.patient_id, &DataSource::Imported)
+        .await?;
+
+    Ok(patient_created)
+}
+
+fn resolve_columns(header: &[String], profile: &ImportMappingProfile) -> Result<ColumnIndex, String> {
+    let find = |name: &str| {
+        header
+            .iter()
+            .position(|column| column.trim().eq_ignore_ascii_case(name.trim()))
+    };
+    let required = |name: &str| find(name).ok_or_else(|| format!("Import file is missing column '{}'", name));
+
+    Ok(ColumnIndex {
+        patient_id: required(&profile.patient_id_column)?,
+        patient_name: profile.patient_name_column.as_deref().and_then(&find),
+        sample_id: required(&profile.sample_id_column)?,
+        test_code: required(&profile.test_code_column)?,
+        value: required(&profile.value_column)?,
+        units: profile.units_column.as_deref().and_then(&find),
+        date: required(&profile.date_column)?,
+    })
+}
+
+/// Validates a single row, returning the offending column and reason on failure
+fn validate_row(
+    fields: &[String],
+    columns: &ColumnIndex,
+    profile: &ImportMappingProfile,
+) -> Result<ImportRow, (Option<String>, String)> {
+    let field = |index: usize| fields.get(index).map(|f| f.trim()).unwrap_or("");
+    let required = |index: usize, name: &str| {
+        let value = field(index);
+        if value.is_empty() {
+            Err((Some(name.to_string()), format!("Missing value for '{}'", name)))
+        } else {
+            Ok(value.to_string())
+        }
+    };
+    let optional = |index: Option<usize>| {
+        index
+            .map(&field)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string())
+    };
+
+    let patient_id = required(columns.patient_id, &profile.patient_id_column)?;
+    let sample_id = required(columns.sample_id, &profile.sample_id_column)?;
+
+    let test_code = required(columns.test_code, &profile.test_code_column)?;
+    let test_id = profile
+        .test_codes
+        .iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(&test_code))
+        .map(|(_, test_id)| test_id.clone())
+        .ok_or_else(|| {
+            (
+                Some(profile.test_code_column.clone()),
+                format!("Unknown test code '{}'", test_code),
+            )
+        })?;
+
+    let value = required(columns.value, &profile.value_column)?;
+    if value.parse::<f64>().is_err() {
+        return Err((
+            Some(profile.value_column.clone()),
+            format!("Value '{}' is not numeric", value),
+        ));
+    }
+
+    let date = required(columns.date, &profile.date_column)?;
+    let completed_date_time = parse_import_date(&date, &profile.date_format).ok_or_else(|| {
+        (
+            Some(profile.date_column.clone()),
+            format!("Date '{}' does not match format '{}'", date, profile.date_format),
+        )
+    })?;
+
+    Ok(ImportRow {
+        patient_id,
+        patient_name: optional(columns.patient_name),
+        sample_id,
+        test_id,
+        value,
+        units: optional(columns.units),
+        completed_date_time,
+    })
+}
+
+/// Parses a date or date-time using the profile format
+fn parse_import_date(value: &str, format: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, format)
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, format)
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .map(|naive| naive.and_utc())
+}
+
+/// Splits "Last, First" or "First Last" into first and last name
+fn split_patient_name(name: Option<&str>) -> (Option<String>, Option<String>) {
+    let name = match name.map(str::trim).filter(|n| !n.is_empty()) {
+        Some(name) => name,
+        None => return (None, None),
+    };
+
+    if let Some((last, first)) = name.split_once(',') {
+        return (Some(first.trim().to_string()), Some(last.trim().to_string()));
+    }
+
+    match name.split_once(' ') {
+        Some((first, last)) => (Some(first.to_string()), Some(last.trim().to_string())),
+        None => (Some(name.to_string()), None),
+    }
+}
+
+/// Splits one CSV line, honouring double-quoted fields and "" escapes
+fn split_csv_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => fields.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+
+    const FIXTURE: &str = "patient_id,patient_name,sample_id,test_code,value,units,date\n\
+        P001,\"Sharma, Anita\",S100,HGB,12.5,g/dL,2025-01-10\n\
+        P001,\"Sharma, Anita\",S100,WBC,7.2,10^3/uL,2025-01-10\n\
+        P002,Ravi Kumar,S101,HGB,abc,g/dL,2025-01-11\n\
+        P003,Meena Rao,S102,XYZ,5.0,g/dL,2025-01-12\n\
+        P004,Arjun Das,S103,PLT,250,10^3/uL,12/01/2025\n\
+        ,No Id,S104,HGB,13.1,g/dL,2025-01-13\n\
+        P005,Kiran Shah,S105,PLT,310,10^3/uL,2025-01-14\n";
+
+    fn profile() -> ImportMappingProfile {
+        let mut profile = ImportMappingProfile::default();
+        profile.test_codes = HashMap::from([
+            ("HGB".to_string(), "HGB".to_string()),
+            ("WBC".to_string(), "WBC".to_string()),
+            ("PLT".to_string(), "PLT".to_string()),
+        ]);
+        profile
+    }
+
+    #[tokio::test]
+    async fn test_import_reports_bad_rows_and_tags_source() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+
+        let report = import_results_from_reader(&repository, FIXTURE.as_bytes(), &profile(), false)
+            .await
+            .unwrap();
+
+        assert_eq!(report.rows_read, 7);
+        assert_eq!(report.rows_valid, 3);
+        assert_eq!(report.results_created, 3);
+        assert_eq!(report.patients_created, 2);
+        assert_eq!(report.errors.len(), 4);
+
+        let lines: Vec<usize> = report.errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![4, 5, 6, 7]);
+        assert_eq!(report.errors[0].column.as_deref(), Some("value"));
+        assert_eq!(report.errors[1].column.as_deref(), Some("test_code"));
+        assert!(report.errors[1].message.contains("XYZ"));
+        assert_eq!(report.errors[2].column.as_deref(), Some("date"));
+        assert_eq!(report.errors[3].column.as_deref(), Some("patient_id"));
+
+        assert_eq!(repository.count_results_by_source(&DataSource::Imported).await.unwrap(), 3);
+        assert_eq!(repository.count_results_by_source(&DataSource::Analyzer).await.unwrap(), 0);
+
+        let patient = repository.get_patient("P001").await.unwrap().unwrap();
+        assert_eq!(patient.name.first_name.as_deref(), Some("Anita"));
+        assert_eq!(patient.name.last_name.as_deref(), Some("Sharma"));
+
+        // Imports never touch the HIS upload queue
+        assert!(repository.get_pending_uploads(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_dry_run_writes_nothing() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+
+        let report = import_results_from_reader(&repository, FIXTURE.as_bytes(), &profile(), true)
+            .await
+            .unwrap();
+
+        assert!(report.dry_run);
+        assert_eq!(report.rows_valid, 3);
+        assert_eq!(report.errors.len(), 4);
+        assert_eq!(repository.count_results_by_source(&DataSource::Imported).await.unwrap(), 0);
+        assert!(repository.get_patient("P001").await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_split_csv_line_with_quotes() {
+        let fields = split_csv_line("a,\"b, c\",\"d \"\"e\"\"\"", ',');
+        assert_eq!(fields, vec!["a", "b, c", "d \"e\""]);
+    }
+}
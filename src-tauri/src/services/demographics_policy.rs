@@ -0,0 +1,541 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::db::SqliteRepository;
+use crate::models::patient::Sex;
+use crate::models::{DemographicField, DemographicsHold, HoldStatus, Patient, UploadPriority};
+use crate::services::his_client::HisApiPayload;
+use crate::services::upload_worker::UploadWorker;
+
+/// Key in the app settings store (`settings.json`) holding the mandatory-demographics policy
+pub const DEMOGRAPHICS_POLICY_STORE_KEY: &str = "demographics_policy";
+
+// ============================================================================
+// POLICY
+// ============================================================================
+
+/// Patient fields that must be known before results are released to the HIS
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DemographicsPolicy {
+    pub enabled: bool,
+    pub required_fields: Vec<DemographicField>,
+}
+
+impl Default for DemographicsPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            required_fields: vec![DemographicField::Name, DemographicField::BirthDate],
+        }
+    }
+}
+
+/// Reads a stored policy, falling back to the default when missing or invalid
+pub fn demographics_policy_from_store(stored: Option<serde_json::Value>) -> DemographicsPolicy {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid demographics policy: {}", e);
+            DemographicsPolicy::default()
+        }),
+        None => DemographicsPolicy::default(),
+    }
+}
+
+/// Demographics known for a patient, from a message and/or the stored record
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Demographics {
+    pub name: Option<String>,
+    pub birth_date: Option<String>,
+    pub sex: Option<String>,
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value.map(str::trim).filter(|value| !value.is_empty()).map(str::to_string)
+}
+
+impl Demographics {
+    /// Demographics carried by an analyzer message; empty values and an unknown sex count as missing
+    pub fn from_message(name: &str, birth_date: Option<&str>, sex: Option<&str>) -> Self {
+        Self {
+            name: non_empty(Some(name)).filter(|name| name.chars().any(char::is_alphanumeric)),
+            birth_date: non_empty(birth_date),
+            sex: non_empty(sex).filter(|sex| Sex::from(sex.as_str()) != Sex::Other),
+        }
+    }
+
+    pub fn from_patient(patient: &Patient) -> Self {
+        let name = [&patient.name.first_name, &patient.name.last_name]
+            .into_iter()
+            .flatten()
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        Self {
+            name: non_empty(Some(&name)),
+            birth_date: patient.birth_date.map(|date| date.format("%Y%m%d").to_string()),
+            sex: (patient.sex != Sex::Other).then(|| String::from(patient.sex.clone())),
+        }
+    }
+
+    /// Fills the fields missing here from `other`
+    pub fn or(self, other: Demographics) -> Self {
+        Self {
+            name: self.name.or(other.name),
+            birth_date: self.birth_date.or(other.birth_date),
+            sex: self.sex.or(other.sex),
+        }
+    }
+
+    fn has(&self, field: DemographicField) -> bool {
+        match field {
+            DemographicField::Name => self.name.is_some(),
+            DemographicField::BirthDate => self.birth_date.is_some(),
+            DemographicField::Sex => self.sex.is_some(),
+        }
+    }
+}
+
+impl DemographicsPolicy {
+    /// Required fields the demographics lack; always empty while the policy is off
+    pub fn missing_fields(&self, demographics: &Demographics) -> Vec<DemographicField> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        self.required_fields
+            .iter()
+            .copied()
+            .filter(|field| !demographics.has(*field))
+            .collect()
+    }
+}
+
+// ============================================================================
+// GATE
+// ============================================================================
+
+/// What happened to the results submitted for one sample
+#[derive(Debug, Clone)]
+pub enum GateOutcome {
+    /// Queued for HIS upload (`None` when shadow mode skipped it)
+    Queued { upload_id: Option<String> },
+    /// Held until the patient's demographics are complete
+    Held(DemographicsHold),
+}
+
+/// Sits in front of the HIS upload queue and holds results of patients whose
+/// mandatory demographics are incomplete. Held results are released, in the
+/// order they were held, once the patient record or a later message completes them.
+pub struct DemographicsGate {
+    repository: Arc<SqliteRepository>,
+    upload_worker: Arc<UploadWorker>,
+    /// Serializes releases so a hold is never queued twice
+    release_lock: Mutex<()>,
+}
+
+impl DemographicsGate {
+    pub fn new(repository: Arc<SqliteRepository>, upload_worker: Arc<UploadWorker>) -> Self {
+        Self {
+            repository,
+            upload_worker,
+            release_lock: Mutex::new(()),
+        }
+    }
+
+    /// Queues a sample's results for upload, or holds them when the policy is not met.
+    /// Demographics from the message count together with the stored patient record;
+    /// when they complete the patient, earlier holds are released and returned as well.
+    /// `critical` marks results carrying a critical flag, which are dispatched first.
+    pub async fn submit(
+        &self,
+        policy: &DemographicsPolicy,
+        analyzer_id: &str,
+        patient_id: Option<&str>,
+        message_demographics: Demographics,
+        payload: &HisApiPayload,
+        critical: bool,
+    ) -> Result<(GateOutcome, Vec<DemographicsHold>), String> {
+        let priority = self.dispatch_priority(&payload.sample_no, critical).await;
+        self.submit_with_priority(policy, analyzer_id, patient_id, message_demographics, payload, priority)
+            .await
+    }
+
+    /// Dispatch priority results of a sample are queued with
+    pub async fn dispatch_priority(&self, sample_id: &str, critical: bool) -> UploadPriority {
+        self.upload_worker.dispatch_priority(sample_id, critical).await
+    }
+
+    /// Same as [`Self::submit`] with an already known dispatch priority, e.g. for
+    /// results released from another hold
+    pub async fn submit_with_priority(
+        &self,
+        policy: &DemographicsPolicy,
+        analyzer_id: &str,
+        patient_id: Option<&str>,
+        message_demographics: Demographics,
+        payload: &HisApiPayload,
+        priority: UploadPriority,
+    ) -> Result<(GateOutcome, Vec<DemographicsHold>), String> {
+        let stored = match patient_id {
+            Some(patient_id) => {
+                // Holds made before the sample's patient was known now belong to them
+                let assigned = self
+                    .repository
+                    .assign_sample_demographics_holds(&payload.sample_no, patient_id)
+                    .await?;
+                if assigned > 0 {
+                    log::info!(
+                        "Assigned {} demographics holds of sample {} to patient {}",
+                        assigned,
+                        payload.sample_no,
+                        patient_id
+                    );
+                }
+                self.repository.get_patient(patient_id).await?
+            }
+            None => None,
+        };
+        let demographics = message_demographics.or(stored.as_ref().map(Demographics::from_patient).unwrap_or_default());
+        let missing = policy.missing_fields(&demographics);
+
+        if !missing.is_empty() {
+            let serialized = serde_json::to_string(payload)
+                .map_err(|e| format!("Failed to serialize HIS payload: {}", e))?;
+            let hold = DemographicsHold {
+                id: Uuid::new_v4().to_string(),
+                patient_id: patient_id.map(str::to_string),
+                sample_id: payload.sample_no.clone(),
+                analyzer_id: analyzer_id.to_string(),
+                missing_fields: missing,
+                payload: serialized,
+                priority,
+                status: HoldStatus::PendingDemographics,
+                upload_id: None,
+                created_at: Utc::now(),
+                released_at: None,
+            };
+            self.repository.save_demographics_hold(&hold).await?;
+            log::warn!(
+                "Holding results of sample {} (patient {:?}): missing {:?}",
+                hold.sample_id,
+                hold.patient_id,
+                hold.missing_fields
+            );
+            return Ok((GateOutcome::Held(hold), Vec::new()));
+        }
+
+        let released = match patient_id {
+            Some(patient_id) => self.release_patient(patient_id).await?,
+            None => Vec::new(),
+        };
+        let upload_id = self.upload_worker.enqueue(&payload.sample_no, payload, priority).await?;
+        Ok((GateOutcome::Queued { upload_id }, released))
+    }
+
+    /// Releases a patient's held results if the stored record now satisfies the policy
+    pub async fn release_if_complete(
+        &self,
+        policy: &DemographicsPolicy,
+        patient_id: &str,
+    ) -> Result<Vec<DemographicsHold>, String> {
+        let demographics = self
+            .repository
+            .get_patient(patient_id)
+            .await?
+            .as_ref()
+            .map(Demographics::from_patient)
+            .unwrap_or_default();
+        if !policy.missing_fields(&demographics).is_empty() {
+            return Ok(Vec::new());
+        }
+        self.release_patient(patient_id).await
+    }
+
+    /// Queues one held result for upload regardless of the policy, for holds a
+    /// technologist releases by hand, e.g. samples whose patient never became known.
+    /// Returns None if the hold was already released.
+    pub async fn release_hold(&self, hold_id: &str) -> Result<Option<DemographicsHold>, String> {
+        let _release = self.release_lock.lock().await;
+        let Some(mut hold) = self.repository.get_demographics_hold(hold_id).await? else {
+            return Err(format!("Demographics hold {} not found", hold_id));
+        };
+        if hold.status != HoldStatus::PendingDemographics {
+            return Ok(None);
+        }
+
+        let payload: HisApiPayload = serde_json::from_str(&hold.payload)
+            .map_err(|e| format!("Failed to decode held payload {}: {}", hold.id, e))?;
+        let upload_id = self
+            .upload_worker
+            .enqueue(&payload.sample_no, &payload, hold.priority)
+            .await?;
+        if !self.repository.release_demographics_hold(&hold.id, upload_id.as_deref()).await? {
+            return Ok(None);
+        }
+        log::info!("Released held results of sample {} by hand", hold.sample_id);
+        hold.status = HoldStatus::Released;
+        hold.upload_id = upload_id;
+        hold.released_at = Some(Utc::now());
+        Ok(Some(hold))
+    }
+
+    /// Queues every held result of a patient for upload, oldest first
+    async fn release_patient(&self, patient_id: &str) -> Result<Vec<DemographicsHold>, String> {
+        let _release = self.release_lock.lock().await;
+        let mut released = Vec::new();
+
+        for mut hold in self.repository.get_patient_demographics_holds(patient_id).await? {
+            let payload: HisApiPayload = serde_json::from_str(&hold.payload)
+                .map_err(|e| format!("Failed to decode held payload {}: {}", hold.id, e))?;
+            let upload_id = self
+                .upload_worker
+                .enqueue(&payload.sample_no, &payload, hold.priority)
+                .await?;
+            if self.repository.release_demographics_hold(&hold.id, upload_id.as_deref()).await? {
+                log::info!("Released held results of sample {} for patient {}", hold.sample_id, patient_id);
+                hold.status = HoldStatus::Released;
+                hold.upload_id = upload_id;
+                hold.released_at = Some(Utc::now());
+                released.push(hold);
+            }
+        }
+        Ok(released)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+    use crate::models::patient::PatientName;
+    use crate::models::DataSource;
+    use crate::services::his_client::HisClient;
+    use crate::services::shadow_mode::ShadowMode;
+    use crate::services::upload_worker::UploadWorkerConfig;
+    use chrono::TimeZone;
+
+    fn gate(repository: &Arc<SqliteRepository>) -> DemographicsGate {
+        let upload_worker = Arc::new(UploadWorker::new(
+            repository.clone(),
+            Arc::new(HisClient::with_default_config()),
+            UploadWorkerConfig::default(),
+            ShadowMode::default(),
+        ));
+        DemographicsGate::new(repository.clone(), upload_worker)
+    }
+
+    fn policy() -> DemographicsPolicy {
+        DemographicsPolicy {
+            enabled: true,
+            ..DemographicsPolicy::default()
+        }
+    }
+
+    fn payload(sample_no: &str) -> HisApiPayload {
+        HisApiPayload {
+            machine: "AutoQuant".to_string(),
+            sent_on: "2025-03-01 09:00:00".to_string(),
+            sample_no: sample_no.to_string(),
+            sent: false,
+            values: Vec::new(),
+            correction: None,
+            contact: None,
+            patient_class: None,
+        }
+    }
+
+    async fn seed_patient(repository: &SqliteRepository, id: &str, birth_date: bool) {
+        let created_at = Utc.with_ymd_and_hms(2025, 3, 1, 9, 0, 0).unwrap();
+        let patient = Patient {
+            name: PatientName {
+                last_name: Some("Rao".to_string()),
+                first_name: Some("Anita".to_string()),
+                ..Default::default()
+            },
+            birth_date: birth_date.then(|| Utc.with_ymd_and_hms(1979, 2, 21, 0, 0, 0).unwrap()),
+            sex: Sex::Female,
+            created_at,
+            updated_at: created_at,
+            ..Patient::fixture(id)
+        };
+        repository.save_patient(&patient, &DataSource::Analyzer).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_incomplete_patient_results_are_held_not_uploaded() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let gate = gate(&repository);
+        seed_patient(&repository, "P1", false).await;
+
+        let (outcome, released) = gate
+            .submit(
+                &policy(),
+                "meril-1",
+                Some("P1"),
+                Demographics::from_message("", None, None),
+                &payload("S1"),
+                false,
+            )
+            .await
+            .unwrap();
+
+        let GateOutcome::Held(hold) = outcome else {
+            panic!("Expected the results to be held");
+        };
+        assert_eq!(hold.missing_fields, vec![DemographicField::BirthDate]);
+        assert!(released.is_empty());
+        assert_eq!(repository.count_queued_uploads().await.unwrap(), 0);
+        let pending = repository.get_results_pending_demographics().await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].sample_id, "S1");
+
+        // With the policy off nothing is held
+        let (outcome, _) = gate
+            .submit(
+                &DemographicsPolicy::default(),
+                "meril-1",
+                Some("P9"),
+                Demographics::default(),
+                &payload("S9"),
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(outcome, GateOutcome::Queued { upload_id: Some(_) }));
+    }
+
+    #[tokio::test]
+    async fn test_manual_completion_releases_held_results() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let gate = gate(&repository);
+        seed_patient(&repository, "P1", false).await;
+
+        for sample in ["S1", "S2"] {
+            gate.submit(&policy(), "meril-1", Some("P1"), Demographics::default(), &payload(sample), false)
+                .await
+                .unwrap();
+        }
+        assert!(gate.release_if_complete(&policy(), "P1").await.unwrap().is_empty());
+
+        let name = PatientName {
+            last_name: None,
+            first_name: None,
+            middle_name: None,
+            title: None,
+        };
+        let birth_date = Utc.with_ymd_and_hms(1979, 2, 21, 0, 0, 0).unwrap();
+        assert!(repository
+            .update_patient_demographics("P1", &name, Some(birth_date), None)
+            .await
+            .unwrap());
+
+        let released = gate.release_if_complete(&policy(), "P1").await.unwrap();
+        let samples: Vec<&str> = released.iter().map(|hold| hold.sample_id.as_str()).collect();
+        assert_eq!(samples, vec!["S1", "S2"]);
+        assert!(released.iter().all(|hold| hold.status == HoldStatus::Released && hold.upload_id.is_some()));
+        assert_eq!(repository.count_queued_uploads().await.unwrap(), 2);
+        assert!(repository.get_results_pending_demographics().await.unwrap().is_empty());
+
+        // Released holds are not released again
+        assert!(gate.release_if_complete(&policy(), "P1").await.unwrap().is_empty());
+        assert_eq!(repository.count_queued_uploads().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_later_message_with_demographics_releases_held_results() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let gate = gate(&repository);
+
+        // Unknown patient, message without birth date
+        let (outcome, _) = gate
+            .submit(
+                &policy(),
+                "meril-1",
+                Some("P2"),
+                Demographics::from_message("Rao^Anita", None, Some("F")),
+                &payload("S1"),
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(outcome, GateOutcome::Held(_)));
+
+        let (outcome, released) = gate
+            .submit(
+                &policy(),
+                "meril-1",
+                Some("P2"),
+                Demographics::from_message("Rao^Anita", Some("19790221"), Some("F")),
+                &payload("S2"),
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(outcome, GateOutcome::Queued { upload_id: Some(_) }));
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].sample_id, "S1");
+
+        let queued: Vec<String> = repository
+            .get_pending_uploads(10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|upload| upload.result_id)
+            .collect();
+        assert_eq!(queued.len(), 2);
+        assert!(queued.contains(&"S1".to_string()) && queued.contains(&"S2".to_string()));
+        assert!(repository.get_results_pending_demographics().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_holds_without_a_patient_are_matched_by_sample_or_released_by_hand() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let gate = gate(&repository);
+
+        for sample in ["S1", "S2"] {
+            let (outcome, _) = gate
+                .submit(&policy(), "meril-1", None, Demographics::default(), &payload(sample), false)
+                .await
+                .unwrap();
+            assert!(matches!(outcome, GateOutcome::Held(_)));
+        }
+
+        // A later message naming the sample's patient adopts and releases its hold
+        let (outcome, released) = gate
+            .submit(
+                &policy(),
+                "meril-1",
+                Some("P3"),
+                Demographics::from_message("Rao^Anita", Some("19790221"), Some("F")),
+                &payload("S1"),
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(outcome, GateOutcome::Queued { upload_id: Some(_) }));
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].sample_id, "S1");
+        assert_eq!(released[0].patient_id.as_deref(), Some("P3"));
+
+        // The other sample's patient never becomes known; it is released by hand
+        let pending = repository.get_results_pending_demographics().await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].sample_id, "S2");
+        let queued = repository.count_queued_uploads().await.unwrap();
+        let hold = gate.release_hold(&pending[0].id).await.unwrap().unwrap();
+        assert_eq!(hold.status, HoldStatus::Released);
+        assert!(hold.upload_id.is_some());
+        assert!(repository.get_results_pending_demographics().await.unwrap().is_empty());
+        assert_eq!(repository.count_queued_uploads().await.unwrap(), queued + 1);
+
+        // Released holds are not released again, unknown holds are an error
+        assert!(gate.release_hold(&hold.id).await.unwrap().is_none());
+        assert_eq!(repository.count_queued_uploads().await.unwrap(), queued + 1);
+        assert!(gate.release_hold("missing").await.is_err());
+    }
+}
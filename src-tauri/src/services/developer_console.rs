@@ -0,0 +1,284 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::db::SqliteRepository;
+use crate::models::AnalyzerEventType;
+use crate::services::setup_wizard::{OperatorAccount, OperatorRole};
+
+/// Key in the app settings store (`settings.json`) holding the developer console settings
+pub const DEVELOPER_CONSOLE_STORE_KEY: &str = "developer_console";
+
+/// How long replies to a raw message are collected when the caller does not say
+pub const DEFAULT_RAW_RESPONSE_WINDOW: Duration = Duration::from_secs(5);
+
+/// Upper bound on the reply window a caller can ask for
+pub const MAX_RAW_RESPONSE_WINDOW: Duration = Duration::from_secs(60);
+
+// ============================================================================
+// SETTINGS
+// ============================================================================
+
+/// Gates the developer console. Debug builds always have it; release builds need
+/// `enabled`. Raw sends also need an operator account with the admin role.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DeveloperConsoleSettings {
+    pub enabled: bool,
+}
+
+/// Reads stored settings, falling back to the default when missing or invalid
+pub fn developer_console_from_store(stored: Option<serde_json::Value>) -> DeveloperConsoleSettings {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid developer console settings: {}", e);
+            DeveloperConsoleSettings::default()
+        }),
+        None => DeveloperConsoleSettings::default(),
+    }
+}
+
+impl DeveloperConsoleSettings {
+    /// Checks that a raw send may go out: console available, sent by an admin
+    /// operator, and an explicit acknowledgement that the message bypasses all validation
+    pub fn authorize(
+        &self,
+        debug_build: bool,
+        operator: Option<&OperatorAccount>,
+        understood: bool,
+    ) -> Result<(), String> {
+        if !debug_build && !self.enabled {
+            return Err("The developer console is disabled".to_string());
+        }
+        match operator {
+            None => return Err("Raw sends need a known operator account".to_string()),
+            Some(operator) if operator.role != OperatorRole::Admin => {
+                return Err(format!("Operator {} is not an admin", operator.username));
+            }
+            Some(_) => {}
+        }
+        if !understood {
+            return Err("Confirm that the message is sent to the analyzer unvalidated".to_string());
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// RAW MESSAGES
+// ============================================================================
+
+/// Payload of a raw send, framed by the LIS according to its protocol
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "protocol")]
+pub enum RawPayload {
+    /// Full HL7 message; segments may be separated by CR, LF or CRLF. Sent in MLLP framing.
+    Hl7 { message: String },
+    /// ASTM records (H, P, O, ..., L), one per frame, sent as the line's sender
+    Astm { records: Vec<String> },
+}
+
+impl RawPayload {
+    pub fn protocol(&self) -> &'static str {
+        match self {
+            RawPayload::Hl7 { .. } => "HL7",
+            RawPayload::Astm { .. } => "ASTM",
+        }
+    }
+
+    /// HL7 message with CR segment separators, or the ASTM records without line
+    /// endings; empty segments/records are dropped
+    pub fn normalized(&self) -> Result<RawPayload, String> {
+        let lines = |text: &str| -> Vec<String> {
+            text.split(['\r', '\n'])
+                .filter(|line| !line.trim().is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+
+        match self {
+            RawPayload::Hl7 { message } => {
+                let segments = lines(message);
+                if !segments.first().is_some_and(|segment| segment.starts_with("MSH")) {
+                    return Err("An HL7 message must start with an MSH segment".to_string());
+                }
+                Ok(RawPayload::Hl7 { message: segments.join("\r") + "\r" })
+            }
+            RawPayload::Astm { records } => {
+                let records: Vec<String> = records.iter().flat_map(|record| lines(record)).collect();
+                if records.is_empty() {
+                    return Err("No ASTM records to send".to_string());
+                }
+                Ok(RawPayload::Astm { records })
+            }
+        }
+    }
+
+    /// Payload as text for the audit trail
+    fn describe(&self) -> String {
+        match self {
+            RawPayload::Hl7 { message } => message.replace('\r', "<CR>"),
+            RawPayload::Astm { records } => records.join("<CR>"),
+        }
+    }
+}
+
+/// Outcome of a raw send
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RawExchange {
+    pub analyzer_id: String,
+    pub protocol: String,
+    /// Bytes written, framing included
+    pub bytes_sent: usize,
+    /// Data received within the reply window, control characters shown as `<STX>` etc.
+    pub responses: Vec<String>,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// Renders received bytes with their control characters spelled out
+pub fn describe_bytes(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for &byte in bytes {
+        match byte {
+            0x02 => text.push_str("<STX>"),
+            0x03 => text.push_str("<ETX>"),
+            0x04 => text.push_str("<EOT>"),
+            0x05 => text.push_str("<ENQ>"),
+            0x06 => text.push_str("<ACK>"),
+            0x0A => text.push_str("<LF>"),
+            0x0B => text.push_str("<VT>"),
+            0x0D => text.push_str("<CR>"),
+            0x15 => text.push_str("<NAK>"),
+            0x17 => text.push_str("<ETB>"),
+            0x1C => text.push_str("<FS>"),
+            0x20..=0x7E => text.push(byte as char),
+            _ => text.push_str(&format!("<0x{:02X}>", byte)),
+        }
+    }
+    text
+}
+
+/// Writes the audit row of a raw send, whether it went out or not
+pub async fn audit_raw_send(
+    repository: &SqliteRepository,
+    analyzer_id: &str,
+    operator: &str,
+    payload: &RawPayload,
+    outcome: &Result<RawExchange, String>,
+) -> Result<(), String> {
+    let message = match outcome {
+        Ok(exchange) => format!(
+            "Raw {} message sent by {} ({} bytes, {} replies): {}",
+            payload.protocol(),
+            operator,
+            exchange.bytes_sent,
+            exchange.responses.len(),
+            payload.describe()
+        ),
+        Err(e) => format!(
+            "Raw {} message by {} failed ({}): {}",
+            payload.protocol(),
+            operator,
+            e,
+            payload.describe()
+        ),
+    };
+    log::warn!("Developer console, analyzer {}: {}", analyzer_id, message);
+    repository
+        .record_analyzer_event(analyzer_id, &AnalyzerEventType::RawMessageSent, &message)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+
+    #[test]
+    fn test_raw_send_needs_console_admin_and_confirmation() {
+        let operator = |username: &str, role: OperatorRole| OperatorAccount {
+            username: username.to_string(),
+            display_name: username.to_string(),
+            role,
+        };
+        let admin = operator("admin", OperatorRole::Admin);
+        let tech = operator("tech", OperatorRole::Technologist);
+
+        let enabled = DeveloperConsoleSettings { enabled: true };
+        assert!(enabled.authorize(false, Some(&admin), true).is_ok());
+        assert!(enabled.authorize(false, Some(&admin), false).is_err());
+
+        // A non-admin operator is refused even with the console on and the warning confirmed
+        assert_eq!(
+            enabled.authorize(false, Some(&tech), true).unwrap_err(),
+            "Operator tech is not an admin"
+        );
+        assert!(enabled.authorize(true, Some(&tech), true).is_err());
+        assert!(enabled.authorize(false, None, true).is_err());
+
+        let disabled = DeveloperConsoleSettings { enabled: false };
+        assert!(disabled.authorize(false, Some(&admin), true).is_err());
+        assert!(disabled.authorize(true, Some(&admin), true).is_ok());
+
+        // A role left in the console settings by earlier versions grants nothing
+        let stored = developer_console_from_store(Some(serde_json::json!({ "enabled": false, "operator_role": "Admin" })));
+        assert!(stored.authorize(false, Some(&admin), true).is_err());
+    }
+
+    #[test]
+    fn test_payload_normalization() {
+        let hl7 = RawPayload::Hl7 { message: "MSH|^~\\&|LIS\r\nORC|XO|W1\n".to_string() };
+        assert_eq!(
+            hl7.normalized().unwrap(),
+            RawPayload::Hl7 { message: "MSH|^~\\&|LIS\rORC|XO|W1\r".to_string() }
+        );
+        assert!(RawPayload::Hl7 { message: "ORC|XO".to_string() }.normalized().is_err());
+
+        let astm = RawPayload::Astm { records: vec!["H|\\^&\r".to_string(), "".to_string(), "L|1|N".to_string()] };
+        assert_eq!(
+            astm.normalized().unwrap(),
+            RawPayload::Astm { records: vec!["H|\\^&".to_string(), "L|1|N".to_string()] }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_raw_sends_are_audited_for_both_protocols() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let hl7 = RawPayload::Hl7 { message: "MSH|^~\\&|LIS\rORC|XO|W1\r".to_string() };
+        let astm = RawPayload::Astm { records: vec!["H|\\^&".to_string(), "L|1|N".to_string()] };
+        let exchange = |protocol: &str| RawExchange {
+            analyzer_id: "A1".to_string(),
+            protocol: protocol.to_string(),
+            bytes_sent: 42,
+            responses: vec!["<ACK>".to_string()],
+            sent_at: Utc::now(),
+        };
+
+        audit_raw_send(&repository, "A1", "admin", &hl7, &Ok(exchange("HL7"))).await.unwrap();
+        audit_raw_send(&repository, "A1", "admin", &astm, &Err("Analyzer A1 is not connected".to_string()))
+            .await
+            .unwrap();
+
+        let summary = repository
+            .analyzer_event_summary("A1", Utc::now() - chrono::Duration::minutes(1), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(summary.by_type.len(), 1);
+        assert_eq!(summary.by_type[0].event_type, AnalyzerEventType::RawMessageSent);
+        assert_eq!(summary.by_type[0].count, 2);
+
+        let messages: Vec<String> = sqlx::query_scalar("SELECT message FROM analyzer_events ORDER BY created_at, rowid")
+            .fetch_all(repository.pool())
+            .await
+            .unwrap();
+        assert_eq!(
+            messages[0],
+            "Raw HL7 message sent by admin (42 bytes, 1 replies): MSH|^~\\&|LIS<CR>ORC|XO|W1<CR>"
+        );
+        assert_eq!(
+            messages[1],
+            "Raw ASTM message by admin failed (Analyzer A1 is not connected): H|\\^&<CR>L|1|N"
+        );
+    }
+}
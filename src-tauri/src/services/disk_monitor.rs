@@ -0,0 +1,405 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot, RwLock};
+
+// ============================================================================
+// SETTINGS
+// ============================================================================
+
+/// Disk monitor thresholds, stored in the app settings store under `disk_monitor`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskMonitorSettings {
+    pub check_interval_secs: u64,
+    pub warning_free_mb: u64,
+    pub critical_free_mb: u64,
+}
+
+impl Default for DiskMonitorSettings {
+    fn default() -> Self {
+        Self {
+            check_interval_secs: 60,
+            warning_free_mb: 2048,
+            critical_free_mb: 512,
+        }
+    }
+}
+
+// ============================================================================
+// STATE AND EVENTS
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum DiskSpaceState {
+    Normal,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DiskMonitorEvent {
+    /// Free space dropped below the warning threshold
+    LowDiskSpace {
+        free_bytes: u64,
+        timestamp: DateTime<Utc>,
+    },
+    /// Free space dropped below the critical threshold; ingestion is now protective
+    ProtectiveModeEntered {
+        free_bytes: u64,
+        timestamp: DateTime<Utc>,
+    },
+    /// Free space recovered above the warning threshold
+    DiskSpaceRecovered {
+        free_bytes: u64,
+        timestamp: DateTime<Utc>,
+    },
+}
+
+/// Snapshot of the monitor for status/readiness reporting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskStatus {
+    pub path: String,
+    pub state: DiskSpaceState,
+    pub free_bytes: Option<u64>,
+    pub protective_mode: bool,
+    pub settings: DiskMonitorSettings,
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+// ============================================================================
+// FREE SPACE PROVIDER
+// ============================================================================
+
+/// Reports free space for the volume holding a path
+pub trait FreeSpaceProvider: Send + Sync {
+    fn free_bytes(&self, path: &Path) -> Result<u64, String>;
+}
+
+/// Asks the operating system for the space available to this user on the volume
+/// holding the path (`statvfs` / `GetDiskFreeSpaceExW`), without spawning a process.
+/// Works for relative and UNC paths alike.
+pub struct SystemFreeSpaceProvider;
+
+impl FreeSpaceProvider for SystemFreeSpaceProvider {
+    fn free_bytes(&self, path: &Path) -> Result<u64, String> {
+        fs2::available_space(path).map_err(|e| format!("Failed to read free space of {}: {}", path.display(), e))
+    }
+}
+
+// ============================================================================
+// TWO-PHASE ACK
+// ============================================================================
+
+/// How long a connection in protective mode waits for its results to be stored
+/// before it gives up and refuses the message
+pub const STORE_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Shared protective-mode flag. Connections read it to decide whether a message is
+/// acknowledged only after its results are stored. Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct ProtectiveMode {
+    enabled: Arc<AtomicBool>,
+}
+
+impl ProtectiveMode {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(enabled)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+}
+
+/// Travels with a result event from the connection to ingestion, which reports
+/// through it whether the results were stored. Empty outside protective mode.
+/// Clones share one report; a receipt dropped unreported counts as not stored.
+#[derive(Clone, Default)]
+pub struct StoreReceipt {
+    sender: Option<Arc<std::sync::Mutex<Option<oneshot::Sender<Result<(), String>>>>>>,
+}
+
+impl std::fmt::Debug for StoreReceipt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StoreReceipt").field("requested", &self.sender.is_some()).finish()
+    }
+}
+
+impl StoreReceipt {
+    /// A receipt for a connection to wait on with `wait_until_stored`
+    pub fn requested() -> (Self, oneshot::Receiver<Result<(), String>>) {
+        let (sender, receiver) = oneshot::channel();
+        let receipt = Self {
+            sender: Some(Arc::new(std::sync::Mutex::new(Some(sender)))),
+        };
+        (receipt, receiver)
+    }
+
+    /// Reports the outcome of storing the results; only the first report counts
+    pub fn report(&self, stored: Result<(), String>) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        if let Some(sender) = sender.lock().unwrap().take() {
+            let _ = sender.send(stored);
+        }
+    }
+}
+
+/// Waits for ingestion to report a receipt, up to `timeout`
+pub async fn wait_until_stored(
+    receiver: oneshot::Receiver<Result<(), String>>,
+    timeout: Duration,
+) -> Result<(), String> {
+    match tokio::time::timeout(timeout, receiver).await {
+        Ok(Ok(stored)) => stored,
+        Ok(Err(_)) => Err("The results were dropped before they were stored".to_string()),
+        Err(_) => Err(format!("The results were not stored within {} s", timeout.as_secs())),
+    }
+}
+
+// ============================================================================
+// DISK MONITOR
+// ============================================================================
+
+/// Watches free space on the app-data volume.
+///
+/// Below the warning threshold a `LowDiskSpace` event is raised. Below the critical
+/// threshold the monitor switches on protective mode: debug logging stops, connection
+/// archiving, graph images and exports are refused so writes are kept to the results
+/// themselves (see `check_non_essential_write`), and analyzers get their final ACK only
+/// once the results are stored (see `StoreReceipt`). Both clear automatically once space
+/// frees up.
+pub struct DiskMonitor {
+    path: PathBuf,
+    settings: DiskMonitorSettings,
+    provider: Arc<dyn FreeSpaceProvider>,
+    event_sender: mpsc::Sender<DiskMonitorEvent>,
+    state: RwLock<DiskSpaceState>,
+    last_free_bytes: RwLock<Option<u64>>,
+    checked_at: RwLock<Option<DateTime<Utc>>>,
+    protective_mode: ProtectiveMode,
+}
+
+impl DiskMonitor {
+    pub fn new(
+        path: PathBuf,
+        settings: DiskMonitorSettings,
+        provider: Arc<dyn FreeSpaceProvider>,
+        event_sender: mpsc::Sender<DiskMonitorEvent>,
+    ) -> Self {
+        Self {
+            path,
+            settings,
+            provider,
+            event_sender,
+            state: RwLock::new(DiskSpaceState::Normal),
+            last_free_bytes: RwLock::new(None),
+            checked_at: RwLock::new(None),
+            protective_mode: ProtectiveMode::default(),
+        }
+    }
+
+    /// Whether ingestion should run in protective mode
+    pub fn is_protective_mode(&self) -> bool {
+        self.protective_mode.is_enabled()
+    }
+
+    /// The protective-mode flag, for the analyzer connections
+    pub fn protective_mode(&self) -> ProtectiveMode {
+        self.protective_mode.clone()
+    }
+
+    /// Refuses a write the LIS can do without, e.g. an export file or a graph
+    /// image, while in protective mode. Results themselves are always stored.
+    pub fn check_non_essential_write(&self, what: &str) -> Result<(), String> {
+        if self.is_protective_mode() {
+            return Err(format!("Free disk space is critically low; {} is paused", what));
+        }
+        Ok(())
+    }
+
+    /// Gets the current disk status
+    pub async fn get_status(&self) -> DiskStatus {
+        DiskStatus {
+            path: self.path.display().to_string(),
+            state: *self.state.read().await,
+            free_bytes: *self.last_free_bytes.read().await,
+            protective_mode: self.is_protective_mode(),
+            settings: self.settings.clone(),
+            checked_at: *self.checked_at.read().await,
+        }
+    }
+
+    /// Classifies a free-space reading against the configured thresholds
+    fn classify(&self, free_bytes: u64) -> DiskSpaceState {
+        let free_mb = free_bytes / (1024 * 1024);
+        if free_mb < self.settings.critical_free_mb {
+            DiskSpaceState::Critical
+        } else if free_mb < self.settings.warning_free_mb {
+            DiskSpaceState::Warning
+        } else {
+            DiskSpaceState::Normal
+        }
+    }
+
+    /// Runs a single check, emitting an event when the state changes.
+    /// Returns the resulting state.
+    pub async fn check_once(&self) -> Result<DiskSpaceState, String> {
+        let free_bytes = self.provider.free_bytes(&self.path)?;
+        let new_state = self.classify(free_bytes);
+        let timestamp = Utc::now();
+
+        *self.last_free_bytes.write().await = Some(free_bytes);
+        *self.checked_at.write().await = Some(timestamp);
+
+        let previous_state = {
+            let mut state = self.state.write().await;
+            std::mem::replace(&mut *state, new_state)
+        };
+        if previous_state == new_state {
+            return Ok(new_state);
+        }
+
+        self.protective_mode.set_enabled(new_state == DiskSpaceState::Critical);
+
+        let event = match new_state {
+            DiskSpaceState::Critical => {
+                log::error!(
+                    "Free disk space critical ({} MB) on {}; entering protective mode",
+                    free_bytes / (1024 * 1024),
+                    self.path.display()
+                );
+                DiskMonitorEvent::ProtectiveModeEntered { free_bytes, timestamp }
+            }
+            DiskSpaceState::Warning => {
+                log::warn!(
+                    "Free disk space low ({} MB) on {}",
+                    free_bytes / (1024 * 1024),
+                    self.path.display()
+                );
+                DiskMonitorEvent::LowDiskSpace { free_bytes, timestamp }
+            }
+            DiskSpaceState::Normal => {
+                log::info!(
+                    "Free disk space recovered ({} MB) on {}",
+                    free_bytes / (1024 * 1024),
+                    self.path.display()
+                );
+                DiskMonitorEvent::DiskSpaceRecovered { free_bytes, timestamp }
+            }
+        };
+        let _ = self.event_sender.send(event).await;
+
+        Ok(new_state)
+    }
+
+    /// Runs the monitor until the task is dropped
+    pub async fn run(self: Arc<Self>) {
+        let interval = Duration::from_secs(self.settings.check_interval_secs.max(1));
+        loop {
+            if let Err(e) = self.check_once().await {
+                log::warn!("Disk space check failed: {}", e);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU64;
+
+    const MB: u64 = 1024 * 1024;
+
+    struct MockFreeSpaceProvider {
+        free_bytes: AtomicU64,
+    }
+
+    impl FreeSpaceProvider for MockFreeSpaceProvider {
+        fn free_bytes(&self, _path: &Path) -> Result<u64, String> {
+            Ok(self.free_bytes.load(Ordering::SeqCst))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disk_monitor_state_transitions() {
+        let provider = Arc::new(MockFreeSpaceProvider {
+            free_bytes: AtomicU64::new(10_000 * MB),
+        });
+        let (sender, mut receiver) = mpsc::channel(10);
+        let monitor = DiskMonitor::new(
+            PathBuf::from("/data"),
+            DiskMonitorSettings::default(),
+            provider.clone(),
+            sender,
+        );
+
+        // Plenty of space: no event
+        assert_eq!(monitor.check_once().await.unwrap(), DiskSpaceState::Normal);
+        assert!(receiver.try_recv().is_err());
+
+        // Below warning
+        provider.free_bytes.store(1000 * MB, Ordering::SeqCst);
+        assert_eq!(monitor.check_once().await.unwrap(), DiskSpaceState::Warning);
+        assert!(matches!(receiver.try_recv(), Ok(DiskMonitorEvent::LowDiskSpace { .. })));
+        assert!(!monitor.is_protective_mode());
+
+        // Below critical
+        provider.free_bytes.store(100 * MB, Ordering::SeqCst);
+        assert_eq!(monitor.check_once().await.unwrap(), DiskSpaceState::Critical);
+        assert!(matches!(receiver.try_recv(), Ok(DiskMonitorEvent::ProtectiveModeEntered { .. })));
+        assert!(monitor.is_protective_mode());
+        assert!(monitor.check_non_essential_write("exporting results").is_err());
+
+        // Still critical: no repeated event
+        assert_eq!(monitor.check_once().await.unwrap(), DiskSpaceState::Critical);
+        assert!(receiver.try_recv().is_err());
+
+        // Space freed
+        provider.free_bytes.store(5000 * MB, Ordering::SeqCst);
+        assert_eq!(monitor.check_once().await.unwrap(), DiskSpaceState::Normal);
+        assert!(matches!(receiver.try_recv(), Ok(DiskMonitorEvent::DiskSpaceRecovered { .. })));
+        assert!(!monitor.is_protective_mode());
+        assert!(monitor.check_non_essential_write("exporting results").is_ok());
+
+        let status = monitor.get_status().await;
+        assert_eq!(status.state, DiskSpaceState::Normal);
+        assert_eq!(status.free_bytes, Some(5000 * MB));
+    }
+
+    #[tokio::test]
+    async fn test_critical_to_warning_leaves_protective_mode() {
+        let provider = Arc::new(MockFreeSpaceProvider {
+            free_bytes: AtomicU64::new(100 * MB),
+        });
+        let (sender, mut receiver) = mpsc::channel(10);
+        let monitor = DiskMonitor::new(
+            PathBuf::from("/data"),
+            DiskMonitorSettings::default(),
+            provider.clone(),
+            sender,
+        );
+
+        assert_eq!(monitor.check_once().await.unwrap(), DiskSpaceState::Critical);
+        assert!(monitor.is_protective_mode());
+
+        provider.free_bytes.store(1000 * MB, Ordering::SeqCst);
+        assert_eq!(monitor.check_once().await.unwrap(), DiskSpaceState::Warning);
+        assert!(!monitor.is_protective_mode());
+
+        assert!(matches!(receiver.try_recv(), Ok(DiskMonitorEvent::ProtectiveModeEntered { .. })));
+        assert!(matches!(receiver.try_recv(), Ok(DiskMonitorEvent::LowDiskSpace { .. })));
+    }
+}
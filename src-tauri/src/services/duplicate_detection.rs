@@ -0,0 +1,450 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::SqliteRepository;
+use crate::models::patient::{PatientName, Sex};
+use crate::models::{CandidateStatus, DuplicateCandidate, Patient};
+
+/// Key in the app settings store (`settings.json`) holding the duplicate detection heuristics
+pub const DUPLICATE_DETECTION_STORE_KEY: &str = "duplicate_detection";
+
+/// Score contributions; a pair matching on every enabled heuristic scores 1.0
+const NAME_WEIGHT: f64 = 0.6;
+const BIRTH_DATE_WEIGHT: f64 = 0.3;
+const SEX_WEIGHT: f64 = 0.1;
+
+// ============================================================================
+// SETTINGS
+// ============================================================================
+
+/// Heuristics used to suggest probable duplicate patients
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DuplicateDetectionSettings {
+    /// Runs the scan in the background every `scan_interval_hours`
+    pub scheduled_scan: bool,
+    pub scan_interval_hours: u64,
+    /// Different birth dates rule a pair out; equal ones raise the score
+    pub match_birth_date: bool,
+    /// Different known sexes rule a pair out; equal ones raise the score
+    pub match_sex: bool,
+    /// Largest edit distance between normalized names still considered a match (0 = equal names only)
+    pub max_name_distance: usize,
+    /// Pairs scoring below this are not suggested
+    pub min_score: f64,
+}
+
+impl Default for DuplicateDetectionSettings {
+    fn default() -> Self {
+        Self {
+            scheduled_scan: true,
+            scan_interval_hours: 24,
+            match_birth_date: true,
+            match_sex: true,
+            max_name_distance: 2,
+            min_score: 0.7,
+        }
+    }
+}
+
+/// Reads stored heuristics, falling back to the defaults when missing or invalid
+pub fn duplicate_detection_from_store(stored: Option<serde_json::Value>) -> DuplicateDetectionSettings {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid duplicate detection settings: {}", e);
+            DuplicateDetectionSettings::default()
+        }),
+        None => DuplicateDetectionSettings::default(),
+    }
+}
+
+// ============================================================================
+// SCORING
+// ============================================================================
+
+/// Lower-cases the name parts, drops punctuation and sorts the words,
+/// so "SHARMA, Anita" and "anita sharma" compare equal. Titles are ignored.
+pub fn normalize_name(name: &PatientName) -> String {
+    let mut words: Vec<String> = [&name.first_name, &name.middle_name, &name.last_name]
+        .into_iter()
+        .flatten()
+        .flat_map(|part| {
+            part.chars()
+                .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { ' ' })
+                .collect::<String>()
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    words.sort();
+    words.join(" ")
+}
+
+/// Edit distance between two strings, counted in characters
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Scores two patients as probable duplicates. Returns the score and the
+/// heuristics that matched, or None when the pair should not be suggested.
+pub fn score_pair(a: &Patient, b: &Patient, settings: &DuplicateDetectionSettings) -> Option<(f64, Vec<String>)> {
+    score_normalized(a, &normalize_name(&a.name), b, &normalize_name(&b.name), settings)
+}
+
+fn score_normalized(
+    a: &Patient,
+    name_a: &str,
+    b: &Patient,
+    name_b: &str,
+    settings: &DuplicateDetectionSettings,
+) -> Option<(f64, Vec<String>)> {
+    if name_a.is_empty() || name_b.is_empty() {
+        return None;
+    }
+    let (len_a, len_b) = (name_a.chars().count(), name_b.chars().count());
+    if len_a.abs_diff(len_b) > settings.max_name_distance {
+        return None;
+    }
+    let distance = levenshtein(name_a, name_b);
+    if distance > settings.max_name_distance {
+        return None;
+    }
+
+    let mut reasons = vec![if distance == 0 { "exact_name" } else { "similar_name" }.to_string()];
+    let mut score = NAME_WEIGHT * (1.0 - distance as f64 / len_a.max(len_b) as f64);
+    let mut max_score = NAME_WEIGHT;
+
+    if settings.match_birth_date {
+        max_score += BIRTH_DATE_WEIGHT;
+        match (a.birth_date, b.birth_date) {
+            (Some(x), Some(y)) if x.date_naive() == y.date_naive() => {
+                score += BIRTH_DATE_WEIGHT;
+                reasons.push("birth_date".to_string());
+            }
+            (Some(_), Some(_)) => return None,
+            _ => {}
+        }
+    }
+
+    if settings.match_sex {
+        max_score += SEX_WEIGHT;
+        match (&a.sex, &b.sex) {
+            (Sex::Other, _) | (_, Sex::Other) => {}
+            (x, y) if x == y => {
+                score += SEX_WEIGHT;
+                reasons.push("sex".to_string());
+            }
+            _ => return None,
+        }
+    }
+
+    let score = score / max_score;
+    (score >= settings.min_score).then_some((score, reasons))
+}
+
+// ============================================================================
+// SCAN AND REVIEW
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DuplicateScanSummary {
+    pub patients_scanned: usize,
+    /// Pairs scoring above the threshold, including ones already suggested or reviewed
+    pub pairs_matched: usize,
+    /// Pairs suggested for the first time
+    pub new_candidates: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeOutcome {
+    pub primary_patient_id: String,
+    pub merged_patient_id: String,
+    pub results_moved: u64,
+}
+
+/// Compares every pair of patients and records the probable duplicates for review.
+/// Pairs already dismissed or accepted stay as they are.
+pub async fn scan_for_duplicates(
+    repository: &SqliteRepository,
+    settings: &DuplicateDetectionSettings,
+) -> Result<DuplicateScanSummary, String> {
+    let patients = repository.list_patients().await?;
+    let names: Vec<String> = patients.iter().map(|p| normalize_name(&p.name)).collect();
+    let mut summary = DuplicateScanSummary {
+        patients_scanned: patients.len(),
+        ..Default::default()
+    };
+
+    for (i, (first, first_name)) in patients.iter().zip(&names).enumerate() {
+        for (second, second_name) in patients.iter().zip(&names).skip(i + 1) {
+            let Some((score, reasons)) = score_normalized(first, first_name, second, second_name, settings) else {
+                continue;
+            };
+            summary.pairs_matched += 1;
+
+            let (a, b) = if first.id <= second.id { (first, second) } else { (second, first) };
+            let now = Utc::now();
+            let candidate = DuplicateCandidate {
+                id: Uuid::new_v4().to_string(),
+                patient_id_a: a.id.clone(),
+                patient_id_b: b.id.clone(),
+                score,
+                reasons,
+                status: CandidateStatus::Pending,
+                created_at: now,
+                updated_at: now,
+            };
+            if repository.upsert_duplicate_candidate(&candidate).await? {
+                summary.new_candidates += 1;
+            }
+        }
+    }
+
+    log::info!(
+        "Duplicate patient scan: {} patients, {} matching pairs, {} new candidates",
+        summary.patients_scanned,
+        summary.pairs_matched,
+        summary.new_candidates
+    );
+    Ok(summary)
+}
+
+/// Merges a pending candidate pair. The patient to keep defaults to the one created first.
+pub async fn accept_duplicate_candidate(
+    repository: &SqliteRepository,
+    candidate_id: &str,
+    keep_patient_id: Option<&str>,
+) -> Result<MergeOutcome, String> {
+    let candidate = pending_candidate(repository, candidate_id).await?;
+
+    let primary_id = match keep_patient_id {
+        Some(id) if id == candidate.patient_id_a || id == candidate.patient_id_b => id.to_string(),
+        Some(id) => {
+            return Err(format!("Patient {} is not part of duplicate candidate {}", id, candidate_id));
+        }
+        None => {
+            let a = repository.get_patient(&candidate.patient_id_a).await?;
+            let b = repository.get_patient(&candidate.patient_id_b).await?;
+            match (a, b) {
+                (Some(a), Some(b)) if b.created_at < a.created_at => b.id,
+                _ => candidate.patient_id_a.clone(),
+            }
+        }
+    };
+    let merged_id = if primary_id == candidate.patient_id_a {
+        candidate.patient_id_b.clone()
+    } else {
+        candidate.patient_id_a.clone()
+    };
+
+    let results_moved = repository.merge_patients(&primary_id, &merged_id).await?;
+    repository
+        .update_duplicate_candidate_status(candidate_id, CandidateStatus::Accepted)
+        .await?;
+    repository.delete_pending_duplicate_candidates_for(&merged_id).await?;
+
+    log::info!(
+        "Merged patient {} into {} ({} results moved)",
+        merged_id,
+        primary_id,
+        results_moved
+    );
+    Ok(MergeOutcome {
+        primary_patient_id: primary_id,
+        merged_patient_id: merged_id,
+        results_moved,
+    })
+}
+
+/// Marks a pending candidate as different patients so later scans do not suggest it again
+pub async fn dismiss_duplicate_candidate(repository: &SqliteRepository, candidate_id: &str) -> Result<(), String> {
+    pending_candidate(repository, candidate_id).await?;
+    repository
+        .update_duplicate_candidate_status(candidate_id, CandidateStatus::Dismissed)
+        .await?;
+    log::info!("Dismissed duplicate candidate {}", candidate_id);
+    Ok(())
+}
+
+async fn pending_candidate(repository: &SqliteRepository, candidate_id: &str) -> Result<DuplicateCandidate, String> {
+    let candidate = repository
+        .get_duplicate_candidate(candidate_id)
+        .await?
+        .ok_or_else(|| format!("Duplicate candidate {} not found", candidate_id))?;
+    if candidate.status != CandidateStatus::Pending {
+        return Err(format!(
+            "Duplicate candidate {} was already reviewed ({})",
+            candidate_id,
+            candidate.status.to_string()
+        ));
+    }
+    Ok(candidate)
+}
+
+/// Background job: rescans every `scan_interval_hours`. `settings` is re-read
+/// before each wait so changes apply without a restart.
+pub async fn run_scheduled_scans<F>(repository: Arc<SqliteRepository>, settings: F)
+where
+    F: Fn() -> DuplicateDetectionSettings,
+{
+    loop {
+        let interval_hours = settings().scan_interval_hours.max(1);
+        tokio::time::sleep(Duration::from_secs(interval_hours * 3600)).await;
+
+        let current = settings();
+        if !current.scheduled_scan {
+            continue;
+        }
+        if let Err(e) = scan_for_duplicates(&repository, &current).await {
+            log::error!("Scheduled duplicate patient scan failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+    use crate::models::{DataSource, TestResult};
+    use chrono::{DateTime, TimeZone};
+
+    fn patient(id: &str, first: &str, last: &str, birth_day: Option<u32>, sex: Sex, created_minute: u32) -> Patient {
+        let created_at = Utc.with_ymd_and_hms(2025, 3, 1, 9, created_minute, 0).unwrap();
+        Patient {
+            name: PatientName {
+                last_name: Some(last.to_string()),
+                first_name: Some(first.to_string()),
+                ..Default::default()
+            },
+            birth_date: birth_day.map(|day| Utc.with_ymd_and_hms(1980, 5, day, 0, 0, 0).unwrap()),
+            sex,
+            created_at,
+            updated_at: created_at,
+            ..Patient::fixture(id)
+        }
+    }
+
+    fn result(id: &str, sample_id: &str, at: DateTime<Utc>) -> TestResult {
+        TestResult {
+            sample_id: sample_id.to_string(),
+            units: None,
+            completed_date_time: Some(at),
+            created_at: at,
+            updated_at: at,
+            ..TestResult::fixture(id)
+        }
+    }
+
+    async fn seeded_repository() -> SqliteRepository {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        for seeded in [
+            patient("P1", "Anita", "Sharma", Some(12), Sex::Female, 0),
+            patient("P2", "Anitha", "SHARMA", Some(12), Sex::Female, 5),
+            patient("P3", "Rahul", "Verma", Some(12), Sex::Male, 10),
+        ] {
+            repository.save_patient(&seeded, &DataSource::Analyzer).await.unwrap();
+        }
+        repository
+    }
+
+    #[test]
+    fn test_scoring_rewards_matches_and_rules_out_conflicts() {
+        let settings = DuplicateDetectionSettings::default();
+        let anita = patient("P1", "Anita", "Sharma", Some(12), Sex::Female, 0);
+
+        let swapped = patient("P2", "SHARMA", "anita.", Some(12), Sex::Female, 0);
+        let (score, reasons) = score_pair(&anita, &swapped, &settings).unwrap();
+        assert_eq!(score, 1.0);
+        assert_eq!(reasons, vec!["exact_name", "birth_date", "sex"]);
+
+        let typo = patient("P2", "Anitha", "Sharma", Some(12), Sex::Female, 0);
+        let (score, reasons) = score_pair(&anita, &typo, &settings).unwrap();
+        assert!(score > 0.9 && score < 1.0, "score was {}", score);
+        assert_eq!(reasons[0], "similar_name");
+
+        // Unknown birth date or sex neither rules a pair out nor raises the score
+        let sparse = patient("P2", "Anita", "Sharma", None, Sex::Other, 0);
+        let lenient = DuplicateDetectionSettings {
+            min_score: 0.0,
+            ..Default::default()
+        };
+        assert!((score_pair(&anita, &sparse, &lenient).unwrap().0 - NAME_WEIGHT).abs() < 1e-9);
+        assert!(score_pair(&anita, &sparse, &settings).is_none());
+
+        let other_birthday = patient("P2", "Anita", "Sharma", Some(13), Sex::Female, 0);
+        assert!(score_pair(&anita, &other_birthday, &settings).is_none());
+        let other_name = patient("P2", "Anil", "Sarma", Some(12), Sex::Female, 0);
+        assert!(score_pair(&anita, &other_name, &settings).is_none());
+
+        let exact_only = DuplicateDetectionSettings {
+            max_name_distance: 0,
+            ..Default::default()
+        };
+        assert!(score_pair(&anita, &typo, &exact_only).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_scan_suggests_pairs_and_remembers_dismissals() {
+        let repository = seeded_repository().await;
+        let settings = DuplicateDetectionSettings::default();
+
+        let summary = scan_for_duplicates(&repository, &settings).await.unwrap();
+        assert_eq!(summary.patients_scanned, 3);
+        assert_eq!(summary.new_candidates, 1);
+
+        let candidates = repository.get_pending_duplicate_candidates(0.0).await.unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!((candidates[0].patient_id_a.as_str(), candidates[0].patient_id_b.as_str()), ("P1", "P2"));
+        assert!(repository.get_pending_duplicate_candidates(0.99).await.unwrap().is_empty());
+
+        // Rescanning refreshes the pending pair instead of adding another
+        let summary = scan_for_duplicates(&repository, &settings).await.unwrap();
+        assert_eq!((summary.pairs_matched, summary.new_candidates), (1, 0));
+
+        dismiss_duplicate_candidate(&repository, &candidates[0].id).await.unwrap();
+        let summary = scan_for_duplicates(&repository, &settings).await.unwrap();
+        assert_eq!(summary.new_candidates, 0);
+        assert!(repository.get_pending_duplicate_candidates(0.0).await.unwrap().is_empty());
+        assert!(dismiss_duplicate_candidate(&repository, &candidates[0].id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_accepting_candidate_merges_into_oldest_patient() {
+        let repository = seeded_repository().await;
+        let at = Utc.with_ymd_and_hms(2025, 3, 2, 10, 0, 0).unwrap();
+        repository.save_test_result(&result("R1", "S1", at), "P1", &DataSource::Analyzer).await.unwrap();
+        repository.save_test_result(&result("R2", "S2", at), "P2", &DataSource::Analyzer).await.unwrap();
+        // The same result was stored for both patients; the merge must not trip over its hash
+        repository.save_test_result(&result("R3", "S1", at), "P2", &DataSource::Analyzer).await.unwrap();
+
+        scan_for_duplicates(&repository, &DuplicateDetectionSettings::default()).await.unwrap();
+        let candidate = repository.get_pending_duplicate_candidates(0.0).await.unwrap().remove(0);
+
+        let outcome = accept_duplicate_candidate(&repository, &candidate.id, None).await.unwrap();
+        assert_eq!(outcome.primary_patient_id, "P1");
+        assert_eq!(outcome.merged_patient_id, "P2");
+        assert_eq!(outcome.results_moved, 2);
+
+        assert!(repository.get_patient("P2").await.unwrap().is_none());
+        assert_eq!(repository.get_patient_results("P1").await.unwrap().len(), 3);
+        let stored = repository.get_duplicate_candidate(&candidate.id).await.unwrap().unwrap();
+        assert_eq!(stored.status, CandidateStatus::Accepted);
+        assert!(accept_duplicate_candidate(&repository, &candidate.id, None).await.is_err());
+    }
+}
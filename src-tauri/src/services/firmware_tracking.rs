@@ -0,0 +1,246 @@
+use chrono::{DateTime, Duration, Utc};
+use serde_json::Value;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::models::{Analyzer, AnalyzerIdentity, ConformanceWarning, FirmwareChange};
+use crate::protocol::hl7_parser::{parse_msh_segment, HL7Message};
+use crate::services::autoquant_meril::AstmHeader;
+use crate::services::log_sampling::LogSampler;
+
+// ============================================================================
+// FIRMWARE IDENTITY TRACKING
+// ============================================================================
+
+/// Settings store key of the switch that puts an analyzer in conformance-report mode on a firmware change
+pub const FIRMWARE_CHANGE_CONFORMANCE_MODE_STORE_KEY: &str = "firmware_change_conformance_mode";
+
+/// Frontend event emitted when an analyzer declares a different identity
+pub const ANALYZER_IDENTITY_CHANGED_EVENT: &str = "lis:analyzer-identity-changed";
+
+/// How long conformance-report mode lasts after a firmware change. While it lasts every message
+/// is logged in full and a message with conformance deviations is held for review.
+pub const CONFORMANCE_REPORT_HOURS: i64 = 24;
+
+/// Reads the conformance-report switch from the store; off unless set
+pub fn conformance_mode_from_store(value: Option<Value>) -> bool {
+    value.and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+/// Identity declared by MSH-3 (application), MSH-4 (facility) and MSH-12 (version)
+pub fn hl7_identity(message: &HL7Message) -> Option<AnalyzerIdentity> {
+    let msh = message.segments.iter().find(|s| s.segment_type == "MSH")?;
+    let msh = parse_msh_segment(msh).ok()?;
+    let declared = |field: String| Some(field).filter(|f| !f.is_empty());
+    Some(AnalyzerIdentity {
+        protocol: "HL7".to_string(),
+        application: declared(msh.sending_application),
+        facility: declared(msh.sending_facility),
+        version: declared(msh.version_id),
+    })
+}
+
+/// Identity declared by ASTM header fields 5 (sender) and 13 (version)
+pub fn astm_identity(header: &AstmHeader) -> AnalyzerIdentity {
+    AnalyzerIdentity {
+        protocol: "ASTM".to_string(),
+        application: header.sender_id.clone(),
+        facility: None,
+        version: header.version.clone(),
+    }
+}
+
+/// What comparing a declared identity against the analyzer's last one found
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdentityObservation {
+    Unchanged,
+    FirstSeen,
+    Changed(FirmwareChange),
+}
+
+/// Compares `identity` with the one last declared by the analyzer and stores it if new.
+/// A change opens a conformance-report window when `conformance_mode` is on.
+pub fn observe_identity(
+    analyzer: &mut Analyzer,
+    identity: AnalyzerIdentity,
+    conformance_mode: bool,
+    now: DateTime<Utc>,
+) -> IdentityObservation {
+    if analyzer.last_seen_identity.as_ref() == Some(&identity) {
+        return IdentityObservation::Unchanged;
+    }
+
+    let previous = analyzer.last_seen_identity.replace(identity.clone());
+    let previous_since = analyzer.identity_since.replace(now);
+    analyzer.updated_at = now;
+
+    let Some(previous) = previous else {
+        return IdentityObservation::FirstSeen;
+    };
+
+    let conformance_report_until = conformance_mode.then(|| now + Duration::hours(CONFORMANCE_REPORT_HOURS));
+    if conformance_report_until.is_some() {
+        analyzer.conformance_report_until = conformance_report_until;
+    }
+
+    IdentityObservation::Changed(FirmwareChange {
+        id: Uuid::new_v4().to_string(),
+        analyzer_id: analyzer.id.clone(),
+        previous,
+        current: identity,
+        previous_since,
+        changed_at: now,
+        conformance_report_until,
+    })
+}
+
+impl IdentityObservation {
+    /// The firmware change found, if any
+    pub fn into_change(self) -> Option<FirmwareChange> {
+        match self {
+            IdentityObservation::Changed(change) => Some(change),
+            _ => None,
+        }
+    }
+}
+
+/// Observes `identity` on a running service's analyzer. A change is logged and, when it
+/// opens a conformance-report window, the service's `log_sampler` logs every message until
+/// the window closes. The caller persists the analyzer unless `Unchanged`.
+pub async fn record_identity(
+    analyzer: &RwLock<Analyzer>,
+    log_sampler: &LogSampler,
+    identity: AnalyzerIdentity,
+    conformance_mode: bool,
+) -> IdentityObservation {
+    let observation = {
+        let mut analyzer = analyzer.write().await;
+        observe_identity(&mut analyzer, identity, conformance_mode, Utc::now())
+    };
+
+    if let IdentityObservation::Changed(change) = &observation {
+        log::warn!(
+            "⚠️ Analyzer {} changed identity from {:?} to {:?}",
+            change.analyzer_id,
+            change.previous,
+            change.current
+        );
+        if change.conformance_report_until.is_some() {
+            log_sampler.capture_until(change.conformance_report_until);
+        }
+    }
+    observation
+}
+
+/// While the analyzer's conformance-report window is open a message with conformance
+/// deviations is held for review instead of being released. Returns the review detail
+/// for such a message.
+pub fn conformance_review_detail(
+    analyzer: &Analyzer,
+    warnings: &[ConformanceWarning],
+    now: DateTime<Utc>,
+) -> Option<String> {
+    let reporting = analyzer.conformance_report_until.is_some_and(|until| until > now);
+    if warnings.is_empty() || !reporting {
+        return None;
+    }
+    let rules: Vec<&str> = warnings.iter().map(|warning| warning.rule_id.as_str()).collect();
+    Some(format!("Conformance deviations after a firmware change: {}", rules.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::hl7_parser::parse_hl7_message;
+
+    fn identity(version: &str) -> AnalyzerIdentity {
+        AnalyzerIdentity {
+            protocol: "HL7".to_string(),
+            application: Some("CQ5Plus".to_string()),
+            facility: Some("Meril".to_string()),
+            version: Some(version.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_identity_first_seen_then_changed() {
+        let mut analyzer = crate::test_support::bf6900_analyzer();
+        let seen = Utc::now();
+
+        assert_eq!(
+            observe_identity(&mut analyzer, identity("2.3.1"), false, seen),
+            IdentityObservation::FirstSeen
+        );
+        assert_eq!(
+            observe_identity(&mut analyzer, identity("2.3.1"), false, seen),
+            IdentityObservation::Unchanged
+        );
+
+        let changed_at = seen + Duration::minutes(5);
+        match observe_identity(&mut analyzer, identity("2.4"), true, changed_at) {
+            IdentityObservation::Changed(change) => {
+                assert_eq!(change.previous, identity("2.3.1"));
+                assert_eq!(change.current, identity("2.4"));
+                assert_eq!(change.previous_since, Some(seen));
+                assert_eq!(
+                    change.conformance_report_until,
+                    Some(changed_at + Duration::hours(CONFORMANCE_REPORT_HOURS))
+                );
+            }
+            other => panic!("Expected a change, got {:?}", other),
+        }
+        assert_eq!(analyzer.last_seen_identity, Some(identity("2.4")));
+        assert_eq!(analyzer.identity_since, Some(changed_at));
+    }
+
+    #[tokio::test]
+    async fn test_deviations_are_held_only_after_a_change_in_conformance_mode() {
+        let mut analyzer = crate::test_support::bf6900_analyzer();
+        analyzer.last_seen_identity = Some(identity("2.3.1"));
+        let analyzer = RwLock::new(analyzer);
+        let log_sampler = LogSampler::new(10);
+        let warnings = vec![ConformanceWarning {
+            rule_id: "HL7-MSH-12".to_string(),
+            description: "Unexpected HL7 version".to_string(),
+            detail: "2.4".to_string(),
+        }];
+        let now = Utc::now();
+        assert_eq!(conformance_review_detail(&*analyzer.read().await, &warnings, now), None);
+
+        // A change without conformance mode is recorded but holds nothing
+        let observation = record_identity(&analyzer, &log_sampler, identity("2.4"), false).await;
+        assert!(observation.into_change().is_some());
+        assert_eq!(conformance_review_detail(&*analyzer.read().await, &warnings, now), None);
+
+        let observation = record_identity(&analyzer, &log_sampler, identity("2.5"), true).await;
+        let until = observation.into_change().unwrap().conformance_report_until.unwrap();
+        assert!(log_sampler.is_capturing());
+        assert_eq!(
+            conformance_review_detail(&*analyzer.read().await, &warnings, now).as_deref(),
+            Some("Conformance deviations after a firmware change: HL7-MSH-12")
+        );
+        // A conforming message still passes
+        assert_eq!(conformance_review_detail(&*analyzer.read().await, &[], now), None);
+
+        // The analyzer's window decides, not the sampler: nothing is held once it closes,
+        // and a window restored with the analyzer holds on its own
+        assert_eq!(conformance_review_detail(&*analyzer.read().await, &warnings, until), None);
+        let restored = Analyzer {
+            conformance_report_until: Some(until),
+            ..crate::test_support::bf6900_analyzer()
+        };
+        assert!(conformance_review_detail(&restored, &warnings, now).is_some());
+
+        let observation = record_identity(&analyzer, &log_sampler, identity("2.5"), true).await;
+        assert_eq!(observation, IdentityObservation::Unchanged);
+    }
+
+    #[test]
+    fn test_hl7_identity_from_msh() {
+        let message = parse_hl7_message(
+            "MSH|^~\\&|CQ5Plus|Meril|LIS||20240101120000||ORU^R01|42|P|2.3.1\rPID|1||P1",
+        )
+        .unwrap();
+        assert_eq!(hl7_identity(&message), Some(identity("2.3.1")));
+    }
+}
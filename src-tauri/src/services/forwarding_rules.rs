@@ -0,0 +1,236 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::hematology::{is_critical_value, HematologyResult};
+use crate::models::result::DILUTED_FLAG;
+use crate::models::TestResult;
+
+/// Key in the app settings store (`settings.json`) holding the forwarding rules
+pub const FORWARDING_RULES_STORE_KEY: &str = "forwarding_rules";
+
+/// Flags that mean the analyzer found a critical (panic) value
+const CRITICAL_FLAGS: [&str; 2] = ["HH", "LL"];
+
+// ============================================================================
+// RULE DEFINITIONS
+// ============================================================================
+
+/// What a result must satisfy for a rule to fire
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum RuleCondition {
+    /// Any abnormal flag (anything other than "N")
+    Abnormal,
+    /// HH/LL flag, or a hematology value beyond its critical limits
+    Critical,
+    /// A specific flag, e.g. "H"
+    Flag { flag: String },
+    /// One of the listed tests (case-insensitive)
+    Test { test_ids: Vec<String> },
+    /// Every nested condition holds
+    All { conditions: Vec<RuleCondition> },
+}
+
+/// What happens when a rule fires
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum RuleAction {
+    /// Emits a `forwarding:triggered` event for the named target (e.g. a pager system)
+    EmitEvent { target: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ForwardingRule {
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    pub condition: RuleCondition,
+    pub action: RuleAction,
+}
+
+/// The parts of a processed result the rules look at
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardCandidate {
+    pub analyzer_id: String,
+    pub sample_id: String,
+    pub test_id: String,
+    pub value: String,
+    pub flags: Vec<String>,
+}
+
+/// A rule that fired for a result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardMatch {
+    pub rule_id: String,
+    pub rule_name: String,
+    pub action: RuleAction,
+    pub result: ForwardCandidate,
+}
+
+impl ForwardCandidate {
+    pub fn from_meril_result(analyzer_id: &str, result: &TestResult) -> Self {
+        Self {
+            analyzer_id: analyzer_id.to_string(),
+            sample_id: result.sample_id.clone(),
+            test_id: result.test_id.clone(),
+            value: result.value.clone(),
+            flags: result.flag_list(),
+        }
+    }
+
+    pub fn from_hematology_result(analyzer_id: &str, result: &HematologyResult) -> Self {
+        Self {
+            analyzer_id: analyzer_id.to_string(),
+            sample_id: result.sample_id.clone(),
+            test_id: result.parameter_code.clone(),
+            value: result.value.clone(),
+            flags: result.flags.clone(),
+        }
+    }
+
+    fn abnormal_flags(&self) -> impl Iterator<Item = &str> {
+        self.flags
+            .iter()
+            .map(|flag| flag.trim())
+            .filter(|flag| !flag.is_empty() && !flag.eq_ignore_ascii_case("N") && *flag != DILUTED_FLAG)
+    }
+}
+
+impl RuleCondition {
+    pub fn matches(&self, candidate: &ForwardCandidate) -> bool {
+        match self {
+            RuleCondition::Abnormal => candidate.abnormal_flags().next().is_some(),
+            RuleCondition::Critical => {
+                candidate
+                    .abnormal_flags()
+                    .any(|flag| CRITICAL_FLAGS.iter().any(|critical| flag.eq_ignore_ascii_case(critical)))
+                    || candidate
+                        .value
+                        .trim()
+                        .parse::<f64>()
+                        .map(|value| is_critical_value(&candidate.test_id, value))
+                        .unwrap_or(false)
+            }
+            RuleCondition::Flag { flag } => candidate
+                .abnormal_flags()
+                .any(|candidate_flag| candidate_flag.eq_ignore_ascii_case(flag.trim())),
+            RuleCondition::Test { test_ids } => test_ids
+                .iter()
+                .any(|test_id| test_id.trim().eq_ignore_ascii_case(&candidate.test_id)),
+            RuleCondition::All { conditions } => conditions.iter().all(|c| c.matches(candidate)),
+        }
+    }
+}
+
+// ============================================================================
+// EVALUATION
+// ============================================================================
+
+/// Returns the enabled rules that fire for a result, in rule order
+pub fn evaluate_rules(rules: &[ForwardingRule], candidate: &ForwardCandidate) -> Vec<ForwardMatch> {
+    rules
+        .iter()
+        .filter(|rule| rule.enabled && rule.condition.matches(candidate))
+        .map(|rule| ForwardMatch {
+            rule_id: rule.id.clone(),
+            rule_name: rule.name.clone(),
+            action: rule.action.clone(),
+            result: candidate.clone(),
+        })
+        .collect()
+}
+
+/// Checks a rule set before it is saved
+pub fn validate_rules(rules: &[ForwardingRule]) -> Result<(), String> {
+    let mut ids = std::collections::HashSet::new();
+    for rule in rules {
+        if rule.id.trim().is_empty() {
+            return Err(format!("Rule '{}' has no id", rule.name));
+        }
+        if !ids.insert(rule.id.as_str()) {
+            return Err(format!("Duplicate rule id '{}'", rule.id));
+        }
+        match &rule.action {
+            RuleAction::EmitEvent { target } if target.trim().is_empty() => {
+                return Err(format!("Rule '{}' has no forwarding target", rule.id));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Reads stored rules, falling back to none when missing or invalid
+pub fn forwarding_rules_from_store(stored: Option<serde_json::Value>) -> Vec<ForwardingRule> {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid forwarding rules: {}", e);
+            Vec::new()
+        }),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(test_id: &str, value: &str, flags: &[&str]) -> ForwardCandidate {
+        ForwardCandidate {
+            analyzer_id: "A1".to_string(),
+            sample_id: "S1".to_string(),
+            test_id: test_id.to_string(),
+            value: value.to_string(),
+            flags: flags.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    fn pager_rule(condition: RuleCondition) -> ForwardingRule {
+        ForwardingRule {
+            id: "pager".to_string(),
+            name: "Abnormal to pager".to_string(),
+            enabled: true,
+            condition,
+            action: RuleAction::EmitEvent {
+                target: "pager".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_abnormal_result_triggers_forward_and_normal_does_not() {
+        let rules = vec![pager_rule(RuleCondition::Abnormal)];
+
+        let matches = evaluate_rules(&rules, &candidate("GLU", "250", &["H"]));
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].rule_id, "pager");
+        assert_eq!(
+            matches[0].action,
+            RuleAction::EmitEvent {
+                target: "pager".to_string()
+            }
+        );
+
+        assert!(evaluate_rules(&rules, &candidate("GLU", "95", &["N"])).is_empty());
+        assert!(evaluate_rules(&rules, &candidate("GLU", "95", &[DILUTED_FLAG])).is_empty());
+    }
+
+    #[test]
+    fn test_combined_and_critical_conditions() {
+        let rules = vec![pager_rule(RuleCondition::All {
+            conditions: vec![
+                RuleCondition::Test {
+                    test_ids: vec!["k".to_string()],
+                },
+                RuleCondition::Critical,
+            ],
+        })];
+
+        assert_eq!(evaluate_rules(&rules, &candidate("K", "7.1", &["HH"])).len(), 1);
+        assert!(evaluate_rules(&rules, &candidate("K", "5.6", &["H"])).is_empty());
+        assert!(evaluate_rules(&rules, &candidate("NA", "170", &["HH"])).is_empty());
+
+        let mut disabled = rules.clone();
+        disabled[0].enabled = false;
+        assert!(evaluate_rules(&disabled, &candidate("K", "7.1", &["HH"])).is_empty());
+    }
+}
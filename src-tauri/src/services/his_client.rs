@@ -1,9 +1,13 @@
 use chrono::{DateTime, Local, Utc};
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 use crate::models::hematology::HematologyResult;
-use crate::services::autoquant_meril::TestResult;
+use crate::models::patient::{ContactInfo, PatientAddress};
+use crate::models::TestResult;
+use crate::protocol::hl7_parser::find_negative_acknowledgment;
+use crate::protocol::message_profile::{MessageProfile, ObservationCoding};
 
 // ============================================================================
 // HIS API DATA STRUCTURES
@@ -15,6 +19,10 @@ pub struct HisTestValue {
     pub name: String,
     #[serde(rename = "Value")]
     pub value: String,
+    #[serde(rename = "Units", skip_serializing_if = "Option::is_none", default)]
+    pub units: Option<String>,
+    #[serde(rename = "LOINC", skip_serializing_if = "Option::is_none", default)]
+    pub loinc: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,9 +37,100 @@ pub struct HisApiPayload {
     pub sent: bool,
     #[serde(rename = "Values")]
     pub values: Vec<HisTestValue>,
+    /// Set only when the payload fixes one the HIS already received
+    #[serde(rename = "Correction", skip_serializing_if = "Option::is_none", default)]
+    pub correction: Option<PayloadCorrection>,
+    /// Structured patient contact details, when the message carried any
+    #[serde(rename = "Contact", skip_serializing_if = "Option::is_none", default)]
+    pub contact: Option<HisContact>,
+    /// Patient class (e.g. I inpatient, O outpatient) from PV1-2 or the analyzer's default
+    #[serde(rename = "PatientClass", skip_serializing_if = "Option::is_none", default)]
+    pub patient_class: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HisPhone {
+    #[serde(rename = "Use", skip_serializing_if = "Option::is_none", default)]
+    pub use_code: Option<String>,
+    #[serde(rename = "CountryCode", skip_serializing_if = "Option::is_none", default)]
+    pub country_code: Option<String>,
+    #[serde(rename = "AreaCode", skip_serializing_if = "Option::is_none", default)]
+    pub area_code: Option<String>,
+    #[serde(rename = "Number")]
+    pub number: String,
+    #[serde(rename = "Extension", skip_serializing_if = "Option::is_none", default)]
+    pub extension: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HisAddress {
+    #[serde(rename = "Street", skip_serializing_if = "Option::is_none", default)]
+    pub street: Option<String>,
+    #[serde(rename = "OtherDesignation", skip_serializing_if = "Option::is_none", default)]
+    pub other_designation: Option<String>,
+    #[serde(rename = "City", skip_serializing_if = "Option::is_none", default)]
+    pub city: Option<String>,
+    #[serde(rename = "State", skip_serializing_if = "Option::is_none", default)]
+    pub state: Option<String>,
+    #[serde(rename = "Zip", skip_serializing_if = "Option::is_none", default)]
+    pub zip: Option<String>,
+    #[serde(rename = "Country", skip_serializing_if = "Option::is_none", default)]
+    pub country: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HisContact {
+    #[serde(rename = "Phones", skip_serializing_if = "Vec::is_empty", default)]
+    pub phones: Vec<HisPhone>,
+    #[serde(rename = "Address", skip_serializing_if = "Option::is_none", default)]
+    pub address: Option<HisAddress>,
+}
+
+impl HisContact {
+    /// Maps parsed contact details to the HIS shape. Values that could not be
+    /// split are left out; returns None when nothing structured remains.
+    pub fn from_parts(contacts: &[ContactInfo], address: Option<&PatientAddress>) -> Option<Self> {
+        let phones: Vec<HisPhone> = contacts
+            .iter()
+            .filter(|contact| !contact.unparsed)
+            .filter_map(|contact| {
+                Some(HisPhone {
+                    use_code: contact.use_code.clone(),
+                    country_code: contact.country_code.clone(),
+                    area_code: contact.area_code.clone(),
+                    number: contact.local_number.clone()?,
+                    extension: contact.extension.clone(),
+                })
+            })
+            .collect();
+        let address = address.filter(|a| !a.unparsed).map(|a| HisAddress {
+            street: a.street.clone(),
+            other_designation: a.other_designation.clone(),
+            city: a.city.clone(),
+            state: a.state.clone(),
+            zip: a.zip.clone(),
+            country: a.country_code.clone(),
+        });
+
+        (!phones.is_empty() || address.is_some()).then_some(Self { phones, address })
+    }
+}
+
+/// How a payload relates to one sent earlier for the same sample
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PayloadCorrection {
+    /// Replaces the values sent earlier
+    Corrected,
+    /// Withdraws the values sent earlier
+    Cancelled,
+}
+
+/// Key in the app settings store (`settings.json`) holding the HIS connection settings
+pub const HIS_CONFIG_STORE_KEY: &str = "his";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
 pub struct HisApiConfig {
     pub base_url: String,
     pub timeout_seconds: u64,
@@ -50,6 +149,34 @@ impl Default for HisApiConfig {
     }
 }
 
+impl HisApiConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let url = reqwest::Url::parse(&self.base_url)
+            .map_err(|e| format!("Invalid HIS URL {}: {}", self.base_url, e))?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(format!("HIS URL must use http or https: {}", self.base_url));
+        }
+        if self.timeout_seconds == 0 || self.timeout_seconds > 300 {
+            return Err("HIS timeout must be between 1 and 300 seconds".to_string());
+        }
+        if self.retry_attempts > 10 {
+            return Err("HIS retry attempts cannot exceed 10".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Reads stored HIS settings, falling back to the default when missing or invalid
+pub fn his_config_from_store(stored: Option<serde_json::Value>) -> HisApiConfig {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid HIS settings: {}", e);
+            HisApiConfig::default()
+        }),
+        None => HisApiConfig::default(),
+    }
+}
+
 // ============================================================================
 // HIS API CLIENT
 // ============================================================================
@@ -57,6 +184,9 @@ impl Default for HisApiConfig {
 pub struct HisClient {
     config: HisApiConfig,
     client: reqwest::Client,
+    /// Fails every send without touching the network; only settable in test
+    /// and `his-simulation` builds
+    simulated_failure: AtomicBool,
 }
 
 impl HisClient {
@@ -71,7 +201,11 @@ impl HisClient {
         log::info!("HIS client initialized with timeout: {}s, retry attempts: {}, retry delay: {}s", 
                    config.timeout_seconds, config.retry_attempts, config.retry_delay_seconds);
 
-        Self { config, client }
+        Self {
+            config,
+            client,
+            simulated_failure: AtomicBool::new(false),
+        }
     }
 
     pub fn with_default_config() -> Self {
@@ -86,7 +220,34 @@ impl HisClient {
         patient_id: Option<&str>,
         test_results: &[TestResult],
     ) -> Result<(), String> {
-        log::info!("Starting to send Meril results - Analyzer: {}, Patient: {:?}, Test count: {}", 
+        let payload = self.build_meril_payload(analyzer_id, patient_id, test_results);
+        log::info!("Sending Meril payload to HIS system for sample {}", payload.sample_no);
+
+        self.send_payload(&payload).await
+    }
+
+    /// Send hematology results from BF-6900 analyzer to HIS system
+    pub async fn send_hematology_results(
+        &self,
+        analyzer_id: &str,
+        patient_id: Option<&str>,
+        test_results: &[HematologyResult],
+        timestamp: DateTime<Utc>,
+    ) -> Result<(), String> {
+        let payload = self.build_hematology_payload(analyzer_id, patient_id, test_results, timestamp);
+        log::info!("Sending Hematology payload to HIS system for sample {}", payload.sample_no);
+
+        self.send_payload(&payload).await
+    }
+
+    /// Build the HIS payload for AutoQuant Meril results without sending it
+    pub fn build_meril_payload(
+        &self,
+        analyzer_id: &str,
+        patient_id: Option<&str>,
+        test_results: &[TestResult],
+    ) -> HisApiPayload {
+        log::info!("Building Meril payload - Analyzer: {}, Patient: {:?}, Test count: {}", 
                    analyzer_id, patient_id, test_results.len());
         
         log::debug!("Meril test results details: {:?}", test_results);
@@ -104,6 +265,8 @@ impl HisClient {
                 log::debug!("Mapping test ID '{}' to name '{}' with value '{}'", 
                            result.sample_id, mapped_name, result.value);
                 HisTestValue {
+                    loinc: Self::loinc_code_for(&mapped_name).map(|c| c.to_string()),
+                    units: result.units.clone().filter(|u| !u.is_empty()),
                     name: mapped_name,
                     value: result.value.clone(),
                 }
@@ -118,23 +281,24 @@ impl HisClient {
             sample_no,
             sent: true,
             values,
+            correction: None,
+            contact: None,
+            patient_class: None,
         };
 
         log::debug!("Constructed HIS API payload: {:?}", payload);
-        log::info!("Sending Meril payload to HIS system for sample {}", payload.sample_no);
-
-        self.send_payload(&payload).await
+        payload
     }
 
-    /// Send hematology results from BF-6900 analyzer to HIS system
-    pub async fn send_hematology_results(
+    /// Build the HIS payload for BF-6900 hematology results without sending it
+    pub fn build_hematology_payload(
         &self,
         analyzer_id: &str,
         patient_id: Option<&str>,
         test_results: &[HematologyResult],
-        timestamp: DateTime<Utc>,
-    ) -> Result<(), String> {
-        log::info!("Starting to send Hematology results - Analyzer: {}, Patient: {:?}, Test count: {}", 
+        _timestamp: DateTime<Utc>,
+    ) -> HisApiPayload {
+        log::info!("Building Hematology payload - Analyzer: {}, Patient: {:?}, Test count: {}", 
                    analyzer_id, patient_id, test_results.len());
         
         log::debug!("Hematology test results details: {:?}", test_results);
@@ -145,14 +309,24 @@ impl HisClient {
         log::debug!("Mapped analyzer '{}' to machine name '{}'", analyzer_id, machine_name);
         log::debug!("Using sample number: '{}'", sample_no);
         
+        // A parameter reported more than once is sent once per sub-id
+        let repeated = |parameter: &str| {
+            test_results.iter().filter(|result| result.parameter == parameter).count() > 1
+        };
         let values: Vec<HisTestValue> = test_results
             .iter()
             .map(|result| {
                 log::debug!("Processing hematology parameter '{}' with value '{}'", 
                            result.parameter, result.value);
                 HisTestValue {
-                    name: result.parameter.clone(),
+                    name: if repeated(&result.parameter) {
+                        result.qualified_parameter()
+                    } else {
+                        result.parameter.clone()
+                    },
                     value: result.value.clone(),
+                    units: result.units.clone().filter(|u| !u.is_empty()),
+                    loinc: Self::loinc_code_for(&result.parameter).map(|c| c.to_string()),
                 }
             })
             .collect();
@@ -165,16 +339,66 @@ impl HisClient {
             sample_no,
             sent: true,
             values,
+            correction: None,
+            contact: None,
+            patient_class: None,
         };
 
         log::debug!("Constructed HIS API payload: {:?}", payload);
-        log::info!("Sending Hematology payload to HIS system for sample {}", payload.sample_no);
+        payload
+    }
 
-        self.send_payload(&payload).await
+    /// Build the HIS payload for results already stored in the database
+    pub fn build_stored_results_payload(
+        &self,
+        sample_no: &str,
+        test_results: &[TestResult],
+    ) -> HisApiPayload {
+        let analyzer_id = test_results
+            .iter()
+            .find_map(|result| result.analyzer_id.clone())
+            .unwrap_or_default();
+        let machine_name = self.get_machine_name_for_analyzer(&analyzer_id.to_lowercase());
+
+        let values = test_results
+            .iter()
+            .map(|result| {
+                let mapped_name = self.map_test_name(&result.test_id);
+                HisTestValue {
+                    loinc: Self::loinc_code_for(&mapped_name).map(|c| c.to_string()),
+                    units: result.units.clone().filter(|u| !u.is_empty()),
+                    name: mapped_name,
+                    value: result.value.clone(),
+                }
+            })
+            .collect();
+
+        HisApiPayload {
+            machine: machine_name,
+            sent_on: Local::now().to_rfc3339(),
+            sample_no: sample_no.to_string(),
+            sent: true,
+            values,
+            correction: None,
+            contact: None,
+            patient_class: None,
+        }
+    }
+
+    /// Makes every send fail as if the HIS were down, for resilience testing
+    #[cfg(any(test, feature = "his-simulation"))]
+    pub fn set_simulated_failure(&self, enabled: bool) {
+        log::warn!("Simulated HIS failure {}", if enabled { "enabled" } else { "disabled" });
+        self.simulated_failure.store(enabled, Ordering::SeqCst);
     }
 
     /// Send the payload to HIS system with retry logic
-    async fn send_payload(&self, payload: &HisApiPayload) -> Result<(), String> {
+    pub(crate) async fn send_payload(&self, payload: &HisApiPayload) -> Result<(), String> {
+        if self.simulated_failure.load(Ordering::SeqCst) {
+            log::warn!("Simulated HIS failure: not sending sample {}", payload.sample_no);
+            return Err("Simulated HIS failure".to_string());
+        }
+
         log::debug!("Starting payload transmission to HIS system at URL: {}", self.config.base_url);
         log::debug!("Payload details - Machine: {}, Sample: {}, Values count: {}", 
                    payload.machine, payload.sample_no, payload.values.len());
@@ -264,6 +488,12 @@ impl HisClient {
                     } else {
                         log::debug!("HIS API response body is empty");
                     }
+
+                    // An HL7 ACK body can still reject the result behind a 2xx status
+                    if let Some(msa) = find_negative_acknowledgment(&body) {
+                        log::error!("HIS rejected sample {}: {}", payload.sample_no, msa.error_summary());
+                        return Err(format!("HIS rejected the result: {}", msa.error_summary()));
+                    }
                 }
                 Err(e) => {
                     log::warn!("Failed to read response body: {}", e);
@@ -279,6 +509,10 @@ impl HisClient {
                 .unwrap_or_else(|_| "Failed to read response body".to_string());
             
             log::error!("HIS API returned error status {}: {}", status, body);
+
+            if let Some(msa) = find_negative_acknowledgment(&body) {
+                return Err(format!("HIS API returned error status {}: {}", status, msa.error_summary()));
+            }
             
             Err(format!(
                 "HIS API returned error status {}: {}",
@@ -304,8 +538,33 @@ impl HisClient {
         machine_name
     }
 
+    /// Map HIS test names to LOINC codes
+    fn loinc_code_for(test_name: &str) -> Option<&'static str> {
+        let code = match test_name.to_uppercase().as_str() {
+            "ALB" => "1751-7",
+            "AST" => "1920-8",
+            "ALT" => "1742-6",
+            "GLU-G" => "2345-7",
+            "CREA-S" => "2160-0",
+            "TG" => "2571-8",
+            "HDL-C" => "2085-9",
+            "TC" => "2093-3",
+            "UREA" => "3094-0",
+            "WBC" => "6690-2",
+            "RBC" => "789-8",
+            "HGB" => "718-7",
+            "HCT" => "4544-3",
+            "MCV" => "787-2",
+            "MCH" => "785-6",
+            "MCHC" => "786-4",
+            "PLT" => "777-3",
+            _ => return None,
+        };
+        Some(code)
+    }
+
     /// Map internal test IDs to HIS system test names
-    fn map_test_name(&self, test_id: &str) -> String {
+    pub(crate) fn map_test_name(&self, test_id: &str) -> String {
         log::debug!("Mapping test ID '{}' to HIS test name", test_id);
         
         // Remove ASTM formatting and return clean test name
@@ -331,6 +590,90 @@ impl HisClient {
     }
 }
 
+// ============================================================================
+// HL7 ORU BUILDER
+// ============================================================================
+
+impl HisClient {
+    /// Builds an ORU^R01 message for stored results, shaped by the destination's profile.
+    /// `now` stamps MSH-7 and the control ID, and stands in for results without a completion time.
+    pub fn build_oru_message(
+        &self,
+        profile: &MessageProfile,
+        now: &DateTime<Utc>,
+        patient_id: Option<&str>,
+        sample_id: &str,
+        test_results: &[TestResult],
+        notes: &[String],
+    ) -> String {
+        let mut segments = Vec::new();
+
+        segments.push(format!(
+            "MSH|^~\\&|{}|{}|{}|{}|{}||ORU^R01|{}|P|{}||||||UTF-8",
+            profile.sending_application,
+            profile.sending_facility,
+            profile.receiving_application.as_deref().unwrap_or("HIS"),
+            profile.receiving_facility.as_deref().unwrap_or(""),
+            profile.format_timestamp(now),
+            format!("ORU{}", now.format("%Y%m%d%H%M%S")),
+            profile.version
+        ));
+        segments.push(format!("PID|1||{}", patient_id.unwrap_or("")));
+
+        let observed_at = test_results
+            .iter()
+            .filter_map(|result| result.completed_date_time)
+            .min()
+            .unwrap_or(*now);
+        segments.push(format!(
+            "OBR|1||{}|{}|||{}",
+            profile.format_filler_order(sample_id),
+            profile.sending_application,
+            profile.format_timestamp(&observed_at)
+        ));
+
+        if profile.include_nte {
+            for (index, note) in notes.iter().enumerate() {
+                segments.push(format!("NTE|{}|L|{}", index + 1, note));
+            }
+        }
+
+        for (index, result) in test_results.iter().enumerate() {
+            let name = self.map_test_name(&result.test_id);
+            let observation_id = match (profile.observation_coding, Self::loinc_code_for(&name)) {
+                (ObservationCoding::Loinc, Some(loinc)) => format!("{}^{}^LN", loinc, name),
+                _ => format!("{}^{}", name, name),
+            };
+            let value_type = if result.value.trim().parse::<f64>().is_ok() { "NM" } else { "ST" };
+            let reference_range = result
+                .reference_range
+                .as_ref()
+                .map(|range| range.to_string())
+                .unwrap_or_default();
+            let abnormal_flag = result
+                .flags
+                .as_ref()
+                .and_then(|flags| flags.abnormal_flag.clone())
+                .unwrap_or_default();
+
+            segments.push(format!(
+                "OBX|{}|{}|{}||{}|{}|{}|{}|||{}|||{}",
+                index + 1,
+                value_type,
+                observation_id,
+                result.value,
+                profile.format_units(result.units.as_deref().unwrap_or("")),
+                reference_range,
+                abnormal_flag,
+                result.status.as_hl7_code(),
+                profile.format_timestamp(&result.completed_date_time.unwrap_or(*now))
+            ));
+        }
+
+        segments.join("\r") + "\r"
+    }
+}
+
 // ============================================================================
 // TESTS
 // ============================================================================
@@ -351,12 +694,19 @@ mod tests {
                 HisTestValue {
                     name: "AST".to_string(),
                     value: "17.36".to_string(),
+                    units: None,
+                    loinc: None,
                 },
                 HisTestValue {
                     name: "ALT".to_string(),
                     value: "15.05".to_string(),
+                    units: None,
+                    loinc: None,
                 },
             ],
+            correction: None,
+            contact: None,
+            patient_class: None,
         };
 
         let json = serde_json::to_string_pretty(&payload).unwrap();
@@ -370,6 +720,27 @@ mod tests {
         assert!(json.contains("\"Values\""));
     }
 
+    #[test]
+    fn test_contact_mapping_skips_unparsed_values() {
+        use crate::protocol::contact::{normalize_address_text, parse_xtn};
+
+        let contacts = vec![parse_xtn("^PRN^PH^^^555^1234567^89"), parse_xtn("^PRN^PH")];
+        let address = normalize_address_text("12 Main St, Springfield IL 62704");
+        let contact = HisContact::from_parts(&contacts, Some(&address)).unwrap();
+        assert_eq!(contact.phones.len(), 1);
+        assert_eq!(contact.phones[0].area_code.as_deref(), Some("555"));
+        assert_eq!(contact.phones[0].number, "1234567");
+        assert_eq!(contact.address.as_ref().unwrap().zip.as_deref(), Some("62704"));
+
+        let json = serde_json::to_string(&contact).unwrap();
+        assert!(json.contains("\"AreaCode\":\"555\""));
+        assert!(json.contains("\"Extension\":\"89\""));
+
+        // Raw-only values are not sent
+        let unparsed = normalize_address_text("behind the old mill");
+        assert!(HisContact::from_parts(&contacts[1..], Some(&unparsed)).is_none());
+    }
+
     #[test]
     fn test_machine_name_mapping() {
         let client = HisClient::with_default_config();
@@ -406,4 +777,96 @@ mod tests {
         assert_eq!(client.config.timeout_seconds, 30);
         assert_eq!(client.config.retry_attempts, 3);
     }
-}
\ No newline at end of file
+
+    fn oru_results() -> Vec<TestResult> {
+        use crate::models::result::{ReferenceRange, ResultFlags, TestResultMetadata};
+        use chrono::TimeZone;
+
+        let completed = Utc.with_ymd_and_hms(2025, 1, 1, 10, 15, 30).unwrap();
+        [("^^^ALB", "3.5", "g/dL"), ("^^^GLU", "95", "mg/dL")]
+            .iter()
+            .enumerate()
+            .map(|(index, (test_id, value, units))| TestResult {
+                test_id: test_id.to_string(),
+                sample_id: "S200".to_string(),
+                value: value.to_string(),
+                units: Some(units.to_string()),
+                reference_range: Some(ReferenceRange {
+                    lower_limit: Some(3.4),
+                    upper_limit: Some(5.4),
+                }),
+                flags: Some(ResultFlags {
+                    abnormal_flag: Some("N".to_string()),
+                    nature_of_abnormality: None,
+                }),
+                completed_date_time: Some(completed),
+                metadata: TestResultMetadata {
+                    sequence_number: index as u32 + 1,
+                    ..Default::default()
+                },
+                analyzer_id: Some("autoquant-meril-001".to_string()),
+                created_at: completed,
+                updated_at: completed,
+                ..TestResult::fixture(&format!("R{}", index))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_oru_message_under_two_profiles() {
+        use crate::protocol::message_profile::{FillerOrderFormat, TimestampPrecision, UnitsCoding};
+        use chrono::TimeZone;
+
+        let client = HisClient::with_default_config();
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 45).unwrap();
+        let results = oru_results();
+        let notes = vec!["Sample slightly hemolyzed".to_string()];
+
+        let default_message = client.build_oru_message(
+            &MessageProfile::default(),
+            &now,
+            Some("P1"),
+            "S200",
+            &results,
+            &notes,
+        );
+        let default_segments: Vec<&str> = default_message.split('\r').filter(|s| !s.is_empty()).collect();
+        assert_eq!(
+            default_segments,
+            vec![
+                "MSH|^~\\&|LIS|HOSPITAL|HIS||20250101120045||ORU^R01|ORU20250101120045|P|2.3.1||||||UTF-8",
+                "PID|1||P1",
+                "OBR|1||S200|LIS|||20250101101530",
+                "OBX|1|NM|ALB^ALB||3.5|g/dL|3.4-5.4|N|||F|||20250101101530",
+                "OBX|2|NM|Glu-G^Glu-G||95|mg/dL|3.4-5.4|N|||F|||20250101101530",
+            ]
+        );
+
+        let site_profile = MessageProfile {
+            sending_application: "NRAMH-LIS".to_string(),
+            sending_facility: "NRAMH".to_string(),
+            receiving_application: Some("CAREMAP".to_string()),
+            receiving_facility: Some("MAIN".to_string()),
+            version: "2.5.1".to_string(),
+            units_coding: UnitsCoding::Iso,
+            observation_coding: ObservationCoding::Loinc,
+            filler_order_format: FillerOrderFormat::SampleIdNamespace,
+            include_nte: true,
+            timestamp_precision: TimestampPrecision::Minute,
+            acknowledgment_text: "Message accepted".to_string(),
+        };
+        let site_message = client.build_oru_message(&site_profile, &now, Some("P1"), "S200", &results, &notes);
+        let site_segments: Vec<&str> = site_message.split('\r').filter(|s| !s.is_empty()).collect();
+        assert_eq!(
+            site_segments,
+            vec![
+                "MSH|^~\\&|NRAMH-LIS|NRAMH|CAREMAP|MAIN|202501011200||ORU^R01|ORU20250101120045|P|2.5.1||||||UTF-8",
+                "PID|1||P1",
+                "OBR|1||S200^NRAMH-LIS|NRAMH-LIS|||202501011015",
+                "NTE|1|L|Sample slightly hemolyzed",
+                "OBX|1|NM|1751-7^ALB^LN||3.5|g/dL^^ISO+|3.4-5.4|N|||F|||202501011015",
+                "OBX|2|NM|2345-7^Glu-G^LN||95|mg/dL^^ISO+|3.4-5.4|N|||F|||202501011015",
+            ]
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+/// Key in the app settings store (`settings.json`) holding the id normalization rules
+pub const ID_NORMALIZATION_STORE_KEY: &str = "id_normalization";
+
+/// Rules that bring inbound patient/sample ids to one canonical form before they
+/// are matched or stored, so `00123`, `123` and `LAB-123` refer to the same patient.
+/// The default leaves ids unchanged apart from surrounding whitespace.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct IdNormalization {
+    /// Facility prefixes removed from the start of an id (case-insensitive, first match wins)
+    pub strip_prefixes: Vec<String>,
+    /// Removes leading zeros ("00123" -> "123")
+    pub strip_leading_zeros: bool,
+    /// Left-pads all-digit ids with zeros to this length ("123" -> "000123")
+    pub pad_to_length: Option<usize>,
+}
+
+impl IdNormalization {
+    /// Applies the rules to a single id
+    pub fn normalize(&self, id: &str) -> String {
+        let mut normalized = id.trim();
+
+        if let Some(prefix) = self.strip_prefixes.iter().find(|prefix| {
+            !prefix.is_empty()
+                && normalized.len() > prefix.len()
+                && normalized.is_char_boundary(prefix.len())
+                && normalized[..prefix.len()].eq_ignore_ascii_case(prefix)
+        }) {
+            normalized = &normalized[prefix.len()..];
+        }
+
+        if self.strip_leading_zeros {
+            let stripped = normalized.trim_start_matches('0');
+            normalized = if stripped.is_empty() && !normalized.is_empty() {
+                "0"
+            } else {
+                stripped
+            };
+        }
+
+        match self.pad_to_length {
+            Some(length) if !normalized.is_empty() && normalized.chars().all(|c| c.is_ascii_digit()) => {
+                format!("{:0>width$}", normalized, width = length)
+            }
+            _ => normalized.to_string(),
+        }
+    }
+
+    /// Applies the rules to an optional id, dropping ids that normalize to nothing
+    pub fn normalize_opt(&self, id: Option<&str>) -> Option<String> {
+        id.map(|id| self.normalize(id)).filter(|id| !id.is_empty())
+    }
+}
+
+/// Reads stored rules, falling back to the no-op default when missing or invalid
+pub fn id_normalization_from_store(stored: Option<serde_json::Value>) -> IdNormalization {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid id normalization settings: {}", e);
+            IdNormalization::default()
+        }),
+        None => IdNormalization::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leading_zeros_and_prefix_normalize_to_same_id() {
+        let rules = IdNormalization {
+            strip_prefixes: vec!["LAB-".to_string()],
+            strip_leading_zeros: true,
+            pad_to_length: None,
+        };
+
+        assert_eq!(rules.normalize("00123"), "123");
+        assert_eq!(rules.normalize("LAB-123"), "123");
+        assert_eq!(rules.normalize("lab-00123 "), "123");
+        assert_eq!(rules.normalize("000"), "0");
+        assert_eq!(IdNormalization::default().normalize(" 00123 "), "00123");
+    }
+
+    #[test]
+    fn test_padding_applies_to_numeric_ids_only() {
+        let rules = IdNormalization {
+            strip_prefixes: vec!["LAB-".to_string()],
+            strip_leading_zeros: false,
+            pad_to_length: Some(6),
+        };
+
+        assert_eq!(rules.normalize("LAB-123"), "000123");
+        assert_eq!(rules.normalize("00123"), "000123");
+        assert_eq!(rules.normalize("A123"), "A123");
+        assert_eq!(rules.normalize_opt(Some("  ")), None);
+    }
+}
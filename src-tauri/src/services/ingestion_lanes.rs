@@ -0,0 +1,372 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::services::maintenance::MaintenanceQueue;
+
+/// Messages waiting per lane before `submit` applies backpressure
+const LANE_CAPACITY: usize = 64;
+
+/// Analyzer/sample pairs whose latest arrival sequence is remembered for inversion checks
+const MAX_TRACKED_SAMPLES: usize = 10_000;
+
+/// Process-wide arrival counter; the protocol layer stamps each complete message with it
+static ARRIVAL_SEQUENCE: AtomicU64 = AtomicU64::new(1);
+
+/// Returns the next arrival sequence number, increasing for the life of the process
+pub fn next_arrival_sequence() -> u64 {
+    ARRIVAL_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Continues the counter after `latest`, the highest sequence persisted before a
+/// restart, so new messages never rank below ones applied by an earlier run
+pub fn seed_arrival_sequence(latest: u64) {
+    ARRIVAL_SEQUENCE.fetch_max(latest.saturating_add(1), Ordering::Relaxed);
+}
+
+// ============================================================================
+// LANES
+// ============================================================================
+
+/// A unit of ingestion work for one sample
+#[derive(Debug, Clone)]
+pub struct LaneItem<T> {
+    pub sample_id: String,
+    pub arrival_sequence: u64,
+    pub item: T,
+}
+
+/// Processes the items of one lane, one at a time
+#[async_trait]
+pub trait LaneHandler<T>: Send + Sync + 'static {
+    async fn handle(&self, item: LaneItem<T>);
+}
+
+/// Picks the lane for a sample (FNV-1a, so the mapping is stable across runs)
+pub fn lane_for(sample_id: &str, lane_count: usize) -> usize {
+    let hash = sample_id
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3));
+    (hash % lane_count.max(1) as u64) as usize
+}
+
+/// Ingestion work queue partitioned by sample id. Each lane has one worker, so
+/// messages for the same sample are handled sequentially in submission order
+/// while different samples are handled in parallel.
+pub struct IngestionLanes<T> {
+    name: String,
+    senders: Vec<mpsc::Sender<LaneItem<T>>>,
+    /// Submitted but not yet handled
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl<T: Send + 'static> IngestionLanes<T> {
+    /// Creates the lanes and spawns one worker per lane
+    pub fn new<H: LaneHandler<T>>(name: &str, lane_count: usize, handler: Arc<H>) -> Self {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let senders = (0..lane_count.max(1))
+            .map(|_| {
+                let (sender, mut receiver) = mpsc::channel::<LaneItem<T>>(LANE_CAPACITY);
+                let handler = handler.clone();
+                let in_flight = in_flight.clone();
+                tokio::spawn(async move {
+                    while let Some(item) = receiver.recv().await {
+                        handler.handle(item).await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                    }
+                });
+                sender
+            })
+            .collect();
+
+        Self {
+            name: name.to_string(),
+            senders,
+            in_flight,
+        }
+    }
+
+    pub fn lane_count(&self) -> usize {
+        self.senders.len()
+    }
+
+    /// Queues an item on its sample's lane
+    pub async fn submit(&self, sample_id: &str, arrival_sequence: u64, item: T) -> Result<(), String> {
+        let lane = lane_for(sample_id, self.senders.len());
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+
+        let queued = self.senders[lane]
+            .send(LaneItem {
+                sample_id: sample_id.to_string(),
+                arrival_sequence,
+                item,
+            })
+            .await;
+        if queued.is_err() {
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            return Err(format!("{} lane {} is closed", self.name, lane));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T: Send + 'static> MaintenanceQueue for IngestionLanes<T> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn pending(&self) -> Result<usize, String> {
+        Ok(self.in_flight.load(Ordering::SeqCst))
+    }
+
+    /// The lane workers consume the queue; draining only waits for them
+    async fn drain_once(&self) -> Result<usize, String> {
+        Ok(0)
+    }
+
+    fn is_persistent(&self) -> bool {
+        false
+    }
+}
+
+// ============================================================================
+// ORDERING GUARD
+// ============================================================================
+
+/// Where a message stands among the messages of its sample from the same analyzer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrivalOrder {
+    /// Newer than anything applied for the sample so far
+    InOrder,
+    /// Reached ingestion after `latest`, a newer message for the sample; its results
+    /// are still stored, as superseded, and held for review
+    OutOfOrder { latest: u64 },
+}
+
+/// Remembers the latest arrival sequence applied per analyzer and sample so a
+/// message that reaches the handler late is recognised as older than one applied.
+/// Keyed by analyzer rather than connection, as an analyzer may reconnect for every
+/// transmission; pairs it does not know yet are seeded from storage.
+#[derive(Debug, Default)]
+pub struct SampleSequenceGuard {
+    state: Mutex<SequenceState>,
+}
+
+#[derive(Debug, Default)]
+struct SequenceState {
+    latest: HashMap<(String, String), u64>,
+    /// Insertion order, so the oldest pairs are forgotten first
+    order: VecDeque<(String, String)>,
+}
+
+impl SampleSequenceGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the latest sequence of the sample on `analyzer_id` is remembered
+    pub fn is_tracked(&self, analyzer_id: &str, sample_id: &str) -> bool {
+        let key = (analyzer_id.to_string(), sample_id.to_string());
+        self.state.lock().unwrap().latest.contains_key(&key)
+    }
+
+    /// Remembers `latest`, read from storage, for a sample not tracked yet
+    pub fn seed(&self, analyzer_id: &str, sample_id: &str, latest: u64) {
+        let mut state = self.state.lock().unwrap();
+        let key = (analyzer_id.to_string(), sample_id.to_string());
+        if !state.latest.contains_key(&key) {
+            state.track(key, latest);
+        }
+    }
+
+    /// Records `arrival_sequence` for the sample on `analyzer_id` when it is newer
+    /// than anything applied. An inversion is logged and reported as out of order.
+    pub fn admit(&self, analyzer_id: &str, sample_id: &str, arrival_sequence: u64) -> ArrivalOrder {
+        let mut state = self.state.lock().unwrap();
+        let key = (analyzer_id.to_string(), sample_id.to_string());
+
+        match state.latest.get(&key).copied() {
+            Some(latest) if arrival_sequence <= latest => {
+                log::warn!(
+                    "Arrival sequence inversion for sample {} of analyzer {}: message {} reached ingestion after {}; storing it as superseded",
+                    sample_id,
+                    analyzer_id,
+                    arrival_sequence,
+                    latest
+                );
+                ArrivalOrder::OutOfOrder { latest }
+            }
+            Some(_) => {
+                state.latest.insert(key, arrival_sequence);
+                ArrivalOrder::InOrder
+            }
+            None => {
+                state.track(key, arrival_sequence);
+                ArrivalOrder::InOrder
+            }
+        }
+    }
+}
+
+impl SequenceState {
+    /// Starts tracking a pair, forgetting the oldest one past the limit
+    fn track(&mut self, key: (String, String), sequence: u64) {
+        self.latest.insert(key.clone(), sequence);
+        self.order.push_back(key);
+        if self.order.len() > MAX_TRACKED_SAMPLES {
+            if let Some(oldest) = self.order.pop_front() {
+                self.latest.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ResultStatus;
+    use std::time::Duration;
+
+    /// Applies a sample's status the way result ingestion does: a newer message replaces
+    /// the older one, and an older message arriving late is kept as superseded
+    #[derive(Default)]
+    struct StatusRecorder {
+        guard: SampleSequenceGuard,
+        applied: Mutex<HashMap<String, (u64, ResultStatus)>>,
+        superseded: Mutex<Vec<(String, u64)>>,
+        handled: Mutex<Vec<(String, u64)>>,
+    }
+
+    #[async_trait]
+    impl LaneHandler<ResultStatus> for StatusRecorder {
+        async fn handle(&self, item: LaneItem<ResultStatus>) {
+            tokio::task::yield_now().await;
+            self.handled.lock().unwrap().push((item.sample_id.clone(), item.arrival_sequence));
+            match self.guard.admit("A1", &item.sample_id, item.arrival_sequence) {
+                ArrivalOrder::InOrder => {
+                    self.applied
+                        .lock()
+                        .unwrap()
+                        .insert(item.sample_id, (item.arrival_sequence, item.item));
+                }
+                ArrivalOrder::OutOfOrder { .. } => {
+                    self.superseded.lock().unwrap().push((item.sample_id, item.arrival_sequence));
+                }
+            }
+        }
+    }
+
+    async fn wait_until_drained<T: Send + 'static>(lanes: &IngestionLanes<T>) {
+        for _ in 0..500 {
+            if lanes.pending().await.unwrap() == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+        panic!("lanes did not drain");
+    }
+
+    #[test]
+    fn test_same_sample_always_maps_to_same_lane() {
+        assert_eq!(lane_for("S-1001", 4), lane_for("S-1001", 4));
+        assert!((0..1000).all(|n| lane_for(&format!("S-{}", n), 4) < 4));
+        let used: std::collections::HashSet<usize> = (0..100).map(|n| lane_for(&format!("S-{}", n), 4)).collect();
+        assert_eq!(used.len(), 4);
+        assert_eq!(lane_for("S-1001", 0), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_sample_messages_handled_in_submission_order() {
+        let handler = Arc::new(StatusRecorder::default());
+        let lanes = IngestionLanes::new("test", 4, handler.clone());
+
+        for sequence in 1..=50 {
+            lanes.submit("S1", sequence, ResultStatus::Preliminary).await.unwrap();
+            lanes.submit(&format!("OTHER-{}", sequence), sequence, ResultStatus::Final).await.unwrap();
+        }
+        wait_until_drained(&lanes).await;
+
+        let handled = handler.handled.lock().unwrap().clone();
+        assert_eq!(handled.len(), 100);
+        let s1: Vec<u64> = handled
+            .iter()
+            .filter(|(sample_id, _)| sample_id == "S1")
+            .map(|(_, sequence)| *sequence)
+            .collect();
+        assert_eq!(s1, (1..=50).collect::<Vec<u64>>());
+        assert_eq!(handler.applied.lock().unwrap()["S1"].0, 50);
+        assert!(handler.superseded.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_preliminary_and_final_settle_on_final() {
+        for _ in 0..100 {
+            let handler = Arc::new(StatusRecorder::default());
+            let lanes = Arc::new(IngestionLanes::new("test", 4, handler.clone()));
+
+            // Sequenced at the protocol layer, then submitted from two racing tasks
+            let preliminary = next_arrival_sequence();
+            let final_result = next_arrival_sequence();
+            let first = {
+                let lanes = lanes.clone();
+                tokio::spawn(async move { lanes.submit("S1", preliminary, ResultStatus::Preliminary).await })
+            };
+            let second = {
+                let lanes = lanes.clone();
+                tokio::spawn(async move { lanes.submit("S1", final_result, ResultStatus::Final).await })
+            };
+            first.await.unwrap().unwrap();
+            second.await.unwrap().unwrap();
+            wait_until_drained(&lanes).await;
+
+            let applied = handler.applied.lock().unwrap()["S1"].clone();
+            assert_eq!(applied, (final_result, ResultStatus::Final));
+            // A preliminary that lost the race is kept, never dropped
+            let superseded = handler.superseded.lock().unwrap().clone();
+            assert!(superseded.is_empty() || superseded == vec![("S1".to_string(), preliminary)]);
+        }
+    }
+
+    #[test]
+    fn test_guard_flags_inversions_per_analyzer_and_forgets_oldest_samples() {
+        let guard = SampleSequenceGuard::new();
+        assert_eq!(guard.admit("A1", "S1", 5), ArrivalOrder::InOrder);
+        assert_eq!(guard.admit("A1", "S1", 4), ArrivalOrder::OutOfOrder { latest: 5 });
+        assert_eq!(guard.admit("A1", "S1", 5), ArrivalOrder::OutOfOrder { latest: 5 });
+        assert_eq!(guard.admit("A1", "S1", 6), ArrivalOrder::InOrder);
+
+        // The same sample id on another analyzer is ordered on its own
+        assert_eq!(guard.admit("A2", "S1", 3), ArrivalOrder::InOrder);
+        assert_eq!(guard.admit("A1", "S1", 3), ArrivalOrder::OutOfOrder { latest: 6 });
+
+        for n in 0..MAX_TRACKED_SAMPLES {
+            guard.admit("A1", &format!("X{}", n), 1);
+        }
+        // S1 was the oldest sample tracked and has been forgotten
+        assert!(!guard.is_tracked("A1", "S1"));
+        assert_eq!(guard.admit("A1", "S1", 1), ArrivalOrder::InOrder);
+    }
+
+    #[test]
+    fn test_guard_seeded_from_storage_orders_messages_after_a_restart() {
+        // The sequence persisted by the previous run
+        seed_arrival_sequence(1_000_000);
+        let guard = SampleSequenceGuard::new();
+        guard.seed("A1", "S1", 1_000_000);
+        assert!(guard.is_tracked("A1", "S1"));
+
+        // A message stamped before the restart is older; one stamped after is newer
+        assert_eq!(guard.admit("A1", "S1", 999_999), ArrivalOrder::OutOfOrder { latest: 1_000_000 });
+        let after_restart = next_arrival_sequence();
+        assert!(after_restart > 1_000_000);
+        assert_eq!(guard.admit("A1", "S1", after_restart), ArrivalOrder::InOrder);
+
+        // Seeding never replaces what the guard already applied
+        guard.seed("A1", "S1", 5);
+        assert_eq!(guard.admit("A1", "S1", 6), ArrivalOrder::OutOfOrder { latest: after_restart });
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::TcpListener;
+
+// ============================================================================
+// BIND ADDRESS
+// ============================================================================
+
+/// Address family an analyzer listener accepts connections on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListenFamily {
+    Ipv4,
+    /// IPv6 only (`IPV6_V6ONLY` set)
+    Ipv6,
+    /// IPv6 socket also accepting IPv4 clients as IPv4-mapped addresses
+    DualStack,
+}
+
+/// Resolves a configured bind address into the socket address to listen on.
+///
+/// `None` or an empty value keeps the historical `0.0.0.0` (all IPv4 interfaces).
+/// IPv6 literals may be written with or without brackets (`::`, `[::1]`).
+/// Dual-stack needs the IPv6 wildcard `::`, since only it can also receive IPv4.
+pub fn resolve_listen_address(
+    bind_address: Option<&str>,
+    port: u16,
+    dual_stack: bool,
+) -> Result<(SocketAddr, ListenFamily), String> {
+    let trimmed = bind_address.map(str::trim).unwrap_or_default();
+    let ip = if trimmed.is_empty() {
+        if dual_stack {
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+        } else {
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        }
+    } else {
+        let literal = trimmed
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+            .unwrap_or(trimmed);
+        literal.parse::<IpAddr>().map_err(|_| {
+            format!(
+                "Invalid bind address '{}': expected an IPv4 or IPv6 address without a port",
+                trimmed
+            )
+        })?
+    };
+
+    let family = match ip {
+        IpAddr::V4(_) if dual_stack => {
+            return Err(format!(
+                "Dual-stack listening needs the IPv6 wildcard '::', not IPv4 address {}",
+                ip
+            ))
+        }
+        IpAddr::V4(_) => ListenFamily::Ipv4,
+        IpAddr::V6(v6) if dual_stack && !v6.is_unspecified() => {
+            return Err(format!(
+                "Dual-stack listening needs the IPv6 wildcard '::', not {}",
+                ip
+            ))
+        }
+        IpAddr::V6(_) if dual_stack => ListenFamily::DualStack,
+        IpAddr::V6(_) => ListenFamily::Ipv6,
+    };
+
+    Ok((SocketAddr::new(ip, port), family))
+}
+
+/// Binds a non-blocking TCP listener for an analyzer, letting the OS queue up to
+/// `backlog` pending connections. The IPv6 only flag is always set explicitly
+/// because its default differs between operating systems.
+pub fn bind_tcp_listener(
+    bind_address: Option<&str>,
+    port: u16,
+    dual_stack: bool,
+    backlog: u32,
+) -> Result<(TcpListener, ListenFamily), String> {
+    let (addr, family) = resolve_listen_address(bind_address, port, dual_stack)?;
+
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))
+        .map_err(|e| format!("Failed to create socket for {}: {}", addr, e))?;
+    if family != ListenFamily::Ipv4 {
+        socket
+            .set_only_v6(family == ListenFamily::Ipv6)
+            .map_err(|e| format!("Failed to configure IPv6 socket for {}: {}", addr, e))?;
+    }
+    // Matches std/tokio: lets a restarted listener rebind while old connections linger
+    #[cfg(unix)]
+    socket
+        .set_reuse_address(true)
+        .map_err(|e| format!("Failed to configure socket for {}: {}", addr, e))?;
+    socket
+        .set_nonblocking(true)
+        .map_err(|e| format!("Failed to configure socket for {}: {}", addr, e))?;
+    socket
+        .bind(&addr.into())
+        .map_err(|e| format!("Failed to bind to {}: {}", addr, e))?;
+    socket
+        .listen(backlog.clamp(1, i32::MAX as u32) as i32)
+        .map_err(|e| format!("Failed to listen on {}: {}", addr, e))?;
+
+    let listener = TcpListener::from_std(socket.into())
+        .map_err(|e| format!("Failed to register listener on {}: {}", addr, e))?;
+    Ok((listener, family))
+}
+
+// ============================================================================
+// PER-IP CONNECTION LIMIT
+// ============================================================================
+
+/// Counts the open connections of each remote IP on one listener
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionLimiter {
+    /// `None` admits every connection
+    max_per_ip: Option<u32>,
+    open: Arc<Mutex<HashMap<IpAddr, u32>>>,
+}
+
+/// Holds one connection slot of a remote IP, released when dropped
+#[derive(Debug)]
+pub struct ConnectionPermit {
+    ip: IpAddr,
+    open: Arc<Mutex<HashMap<IpAddr, u32>>>,
+}
+
+impl ConnectionLimiter {
+    pub fn new(max_per_ip: Option<u32>) -> Self {
+        Self {
+            max_per_ip,
+            open: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Takes a slot for a connection from `ip`, or says why it is refused.
+    /// IPv4-mapped IPv6 peers of a dual-stack listener count as their IPv4 address.
+    pub fn admit(&self, ip: IpAddr) -> Result<ConnectionPermit, String> {
+        let ip = ip.to_canonical();
+        let mut open = self.open.lock().unwrap_or_else(|e| e.into_inner());
+        let count = open.entry(ip).or_insert(0);
+        if let Some(max) = self.max_per_ip {
+            if *count >= max {
+                return Err(format!(
+                    "{} already has {} open connection(s), the limit per IP is {}",
+                    ip, count, max
+                ));
+            }
+        }
+        *count += 1;
+        Ok(ConnectionPermit {
+            ip,
+            open: self.open.clone(),
+        })
+    }
+
+    /// Connections from `ip` currently holding a permit
+    pub fn open_connections(&self, ip: IpAddr) -> u32 {
+        let open = self.open.lock().unwrap_or_else(|e| e.into_inner());
+        open.get(&ip.to_canonical()).copied().unwrap_or(0)
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let mut open = self.open.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = open.get_mut(&self.ip) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                open.remove(&self.ip);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    #[test]
+    fn test_bind_address_family_validation() {
+        assert_eq!(
+            resolve_listen_address(None, 9000, false).unwrap(),
+            ("0.0.0.0:9000".parse().unwrap(), ListenFamily::Ipv4)
+        );
+        assert_eq!(
+            resolve_listen_address(Some("[::]"), 9000, true).unwrap(),
+            ("[::]:9000".parse().unwrap(), ListenFamily::DualStack)
+        );
+        assert_eq!(
+            resolve_listen_address(None, 9000, true).unwrap().1,
+            ListenFamily::DualStack
+        );
+        assert_eq!(
+            resolve_listen_address(Some("fe80::1"), 9000, false).unwrap().1,
+            ListenFamily::Ipv6
+        );
+        assert!(resolve_listen_address(Some("192.168.1.10"), 9000, true).is_err());
+        assert!(resolve_listen_address(Some("::1"), 9000, true).is_err());
+        assert!(resolve_listen_address(Some("192.168.1.10:9000"), 9000, false).is_err());
+        assert!(resolve_listen_address(Some("lis-host"), 9000, false).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_listener_on_ipv6_loopback_accepts_ipv6_client() {
+        let (listener, family) = match bind_tcp_listener(Some("[::1]"), 0, false, 128) {
+            Ok(bound) => bound,
+            Err(e) => {
+                // Hosts with IPv6 disabled cannot run this test
+                log::warn!("Skipping IPv6 listener test: {}", e);
+                return;
+            }
+        };
+        assert_eq!(family, ListenFamily::Ipv6);
+        let addr = listener.local_addr().unwrap();
+        assert!(addr.is_ipv6());
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let (mut server, peer) = listener.accept().await.unwrap();
+        assert!(peer.is_ipv6());
+
+        client.write_all(b"MSH").await.unwrap();
+        let mut received = [0u8; 3];
+        server.read_exact(&mut received).await.unwrap();
+        assert_eq!(&received, b"MSH");
+    }
+
+    #[test]
+    fn test_limiter_refuses_connections_over_the_per_ip_cap() {
+        let limiter = ConnectionLimiter::new(Some(2));
+        let analyzer: IpAddr = "192.168.1.20".parse().unwrap();
+        let other: IpAddr = "192.168.1.21".parse().unwrap();
+
+        let first = limiter.admit(analyzer).unwrap();
+        let _second = limiter.admit(analyzer).unwrap();
+        let refused = limiter.admit(analyzer).unwrap_err();
+        assert!(refused.contains("the limit per IP is 2"), "{}", refused);
+
+        // The cap is per IP, and a dual-stack peer counts as its IPv4 address
+        let _other = limiter.admit(other).unwrap();
+        assert!(limiter.admit("::ffff:192.168.1.20".parse().unwrap()).is_err());
+
+        drop(first);
+        assert_eq!(limiter.open_connections(analyzer), 1);
+        let _third = limiter.admit(analyzer).unwrap();
+
+        let unlimited = ConnectionLimiter::default();
+        let permits: Vec<_> = (0..10).map(|_| unlimited.admit(analyzer).unwrap()).collect();
+        assert_eq!(unlimited.open_connections(analyzer), 10);
+        drop(permits);
+        assert_eq!(unlimited.open_connections(analyzer), 0);
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+
+// ============================================================================
+// LOG SAMPLING
+// ============================================================================
+
+/// Decides which processed messages are logged at info, to keep high-throughput
+/// analyzers from flooding the log. Keeps the first message of every `rate`;
+/// warnings and errors are logged outside the sampler and never dropped.
+#[derive(Debug)]
+pub struct LogSampler {
+    rate: u64,
+    seen: AtomicU64,
+    capture_until: Mutex<Option<DateTime<Utc>>>, // Conformance-report mode: keep every message until then
+}
+
+impl LogSampler {
+    /// Samples 1 in `rate` messages; 0 and 1 keep every message
+    pub fn new(rate: u32) -> Self {
+        Self {
+            rate: u64::from(rate.max(1)),
+            seen: AtomicU64::new(0),
+            capture_until: Mutex::new(None),
+        }
+    }
+
+    /// Keeps every message until `until`, e.g. while a firmware change is under review
+    pub fn capture_until(&self, until: Option<DateTime<Utc>>) {
+        *self.capture_until.lock().unwrap() = until;
+    }
+
+    /// Whether conformance-report mode is on, i.e. every message is being kept
+    pub fn is_capturing(&self) -> bool {
+        self.capture_until.lock().unwrap().is_some_and(|until| Utc::now() < until)
+    }
+
+    /// Counts a message and returns whether its info lines should be logged
+    pub fn sample(&self) -> bool {
+        let sampled = self.seen.fetch_add(1, Ordering::Relaxed) % self.rate == 0;
+        sampled || self.is_capturing()
+    }
+}
+
+impl Default for LogSampler {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+/// Captures log records so tests can count the lines a code path logs
+#[cfg(test)]
+pub(crate) mod test_logger {
+    use std::sync::Mutex;
+
+    struct CapturingLogger {
+        records: Mutex<Vec<(log::Level, String)>>,
+    }
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.records.lock().unwrap().push((record.level(), record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger {
+        records: Mutex::new(Vec::new()),
+    };
+
+    /// Installs the capturing logger for the whole test binary (first call wins)
+    pub fn install() {
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(log::LevelFilter::Trace);
+        }
+    }
+
+    /// Lines logged at `level` that contain `needle`
+    pub fn count(level: log::Level, needle: &str) -> usize {
+        LOGGER
+            .records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(l, message)| *l == level && message.contains(needle))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampler_keeps_first_of_every_n() {
+        let sampler = LogSampler::new(10);
+        let kept: Vec<usize> = (0..30).filter(|_| sampler.sample()).collect();
+        assert_eq!(kept, vec![0, 10, 20]);
+
+        let every = LogSampler::new(0);
+        assert!((0..5).all(|_| every.sample()));
+
+        // Conformance-report mode keeps every message until it expires
+        sampler.capture_until(Some(Utc::now() + chrono::Duration::hours(1)));
+        assert!((0..5).all(|_| sampler.sample()));
+        sampler.capture_until(Some(Utc::now() - chrono::Duration::hours(1)));
+        assert_eq!((0..10).filter(|_| sampler.sample()).count(), 1);
+    }
+}
@@ -0,0 +1,587 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::services::autoquant_meril::AutoQuantMerilService;
+use crate::services::bf6900_service::BF6900Service;
+use crate::services::upload_worker::UploadWorker;
+
+/// Grace period given to in-flight transmissions when the app is closing
+pub const SHUTDOWN_GRACE_SECONDS: u64 = 10;
+
+/// How long in-memory queues may take to empty before quiescing gives up
+const MEMORY_QUEUE_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+// ============================================================================
+// PARTICIPANTS
+// ============================================================================
+
+/// An analyzer listener that can stop taking new connections
+#[async_trait]
+pub trait MaintenanceListener: Send + Sync {
+    fn name(&self) -> &str;
+    /// Refuses new connections. Returns false if the listener was not open.
+    async fn pause_listening(&self) -> bool;
+    async fn resume_listening(&self) -> Result<(), String>;
+    async fn active_connections(&self) -> usize;
+    /// Drops connections still open when the grace period runs out
+    async fn close_connections(&self);
+}
+
+/// Work that must be finished (or safely persisted) before an update
+#[async_trait]
+pub trait MaintenanceQueue: Send + Sync {
+    fn name(&self) -> &str;
+    async fn pending(&self) -> Result<usize, String>;
+    /// Makes one pass over the queue. Returns the number of items handled.
+    async fn drain_once(&self) -> Result<usize, String>;
+    /// Whether queued items survive a restart, so a stalled drain can stop and leave them
+    fn is_persistent(&self) -> bool;
+}
+
+#[async_trait]
+impl MaintenanceListener for AutoQuantMerilService {
+    fn name(&self) -> &str {
+        "AutoQuantMeril"
+    }
+
+    async fn pause_listening(&self) -> bool {
+        AutoQuantMerilService::pause_listening(self).await
+    }
+
+    async fn resume_listening(&self) -> Result<(), String> {
+        AutoQuantMerilService::resume_listening(self).await
+    }
+
+    async fn active_connections(&self) -> usize {
+        self.get_connections_count().await
+    }
+
+    async fn close_connections(&self) {
+        AutoQuantMerilService::close_connections(self).await
+    }
+}
+
+#[async_trait]
+impl MaintenanceListener for BF6900Service {
+    fn name(&self) -> &str {
+        "BF-6900"
+    }
+
+    async fn pause_listening(&self) -> bool {
+        BF6900Service::pause_listening(self).await
+    }
+
+    async fn resume_listening(&self) -> Result<(), String> {
+        BF6900Service::resume_listening(self).await
+    }
+
+    async fn active_connections(&self) -> usize {
+        self.get_connections_count().await
+    }
+
+    async fn close_connections(&self) {
+        BF6900Service::close_connections(self).await
+    }
+}
+
+/// HIS uploads are persisted in `result_upload_status`, so anything the HIS
+/// does not take now is sent after the update
+#[async_trait]
+impl MaintenanceQueue for UploadWorker {
+    fn name(&self) -> &str {
+        "HIS uploads"
+    }
+
+    async fn pending(&self) -> Result<usize, String> {
+        self.queued_count().await
+    }
+
+    async fn drain_once(&self) -> Result<usize, String> {
+        self.process_pending().await
+    }
+
+    fn is_persistent(&self) -> bool {
+        true
+    }
+}
+
+/// Events waiting in a service's channel for the app-state handler (ingestion)
+pub struct ChannelQueue<T> {
+    name: String,
+    sender: mpsc::Sender<T>,
+}
+
+impl<T> ChannelQueue<T> {
+    pub fn new(name: &str, sender: mpsc::Sender<T>) -> Self {
+        Self {
+            name: name.to_string(),
+            sender,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Send + 'static> MaintenanceQueue for ChannelQueue<T> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn pending(&self) -> Result<usize, String> {
+        Ok(self.sender.max_capacity() - self.sender.capacity())
+    }
+
+    /// The handler task consumes the channel; draining only waits for it
+    async fn drain_once(&self) -> Result<usize, String> {
+        Ok(0)
+    }
+
+    fn is_persistent(&self) -> bool {
+        false
+    }
+}
+
+// ============================================================================
+// STATUS
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MaintenancePhase {
+    Normal,
+    /// Listeners are closed; waiting for transmissions and queues
+    Draining,
+    /// Nothing in flight; the app can be updated or closed
+    SafeToUpdate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueBacklog {
+    pub name: String,
+    pub pending: usize,
+    /// Left in persistent storage instead of being drained
+    pub persisted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceStatus {
+    pub phase: MaintenancePhase,
+    pub entered_at: Option<DateTime<Utc>>,
+    /// When connections still open are dropped
+    pub grace_deadline: Option<DateTime<Utc>>,
+    pub safe_at: Option<DateTime<Utc>>,
+    pub active_connections: usize,
+    /// Connections dropped because they outlived the grace period
+    pub forced_disconnects: usize,
+    pub queues: Vec<QueueBacklog>,
+    pub error: Option<String>,
+}
+
+impl MaintenanceStatus {
+    fn normal() -> Self {
+        Self {
+            phase: MaintenancePhase::Normal,
+            entered_at: None,
+            grace_deadline: None,
+            safe_at: None,
+            active_connections: 0,
+            forced_disconnects: 0,
+            queues: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+// ============================================================================
+// MAINTENANCE CONTROLLER
+// ============================================================================
+
+struct MaintenanceState {
+    status: MaintenanceStatus,
+    /// Listeners paused by the current maintenance window, resumed on exit
+    paused: Vec<usize>,
+    /// Bumped on every enter/exit so a stale drain notices it was cancelled
+    generation: u64,
+}
+
+/// Quiesces analyzer traffic before an app update or shutdown.
+///
+/// Entering maintenance closes the listeners, lets open transmissions finish
+/// within a grace period (then drops them; the analyzer retransmits anything
+/// not yet ACKed), and drains the ingestion and upload queues before reporting
+/// that it is safe to update.
+pub struct MaintenanceController {
+    listeners: Vec<Arc<dyn MaintenanceListener>>,
+    queues: Vec<Arc<dyn MaintenanceQueue>>,
+    poll_interval: Duration,
+    state: Mutex<MaintenanceState>,
+}
+
+impl MaintenanceController {
+    pub fn new(listeners: Vec<Arc<dyn MaintenanceListener>>, queues: Vec<Arc<dyn MaintenanceQueue>>) -> Self {
+        Self {
+            listeners,
+            queues,
+            poll_interval: Duration::from_millis(250),
+            state: Mutex::new(MaintenanceState {
+                status: MaintenanceStatus::normal(),
+                paused: Vec::new(),
+                generation: 0,
+            }),
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn status(&self) -> MaintenanceStatus {
+        self.state.lock().unwrap().status.clone()
+    }
+
+    /// Closes the listeners and starts the grace period. Call [`quiesce`](Self::quiesce) to wait for it.
+    pub async fn begin(&self, grace: Duration) -> Result<MaintenanceStatus, String> {
+        let generation = {
+            let mut state = self.state.lock().unwrap();
+            if state.status.phase != MaintenancePhase::Normal {
+                return Err("Maintenance mode is already active".to_string());
+            }
+
+            let now = Utc::now();
+            state.generation += 1;
+            state.status = MaintenanceStatus {
+                phase: MaintenancePhase::Draining,
+                entered_at: Some(now),
+                grace_deadline: Some(now + chrono::Duration::from_std(grace).unwrap_or_default()),
+                ..MaintenanceStatus::normal()
+            };
+            state.generation
+        };
+
+        log::warn!("Entering maintenance mode with a {}s grace period", grace.as_secs());
+
+        let mut paused = Vec::new();
+        for (index, listener) in self.listeners.iter().enumerate() {
+            if listener.pause_listening().await {
+                paused.push(index);
+            }
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.generation == generation {
+            state.paused = paused;
+        }
+        Ok(state.status.clone())
+    }
+
+    /// Waits for in-flight transmissions and queues, then marks the app safe to update
+    pub async fn quiesce(&self) -> Result<MaintenanceStatus, String> {
+        let (generation, deadline) = {
+            let state = self.state.lock().unwrap();
+            match state.status.phase {
+                MaintenancePhase::Normal => return Err("Maintenance mode is not active".to_string()),
+                MaintenancePhase::SafeToUpdate => return Ok(state.status.clone()),
+                MaintenancePhase::Draining => (state.generation, state.status.grace_deadline.unwrap_or_else(Utc::now)),
+            }
+        };
+
+        // In-flight transmissions get until the grace deadline
+        loop {
+            let mut active = 0;
+            for listener in &self.listeners {
+                active += listener.active_connections().await;
+            }
+            self.update(generation, |status| status.active_connections = active)?;
+
+            if active == 0 {
+                break;
+            }
+            if Utc::now() >= deadline {
+                log::warn!("Grace period over; dropping {} open analyzer connections", active);
+                for listener in &self.listeners {
+                    listener.close_connections().await;
+                }
+                self.update(generation, |status| {
+                    status.active_connections = 0;
+                    status.forced_disconnects = active;
+                })?;
+                break;
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+
+        // Then the queues, in order: ingestion before uploads
+        for queue in &self.queues {
+            let started = tokio::time::Instant::now();
+            loop {
+                let pending = queue.pending().await?;
+                self.set_backlog(generation, queue.name(), pending, false)?;
+                if pending == 0 {
+                    break;
+                }
+
+                if queue.drain_once().await? > 0 {
+                    continue;
+                }
+                if queue.is_persistent() {
+                    log::info!("Leaving {} {} persisted for after the update", pending, queue.name());
+                    self.set_backlog(generation, queue.name(), pending, true)?;
+                    break;
+                }
+                if started.elapsed() >= MEMORY_QUEUE_DRAIN_TIMEOUT {
+                    let error = format!("{} did not drain ({} still pending)", queue.name(), pending);
+                    self.update(generation, |status| status.error = Some(error.clone()))?;
+                    return Err(error);
+                }
+                tokio::time::sleep(self.poll_interval).await;
+            }
+        }
+
+        let status = self.update(generation, |status| {
+            status.phase = MaintenancePhase::SafeToUpdate;
+            status.safe_at = Some(Utc::now());
+        })?;
+        log::warn!("Maintenance mode: safe to update");
+        Ok(status)
+    }
+
+    /// Enters maintenance and waits until it is safe to update
+    pub async fn enter(&self, grace: Duration) -> Result<MaintenanceStatus, String> {
+        self.begin(grace).await?;
+        self.quiesce().await
+    }
+
+    /// Reopens the listeners that were paused and returns to normal operation
+    pub async fn exit(&self) -> Result<MaintenanceStatus, String> {
+        let paused = {
+            let mut state = self.state.lock().unwrap();
+            if state.status.phase == MaintenancePhase::Normal {
+                return Ok(state.status.clone());
+            }
+            state.generation += 1;
+            state.status = MaintenanceStatus::normal();
+            std::mem::take(&mut state.paused)
+        };
+
+        let mut errors = Vec::new();
+        for index in paused {
+            let listener = &self.listeners[index];
+            if let Err(e) = listener.resume_listening().await {
+                log::error!("Failed to resume {} listener: {}", listener.name(), e);
+                errors.push(format!("{}: {}", listener.name(), e));
+            }
+        }
+
+        log::info!("Left maintenance mode");
+        if errors.is_empty() {
+            Ok(self.status())
+        } else {
+            Err(format!("Failed to resume listeners: {}", errors.join("; ")))
+        }
+    }
+
+    /// Applies a change to the status unless maintenance was exited meanwhile
+    fn update(&self, generation: u64, change: impl FnOnce(&mut MaintenanceStatus)) -> Result<MaintenanceStatus, String> {
+        let mut state = self.state.lock().unwrap();
+        if state.generation != generation {
+            return Err("Maintenance mode was exited before it became safe to update".to_string());
+        }
+        change(&mut state.status);
+        Ok(state.status.clone())
+    }
+
+    fn set_backlog(&self, generation: u64, name: &str, pending: usize, persisted: bool) -> Result<(), String> {
+        self.update(generation, |status| {
+            match status.queues.iter_mut().find(|queue| queue.name == name) {
+                Some(queue) => {
+                    queue.pending = pending;
+                    queue.persisted = persisted;
+                }
+                None => status.queues.push(QueueBacklog {
+                    name: name.to_string(),
+                    pending,
+                    persisted,
+                }),
+            }
+        })
+        .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct FakeListener {
+        listening: AtomicBool,
+        connections: AtomicUsize,
+        closed: AtomicBool,
+    }
+
+    #[async_trait]
+    impl MaintenanceListener for FakeListener {
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        async fn pause_listening(&self) -> bool {
+            self.listening.swap(false, Ordering::SeqCst)
+        }
+
+        async fn resume_listening(&self) -> Result<(), String> {
+            self.listening.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn active_connections(&self) -> usize {
+            self.connections.load(Ordering::SeqCst)
+        }
+
+        async fn close_connections(&self) {
+            self.connections.store(0, Ordering::SeqCst);
+            self.closed.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Drains one item per pass, or none when stalled
+    struct FakeQueue {
+        pending: AtomicUsize,
+        stalled: bool,
+        persistent: bool,
+    }
+
+    #[async_trait]
+    impl MaintenanceQueue for FakeQueue {
+        fn name(&self) -> &str {
+            "fake queue"
+        }
+
+        async fn pending(&self) -> Result<usize, String> {
+            Ok(self.pending.load(Ordering::SeqCst))
+        }
+
+        async fn drain_once(&self) -> Result<usize, String> {
+            if self.stalled {
+                return Ok(0);
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            Ok(1)
+        }
+
+        fn is_persistent(&self) -> bool {
+            self.persistent
+        }
+    }
+
+    fn listener(connections: usize) -> Arc<FakeListener> {
+        let listener = FakeListener::default();
+        listener.listening.store(true, Ordering::SeqCst);
+        listener.connections.store(connections, Ordering::SeqCst);
+        Arc::new(listener)
+    }
+
+    fn controller(listener: &Arc<FakeListener>, queues: Vec<Arc<dyn MaintenanceQueue>>) -> MaintenanceController {
+        MaintenanceController::new(vec![listener.clone()], queues).with_poll_interval(Duration::from_millis(10))
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_transmission_completes_within_grace() {
+        let listener = listener(1);
+        let controller = controller(&listener, Vec::new());
+
+        let status = controller.begin(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(status.phase, MaintenancePhase::Draining);
+        assert!(!listener.listening.load(Ordering::SeqCst));
+        assert!(controller.begin(Duration::from_secs(5)).await.is_err());
+
+        // The analyzer finishes its transmission and hangs up
+        let finishing = listener.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            finishing.connections.store(0, Ordering::SeqCst);
+        });
+
+        let status = controller.quiesce().await.unwrap();
+        assert_eq!(status.phase, MaintenancePhase::SafeToUpdate);
+        assert_eq!(status.forced_disconnects, 0);
+        assert!(!listener.closed.load(Ordering::SeqCst));
+
+        let status = controller.exit().await.unwrap();
+        assert_eq!(status.phase, MaintenancePhase::Normal);
+        assert!(listener.listening.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_connection_outliving_grace_is_dropped() {
+        let listener = listener(2);
+        let controller = controller(&listener, Vec::new());
+
+        let status = controller.enter(Duration::from_millis(30)).await.unwrap();
+        assert_eq!(status.phase, MaintenancePhase::SafeToUpdate);
+        assert_eq!(status.forced_disconnects, 2);
+        assert!(listener.closed.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_safe_signal_only_after_queues_drain() {
+        let listener = listener(0);
+        let queue = Arc::new(FakeQueue {
+            pending: AtomicUsize::new(5),
+            stalled: false,
+            persistent: false,
+        });
+        let controller = Arc::new(controller(&listener, vec![queue.clone()]));
+        controller.begin(Duration::from_secs(1)).await.unwrap();
+
+        let draining = controller.clone();
+        let handle = tokio::spawn(async move { draining.quiesce().await });
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(queue.pending.load(Ordering::SeqCst) > 0);
+        assert_eq!(controller.status().phase, MaintenancePhase::Draining);
+
+        let status = handle.await.unwrap().unwrap();
+        assert_eq!(status.phase, MaintenancePhase::SafeToUpdate);
+        assert_eq!(queue.pending.load(Ordering::SeqCst), 0);
+        assert_eq!(status.queues[0].pending, 0);
+        assert!(!status.queues[0].persisted);
+    }
+
+    #[tokio::test]
+    async fn test_stalled_persistent_queue_is_left_persisted() {
+        let listener = listener(0);
+        let queue = Arc::new(FakeQueue {
+            pending: AtomicUsize::new(3),
+            stalled: true,
+            persistent: true,
+        });
+        let controller = controller(&listener, vec![queue]);
+
+        let status = controller.enter(Duration::from_secs(1)).await.unwrap();
+        assert_eq!(status.phase, MaintenancePhase::SafeToUpdate);
+        assert_eq!(status.queues[0].pending, 3);
+        assert!(status.queues[0].persisted);
+    }
+
+    #[tokio::test]
+    async fn test_exit_cancels_pending_drain() {
+        let listener = listener(1);
+        let controller = Arc::new(controller(&listener, Vec::new()));
+        controller.begin(Duration::from_secs(5)).await.unwrap();
+
+        let draining = controller.clone();
+        let handle = tokio::spawn(async move { draining.quiesce().await });
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        controller.exit().await.unwrap();
+        assert!(handle.await.unwrap().is_err());
+        assert_eq!(controller.status().phase, MaintenancePhase::Normal);
+        assert!(listener.listening.load(Ordering::SeqCst));
+    }
+}
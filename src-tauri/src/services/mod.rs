@@ -1,9 +1,77 @@
+pub mod app_paths;
 pub mod autoquant_meril;
 pub mod bf6900_service;
 pub mod bootup;
+pub mod config_persistence;
+pub mod conformance;
+pub mod conversation_log;
+pub mod developer_console;
+pub mod csv_import;
+pub mod demographics_policy;
+pub mod disk_monitor;
+pub mod duplicate_detection;
+pub mod firmware_tracking;
+pub mod forwarding_rules;
 pub mod his_client;
+pub mod id_normalization;
+pub mod listen_address;
+pub mod log_sampling;
+pub mod ingestion_lanes;
+pub mod maintenance;
+pub mod order_dispatcher;
+pub mod remote_address_guard;
+pub mod report_locale;
+pub mod result_export;
+pub mod result_feed;
+pub mod result_images;
+pub mod results_package;
+pub mod sample_locks;
+pub mod sample_report;
+pub mod service_controller;
+pub mod setup_sheet;
+pub mod setup_wizard;
+pub mod shadow_mode;
+pub mod stale_results;
+pub mod store_recovery;
+pub mod upload_remediation;
+pub mod upload_worker;
+pub mod verification_rules;
 
+pub use app_paths::*;
 pub use autoquant_meril::*;
 pub use bf6900_service::*;
 pub use bootup::*;
+pub use config_persistence::*;
+pub use conformance::*;
+pub use conversation_log::*;
+pub use developer_console::*;
+pub use csv_import::*;
+pub use demographics_policy::*;
+pub use disk_monitor::*;
+pub use duplicate_detection::*;
+pub use firmware_tracking::*;
+pub use forwarding_rules::*;
 pub use his_client::*;
+pub use id_normalization::*;
+pub use listen_address::*;
+pub use log_sampling::*;
+pub use ingestion_lanes::*;
+pub use maintenance::*;
+pub use order_dispatcher::*;
+pub use remote_address_guard::*;
+pub use report_locale::*;
+pub use result_export::*;
+pub use result_feed::*;
+pub use result_images::*;
+pub use results_package::*;
+pub use sample_locks::*;
+pub use sample_report::*;
+pub use service_controller::*;
+pub use setup_sheet::*;
+pub use setup_wizard::*;
+pub use shadow_mode::*;
+pub use stale_results::*;
+pub use store_recovery::*;
+pub use upload_remediation::*;
+pub use upload_worker::*;
+pub use verification_rules::*;
@@ -0,0 +1,252 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::db::SqliteRepository;
+use crate::models::{DispatchStatus, OrderDispatch, TestOrder};
+
+// ============================================================================
+// TRANSPORT ABSTRACTION
+// ============================================================================
+
+/// Delivers worklist downloads to an analyzer connection
+#[async_trait]
+pub trait OrderTransport: Send + Sync {
+    /// Whether the analyzer currently has a live connection
+    async fn is_connected(&self, analyzer_id: &str) -> bool;
+
+    /// Transmits one order to the analyzer
+    async fn send_order(&self, analyzer_id: &str, order: &TestOrder) -> Result<(), String>;
+}
+
+// ============================================================================
+// ORDER DISPATCHER
+// ============================================================================
+
+#[derive(Debug, Clone)]
+pub struct OrderDispatcherConfig {
+    pub poll_interval_ms: u64,
+    /// Orders sent but not yet acknowledged, per analyzer
+    pub max_in_flight: u32,
+    pub max_attempts: u32,
+}
+
+impl Default for OrderDispatcherConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: 2000,
+            max_in_flight: 4,
+            max_attempts: 3,
+        }
+    }
+}
+
+/// Drains the persistent order outbox.
+///
+/// Orders are written to `order_dispatch_queue` instead of being sent directly,
+/// so orders created while an analyzer is disconnected go out once it connects.
+/// STAT orders are sent first and at most `max_in_flight` unacknowledged orders
+/// are outstanding per analyzer.
+pub struct OrderDispatcher {
+    repository: Arc<SqliteRepository>,
+    transport: Arc<dyn OrderTransport>,
+    config: OrderDispatcherConfig,
+    notify: Notify,
+}
+
+impl OrderDispatcher {
+    pub fn new(
+        repository: Arc<SqliteRepository>,
+        transport: Arc<dyn OrderTransport>,
+        config: OrderDispatcherConfig,
+    ) -> Self {
+        Self {
+            repository,
+            transport,
+            config,
+            notify: Notify::new(),
+        }
+    }
+
+    /// Dispatch hook: persists a patient's order for an analyzer as PENDING and wakes the dispatcher
+    pub async fn enqueue(&self, analyzer_id: &str, patient_id: &str, order: &TestOrder) -> Result<OrderDispatch, String> {
+        let dispatch = self.repository.enqueue_order_dispatch(analyzer_id, patient_id, order).await?;
+
+        log::info!("Queued order {} for analyzer {}", order.id, analyzer_id);
+        self.notify.notify_one();
+        Ok(dispatch)
+    }
+
+    /// Records that the analyzer accepted an order, freeing its in-flight slot
+    pub async fn acknowledge(&self, order_id: &str) -> Result<bool, String> {
+        let acknowledged = self.repository.acknowledge_order_dispatch(order_id).await?;
+        if acknowledged {
+            self.notify.notify_one();
+        }
+        Ok(acknowledged)
+    }
+
+    /// Sends pending orders to one analyzer while it is connected and has free
+    /// in-flight slots. Returns the number of orders sent.
+    pub async fn drain(&self, analyzer_id: &str) -> Result<usize, String> {
+        if !self.transport.is_connected(analyzer_id).await {
+            return Ok(0);
+        }
+
+        let in_flight = self.repository.count_in_flight_order_dispatches(analyzer_id).await?;
+        if in_flight >= self.config.max_in_flight {
+            return Ok(0);
+        }
+
+        let pending = self
+            .repository
+            .get_pending_order_dispatches(analyzer_id, self.config.max_in_flight - in_flight)
+            .await?;
+        let mut sent = 0;
+
+        for dispatch in pending {
+            self.repository
+                .update_order_dispatch_status(&dispatch.id, DispatchStatus::Sent, None)
+                .await?;
+
+            match self.transport.send_order(analyzer_id, &dispatch.order).await {
+                Ok(()) => sent += 1,
+                Err(e) => {
+                    let status = if dispatch.attempts + 1 >= self.config.max_attempts {
+                        log::error!(
+                            "Order {} for analyzer {} failed permanently: {}",
+                            dispatch.order_id,
+                            analyzer_id,
+                            e
+                        );
+                        DispatchStatus::Failed
+                    } else {
+                        log::warn!(
+                            "Order {} for analyzer {} failed (attempt {}): {}",
+                            dispatch.order_id,
+                            analyzer_id,
+                            dispatch.attempts + 1,
+                            e
+                        );
+                        DispatchStatus::Pending
+                    };
+                    self.repository
+                        .update_order_dispatch_status(&dispatch.id, status, Some(&e))
+                        .await?;
+
+                    // The connection most likely dropped; retry on the next pass
+                    break;
+                }
+            }
+        }
+
+        Ok(sent)
+    }
+
+    /// Runs the dispatcher for the given analyzers until the task is dropped
+    pub async fn run(self: Arc<Self>, analyzer_ids: Vec<String>) {
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms);
+        loop {
+            for analyzer_id in &analyzer_ids {
+                if let Err(e) = self.drain(analyzer_id).await {
+                    log::error!("Order dispatcher error for analyzer {}: {}", analyzer_id, e);
+                }
+            }
+
+            let _ = tokio::time::timeout(poll_interval, self.notify.notified()).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+    use crate::models::test_order::{ActionCode, OrderPriority, Test};
+    use chrono::Utc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+
+    /// Analyzer simulator recording the orders it receives
+    #[derive(Default)]
+    struct SimulatedAnalyzer {
+        connected: AtomicBool,
+        received: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl OrderTransport for SimulatedAnalyzer {
+        async fn is_connected(&self, _analyzer_id: &str) -> bool {
+            self.connected.load(Ordering::SeqCst)
+        }
+
+        async fn send_order(&self, _analyzer_id: &str, order: &TestOrder) -> Result<(), String> {
+            self.received.lock().unwrap().push(order.id.clone());
+            Ok(())
+        }
+    }
+
+    fn order(id: &str, priority: OrderPriority) -> TestOrder {
+        TestOrder {
+            id: id.to_string(),
+            sequence_number: 1,
+            specimen_id: format!("S-{}", id),
+            tests: vec![Test {
+                universal_id: "^^^ALB".to_string(),
+                name: "Albumin".to_string(),
+            }],
+            priority,
+            action_code: ActionCode::New,
+            ordering_provider: None,
+            scheduling_info: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_orders_queued_while_disconnected_drain_stat_first() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let analyzer = Arc::new(SimulatedAnalyzer::default());
+        let dispatcher = OrderDispatcher::new(
+            repository.clone(),
+            analyzer.clone(),
+            OrderDispatcherConfig {
+                max_in_flight: 2,
+                ..Default::default()
+            },
+        );
+
+        dispatcher.enqueue("A1", "P1", &order("R1", OrderPriority::Routine)).await.unwrap();
+        dispatcher.enqueue("A1", "P1", &order("R2", OrderPriority::Routine)).await.unwrap();
+        dispatcher.enqueue("A1", "P1", &order("S1", OrderPriority::Stat)).await.unwrap();
+
+        // Disconnected: nothing leaves the queue
+        assert_eq!(dispatcher.drain("A1").await.unwrap(), 0);
+        assert!(repository
+            .list_order_dispatches()
+            .await
+            .unwrap()
+            .iter()
+            .all(|d| d.status == DispatchStatus::Pending));
+
+        // Connected: STAT first, limited by the in-flight window
+        analyzer.connected.store(true, Ordering::SeqCst);
+        assert_eq!(dispatcher.drain("A1").await.unwrap(), 2);
+        assert_eq!(*analyzer.received.lock().unwrap(), vec!["S1", "R1"]);
+        assert_eq!(dispatcher.drain("A1").await.unwrap(), 0);
+
+        // An acknowledgment frees a slot for the remaining order
+        assert!(dispatcher.acknowledge("S1").await.unwrap());
+        assert_eq!(dispatcher.drain("A1").await.unwrap(), 1);
+        assert_eq!(*analyzer.received.lock().unwrap(), vec!["S1", "R1", "R2"]);
+
+        let queue = repository.list_order_dispatches().await.unwrap();
+        let s1 = queue.iter().find(|d| d.order_id == "S1").unwrap();
+        assert_eq!(s1.status, DispatchStatus::Acked);
+        assert_eq!(s1.attempts, 1);
+        let r2 = queue.iter().find(|d| d.order_id == "R2").unwrap();
+        assert_eq!(r2.status, DispatchStatus::Sent);
+    }
+}
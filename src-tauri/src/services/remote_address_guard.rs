@@ -0,0 +1,209 @@
+use chrono::Utc;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::db::SqliteRepository;
+use crate::models::{AnalyzerEventType, HeldMessage, RemoteAddress};
+
+// ============================================================================
+// REMOTE ADDRESS GUARD
+// ============================================================================
+
+/// How a connection's remote address relates to the addresses seen before
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub enum RemoteAddressStatus {
+    /// First address ever seen for the analyzer; learned as trusted
+    Learned,
+    /// Seen before and approved
+    Known,
+    /// Never seen before; an alert is raised once. `held` when the analyzer is in strict mode.
+    New { held: bool },
+    /// Seen before but still awaiting operator approval
+    Pending,
+}
+
+impl RemoteAddressStatus {
+    /// Whether messages from the address are ACKed and archived instead of ingested
+    pub fn holds_ingestion(&self) -> bool {
+        matches!(self, RemoteAddressStatus::New { held: true } | RemoteAddressStatus::Pending)
+    }
+}
+
+/// Tracks the remote IPs each analyzer has connected from, so a different
+/// instrument reaching our listener (e.g. through a misrouted VLAN) is noticed
+/// instead of silently ingested under the analyzer's identity.
+pub struct RemoteAddressGuard {
+    repository: Arc<SqliteRepository>,
+}
+
+impl RemoteAddressGuard {
+    pub fn new(repository: Arc<SqliteRepository>) -> Self {
+        Self { repository }
+    }
+
+    /// Classifies a remote address, learning it on first sight. A new address
+    /// writes an audit row; in strict mode it stays unapproved until [`approve`](Self::approve).
+    pub async fn check(&self, analyzer_id: &str, ip_address: &str, strict: bool) -> Result<RemoteAddressStatus, String> {
+        if let Some(address) = self.repository.get_remote_address(analyzer_id, ip_address).await? {
+            self.repository.touch_remote_address(analyzer_id, ip_address).await?;
+            return Ok(if address.approved || !strict {
+                RemoteAddressStatus::Known
+            } else {
+                RemoteAddressStatus::Pending
+            });
+        }
+
+        let first_address = self.repository.list_remote_addresses(analyzer_id).await?.is_empty();
+        let now = Utc::now();
+        self.repository
+            .save_remote_address(&RemoteAddress {
+                analyzer_id: analyzer_id.to_string(),
+                ip_address: ip_address.to_string(),
+                approved: first_address || !strict,
+                first_seen: now,
+                last_seen: now,
+            })
+            .await?;
+
+        if first_address {
+            log::info!("Learned remote address {} for analyzer {}", ip_address, analyzer_id);
+            return Ok(RemoteAddressStatus::Learned);
+        }
+
+        let held = strict;
+        log::warn!(
+            "Analyzer {} connected from new remote address {}{}",
+            analyzer_id,
+            ip_address,
+            if held { "; holding ingestion until approved" } else { "" }
+        );
+        self.repository
+            .record_analyzer_event(
+                analyzer_id,
+                &AnalyzerEventType::NewRemoteAddress,
+                &format!(
+                    "Connection from new remote address {}{}",
+                    ip_address,
+                    if held { " (held for approval)" } else { "" }
+                ),
+            )
+            .await?;
+
+        Ok(RemoteAddressStatus::New { held })
+    }
+
+    /// Archives a processed message instead of ingesting it
+    pub async fn hold(&self, analyzer_id: &str, ip_address: &str, protocol: &str, payload: String) -> Result<(), String> {
+        self.repository
+            .hold_message(&HeldMessage {
+                id: uuid::Uuid::new_v4().to_string(),
+                analyzer_id: analyzer_id.to_string(),
+                ip_address: ip_address.to_string(),
+                protocol: protocol.to_string(),
+                payload,
+                received_at: Utc::now(),
+            })
+            .await
+    }
+
+    /// Approves an address and returns the messages held for it, oldest first, for replay
+    pub async fn approve(&self, analyzer_id: &str, ip_address: &str) -> Result<Vec<HeldMessage>, String> {
+        if !self.repository.approve_remote_address(analyzer_id, ip_address).await? {
+            return Err(format!(
+                "Remote address {} has never connected for analyzer {}",
+                ip_address, analyzer_id
+            ));
+        }
+
+        log::info!("Approved remote address {} for analyzer {}", ip_address, analyzer_id);
+        self.repository.take_held_messages(analyzer_id, ip_address).await
+    }
+
+    pub async fn list(&self, analyzer_id: &str) -> Result<Vec<RemoteAddress>, String> {
+        self.repository.list_remote_addresses(analyzer_id).await
+    }
+}
+
+/// IP part of a `SocketAddr` string ("10.0.0.5:4001" -> "10.0.0.5"); other input is returned as-is
+pub fn remote_ip(remote_addr: &str) -> String {
+    remote_addr
+        .parse::<SocketAddr>()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|_| remote_addr.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+
+    async fn guard() -> (RemoteAddressGuard, Arc<SqliteRepository>) {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        (RemoteAddressGuard::new(repository.clone()), repository)
+    }
+
+    #[tokio::test]
+    async fn test_first_address_is_learned_and_new_one_alerts() {
+        let (guard, repository) = guard().await;
+
+        assert_eq!(guard.check("A1", "10.0.0.5", false).await.unwrap(), RemoteAddressStatus::Learned);
+        assert_eq!(guard.check("A1", "10.0.0.5", false).await.unwrap(), RemoteAddressStatus::Known);
+        assert_eq!(
+            guard.check("A1", "10.9.9.9", false).await.unwrap(),
+            RemoteAddressStatus::New { held: false }
+        );
+        assert_eq!(guard.check("A1", "10.9.9.9", false).await.unwrap(), RemoteAddressStatus::Known);
+
+        let summary = repository
+            .analyzer_event_summary("A1", Utc::now() - chrono::Duration::hours(1), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(summary.total, 1);
+        assert_eq!(summary.by_type[0].event_type, AnalyzerEventType::NewRemoteAddress);
+        assert!(guard.list("A1").await.unwrap().iter().all(|a| a.approved));
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode_holds_new_address_until_approved() {
+        let (guard, _) = guard().await;
+        guard.check("A1", "10.0.0.5", true).await.unwrap();
+
+        let status = guard.check("A1", "10.9.9.9", true).await.unwrap();
+        assert_eq!(status, RemoteAddressStatus::New { held: true });
+        assert!(status.holds_ingestion());
+        guard.hold("A1", "10.9.9.9", "ASTM", "first".to_string()).await.unwrap();
+
+        // Reconnecting does not alert again but stays held
+        assert_eq!(guard.check("A1", "10.9.9.9", true).await.unwrap(), RemoteAddressStatus::Pending);
+        guard.hold("A1", "10.9.9.9", "ASTM", "second".to_string()).await.unwrap();
+
+        // The trusted address is unaffected
+        assert!(!guard.check("A1", "10.0.0.5", true).await.unwrap().holds_ingestion());
+    }
+
+    #[tokio::test]
+    async fn test_approval_returns_held_messages_for_replay() {
+        let (guard, _) = guard().await;
+        guard.check("A1", "10.0.0.5", true).await.unwrap();
+        guard.check("A1", "10.9.9.9", true).await.unwrap();
+        guard.hold("A1", "10.9.9.9", "ASTM", "first".to_string()).await.unwrap();
+        guard.hold("A1", "10.9.9.9", "ASTM", "second".to_string()).await.unwrap();
+
+        assert!(guard.approve("A1", "10.1.1.1").await.is_err());
+
+        let replay = guard.approve("A1", "10.9.9.9").await.unwrap();
+        let payloads: Vec<&str> = replay.iter().map(|m| m.payload.as_str()).collect();
+        assert_eq!(payloads, vec!["first", "second"]);
+
+        assert_eq!(guard.check("A1", "10.9.9.9", true).await.unwrap(), RemoteAddressStatus::Known);
+        assert!(guard.approve("A1", "10.9.9.9").await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_remote_ip_strips_port() {
+        assert_eq!(remote_ip("10.0.0.5:4001"), "10.0.0.5");
+        assert_eq!(remote_ip("[::1]:4001"), "::1");
+        assert_eq!(remote_ip("10.0.0.5"), "10.0.0.5");
+    }
+}
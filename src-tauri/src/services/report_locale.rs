@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// REPORT LOCALIZATION
+// ============================================================================
+//
+// Printed reports are rendered in the locale of their template (or of the
+// clinic printing them). Only the rendered text is localized; stored values,
+// exports and HIS messages keep their canonical form.
+
+/// Settings store key of the report locale settings
+pub const REPORT_LOCALE_STORE_KEY: &str = "report_locale";
+
+/// Locale every lookup finally falls back to
+pub const FALLBACK_REPORT_LOCALE: &str = "en";
+
+/// Translation bundles shipped with the app, keyed by language
+const BUNDLED_LOCALES: [(&str, &str); 2] = [
+    ("en", include_str!("../../locales/en.json")),
+    ("fr", include_str!("../../locales/fr.json")),
+];
+
+type Bundle = HashMap<String, String>;
+
+fn bundles() -> &'static HashMap<&'static str, Bundle> {
+    static BUNDLES: OnceLock<HashMap<&'static str, Bundle>> = OnceLock::new();
+    BUNDLES.get_or_init(|| {
+        BUNDLED_LOCALES
+            .iter()
+            .filter_map(|(language, json)| match serde_json::from_str::<Bundle>(json) {
+                Ok(bundle) => Some((*language, bundle)),
+                Err(e) => {
+                    log::error!("Ignoring invalid '{}' report translations: {}", language, e);
+                    None
+                }
+            })
+            .collect()
+    })
+}
+
+/// Which locale each report is rendered in
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ReportLocaleSettings {
+    /// Locale of reports with no template or clinic override, e.g. "en" or "fr-FR"
+    pub default_locale: String,
+    /// Locale per report template name
+    pub templates: HashMap<String, String>,
+    /// Locale per clinic profile, used when the template has no override
+    pub clinics: HashMap<String, String>,
+}
+
+impl Default for ReportLocaleSettings {
+    fn default() -> Self {
+        Self {
+            default_locale: FALLBACK_REPORT_LOCALE.to_string(),
+            templates: HashMap::new(),
+            clinics: HashMap::new(),
+        }
+    }
+}
+
+impl ReportLocaleSettings {
+    /// Locale for `template` printed at `clinic`: template, then clinic, then the default
+    pub fn locale_for(&self, template: &str, clinic: Option<&str>) -> ReportLocale {
+        let tag = self
+            .templates
+            .get(template)
+            .or_else(|| clinic.and_then(|clinic| self.clinics.get(clinic)))
+            .unwrap_or(&self.default_locale);
+        ReportLocale::new(tag)
+    }
+}
+
+/// Reads stored settings, falling back to English when missing or invalid
+pub fn report_locale_from_store(stored: Option<serde_json::Value>) -> ReportLocaleSettings {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid report locale settings: {}", e);
+            ReportLocaleSettings::default()
+        }),
+        None => ReportLocaleSettings::default(),
+    }
+}
+
+/// A resolved locale: translations are looked up along the chain
+/// full tag -> language -> English, and a key missing everywhere renders as itself
+#[derive(Debug, Clone)]
+pub struct ReportLocale {
+    tag: String,
+    chain: Vec<&'static Bundle>,
+}
+
+impl ReportLocale {
+    pub fn new(tag: &str) -> Self {
+        let tag = tag.trim().replace('_', "-");
+        let language = tag.split('-').next().unwrap_or_default().to_lowercase();
+
+        let mut chain: Vec<&'static Bundle> = Vec::new();
+        for candidate in [tag.to_lowercase(), language, FALLBACK_REPORT_LOCALE.to_string()] {
+            if let Some(bundle) = bundles().get(candidate.as_str()) {
+                if !chain.iter().any(|b| std::ptr::eq(*b, bundle)) {
+                    chain.push(bundle);
+                }
+            }
+        }
+
+        Self { tag, chain }
+    }
+
+    /// Locale tag as configured
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Translated text for `key`
+    pub fn text(&self, key: &str) -> String {
+        self.chain
+            .iter()
+            .find_map(|bundle| bundle.get(key))
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    pub fn format_date(&self, at: DateTime<Utc>) -> String {
+        at.format(&self.text("format.date")).to_string()
+    }
+
+    pub fn format_datetime(&self, at: DateTime<Utc>) -> String {
+        at.format(&self.text("format.datetime")).to_string()
+    }
+
+    /// A displayed value with the locale's decimal separator; text that is not a
+    /// number (optionally prefixed by `<`, `>` or `=`) is returned unchanged
+    pub fn format_number(&self, value: &str) -> String {
+        let separator = self.text("format.decimal_separator");
+        let numeric = value.trim().trim_start_matches(['<', '>', '=']).trim();
+        if separator == "." || numeric.parse::<f64>().is_err() {
+            return value.to_string();
+        }
+        value.replacen('.', &separator, 1)
+    }
+
+    pub fn format_decimal(&self, value: f64) -> String {
+        self.format_number(&value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundles_have_the_same_keys() {
+        let en = &bundles()["en"];
+        let fr = &bundles()["fr"];
+        let mut missing: Vec<&String> = en.keys().filter(|key| !fr.contains_key(*key)).collect();
+        missing.sort();
+        assert!(missing.is_empty(), "Missing French translations: {:?}", missing);
+    }
+
+    #[test]
+    fn test_fallback_chain() {
+        // Region falls back to its language, unknown languages to English
+        assert_eq!(ReportLocale::new("fr-CA").text("column.result"), "Résultat");
+        assert_eq!(ReportLocale::new("fr_FR").text("column.result"), "Résultat");
+        assert_eq!(ReportLocale::new("de").text("column.result"), "Result");
+        // A key missing from every bundle renders as the key
+        assert_eq!(ReportLocale::new("fr").text("report.no_such_key"), "report.no_such_key");
+    }
+
+    #[test]
+    fn test_decimal_separator_only_changes_numbers() {
+        let fr = ReportLocale::new("fr");
+        assert_eq!(fr.format_number("5.4"), "5,4");
+        assert_eq!(fr.format_number("<0.5"), "<0,5");
+        assert_eq!(fr.format_number("12"), "12");
+        assert_eq!(fr.format_number("Positive 1.5x"), "Positive 1.5x");
+        assert_eq!(ReportLocale::new("en").format_number("5.4"), "5.4");
+    }
+
+    #[test]
+    fn test_template_override_wins_over_clinic() {
+        let settings = ReportLocaleSettings {
+            templates: HashMap::from([("sample_results".to_string(), "fr".to_string())]),
+            clinics: HashMap::from([("satellite".to_string(), "fr-FR".to_string())]),
+            ..Default::default()
+        };
+        assert_eq!(settings.locale_for("sample_results", None).tag(), "fr");
+        assert_eq!(settings.locale_for("other", Some("satellite")).tag(), "fr-FR");
+        assert_eq!(settings.locale_for("other", Some("main")).tag(), "en");
+    }
+}
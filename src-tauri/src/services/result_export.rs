@@ -0,0 +1,376 @@
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+
+use crate::db::SqliteRepository;
+use crate::models::TestResult;
+use crate::protocol::message_profile::MessageProfile;
+use crate::services::app_paths::ensure_parent_dir;
+use crate::services::his_client::HisClient;
+
+/// Results fetched from the database per round trip; only one page is held in memory
+const EXPORT_PAGE_SIZE: u32 = 500;
+
+const CSV_HEADER: &str = "patient_id,sample_id,test_id,value,units,reference_lower,reference_upper,\
+abnormal_flag,status,completed_date_time,analyzer_id,instrument,operator";
+
+// ============================================================================
+// EXPORT CONFIGURATION
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One row per result
+    Csv,
+    /// One ORU^R01 per sample, rendered with the given message profile
+    Hl7,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportRequest {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub format: ExportFormat,
+    /// Only this patient's results, streamed row by row rather than paged by date
+    #[serde(default)]
+    pub patient_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub results_exported: usize,
+    /// HL7 messages written (HL7 exports only)
+    pub messages_written: usize,
+}
+
+// ============================================================================
+// EXPORT
+// ============================================================================
+
+/// Exports results in a date range to a file, streaming page by page
+pub async fn export_results(
+    repository: &SqliteRepository,
+    his_client: &HisClient,
+    request: &ExportRequest,
+    profile: &MessageProfile,
+    path: &Path,
+) -> Result<ExportSummary, String> {
+    ensure_parent_dir(path)?;
+    let file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut writer = BufWriter::new(file);
+
+    let summary = export_results_to_writer(repository, his_client, request, profile, &mut writer).await?;
+    log::info!(
+        "Exported {} results ({:?}) to {}",
+        summary.results_exported,
+        request.format,
+        path.display()
+    );
+    Ok(summary)
+}
+
+/// Exports results in a date range to any writer; split out so tests need no file
+pub async fn export_results_to_writer<W: AsyncWrite + Unpin>(
+    repository: &SqliteRepository,
+    his_client: &HisClient,
+    request: &ExportRequest,
+    profile: &MessageProfile,
+    writer: &mut W,
+) -> Result<ExportSummary, String> {
+    if request.from > request.to {
+        return Err("Export range starts after it ends".to_string());
+    }
+
+    let mut export = ExportWriter {
+        writer,
+        his_client,
+        profile,
+        format: request.format,
+        summary: ExportSummary::default(),
+        sample_group: Vec::new(),
+    };
+    if request.format == ExportFormat::Csv {
+        write_text(export.writer, &format!("{}\n", CSV_HEADER)).await?;
+    }
+
+    if let Some(patient_id) = &request.patient_id {
+        let mut results = repository.stream_patient_results(patient_id);
+        while let Some(result) = results.next().await {
+            let result = result?;
+            let at = result.completed_date_time.unwrap_or(result.created_at);
+            if at >= request.from && at <= request.to {
+                export.write(patient_id.clone(), result).await?;
+            }
+        }
+    } else {
+        let mut after_rowid = 0;
+        loop {
+            let page = repository
+                .get_results_page_in_range(request.from, request.to, after_rowid, EXPORT_PAGE_SIZE)
+                .await?;
+            let Some((last_rowid, _, _)) = page.last() else {
+                break;
+            };
+            after_rowid = *last_rowid;
+
+            for (_, patient_id, result) in page {
+                export.write(patient_id, result).await?;
+            }
+        }
+    }
+
+    export.finish().await
+}
+
+/// Writes results one at a time in the requested format
+struct ExportWriter<'a, W> {
+    writer: &'a mut W,
+    his_client: &'a HisClient,
+    profile: &'a MessageProfile,
+    format: ExportFormat,
+    summary: ExportSummary,
+    /// Consecutive results of one sample form one ORU; a sample can straddle pages
+    sample_group: Vec<(String, TestResult)>,
+}
+
+impl<W: AsyncWrite + Unpin> ExportWriter<'_, W> {
+    async fn write(&mut self, patient_id: String, result: TestResult) -> Result<(), String> {
+        self.summary.results_exported += 1;
+        match self.format {
+            ExportFormat::Csv => write_text(self.writer, &csv_row(&patient_id, &result)).await,
+            ExportFormat::Hl7 => {
+                if self
+                    .sample_group
+                    .first()
+                    .is_some_and(|(_, first)| first.sample_id != result.sample_id)
+                {
+                    let group = std::mem::take(&mut self.sample_group);
+                    write_oru(self.writer, self.his_client, self.profile, &group).await?;
+                    self.summary.messages_written += 1;
+                }
+                self.sample_group.push((patient_id, result));
+                Ok(())
+            }
+        }
+    }
+
+    async fn finish(self) -> Result<ExportSummary, String> {
+        let mut summary = self.summary;
+        if !self.sample_group.is_empty() {
+            write_oru(self.writer, self.his_client, self.profile, &self.sample_group).await?;
+            summary.messages_written += 1;
+        }
+
+        self.writer
+            .flush()
+            .await
+            .map_err(|e| format!("Failed to flush export: {}", e))?;
+        Ok(summary)
+    }
+}
+
+async fn write_text<W: AsyncWrite + Unpin>(writer: &mut W, text: &str) -> Result<(), String> {
+    writer
+        .write_all(text.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write export: {}", e))
+}
+
+/// Writes one sample's results as an ORU^R01, messages separated by a newline
+async fn write_oru<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    his_client: &HisClient,
+    profile: &MessageProfile,
+    group: &[(String, TestResult)],
+) -> Result<(), String> {
+    let Some((patient_id, first)) = group.first() else {
+        return Ok(());
+    };
+    let results: Vec<TestResult> = group.iter().map(|(_, result)| result.clone()).collect();
+    let message = his_client.build_oru_message(profile, &Utc::now(), Some(patient_id), &first.sample_id, &results, &[]);
+    write_text(writer, &format!("{}\n", message)).await
+}
+
+fn csv_row(patient_id: &str, result: &TestResult) -> String {
+    let range = result.reference_range.as_ref();
+    let fields = [
+        patient_id.to_string(),
+        result.sample_id.clone(),
+        result.test_id.clone(),
+        result.value.clone(),
+        result.units.clone().unwrap_or_default(),
+        range.and_then(|r| r.lower_limit).map(|v| v.to_string()).unwrap_or_default(),
+        range.and_then(|r| r.upper_limit).map(|v| v.to_string()).unwrap_or_default(),
+        result
+            .flags
+            .as_ref()
+            .and_then(|f| f.abnormal_flag.clone())
+            .unwrap_or_default(),
+        result.status.as_db_str().to_string(),
+        result.completed_date_time.map(|t| t.to_rfc3339()).unwrap_or_default(),
+        result.analyzer_id.clone().unwrap_or_default(),
+        result.metadata.instrument.clone().unwrap_or_default(),
+        result.metadata.operator.clone().unwrap_or_default(),
+    ];
+
+    let escaped: Vec<String> = fields.iter().map(|field| csv_escape(field)).collect();
+    format!("{}\n", escaped.join(","))
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+    use crate::models::patient::{PatientName, Sex};
+    use crate::models::result::{ResultFlags, TestResultMetadata};
+    use crate::models::{DataSource, Patient};
+    use crate::test_support::dated_result;
+    use chrono::TimeZone;
+
+    fn result(id: &str, sample_id: &str, test_id: &str, value: &str, day: u32) -> TestResult {
+        let result = dated_result(id, sample_id, value, day);
+        TestResult {
+            test_id: test_id.to_string(),
+            flags: Some(ResultFlags {
+                abnormal_flag: Some("N".to_string()),
+                nature_of_abnormality: None,
+            }),
+            metadata: TestResultMetadata {
+                operator: Some("OP, 1".to_string()),
+                ..result.metadata.clone()
+            },
+            ..result
+        }
+    }
+
+    async fn seeded_repository() -> SqliteRepository {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let patient = Patient {
+            name: PatientName {
+                first_name: Some("Anita".to_string()),
+                ..Default::default()
+            },
+            sex: Sex::Female,
+            ..Patient::fixture("P1")
+        };
+        repository.save_patient(&patient, &DataSource::Analyzer).await.unwrap();
+
+        for stored in [
+            result("R1", "S1", "GLU", "95", 10),
+            result("R2", "S1", "ALB", "4.1", 10),
+            result("R3", "S2", "GLU", "130", 11),
+            result("R4", "S3", "GLU", "88", 20),
+        ] {
+            repository
+                .save_test_result(&stored, "P1", &DataSource::Analyzer)
+                .await
+                .unwrap();
+        }
+        repository
+    }
+
+    fn request(format: ExportFormat) -> ExportRequest {
+        ExportRequest {
+            from: Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            to: Utc.with_ymd_and_hms(2025, 1, 15, 0, 0, 0).unwrap(),
+            format,
+            patient_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_csv_export_writes_header_and_rows_in_range() {
+        let repository = seeded_repository().await;
+        let mut output = Vec::new();
+
+        let summary = export_results_to_writer(
+            &repository,
+            &HisClient::with_default_config(),
+            &request(ExportFormat::Csv),
+            &MessageProfile::default(),
+            &mut output,
+        )
+        .await
+        .unwrap();
+        assert_eq!(summary.results_exported, 3);
+
+        let csv = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            "P1,S1,GLU,95,mg/dL,70,110,N,F,2025-01-10T10:00:00+00:00,A1,,\"OP, 1\""
+        );
+        assert!(lines[2].starts_with("P1,S1,ALB,4.1,"));
+        assert!(lines[3].starts_with("P1,S2,GLU,130,"));
+        assert!(!csv.contains("S3"));
+    }
+
+    #[tokio::test]
+    async fn test_hl7_export_writes_one_message_per_sample() {
+        let repository = seeded_repository().await;
+        let mut output = Vec::new();
+
+        let summary = export_results_to_writer(
+            &repository,
+            &HisClient::with_default_config(),
+            &request(ExportFormat::Hl7),
+            &MessageProfile::default(),
+            &mut output,
+        )
+        .await
+        .unwrap();
+        assert_eq!(summary.results_exported, 3);
+        assert_eq!(summary.messages_written, 2);
+
+        let hl7 = String::from_utf8(output).unwrap();
+        assert_eq!(hl7.matches("MSH|").count(), 2);
+        assert_eq!(hl7.matches("OBX|").count(), 3);
+        assert!(hl7.contains("PID|1||P1"));
+    }
+
+    #[tokio::test]
+    async fn test_patient_export_streams_only_that_patient_in_range() {
+        let repository = seeded_repository().await;
+        let other = result("R5", "S9", "GLU", "101", 10);
+        repository
+            .save_test_result(&other, "P2", &DataSource::Analyzer)
+            .await
+            .unwrap();
+        let mut output = Vec::new();
+
+        let summary = export_results_to_writer(
+            &repository,
+            &HisClient::with_default_config(),
+            &ExportRequest {
+                patient_id: Some("P1".to_string()),
+                ..request(ExportFormat::Hl7)
+            },
+            &MessageProfile::default(),
+            &mut output,
+        )
+        .await
+        .unwrap();
+        assert_eq!(summary.results_exported, 3);
+        assert_eq!(summary.messages_written, 2);
+
+        let hl7 = String::from_utf8(output).unwrap();
+        assert!(!hl7.contains("S9"));
+        assert!(!hl7.contains("S3"));
+    }
+}
@@ -0,0 +1,363 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+
+use crate::db::SqliteRepository;
+
+/// Key in the app settings store (`settings.json`) holding the result feed listener settings
+pub const RESULT_FEED_STORE_KEY: &str = "result_feed";
+
+/// Most changes returned by one page of the feed
+pub const MAX_RESULT_CHANGES_PAGE: u32 = 1000;
+
+/// Changes returned when the consumer asks for no particular page size
+pub const DEFAULT_RESULT_CHANGES_PAGE: u32 = 100;
+
+/// Shortest token the listener accepts
+const MIN_TOKEN_LENGTH: usize = 16;
+
+/// Largest request head read before the request is refused
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+/// How long a client may take to send its request, and to take the response
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Consumers served at once; connections beyond it are closed unanswered
+const MAX_FEED_CONNECTIONS: usize = 16;
+
+/// Read-only HTTP access to the result change feed for consumers outside the app,
+/// e.g. a departmental dashboard. Off unless enabled with a static token.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ResultFeedSettings {
+    pub enabled: bool,
+    /// Address and port the listener binds. Loopback by default; set e.g. `0.0.0.0:8686`
+    /// to serve consumers on other machines
+    pub listen_address: String,
+    /// Sent by consumers as `Authorization: Bearer <token>`
+    pub token: String,
+}
+
+impl Default for ResultFeedSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_address: "127.0.0.1:8686".to_string(),
+            token: String::new(),
+        }
+    }
+}
+
+/// Reads the stored settings, falling back to the default (off) when missing or invalid
+pub fn result_feed_from_store(stored: Option<serde_json::Value>) -> ResultFeedSettings {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid result feed settings: {}", e);
+            ResultFeedSettings::default()
+        }),
+        None => ResultFeedSettings::default(),
+    }
+}
+
+/// Binds the feed listener and serves it in the background. Returns the bound
+/// address, or `None` when the feed is off.
+pub async fn start_result_feed(
+    repository: Arc<SqliteRepository>,
+    settings: &ResultFeedSettings,
+) -> Result<Option<std::net::SocketAddr>, String> {
+    if !settings.enabled {
+        return Ok(None);
+    }
+    if settings.token.trim().len() < MIN_TOKEN_LENGTH {
+        return Err(format!(
+            "The result feed needs a token of at least {} characters",
+            MIN_TOKEN_LENGTH
+        ));
+    }
+
+    let listener = TcpListener::bind(&settings.listen_address)
+        .await
+        .map_err(|e| format!("Failed to bind result feed on {}: {}", settings.listen_address, e))?;
+    let address = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to read result feed address: {}", e))?;
+    log::info!("Serving the result change feed on {}", address);
+
+    tokio::spawn(serve(listener, repository, settings.token.trim().to_string()));
+    Ok(Some(address))
+}
+
+/// Accepts feed consumers until the listener fails, serving at most
+/// `MAX_FEED_CONNECTIONS` at once
+async fn serve(listener: TcpListener, repository: Arc<SqliteRepository>, token: String) {
+    let token = Arc::new(token);
+    let connections = Arc::new(Semaphore::new(MAX_FEED_CONNECTIONS));
+    loop {
+        match listener.accept().await {
+            Ok((stream, remote)) => {
+                let Ok(permit) = connections.clone().try_acquire_owned() else {
+                    log::warn!(
+                        "Result feed refused {}: {} consumers are already being served",
+                        remote,
+                        MAX_FEED_CONNECTIONS
+                    );
+                    continue;
+                };
+                let repository = repository.clone();
+                let token = token.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, &repository, &token).await {
+                        log::warn!("Result feed request from {} failed: {}", remote, e);
+                    }
+                    drop(permit);
+                });
+            }
+            Err(e) => {
+                log::error!("Result feed listener stopped: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Answers one request and closes the connection
+async fn handle_connection(mut stream: TcpStream, repository: &SqliteRepository, token: &str) -> Result<(), String> {
+    let head = tokio::time::timeout(REQUEST_TIMEOUT, read_request_head(&mut stream))
+        .await
+        .map_err(|_| "Timed out reading the request".to_string())??;
+    let (status, body) = match head {
+        Some(head) => respond(repository, token, &head).await,
+        None => (431, error_body("Request head too large")),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason_phrase(status),
+        body.len(),
+        body
+    );
+    tokio::time::timeout(REQUEST_TIMEOUT, stream.write_all(response.as_bytes()))
+        .await
+        .map_err(|_| "Timed out writing the response".to_string())?
+        .map_err(|e| format!("Failed to write response: {}", e))?;
+    stream.shutdown().await.map_err(|e| format!("Failed to close connection: {}", e))
+}
+
+/// Reads up to the blank line ending the request head; `None` when it is too large
+async fn read_request_head(stream: &mut TcpStream) -> Result<Option<String>, String> {
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_HEAD {
+            return Ok(None);
+        }
+        let read = stream
+            .read(&mut buffer)
+            .await
+            .map_err(|e| format!("Failed to read request: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    Ok(Some(String::from_utf8_lossy(&head).into_owned()))
+}
+
+/// Status code and JSON body for a request head. Only `GET /results/changes` with
+/// the right bearer token is served; `since_seq` and `limit` work as in the command.
+async fn respond(repository: &SqliteRepository, token: &str, head: &str) -> (u16, String) {
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (method, target) = (request_line.next().unwrap_or_default(), request_line.next().unwrap_or_default());
+
+    let authorized = lines
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(name, _)| name.trim().eq_ignore_ascii_case("authorization"))
+        .filter_map(|(_, value)| value.trim().strip_prefix("Bearer "))
+        .any(|presented| tokens_match(presented.trim(), token));
+    if !authorized {
+        return (401, error_body("Missing or invalid token"));
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if path != "/results/changes" {
+        return (404, error_body("Not found"));
+    }
+    if method != "GET" {
+        return (405, error_body("The result feed is read-only"));
+    }
+
+    let mut since_seq: i64 = 0;
+    let mut limit = DEFAULT_RESULT_CHANGES_PAGE;
+    for (name, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        let parsed = match name {
+            "since_seq" => value.parse().map(|value| since_seq = value).is_ok(),
+            "limit" => value.parse().map(|value| limit = value).is_ok(),
+            _ => true,
+        };
+        if !parsed {
+            return (400, error_body(&format!("Invalid {}: {}", name, value)));
+        }
+    }
+
+    match repository
+        .get_result_changes(since_seq, limit.clamp(1, MAX_RESULT_CHANGES_PAGE))
+        .await
+    {
+        Ok(page) => match serde_json::to_string(&page) {
+            Ok(body) => (200, body),
+            Err(e) => (500, error_body(&format!("Failed to serialize result changes: {}", e))),
+        },
+        Err(e) => {
+            log::error!("Result feed query failed: {}", e);
+            (500, error_body("Failed to read result changes"))
+        }
+    }
+}
+
+/// Compares tokens without stopping at the first differing byte
+fn tokens_match(presented: &str, expected: &str) -> bool {
+    presented.len() == expected.len()
+        && presented
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+    use crate::models::{DataSource, ResultChangePage, TestResult};
+
+    const TOKEN: &str = "feed-token-0123456789";
+
+    fn request(target: &str, token: Option<&str>) -> String {
+        let authorization = token.map(|token| format!("Authorization: Bearer {}\r\n", token)).unwrap_or_default();
+        format!("GET {} HTTP/1.1\r\nHost: lis\r\n{}\r\n", target, authorization)
+    }
+
+    #[tokio::test]
+    async fn test_feed_is_served_over_http_only_with_the_token() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        for id in ["R1", "R2", "R3"] {
+            let result = TestResult {
+                sample_id: format!("S-{}", id),
+                ..TestResult::fixture(id)
+            };
+            repository.save_test_result(&result, "P1", &DataSource::Analyzer).await.unwrap();
+        }
+
+        let (status, _) = respond(&repository, TOKEN, &request("/results/changes", None)).await;
+        assert_eq!(status, 401);
+        let (status, _) = respond(&repository, TOKEN, &request("/results/changes", Some("feed-token-9876543210"))).await;
+        assert_eq!(status, 401);
+        let (status, _) = respond(&repository, TOKEN, &request("/results", Some(TOKEN))).await;
+        assert_eq!(status, 404);
+        let post = request("/results/changes", Some(TOKEN)).replacen("GET", "POST", 1);
+        assert_eq!(respond(&repository, TOKEN, &post).await.0, 405);
+        let (status, _) = respond(&repository, TOKEN, &request("/results/changes?limit=many", Some(TOKEN))).await;
+        assert_eq!(status, 400);
+
+        let (status, body) = respond(&repository, TOKEN, &request("/results/changes?since_seq=0&limit=2", Some(TOKEN))).await;
+        assert_eq!(status, 200);
+        let page: ResultChangePage = serde_json::from_str(&body).unwrap();
+        let ids: Vec<&str> = page.changes.iter().map(|change| change.result_id.as_str()).collect();
+        assert_eq!(ids, vec!["R1", "R2"]);
+
+        // Over a real socket, resuming from the returned cursor
+        let settings = ResultFeedSettings {
+            enabled: true,
+            listen_address: "127.0.0.1:0".to_string(),
+            token: TOKEN.to_string(),
+        };
+        let address = start_result_feed(repository.clone(), &settings).await.unwrap().unwrap();
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        let target = format!("/results/changes?since_seq={}", page.next_seq);
+        stream.write_all(request(&target, Some(TOKEN)).as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let page: ResultChangePage = serde_json::from_str(body).unwrap();
+        let ids: Vec<&str> = page.changes.iter().map(|change| change.result_id.as_str()).collect();
+        assert_eq!(ids, vec!["R3"]);
+    }
+
+    #[tokio::test]
+    async fn test_feed_stays_off_without_a_usable_token() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        assert_eq!(result_feed_from_store(None), ResultFeedSettings::default());
+        assert!(start_result_feed(repository.clone(), &ResultFeedSettings::default())
+            .await
+            .unwrap()
+            .is_none());
+
+        let settings = ResultFeedSettings {
+            enabled: true,
+            listen_address: "127.0.0.1:0".to_string(),
+            token: "short".to_string(),
+        };
+        assert!(start_result_feed(repository, &settings).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_feed_listens_on_loopback_and_caps_concurrent_consumers() {
+        assert!(ResultFeedSettings::default().listen_address.starts_with("127.0.0.1:"));
+
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let settings = ResultFeedSettings {
+            enabled: true,
+            listen_address: "127.0.0.1:0".to_string(),
+            token: TOKEN.to_string(),
+        };
+        let address = start_result_feed(repository, &settings).await.unwrap().unwrap();
+
+        // Consumers that never send their request hold every slot
+        let mut idle = Vec::new();
+        for _ in 0..MAX_FEED_CONNECTIONS {
+            idle.push(TcpStream::connect(address).await.unwrap());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // One more is closed without an answer
+        let mut refused = TcpStream::connect(address).await.unwrap();
+        let mut response = String::new();
+        tokio::time::timeout(Duration::from_secs(1), refused.read_to_string(&mut response))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(response.is_empty());
+
+        // A slot frees up once a consumer is done
+        drop(idle.pop());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream.write_all(request("/results/changes", Some(TOKEN)).as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::Path;
+
+use chrono::Utc;
+
+use crate::db::SqliteRepository;
+use crate::models::{ResultImage, TestResult};
+use crate::protocol::ed_image::decode_ed_png;
+use crate::protocol::hl7_parser::{extract_parameter_code, is_histogram_parameter};
+
+// ============================================================================
+// RESULT IMAGES
+// ============================================================================
+//
+// Histograms and scattergrams arrive as base64 PNGs in the value of their
+// result. At ingestion each one is written to the images directory and
+// recorded in `result_images`, which the result detail and the sample report
+// read. The result keeps its value as received.
+
+/// Directory, under the app data dir, the decoded images are written to
+pub const RESULT_IMAGES_DIR: &str = "result_images";
+
+/// Kind recorded for an image parameter: the DIFF and BASO graphs are scattergrams
+fn image_kind(parameter_code: &str) -> &'static str {
+    match parameter_code {
+        "2033" | "2034" => "scattergram",
+        _ => "histogram",
+    }
+}
+
+/// Decodes the image results among `results`, writes each PNG to `dir` and records
+/// it against its result. Results that are not images are skipped; an image that
+/// does not decode is logged and skipped. Returns how many images were stored.
+pub async fn store_result_images(
+    repository: &SqliteRepository,
+    dir: &Path,
+    results: &[TestResult],
+) -> Result<usize, String> {
+    let mut stored = 0;
+    for result in results {
+        let parameter_code = extract_parameter_code(result.test_id.trim_start_matches('^'));
+        if !is_histogram_parameter(&parameter_code) {
+            continue;
+        }
+        let png = match decode_ed_png(&result.value) {
+            Ok(png) => png,
+            Err(e) => {
+                log::warn!("Not storing image of result {}: {}", result.id, e);
+                continue;
+            }
+        };
+
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| format!("Failed to create directory {}: {}", dir.display(), e))?;
+        // Named after the image record, never after analyzer-supplied ids
+        let id = uuid::Uuid::new_v4().to_string();
+        let path = dir.join(format!("{}.png", id));
+        tokio::fs::write(&path, &png)
+            .await
+            .map_err(|e| format!("Failed to write image {}: {}", path.display(), e))?;
+
+        repository
+            .save_result_image(&ResultImage {
+                id,
+                result_id: result.id.clone(),
+                kind: image_kind(&parameter_code).to_string(),
+                mime_type: "image/png".to_string(),
+                reference: path.to_string_lossy().into_owned(),
+                created_at: Utc::now(),
+            })
+            .await?;
+        stored += 1;
+    }
+    Ok(stored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+    use crate::models::DataSource;
+
+    /// 1x1 PNG
+    const PNG_BASE64: &str =
+        "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+
+    #[tokio::test]
+    async fn test_image_results_are_written_and_recorded() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let dir = std::env::temp_dir().join(format!("result-images-{}", uuid::Uuid::new_v4()));
+        let result = |id: &str, test_id: &str, value: String| TestResult {
+            test_id: test_id.to_string(),
+            sample_id: "S1".to_string(),
+            value,
+            ..TestResult::fixture(id)
+        };
+        let results = vec![
+            result("R1", "6690-2^WBC^LN", "7.2".to_string()),
+            result("R2", "2101^RBCHistogram.PNG^LOCAL", format!("^Image^PNG^Base64^{}", PNG_BASE64)),
+            result("R3", "2034^DIFFScattergram.PNG^LOCAL", format!("^Image^PNG^Base64^{}", PNG_BASE64)),
+            result("R4", "2102^PLTHistogram.PNG^LOCAL", "^Image^PNG^Base64^iVBORw0K".to_string()),
+        ];
+        for result in &results {
+            repository.save_test_result(result, "P1", &DataSource::Analyzer).await.unwrap();
+        }
+
+        assert_eq!(store_result_images(&repository, &dir, &results).await.unwrap(), 2);
+
+        let images = repository.get_sample_images("S1").await.unwrap();
+        let stored: Vec<(&str, &str)> = images
+            .iter()
+            .map(|image| (image.result_id.as_str(), image.kind.as_str()))
+            .collect();
+        assert_eq!(stored, vec![("R2", "histogram"), ("R3", "scattergram")]);
+        for image in &images {
+            assert!(Path::new(&image.reference).starts_with(&dir));
+            assert_eq!(tokio::fs::read(&image.reference).await.unwrap().len(), 70);
+        }
+
+        tokio::fs::remove_dir_all(dir).await.unwrap();
+    }
+}
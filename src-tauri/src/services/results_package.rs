@@ -0,0 +1,547 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::db::SqliteRepository;
+use crate::models::patient::Sex;
+use crate::models::results_package::{RESULTS_PACKAGE_FORMAT, RESULTS_PACKAGE_VERSION};
+use crate::models::{
+    CandidateStatus, DuplicateCandidate, EntityImportCounts, PackageContent, PackageImportReport, PackageManifest,
+    PackagedResult, Patient, PatientImport, RemappedPatient, ResultImport,
+};
+use crate::services::app_paths::ensure_parent_dir;
+use crate::services::duplicate_detection::{normalize_name, score_pair, DuplicateDetectionSettings};
+
+/// Results fetched from the database per round trip while exporting
+const EXPORT_PAGE_SIZE: u32 = 500;
+/// Rows applied per transaction while importing
+const IMPORT_BATCH_SIZE: usize = 200;
+/// Reason recorded on the duplicate candidate of a remapped patient
+const ID_CONFLICT_REASON: &str = "package_id_conflict";
+
+// ============================================================================
+// EXPORT
+// ============================================================================
+
+/// Writes the results completed between `from` and `to`, and their patients, to
+/// a package file for carrying to another LIS instance.
+///
+/// A package is gzip-compressed: one line of JSON manifest, then the JSON content
+/// whose SHA-256 the manifest records.
+pub async fn export_results_package(
+    repository: &SqliteRepository,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    path: &Path,
+) -> Result<PackageManifest, String> {
+    let (manifest, bytes) = build_results_package(repository, from, to).await?;
+    ensure_parent_dir(path)?;
+    tokio::fs::write(path, bytes)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    log::info!(
+        "Exported package of {} results for {} patients to {}",
+        manifest.results,
+        manifest.patients,
+        path.display()
+    );
+    Ok(manifest)
+}
+
+/// Builds a package in memory; split out so tests need no file
+pub async fn build_results_package(
+    repository: &SqliteRepository,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<(PackageManifest, Vec<u8>), String> {
+    if from > to {
+        return Err("Export range starts after it ends".to_string());
+    }
+
+    let mut content = PackageContent::default();
+    let mut patient_ids = BTreeSet::new();
+    let mut after_rowid = 0;
+    loop {
+        let page = repository
+            .get_results_page_in_range(from, to, after_rowid, EXPORT_PAGE_SIZE)
+            .await?;
+        let Some((last_rowid, _, _)) = page.last() else {
+            break;
+        };
+        after_rowid = *last_rowid;
+
+        for (_, patient_id, result) in page {
+            patient_ids.insert(patient_id.clone());
+            content.results.push(PackagedResult { patient_id, result });
+        }
+    }
+    for patient_id in patient_ids {
+        match repository.get_patient(&patient_id).await? {
+            Some(patient) => content.patients.push(patient),
+            None => return Err(format!("Patient {} of exported results not found", patient_id)),
+        }
+    }
+
+    let content_bytes = serde_json::to_vec(&content)
+        .map_err(|e| format!("Failed to serialize package content: {}", e))?;
+    let manifest = PackageManifest {
+        format: RESULTS_PACKAGE_FORMAT.to_string(),
+        version: RESULTS_PACKAGE_VERSION,
+        exported_at: Utc::now(),
+        from,
+        to,
+        patients: content.patients.len(),
+        samples: content
+            .results
+            .iter()
+            .map(|packaged| packaged.result.sample_id.as_str())
+            .collect::<HashSet<_>>()
+            .len(),
+        results: content.results.len(),
+        content_sha256: sha256_hex(&content_bytes),
+    };
+
+    let bytes = encode_package(&manifest, &content_bytes)?;
+    Ok((manifest, bytes))
+}
+
+fn encode_package(manifest: &PackageManifest, content: &[u8]) -> Result<Vec<u8>, String> {
+    let manifest = serde_json::to_vec(manifest)
+        .map_err(|e| format!("Failed to serialize package manifest: {}", e))?;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(&manifest)
+        .and_then(|_| encoder.write_all(b"\n"))
+        .and_then(|_| encoder.write_all(content))
+        .map_err(|e| format!("Failed to compress package: {}", e))?;
+    encoder
+        .finish()
+        .map_err(|e| format!("Failed to compress package: {}", e))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// ============================================================================
+// IMPORT
+// ============================================================================
+
+/// Imports a package produced by [`export_results_package`].
+///
+/// The format, version and content hash are checked before anything is written.
+/// Patients are imported before results, each in batches applied in one transaction;
+/// a failing batch is rolled back and stops the import, with the counts of the
+/// batches already committed reported. Imported records are tagged `source = imported`.
+pub async fn import_results_package(
+    repository: &SqliteRepository,
+    path: &Path,
+) -> Result<PackageImportReport, String> {
+    let bytes = tokio::fs::read(path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    log::info!("Importing results package {}", path.display());
+    import_results_package_bytes(repository, &bytes).await
+}
+
+/// Imports a package from memory
+pub async fn import_results_package_bytes(
+    repository: &SqliteRepository,
+    bytes: &[u8],
+) -> Result<PackageImportReport, String> {
+    let (manifest, content) = decode_package(bytes)?;
+    let mut report = PackageImportReport {
+        manifest,
+        patients: EntityImportCounts::default(),
+        results: EntityImportCounts::default(),
+        remapped_patients: Vec::new(),
+        error: None,
+    };
+
+    if let Err(e) = import_patients(repository, &content.patients, &mut report).await {
+        log::error!("Results package import stopped while importing patients: {}", e);
+        report.error = Some(e);
+        return Ok(report);
+    }
+    if let Err(e) = import_results(repository, content.results, &mut report).await {
+        log::error!("Results package import stopped while importing results: {}", e);
+        report.error = Some(e);
+        return Ok(report);
+    }
+
+    log::info!(
+        "Imported results package: patients {:?}, results {:?}, {} patients remapped",
+        report.patients,
+        report.results,
+        report.remapped_patients.len()
+    );
+    Ok(report)
+}
+
+/// Decompresses a package and checks its format, version and content hash
+fn decode_package(bytes: &[u8]) -> Result<(PackageManifest, PackageContent), String> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(bytes)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| format!("Not a results package: {}", e))?;
+
+    let split = decompressed
+        .iter()
+        .position(|b| *b == b'\n')
+        .ok_or("Results package has no manifest")?;
+    let (manifest, content) = (&decompressed[..split], &decompressed[split + 1..]);
+
+    let manifest: PackageManifest = serde_json::from_slice(manifest)
+        .map_err(|e| format!("Invalid results package manifest: {}", e))?;
+    if manifest.format != RESULTS_PACKAGE_FORMAT {
+        return Err(format!("Not a results package (format '{}')", manifest.format));
+    }
+    if manifest.version > RESULTS_PACKAGE_VERSION {
+        return Err(format!(
+            "Results package version {} is newer than supported version {}",
+            manifest.version, RESULTS_PACKAGE_VERSION
+        ));
+    }
+    let hash = sha256_hex(content);
+    if hash != manifest.content_sha256 {
+        return Err(format!(
+            "Results package is corrupt: content hash {} does not match manifest {}",
+            hash, manifest.content_sha256
+        ));
+    }
+
+    let content: PackageContent = serde_json::from_slice(content)
+        .map_err(|e| format!("Invalid results package content: {}", e))?;
+    if content.patients.len() != manifest.patients || content.results.len() != manifest.results {
+        return Err("Results package content does not match its manifest counts".to_string());
+    }
+    Ok((manifest, content))
+}
+
+async fn import_patients(
+    repository: &SqliteRepository,
+    patients: &[Patient],
+    report: &mut PackageImportReport,
+) -> Result<(), String> {
+    for chunk in patients.chunks(IMPORT_BATCH_SIZE) {
+        let mut batch = Vec::new();
+        let mut counts = EntityImportCounts::default();
+        let mut remapped = Vec::new();
+
+        for packaged in chunk {
+            let mut patient = packaged.clone();
+            let mut stored = repository.get_patient(&patient.id).await?;
+
+            // The id belongs to someone else here: import under a derived id and
+            // leave the pair for duplicate review
+            let conflicting = stored
+                .as_ref()
+                .filter(|existing| demographics_conflict(existing, &patient))
+                .cloned();
+            if let Some(existing) = conflicting {
+                let imported_as = remapped_patient_id(&patient);
+                remapped.push((existing, patient.id.clone(), imported_as.clone()));
+                patient.id = imported_as;
+                stored = repository.get_patient(&patient.id).await?;
+            }
+
+            match stored {
+                None => {
+                    counts.inserted += 1;
+                    batch.push(PatientImport::Insert(patient));
+                }
+                Some(existing) if fills_gaps(&existing, &patient) => {
+                    counts.updated += 1;
+                    batch.push(PatientImport::FillGaps(patient));
+                }
+                Some(_) => counts.skipped += 1,
+            }
+        }
+
+        repository.import_patient_batch(&batch).await?;
+        report.patients.inserted += counts.inserted;
+        report.patients.updated += counts.updated;
+        report.patients.skipped += counts.skipped;
+
+        for (existing, package_patient_id, imported_as) in remapped {
+            suggest_duplicate(repository, &existing, &imported_as).await?;
+            log::warn!(
+                "Packaged patient {} conflicts with the local patient; imported as {}",
+                package_patient_id,
+                imported_as
+            );
+            report.remapped_patients.push(RemappedPatient {
+                package_patient_id,
+                imported_as,
+            });
+        }
+    }
+    Ok(())
+}
+
+async fn import_results(
+    repository: &SqliteRepository,
+    results: Vec<PackagedResult>,
+    report: &mut PackageImportReport,
+) -> Result<(), String> {
+    let remaps: HashMap<&str, &str> = report
+        .remapped_patients
+        .iter()
+        .map(|remap| (remap.package_patient_id.as_str(), remap.imported_as.as_str()))
+        .collect();
+
+    let mut results = results.into_iter().peekable();
+    while results.peek().is_some() {
+        let mut batch = Vec::new();
+        let mut counts = EntityImportCounts::default();
+
+        for mut packaged in results.by_ref().take(IMPORT_BATCH_SIZE) {
+            if let Some(imported_as) = remaps.get(packaged.patient_id.as_str()) {
+                packaged.patient_id = imported_as.to_string();
+            }
+
+            match repository.get_test_result(&packaged.result.id).await? {
+                None => {
+                    counts.inserted += 1;
+                    batch.push(ResultImport::Insert(packaged));
+                }
+                Some(stored) if stored != packaged.result && packaged.result.updated_at > stored.updated_at => {
+                    counts.updated += 1;
+                    batch.push(ResultImport::Replace(packaged));
+                }
+                Some(_) => counts.skipped += 1,
+            }
+        }
+
+        repository.import_result_batch(&batch).await?;
+        report.results.inserted += counts.inserted;
+        report.results.updated += counts.updated;
+        report.results.skipped += counts.skipped;
+    }
+    Ok(())
+}
+
+/// True when both records know a field and disagree on it
+fn demographics_conflict(stored: &Patient, packaged: &Patient) -> bool {
+    let (stored_name, packaged_name) = (normalize_name(&stored.name), normalize_name(&packaged.name));
+    let name_differs = !stored_name.is_empty() && !packaged_name.is_empty() && stored_name != packaged_name;
+    let birth_date_differs = matches!(
+        (stored.birth_date, packaged.birth_date),
+        (Some(a), Some(b)) if a.date_naive() != b.date_naive()
+    );
+    let sex_differs = stored.sex != Sex::Other && packaged.sex != Sex::Other && stored.sex != packaged.sex;
+
+    name_differs || birth_date_differs || sex_differs
+}
+
+/// True when the packaged record knows demographics the stored one lacks
+fn fills_gaps(stored: &Patient, packaged: &Patient) -> bool {
+    let name = (&stored.name, &packaged.name);
+    (name.0.last_name.is_none() && name.1.last_name.is_some())
+        || (name.0.first_name.is_none() && name.1.first_name.is_some())
+        || (name.0.middle_name.is_none() && name.1.middle_name.is_some())
+        || (name.0.title.is_none() && name.1.title.is_some())
+        || (stored.birth_date.is_none() && packaged.birth_date.is_some())
+        || (stored.sex == Sex::Other && packaged.sex != Sex::Other)
+}
+
+/// Id a conflicting packaged patient is imported under. Derived from its demographics
+/// so importing the same person again reuses the record instead of adding another.
+fn remapped_patient_id(patient: &Patient) -> String {
+    let fingerprint = format!(
+        "{}|{}|{}",
+        normalize_name(&patient.name),
+        patient.birth_date.map(|date| date.date_naive().to_string()).unwrap_or_default(),
+        String::from(patient.sex.clone())
+    );
+    format!("{}~{}", patient.id, &sha256_hex(fingerprint.as_bytes())[..8])
+}
+
+async fn suggest_duplicate(repository: &SqliteRepository, existing: &Patient, imported_as: &str) -> Result<(), String> {
+    let Some(imported) = repository.get_patient(imported_as).await? else {
+        return Ok(());
+    };
+    let (score, mut reasons) =
+        score_pair(existing, &imported, &DuplicateDetectionSettings::default()).unwrap_or((0.0, Vec::new()));
+    reasons.insert(0, ID_CONFLICT_REASON.to_string());
+
+    let (a, b) = if existing.id <= imported.id { (existing, &imported) } else { (&imported, existing) };
+    let now = Utc::now();
+    repository
+        .upsert_duplicate_candidate(&DuplicateCandidate {
+            id: Uuid::new_v4().to_string(),
+            patient_id_a: a.id.clone(),
+            patient_id_b: b.id.clone(),
+            score,
+            reasons,
+            status: CandidateStatus::Pending,
+            created_at: now,
+            updated_at: now,
+        })
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+    use crate::models::patient::PatientName;
+    use crate::models::result::TestResultMetadata;
+    use crate::models::{DataSource, TestResult};
+    use crate::test_support::dated_result;
+    use chrono::TimeZone;
+
+    fn patient(id: &str, first: &str, last: &str, birth_day: Option<u32>) -> Patient {
+        let created_at = Utc.with_ymd_and_hms(2025, 1, 1, 8, 0, 0).unwrap();
+        Patient {
+            name: PatientName {
+                last_name: Some(last.to_string()),
+                first_name: Some(first.to_string()),
+                ..Default::default()
+            },
+            birth_date: birth_day.map(|day| Utc.with_ymd_and_hms(1980, 5, day, 0, 0, 0).unwrap()),
+            sex: Sex::Female,
+            telephone: vec!["555-0101".to_string()],
+            created_at,
+            updated_at: created_at,
+            ..Patient::fixture(id)
+        }
+    }
+
+    fn result(id: &str, sample_id: &str, value: &str, day: u32) -> TestResult {
+        let result = dated_result(id, sample_id, value, day);
+        TestResult {
+            metadata: TestResultMetadata {
+                instrument: Some("CQ5".to_string()),
+                operator: Some("OP1".to_string()),
+                ..result.metadata.clone()
+            },
+            ..result
+        }
+    }
+
+    async fn satellite_repository() -> SqliteRepository {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        for seeded in [patient("P1", "Anita", "Sharma", Some(12)), patient("P2", "Rahul", "Verma", None)] {
+            repository.save_patient(&seeded, &DataSource::Analyzer).await.unwrap();
+        }
+        for (stored, patient_id) in [
+            (result("R1", "S1", "95", 10), "P1"),
+            (result("R2", "S2", "130", 10), "P2"),
+            (result("R3", "S2", "4.1", 11), "P2"),
+            (result("R4", "S3", "88", 20), "P1"),
+        ] {
+            repository.save_test_result(&stored, patient_id, &DataSource::Analyzer).await.unwrap();
+        }
+        repository
+    }
+
+    fn range() -> (DateTime<Utc>, DateTime<Utc>) {
+        (
+            Utc.with_ymd_and_hms(2025, 1, 10, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2025, 1, 11, 23, 59, 59).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_package_round_trip_reproduces_patients_and_results() {
+        let satellite = satellite_repository().await;
+        let (from, to) = range();
+        let dir = std::env::temp_dir().join(format!("results-package-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("daily.lispkg");
+
+        let manifest = export_results_package(&satellite, from, to, &path).await.unwrap();
+        assert_eq!((manifest.patients, manifest.samples, manifest.results), (2, 2, 3));
+
+        let main_lab = SqliteRepository::new(establish_test_connection().await);
+        let report = import_results_package(&main_lab, &path).await.unwrap();
+        assert!(report.error.is_none());
+        assert_eq!(report.patients, EntityImportCounts { inserted: 2, updated: 0, skipped: 0 });
+        assert_eq!(report.results, EntityImportCounts { inserted: 3, updated: 0, skipped: 0 });
+
+        for patient_id in ["P1", "P2"] {
+            let exported = satellite.get_patient(patient_id).await.unwrap().unwrap();
+            let imported = main_lab.get_patient(patient_id).await.unwrap().unwrap();
+            assert_eq!(serde_json::to_value(&exported).unwrap(), serde_json::to_value(&imported).unwrap());
+        }
+        for sample_id in ["S1", "S2"] {
+            assert_eq!(
+                satellite.get_results_by_sample_id(sample_id).await.unwrap(),
+                main_lab.get_results_by_sample_id(sample_id).await.unwrap()
+            );
+        }
+        assert!(main_lab.get_test_result("R4").await.unwrap().is_none());
+        assert_eq!(main_lab.count_results_by_source(&DataSource::Imported).await.unwrap(), 3);
+
+        // Carrying the same stick in twice changes nothing
+        let again = import_results_package(&main_lab, &path).await.unwrap();
+        assert_eq!(again.patients, EntityImportCounts { inserted: 0, updated: 0, skipped: 2 });
+        assert_eq!(again.results, EntityImportCounts { inserted: 0, updated: 0, skipped: 3 });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_patient_id_is_remapped_and_suggested_as_duplicate() {
+        let satellite = satellite_repository().await;
+        let (from, to) = range();
+        let (_, bytes) = build_results_package(&satellite, from, to).await.unwrap();
+
+        let main_lab = SqliteRepository::new(establish_test_connection().await);
+        // P1 is someone else here; P2 is the same person with the birth date known
+        main_lab.save_patient(&patient("P1", "Meera", "Iyer", Some(3)), &DataSource::Analyzer).await.unwrap();
+        main_lab.save_patient(&patient("P2", "Rahul", "Verma", None), &DataSource::Analyzer).await.unwrap();
+        let mut updated = result("R2", "S2", "131", 10);
+        updated.updated_at = Utc.with_ymd_and_hms(2025, 1, 9, 0, 0, 0).unwrap();
+        main_lab.save_test_result(&updated, "P2", &DataSource::Analyzer).await.unwrap();
+
+        let report = import_results_package_bytes(&main_lab, &bytes).await.unwrap();
+        assert_eq!(report.patients, EntityImportCounts { inserted: 1, updated: 0, skipped: 1 });
+        assert_eq!(report.results, EntityImportCounts { inserted: 2, updated: 1, skipped: 0 });
+        assert_eq!(report.remapped_patients.len(), 1);
+        let imported_as = report.remapped_patients[0].imported_as.clone();
+        assert!(imported_as.starts_with("P1~"));
+
+        assert_eq!(main_lab.get_patient("P1").await.unwrap().unwrap().name.first_name.as_deref(), Some("Meera"));
+        assert_eq!(main_lab.get_patient_results(&imported_as).await.unwrap().len(), 1);
+        assert_eq!(main_lab.get_test_result("R2").await.unwrap().unwrap().value, "130");
+
+        let candidates = main_lab.get_pending_duplicate_candidates(0.0).await.unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].reasons[0], ID_CONFLICT_REASON);
+    }
+
+    #[tokio::test]
+    async fn test_tampered_or_newer_package_is_rejected() {
+        let satellite = satellite_repository().await;
+        let (from, to) = range();
+        let (mut manifest, _) = build_results_package(&satellite, from, to).await.unwrap();
+        let main_lab = SqliteRepository::new(establish_test_connection().await);
+
+        let tampered = encode_package(&manifest, br#"{"patients":[],"results":[]}"#).unwrap();
+        let error = import_results_package_bytes(&main_lab, &tampered).await.unwrap_err();
+        assert!(error.contains("hash"), "{}", error);
+
+        let content = br#"{"patients":[],"results":[]}"#;
+        manifest.content_sha256 = sha256_hex(content);
+        manifest.version = RESULTS_PACKAGE_VERSION + 1;
+        let newer = encode_package(&manifest, content).unwrap();
+        let error = import_results_package_bytes(&main_lab, &newer).await.unwrap_err();
+        assert!(error.contains("newer"), "{}", error);
+
+        assert!(import_results_package_bytes(&main_lab, b"SQLite format 3").await.is_err());
+        assert!(main_lab.list_patients().await.unwrap().is_empty());
+    }
+}
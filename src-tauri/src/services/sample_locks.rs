@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Prefix of the error returned when a sample stays locked past the wait timeout.
+/// Nothing was changed, so the operation can simply be retried.
+pub const SAMPLE_BUSY_ERROR: &str = "SAMPLE_BUSY";
+
+/// Longest a command waits for a sample held by ingestion or another edit
+pub const DEFAULT_SAMPLE_LOCK_WAIT: Duration = Duration::from_secs(10);
+
+/// Unused lock entries are dropped after this long
+pub const DEFAULT_SAMPLE_LOCK_EXPIRY: Duration = Duration::from_secs(300);
+
+struct LockEntry {
+    lock: Arc<AsyncMutex<()>>,
+    last_used: Instant,
+}
+
+/// Held while a sample's results are being written; released on drop
+pub struct SampleLockGuard {
+    sample_id: String,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl SampleLockGuard {
+    pub fn sample_id(&self) -> &str {
+        &self.sample_id
+    }
+}
+
+/// Advisory per-sample locks shared by the ingestion workers and the result
+/// commands, so writes to one sample serialize while different samples proceed
+/// in parallel. Entries nobody holds or waits for expire after `expiry`.
+pub struct SampleLocks {
+    entries: Mutex<HashMap<String, LockEntry>>,
+    wait: Duration,
+    expiry: Duration,
+}
+
+impl Default for SampleLocks {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAMPLE_LOCK_WAIT, DEFAULT_SAMPLE_LOCK_EXPIRY)
+    }
+}
+
+impl SampleLocks {
+    pub fn new(wait: Duration, expiry: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            wait,
+            expiry,
+        }
+    }
+
+    /// Locks a sample for a command. Returns a `SAMPLE_BUSY` error when the sample
+    /// is still held after the wait timeout.
+    pub async fn acquire(&self, sample_id: &str) -> Result<SampleLockGuard, String> {
+        let lock = self.entry(sample_id);
+        match tokio::time::timeout(self.wait, lock.lock_owned()).await {
+            Ok(guard) => Ok(SampleLockGuard {
+                sample_id: sample_id.to_string(),
+                _guard: guard,
+            }),
+            Err(_) => {
+                log::warn!("Sample {} still locked after {:?}", sample_id, self.wait);
+                Err(format!(
+                    "{}: sample {} is being updated, try again shortly",
+                    SAMPLE_BUSY_ERROR, sample_id
+                ))
+            }
+        }
+    }
+
+    /// Locks a sample for ingestion, waiting as long as it takes; an analyzer
+    /// message is never dropped because a technologist is editing the sample
+    pub async fn lock(&self, sample_id: &str) -> SampleLockGuard {
+        SampleLockGuard {
+            sample_id: sample_id.to_string(),
+            _guard: self.entry(sample_id).lock_owned().await,
+        }
+    }
+
+    /// Whether an error is the retriable "sample busy" error
+    pub fn is_busy_error(error: &str) -> bool {
+        error.starts_with(SAMPLE_BUSY_ERROR)
+    }
+
+    /// Number of samples with a live lock entry
+    pub fn tracked(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Gets (or creates) the lock of a sample, dropping expired entries on the way
+    fn entry(&self, sample_id: &str) -> Arc<AsyncMutex<()>> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        // An entry is in use while a guard or a waiter holds a clone of its lock
+        entries.retain(|_, entry| Arc::strong_count(&entry.lock) > 1 || now.duration_since(entry.last_used) < self.expiry);
+
+        let entry = entries.entry(sample_id.to_string()).or_insert_with(|| LockEntry {
+            lock: Arc::new(AsyncMutex::new(())),
+            last_used: now,
+        });
+        entry.last_used = now;
+        entry.lock.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::commands::patient_handler::correct_test_result;
+    use crate::app_state::AppState;
+    use crate::db::{establish_test_connection, SqliteRepository};
+    use crate::models::{DataSource, ResultStatus, TestResult};
+    use crate::services::ingestion_lanes::ArrivalOrder;
+
+    fn result(id: &str, sample_id: &str) -> TestResult {
+        TestResult {
+            sample_id: sample_id.to_string(),
+            completed_date_time: None,
+            analyzer_id: Some("A1".to_string()),
+            ..TestResult::fixture(id)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_busy_sample_times_out_while_other_samples_proceed() {
+        let locks = SampleLocks::new(Duration::from_millis(50), DEFAULT_SAMPLE_LOCK_EXPIRY);
+        let held = locks.acquire("S1").await.unwrap();
+        assert_eq!(held.sample_id(), "S1");
+
+        let error = locks.acquire("S1").await.err().unwrap();
+        assert!(SampleLocks::is_busy_error(&error));
+        assert!(locks.acquire("S2").await.is_ok());
+
+        drop(held);
+        assert!(locks.acquire("S1").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_unused_entries_expire() {
+        let locks = SampleLocks::new(DEFAULT_SAMPLE_LOCK_WAIT, Duration::ZERO);
+        let held = locks.lock("S1").await;
+        drop(locks.lock("S2").await);
+
+        // S2 is free and expired; S1 is still held
+        drop(locks.acquire("S3").await.unwrap());
+        assert_eq!(locks.tracked(), 2);
+        drop(held);
+        drop(locks.acquire("S3").await.unwrap());
+        assert_eq!(locks.tracked(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_edit_and_correction_keep_one_chain() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let locks = Arc::new(SampleLocks::default());
+
+        for run in 0..100 {
+            let original = TestResult {
+                comments: vec!["Hemolyzed".to_string()],
+                ..result(&format!("R{}", run), &format!("S{}", run))
+            };
+            repository.save_test_result(&original, "P1", &DataSource::Analyzer).await.unwrap();
+
+            // Ingestion: an analyzer correction replaces whatever result is current,
+            // stored under the sample lock the ingestion lane takes
+            let correction = {
+                let (repository, locks) = (repository.clone(), locks.clone());
+                let correction = TestResult {
+                    id: format!("{}-corrected", original.id),
+                    value: "120".to_string(),
+                    status: ResultStatus::Correction,
+                    ..original.clone()
+                };
+                tokio::spawn(async move {
+                    let _lock = locks.lock(&correction.sample_id).await;
+                    AppState::<tauri::Wry>::store_meril_results(
+                        &repository,
+                        &correction.sample_id,
+                        Some("P1"),
+                        None,
+                        std::slice::from_ref(&correction),
+                        ArrivalOrder::InOrder,
+                    )
+                    .await
+                    .unwrap();
+                })
+            };
+
+            // Edit: a technologist corrects the result they are looking at
+            let edit = {
+                let (repository, locks, result_id) = (repository.clone(), locks.clone(), original.id.clone());
+                tokio::spawn(async move { correct_test_result(&repository, &locks, &result_id, "121".to_string()).await })
+            };
+
+            correction.await.unwrap();
+            let edited = edit.await.unwrap();
+
+            // The edit applies on top of the original, or is refused once the correction replaced it
+            let chain = repository.get_result_chain(&original.sample_id, "GLU").await.unwrap();
+            match edited {
+                Ok(replacement) => {
+                    assert_eq!(chain.len(), 3, "run {}", run);
+                    assert_eq!(chain[1].id, replacement.id);
+                    assert_eq!(chain[1].comments, original.comments);
+                    assert_eq!(chain[2].id, format!("{}-corrected", original.id));
+                }
+                Err(e) => {
+                    assert!(e.contains("has been superseded"), "run {}: {}", run, e);
+                    assert_eq!(chain.len(), 2, "run {}", run);
+                }
+            }
+            assert_eq!(chain[0].id, original.id);
+            let head = repository.get_current_result(&original.sample_id, "GLU").await.unwrap().unwrap();
+            assert_eq!(head.id, chain[2].id);
+        }
+    }
+}
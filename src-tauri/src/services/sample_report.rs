@@ -0,0 +1,412 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::patient::Sex;
+use crate::models::{Patient, ResultImage, ResultStatus, TestResult};
+use crate::protocol::ed_image::encode_base64;
+use crate::protocol::hl7_parser::{extract_parameter_code, parameter_display_name};
+use crate::services::report_locale::ReportLocale;
+
+// ============================================================================
+// SAMPLE REPORT
+// ============================================================================
+//
+// A printable Markdown report of one sample's results, rendered in the locale
+// configured for its template. Values are only reformatted for display. Graphs
+// are inline HTML images carrying their data, so the Markdown stands alone.
+
+/// Template name the report locale settings refer to
+pub const SAMPLE_REPORT_TEMPLATE: &str = "sample_results";
+
+/// Settings store key of the per-template graph embedding flags
+pub const REPORT_GRAPHS_STORE_KEY: &str = "report_graphs";
+
+/// Display width of an embedded graph; the image is scaled to it
+const GRAPH_WIDTH_PX: u32 = 320;
+
+/// Which report templates embed the sample's histograms and scattergrams
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ReportGraphSettings {
+    /// Whether each template embeds graphs; templates not listed do not
+    pub templates: HashMap<String, bool>,
+}
+
+impl ReportGraphSettings {
+    pub fn embeds_graphs(&self, template: &str) -> bool {
+        self.templates.get(template).copied().unwrap_or(false)
+    }
+}
+
+/// Reads stored settings, embedding no graphs when missing or invalid
+pub fn report_graphs_from_store(stored: Option<serde_json::Value>) -> ReportGraphSettings {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid report graph settings: {}", e);
+            ReportGraphSettings::default()
+        }),
+        None => ReportGraphSettings::default(),
+    }
+}
+
+/// A graph kept for one of the sample's results, as placed on the report and its preview
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReportAttachment {
+    pub result_id: String,
+    pub kind: String, // e.g. "histogram", "scattergram"
+    pub parameter_code: String,
+    pub caption: String,
+    pub mime_type: String,
+    pub path: String,
+    /// Size of the file; `None` when it is missing, and the report shows a placeholder
+    pub size_bytes: Option<u64>,
+}
+
+/// Attachments for the images of a sample's results, captioned from the parameter
+/// code table and checked against the files they point to
+pub async fn report_attachments(results: &[TestResult], images: &[ResultImage]) -> Vec<ReportAttachment> {
+    let mut attachments = Vec::with_capacity(images.len());
+    for image in images {
+        let parameter_code = results
+            .iter()
+            .find(|result| result.id == image.result_id)
+            .map(|result| extract_parameter_code(result.test_id.trim_start_matches('^')))
+            .unwrap_or_default();
+        let size_bytes = tokio::fs::metadata(&image.reference)
+            .await
+            .ok()
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len());
+        attachments.push(ReportAttachment {
+            result_id: image.result_id.clone(),
+            kind: image.kind.clone(),
+            caption: parameter_display_name(&parameter_code)
+                .map(str::to_string)
+                .unwrap_or_else(|| image.kind.clone()),
+            parameter_code,
+            mime_type: image.mime_type.clone(),
+            path: image.reference.clone(),
+            size_bytes,
+        });
+    }
+    attachments
+}
+
+/// A graph as placed on the report, its image inlined so the report does not
+/// depend on the reader being able to open files of the app
+#[derive(Debug, Clone)]
+pub struct ReportGraph {
+    pub attachment: ReportAttachment,
+    /// `data:` URI of the image; `None` when the file could not be read, and the report shows a placeholder
+    pub data_uri: Option<String>,
+}
+
+/// Reads the file of each attachment into a `data:` URI
+pub async fn load_report_graphs(attachments: Vec<ReportAttachment>) -> Vec<ReportGraph> {
+    let mut graphs = Vec::with_capacity(attachments.len());
+    for attachment in attachments {
+        let data_uri = match tokio::fs::read(&attachment.path).await {
+            Ok(bytes) => Some(format!("data:{};base64,{}", attachment.mime_type, encode_base64(&bytes))),
+            Err(e) => {
+                log::warn!("Graph {} of result {} is not available: {}", attachment.path, attachment.result_id, e);
+                None
+            }
+        };
+        graphs.push(ReportGraph { attachment, data_uri });
+    }
+    graphs
+}
+
+/// Escapes text placed in the report's HTML
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Everything the sample report is rendered from
+#[derive(Debug, Clone)]
+pub struct SampleReportInput {
+    pub sample_id: String,
+    pub patient: Option<Patient>,
+    pub results: Vec<TestResult>,
+    /// Graphs of the results, shown only when `embed_graphs` is set for the template
+    pub graphs: Vec<ReportGraph>,
+    pub embed_graphs: bool,
+    pub generated_at: DateTime<Utc>,
+}
+
+fn patient_name(patient: &Patient) -> String {
+    [&patient.name.title, &patient.name.first_name, &patient.name.middle_name, &patient.name.last_name]
+        .into_iter()
+        .flatten()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn sex_key(sex: &Sex) -> &'static str {
+    match sex {
+        Sex::Male => "sex.male",
+        Sex::Female => "sex.female",
+        Sex::Other => "sex.other",
+    }
+}
+
+fn status_key(status: &ResultStatus) -> &'static str {
+    match status {
+        ResultStatus::Final => "status.final",
+        ResultStatus::Preliminary => "status.preliminary",
+        ResultStatus::Correction => "status.correction",
+    }
+}
+
+/// Reference range with localized numbers, e.g. "3,5-5"
+fn reference_range(result: &TestResult, locale: &ReportLocale) -> String {
+    match result.reference_range.as_ref().map(|range| (range.lower_limit, range.upper_limit)) {
+        Some((Some(lower), Some(upper))) => {
+            format!("{}-{}", locale.format_decimal(lower), locale.format_decimal(upper))
+        }
+        Some((Some(lower), None)) => format!(">{}", locale.format_decimal(lower)),
+        Some((None, Some(upper))) => format!("<{}", locale.format_decimal(upper)),
+        _ => "-".to_string(),
+    }
+}
+
+/// Renders the sample report in `locale`
+pub fn render_sample_report(input: &SampleReportInput, locale: &ReportLocale) -> String {
+    let mut report = String::new();
+
+    let _ = writeln!(report, "# {}", locale.text("report.title"));
+    let _ = writeln!(report);
+    let _ = writeln!(report, "{}: {}", locale.text("report.generated"), locale.format_datetime(input.generated_at));
+    let _ = writeln!(report);
+
+    let _ = writeln!(report, "## {}", locale.text("report.patient"));
+    let _ = writeln!(report);
+    match &input.patient {
+        Some(patient) => {
+            let _ = writeln!(report, "- {}: {}", locale.text("report.patient"), patient_name(patient));
+            let _ = writeln!(report, "- {}: {}", locale.text("report.patient_id"), patient.id);
+            if let Some(birth_date) = patient.birth_date {
+                let _ = writeln!(report, "- {}: {}", locale.text("report.birth_date"), locale.format_date(birth_date));
+            }
+            let _ = writeln!(report, "- {}: {}", locale.text("report.sex"), locale.text(sex_key(&patient.sex)));
+        }
+        None => {
+            let _ = writeln!(report, "- {}", locale.text("report.unknown_patient"));
+        }
+    }
+    let _ = writeln!(report, "- {}: {}", locale.text("report.sample"), input.sample_id);
+    let _ = writeln!(report);
+
+    let _ = writeln!(report, "## {}", locale.text("report.results"));
+    let _ = writeln!(report);
+    if input.results.is_empty() {
+        let _ = writeln!(report, "{}", locale.text("report.no_results"));
+        return report;
+    }
+
+    let columns = [
+        "column.test",
+        "column.result",
+        "column.units",
+        "column.reference_range",
+        "column.flags",
+        "column.status",
+        "column.completed",
+    ];
+    let headers: Vec<String> = columns.iter().map(|key| locale.text(key)).collect();
+    let _ = writeln!(report, "| {} |", headers.join(" | "));
+    let _ = writeln!(report, "|{}", "---|".repeat(columns.len()));
+
+    let mut results: Vec<&TestResult> = input.results.iter().collect();
+    results.sort_by_key(|result| result.metadata.sequence_number);
+    for result in results {
+        let flags = result.flag_list();
+        let _ = writeln!(
+            report,
+            "| {} | {} | {} | {} | {} | {} | {} |",
+            result.test_id.trim_start_matches('^'),
+            locale.format_number(&result.value),
+            result.units.as_deref().unwrap_or("-"),
+            reference_range(result, locale),
+            if flags.is_empty() { "-".to_string() } else { flags.join(", ") },
+            locale.text(status_key(&result.status)),
+            result
+                .completed_date_time
+                .map(|at| locale.format_datetime(at))
+                .unwrap_or_else(|| "-".to_string()),
+        );
+    }
+
+    if input.embed_graphs && !input.graphs.is_empty() {
+        let _ = writeln!(report);
+        let _ = writeln!(report, "## {}", locale.text("report.graphs"));
+        for graph in &input.graphs {
+            let caption = escape_html(&graph.attachment.caption);
+            let _ = writeln!(report);
+            match &graph.data_uri {
+                Some(data_uri) => {
+                    let _ = writeln!(
+                        report,
+                        "<img src=\"{}\" alt=\"{}\" width=\"{}\">",
+                        escape_html(data_uri),
+                        caption,
+                        GRAPH_WIDTH_PX
+                    );
+                    let _ = writeln!(report);
+                    let _ = writeln!(report, "*{}*", caption);
+                }
+                None => {
+                    let _ = writeln!(report, "*{}: {}*", caption, locale.text("report.graph_missing"));
+                }
+            }
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::patient::PatientName;
+    use crate::models::result::ReferenceRange;
+    use chrono::TimeZone;
+
+    fn report_input() -> SampleReportInput {
+        let completed = Utc.with_ymd_and_hms(2024, 3, 7, 14, 5, 0).unwrap();
+        SampleReportInput {
+            sample_id: "S100".to_string(),
+            patient: Some(Patient {
+                name: PatientName {
+                    last_name: Some("Martin".to_string()),
+                    first_name: Some("Claire".to_string()),
+                    ..Default::default()
+                },
+                birth_date: Some(Utc.with_ymd_and_hms(1980, 11, 2, 0, 0, 0).unwrap()),
+                sex: Sex::Female,
+                created_at: completed,
+                updated_at: completed,
+                ..Patient::fixture("P1")
+            }),
+            results: vec![TestResult {
+                test_id: "^^^ALB".to_string(),
+                sample_id: "S100".to_string(),
+                value: "3.8".to_string(),
+                units: Some("g/dL".to_string()),
+                reference_range: Some(ReferenceRange {
+                    lower_limit: Some(3.5),
+                    upper_limit: Some(5.2),
+                }),
+                completed_date_time: Some(completed),
+                created_at: completed,
+                updated_at: completed,
+                ..TestResult::fixture("R1")
+            }],
+            graphs: Vec::new(),
+            embed_graphs: false,
+            generated_at: Utc.with_ymd_and_hms(2024, 3, 8, 9, 30, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_same_report_in_english_and_french() {
+        let input = report_input();
+        let en = render_sample_report(&input, &ReportLocale::new("en"));
+        let fr = render_sample_report(&input, &ReportLocale::new("fr"));
+
+        // Headers
+        assert!(en.contains("# Laboratory Report"));
+        assert!(fr.contains("# Compte rendu d'analyses"));
+        assert!(en.contains("| Test | Result | Units | Reference range | Flags | Status | Completed |"));
+        assert!(fr.contains("| Analyse | Résultat | Unités | Valeurs de référence | Indicateurs | Statut | Terminé le |"));
+
+        // Dates
+        assert!(en.contains("Generated: 2024-03-08 09:30"));
+        assert!(fr.contains("Édité le: 08/03/2024 09:30"));
+        assert!(en.contains("- Date of birth: 1980-11-02"));
+        assert!(fr.contains("- Date de naissance: 02/11/1980"));
+
+        // Decimal separators, on values and reference ranges
+        assert!(en.contains("| ALB | 3.8 | g/dL | 3.5-5.2 | - | Final | 2024-03-07 14:05 |"));
+        assert!(fr.contains("| ALB | 3,8 | g/dL | 3,5-5,2 | - | Définitif | 07/03/2024 14:05 |"));
+
+        // The model itself keeps its canonical value
+        assert_eq!(input.results[0].value, "3.8");
+    }
+
+    #[tokio::test]
+    async fn test_graphs_embedded_only_when_the_template_asks() {
+        let path = std::env::temp_dir().join(format!("report-graph-{}.png", uuid::Uuid::new_v4()));
+        tokio::fs::write(&path, [0x89, b'P', b'N', b'G']).await.unwrap();
+        let mut input = report_input();
+        input.results[0].test_id = "2101^RBCHistogram.PNG^LOCAL".to_string();
+        let image = |id: &str, kind: &str, reference: &str| ResultImage {
+            id: id.to_string(),
+            result_id: if kind == "histogram" { "R1" } else { "R9" }.to_string(),
+            kind: kind.to_string(),
+            mime_type: "image/png".to_string(),
+            reference: reference.to_string(),
+            created_at: input.generated_at,
+        };
+        let attachments = report_attachments(
+            &input.results,
+            &[
+                image("I1", "histogram", path.to_str().unwrap()),
+                image("I2", "histogram", "/nonexistent/PLT_histogram.png"),
+                image("I3", "graph \"<b>", path.to_str().unwrap()),
+            ],
+        )
+        .await;
+        assert_eq!(attachments[0].caption, "RBC histogram");
+        assert_eq!(attachments[0].parameter_code, "2101");
+        assert_eq!(attachments[0].size_bytes, Some(4));
+        assert_eq!(attachments[1].size_bytes, None);
+        input.graphs = load_report_graphs(attachments).await;
+        assert_eq!(input.graphs[0].data_uri.as_deref(), Some("data:image/png;base64,iVBORw=="));
+        assert_eq!(input.graphs[1].data_uri, None);
+
+        // Not embedded unless the template asks
+        let settings = ReportGraphSettings {
+            templates: HashMap::from([(SAMPLE_REPORT_TEMPLATE.to_string(), true)]),
+        };
+        assert!(settings.embeds_graphs(SAMPLE_REPORT_TEMPLATE));
+        assert!(!ReportGraphSettings::default().embeds_graphs(SAMPLE_REPORT_TEMPLATE));
+        let plain = render_sample_report(&input, &ReportLocale::new("en"));
+        assert!(!plain.contains("## Graphs"));
+        assert!(!plain.contains("<img"));
+
+        // Readable files are inlined scaled with their caption, missing ones leave a note;
+        // the file path never reaches the report and captions are escaped
+        input.embed_graphs = true;
+        let report = render_sample_report(&input, &ReportLocale::new("en"));
+        assert!(report.contains("## Graphs"));
+        assert!(report.contains("<img src=\"data:image/png;base64,iVBORw==\" alt=\"RBC histogram\" width=\"320\">"));
+        assert!(report.contains("*RBC histogram*"));
+        assert!(report.contains("*RBC histogram: image not available*"));
+        assert!(report.contains("alt=\"graph &quot;&lt;b&gt;\""));
+        assert!(!report.contains(path.to_str().unwrap()));
+        assert_eq!(report.matches("<img").count(), 2);
+
+        // A sample without attachments has no graph section at all
+        input.graphs.clear();
+        assert!(!render_sample_report(&input, &ReportLocale::new("en")).contains("## Graphs"));
+
+        tokio::fs::remove_file(path).await.unwrap();
+    }
+}
@@ -0,0 +1,288 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::models::Analyzer;
+use crate::services::autoquant_meril::AutoQuantMerilService;
+use crate::services::bf6900_service::BF6900Service;
+
+// ============================================================================
+// MANAGED SERVICES
+// ============================================================================
+
+/// An analyzer service whose listener can be started and stopped from commands
+#[async_trait]
+pub trait ManagedService: Send + Sync {
+    /// Checks the service can start before it is auto-started at boot
+    async fn initialize(&self) -> Result<(), String> {
+        Ok(())
+    }
+    async fn start(&self) -> Result<(), String>;
+    async fn stop(&self) -> Result<(), String>;
+    async fn connections_count(&self) -> usize;
+}
+
+#[async_trait]
+impl ManagedService for AutoQuantMerilService {
+    async fn initialize(&self) -> Result<(), String> {
+        self.get_analyzer_config().await.port.map(|_| ()).ok_or_else(|| "No port configured".to_string())
+    }
+
+    async fn start(&self) -> Result<(), String> {
+        AutoQuantMerilService::start(self).await
+    }
+
+    async fn stop(&self) -> Result<(), String> {
+        AutoQuantMerilService::stop(self).await
+    }
+
+    async fn connections_count(&self) -> usize {
+        self.get_connections_count().await
+    }
+}
+
+#[async_trait]
+impl ManagedService for BF6900Service {
+    async fn initialize(&self) -> Result<(), String> {
+        self.get_analyzer_config().await.port.map(|_| ()).ok_or_else(|| "No port configured".to_string())
+    }
+
+    async fn start(&self) -> Result<(), String> {
+        BF6900Service::start(self).await
+    }
+
+    async fn stop(&self) -> Result<(), String> {
+        BF6900Service::stop(self).await
+    }
+
+    async fn connections_count(&self) -> usize {
+        self.get_connections_count().await
+    }
+}
+
+// ============================================================================
+// SERVICE CONTROLLER
+// ============================================================================
+
+/// Lifecycle of one service, locked independently of every other service.
+///
+/// Start and stop are serialized by the controller's own lock; the running flag is
+/// read without it, so a status query never waits for a slow stop. No method
+/// awaits another controller, which keeps the controllers deadlock-free.
+pub struct ServiceController<S: ManagedService + ?Sized> {
+    name: &'static str,
+    service: Arc<S>,
+    lifecycle: Mutex<()>,
+    running: AtomicBool,
+}
+
+impl<S: ManagedService + ?Sized> ServiceController<S> {
+    pub fn new(name: &'static str, service: Arc<S>) -> Self {
+        Self {
+            name,
+            service,
+            lifecycle: Mutex::new(()),
+            running: AtomicBool::new(false),
+        }
+    }
+
+    /// The controlled service
+    pub fn service(&self) -> &Arc<S> {
+        &self.service
+    }
+
+    /// Starts the service's listener
+    pub async fn start(&self) -> Result<(), String> {
+        let _lifecycle = self.lifecycle.lock().await;
+        if self.running.load(Ordering::SeqCst) {
+            return Err(format!("{} service is already running", self.name));
+        }
+
+        self.service.start().await?;
+        self.running.store(true, Ordering::SeqCst);
+
+        log::info!("{} service started successfully", self.name);
+        Ok(())
+    }
+
+    /// Stops the service's listener and waits until it has shut down
+    pub async fn stop(&self) -> Result<(), String> {
+        let _lifecycle = self.lifecycle.lock().await;
+        if !self.running.load(Ordering::SeqCst) {
+            return Err(format!("{} service is not running", self.name));
+        }
+
+        // Whatever the outcome, the listener is no longer trusted to be up
+        let stopped = self.service.stop().await;
+        self.running.store(false, Ordering::SeqCst);
+
+        match &stopped {
+            Ok(()) => log::info!("{} service stopped successfully", self.name),
+            Err(e) => log::error!("Error stopping {} service: {}", self.name, e),
+        }
+        stopped
+    }
+
+    /// Whether the service is running and how many connections it holds
+    pub async fn status(&self) -> (bool, usize) {
+        (self.running.load(Ordering::SeqCst), self.service.connections_count().await)
+    }
+}
+
+/// Whether `analyzer` is in the auto-start set; an analyzer with no entry falls
+/// back to its own `activate_on_start` flag
+pub fn autostart_enabled(analyzer: &Analyzer, autostart: &HashMap<String, bool>) -> bool {
+    autostart.get(&analyzer.id).copied().unwrap_or(analyzer.activate_on_start)
+}
+
+impl<S: ManagedService + ?Sized> ServiceController<S> {
+    /// Initializes and starts the service if its analyzer is in the auto-start set;
+    /// returns whether it was started. A service that fails to initialize is not started.
+    pub async fn autostart(&self, analyzer: &Analyzer, autostart: &HashMap<String, bool>) -> Result<bool, String> {
+        if !autostart_enabled(analyzer, autostart) {
+            return Ok(false);
+        }
+
+        log::info!("Auto-starting {} service for analyzer {}", self.name, analyzer.id);
+        self.service
+            .initialize()
+            .await
+            .map_err(|e| format!("{} service failed to initialize: {}", self.name, e))?;
+        self.start().await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{establish_test_connection, SqliteRepository};
+    use std::sync::Mutex as StdMutex;
+    use std::time::{Duration, Instant};
+
+    /// Takes `stop_delay` to stop, like a listener waiting out open connections
+    struct SlowService {
+        stop_delay: Duration,
+    }
+
+    #[async_trait]
+    impl ManagedService for SlowService {
+        async fn start(&self) -> Result<(), String> {
+            Ok(())
+        }
+
+        async fn stop(&self) -> Result<(), String> {
+            tokio::time::sleep(self.stop_delay).await;
+            Ok(())
+        }
+
+        async fn connections_count(&self) -> usize {
+            0
+        }
+    }
+
+    /// Records the lifecycle calls it receives; initialize fails when `init_error` is set
+    #[derive(Default)]
+    struct RecordingService {
+        init_error: Option<String>,
+        calls: StdMutex<Vec<&'static str>>,
+    }
+
+    #[async_trait]
+    impl ManagedService for RecordingService {
+        async fn initialize(&self) -> Result<(), String> {
+            self.calls.lock().unwrap().push("initialize");
+            self.init_error.clone().map_or(Ok(()), Err)
+        }
+
+        async fn start(&self) -> Result<(), String> {
+            self.calls.lock().unwrap().push("start");
+            Ok(())
+        }
+
+        async fn stop(&self) -> Result<(), String> {
+            self.calls.lock().unwrap().push("stop");
+            Ok(())
+        }
+
+        async fn connections_count(&self) -> usize {
+            0
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_stop_does_not_block_other_service_status() {
+        let meril = Arc::new(ServiceController::new(
+            "Meril",
+            Arc::new(SlowService { stop_delay: Duration::from_millis(500) }),
+        ));
+        let bf6900 = ServiceController::new("BF-6900", Arc::new(SlowService { stop_delay: Duration::ZERO }));
+        meril.start().await.unwrap();
+        bf6900.start().await.unwrap();
+
+        let stopping = tokio::spawn({
+            let meril = meril.clone();
+            async move { meril.stop().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let queried = Instant::now();
+        assert_eq!(bf6900.status().await, (true, 0));
+        // The stopping service itself still answers without waiting for the stop
+        assert_eq!(meril.status().await, (true, 0));
+        assert!(queried.elapsed() < Duration::from_millis(10), "status blocked for {:?}", queried.elapsed());
+        assert!(!stopping.is_finished());
+
+        stopping.await.unwrap().unwrap();
+        assert_eq!(meril.status().await, (false, 0));
+        assert!(meril.stop().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_toggled_autostart_is_started_by_initialize() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let mut analyzer = crate::test_support::bf6900_analyzer();
+        analyzer.activate_on_start = false;
+
+        let service = Arc::new(RecordingService::default());
+        let controller = ServiceController::new("BF-6900", service.clone());
+        let autostart = repository.get_autostart_settings().await.unwrap();
+        assert!(!controller.autostart(&analyzer, &autostart).await.unwrap());
+        assert_eq!(controller.status().await, (false, 0));
+        assert!(service.calls.lock().unwrap().is_empty());
+
+        repository.set_autostart(&analyzer.id, true).await.unwrap();
+        let autostart = repository.get_autostart_settings().await.unwrap();
+        assert!(controller.autostart(&analyzer, &autostart).await.unwrap());
+        assert_eq!(controller.status().await, (true, 0));
+        assert_eq!(*service.calls.lock().unwrap(), vec!["initialize", "start"]);
+
+        // The stored entry overrides the analyzer's own flag both ways
+        repository.set_autostart(&analyzer.id, false).await.unwrap();
+        analyzer.activate_on_start = true;
+        let autostart = repository.get_autostart_settings().await.unwrap();
+        let service = Arc::new(RecordingService::default());
+        let restarted = ServiceController::new("BF-6900", service.clone());
+        assert!(!restarted.autostart(&analyzer, &autostart).await.unwrap());
+        assert_eq!(restarted.status().await, (false, 0));
+        assert!(service.calls.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_autostart_skips_start_when_initialize_fails() {
+        let analyzer = crate::test_support::bf6900_analyzer();
+        let autostart = HashMap::from([(analyzer.id.clone(), true)]);
+        let service = Arc::new(RecordingService {
+            init_error: Some("No port configured".to_string()),
+            ..Default::default()
+        });
+        let controller = ServiceController::new("BF-6900", service.clone());
+
+        let error = controller.autostart(&analyzer, &autostart).await.unwrap_err();
+        assert_eq!(error, "BF-6900 service failed to initialize: No port configured");
+        assert_eq!(*service.calls.lock().unwrap(), vec!["initialize"]);
+        assert_eq!(controller.status().await, (false, 0));
+    }
+}
@@ -0,0 +1,322 @@
+use std::fmt::Write;
+
+use crate::models::hematology::HL7Settings;
+use crate::models::{Analyzer, Protocol, RemoteAddress};
+use crate::protocol::message_profile::MessageProfile;
+
+// ============================================================================
+// SETUP SHEET
+// ============================================================================
+//
+// A one-page Markdown sheet a field engineer can print and carry to the
+// instrument. Everything on it comes from the live configuration passed in.
+
+/// Live configuration the setup sheet is rendered from
+#[derive(Debug, Clone)]
+pub struct SetupSheetInput {
+    pub analyzer: Analyzer,
+    /// LIS host interface addresses, the selected (default route) one first
+    pub host_ips: Vec<String>,
+    /// Present for HL7 analyzers
+    pub hl7_settings: Option<HL7Settings>,
+    /// Present for HL7 analyzers
+    pub message_profile: Option<MessageProfile>,
+    pub remote_addresses: Vec<RemoteAddress>,
+    pub shadow_mode: bool,
+}
+
+/// Non-loopback interface addresses of this host, the default-route address first
+pub fn host_interface_ips() -> Vec<String> {
+    let mut ips: Vec<String> = Vec::new();
+    match local_ip_address::local_ip() {
+        Ok(ip) => ips.push(ip.to_string()),
+        Err(e) => log::warn!("Failed to get local IP address: {}", e),
+    }
+    match local_ip_address::list_afinet_netifas() {
+        Ok(interfaces) => {
+            for (_, ip) in interfaces {
+                let ip = ip.to_string();
+                if !ip.parse::<std::net::IpAddr>().map(|addr| addr.is_loopback()).unwrap_or(true)
+                    && !ips.contains(&ip)
+                {
+                    ips.push(ip);
+                }
+            }
+        }
+        Err(e) => log::warn!("Failed to list network interfaces: {}", e),
+    }
+    ips
+}
+
+fn is_hl7(protocol: &Protocol) -> bool {
+    protocol.is_hl7()
+}
+
+/// Protocol and version as the instrument menus name them
+fn protocol_label(input: &SetupSheetInput) -> String {
+    let analyzer = &input.analyzer;
+    if analyzer.protocol == Protocol::DelimitedText {
+        return format!("Delimited text, '{}' separated", analyzer.delimited_layout.delimiter);
+    }
+    if is_hl7(&analyzer.protocol) {
+        let version = input
+            .message_profile
+            .as_ref()
+            .map(|profile| profile.version.clone())
+            .unwrap_or_else(|| match analyzer.protocol {
+                Protocol::Hl7V24 => "2.4".to_string(),
+                _ => "2.3.1".to_string(),
+            });
+        format!("HL7 v{}", version)
+    } else {
+        let version = analyzer
+            .astm_version
+            .clone()
+            .unwrap_or_else(|| "E 1394-97 (not yet reported by the analyzer)".to_string());
+        format!("ASTM {}", version)
+    }
+}
+
+fn ack_mode_lines(input: &SetupSheetInput) -> Vec<String> {
+    let mut lines = Vec::new();
+    if is_hl7(&input.analyzer.protocol) {
+        let text = input
+            .message_profile
+            .as_ref()
+            .map(|profile| profile.acknowledgment_text.clone())
+            .unwrap_or_else(|| MessageProfile::default().acknowledgment_text);
+        lines.push(format!(
+            "Automatic: the LIS answers every message with an ACK (MSA-1 `AA`, MSA-3 \"{}\")",
+            text
+        ));
+        lines.push("Enhanced mode is honored when the analyzer sets MSH-15/MSH-16; otherwise original mode".to_string());
+    } else {
+        lines.push("ACK (0x06) after ENQ and after every valid frame".to_string());
+        if input.shadow_mode {
+            lines.push("Shadow mode is on: invalid frames are also ACKed, never NAKed".to_string());
+        } else {
+            lines.push("NAK (0x15) on checksum or frame-number errors; the analyzer retransmits".to_string());
+        }
+    }
+    lines
+}
+
+/// Instrument-side settings derived from the analyzer model's defaults and the live config
+fn checklist(input: &SetupSheetInput) -> Vec<String> {
+    let analyzer = &input.analyzer;
+    let host = input.host_ips.first().cloned().unwrap_or_else(|| "<LIS host IP>".to_string());
+    let port = analyzer
+        .port
+        .map(|port| port.to_string())
+        .unwrap_or_else(|| "<not configured>".to_string());
+
+    let mut items = vec![
+        "Network cable connected; analyzer has a static IP on the LIS subnet".to_string(),
+        format!("LIS / host IP address: {}", host),
+        format!("LIS / host port: {}", port),
+        "Connection role: analyzer is the TCP client, the LIS listens".to_string(),
+        format!("Communication protocol: {}", protocol_label(input)),
+    ];
+
+    if is_hl7(&analyzer.protocol) {
+        let settings = input.hl7_settings.clone().unwrap_or_default();
+        items.push("MLLP framing enabled".to_string());
+        items.push(format!("Character encoding: {}", settings.encoding));
+        items.push("Wait for LIS ACK: On".to_string());
+        items.push("Auto-upload results after validation: On".to_string());
+        if let Some(profile) = &input.message_profile {
+            items.push(format!(
+                "Receiving application / facility: {} / {}",
+                profile.sending_application, profile.sending_facility
+            ));
+        }
+    } else {
+        items.push("Checksum: enabled".to_string());
+        items.push("Auto-transmit results after validation: On".to_string());
+        items.push(format!(
+            "Sender id (H.5): {}",
+            analyzer
+                .astm_sender_id
+                .clone()
+                .unwrap_or_else(|| format!("{}^{}", analyzer.name, analyzer.model))
+        ));
+    }
+    items
+}
+
+/// Renders the setup sheet as Markdown
+pub fn render_setup_sheet(input: &SetupSheetInput) -> String {
+    let analyzer = &input.analyzer;
+    let mut sheet = String::new();
+
+    let _ = writeln!(sheet, "# Setup sheet: {} {}", analyzer.name, analyzer.model);
+    let _ = writeln!(sheet);
+    let _ = writeln!(sheet, "Analyzer id: `{}`", analyzer.id);
+    if let Some(serial) = &analyzer.serial_number {
+        let _ = writeln!(sheet, "Serial number: {}", serial);
+    }
+    let _ = writeln!(sheet);
+
+    let _ = writeln!(sheet, "## LIS host");
+    let _ = writeln!(sheet);
+    if input.host_ips.is_empty() {
+        let _ = writeln!(sheet, "- No network interface found");
+    }
+    for (index, ip) in input.host_ips.iter().enumerate() {
+        let marker = if index == 0 { " (selected)" } else { "" };
+        let _ = writeln!(sheet, "- {}{}", ip, marker);
+    }
+    let _ = writeln!(sheet);
+
+    let _ = writeln!(sheet, "## Connection");
+    let _ = writeln!(sheet);
+    let _ = writeln!(sheet, "| Setting | Value |");
+    let _ = writeln!(sheet, "|---|---|");
+    let _ = writeln!(sheet, "| Connection type | {:?} |", analyzer.connection_type);
+    let _ = writeln!(
+        sheet,
+        "| Port | {} |",
+        analyzer.port.map(|port| port.to_string()).unwrap_or_else(|| "-".to_string())
+    );
+    let _ = writeln!(sheet, "| Protocol | {} |", protocol_label(input));
+    let _ = writeln!(sheet, "| Start with LIS | {} |", if analyzer.activate_on_start { "Yes" } else { "No" });
+    let _ = writeln!(sheet);
+
+    let _ = writeln!(sheet, "## Framing");
+    let _ = writeln!(sheet);
+    if is_hl7(&analyzer.protocol) {
+        let _ = writeln!(sheet, "- MLLP: each message is `<VT>` (0x0B) ... `<FS><CR>` (0x1C 0x0D)");
+        let _ = writeln!(sheet, "- Segments end with `<CR>`; delimiters `|^~\\&`");
+    } else if analyzer.protocol == Protocol::DelimitedText {
+        let layout = &analyzer.delimited_layout;
+        let _ = writeln!(sheet, "- One result per line, fields separated by `{}`", layout.delimiter);
+        let _ = writeln!(
+            sheet,
+            "- Columns (from 0): sample id {}, test {}, value {}",
+            layout.sample_id_column, layout.test_column, layout.value_column
+        );
+    } else {
+        let _ = writeln!(sheet, "- ASTM E1381: ENQ, frames `<STX>n...<ETX|ETB>cc<CR><LF>`, EOT");
+        let _ = writeln!(sheet, "- Frame numbers 1-7 then 0; two-digit hex checksum per frame");
+        let _ = writeln!(sheet, "- Delimiters declared by the header record, normally `|\\^&`");
+    }
+    let _ = writeln!(sheet);
+
+    let _ = writeln!(sheet, "## Expected messages");
+    let _ = writeln!(sheet);
+    if is_hl7(&analyzer.protocol) {
+        let settings = input.hl7_settings.clone().unwrap_or_default();
+        for message_type in &settings.supported_message_types {
+            let _ = writeln!(sheet, "- {}", message_type);
+        }
+    } else {
+        let _ = writeln!(sheet, "- H (header), P (patient), O (order), R (result), C (comment), L (terminator)");
+    }
+    let _ = writeln!(sheet);
+
+    let _ = writeln!(sheet, "## Acknowledgments");
+    let _ = writeln!(sheet);
+    for line in ack_mode_lines(input) {
+        let _ = writeln!(sheet, "- {}", line);
+    }
+    let _ = writeln!(sheet);
+
+    let _ = writeln!(sheet, "## Allowed analyzer addresses");
+    let _ = writeln!(sheet);
+    let _ = writeln!(
+        sheet,
+        "Strict remote address: {}",
+        if analyzer.strict_remote_address {
+            "On (unapproved addresses are held)"
+        } else {
+            "Off (any address is accepted)"
+        }
+    );
+    let _ = writeln!(sheet);
+    let approved: Vec<&RemoteAddress> = input.remote_addresses.iter().filter(|address| address.approved).collect();
+    if approved.is_empty() {
+        let _ = writeln!(sheet, "- None approved yet");
+    }
+    for address in approved {
+        let _ = writeln!(
+            sheet,
+            "- {} (last seen {})",
+            address.ip_address,
+            address.last_seen.format("%Y-%m-%d %H:%M")
+        );
+    }
+    let _ = writeln!(sheet);
+
+    let _ = writeln!(sheet, "## Instrument checklist");
+    let _ = writeln!(sheet);
+    for item in checklist(input) {
+        let _ = writeln!(sheet, "- [ ] {}", item);
+    }
+
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn analyzer(protocol: Protocol, port: u16) -> Analyzer {
+        Analyzer {
+            id: "bf6900-1".to_string(),
+            port: Some(port),
+            protocol,
+            strict_remote_address: true,
+            ..crate::test_support::bf6900_analyzer()
+        }
+    }
+
+    fn input(analyzer: Analyzer, hl7_settings: Option<HL7Settings>) -> SetupSheetInput {
+        SetupSheetInput {
+            analyzer,
+            host_ips: vec!["192.168.1.20".to_string(), "10.0.0.4".to_string()],
+            message_profile: hl7_settings.as_ref().map(|_| MessageProfile::default()),
+            hl7_settings,
+            remote_addresses: vec![RemoteAddress {
+                analyzer_id: "bf6900-1".to_string(),
+                ip_address: "192.168.1.50".to_string(),
+                approved: true,
+                first_seen: Utc::now(),
+                last_seen: Utc::now(),
+            }],
+            shadow_mode: false,
+        }
+    }
+
+    #[test]
+    fn test_sheet_reflects_modified_port() {
+        let default_sheet = render_setup_sheet(&input(analyzer(Protocol::Hl7V231, 9100), Some(HL7Settings::default())));
+        assert!(default_sheet.contains("| Port | 9100 |"));
+        assert!(default_sheet.contains("Automatic: the LIS answers every message"));
+        assert!(default_sheet.contains("Wait for LIS ACK: On"));
+
+        let sheet = render_setup_sheet(&input(analyzer(Protocol::Hl7V231, 9200), Some(HL7Settings::default())));
+
+        assert!(sheet.contains("| Port | 9200 |"));
+        assert!(sheet.contains("LIS / host port: 9200"));
+        assert!(!sheet.contains("9100"));
+        assert!(sheet.contains("- 192.168.1.20 (selected)"));
+        assert!(sheet.contains("- 192.168.1.50"));
+        assert!(sheet.contains("HL7 v2.3.1"));
+        assert!(sheet.contains("- ORU^R01"));
+    }
+
+    #[test]
+    fn test_astm_sheet_uses_reported_version_and_shadow_ack() {
+        let mut astm = analyzer(Protocol::Astm, 5601);
+        astm.astm_version = Some("LIS2-A2".to_string());
+        let mut sheet_input = input(astm, None);
+        sheet_input.shadow_mode = true;
+
+        let sheet = render_setup_sheet(&sheet_input);
+        assert!(sheet.contains("| Port | 5601 |"));
+        assert!(sheet.contains("ASTM LIS2-A2"));
+        assert!(sheet.contains("invalid frames are also ACKed, never NAKed"));
+        assert!(!sheet.contains("MLLP"));
+    }
+}
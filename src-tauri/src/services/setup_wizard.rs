@@ -0,0 +1,539 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::api::commands::bf6900_handler::{validate_bf6900_config, BF6900StoreData};
+use crate::api::commands::meril_handler::{validate_meril_config, MerilStoreData};
+use crate::models::Analyzer;
+use crate::services::app_paths::DirectoryCheck;
+use crate::services::config_persistence::ConfigPersistence;
+use crate::services::conversation_log::{ConnectionHistorySettings, CONNECTION_HISTORY_STORE_KEY};
+use crate::services::his_client::{HisApiConfig, HIS_CONFIG_STORE_KEY};
+
+/// Key in the app settings store (`settings.json`) holding the wizard's progress
+pub const SETUP_STORE_KEY: &str = "setup";
+
+/// Key in the app settings store holding the lab identity
+pub const LAB_IDENTITY_STORE_KEY: &str = "lab_identity";
+
+/// Key in the app settings store holding the operator accounts
+pub const USERS_STORE_KEY: &str = "users";
+
+/// Analyzer config key in `meril.json` / `bf6900.json`
+const ANALYZER_CONFIG_KEY: &str = "config";
+
+// ============================================================================
+// SECTIONS
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SetupSection {
+    LabIdentity,
+    Analyzers,
+    His,
+    Users,
+    Retention,
+}
+
+impl SetupSection {
+    /// Wizard order
+    pub const ALL: [SetupSection; 5] = [
+        SetupSection::LabIdentity,
+        SetupSection::Analyzers,
+        SetupSection::His,
+        SetupSection::Users,
+        SetupSection::Retention,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SetupSection::LabIdentity => "Lab identity",
+            SetupSection::Analyzers => "Analyzers",
+            SetupSection::His => "HIS connection",
+            SetupSection::Users => "Users",
+            SetupSection::Retention => "Retention",
+        }
+    }
+
+    /// Settings store key of sections kept in `settings.json`
+    fn settings_key(&self) -> Option<&'static str> {
+        match self {
+            SetupSection::LabIdentity => Some(LAB_IDENTITY_STORE_KEY),
+            SetupSection::Analyzers => None,
+            SetupSection::His => Some(HIS_CONFIG_STORE_KEY),
+            SetupSection::Users => Some(USERS_STORE_KEY),
+            SetupSection::Retention => Some(CONNECTION_HISTORY_STORE_KEY),
+        }
+    }
+}
+
+/// Name and HL7 facility code of the laboratory
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LabIdentity {
+    pub name: String,
+    pub facility_code: String,
+}
+
+impl LabIdentity {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Lab name is required".to_string());
+        }
+        let code = self.facility_code.trim();
+        if code.is_empty() || code.len() > 20 {
+            return Err("Facility code must be 1 to 20 characters".to_string());
+        }
+        if code.contains(['|', '^', '~', '\\', '&']) {
+            return Err(format!("Facility code cannot contain HL7 delimiters: {}", code));
+        }
+        Ok(())
+    }
+}
+
+/// Role of an operator account
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OperatorRole {
+    #[default]
+    Technologist,
+    Admin,
+}
+
+/// An operator allowed to use this workstation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OperatorAccount {
+    pub username: String,
+    pub display_name: String,
+    pub role: OperatorRole,
+}
+
+/// Reads the operator accounts from the store; none when missing or invalid
+pub fn operators_from_store(stored: Option<JsonValue>) -> Vec<OperatorAccount> {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid operator accounts: {}", e);
+            Vec::new()
+        }),
+        None => Vec::new(),
+    }
+}
+
+/// The account with `username`, compared as the wizard does: trimmed, ignoring case
+pub fn find_operator<'a>(users: &'a [OperatorAccount], username: &str) -> Option<&'a OperatorAccount> {
+    let username = username.trim().to_lowercase();
+    users.iter().find(|user| user.username.trim().to_lowercase() == username)
+}
+
+fn validate_users(users: &[OperatorAccount]) -> Result<(), String> {
+    let mut seen = std::collections::HashSet::new();
+    for user in users {
+        let username = user.username.trim().to_lowercase();
+        if username.is_empty() {
+            return Err("Every user needs a username".to_string());
+        }
+        if !seen.insert(username) {
+            return Err(format!("Duplicate username: {}", user.username));
+        }
+    }
+    if !users.iter().any(|user| user.role == OperatorRole::Admin) {
+        return Err("At least one user must have the admin role".to_string());
+    }
+    Ok(())
+}
+
+/// Analyzer step; an analyzer left out keeps its current configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AnalyzerSetup {
+    pub meril: Option<Analyzer>,
+    pub bf6900: Option<Analyzer>,
+}
+
+/// A parsed and validated step payload
+enum SetupStep {
+    LabIdentity(LabIdentity),
+    Analyzers(AnalyzerSetup),
+    His(HisApiConfig),
+    Users(Vec<OperatorAccount>),
+    Retention(ConnectionHistorySettings),
+}
+
+impl SetupStep {
+    fn parse(section: SetupSection, payload: &JsonValue) -> Result<SetupStep, String> {
+        fn parse<T: serde::de::DeserializeOwned>(section: SetupSection, payload: &JsonValue) -> Result<T, String> {
+            serde_json::from_value(payload.clone())
+                .map_err(|e| format!("Invalid {} settings: {}", section.label(), e))
+        }
+
+        let step = match section {
+            SetupSection::LabIdentity => {
+                let identity: LabIdentity = parse(section, payload)?;
+                identity.validate()?;
+                SetupStep::LabIdentity(identity)
+            }
+            SetupSection::Analyzers => {
+                let analyzers: AnalyzerSetup = parse(section, payload)?;
+                if analyzers.meril.is_none() && analyzers.bf6900.is_none() {
+                    return Err("Configure at least one analyzer".to_string());
+                }
+                if let Some(analyzer) = &analyzers.meril {
+                    validate_meril_config(analyzer)?;
+                }
+                if let Some(analyzer) = &analyzers.bf6900 {
+                    validate_bf6900_config(analyzer)?;
+                }
+                SetupStep::Analyzers(analyzers)
+            }
+            SetupSection::His => {
+                let config: HisApiConfig = parse(section, payload)?;
+                config.validate()?;
+                SetupStep::His(config)
+            }
+            SetupSection::Users => {
+                let users: Vec<OperatorAccount> = parse(section, payload)?;
+                validate_users(&users)?;
+                SetupStep::Users(users)
+            }
+            SetupSection::Retention => {
+                let settings: ConnectionHistorySettings = parse(section, payload)?;
+                if settings.recent_limit == 0 {
+                    return Err("Keep at least one closed connection in memory".to_string());
+                }
+                SetupStep::Retention(settings)
+            }
+        };
+        Ok(step)
+    }
+}
+
+// ============================================================================
+// WIZARD STATE
+// ============================================================================
+
+/// The stores the wizard reads and writes
+pub struct SetupStores<'a> {
+    pub settings: &'a dyn ConfigPersistence,
+    pub meril: &'a dyn ConfigPersistence,
+    pub bf6900: &'a dyn ConfigPersistence,
+}
+
+/// Wizard progress as persisted in the settings store
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SetupRecord {
+    /// Sections applied through the wizard
+    pub configured: Vec<SetupSection>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SetupSectionState {
+    pub section: SetupSection,
+    pub configured: bool,
+    /// Current configuration, to pre-populate the step
+    pub current: Option<JsonValue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SetupState {
+    pub completed_at: Option<DateTime<Utc>>,
+    pub sections: Vec<SetupSectionState>,
+}
+
+impl SetupState {
+    pub fn unconfigured(&self) -> Vec<SetupSection> {
+        self.sections
+            .iter()
+            .filter(|state| !state.configured)
+            .map(|state| state.section)
+            .collect()
+    }
+}
+
+fn setup_record(stores: &SetupStores) -> SetupRecord {
+    match stores.settings.get(SETUP_STORE_KEY) {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid setup record: {}", e);
+            SetupRecord::default()
+        }),
+        None => SetupRecord::default(),
+    }
+}
+
+/// Both analyzer stores keep the analyzer under `config.analyzer`
+fn stored_analyzer(store: &dyn ConfigPersistence) -> Option<Analyzer> {
+    store
+        .get(ANALYZER_CONFIG_KEY)
+        .and_then(|value| serde_json::from_value::<MerilStoreData>(value).ok())
+        .and_then(|data| data.analyzer)
+}
+
+/// Current configuration of a section; defaults stand in for HIS and retention
+/// since the app runs with those until they are set
+fn current_section(stores: &SetupStores, section: SetupSection) -> Option<JsonValue> {
+    let stored = section.settings_key().and_then(|key| stores.settings.get(key));
+    match section {
+        SetupSection::LabIdentity | SetupSection::Users => stored,
+        SetupSection::His => stored.or_else(|| serde_json::to_value(HisApiConfig::default()).ok()),
+        SetupSection::Retention => stored.or_else(|| serde_json::to_value(ConnectionHistorySettings::default()).ok()),
+        SetupSection::Analyzers => serde_json::to_value(AnalyzerSetup {
+            meril: stored_analyzer(stores.meril),
+            bf6900: stored_analyzer(stores.bf6900),
+        })
+        .ok(),
+    }
+}
+
+/// Which sections are configured, with their current values. Analyzers count only
+/// once applied through the wizard, as the services persist their defaults on start.
+pub fn setup_state(stores: &SetupStores) -> SetupState {
+    let record = setup_record(stores);
+    let sections = SetupSection::ALL
+        .iter()
+        .map(|&section| SetupSectionState {
+            section,
+            configured: record.configured.contains(&section)
+                || section.settings_key().is_some_and(|key| stores.settings.get(key).is_some()),
+            current: current_section(stores, section),
+        })
+        .collect();
+
+    SetupState {
+        completed_at: record.completed_at,
+        sections,
+    }
+}
+
+/// Validates a step payload without saving it
+pub fn validate_setup_step(section: SetupSection, payload: &JsonValue) -> Result<(), String> {
+    SetupStep::parse(section, payload).map(|_| ())
+}
+
+/// Validates and saves one section. Nothing is written unless the whole payload is
+/// valid. Analyzer changes take effect when the services next start.
+pub fn apply_setup_step(
+    stores: &SetupStores,
+    section: SetupSection,
+    payload: &JsonValue,
+) -> Result<SetupState, String> {
+    fn to_value<T: Serialize>(section: SetupSection, value: &T) -> Result<JsonValue, String> {
+        serde_json::to_value(value)
+            .map_err(|e| format!("Failed to serialize {} settings: {}", section.label(), e))
+    }
+
+    let step = SetupStep::parse(section, payload)?;
+
+    // Collect every write first so a serialization error leaves the stores untouched
+    let mut settings_writes: Vec<(&str, JsonValue)> = Vec::new();
+    let mut meril_write = None;
+    let mut bf6900_write = None;
+    match step {
+        SetupStep::LabIdentity(identity) => {
+            settings_writes.push((LAB_IDENTITY_STORE_KEY, to_value(section, &identity)?))
+        }
+        SetupStep::His(config) => settings_writes.push((HIS_CONFIG_STORE_KEY, to_value(section, &config)?)),
+        SetupStep::Users(users) => settings_writes.push((USERS_STORE_KEY, to_value(section, &users)?)),
+        SetupStep::Retention(settings) => {
+            settings_writes.push((CONNECTION_HISTORY_STORE_KEY, to_value(section, &settings)?))
+        }
+        SetupStep::Analyzers(analyzers) => {
+            if let Some(mut analyzer) = analyzers.meril {
+                analyzer.updated_at = Utc::now();
+                meril_write = Some(to_value(section, &MerilStoreData { analyzer: Some(analyzer) })?);
+            }
+            if let Some(mut analyzer) = analyzers.bf6900 {
+                analyzer.updated_at = Utc::now();
+                // Keep the HL7 settings, which the wizard does not cover
+                let hl7_settings = stores
+                    .bf6900
+                    .get(ANALYZER_CONFIG_KEY)
+                    .and_then(|value| serde_json::from_value::<BF6900StoreData>(value).ok())
+                    .and_then(|data| data.hl7_settings);
+                bf6900_write = Some(to_value(section, &BF6900StoreData {
+                    analyzer: Some(analyzer),
+                    hl7_settings,
+                })?);
+            }
+        }
+    }
+
+    let mut record = setup_record(stores);
+    if !record.configured.contains(&section) {
+        record.configured.push(section);
+    }
+    settings_writes.push((SETUP_STORE_KEY, to_value(section, &record)?));
+
+    if let Some(value) = meril_write {
+        stores.meril.set(ANALYZER_CONFIG_KEY, value);
+        stores.meril.save()?;
+    }
+    if let Some(value) = bf6900_write {
+        stores.bf6900.set(ANALYZER_CONFIG_KEY, value);
+        stores.bf6900.save()?;
+    }
+    for (key, value) in settings_writes {
+        stores.settings.set(key, value);
+    }
+    stores.settings.save()?;
+
+    log::info!("Setup section {} saved", section.label());
+    Ok(setup_state(stores))
+}
+
+/// Problems that keep setup from completing: required directories the startup check
+/// could not write, unconfigured sections and stored configuration that no longer validates
+pub fn check_setup_readiness(stores: &SetupStores, directories: &[DirectoryCheck]) -> Vec<String> {
+    let state = setup_state(stores);
+    let mut problems: Vec<String> = directories
+        .iter()
+        .filter_map(|check| {
+            let error = check.error.as_ref()?;
+            Some(format!("Directory for {} is not writable: {}", check.purpose, error))
+        })
+        .collect();
+    for section_state in &state.sections {
+        let section = section_state.section;
+        if !section_state.configured {
+            problems.push(format!("{} is not configured", section.label()));
+            continue;
+        }
+        let current = section_state.current.clone().unwrap_or(JsonValue::Null);
+        if let Err(e) = validate_setup_step(section, &current) {
+            problems.push(format!("{}: {}", section.label(), e));
+        }
+    }
+    problems
+}
+
+/// Records setup as complete once every section is ready, so the wizard stops showing
+pub fn complete_setup(stores: &SetupStores, directories: &[DirectoryCheck]) -> Result<SetupState, String> {
+    let problems = check_setup_readiness(stores, directories);
+    if !problems.is_empty() {
+        return Err(format!("Setup is not complete: {}", problems.join("; ")));
+    }
+
+    let mut record = setup_record(stores);
+    record.completed_at = Some(Utc::now());
+    let value = serde_json::to_value(&record)
+        .map_err(|e| format!("Failed to serialize setup record: {}", e))?;
+    stores.settings.set(SETUP_STORE_KEY, value);
+    stores.settings.save()?;
+
+    log::info!("First-run setup completed");
+    Ok(setup_state(stores))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::hematology::HL7Settings;
+    use crate::services::config_persistence::InMemoryConfigStore;
+    use serde_json::json;
+
+    #[test]
+    fn test_wizard_walkthrough_keeps_prior_sections_on_validation_failure() {
+        let settings = InMemoryConfigStore::new();
+        let meril = InMemoryConfigStore::new();
+        let bf6900 = InMemoryConfigStore::new();
+        let stores = SetupStores { settings: &settings, meril: &meril, bf6900: &bf6900 };
+
+        let state = setup_state(&stores);
+        assert_eq!(state.unconfigured(), SetupSection::ALL.to_vec());
+        assert!(state.completed_at.is_none());
+
+        apply_setup_step(
+            &stores,
+            SetupSection::LabIdentity,
+            &json!({ "name": "NRAMH Central Lab", "facility_code": "NRAMH" }),
+        )
+        .unwrap();
+
+        let mut bf6900_analyzer = crate::test_support::bf6900_analyzer();
+        bf6900_analyzer.ip_address = Some("192.168.1.50".to_string());
+        let analyzers = AnalyzerSetup {
+            meril: Some(crate::test_support::meril_analyzer()),
+            bf6900: Some(bf6900_analyzer),
+        };
+        apply_setup_step(&stores, SetupSection::Analyzers, &serde_json::to_value(&analyzers).unwrap()).unwrap();
+
+        let his = HisApiConfig { base_url: "https://his.example.org/lis".to_string(), ..HisApiConfig::default() };
+        apply_setup_step(&stores, SetupSection::His, &serde_json::to_value(&his).unwrap()).unwrap();
+
+        let users = json!([{ "username": "admin", "display_name": "Lab Admin", "role": "Admin" }]);
+        apply_setup_step(&stores, SetupSection::Users, &users).unwrap();
+
+        // Retention is still missing, so setup cannot complete
+        let error = complete_setup(&stores, &[]).unwrap_err();
+        assert!(error.contains("Retention is not configured"));
+
+        // A failed step changes nothing, including the sections saved before it
+        let no_admin = json!([{ "username": "tech", "display_name": "Tech", "role": "Technologist" }]);
+        assert!(validate_setup_step(SetupSection::Users, &no_admin).is_err());
+        assert!(apply_setup_step(&stores, SetupSection::Users, &no_admin).is_err());
+        assert!(apply_setup_step(&stores, SetupSection::His, &json!({ "base_url": "ftp://his" })).is_err());
+        assert_eq!(settings.get(USERS_STORE_KEY), Some(users.clone()));
+        assert_eq!(settings.get(HIS_CONFIG_STORE_KEY), Some(serde_json::to_value(&his).unwrap()));
+        assert_eq!(setup_state(&stores).unconfigured(), vec![SetupSection::Retention]);
+
+        apply_setup_step(&stores, SetupSection::Retention, &json!({ "recent_limit": 100 })).unwrap();
+
+        // A directory the startup check could not write keeps setup from completing
+        let unwritable = DirectoryCheck {
+            purpose: "logs".to_string(),
+            path: "/var/lib/nramh-lis/logs".to_string(),
+            error: Some("Failed to write /var/lib/nramh-lis/logs/.nramh-lis-write-probe: Permission denied".to_string()),
+            checked_at: Utc::now(),
+        };
+        assert_eq!(
+            check_setup_readiness(&stores, std::slice::from_ref(&unwritable)),
+            vec!["Directory for logs is not writable: Failed to write /var/lib/nramh-lis/logs/.nramh-lis-write-probe: Permission denied"]
+        );
+        assert!(complete_setup(&stores, &[unwritable]).is_err());
+        assert!(setup_state(&stores).completed_at.is_none());
+
+        let state = complete_setup(&stores, &[]).unwrap();
+        assert!(state.completed_at.is_some());
+        assert!(state.unconfigured().is_empty());
+
+        // Re-running the wizard starts from the saved configuration
+        let current = |section: SetupSection| {
+            state.sections.iter().find(|s| s.section == section).unwrap().current.clone().unwrap()
+        };
+        assert_eq!(current(SetupSection::LabIdentity)["facility_code"], "NRAMH");
+        assert_eq!(current(SetupSection::Users), users);
+        assert_eq!(current(SetupSection::His)["base_url"], "https://his.example.org/lis");
+        assert_eq!(current(SetupSection::Analyzers)["bf6900"]["ip_address"], "192.168.1.50");
+        assert_eq!(current(SetupSection::Retention)["recent_limit"], 100);
+    }
+
+    #[test]
+    fn test_analyzer_step_uses_analyzer_validators_and_keeps_hl7_settings() {
+        let settings = InMemoryConfigStore::new();
+        let meril = InMemoryConfigStore::new();
+        let bf6900 = InMemoryConfigStore::new();
+        let stores = SetupStores { settings: &settings, meril: &meril, bf6900: &bf6900 };
+        let hl7_settings = HL7Settings { retry_attempts: 7, ..HL7Settings::default() };
+        bf6900.set(
+            ANALYZER_CONFIG_KEY,
+            json!({ "analyzer": null, "hl7_settings": serde_json::to_value(&hl7_settings).unwrap() }),
+        );
+
+        let mut meril_analyzer = crate::test_support::meril_analyzer();
+        meril_analyzer.ip_address = Some("not-an-ip".to_string());
+        let invalid = AnalyzerSetup { meril: Some(meril_analyzer), bf6900: None };
+        let error = validate_setup_step(SetupSection::Analyzers, &serde_json::to_value(&invalid).unwrap()).unwrap_err();
+        assert!(error.contains("Invalid IP address format"));
+        assert!(validate_setup_step(SetupSection::Analyzers, &json!({})).is_err());
+
+        let valid = AnalyzerSetup {
+            meril: None,
+            bf6900: Some(crate::test_support::bf6900_analyzer()),
+        };
+        apply_setup_step(&stores, SetupSection::Analyzers, &serde_json::to_value(&valid).unwrap()).unwrap();
+
+        let stored: BF6900StoreData = serde_json::from_value(bf6900.get(ANALYZER_CONFIG_KEY).unwrap()).unwrap();
+        assert!(stored.analyzer.is_some());
+        assert_eq!(stored.hl7_settings.unwrap().retry_attempts, 7);
+        assert!(meril.get(ANALYZER_CONFIG_KEY).is_none());
+    }
+}
@@ -0,0 +1,39 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Key in the app settings store (`settings.json`) holding the shadow-mode flag
+pub const SHADOW_MODE_STORE_KEY: &str = "shadow_mode";
+
+/// Global store-only switch for validation/shadow deployments.
+///
+/// While enabled, everything received is still parsed and stored, but nothing
+/// outbound changes analyzer or HIS behavior: frames that fail validation are
+/// answered with a plain ACK instead of NAK/AE, and no HIS uploads are queued
+/// or sent. Clones share the same flag, so it can be toggled at runtime.
+#[derive(Debug, Clone, Default)]
+pub struct ShadowMode {
+    enabled: Arc<AtomicBool>,
+}
+
+impl ShadowMode {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(enabled)),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        let previous = self.enabled.swap(enabled, Ordering::SeqCst);
+        if previous != enabled {
+            if enabled {
+                log::warn!("Shadow mode enabled: HIS uploads and analyzer NAKs are suppressed");
+            } else {
+                log::info!("Shadow mode disabled: normal outbound traffic resumed");
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::TestResult;
+
+/// Key in the app settings store (`settings.json`) holding the stale-result threshold
+pub const STALE_RESULTS_STORE_KEY: &str = "stale_results";
+
+/// How old results may be when ingested and still upload to the HIS without review,
+/// e.g. when the app was down and the analyzer's memory is replayed afterwards
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct StaleResultSettings {
+    /// Hours between completion (analyzer clock, skew corrected) and ingestion beyond
+    /// which a sample's results are held for review; `None` uploads results of any age
+    pub max_age_hours: Option<u32>,
+}
+
+impl Default for StaleResultSettings {
+    fn default() -> Self {
+        Self { max_age_hours: Some(72) }
+    }
+}
+
+/// Reads the stored threshold, falling back to the default when missing or invalid
+pub fn stale_results_from_store(stored: Option<serde_json::Value>) -> StaleResultSettings {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid stale result settings: {}", e);
+            StaleResultSettings::default()
+        }),
+        None => StaleResultSettings::default(),
+    }
+}
+
+impl StaleResultSettings {
+    /// Why a sample's results ingested at `ingested_at` are stale, or `None` when
+    /// every result is fresh. A result exactly at the threshold is still fresh.
+    pub fn stale_reason(&self, results: &[TestResult], ingested_at: DateTime<Utc>) -> Option<String> {
+        let max_age_hours = self.max_age_hours?;
+        let oldest = results.iter().filter_map(|result| result.completed_date_time).min()?;
+        let age = ingested_at - oldest;
+        (age > Duration::hours(max_age_hours as i64)).then(|| {
+            format!(
+                "Completed {} hours before it was received, more than the {} hour limit",
+                age.num_hours(),
+                max_age_hours
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn completed(at: DateTime<Utc>) -> TestResult {
+        TestResult {
+            completed_date_time: Some(at),
+            analyzer_id: Some("meril-001".to_string()),
+            created_at: at,
+            updated_at: at,
+            ..TestResult::fixture("R1")
+        }
+    }
+
+    #[test]
+    fn test_fresh_borderline_and_stale_results() {
+        let settings = StaleResultSettings { max_age_hours: Some(72) };
+        let ingested_at = Utc.with_ymd_and_hms(2025, 3, 8, 9, 0, 0).unwrap();
+
+        let fresh = completed(ingested_at - Duration::hours(2));
+        assert_eq!(settings.stale_reason(&[fresh.clone()], ingested_at), None);
+
+        let borderline = completed(ingested_at - Duration::hours(72));
+        assert_eq!(settings.stale_reason(&[borderline], ingested_at), None);
+
+        // One stale result makes the sample stale
+        let stale = completed(ingested_at - Duration::hours(72) - Duration::seconds(1));
+        let reason = settings.stale_reason(&[fresh, stale.clone()], ingested_at).unwrap();
+        assert_eq!(reason, "Completed 72 hours before it was received, more than the 72 hour limit");
+
+        // No threshold, or no completion date, never holds
+        assert_eq!(StaleResultSettings { max_age_hours: None }.stale_reason(&[stale], ingested_at), None);
+        let mut undated = completed(ingested_at);
+        undated.completed_date_time = None;
+        assert_eq!(settings.stale_reason(&[undated], ingested_at), None);
+    }
+
+    #[test]
+    fn test_settings_fall_back_to_default() {
+        assert_eq!(stale_results_from_store(None), StaleResultSettings::default());
+        assert_eq!(
+            stale_results_from_store(Some(serde_json::json!({ "max_age_hours": "soon" }))),
+            StaleResultSettings::default()
+        );
+        assert_eq!(
+            stale_results_from_store(Some(serde_json::json!({ "max_age_hours": null }))),
+            StaleResultSettings { max_age_hours: None }
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tauri_plugin_store::{Store, StoreExt};
+
+use crate::services::app_paths::{ensure_dir, with_file_name_suffix};
+
+/// A store file that could not be read and was replaced by an empty store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreRecovery {
+    pub store_path: String,
+    /// Where the unreadable file was moved, for inspection
+    pub corrupt_copy_path: String,
+    pub error: String,
+    pub recovered_at: DateTime<Utc>,
+}
+
+/// Checks a store file before the store plugin loads it. A file that is not a JSON
+/// object is renamed to `<name>.corrupt-<timestamp>` so the store starts empty and
+/// every setting falls back to its default. Missing files are fine.
+pub fn quarantine_corrupt_store(path: &Path) -> Result<Option<StoreRecovery>, String> {
+    let contents = match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read store {}: {}", path.display(), e)),
+    };
+    let error = match serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(&contents) {
+        Ok(_) => return Ok(None),
+        Err(e) => e.to_string(),
+    };
+
+    let recovered_at = Utc::now();
+    let corrupt_copy = with_file_name_suffix(path, &format!(".corrupt-{}", recovered_at.format("%Y%m%d%H%M%S")));
+    std::fs::rename(path, &corrupt_copy)
+        .map_err(|e| {
+            format!(
+                "Failed to move corrupt store {} aside to {}: {}",
+                path.display(),
+                corrupt_copy.display(),
+                e
+            )
+        })?;
+    log::error!(
+        "Store {} is corrupt ({}); moved to {} and starting with defaults",
+        path.display(),
+        error,
+        corrupt_copy.display()
+    );
+
+    Ok(Some(StoreRecovery {
+        store_path: path.display().to_string(),
+        corrupt_copy_path: corrupt_copy.display().to_string(),
+        error,
+        recovered_at,
+    }))
+}
+
+/// Opens a store through the plugin, first moving a corrupt file aside. Emits
+/// `store:recovered` with the [`StoreRecovery`] when that happened.
+pub fn open_store<R: Runtime>(app: &AppHandle<R>, file_name: &str) -> Result<Arc<Store<R>>, String> {
+    // The plugin resolves relative store names against the app data dir; the full
+    // path is passed so both sides agree on the file
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Error resolving app data dir: {}", e))?;
+    ensure_dir(&data_dir)?;
+    let path: PathBuf = data_dir.join(file_name);
+
+    if let Some(recovery) = quarantine_corrupt_store(&path)? {
+        let _ = app.emit("store:recovered", &recovery);
+    }
+    app.store(&path)
+        .map_err(|e| format!("Error opening store {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::commands::meril_handler::MerilStoreData;
+    use crate::services::config_persistence::{ConfigPersistence, InMemoryConfigStore};
+
+    fn test_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nramh-lis-store-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_corrupt_store_is_moved_aside_and_defaults_are_used() {
+        let dir = test_dir();
+        let path = dir.join("meril.json");
+        let corrupt = b"{\"config\": {\"analyzer\": {\"id\": \"AQ-1\", \"na";
+        std::fs::write(&path, corrupt).unwrap();
+
+        let recovery = quarantine_corrupt_store(&path).unwrap().unwrap();
+        assert!(!path.exists());
+        assert_eq!(std::fs::read(&recovery.corrupt_copy_path).unwrap(), corrupt);
+        assert!(recovery.corrupt_copy_path.contains("meril.json.corrupt-"));
+
+        // The store then starts empty, so config loading takes the default analyzer
+        let store = InMemoryConfigStore::new();
+        let stored = store
+            .get("config")
+            .and_then(|value| serde_json::from_value::<MerilStoreData>(value).ok())
+            .and_then(|data| data.analyzer);
+        assert!(stored.is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_readable_or_missing_store_is_left_alone() {
+        let dir = test_dir();
+        let path = dir.join("settings.json");
+        assert!(quarantine_corrupt_store(&path).unwrap().is_none());
+
+        std::fs::write(&path, b"{\"shadow_mode\": true}").unwrap();
+        assert!(quarantine_corrupt_store(&path).unwrap().is_none());
+        assert!(path.exists());
+
+        // Valid JSON that is not an object is not a store either
+        std::fs::write(&path, b"[]").unwrap();
+        assert!(quarantine_corrupt_store(&path).unwrap().is_some());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,459 @@
+use chrono::{DateTime, Local, Utc};
+use uuid::Uuid;
+
+use crate::db::SqliteRepository;
+use crate::models::{
+    CorrectionPattern, RemediationCandidate, RemediationReport, ResultUploadStatus, TestResult, UploadPriority,
+    UploadRemediation,
+};
+use crate::services::his_client::{HisApiPayload, HisClient, PayloadCorrection};
+use crate::services::upload_worker::UploadWorker;
+
+/// Key in the app settings store (`settings.json`) holding the HIS correction pattern
+pub const HIS_CORRECTION_PATTERN_STORE_KEY: &str = "his_correction_pattern";
+
+/// Reads the stored correction pattern, falling back to corrected results when missing or invalid
+pub fn correction_pattern_from_store(stored: Option<serde_json::Value>) -> CorrectionPattern {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid HIS correction pattern: {}", e);
+            CorrectionPattern::default()
+        }),
+        None => CorrectionPattern::default(),
+    }
+}
+
+// ============================================================================
+// REMAP AND RE-UPLOAD
+// ============================================================================
+
+/// Uploads of one test code to fix, sent between `from` and `to`
+#[derive(Debug, Clone)]
+pub struct RemapRequest {
+    pub test_code: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub pattern: CorrectionPattern,
+    pub dry_run: bool,
+}
+
+/// An affected upload with what is needed to rebuild it
+struct AffectedUpload {
+    upload: ResultUploadStatus,
+    sent: HisApiPayload,
+    results: Vec<TestResult>,
+    candidate: RemediationCandidate,
+}
+
+/// Finds samples uploaded with `test_code` under a name other than its current HIS mapping
+/// and, unless this is a dry run, queues their correction in the configured pattern.
+///
+/// Each fixed upload gets a remediation row linking it to the uploads that replaced it,
+/// so running the same remediation twice does not send the corrections again.
+pub async fn remap_and_reupload(
+    repository: &SqliteRepository,
+    his_client: &HisClient,
+    upload_worker: &UploadWorker,
+    request: &RemapRequest,
+) -> Result<RemediationReport, String> {
+    let corrected_name = his_client.map_test_name(&request.test_code);
+    let affected = find_affected_uploads(repository, his_client, request, &corrected_name).await?;
+    log::info!(
+        "{} uploads sent {} under a name other than '{}' between {} and {}",
+        affected.len(),
+        request.test_code,
+        corrected_name,
+        request.from,
+        request.to
+    );
+
+    let mut report = RemediationReport {
+        test_code: request.test_code.clone(),
+        corrected_name,
+        pattern: request.pattern,
+        dry_run: request.dry_run,
+        affected: affected.iter().map(|a| a.candidate.clone()).collect(),
+        queued: 0,
+        skipped: 0,
+    };
+    if request.dry_run {
+        return Ok(report);
+    }
+
+    for affected in affected {
+        if queue_correction(repository, his_client, upload_worker, request, affected).await? {
+            report.queued += 1;
+        } else {
+            report.skipped += 1;
+        }
+    }
+    log::info!(
+        "Remediation of {}: {} corrections queued, {} skipped",
+        request.test_code,
+        report.queued,
+        report.skipped
+    );
+    Ok(report)
+}
+
+async fn find_affected_uploads(
+    repository: &SqliteRepository,
+    his_client: &HisClient,
+    request: &RemapRequest,
+    corrected_name: &str,
+) -> Result<Vec<AffectedUpload>, String> {
+    let uploads = repository
+        .find_uploads_for_test(&request.test_code, request.from, request.to)
+        .await?;
+    let wanted = normalize_test_code(&request.test_code);
+
+    let mut affected = Vec::new();
+    for upload in uploads {
+        let sent: HisApiPayload = match serde_json::from_str(&upload.payload) {
+            Ok(payload) => payload,
+            Err(e) => {
+                log::warn!("Skipping upload {} with unreadable payload: {}", upload.id, e);
+                continue;
+            }
+        };
+        // Already sent under the right name
+        if sent.values.iter().any(|v| v.name == corrected_name) {
+            continue;
+        }
+
+        let results = repository.get_results_by_sample_id(&upload.result_id).await?;
+        let Some(result) = results.iter().find(|r| normalize_test_code(&r.test_id) == wanted) else {
+            continue;
+        };
+
+        // The value sent for this test, unless another test of the sample has the same value
+        let expected: Vec<String> = results.iter().map(|r| his_client.map_test_name(&r.test_id)).collect();
+        let mut sent_as = sent
+            .values
+            .iter()
+            .filter(|v| v.value == result.value && !expected.contains(&v.name))
+            .map(|v| v.name.clone());
+        let sent_as = match (sent_as.next(), sent_as.next()) {
+            (Some(name), None) => Some(name),
+            _ => None,
+        };
+
+        affected.push(AffectedUpload {
+            candidate: RemediationCandidate {
+                upload_id: upload.id.clone(),
+                sample_id: upload.result_id.clone(),
+                uploaded_at: upload.upload_date,
+                sent_as,
+                value: result.value.clone(),
+            },
+            upload,
+            sent,
+            results,
+        });
+    }
+    Ok(affected)
+}
+
+/// Queues the correction of one upload. Returns false when nothing was queued (shadow mode).
+async fn queue_correction(
+    repository: &SqliteRepository,
+    his_client: &HisClient,
+    upload_worker: &UploadWorker,
+    request: &RemapRequest,
+    affected: AffectedUpload,
+) -> Result<bool, String> {
+    let AffectedUpload { upload, sent, results, .. } = affected;
+
+    // Same sample number and machine as the HIS already holds, values under the current mapping
+    let mut corrected = his_client.build_stored_results_payload(&upload.result_id, &results);
+    corrected.sample_no = sent.sample_no.clone();
+    corrected.machine = sent.machine.clone();
+    corrected.patient_class = sent.patient_class.clone();
+
+    let cancel_upload_id = match request.pattern {
+        CorrectionPattern::CorrectedResult => {
+            corrected.correction = Some(PayloadCorrection::Corrected);
+            None
+        }
+        CorrectionPattern::CancelAndNew => {
+            let cancel = HisApiPayload {
+                sent_on: Local::now().to_rfc3339(),
+                correction: Some(PayloadCorrection::Cancelled),
+                contact: None,
+                ..sent
+            };
+            match upload_worker.enqueue(&upload.result_id, &cancel, UploadPriority::Routine).await? {
+                Some(upload_id) => Some(upload_id),
+                None => return Ok(false),
+            }
+        }
+    };
+
+    let Some(new_upload_id) = upload_worker
+        .enqueue(&upload.result_id, &corrected, UploadPriority::Routine)
+        .await?
+    else {
+        return Ok(false);
+    };
+
+    repository
+        .record_upload_remediation(&UploadRemediation {
+            id: Uuid::new_v4().to_string(),
+            original_upload_id: upload.id.clone(),
+            sample_id: upload.result_id.clone(),
+            test_code: request.test_code.clone(),
+            pattern: request.pattern,
+            cancel_upload_id,
+            new_upload_id,
+            created_at: Utc::now(),
+        })
+        .await?;
+    Ok(true)
+}
+
+/// Test code without ASTM component separators, for comparing `^^^K` with `K`
+fn normalize_test_code(test_code: &str) -> String {
+    test_code.replace('^', "").trim().to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+    use crate::models::result::TestResultMetadata;
+    use crate::models::{DataSource, UploadStatus};
+    use crate::services::his_client::HisTestValue;
+    use crate::services::shadow_mode::ShadowMode;
+    use crate::services::upload_worker::{HisUploader, UploadWorkerConfig, HIS_EXTERNAL_SYSTEM_ID};
+    use async_trait::async_trait;
+    use std::sync::{Arc, Mutex};
+
+    /// Mock HIS recording every payload it accepts
+    #[derive(Default)]
+    struct MockHis {
+        received: Mutex<Vec<HisApiPayload>>,
+    }
+
+    #[async_trait]
+    impl HisUploader for MockHis {
+        async fn upload(&self, payload: &HisApiPayload) -> Result<(), String> {
+            self.received.lock().unwrap().push(payload.clone());
+            Ok(())
+        }
+    }
+
+    fn stored_result(sample_id: &str, test_id: &str, value: &str, sequence_number: u32) -> TestResult {
+        TestResult {
+            test_id: test_id.to_string(),
+            sample_id: sample_id.to_string(),
+            value: value.to_string(),
+            units: Some("mmol/L".to_string()),
+            metadata: TestResultMetadata {
+                sequence_number,
+                ..Default::default()
+            },
+            analyzer_id: Some("autoquant-meril-001".to_string()),
+            ..TestResult::fixture(&format!("{}-R{}", sample_id, sequence_number))
+        }
+    }
+
+    /// Stores a sample's results and an UPLOADED row whose payload names them as given
+    async fn uploaded_sample(repository: &SqliteRepository, sample_id: &str, sent: &[(&str, &str, &str)]) -> String {
+        for (index, (test_id, _, value)) in sent.iter().enumerate() {
+            let result = stored_result(sample_id, test_id, value, index as u32 + 1);
+            repository.save_test_result(&result, "P1", &DataSource::Analyzer).await.unwrap();
+        }
+        let payload = HisApiPayload {
+            machine: "Meril-3.6-11052213".to_string(),
+            sent_on: "2025-01-01T00:00:00+05:30".to_string(),
+            sample_no: sample_id.to_string(),
+            sent: true,
+            values: sent
+                .iter()
+                .map(|(_, name, value)| HisTestValue {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                    units: Some("mmol/L".to_string()),
+                    loinc: None,
+                })
+                .collect(),
+            correction: None,
+            contact: None,
+            patient_class: None,
+        };
+        let upload = repository
+            .track_result_upload(
+                sample_id,
+                HIS_EXTERNAL_SYSTEM_ID,
+                &serde_json::to_string(&payload).unwrap(),
+                UploadPriority::Routine,
+            )
+            .await
+            .unwrap();
+        repository
+            .update_upload_status(&upload.id, UploadStatus::Uploaded, None, None)
+            .await
+            .unwrap();
+        upload.id
+    }
+
+    /// Potassium went out as "NA" on S1 and S2; S3 was sent correctly and S4 has no potassium
+    async fn seed(repository: &SqliteRepository) -> (String, String) {
+        let s1 = uploaded_sample(repository, "S1", &[("^^^K", "NA", "4.2"), ("^^^GLU", "Glu-G", "95")]).await;
+        let s2 = uploaded_sample(repository, "S2", &[("^^^K", "NA", "3.9")]).await;
+        uploaded_sample(repository, "S3", &[("^^^K", "K", "4.0")]).await;
+        uploaded_sample(repository, "S4", &[("^^^GLU", "Glu-G", "101")]).await;
+        (s1, s2)
+    }
+
+    fn request(pattern: CorrectionPattern, dry_run: bool) -> RemapRequest {
+        RemapRequest {
+            test_code: "K".to_string(),
+            from: Utc::now() - chrono::Duration::hours(1),
+            to: Utc::now() + chrono::Duration::hours(1),
+            pattern,
+            dry_run,
+        }
+    }
+
+    fn worker(repository: &Arc<SqliteRepository>, his: &Arc<MockHis>) -> UploadWorker {
+        UploadWorker::new(
+            repository.clone(),
+            his.clone(),
+            UploadWorkerConfig::default(),
+            ShadowMode::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_discovery_finds_only_uploads_of_the_test_in_range() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let (s1, s2) = seed(&repository).await;
+        let now = Utc::now();
+
+        // The query narrows by test code and window; S3 is dropped later for its correct name
+        let found = repository
+            .find_uploads_for_test("^^^K", now - chrono::Duration::hours(1), now + chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        let samples: Vec<&str> = found.iter().map(|u| u.result_id.as_str()).collect();
+        assert_eq!(samples, vec!["S1", "S2", "S3"]);
+        assert_eq!(found[0].id, s1);
+        assert_eq!(found[1].id, s2);
+
+        let earlier = repository
+            .find_uploads_for_test("K", now - chrono::Duration::hours(2), now - chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        assert!(earlier.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_lists_affected_results_without_sending() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let his = Arc::new(MockHis::default());
+        let worker = worker(&repository, &his);
+        let (s1, s2) = seed(&repository).await;
+
+        let report = remap_and_reupload(
+            &repository,
+            &HisClient::with_default_config(),
+            &worker,
+            &request(CorrectionPattern::CorrectedResult, true),
+        )
+        .await
+        .unwrap();
+
+        assert!(report.dry_run);
+        assert_eq!(report.corrected_name, "K");
+        let affected: Vec<(&str, Option<&str>, &str)> = report
+            .affected
+            .iter()
+            .map(|c| (c.upload_id.as_str(), c.sent_as.as_deref(), c.value.as_str()))
+            .collect();
+        assert_eq!(affected, vec![(s1.as_str(), Some("NA"), "4.2"), (s2.as_str(), Some("NA"), "3.9")]);
+        assert_eq!((report.queued, report.skipped), (0, 0));
+
+        // Nothing queued, sent or recorded
+        assert_eq!(worker.queued_count().await.unwrap(), 0);
+        assert_eq!(worker.process_pending().await.unwrap(), 0);
+        assert!(his.received.lock().unwrap().is_empty());
+        assert!(repository.get_upload_remediations("K").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_corrected_results_are_sent_and_linked() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let his = Arc::new(MockHis::default());
+        let worker = worker(&repository, &his);
+        let his_client = HisClient::with_default_config();
+        let (s1, s2) = seed(&repository).await;
+        let request = request(CorrectionPattern::CorrectedResult, false);
+
+        let report = remap_and_reupload(&repository, &his_client, &worker, &request).await.unwrap();
+        assert_eq!((report.affected.len(), report.queued, report.skipped), (2, 2, 0));
+        assert_eq!(worker.process_pending().await.unwrap(), 2);
+
+        let received = his.received.lock().unwrap().clone();
+        assert_eq!(received.len(), 2);
+        assert!(received.iter().all(|p| p.correction == Some(PayloadCorrection::Corrected)));
+        let s1_names: Vec<&str> = received[0].values.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(received[0].sample_no, "S1");
+        assert_eq!(s1_names, vec!["K", "Glu-G"]);
+
+        let remediations = repository.get_upload_remediations("K").await.unwrap();
+        let originals: Vec<&str> = remediations.iter().map(|r| r.original_upload_id.as_str()).collect();
+        assert_eq!(originals, vec![s1.as_str(), s2.as_str()]);
+        for remediation in &remediations {
+            assert_eq!(remediation.pattern, CorrectionPattern::CorrectedResult);
+            assert!(remediation.cancel_upload_id.is_none());
+            let new_upload = repository.get_upload(&remediation.new_upload_id).await.unwrap().unwrap();
+            assert_eq!(new_upload.status, UploadStatus::Uploaded);
+            assert_eq!(new_upload.result_id, remediation.sample_id);
+        }
+
+        // Already remediated uploads are not corrected twice
+        let again = remap_and_reupload(&repository, &his_client, &worker, &request).await.unwrap();
+        assert!(again.affected.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_and_new_sends_cancellation_first() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let his = Arc::new(MockHis::default());
+        let worker = worker(&repository, &his);
+        let (_, s2) = seed(&repository).await;
+        let mut request = request(CorrectionPattern::CancelAndNew, false);
+        request.test_code = "^^^K".to_string();
+
+        let report = remap_and_reupload(&repository, &HisClient::with_default_config(), &worker, &request)
+            .await
+            .unwrap();
+        assert_eq!(report.queued, 2);
+        assert_eq!(worker.process_pending().await.unwrap(), 4);
+
+        let received = his.received.lock().unwrap().clone();
+        let s2_sent: Vec<(Option<PayloadCorrection>, Vec<String>)> = received
+            .iter()
+            .filter(|p| p.sample_no == "S2")
+            .map(|p| (p.correction, p.values.iter().map(|v| v.name.clone()).collect()))
+            .collect();
+        assert_eq!(
+            s2_sent,
+            vec![
+                (Some(PayloadCorrection::Cancelled), vec!["NA".to_string()]),
+                (None, vec!["K".to_string()]),
+            ]
+        );
+
+        let remediations = repository.get_upload_remediations("^^^K").await.unwrap();
+        let remediation = remediations.iter().find(|r| r.original_upload_id == s2).unwrap();
+        let cancel = repository
+            .get_upload(remediation.cancel_upload_id.as_deref().unwrap())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cancel.status, UploadStatus::Uploaded);
+        assert!(cancel.payload.contains("\"Correction\":\"CANCELLED\""));
+    }
+}
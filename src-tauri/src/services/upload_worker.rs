@@ -0,0 +1,762 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Notify};
+use uuid::Uuid;
+
+use crate::db::SqliteRepository;
+use crate::models::{UploadPriority, UploadStatus};
+use crate::protocol::message_profile::MessageProfile;
+use crate::services::his_client::{HisApiPayload, HisClient};
+use crate::services::shadow_mode::ShadowMode;
+
+/// External system id recorded on upload rows destined for the HIS
+pub const HIS_EXTERNAL_SYSTEM_ID: &str = "HIS";
+
+/// Key in the app settings store (`settings.json`) holding the upload retry policy
+pub const UPLOAD_RETRY_STORE_KEY: &str = "upload_retry";
+
+// ============================================================================
+// UPLOADER ABSTRACTION
+// ============================================================================
+
+/// Sends a single payload to the HIS
+#[async_trait]
+pub trait HisUploader: Send + Sync {
+    async fn upload(&self, payload: &HisApiPayload) -> Result<(), String>;
+}
+
+#[async_trait]
+impl HisUploader for HisClient {
+    async fn upload(&self, payload: &HisApiPayload) -> Result<(), String> {
+        self.send_payload(payload).await
+    }
+}
+
+// ============================================================================
+// UPLOAD WORKER
+// ============================================================================
+
+/// How failed uploads are retried: with exponential backoff, until the attempts run out
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct UploadRetryPolicy {
+    /// Attempts after which a failing upload is marked FAILED for good
+    pub max_retries: u32,
+    /// Wait after the first failure; doubles with each further failure
+    pub base_delay_ms: u64,
+    /// Longest wait between two attempts
+    pub max_delay_ms: u64,
+}
+
+impl Default for UploadRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay_ms: 30_000,
+            max_delay_ms: 1_800_000,
+        }
+    }
+}
+
+/// Reads the stored retry policy, falling back to the default when missing or invalid
+pub fn upload_retry_from_store(stored: Option<serde_json::Value>) -> UploadRetryPolicy {
+    match stored {
+        Some(value) => serde_json::from_value(value).unwrap_or_else(|e| {
+            log::warn!("Ignoring invalid upload retry settings: {}", e);
+            UploadRetryPolicy::default()
+        }),
+        None => UploadRetryPolicy::default(),
+    }
+}
+
+impl UploadRetryPolicy {
+    /// Wait before the next attempt of an upload that has now failed `failures` times
+    pub fn delay_after(&self, failures: u32) -> Duration {
+        let doublings = failures.saturating_sub(1).min(32);
+        let delay = self.base_delay_ms.saturating_mul(1u64 << doublings);
+        Duration::from_millis(delay.min(self.max_delay_ms))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UploadWorkerConfig {
+    pub poll_interval_ms: u64,
+    pub batch_size: u32,
+    pub retry: UploadRetryPolicy,
+    /// Claims older than this are presumed abandoned by a stopped worker and released
+    pub claim_timeout_ms: u64,
+}
+
+impl Default for UploadWorkerConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: 5000,
+            batch_size: 20,
+            retry: UploadRetryPolicy::default(),
+            claim_timeout_ms: 300_000,
+        }
+    }
+}
+
+/// Drains the persistent upload queue.
+///
+/// Results are written to `result_upload_status` before any network call, so an
+/// upload interrupted by a crash or restart is picked up again on the next run.
+/// In shadow mode nothing is queued or sent.
+///
+/// Uploads are sent highest priority first. Each batch is claimed under the
+/// worker's id before sending, so several worker instances can share the queue.
+/// A failed upload waits out its backoff before it can be claimed again.
+pub struct UploadWorker {
+    /// Identifies this instance on the rows it claims
+    worker_id: String,
+    repository: Arc<SqliteRepository>,
+    uploader: Arc<dyn HisUploader>,
+    config: UploadWorkerConfig,
+    shadow_mode: ShadowMode,
+    notify: Notify,
+    /// Serializes batches so a maintenance drain never races the polling loop
+    batch_lock: Mutex<()>,
+}
+
+impl UploadWorker {
+    pub fn new(
+        repository: Arc<SqliteRepository>,
+        uploader: Arc<dyn HisUploader>,
+        config: UploadWorkerConfig,
+        shadow_mode: ShadowMode,
+    ) -> Self {
+        Self {
+            worker_id: Uuid::new_v4().to_string(),
+            repository,
+            uploader,
+            config,
+            shadow_mode,
+            notify: Notify::new(),
+            batch_lock: Mutex::new(()),
+        }
+    }
+
+    pub fn worker_id(&self) -> &str {
+        &self.worker_id
+    }
+
+    /// Dispatch priority of a sample's results: critical when any result carries a
+    /// critical flag, otherwise STAT when the sample was ordered STAT
+    pub async fn dispatch_priority(&self, sample_id: &str, critical: bool) -> UploadPriority {
+        if critical {
+            return UploadPriority::Critical;
+        }
+        let order_priority = self.repository.get_sample_order_priority(sample_id).await.unwrap_or_else(|e| {
+            log::warn!("Queueing sample {} as routine: {}", sample_id, e);
+            None
+        });
+        UploadPriority::derive(order_priority.as_ref(), false)
+    }
+
+    /// Persists a payload as PENDING and wakes the worker.
+    /// Returns the upload id, or `None` when shadow mode skipped the upload.
+    pub async fn enqueue(
+        &self,
+        result_id: &str,
+        payload: &HisApiPayload,
+        priority: UploadPriority,
+    ) -> Result<Option<String>, String> {
+        if self.shadow_mode.is_enabled() {
+            log::info!("Shadow mode: not queueing HIS upload for sample {}", result_id);
+            return Ok(None);
+        }
+
+        let serialized = serde_json::to_string(payload)
+            .map_err(|e| format!("Failed to serialize HIS payload: {}", e))?;
+
+        let upload = self
+            .repository
+            .track_result_upload(result_id, HIS_EXTERNAL_SYSTEM_ID, &serialized, priority)
+            .await?;
+
+        log::info!("Queued {:?} HIS upload {} for sample {}", priority, upload.id, result_id);
+        self.notify.notify_one();
+        Ok(Some(upload.id))
+    }
+
+    /// Returns uploads left UPLOADING by a previous run to the queue. Uploads another
+    /// worker claimed within the claim timeout are still being sent and stay claimed.
+    pub async fn resume_in_flight(&self) -> Result<u64, String> {
+        let timeout = chrono::Duration::milliseconds(self.config.claim_timeout_ms as i64);
+        let requeued = self
+            .repository
+            .requeue_in_flight_uploads(&self.worker_id, Utc::now() - timeout)
+            .await?;
+        if requeued > 0 {
+            log::warn!("Requeued {} HIS uploads interrupted by a previous shutdown", requeued);
+        }
+        Ok(requeued)
+    }
+
+    /// Returns uploads claimed longer ago than the claim timeout to the queue
+    pub async fn release_stale_claims(&self) -> Result<u64, String> {
+        let timeout = chrono::Duration::milliseconds(self.config.claim_timeout_ms as i64);
+        let released = self.repository.release_stale_upload_claims(Utc::now() - timeout).await?;
+        if released > 0 {
+            log::warn!("Released {} stale HIS upload claims", released);
+        }
+        Ok(released)
+    }
+
+    /// Sends one batch of pending uploads. Returns the number uploaded successfully.
+    pub async fn process_pending(&self) -> Result<usize, String> {
+        self.process_pending_at(Utc::now()).await
+    }
+
+    /// Same as [`Self::process_pending`] with retries due and scheduled relative to `now`
+    async fn process_pending_at(&self, now: DateTime<Utc>) -> Result<usize, String> {
+        // Uploads queued before shadow mode was switched on wait until it is off
+        if self.shadow_mode.is_enabled() {
+            return Ok(0);
+        }
+
+        let _batch = self.batch_lock.lock().await;
+        self.release_stale_claims().await?;
+        let pending = self
+            .repository
+            .claim_pending_uploads(&self.worker_id, self.config.batch_size, now)
+            .await?;
+        let mut uploaded = 0;
+
+        for upload in pending {
+            let payload: HisApiPayload = match serde_json::from_str(&upload.payload) {
+                Ok(payload) => payload,
+                Err(e) => {
+                    log::error!("Discarding upload {} with unreadable payload: {}", upload.id, e);
+                    self.repository
+                        .update_upload_status(
+                            &upload.id,
+                            UploadStatus::Failed,
+                            None,
+                            Some(&format!("Invalid payload: {}", e)),
+                        )
+                        .await?;
+                    continue;
+                }
+            };
+
+            self.repository
+                .update_upload_status(&upload.id, UploadStatus::Uploading, None, None)
+                .await?;
+
+            match self.uploader.upload(&payload).await {
+                Ok(()) => {
+                    self.repository
+                        .update_upload_status(&upload.id, UploadStatus::Uploaded, None, None)
+                        .await?;
+                    uploaded += 1;
+                }
+                Err(e) if upload.retry_count + 1 >= self.config.retry.max_retries => {
+                    log::error!(
+                        "HIS upload {} for sample {} failed permanently: {}",
+                        upload.id,
+                        upload.result_id,
+                        e
+                    );
+                    self.repository
+                        .update_upload_status(&upload.id, UploadStatus::Failed, None, Some(&e))
+                        .await?;
+                }
+                Err(e) => {
+                    let delay = self.config.retry.delay_after(upload.retry_count + 1);
+                    log::warn!(
+                        "HIS upload {} for sample {} failed (retry {}, next attempt in {:?}): {}",
+                        upload.id,
+                        upload.result_id,
+                        upload.retry_count + 1,
+                        delay,
+                        e
+                    );
+                    let next_attempt_at = now
+                        + chrono::Duration::from_std(delay).unwrap_or_else(|_| chrono::Duration::zero());
+                    self.repository.record_upload_retry(&upload.id, &e, next_attempt_at).await?;
+                }
+            }
+        }
+
+        Ok(uploaded)
+    }
+
+    /// Number of uploads still waiting to be sent
+    pub async fn queued_count(&self) -> Result<usize, String> {
+        self.repository.count_queued_uploads().await
+    }
+
+    /// Runs the worker until the task is dropped
+    pub async fn run(self: Arc<Self>) {
+        if let Err(e) = self.resume_in_flight().await {
+            log::error!("Failed to resume in-flight HIS uploads: {}", e);
+        }
+
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms);
+        loop {
+            if let Err(e) = self.process_pending().await {
+                log::error!("HIS upload worker error: {}", e);
+            }
+
+            let _ = tokio::time::timeout(poll_interval, self.notify.notified()).await;
+        }
+    }
+}
+
+// ============================================================================
+// PREVIEW
+// ============================================================================
+
+/// Builds the HIS payload for a stored sample without sending or queueing it.
+/// Returns the payload exactly as it would be serialized on the wire.
+pub async fn preview_sample_upload(
+    repository: &SqliteRepository,
+    his_client: &HisClient,
+    sample_id: &str,
+) -> Result<String, String> {
+    let results = repository.get_results_by_sample_id(sample_id).await?;
+    if results.is_empty() {
+        return Err(format!("No stored results found for sample {}", sample_id));
+    }
+
+    let payload = his_client.build_stored_results_payload(sample_id, &results);
+    serde_json::to_string_pretty(&payload).map_err(|e| format!("Failed to serialize HIS payload: {}", e))
+}
+
+/// Builds the ORU^R01 message a stored sample would produce under a message profile,
+/// for checking a destination's profile before it goes live
+pub async fn preview_sample_oru(
+    repository: &SqliteRepository,
+    his_client: &HisClient,
+    sample_id: &str,
+    profile: &MessageProfile,
+) -> Result<String, String> {
+    let results = repository.get_results_by_sample_id(sample_id).await?;
+    if results.is_empty() {
+        return Err(format!("No stored results found for sample {}", sample_id));
+    }
+
+    Ok(his_client.build_oru_message(profile, &chrono::Utc::now(), None, sample_id, &results, &[]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+    use crate::models::result::TestResultMetadata;
+    use crate::models::test_order::{ActionCode, Test};
+    use crate::models::{DataSource, OrderPriority, TestOrder, TestResult};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct MockUploader {
+        sent: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl HisUploader for MockUploader {
+        async fn upload(&self, _payload: &HisApiPayload) -> Result<(), String> {
+            self.sent.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Records the samples it is asked to send, in order
+    #[derive(Default)]
+    struct RecordingUploader {
+        sent: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl HisUploader for RecordingUploader {
+        async fn upload(&self, payload: &HisApiPayload) -> Result<(), String> {
+            self.sent.lock().unwrap().push(payload.sample_no.clone());
+            Ok(())
+        }
+    }
+
+    /// Fails the first `failures` attempts, then accepts every payload
+    struct FlakyUploader {
+        failures: usize,
+        attempts: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl HisUploader for FlakyUploader {
+        async fn upload(&self, _payload: &HisApiPayload) -> Result<(), String> {
+            let attempt = self.attempts.fetch_add(1, Ordering::SeqCst);
+            if attempt < self.failures {
+                Err(format!("HIS unavailable (attempt {})", attempt + 1))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn sample_payload() -> HisApiPayload {
+        payload_for("S123")
+    }
+
+    fn payload_for(sample_no: &str) -> HisApiPayload {
+        HisApiPayload {
+            machine: "Meril-3.6-11052213".to_string(),
+            sent_on: "2025-01-01T00:00:00+05:30".to_string(),
+            sample_no: sample_no.to_string(),
+            sent: true,
+            values: vec![],
+            correction: None,
+            contact: None,
+            patient_class: None,
+        }
+    }
+
+    fn worker_with(
+        repository: &Arc<SqliteRepository>,
+        uploader: Arc<dyn HisUploader>,
+        config: UploadWorkerConfig,
+    ) -> UploadWorker {
+        UploadWorker::new(repository.clone(), uploader, config, ShadowMode::default())
+    }
+
+    fn stat_order(specimen_id: &str) -> TestOrder {
+        TestOrder {
+            id: format!("O-{}", specimen_id),
+            sequence_number: 1,
+            specimen_id: specimen_id.to_string(),
+            tests: vec![Test {
+                universal_id: "^^^GLU".to_string(),
+                name: "Glucose".to_string(),
+            }],
+            priority: OrderPriority::Stat,
+            action_code: ActionCode::New,
+            ordering_provider: None,
+            scheduling_info: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_uploads_dispatch_by_priority_then_age() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let uploader = Arc::new(RecordingUploader::default());
+        let worker = worker_with(&repository, uploader.clone(), UploadWorkerConfig::default());
+        repository.enqueue_order_dispatch("A1", "P1", &stat_order("STAT-1")).await.unwrap();
+
+        for (sample, critical) in [("ROUTINE-1", false), ("STAT-1", false), ("ROUTINE-2", false), ("CRIT-1", true)] {
+            let priority = worker.dispatch_priority(sample, critical).await;
+            worker.enqueue(sample, &payload_for(sample), priority).await.unwrap();
+        }
+
+        let summary = repository.get_upload_queue_summary(10).await.unwrap();
+        assert_eq!((summary.pending, summary.critical, summary.stat, summary.routine), (4, 1, 1, 2));
+        let order: Vec<&str> = summary.next.iter().map(|entry| entry.result_id.as_str()).collect();
+        assert_eq!(order, vec!["CRIT-1", "STAT-1", "ROUTINE-1", "ROUTINE-2"]);
+
+        assert_eq!(worker.process_pending().await.unwrap(), 4);
+        assert_eq!(*uploader.sent.lock().unwrap(), vec!["CRIT-1", "STAT-1", "ROUTINE-1", "ROUTINE-2"]);
+    }
+
+    #[tokio::test]
+    async fn test_claimed_uploads_are_exclusive_to_one_worker() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        for sample in ["S1", "S2", "S3"] {
+            repository
+                .track_result_upload(sample, HIS_EXTERNAL_SYSTEM_ID, "{}", UploadPriority::Routine)
+                .await
+                .unwrap();
+        }
+
+        let (first, second) = tokio::join!(
+            repository.claim_pending_uploads("worker-a", 2, Utc::now()),
+            repository.claim_pending_uploads("worker-b", 2, Utc::now())
+        );
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_eq!(first.len() + second.len(), 3);
+        assert!(first.iter().all(|upload| second.iter().all(|other| other.id != upload.id)));
+        assert!(first.iter().all(|upload| upload.claimed_by.as_deref() == Some("worker-a")));
+
+        // Nothing is left for a third worker; the first still holds only its own rows
+        assert!(repository.claim_pending_uploads("worker-c", 2, Utc::now()).await.unwrap().is_empty());
+        let again = repository.claim_pending_uploads("worker-a", 2, Utc::now()).await.unwrap();
+        assert_eq!(again.len(), first.len());
+        assert_eq!(repository.get_upload_queue_summary(10).await.unwrap().claimed, 3);
+    }
+
+    #[tokio::test]
+    async fn test_stale_claim_is_released_to_another_worker() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let uploader = Arc::new(RecordingUploader::default());
+        let payload = serde_json::to_string(&payload_for("S1")).unwrap();
+        let upload = repository
+            .track_result_upload("S1", HIS_EXTERNAL_SYSTEM_ID, &payload, UploadPriority::Stat)
+            .await
+            .unwrap();
+
+        // A worker claims the upload and stops mid-send
+        repository.claim_pending_uploads("stopped-worker", 10, Utc::now()).await.unwrap();
+        repository
+            .update_upload_status(&upload.id, UploadStatus::Uploading, None, None)
+            .await
+            .unwrap();
+
+        // A fresh claim is left alone
+        let worker = worker_with(&repository, uploader.clone(), UploadWorkerConfig::default());
+        assert_eq!(worker.resume_in_flight().await.unwrap(), 0);
+        assert_eq!(worker.process_pending().await.unwrap(), 0);
+        assert!(uploader.sent.lock().unwrap().is_empty());
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let impatient = worker_with(
+            &repository,
+            uploader.clone(),
+            UploadWorkerConfig {
+                claim_timeout_ms: 0,
+                ..Default::default()
+            },
+        );
+        assert_eq!(impatient.process_pending().await.unwrap(), 1);
+        assert_eq!(*uploader.sent.lock().unwrap(), vec!["S1"]);
+
+        let upload = repository.get_upload(&upload.id).await.unwrap().unwrap();
+        assert_eq!(upload.status, UploadStatus::Uploaded);
+        assert_eq!(upload.claimed_by, None);
+    }
+
+    #[tokio::test]
+    async fn test_upload_resumes_after_crash_between_enqueue_and_send() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let uploader = Arc::new(MockUploader { sent: AtomicUsize::new(0) });
+
+        // First run: enqueue, mark in flight, then "crash" before the send completes
+        let worker = UploadWorker::new(
+            repository.clone(),
+            uploader.clone(),
+            UploadWorkerConfig::default(),
+            ShadowMode::default(),
+        );
+        let upload_id = worker
+            .enqueue("S123", &sample_payload(), UploadPriority::Routine)
+            .await
+            .unwrap()
+            .unwrap();
+        repository
+            .update_upload_status(&upload_id, UploadStatus::Uploading, None, None)
+            .await
+            .unwrap();
+        drop(worker);
+        assert_eq!(uploader.sent.load(Ordering::SeqCst), 0);
+
+        // Second run picks the upload back up
+        let worker = UploadWorker::new(
+            repository.clone(),
+            uploader.clone(),
+            UploadWorkerConfig::default(),
+            ShadowMode::default(),
+        );
+        assert_eq!(worker.resume_in_flight().await.unwrap(), 1);
+        assert_eq!(worker.process_pending().await.unwrap(), 1);
+
+        let upload = repository.get_upload(&upload_id).await.unwrap().unwrap();
+        assert_eq!(upload.status, UploadStatus::Uploaded);
+        assert!(upload.upload_date.is_some());
+        assert_eq!(uploader.sent.load(Ordering::SeqCst), 1);
+        assert!(repository.get_pending_uploads(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_simulated_his_failure_spools_result() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let his_client = Arc::new(HisClient::with_default_config());
+        his_client.set_simulated_failure(true);
+        let worker = worker_with(&repository, his_client.clone(), UploadWorkerConfig::default());
+
+        let upload_id = worker
+            .enqueue("S123", &sample_payload(), UploadPriority::Routine)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(worker.process_pending().await.unwrap(), 0);
+
+        // Still queued with the failure recorded, ready for the next attempt
+        let upload = repository.get_upload(&upload_id).await.unwrap().unwrap();
+        assert_eq!(upload.status, UploadStatus::Pending);
+        assert_eq!(upload.retry_count, 1);
+        assert_eq!(upload.response_message.as_deref(), Some("Simulated HIS failure"));
+        assert_eq!(worker.queued_count().await.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_the_cap() {
+        let policy = UploadRetryPolicy {
+            max_retries: 10,
+            base_delay_ms: 30_000,
+            max_delay_ms: 600_000,
+        };
+        let delays: Vec<u64> = (1..=7).map(|failures| policy.delay_after(failures).as_secs()).collect();
+        assert_eq!(delays, vec![30, 60, 120, 240, 480, 600, 600]);
+        assert_eq!(policy.delay_after(0), Duration::from_secs(30));
+        assert_eq!(policy.delay_after(u32::MAX), Duration::from_secs(600));
+
+        assert_eq!(upload_retry_from_store(None), UploadRetryPolicy::default());
+        assert_eq!(
+            upload_retry_from_store(Some(serde_json::json!({ "max_retries": 3 }))),
+            UploadRetryPolicy {
+                max_retries: 3,
+                ..UploadRetryPolicy::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_upload_waits_out_backoff_then_succeeds() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let uploader = Arc::new(FlakyUploader {
+            failures: 2,
+            attempts: AtomicUsize::new(0),
+        });
+        let worker = worker_with(
+            &repository,
+            uploader.clone(),
+            UploadWorkerConfig {
+                retry: UploadRetryPolicy {
+                    max_retries: 5,
+                    base_delay_ms: 200,
+                    max_delay_ms: 10_000,
+                },
+                ..Default::default()
+            },
+        );
+        let upload_id = worker
+            .enqueue("S1", &payload_for("S1"), UploadPriority::Routine)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let start = Utc::now();
+        let at = |millis: i64| start + chrono::Duration::milliseconds(millis);
+
+        assert_eq!(worker.process_pending_at(start).await.unwrap(), 0);
+        let upload = repository.get_upload(&upload_id).await.unwrap().unwrap();
+        assert_eq!(upload.retry_count, 1);
+        assert_eq!(upload.next_attempt_at, Some(at(200)));
+
+        // Not due yet: the next poll leaves it alone
+        assert_eq!(worker.process_pending_at(at(199)).await.unwrap(), 0);
+        assert_eq!(uploader.attempts.load(Ordering::SeqCst), 1);
+
+        assert_eq!(worker.process_pending_at(at(200)).await.unwrap(), 0);
+        let upload = repository.get_upload(&upload_id).await.unwrap().unwrap();
+        assert_eq!(upload.retry_count, 2);
+
+        // The second wait is twice as long
+        assert_eq!(upload.next_attempt_at, Some(at(600)));
+        assert_eq!(worker.process_pending_at(at(599)).await.unwrap(), 0);
+        assert_eq!(uploader.attempts.load(Ordering::SeqCst), 2);
+        assert_eq!(worker.process_pending_at(at(600)).await.unwrap(), 1);
+
+        let upload = repository.get_upload(&upload_id).await.unwrap().unwrap();
+        assert_eq!(upload.status, UploadStatus::Uploaded);
+        assert_eq!(upload.next_attempt_at, None);
+        assert_eq!(uploader.attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_upload_fails_permanently_after_max_retries() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let uploader = Arc::new(FlakyUploader {
+            failures: usize::MAX,
+            attempts: AtomicUsize::new(0),
+        });
+        let worker = worker_with(
+            &repository,
+            uploader.clone(),
+            UploadWorkerConfig {
+                retry: UploadRetryPolicy {
+                    max_retries: 2,
+                    base_delay_ms: 0,
+                    max_delay_ms: 0,
+                },
+                ..Default::default()
+            },
+        );
+        let upload_id = worker
+            .enqueue("S1", &payload_for("S1"), UploadPriority::Routine)
+            .await
+            .unwrap()
+            .unwrap();
+
+        for _ in 0..4 {
+            worker.process_pending().await.unwrap();
+        }
+        let upload = repository.get_upload(&upload_id).await.unwrap().unwrap();
+        assert_eq!(upload.status, UploadStatus::Failed);
+        assert_eq!(upload.response_message.as_deref(), Some("HIS unavailable (attempt 2)"));
+        assert_eq!(uploader.attempts.load(Ordering::SeqCst), 2);
+        assert_eq!(worker.queued_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_shadow_mode_skips_his_upload() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let uploader = Arc::new(MockUploader { sent: AtomicUsize::new(0) });
+        let shadow_mode = ShadowMode::new(true);
+        let worker = UploadWorker::new(
+            repository.clone(),
+            uploader.clone(),
+            UploadWorkerConfig::default(),
+            shadow_mode.clone(),
+        );
+
+        assert_eq!(
+            worker.enqueue("S123", &sample_payload(), UploadPriority::Routine).await.unwrap(),
+            None
+        );
+        assert_eq!(worker.process_pending().await.unwrap(), 0);
+        assert_eq!(uploader.sent.load(Ordering::SeqCst), 0);
+        assert!(repository.get_pending_uploads(10).await.unwrap().is_empty());
+
+        // Leaving shadow mode does not replay what was captured while it was on
+        shadow_mode.set_enabled(false);
+        assert_eq!(worker.process_pending().await.unwrap(), 0);
+        assert_eq!(uploader.sent.load(Ordering::SeqCst), 0);
+    }
+
+    fn stored_result(test_id: &str, value: &str, sequence_number: u32) -> TestResult {
+        TestResult {
+            test_id: test_id.to_string(),
+            sample_id: "S200".to_string(),
+            value: value.to_string(),
+            units: Some("g/dL".to_string()),
+            metadata: TestResultMetadata {
+                sequence_number,
+                ..Default::default()
+            },
+            analyzer_id: Some("autoquant-meril-001".to_string()),
+            ..TestResult::fixture(&format!("R{}", sequence_number))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preview_contains_mapped_loinc_codes() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        let his_client = HisClient::with_default_config();
+        for result in [stored_result("^^^ALB", "3.5", 1), stored_result("^^^GLU", "95", 2)] {
+            repository.save_test_result(&result, "P1", &DataSource::Analyzer).await.unwrap();
+        }
+
+        let preview = preview_sample_upload(&repository, &his_client, "S200").await.unwrap();
+
+        assert!(preview.contains("\"LOINC\": \"1751-7\""));
+        assert!(preview.contains("\"LOINC\": \"2345-7\""));
+        assert!(preview.contains("\"Name\": \"Glu-G\""));
+        assert!(preview.contains("\"SampleNo\": \"S200\""));
+
+        // Previewing never queues an upload
+        assert!(repository.get_pending_uploads(10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,1319 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::SqliteRepository;
+use crate::models::patient::Sex;
+use crate::models::result::DILUTED_FLAG;
+use crate::models::{
+    DemographicsHold, HoldStatus, ReviewReason, RuleScope, TestResult, VerificationCondition, VerificationDecision,
+    VerificationHold, VerificationRule, VerificationStamp,
+};
+use crate::services::demographics_policy::{Demographics, DemographicsGate, DemographicsPolicy, GateOutcome};
+use crate::services::his_client::HisApiPayload;
+
+/// Page size used when replaying historical results in a dry run
+const DRY_RUN_PAGE_SIZE: u32 = 500;
+
+// ============================================================================
+// EVALUATION CONTEXT
+// ============================================================================
+
+/// The parts of one result the verification rules look at
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationSubject {
+    pub test_id: String,
+    pub value: String,
+    pub flags: Vec<String>,
+    /// Value of the patient's previous result of the same test, if any
+    pub previous_value: Option<String>,
+}
+
+/// All results of one sample, with the patient and analyzer they belong to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationPanel {
+    pub analyzer_id: String,
+    pub sample_id: String,
+    pub patient_age_years: Option<u32>,
+    pub patient_sex: Option<Sex>,
+    pub results: Vec<VerificationSubject>,
+}
+
+impl VerificationSubject {
+    pub fn from_result(result: &TestResult, previous: Option<&TestResult>) -> Self {
+        Self {
+            test_id: result.test_id.clone(),
+            value: result.value.clone(),
+            flags: result.flag_list(),
+            previous_value: previous.map(|previous| previous.value.clone()),
+        }
+    }
+
+    fn numeric_value(&self) -> Option<f64> {
+        self.value.trim().parse::<f64>().ok().filter(|value| value.is_finite())
+    }
+
+    /// Absolute change from the previous result in percent of the previous value
+    fn delta_percent(&self) -> Option<f64> {
+        let previous = self.previous_value.as_deref()?.trim().parse::<f64>().ok()?;
+        let current = self.numeric_value()?;
+        (previous != 0.0).then(|| ((current - previous) / previous).abs() * 100.0)
+    }
+
+    fn abnormal_flags(&self) -> impl Iterator<Item = &str> {
+        self.flags
+            .iter()
+            .map(|flag| flag.trim())
+            .filter(|flag| !flag.is_empty() && !flag.eq_ignore_ascii_case("N") && *flag != DILUTED_FLAG)
+    }
+}
+
+/// Whole years between a `YYYYMMDD` birth date and `at`
+pub fn age_in_years(birth_date: &str, at: DateTime<Utc>) -> Option<u32> {
+    let birth = NaiveDate::parse_from_str(birth_date.trim().get(..8)?, "%Y%m%d").ok()?;
+    let today = at.date_naive();
+    let mut years = today.year() - birth.year();
+    if (today.month(), today.day()) < (birth.month(), birth.day()) {
+        years -= 1;
+    }
+    u32::try_from(years).ok()
+}
+
+fn within(value: f64, min: Option<f64>, max: Option<f64>) -> bool {
+    min.map_or(true, |min| value >= min) && max.map_or(true, |max| value <= max)
+}
+
+// ============================================================================
+// CONDITIONS
+// ============================================================================
+
+/// Evaluates a condition for `result` within its panel. Without a result (a
+/// panel-scoped rule) a result-level condition must hold for every result.
+pub fn condition_holds(
+    condition: &VerificationCondition,
+    panel: &VerificationPanel,
+    result: Option<&VerificationSubject>,
+) -> bool {
+    let on_result = |check: &dyn Fn(&VerificationSubject) -> bool| match result {
+        Some(result) => check(result),
+        None => !panel.results.is_empty() && panel.results.iter().all(check),
+    };
+
+    match condition {
+        VerificationCondition::Test { test_ids } => on_result(&|subject| {
+            test_ids
+                .iter()
+                .any(|test_id| test_id.trim().eq_ignore_ascii_case(subject.test_id.trim()))
+        }),
+        VerificationCondition::ValueBetween { min, max } => on_result(&|subject| {
+            subject.numeric_value().is_some_and(|value| within(value, *min, *max))
+        }),
+        VerificationCondition::NoFlags => on_result(&|subject| subject.abnormal_flags().next().is_none()),
+        VerificationCondition::Flag { flag } => on_result(&|subject| {
+            subject
+                .abnormal_flags()
+                .any(|subject_flag| subject_flag.eq_ignore_ascii_case(flag.trim()))
+        }),
+        VerificationCondition::DeltaBelow { max_percent } => {
+            on_result(&|subject| subject.delta_percent().is_some_and(|delta| delta < *max_percent))
+        }
+        VerificationCondition::PriorResult { exists } => {
+            on_result(&|subject| subject.previous_value.is_some() == *exists)
+        }
+        VerificationCondition::PatientAge { min_years, max_years } => panel.patient_age_years.is_some_and(|age| {
+            min_years.map_or(true, |min| age >= min) && max_years.map_or(true, |max| age <= max)
+        }),
+        VerificationCondition::PatientSex { sex } => {
+            panel.patient_sex.as_ref().is_some_and(|patient_sex| *patient_sex == Sex::from(sex.trim()))
+        }
+        VerificationCondition::Analyzer { analyzer_ids } => analyzer_ids
+            .iter()
+            .any(|analyzer_id| analyzer_id.trim().eq_ignore_ascii_case(&panel.analyzer_id)),
+        VerificationCondition::All { conditions } => {
+            conditions.iter().all(|nested| condition_holds(nested, panel, result))
+        }
+        VerificationCondition::Any { conditions } => {
+            conditions.iter().any(|nested| condition_holds(nested, panel, result))
+        }
+        VerificationCondition::Not { condition } => !condition_holds(condition, panel, result),
+        VerificationCondition::EveryResult { condition } => {
+            !panel.results.is_empty()
+                && panel
+                    .results
+                    .iter()
+                    .all(|subject| condition_holds(condition, panel, Some(subject)))
+        }
+        VerificationCondition::AnyResult { condition } => panel
+            .results
+            .iter()
+            .any(|subject| condition_holds(condition, panel, Some(subject))),
+        VerificationCondition::ResultFor { test_id, condition } => panel
+            .results
+            .iter()
+            .filter(|subject| subject.test_id.trim().eq_ignore_ascii_case(test_id.trim()))
+            .any(|subject| condition_holds(condition, panel, Some(subject))),
+    }
+}
+
+// ============================================================================
+// RULES
+// ============================================================================
+
+/// Decides every result of a panel, in panel order. Enabled rules apply in
+/// priority order and the first that holds decides; unmatched results are held.
+pub fn evaluate_panel(rules: &[VerificationRule], panel: &VerificationPanel, at: DateTime<Utc>) -> Vec<VerificationStamp> {
+    let mut ordered: Vec<&VerificationRule> = rules.iter().filter(|rule| rule.enabled).collect();
+    ordered.sort_by(|a, b| a.priority.cmp(&b.priority).then_with(|| a.id.cmp(&b.id)));
+    let panel_matches: Vec<bool> = ordered
+        .iter()
+        .map(|rule| rule.scope == RuleScope::Panel && condition_holds(&rule.condition, panel, None))
+        .collect();
+
+    panel
+        .results
+        .iter()
+        .map(|subject| {
+            let decided_by = ordered.iter().zip(&panel_matches).find_map(|(rule, panel_match)| {
+                let holds = match rule.scope {
+                    RuleScope::Panel => *panel_match,
+                    RuleScope::Result => condition_holds(&rule.condition, panel, Some(subject)),
+                };
+                holds.then_some(*rule)
+            });
+            VerificationStamp {
+                decision: decided_by.map_or(VerificationDecision::Hold, |rule| rule.decision),
+                rule_id: decided_by.map(|rule| rule.id.clone()),
+                rule_version: decided_by.map(|rule| rule.version),
+                evaluated_at: at,
+            }
+        })
+        .collect()
+}
+
+fn validate_condition(rule_id: &str, condition: &VerificationCondition) -> Result<(), String> {
+    let invalid = |reason: &str| Err(format!("Rule '{}': {}", rule_id, reason));
+    match condition {
+        VerificationCondition::Test { test_ids } if test_ids.iter().all(|id| id.trim().is_empty()) => {
+            invalid("test condition lists no tests")
+        }
+        VerificationCondition::ValueBetween {
+            min: Some(min),
+            max: Some(max),
+        } if min > max => invalid("value range minimum is above its maximum"),
+        VerificationCondition::PatientAge {
+            min_years: Some(min),
+            max_years: Some(max),
+        } if min > max => invalid("age range minimum is above its maximum"),
+        VerificationCondition::DeltaBelow { max_percent } if !max_percent.is_finite() || *max_percent <= 0.0 => {
+            invalid("delta limit must be a positive percentage")
+        }
+        VerificationCondition::Flag { flag } if flag.trim().is_empty() => invalid("flag condition has no flag"),
+        VerificationCondition::Analyzer { analyzer_ids } if analyzer_ids.is_empty() => {
+            invalid("analyzer condition lists no analyzers")
+        }
+        VerificationCondition::ResultFor { test_id, .. } if test_id.trim().is_empty() => {
+            invalid("result condition has no test id")
+        }
+        VerificationCondition::All { conditions } | VerificationCondition::Any { conditions } => conditions
+            .iter()
+            .try_for_each(|nested| validate_condition(rule_id, nested)),
+        VerificationCondition::Not { condition }
+        | VerificationCondition::EveryResult { condition }
+        | VerificationCondition::AnyResult { condition }
+        | VerificationCondition::ResultFor { condition, .. } => validate_condition(rule_id, condition),
+        _ => Ok(()),
+    }
+}
+
+/// Checks a rule before it is saved
+pub fn validate_rule(rule: &VerificationRule) -> Result<(), String> {
+    if rule.id.trim().is_empty() {
+        return Err(format!("Rule '{}' has no id", rule.name));
+    }
+    if rule.name.trim().is_empty() {
+        return Err(format!("Rule '{}' has no name", rule.id));
+    }
+    validate_condition(&rule.id, &rule.condition)
+}
+
+// ============================================================================
+// DRY RUN
+// ============================================================================
+
+/// How many results a rule version released in a dry run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RuleReleaseCount {
+    pub rule_id: String,
+    pub rule_version: u32,
+    pub released: usize,
+}
+
+/// Release rates of the active rules and a proposed rule set over stored results
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VerificationDryRun {
+    pub samples: usize,
+    pub results: usize,
+    pub current_released: usize,
+    pub proposed_released: usize,
+    /// Fraction of results auto-verified, 0.0 when there are none
+    pub current_release_rate: f64,
+    pub proposed_release_rate: f64,
+    /// Held by the active rules, auto-verified by the proposed ones
+    pub newly_released: usize,
+    /// Auto-verified by the active rules, held by the proposed ones
+    pub newly_held: usize,
+    /// Results auto-verified per proposed rule
+    pub released_by_rule: Vec<RuleReleaseCount>,
+}
+
+fn release_rate(released: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        released as f64 / total as f64
+    }
+}
+
+// ============================================================================
+// GATE
+// ============================================================================
+
+/// What happened to the results submitted for one sample
+#[derive(Debug, Clone)]
+pub enum VerificationOutcome {
+    /// Auto-verified and passed on to the demographics gate
+    Released(GateOutcome, Vec<DemographicsHold>),
+    /// Held for technologist review
+    Held(VerificationHold),
+}
+
+/// Holds released by [`VerificationGate::approve`], and the ids that failed with why
+#[derive(Debug, Default)]
+pub struct BulkApproval {
+    pub released: Vec<(VerificationHold, GateOutcome, Vec<DemographicsHold>)>,
+    pub failed: Vec<(String, String)>,
+}
+
+/// What a bulk approval did, as reported to the review screen
+#[derive(Debug, Clone, Serialize)]
+pub struct ApprovalSummary {
+    pub released: Vec<VerificationHold>,
+    /// Hold id and why it could not be released
+    pub failed: Vec<(String, String)>,
+}
+
+impl From<&BulkApproval> for ApprovalSummary {
+    fn from(approval: &BulkApproval) -> Self {
+        Self {
+            released: approval.released.iter().map(|(hold, _, _)| hold.clone()).collect(),
+            failed: approval.failed.clone(),
+        }
+    }
+}
+
+/// Applies the auto-verification rules at ingestion. Samples with a held
+/// result wait for review; the rest go on through the demographics gate.
+pub struct VerificationGate {
+    repository: Arc<SqliteRepository>,
+    demographics_gate: Arc<DemographicsGate>,
+}
+
+impl VerificationGate {
+    pub fn new(repository: Arc<SqliteRepository>, demographics_gate: Arc<DemographicsGate>) -> Self {
+        Self {
+            repository,
+            demographics_gate,
+        }
+    }
+
+    /// Builds the evaluation context of a sample's results. Demographics from
+    /// the message count together with the stored patient record.
+    pub async fn build_panel(
+        &self,
+        analyzer_id: &str,
+        patient_id: Option<&str>,
+        message_demographics: Demographics,
+        results: &[TestResult],
+        at: DateTime<Utc>,
+    ) -> Result<VerificationPanel, String> {
+        let stored = match patient_id {
+            Some(patient_id) => self.repository.get_patient(patient_id).await?,
+            None => None,
+        };
+        let demographics = message_demographics.or(stored.as_ref().map(Demographics::from_patient).unwrap_or_default());
+
+        let mut subjects = Vec::with_capacity(results.len());
+        for result in results {
+            let previous = match patient_id {
+                Some(patient_id) => {
+                    let before = result.completed_date_time.unwrap_or(at);
+                    self.repository
+                        .get_previous_result(patient_id, &result.test_id, &result.sample_id, before)
+                        .await?
+                }
+                None => None,
+            };
+            subjects.push(VerificationSubject::from_result(result, previous.as_ref()));
+        }
+
+        Ok(VerificationPanel {
+            analyzer_id: analyzer_id.to_string(),
+            sample_id: results.first().map(|result| result.sample_id.clone()).unwrap_or_default(),
+            patient_age_years: demographics.birth_date.as_deref().and_then(|birth| age_in_years(birth, at)),
+            patient_sex: demographics.sex.as_deref().map(Sex::from),
+            results: subjects,
+        })
+    }
+
+    /// Holds the sample when any result is not auto-verified, otherwise submits it
+    /// to the demographics gate. `stamps` are the decisions of [`evaluate_panel`]
+    /// keyed by test id.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn submit(
+        &self,
+        policy: &DemographicsPolicy,
+        analyzer_id: &str,
+        patient_id: Option<&str>,
+        message_demographics: Demographics,
+        payload: &HisApiPayload,
+        critical: bool,
+        stamps: Vec<(String, VerificationStamp)>,
+    ) -> Result<VerificationOutcome, String> {
+        if stamps
+            .iter()
+            .all(|(_, stamp)| stamp.decision == VerificationDecision::AutoVerify)
+        {
+            let (outcome, released) = self
+                .demographics_gate
+                .submit(policy, analyzer_id, patient_id, message_demographics, payload, critical)
+                .await?;
+            return Ok(VerificationOutcome::Released(outcome, released));
+        }
+
+        let hold = self
+            .hold(ReviewReason::Verification, None, analyzer_id, patient_id, payload, critical, stamps)
+            .await?;
+        log::info!(
+            "Holding results of sample {} for verification ({} of {} not auto-verified)",
+            hold.sample_id,
+            hold.decisions
+                .iter()
+                .filter(|(_, stamp)| stamp.decision == VerificationDecision::Hold)
+                .count(),
+            hold.decisions.len()
+        );
+        Ok(VerificationOutcome::Held(hold))
+    }
+
+    /// Keeps a sample's results from upload until reviewed, whatever the rules decided
+    #[allow(clippy::too_many_arguments)]
+    pub async fn hold(
+        &self,
+        reason: ReviewReason,
+        reason_detail: Option<String>,
+        analyzer_id: &str,
+        patient_id: Option<&str>,
+        payload: &HisApiPayload,
+        critical: bool,
+        stamps: Vec<(String, VerificationStamp)>,
+    ) -> Result<VerificationHold, String> {
+        let serialized =
+            serde_json::to_string(payload).map_err(|e| format!("Failed to serialize HIS payload: {}", e))?;
+        let hold = VerificationHold {
+            id: Uuid::new_v4().to_string(),
+            patient_id: patient_id.map(str::to_string),
+            sample_id: payload.sample_no.clone(),
+            analyzer_id: analyzer_id.to_string(),
+            reason,
+            reason_detail,
+            decisions: stamps,
+            payload: serialized,
+            priority: self.demographics_gate.dispatch_priority(&payload.sample_no, critical).await,
+            status: HoldStatus::PendingVerification,
+            upload_id: None,
+            created_at: Utc::now(),
+            released_at: None,
+        };
+        self.repository.save_verification_hold(&hold).await?;
+        Ok(hold)
+    }
+
+    /// Releases reviewed results through the demographics gate, which may still
+    /// hold them when the patient's mandatory demographics are incomplete
+    pub async fn release(
+        &self,
+        policy: &DemographicsPolicy,
+        hold_id: &str,
+    ) -> Result<(VerificationHold, GateOutcome, Vec<DemographicsHold>), String> {
+        let mut hold = self
+            .repository
+            .get_verification_hold(hold_id)
+            .await?
+            .ok_or_else(|| format!("Verification hold {} not found", hold_id))?;
+        if hold.status != HoldStatus::PendingVerification {
+            return Err(format!("Verification hold {} was already released", hold_id));
+        }
+
+        let payload: HisApiPayload = serde_json::from_str(&hold.payload)
+            .map_err(|e| format!("Failed to decode held payload {}: {}", hold.id, e))?;
+        let (outcome, released) = self
+            .demographics_gate
+            .submit_with_priority(
+                policy,
+                &hold.analyzer_id,
+                hold.patient_id.as_deref(),
+                Demographics::default(),
+                &payload,
+                hold.priority,
+            )
+            .await?;
+        let upload_id = match &outcome {
+            GateOutcome::Queued { upload_id } => upload_id.clone(),
+            GateOutcome::Held(_) => None,
+        };
+        if !self.repository.release_verification_hold(&hold.id, upload_id.as_deref()).await? {
+            return Err(format!("Verification hold {} was already released", hold_id));
+        }
+
+        log::info!("Released verified results of sample {}", hold.sample_id);
+        hold.status = HoldStatus::Released;
+        hold.upload_id = upload_id;
+        hold.released_at = Some(Utc::now());
+        Ok((hold, outcome, released))
+    }
+
+    /// Releases several reviewed holds, e.g. a whole stale-result queue. A hold that
+    /// cannot be released is reported and does not stop the others.
+    pub async fn approve(&self, policy: &DemographicsPolicy, hold_ids: &[String]) -> BulkApproval {
+        let mut approval = BulkApproval::default();
+        for hold_id in hold_ids {
+            match self.release(policy, hold_id).await {
+                Ok(released) => approval.released.push(released),
+                Err(e) => {
+                    log::warn!("Failed to approve held results {}: {}", hold_id, e);
+                    approval.failed.push((hold_id.clone(), e));
+                }
+            }
+        }
+        approval
+    }
+
+    /// Replays stored results completed between `from` and `to` through the
+    /// active rules and `proposed`, comparing how many each would release
+    pub async fn dry_run(
+        &self,
+        current: &[VerificationRule],
+        proposed: &[VerificationRule],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<VerificationDryRun, String> {
+        // Group by sample so panel conditions see the whole panel
+        let mut samples: BTreeMap<String, (String, Vec<TestResult>)> = BTreeMap::new();
+        let mut after_rowid = 0;
+        loop {
+            let page = self
+                .repository
+                .get_results_page_in_range(from, to, after_rowid, DRY_RUN_PAGE_SIZE)
+                .await?;
+            let Some((last_rowid, _, _)) = page.last() else {
+                break;
+            };
+            after_rowid = *last_rowid;
+            for (_, patient_id, result) in page {
+                samples
+                    .entry(result.sample_id.clone())
+                    .or_insert_with(|| (patient_id, Vec::new()))
+                    .1
+                    .push(result);
+            }
+        }
+
+        let mut report = VerificationDryRun::default();
+        let mut by_rule: BTreeMap<(String, u32), usize> = BTreeMap::new();
+        for (_, (patient_id, results)) in samples {
+            let at = results
+                .iter()
+                .filter_map(|result| result.completed_date_time)
+                .max()
+                .unwrap_or_else(|| results[0].created_at);
+            let analyzer_id = results[0].analyzer_id.clone().unwrap_or_default();
+            let panel = self
+                .build_panel(&analyzer_id, Some(&patient_id), Demographics::default(), &results, at)
+                .await?;
+
+            let current_stamps = evaluate_panel(current, &panel, at);
+            let proposed_stamps = evaluate_panel(proposed, &panel, at);
+            report.samples += 1;
+            for (current, proposed) in current_stamps.iter().zip(&proposed_stamps) {
+                let current_released = current.decision == VerificationDecision::AutoVerify;
+                let proposed_released = proposed.decision == VerificationDecision::AutoVerify;
+                report.results += 1;
+                report.current_released += current_released as usize;
+                report.proposed_released += proposed_released as usize;
+                report.newly_released += (!current_released && proposed_released) as usize;
+                report.newly_held += (current_released && !proposed_released) as usize;
+                if let (true, Some(rule_id), Some(version)) =
+                    (proposed_released, proposed.rule_id.as_ref(), proposed.rule_version)
+                {
+                    *by_rule.entry((rule_id.clone(), version)).or_default() += 1;
+                }
+            }
+        }
+
+        report.current_release_rate = release_rate(report.current_released, report.results);
+        report.proposed_release_rate = release_rate(report.proposed_released, report.results);
+        report.released_by_rule = by_rule
+            .into_iter()
+            .map(|((rule_id, rule_version), released)| RuleReleaseCount {
+                rule_id,
+                rule_version,
+                released,
+            })
+            .collect();
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::establish_test_connection;
+    use crate::models::patient::PatientName;
+    use crate::models::{DataSource, Patient};
+    use crate::services::his_client::HisClient;
+    use crate::services::shadow_mode::ShadowMode;
+    use crate::services::upload_worker::{UploadWorker, UploadWorkerConfig};
+    use chrono::TimeZone;
+
+    fn subject(test_id: &str, value: &str, flags: &[&str], previous: Option<&str>) -> VerificationSubject {
+        VerificationSubject {
+            test_id: test_id.to_string(),
+            value: value.to_string(),
+            flags: flags.iter().map(|flag| flag.to_string()).collect(),
+            previous_value: previous.map(str::to_string),
+        }
+    }
+
+    fn panel(results: Vec<VerificationSubject>) -> VerificationPanel {
+        VerificationPanel {
+            analyzer_id: "bf6900-001".to_string(),
+            sample_id: "S1".to_string(),
+            patient_age_years: Some(30),
+            patient_sex: Some(Sex::Female),
+            results,
+        }
+    }
+
+    fn cbc() -> VerificationPanel {
+        panel(vec![
+            subject("WBC", "7.2", &["N"], Some("6.8")),
+            subject("HGB", "13.1", &[], Some("12.9")),
+            subject("PLT", "250", &[DILUTED_FLAG], Some("240")),
+        ])
+    }
+
+    fn rule(id: &str, priority: i32, scope: RuleScope, condition: VerificationCondition) -> VerificationRule {
+        VerificationRule {
+            id: id.to_string(),
+            version: 1,
+            name: id.to_string(),
+            enabled: true,
+            priority,
+            scope,
+            condition,
+            decision: VerificationDecision::AutoVerify,
+            created_at: None,
+        }
+    }
+
+    fn boxed(condition: VerificationCondition) -> Box<VerificationCondition> {
+        Box::new(condition)
+    }
+
+    /// The lab SOP: auto-verify CBC if WBC 4–10, no suspect flags, delta < 20% and patient older than 12
+    fn sop_cbc_rule() -> VerificationRule {
+        rule(
+            "cbc-auto",
+            0,
+            RuleScope::Panel,
+            VerificationCondition::All {
+                conditions: vec![
+                    VerificationCondition::ResultFor {
+                        test_id: "wbc".to_string(),
+                        condition: boxed(VerificationCondition::ValueBetween {
+                            min: Some(4.0),
+                            max: Some(10.0),
+                        }),
+                    },
+                    VerificationCondition::EveryResult {
+                        condition: boxed(VerificationCondition::NoFlags),
+                    },
+                    VerificationCondition::EveryResult {
+                        condition: boxed(VerificationCondition::DeltaBelow { max_percent: 20.0 }),
+                    },
+                    VerificationCondition::PatientAge {
+                        min_years: Some(13),
+                        max_years: None,
+                    },
+                ],
+            },
+        )
+    }
+
+    fn holds(condition: VerificationCondition, result: &VerificationSubject) -> bool {
+        condition_holds(&condition, &panel(vec![result.clone()]), Some(result))
+    }
+
+    #[test]
+    fn test_result_value_and_flag_conditions() {
+        let normal = subject("GLU", "95", &["N", DILUTED_FLAG], None);
+        let high = subject("GLU", "250", &["H"], None);
+        let text = subject("GLU", ">500", &[], None);
+        let in_range = || VerificationCondition::ValueBetween {
+            min: Some(70.0),
+            max: Some(110.0),
+        };
+
+        assert!(holds(in_range(), &normal));
+        assert!(!holds(in_range(), &high));
+        assert!(!holds(in_range(), &text));
+        assert!(holds(VerificationCondition::ValueBetween { min: None, max: Some(95.0) }, &normal));
+
+        assert!(holds(VerificationCondition::NoFlags, &normal));
+        assert!(!holds(VerificationCondition::NoFlags, &high));
+        assert!(holds(VerificationCondition::Flag { flag: "h".to_string() }, &high));
+        assert!(!holds(VerificationCondition::Flag { flag: "L".to_string() }, &high));
+
+        let tests = VerificationCondition::Test {
+            test_ids: vec!["glu".to_string(), "BUN".to_string()],
+        };
+        assert!(holds(tests.clone(), &normal));
+        assert!(!holds(tests, &subject("NA", "140", &[], None)));
+    }
+
+    #[test]
+    fn test_delta_and_prior_result_conditions() {
+        let delta = || VerificationCondition::DeltaBelow { max_percent: 20.0 };
+
+        assert!(holds(delta(), &subject("WBC", "11", &[], Some("10"))));
+        assert!(holds(delta(), &subject("WBC", "8.5", &[], Some("10"))));
+        assert!(!holds(delta(), &subject("WBC", "12", &[], Some("10"))));
+        assert!(!holds(delta(), &subject("WBC", "7", &[], Some("10"))));
+        // No usable previous value never passes a delta check
+        assert!(!holds(delta(), &subject("WBC", "10", &[], None)));
+        assert!(!holds(delta(), &subject("WBC", "10", &[], Some("0"))));
+        assert!(!holds(delta(), &subject("WBC", "10", &[], Some("n/a"))));
+
+        let first_result = subject("WBC", "10", &[], None);
+        assert!(holds(VerificationCondition::PriorResult { exists: false }, &first_result));
+        assert!(!holds(VerificationCondition::PriorResult { exists: true }, &first_result));
+    }
+
+    #[test]
+    fn test_patient_and_analyzer_conditions() {
+        let mut panel = cbc();
+        let age = |min, max| VerificationCondition::PatientAge {
+            min_years: min,
+            max_years: max,
+        };
+
+        assert!(condition_holds(&age(Some(13), None), &panel, None));
+        assert!(condition_holds(&age(Some(30), Some(30)), &panel, None));
+        assert!(!condition_holds(&age(None, Some(12)), &panel, None));
+        assert!(condition_holds(&VerificationCondition::PatientSex { sex: "f".to_string() }, &panel, None));
+        assert!(!condition_holds(&VerificationCondition::PatientSex { sex: "M".to_string() }, &panel, None));
+        assert!(condition_holds(
+            &VerificationCondition::Analyzer {
+                analyzer_ids: vec!["BF6900-001".to_string()]
+            },
+            &panel,
+            None
+        ));
+
+        // Unknown demographics never satisfy a demographic condition
+        panel.patient_age_years = None;
+        panel.patient_sex = None;
+        assert!(!condition_holds(&age(None, None), &panel, None));
+        assert!(!condition_holds(&VerificationCondition::PatientSex { sex: "U".to_string() }, &panel, None));
+    }
+
+    #[test]
+    fn test_boolean_combinators() {
+        let result = subject("K", "4.1", &[], Some("4.0"));
+        let in_range = VerificationCondition::ValueBetween {
+            min: Some(3.5),
+            max: Some(5.1),
+        };
+        let flagged = VerificationCondition::Flag { flag: "H".to_string() };
+
+        assert!(holds(
+            VerificationCondition::All {
+                conditions: vec![in_range.clone(), VerificationCondition::NoFlags]
+            },
+            &result
+        ));
+        assert!(!holds(
+            VerificationCondition::All {
+                conditions: vec![in_range.clone(), flagged.clone()]
+            },
+            &result
+        ));
+        assert!(holds(
+            VerificationCondition::Any {
+                conditions: vec![flagged.clone(), in_range]
+            },
+            &result
+        ));
+        assert!(!holds(VerificationCondition::Any { conditions: Vec::new() }, &result));
+        assert!(holds(VerificationCondition::All { conditions: Vec::new() }, &result));
+        assert!(holds(VerificationCondition::Not { condition: boxed(flagged) }, &result));
+    }
+
+    #[test]
+    fn test_panel_conditions() {
+        let mut panel = cbc();
+        let no_flags = || boxed(VerificationCondition::NoFlags);
+
+        assert!(condition_holds(&VerificationCondition::EveryResult { condition: no_flags() }, &panel, None));
+        // A result-level condition in a panel rule must hold for every result
+        assert!(condition_holds(&VerificationCondition::NoFlags, &panel, None));
+
+        panel.results[2].flags.push("PLT_Clump".to_string());
+        assert!(!condition_holds(&VerificationCondition::EveryResult { condition: no_flags() }, &panel, None));
+        assert!(!condition_holds(&VerificationCondition::NoFlags, &panel, None));
+        assert!(condition_holds(
+            &VerificationCondition::AnyResult {
+                condition: boxed(VerificationCondition::Flag {
+                    flag: "plt_clump".to_string()
+                })
+            },
+            &panel,
+            None
+        ));
+
+        // ResultFor needs the named test in the panel
+        let rbc_present = VerificationCondition::ResultFor {
+            test_id: "RBC".to_string(),
+            condition: boxed(VerificationCondition::All { conditions: Vec::new() }),
+        };
+        assert!(!condition_holds(&rbc_present, &panel, None));
+
+        // Panel conditions look at the whole panel even from a result-scoped rule
+        let hgb = panel.results[1].clone();
+        assert!(!condition_holds(&VerificationCondition::EveryResult { condition: no_flags() }, &panel, Some(&hgb)));
+        assert!(condition_holds(&VerificationCondition::NoFlags, &panel, Some(&hgb)));
+
+        let empty = self::panel(Vec::new());
+        assert!(!condition_holds(&VerificationCondition::NoFlags, &empty, None));
+        assert!(!condition_holds(&VerificationCondition::EveryResult { condition: no_flags() }, &empty, None));
+    }
+
+    #[test]
+    fn test_sop_cbc_panel_rule() {
+        let at = Utc::now();
+        let rules = vec![sop_cbc_rule()];
+        let released = |panel: &VerificationPanel| {
+            evaluate_panel(&rules, panel, at)
+                .iter()
+                .all(|stamp| stamp.decision == VerificationDecision::AutoVerify)
+        };
+
+        assert!(released(&cbc()));
+
+        let mut high_wbc = cbc();
+        high_wbc.results[0].value = "10.5".to_string();
+        high_wbc.results[0].previous_value = Some("10.1".to_string());
+        assert!(!released(&high_wbc));
+
+        let mut suspect = cbc();
+        suspect.results[2].flags.push("A".to_string());
+        assert!(!released(&suspect));
+
+        let mut big_delta = cbc();
+        big_delta.results[1].value = "9.9".to_string();
+        assert!(!released(&big_delta));
+
+        let mut first_cbc = cbc();
+        first_cbc.results[1].previous_value = None;
+        assert!(!released(&first_cbc));
+
+        let mut child = cbc();
+        child.patient_age_years = Some(12);
+        assert!(!released(&child));
+    }
+
+    #[test]
+    fn test_first_matching_rule_decides_and_unmatched_results_are_held() {
+        let at = Utc::now();
+        let panel = cbc();
+        let mut hold_hgb = rule(
+            "hold-hgb",
+            0,
+            RuleScope::Result,
+            VerificationCondition::Test {
+                test_ids: vec!["HGB".to_string()],
+            },
+        );
+        hold_hgb.decision = VerificationDecision::Hold;
+        hold_hgb.version = 3;
+        let wbc_only = rule(
+            "release-wbc",
+            5,
+            RuleScope::Result,
+            VerificationCondition::Test {
+                test_ids: vec!["WBC".to_string()],
+            },
+        );
+        let mut release_all = sop_cbc_rule();
+        release_all.priority = 10;
+
+        let stamps = evaluate_panel(&[release_all.clone(), wbc_only, hold_hgb.clone()], &panel, at);
+        assert_eq!(stamps[0].rule_id.as_deref(), Some("release-wbc"));
+        assert_eq!(stamps[0].decision, VerificationDecision::AutoVerify);
+        assert_eq!(stamps[1].rule_id.as_deref(), Some("hold-hgb"));
+        assert_eq!(stamps[1].rule_version, Some(3));
+        assert_eq!(stamps[1].decision, VerificationDecision::Hold);
+        assert_eq!(stamps[2].rule_id.as_deref(), Some("cbc-auto"));
+        assert!(stamps.iter().all(|stamp| stamp.evaluated_at == at));
+
+        // Disabled rules are skipped; results no rule matches are held by default
+        hold_hgb.enabled = false;
+        release_all.enabled = false;
+        let stamps = evaluate_panel(&[release_all, hold_hgb], &panel, at);
+        assert!(stamps
+            .iter()
+            .all(|stamp| stamp.decision == VerificationDecision::Hold && stamp.rule_id.is_none()));
+    }
+
+    #[test]
+    fn test_age_in_years_counts_birthdays() {
+        let at = Utc.with_ymd_and_hms(2025, 3, 1, 9, 0, 0).unwrap();
+        assert_eq!(age_in_years("20120301", at), Some(13));
+        assert_eq!(age_in_years("20120302", at), Some(12));
+        assert_eq!(age_in_years("19790221120000", at), Some(46));
+        assert_eq!(age_in_years("2030", at), None);
+        assert_eq!(age_in_years("20300101", at), None);
+    }
+
+    #[test]
+    fn test_validate_rule_rejects_inconsistent_conditions() {
+        assert!(validate_rule(&sop_cbc_rule()).is_ok());
+
+        let mut unnamed = sop_cbc_rule();
+        unnamed.id = " ".to_string();
+        assert!(validate_rule(&unnamed).is_err());
+
+        let nested_inverted_range = rule(
+            "bad",
+            0,
+            RuleScope::Result,
+            VerificationCondition::Not {
+                condition: boxed(VerificationCondition::ValueBetween {
+                    min: Some(10.0),
+                    max: Some(4.0),
+                }),
+            },
+        );
+        assert!(validate_rule(&nested_inverted_range).is_err());
+
+        let zero_delta = rule("bad", 0, RuleScope::Result, VerificationCondition::DeltaBelow { max_percent: 0.0 });
+        assert!(validate_rule(&zero_delta).is_err());
+    }
+
+    // ------------------------------------------------------------------------
+    // STORAGE AND GATE
+    // ------------------------------------------------------------------------
+
+    fn gate(repository: &Arc<SqliteRepository>) -> VerificationGate {
+        let upload_worker = Arc::new(UploadWorker::new(
+            repository.clone(),
+            Arc::new(HisClient::with_default_config()),
+            UploadWorkerConfig::default(),
+            ShadowMode::default(),
+        ));
+        let demographics_gate = Arc::new(DemographicsGate::new(repository.clone(), upload_worker));
+        VerificationGate::new(repository.clone(), demographics_gate)
+    }
+
+    fn payload(sample_no: &str) -> HisApiPayload {
+        HisApiPayload {
+            machine: "BF-6900".to_string(),
+            sent_on: "2025-03-01 09:00:00".to_string(),
+            sample_no: sample_no.to_string(),
+            sent: false,
+            values: Vec::new(),
+            correction: None,
+            contact: None,
+            patient_class: None,
+        }
+    }
+
+    fn stored_result(id: &str, sample_id: &str, test_id: &str, value: &str, at: DateTime<Utc>) -> TestResult {
+        TestResult {
+            test_id: test_id.to_string(),
+            sample_id: sample_id.to_string(),
+            value: value.to_string(),
+            units: None,
+            completed_date_time: Some(at),
+            analyzer_id: Some("bf6900-001".to_string()),
+            created_at: at,
+            updated_at: at,
+            ..TestResult::fixture(id)
+        }
+    }
+
+    async fn seed_patient(repository: &SqliteRepository) {
+        let created_at = Utc.with_ymd_and_hms(2025, 3, 1, 9, 0, 0).unwrap();
+        let patient = Patient {
+            name: PatientName {
+                last_name: Some("Rao".to_string()),
+                first_name: Some("Anita".to_string()),
+                ..Default::default()
+            },
+            birth_date: Some(Utc.with_ymd_and_hms(1979, 2, 21, 0, 0, 0).unwrap()),
+            sex: Sex::Female,
+            created_at,
+            updated_at: created_at,
+            ..Patient::fixture("P1")
+        };
+        repository.save_patient(&patient, &DataSource::Analyzer).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_saved_edits_get_new_versions_and_stamps_persist() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+
+        let first = repository.save_verification_rule(&sop_cbc_rule()).await.unwrap();
+        let mut edited = sop_cbc_rule();
+        edited.name = "CBC auto-verification (revised)".to_string();
+        let second = repository.save_verification_rule(&edited).await.unwrap();
+        assert_eq!((first.version, second.version), (1, 2));
+        assert!(second.created_at.is_some());
+
+        let active = repository.get_active_verification_rules().await.unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].version, 2);
+        assert_eq!(active[0].condition, sop_cbc_rule().condition);
+        assert_eq!(repository.get_verification_rule_history("cbc-auto").await.unwrap().len(), 2);
+
+        // The version that decided a result is kept on the stored result
+        let at = Utc.with_ymd_and_hms(2025, 3, 1, 9, 0, 0).unwrap();
+        let mut result = stored_result("R1", "S1", "WBC", "7.2", at);
+        result.metadata.verification = Some(VerificationStamp {
+            decision: VerificationDecision::AutoVerify,
+            rule_id: Some(second.id.clone()),
+            rule_version: Some(second.version),
+            evaluated_at: at,
+        });
+        repository.save_test_result(&result, "P1", &DataSource::Analyzer).await.unwrap();
+        let stored = repository.get_test_result("R1").await.unwrap().unwrap();
+        assert_eq!(stored.metadata.verification, result.metadata.verification);
+
+        // Deleting stops applying the rule but keeps its versions
+        assert!(repository.retire_verification_rule("cbc-auto").await.unwrap());
+        assert!(!repository.retire_verification_rule("cbc-auto").await.unwrap());
+        assert!(repository.get_active_verification_rules().await.unwrap().is_empty());
+        assert_eq!(repository.get_verification_rule_history("cbc-auto").await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_active_rules_are_cached_until_a_rule_changes() {
+        let repository = SqliteRepository::new(establish_test_connection().await);
+        repository.save_verification_rule(&sop_cbc_rule()).await.unwrap();
+        assert_eq!(repository.get_active_verification_rules().await.unwrap().len(), 1);
+
+        // A row written behind the repository's back is not seen until a rule changes
+        sqlx::query(
+            "INSERT INTO verification_rules (id, version, name, enabled, priority, definition, created_at) \
+             SELECT 'copy', 1, name, enabled, priority, definition, created_at FROM verification_rules",
+        )
+        .execute(repository.pool())
+        .await
+        .unwrap();
+        assert_eq!(repository.get_active_verification_rules().await.unwrap().len(), 1);
+
+        let mut edited = sop_cbc_rule();
+        edited.name = "CBC auto-verification (revised)".to_string();
+        repository.save_verification_rule(&edited).await.unwrap();
+        let active = repository.get_active_verification_rules().await.unwrap();
+        assert_eq!(active.len(), 2);
+        assert!(active.iter().any(|rule| rule.id == "cbc-auto" && rule.version == 2));
+
+        assert!(repository.retire_verification_rule("copy").await.unwrap());
+        assert_eq!(repository.get_active_verification_rules().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_hold_with_unreadable_decisions_is_a_decode_error() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let gate = gate(&repository);
+        let stamp = VerificationStamp {
+            decision: VerificationDecision::Hold,
+            rule_id: None,
+            rule_version: None,
+            evaluated_at: Utc::now(),
+        };
+        let outcome = gate
+            .submit(
+                &DemographicsPolicy::default(),
+                "bf6900-001",
+                Some("P1"),
+                Demographics::default(),
+                &payload("S1"),
+                false,
+                vec![("HGB".to_string(), stamp)],
+            )
+            .await
+            .unwrap();
+        let VerificationOutcome::Held(hold) = outcome else {
+            panic!("Expected the sample to be held for verification");
+        };
+
+        // A hold that loses its decisions must not come back as one with none to review
+        sqlx::query("UPDATE verification_holds SET decisions = 'not json' WHERE id = ?")
+            .bind(&hold.id)
+            .execute(repository.pool())
+            .await
+            .unwrap();
+        assert!(repository.get_verification_hold(&hold.id).await.is_err());
+
+        assert!(HoldStatus::from_db_str("ON_ICE").is_err());
+        assert_eq!(HoldStatus::from_db_str("RELEASED"), Ok(HoldStatus::Released));
+    }
+
+    #[tokio::test]
+    async fn test_held_sample_waits_for_review_then_uploads() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let gate = gate(&repository);
+        let now = Utc::now();
+        let stamp = |decision| VerificationStamp {
+            decision,
+            rule_id: None,
+            rule_version: None,
+            evaluated_at: now,
+        };
+
+        let outcome = gate
+            .submit(
+                &DemographicsPolicy::default(),
+                "bf6900-001",
+                Some("P1"),
+                Demographics::default(),
+                &payload("S1"),
+                false,
+                vec![
+                    ("WBC".to_string(), stamp(VerificationDecision::AutoVerify)),
+                    ("HGB".to_string(), stamp(VerificationDecision::Hold)),
+                ],
+            )
+            .await
+            .unwrap();
+        let VerificationOutcome::Held(hold) = outcome else {
+            panic!("Expected the sample to be held for verification");
+        };
+        assert_eq!(repository.count_queued_uploads().await.unwrap(), 0);
+        assert_eq!(repository.get_results_pending_verification().await.unwrap().len(), 1);
+
+        let (released, outcome, _) = gate.release(&DemographicsPolicy::default(), &hold.id).await.unwrap();
+        assert_eq!(released.status, HoldStatus::Released);
+        assert!(matches!(outcome, GateOutcome::Queued { upload_id: Some(_) }));
+        assert_eq!(repository.count_queued_uploads().await.unwrap(), 1);
+        assert!(repository.get_results_pending_verification().await.unwrap().is_empty());
+        assert!(gate.release(&DemographicsPolicy::default(), &hold.id).await.is_err());
+
+        // Fully auto-verified samples go straight on
+        let outcome = gate
+            .submit(
+                &DemographicsPolicy::default(),
+                "bf6900-001",
+                Some("P1"),
+                Demographics::default(),
+                &payload("S2"),
+                false,
+                vec![("WBC".to_string(), stamp(VerificationDecision::AutoVerify))],
+            )
+            .await
+            .unwrap();
+        assert!(matches!(outcome, VerificationOutcome::Released(GateOutcome::Queued { .. }, _)));
+        assert_eq!(repository.count_queued_uploads().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stale_results_and_unknown_statuses_are_held_before_the_rules_run() {
+        use crate::app_state::{AppState, RoutedResults};
+        use crate::services::stale_results::StaleResultSettings;
+
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let gate = gate(&repository);
+        let release_meril = rule(
+            "release-meril",
+            0,
+            RuleScope::Result,
+            VerificationCondition::Analyzer {
+                analyzer_ids: vec!["meril-001".to_string()],
+            },
+        );
+        repository.save_verification_rule(&release_meril).await.unwrap();
+        let now = Utc::now();
+        let completed = |sample_id: &str, hours_ago| TestResult {
+            sample_id: sample_id.to_string(),
+            test_id: "GLU".to_string(),
+            completed_date_time: Some(now - chrono::Duration::hours(hours_ago)),
+            analyzer_id: Some("meril-001".to_string()),
+            ..TestResult::fixture(sample_id)
+        };
+        let route = |sample_id: &'static str, results: Vec<TestResult>| {
+            let (gate, repository) = (&gate, &repository);
+            async move {
+                AppState::<tauri::Wry>::route_results(
+                    gate,
+                    repository,
+                    &StaleResultSettings { max_age_hours: Some(72) },
+                    &DemographicsPolicy::default(),
+                    "meril-001",
+                    Some("P1"),
+                    Demographics::default(),
+                    &payload(sample_id),
+                    false,
+                    &results,
+                    now,
+                )
+                .await
+                .unwrap()
+            }
+        };
+
+        // A replayed sample waits in the stale queue although the rule would release it
+        let RoutedResults::Held(hold) = route("S1", vec![completed("S1", 100)]).await else {
+            panic!("Expected the stale sample to be held before the rules ran");
+        };
+        assert_eq!(hold.reason, ReviewReason::Stale);
+        assert!(hold.decisions.is_empty());
+        assert_eq!(repository.get_results_pending_review(ReviewReason::Stale).await.unwrap().len(), 1);
+        assert_eq!(repository.count_queued_uploads().await.unwrap(), 0);
+
+        // A fresh one is stamped by the rule and uploaded
+        let RoutedResults::Submitted { stamps, outcome } = route("S2", vec![completed("S2", 1)]).await else {
+            panic!("Expected the fresh sample to go through the rules");
+        };
+        assert_eq!(stamps.len(), 1);
+        assert_eq!(stamps[0].decision, VerificationDecision::AutoVerify);
+        assert_eq!(stamps[0].rule_id.as_deref(), Some("release-meril"));
+        assert!(matches!(outcome, VerificationOutcome::Released(GateOutcome::Queued { .. }, _)));
+        assert_eq!(repository.count_queued_uploads().await.unwrap(), 1);
+
+        // So is a fresh one whose status the analyzer's protocol does not define
+        let mut unknown = completed("S3", 1);
+        unknown.metadata.unrecognized_status = Some("X".to_string());
+        let RoutedResults::Held(hold) = route("S3", vec![unknown]).await else {
+            panic!("Expected the result with an unknown status to be held");
+        };
+        assert_eq!(hold.reason, ReviewReason::Verification);
+        assert_eq!(hold.reason_detail.as_deref(), Some("Unknown result status: GLU ('X')"));
+        assert_eq!(repository.count_queued_uploads().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stale_results_queue_released_in_bulk() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let gate = gate(&repository);
+        let detail = Some("Completed 170 hours before it was received, more than the 72 hour limit".to_string());
+
+        let mut stale_ids = Vec::new();
+        for sample in ["S1", "S2"] {
+            let hold = gate
+                .hold(ReviewReason::Stale, detail.clone(), "meril-001", Some("P1"), &payload(sample), false, Vec::new())
+                .await
+                .unwrap();
+            stale_ids.push(hold.id);
+        }
+        gate.hold(ReviewReason::Verification, None, "meril-001", Some("P1"), &payload("S3"), false, Vec::new())
+            .await
+            .unwrap();
+
+        // Each reason has its own queue
+        let stale = repository.get_results_pending_review(ReviewReason::Stale).await.unwrap();
+        assert_eq!(stale.len(), 2);
+        assert_eq!(stale[0].reason_detail, detail);
+        assert_eq!(repository.get_results_pending_review(ReviewReason::Verification).await.unwrap().len(), 1);
+        assert_eq!(repository.count_queued_uploads().await.unwrap(), 0);
+
+        // A missing hold is reported without stopping the others
+        let mut ids = stale_ids.clone();
+        ids.push("missing".to_string());
+        let approval = gate.approve(&DemographicsPolicy::default(), &ids).await;
+        assert_eq!(approval.released.len(), 2);
+        assert!(approval
+            .released
+            .iter()
+            .all(|(hold, outcome, _)| hold.status == HoldStatus::Released
+                && matches!(outcome, GateOutcome::Queued { upload_id: Some(_) })));
+        assert_eq!(approval.failed.len(), 1);
+        assert_eq!(approval.failed[0].0, "missing");
+
+        assert_eq!(repository.count_queued_uploads().await.unwrap(), 2);
+        assert!(repository.get_results_pending_review(ReviewReason::Stale).await.unwrap().is_empty());
+        assert_eq!(repository.get_results_pending_review(ReviewReason::Verification).await.unwrap().len(), 1);
+
+        // Approving again releases nothing twice
+        let again = gate.approve(&DemographicsPolicy::default(), &stale_ids).await;
+        assert!(again.released.is_empty());
+        assert_eq!(again.failed.len(), 2);
+        assert_eq!(repository.count_queued_uploads().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_build_panel_and_dry_run_use_patient_history() {
+        let repository = Arc::new(SqliteRepository::new(establish_test_connection().await));
+        let gate = gate(&repository);
+        seed_patient(&repository).await;
+        let day = |d| Utc.with_ymd_and_hms(2025, 3, d, 9, 0, 0).unwrap();
+
+        let history = [
+            stored_result("R0", "S0", "WBC", "5.0", day(1)),
+            stored_result("R1", "S1", "WBC", "5.5", day(2)),
+            stored_result("R2", "S1", "HGB", "13.0", day(2)),
+        ];
+        for result in &history {
+            repository.save_test_result(result, "P1", &DataSource::Analyzer).await.unwrap();
+        }
+
+        let panel = gate
+            .build_panel("bf6900-001", Some("P1"), Demographics::default(), &history[1..], day(2))
+            .await
+            .unwrap();
+        assert_eq!(panel.patient_age_years, Some(46));
+        assert_eq!(panel.patient_sex, Some(Sex::Female));
+        assert_eq!(panel.results[0].previous_value.as_deref(), Some("5.0"));
+        assert_eq!(panel.results[1].previous_value, None);
+
+        let proposed = vec![rule(
+            "stable",
+            0,
+            RuleScope::Result,
+            VerificationCondition::All {
+                conditions: vec![
+                    VerificationCondition::NoFlags,
+                    VerificationCondition::DeltaBelow { max_percent: 20.0 },
+                ],
+            },
+        )];
+        let report = gate.dry_run(&[], &proposed, day(1), day(3)).await.unwrap();
+        assert_eq!((report.samples, report.results), (2, 3));
+        assert_eq!((report.current_released, report.proposed_released), (0, 1));
+        assert_eq!((report.newly_released, report.newly_held), (1, 0));
+        assert!((report.proposed_release_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(report.current_release_rate, 0.0);
+        assert_eq!(
+            report.released_by_rule,
+            vec![RuleReleaseCount {
+                rule_id: "stable".to_string(),
+                rule_version: 1,
+                released: 1,
+            }]
+        );
+    }
+}
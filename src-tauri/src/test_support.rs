@@ -0,0 +1,52 @@
+//! Fixtures shared by the unit tests of several modules. Tests change what they
+//! need with struct-update syntax.
+
+use std::net::SocketAddr;
+
+use chrono::{TimeZone, Utc};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::app_state::AppState;
+use crate::models::result::{ReferenceRange, TestResultMetadata};
+use crate::models::{Analyzer, TestResult};
+
+/// The AutoQuant analyzer the app creates when none is configured
+pub fn meril_analyzer() -> Analyzer {
+    AppState::<tauri::Wry>::create_default_meril_analyzer()
+}
+
+/// The CQ 5 Plus analyzer the app creates when none is configured
+pub fn bf6900_analyzer() -> Analyzer {
+    AppState::<tauri::Wry>::create_default_bf6900_analyzer()
+}
+
+/// An accepted loopback connection: the server end, its peer's address and the peer end
+pub async fn loopback_connection() -> (TcpStream, SocketAddr, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let peer = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+    let (stream, remote_addr) = listener.accept().await.unwrap();
+    (stream, remote_addr, peer)
+}
+
+/// A `GLU` result of analyzer A1 with a 70-110 reference range, completed and stored
+/// at 10:00 on `day` January 2025; the result sets of the date-range tests
+pub fn dated_result(id: &str, sample_id: &str, value: &str, day: u32) -> TestResult {
+    let completed = Utc.with_ymd_and_hms(2025, 1, day, 10, 0, 0).unwrap();
+    TestResult {
+        sample_id: sample_id.to_string(),
+        value: value.to_string(),
+        reference_range: Some(ReferenceRange {
+            lower_limit: Some(70.0),
+            upper_limit: Some(110.0),
+        }),
+        completed_date_time: Some(completed),
+        metadata: TestResultMetadata {
+            sequence_number: 1,
+            ..Default::default()
+        },
+        analyzer_id: Some("A1".to_string()),
+        created_at: completed,
+        updated_at: completed,
+        ..TestResult::fixture(id)
+    }
+}